@@ -0,0 +1,105 @@
+# Builds fonts/Zeyada_1-CFF2.otf, a variable CFF2 copy of Zeyada_1.ttf
+# with a wght axis (100, 400, 900). Even glyphs (vsindex 0) move 100 units right
+# at wght 900, odd glyphs (vsindex 1) also move 50 units left at wght 100.
+import os, struct
+from mkotf import *
+
+def f2(v): return struct.pack('>h', int(round(v * 16384)))
+
+def index2(items):
+    if not items: return struct.pack('>I', 0)
+    offs = [1]
+    for it in items: offs.append(offs[-1] + len(it))
+    osz = 1 if offs[-1] < 256 else 2 if offs[-1] < 65536 else 3 if offs[-1] < (1 << 24) else 4
+    out = struct.pack('>IB', len(items), osz)
+    for o in offs: out += o.to_bytes(osz, 'big')
+    return out + b''.join(items)
+
+def ivs(data_sets):
+    # regions: 0 = wght max, 1 = wght min
+    regions = struct.pack('>HH', 1, 2) + f2(0) + f2(1) + f2(1) + f2(-1) + f2(-1) + f2(0)
+    datas = []
+    for region_indexes, deltas in data_sets:
+        d = struct.pack('>HHH', len(deltas), len(region_indexes), len(region_indexes))
+        d += b''.join(struct.pack('>H', r) for r in region_indexes)
+        for row in deltas: d += b''.join(struct.pack('>h', v) for v in row)
+        datas.append(d)
+    head_len = 2 + 4 + 2 + 4 * len(datas)
+    out = struct.pack('>HIH', 1, head_len, len(datas))
+    off = head_len + len(regions)
+    for d in datas:
+        out += struct.pack('>I', off); off += len(d)
+    return out + regions + b''.join(datas)
+
+def build2(ttf_path, out_path):
+    data = open(ttf_path, 'rb').read()
+    tables = read_font(data)
+    glyphs = glyphs_of(tables)
+    ng = len(glyphs)
+    nh, = struct.unpack('>H', tables['hhea'][34:36])
+    hmtx = tables['hmtx']
+    local_subrs = []; global_subrs = []; charstrings = []
+    for gid in range(ng):
+        contours = flatten(glyphs, gid)
+        if not contours:
+            charstrings.append(b''); continue
+        xs = [p[0] for c in contours for p in c]; ys = [p[1] for c in contours for p in c]
+        x0, x1, y0, y1 = rnd(min(xs)), rnd(max(xs)), rnd(min(ys)), rnd(max(ys))
+        cs = num(y0) + num(y1 - y0) + op(18) + num(x0) + num(x1 - x0) + op(23) + op(19, 0xC0)
+        if gid % 2: cs = num(1) + op(15) + cs
+        cur = (0, 0)
+        for k, contour in enumerate(contours):
+            start, segs = to_cubic(contour)
+            if k == 0:
+                body, cur = contour_ops(start, segs, cur, gid % 2 == 1)
+                # the first rmoveto, replaced by a blended one
+                moveto = num(start[0]) + num(start[1]) + op(21)
+                assert body.startswith(moveto)
+                deltas = num(100) + num(0) if gid % 2 == 0 else num(100) + num(-50) + num(0) + num(0)
+                cs += num(start[0]) + num(start[1]) + deltas + num(2) + op(16) + op(21) + body[len(moveto):]
+            else:
+                body, cur = contour_ops(start, segs, cur, gid % 2 == 1)
+                if k % 2 == 1:
+                    local_subrs.append(body)
+                    cs += num(len(local_subrs) - 1 - 107) + op(10)
+                elif k % 4 == 2:
+                    global_subrs.append(body)
+                    cs += num(len(global_subrs) - 1 - 107) + op(29)
+                else:
+                    cs += body
+        charstrings.append(cs)
+    assert len(local_subrs) < 1240 and len(global_subrs) < 1240
+
+    store = ivs([([0], []), ([0, 1], [])])
+    vstore = struct.pack('>H', len(store)) + store
+    gsubr_index = index2(global_subrs)
+    cs_index = index2(charstrings)
+    lsubr_index = index2(local_subrs)
+    private = lambda subrs_off: num_dict(0) + b'\x16' + dict_int(subrs_off) + b'\x13'
+    priv_len = len(private(0))
+    font_dict = lambda priv_off: dict_int(priv_len) + dict_int(priv_off) + b'\x12'
+    fd_array_len = len(index2([font_dict(0)]))
+    top = lambda cs_off, fd_off, vs_off: dict_int(cs_off) + b'\x11' + dict_int(fd_off) + b'\x0c\x24' + dict_int(vs_off) + b'\x18'
+    top_len = len(top(0, 0, 0))
+    off = 5 + top_len + len(gsubr_index)
+    vs_off = off; off += len(vstore)
+    cs_off = off; off += len(cs_index)
+    fd_off = off; off += fd_array_len
+    priv_off = off
+    cff2 = (struct.pack('>BBBH', 2, 0, 5, top_len) + top(cs_off, fd_off, vs_off) + gsubr_index + vstore
+            + cs_index + index2([font_dict(priv_off)]) + private(priv_len) + lsubr_index)
+
+    fvar = struct.pack('>HHHHHHHH', 1, 0, 16, 2, 1, 20, 0, 0)
+    fvar += b'wght' + struct.pack('>iiiHH', 100 << 16, 400 << 16, 900 << 16, 0, 2)
+    adv_deltas = [[100, 0] if gid % 2 == 0 else [100, -50] for gid in range(ng)]
+    hvar = struct.pack('>HHIIII', 1, 0, 20, 0, 0, 0) + ivs([([0, 1], adv_deltas)])
+
+    maxp = struct.pack('>IH', 0x00005000, ng)
+    post = struct.pack('>I', 0x00030000) + tables['post'][4:32]
+    out_tables = {k: v for k, v in tables.items() if k not in ('glyf', 'loca', 'gasp', 'prep', 'FFTM', 'maxp', 'post')}
+    out_tables.update({'CFF2': cff2, 'maxp': maxp, 'post': post, 'fvar': fvar, 'HVAR': hvar})
+    write_sfnt(out_path, 0x4F54544F, out_tables)
+
+if __name__ == '__main__':
+    fonts = os.path.join(os.path.dirname(os.path.abspath(__file__)), '..')
+    build2(os.path.join(fonts, 'Zeyada_1.ttf'), os.path.join(fonts, 'Zeyada_1-CFF2.otf'))
@@ -0,0 +1,289 @@
+# Builds fonts/Zeyada_1.otf, a CFF flavored copy of Zeyada_1.ttf
+import math, os, struct
+
+STD_ENC = {}  # code -> SID
+for code in range(32, 127): STD_ENC[code] = code - 31
+for sid, code in zip(range(96, 150), [161,162,163,164,165,166,167,168,169,170,171,172,173,174,175,177,178,179,180,182,183,184,185,186,187,188,189,191,193,194,195,196,197,198,199,200,202,203,205,206,207,208,225,227,232,233,234,235,241,245,248,249,250,251]):
+    STD_ENC[code] = sid
+assert len(STD_ENC) == 95 + 54
+# unicode -> standard encoding code (letters, digits and the accents)
+UNI_TO_STD = {c: c for c in range(32, 127) if c not in (39, 96)}
+UNI_TO_STD.update({0x2019: 39, 0x2018: 96, 0x60: 193, 0xB4: 194, 0x2C6: 195, 0x2DC: 196, 0xAF: 197, 0x2D8: 198, 0x2D9: 199, 0xA8: 200, 0x2DA: 202, 0xB8: 203, 0x2DD: 205, 0x2DB: 206, 0x2C7: 207})
+
+def rnd(v):
+    return int(math.floor(abs(v) + 0.5)) * (1 if v >= 0 else -1)
+
+def read_font(data):
+    n, = struct.unpack('>H', data[4:6])
+    tables = {}
+    for i in range(n):
+        tag, cs, off, ln = struct.unpack('>4sIII', data[12+16*i:28+16*i])
+        tables[tag.decode('latin1')] = data[off:off+ln]
+    return tables
+
+def cmap_rev(cmap):
+    n, = struct.unpack('>H', cmap[2:4])
+    for i in range(n):
+        pid, eid, off = struct.unpack('>HHI', cmap[4+8*i:12+8*i])
+        if struct.unpack('>H', cmap[off:off+2])[0] == 4:
+            s = cmap[off:]
+            segx2, = struct.unpack('>H', s[6:8])
+            seg = segx2 // 2
+            ends = struct.unpack('>%dH' % seg, s[14:14+segx2])
+            starts = struct.unpack('>%dH' % seg, s[16+segx2:16+2*segx2])
+            deltas = struct.unpack('>%dh' % seg, s[16+2*segx2:16+3*segx2])
+            ro_pos = 16 + 3 * segx2
+            ros = struct.unpack('>%dH' % seg, s[ro_pos:ro_pos+segx2])
+            rev = {}
+            for k in range(seg):
+                for c in range(starts[k], ends[k] + 1):
+                    if c == 0xFFFF: continue
+                    if ros[k] == 0: gid = (c + deltas[k]) & 0xFFFF
+                    else:
+                        p = ro_pos + 2 * k + ros[k] + 2 * (c - starts[k])
+                        gid, = struct.unpack('>H', s[p:p+2])
+                        if gid: gid = (gid + deltas[k]) & 0xFFFF
+                    if gid and gid not in rev: rev[gid] = c
+            return rev
+
+def glyphs_of(tables):
+    ng, = struct.unpack('>H', tables['maxp'][4:6])
+    fmt, = struct.unpack('>h', tables['head'][50:52])
+    loca = tables['loca']
+    offs = [struct.unpack('>H', loca[2*i:2*i+2])[0]*2 if fmt == 0 else struct.unpack('>I', loca[4*i:4*i+4])[0] for i in range(ng+1)]
+    out = []
+    for i in range(ng):
+        g = tables['glyf'][offs[i]:offs[i+1]]
+        if not g: out.append(('empty',)); continue
+        nc, = struct.unpack('>h', g[:2])
+        if nc >= 0:
+            from woff2_enc import parse_simple
+            end_pts, instr, flags, xs, ys = parse_simple(g, nc)
+            contours = []; start = 0
+            for e in end_pts:
+                contours.append([(xs[k], ys[k], flags[k] & 1) for k in range(start, e + 1)]); start = e + 1
+            out.append(('simple', contours))
+        else:
+            p = 10; comps = []
+            while True:
+                fl, gi = struct.unpack('>HH', g[p:p+4]); p += 4
+                if fl & 1: a, b = struct.unpack('>hh', g[p:p+4]); p += 4
+                else: a, b = struct.unpack('>bb', g[p:p+2]); p += 2
+                m = (1, 0, 0, 1)
+                if fl & 8: s, = struct.unpack('>h', g[p:p+2]); p += 2; m = (s/16384, 0, 0, s/16384)
+                elif fl & 0x40: sx, sy = struct.unpack('>hh', g[p:p+4]); p += 4; m = (sx/16384, 0, 0, sy/16384)
+                elif fl & 0x80: m = tuple(v/16384 for v in struct.unpack('>hhhh', g[p:p+8])); p += 8
+                comps.append((gi, a, b, m))
+                if not fl & 0x20: break
+            out.append(('compound', comps))
+    return out
+
+def to_cubic(contour):
+    # quadratic TrueType contour -> list of segments starting at an on-curve point
+    pts = contour[:]
+    if not any(p[2] for p in pts):
+        a, b = pts[-1], pts[0]
+        pts.insert(0, ((a[0]+b[0])/2, (a[1]+b[1])/2, 1))
+    k = next(i for i, p in enumerate(pts) if p[2])
+    pts = pts[k:] + pts[:k]
+    # expand implied on-curve points
+    full = []
+    n = len(pts)
+    for i in range(n):
+        p, q = pts[i], pts[(i+1) % n]
+        full.append(p)
+        if not p[2] and not q[2]:
+            full.append(((p[0]+q[0])/2, (p[1]+q[1])/2, 1))
+    start = (rnd(full[0][0]), rnd(full[0][1]))
+    segs = []  # ('l', (x,y)) or ('c', c1, c2, p)
+    cur = full[0]
+    i = 1
+    m = len(full)
+    while i <= m:
+        p = full[i % m]
+        if p[2]:
+            segs.append(('l', (rnd(p[0]), rnd(p[1]))))
+            cur = p; i += 1
+        else:
+            e = full[(i+1) % m]
+            c1 = (cur[0] + 2/3*(p[0]-cur[0]), cur[1] + 2/3*(p[1]-cur[1]))
+            c2 = (e[0] + 2/3*(p[0]-e[0]), e[1] + 2/3*(p[1]-e[1]))
+            segs.append(('c', (rnd(c1[0]), rnd(c1[1])), (rnd(c2[0]), rnd(c2[1])), (rnd(e[0]), rnd(e[1]))))
+            cur = e; i += 2
+    # the closing line back to the start is implicit
+    if segs and segs[-1][0] == 'l' and segs[-1][1] == start: segs.pop()
+    return start, segs
+
+def flatten(glyphs, gid):
+    kind = glyphs[gid]
+    if kind[0] == 'empty': return []
+    if kind[0] == 'simple': return kind[1]
+    out = []
+    for gi, dx, dy, (a, b, c, d) in kind[1]:
+        for contour in flatten(glyphs, gi):
+            out.append([(x*a + y*c + dx, x*b + y*d + dy, on) for x, y, on in contour])
+    return out
+
+def num(v):
+    v = int(v)
+    if -107 <= v <= 107: return bytes([v + 139])
+    if 108 <= v <= 1131: v -= 108; return bytes([(v >> 8) + 247, v & 0xff])
+    if -1131 <= v <= -108: v = -v - 108; return bytes([(v >> 8) + 251, v & 0xff])
+    return b'\x1c' + struct.pack('>h', v)
+
+def op(*codes): return bytes(codes)
+
+def contour_ops(start, segs, cur, use_flex):
+    out = num(start[0]-cur[0]) + num(start[1]-cur[1]) + op(21)
+    cur = start
+    i = 0
+    while i < len(segs):
+        s = segs[i]
+        if s[0] == 'l':
+            dx, dy = s[1][0]-cur[0], s[1][1]-cur[1]
+            if dy == 0: out += num(dx) + op(6)
+            elif dx == 0: out += num(dy) + op(7)
+            else: out += num(dx) + num(dy) + op(5)
+            cur = s[1]; i += 1
+        elif use_flex and i + 1 < len(segs) and segs[i+1][0] == 'c':
+            args = b''
+            for t in (s, segs[i+1]):
+                for p in t[1:]:
+                    args += num(p[0]-cur[0]) + num(p[1]-cur[1]); cur = p
+            out += args + num(50) + op(12, 35)
+            i += 2
+        else:
+            for p in s[1:]:
+                out += num(p[0]-cur[0]) + num(p[1]-cur[1]); cur = p
+            out += op(8); i += 1
+    return out, cur
+
+def index(items):
+    if not items: return struct.pack('>H', 0)
+    offs = [1]
+    for it in items: offs.append(offs[-1] + len(it))
+    osz = 1 if offs[-1] < 256 else 2 if offs[-1] < 65536 else 3 if offs[-1] < (1 << 24) else 4
+    out = struct.pack('>HB', len(items), osz)
+    for o in offs: out += o.to_bytes(osz, 'big')
+    return out + b''.join(items)
+
+def dict_int(v):  # fixed size, so offsets can be patched
+    return b'\x1d' + struct.pack('>i', v)
+
+def build(ttf_path, out_path):
+    data = open(ttf_path, 'rb').read()
+    tables = read_font(data)
+    glyphs = glyphs_of(tables)
+    ng = len(glyphs)
+    rev = cmap_rev(tables['cmap'])
+    nh, = struct.unpack('>H', tables['hhea'][34:36])
+    hmtx = tables['hmtx']
+    adv = [struct.unpack('>H', hmtx[4*min(i, nh-1):4*min(i, nh-1)+2])[0] for i in range(ng)]
+
+    # charset: standard SIDs for glyphs of the standard encoding, custom strings otherwise
+    sids = [0]; strings = []; used = set()
+    code_of = {}
+    for gid in range(1, ng):
+        u = rev.get(gid)
+        code = UNI_TO_STD.get(u) if u is not None else None
+        if code is not None and STD_ENC[code] not in used:
+            sids.append(STD_ENC[code]); used.add(STD_ENC[code]); code_of[gid] = code
+        else:
+            sids.append(391 + len(strings)); strings.append(b'glyph%d' % gid)
+
+    nominal = 500
+    local_subrs = []; global_subrs = []
+    charstrings = []
+    seac_count = 0
+    for gid in range(ng):
+        kind = glyphs[gid]
+        width = num(adv[gid] - nominal)
+        if kind[0] == 'compound' and len(kind[1]) == 2:
+            (b, bx, by, bm), (a, ax, ay, am) = kind[1]
+            if (bx, by) == (0, 0) and bm == (1, 0, 0, 1) and am == (1, 0, 0, 1) and b in code_of and a in code_of \
+               and glyphs[b][0] == 'simple' and glyphs[a][0] == 'simple':
+                charstrings.append(width + num(ax) + num(ay) + num(code_of[b]) + num(code_of[a]) + op(14))
+                seac_count += 1
+                continue
+        contours = flatten(glyphs, gid)
+        if not contours:
+            charstrings.append(width + op(14)); continue
+        xs = [p[0] for c in contours for p in c]; ys = [p[1] for c in contours for p in c]
+        x0, x1, y0, y1 = rnd(min(xs)), rnd(max(xs)), rnd(min(ys)), rnd(max(ys))
+        cs = width + num(y0) + num(y1 - y0) + op(18) + num(x0) + num(x1 - x0) + op(23) + op(19, 0xC0)
+        cur = (0, 0)
+        for k, contour in enumerate(contours):
+            start, segs = to_cubic(contour)
+            body, cur = contour_ops(start, segs, cur, gid % 2 == 1)
+            if k == 0:
+                local_subrs.append(body + op(11))
+                cs += num(len(local_subrs) - 1 - 107) + op(10)
+            elif k % 2 == 1:
+                global_subrs.append(body + op(11))
+                cs += num(len(global_subrs) - 1 - 107) + op(29)
+            else:
+                cs += body
+        charstrings.append(cs + op(14))
+    assert len(local_subrs) < 1240 and len(global_subrs) < 1240
+
+    name_index = index([b'Zeyada-Regular'])
+    string_index = index(strings)
+    gsubr_index = index(global_subrs)
+    cs_index = index(charstrings)
+    lsubr_index = index(local_subrs)
+    charset = b'\x00' + b''.join(struct.pack('>H', s) for s in sids[1:])
+    # no codes by format, the glyphs of the standard encoding as supplements
+    sups = sorted((code_of[g], sids[g]) for g in code_of)
+    encoding = bytes([0x80, 0, len(sups)]) + b''.join(struct.pack('>BH', c, s) for c, s in sups)
+    enc_codes = sups
+    private = lambda subrs_off: num_dict(0) + b'\x14' + num_dict(nominal) + b'\x15' + dict_int(subrs_off) + b'\x13'
+    priv_len = len(private(0))
+    def top(charset_off, enc_off, cs_off, priv_off):
+        return (dict_int(charset_off) + b'\x0f' + dict_int(enc_off) + b'\x10' + dict_int(cs_off) + b'\x11'
+                + dict_int(priv_len) + dict_int(priv_off) + b'\x12')
+    top_len = len(index([top(0, 0, 0, 0)]))
+    off = 4 + len(name_index) + top_len + len(string_index) + len(gsubr_index)
+    enc_off = off; off += len(encoding)
+    charset_off = off; off += len(charset)
+    cs_off = off; off += len(cs_index)
+    priv_off = off; off += priv_len
+    cff = (bytes([1, 0, 4, 4]) + name_index + index([top(charset_off, enc_off, cs_off, priv_off)]) + string_index
+           + gsubr_index + encoding + charset + cs_index + private(priv_len) + lsubr_index)
+
+    maxp = struct.pack('>IH', 0x00005000, ng)
+    post = struct.pack('>I', 0x00030000) + tables['post'][4:32]
+    out_tables = {k: v for k, v in tables.items() if k not in ('glyf', 'loca', 'gasp', 'prep', 'FFTM', 'maxp', 'post')}
+    out_tables['CFF '] = cff; out_tables['maxp'] = maxp; out_tables['post'] = post
+    write_sfnt(out_path, 0x4F54544F, out_tables)
+
+def num_dict(v):
+    if -107 <= v <= 107: return bytes([v + 139])
+    if 108 <= v <= 1131: v -= 108; return bytes([(v >> 8) + 247, v & 0xff])
+    return b'\x1c' + struct.pack('>h', v)
+
+def checksum(b):
+    b = b + b'\0' * (-len(b) % 4)
+    return sum(struct.unpack('>%dI' % (len(b) // 4), b)) & 0xFFFFFFFF
+
+def write_sfnt(path, flavor, tables):
+    tags = sorted(tables)
+    n = len(tags)
+    es = n.bit_length() - 1
+    out = struct.pack('>IHHHH', flavor, n, 16 << es, es, n * 16 - (16 << es))
+    off = 12 + 16 * n
+    body = b''
+    tables = dict(tables)
+    head = bytearray(tables['head']); head[8:12] = b'\0\0\0\0'; tables['head'] = bytes(head)
+    for t in tags:
+        d = tables[t]
+        out += struct.pack('>4sIII', t.encode('latin1'), checksum(d), off + len(body), len(d))
+        body += d + b'\0' * (-len(d) % 4)
+    font = bytearray(out + body)
+    adj = (0xB1B0AFBA - checksum(bytes(font))) & 0xFFFFFFFF
+    hoff = 12 + 16 * n + sum(len(tables[t]) + (-len(tables[t]) % 4) for t in tags[:tags.index('head')])
+    font[hoff+8:hoff+12] = struct.pack('>I', adj)
+    open(path, 'wb').write(font)
+
+if __name__ == '__main__':
+    fonts = os.path.join(os.path.dirname(os.path.abspath(__file__)), '..')
+    build(os.path.join(fonts, 'Zeyada_1.ttf'), os.path.join(fonts, 'Zeyada_1.otf'))
@@ -0,0 +1,249 @@
+# Builds fonts/Zeyada_1.woff2 and the collection fonts/GolosText-Zeyada.woff2.
+# Needs libbrotlienc, which is loaded through ctypes.
+import ctypes, os, struct
+
+KNOWN = ["cmap","head","hhea","hmtx","maxp","name","OS/2","post","cvt ","fpgm","glyf","loca","prep","CFF ","VORG","EBDT","EBLC","gasp","hdmx","kern","LTSH","PCLT","VDMX","vhea","vmtx","BASE","GDEF","GPOS","GSUB","EBSC","JSTF","MATH","CBDT","CBLC","COLR","CPAL","SVG ","sbix","acnt","avar","bdat","bloc","bsln","cvar","fdsc","feat","fmtx","fvar","gvar","hsty","just","lcar","mort","morx","opbd","prop","trak","Zapf","Silf","Glat","Gloc","Feat","Sill"]
+assert len(KNOWN) == 63
+
+enc = ctypes.CDLL('libbrotlienc.so.1')
+def brotli(data, q=11):
+    out = ctypes.create_string_buffer(len(data) * 2 + 1024)
+    n = ctypes.c_size_t(len(out))
+    assert enc.BrotliEncoderCompress(q, 22, 2, ctypes.c_size_t(len(data)), data, ctypes.byref(n), out) == 1
+    return out.raw[:n.value]
+
+def base128(v):
+    out = [v & 0x7f]
+    v >>= 7
+    while v:
+        out.insert(0, 0x80 | (v & 0x7f)); v >>= 7
+    return bytes(out)
+
+def u255(v):
+    if v < 253: return bytes([v])
+    if v < 506: return bytes([255, v - 253])
+    if v < 762: return bytes([254, v - 506])
+    return bytes([253]) + struct.pack('>H', v)
+
+def read_font(data, offset=0):
+    flavor, n = struct.unpack('>IH', data[offset:offset+6])
+    tables = {}
+    for i in range(n):
+        tag, cs, off, ln = struct.unpack('>4sIII', data[offset+12+16*i:offset+28+16*i])
+        tables[tag.decode('latin1')] = data[off:off+ln]
+    return flavor, tables
+
+def triplet(dx, dy, on):
+    ax, ay = abs(dx), abs(dy)
+    ob = 0 if on else 128
+    xs = 0 if dx < 0 else 1
+    ys = 0 if dy < 0 else 1
+    xy = xs + 2 * ys
+    if dx == 0 and ay < 1280:
+        return bytes([ob + ((ay & 0xf00) >> 7) + ys]), bytes([ay & 0xff])
+    if dy == 0 and ax < 1280:
+        return bytes([ob + 10 + ((ax & 0xf00) >> 7) + xs]), bytes([ax & 0xff])
+    if ax < 65 and ay < 65:
+        return bytes([ob + 20 + ((ax - 1) & 0x30) + (((ay - 1) & 0x30) >> 2) + xy]), bytes([(((ax - 1) & 0xf) << 4) | ((ay - 1) & 0xf)])
+    if ax < 769 and ay < 769:
+        return bytes([ob + 84 + 12 * (((ax - 1) & 0x300) >> 8) + (((ay - 1) & 0x300) >> 6) + xy]), bytes([(ax - 1) & 0xff, (ay - 1) & 0xff])
+    if ax < 4096 and ay < 4096:
+        return bytes([ob + 120 + xy]), bytes([ax >> 4, ((ax & 0xf) << 4) | (ay >> 8), ay & 0xff])
+    return bytes([ob + 124 + xy]), bytes([ax >> 8, ax & 0xff, ay >> 8, ay & 0xff])
+
+def parse_simple(g, nc):
+    end_pts = struct.unpack('>%dH' % nc, g[10:10+2*nc])
+    p = 10 + 2 * nc
+    il = struct.unpack('>H', g[p:p+2])[0]; p += 2
+    instr = g[p:p+il]; p += il
+    npts = end_pts[-1] + 1 if nc else 0
+    flags = []
+    while len(flags) < npts:
+        f = g[p]; p += 1
+        flags.append(f)
+        if f & 8:
+            r = g[p]; p += 1
+            flags.extend([f] * r)
+    xs = []; x = 0
+    for f in flags:
+        if f & 2:
+            d = g[p]; p += 1
+            x += d if f & 0x10 else -d
+        elif not f & 0x10:
+            x += struct.unpack('>h', g[p:p+2])[0]; p += 2
+        xs.append(x)
+    ys = []; y = 0
+    for f in flags:
+        if f & 4:
+            d = g[p]; p += 1
+            y += d if f & 0x20 else -d
+        elif not f & 0x20:
+            y += struct.unpack('>h', g[p:p+2])[0]; p += 2
+        ys.append(y)
+    return end_pts, instr, flags, xs, ys
+
+def parse_composite(g):
+    p = 10
+    have_instr = False
+    while True:
+        flags, = struct.unpack('>H', g[p:p+2])
+        p += 4
+        p += 4 if flags & 1 else 2
+        if flags & 8: p += 2
+        elif flags & 0x40: p += 4
+        elif flags & 0x80: p += 8
+        if flags & 0x100: have_instr = True
+        if not flags & 0x20: break
+    comp = g[10:p]
+    instr = b''
+    if have_instr:
+        il, = struct.unpack('>H', g[p:p+2])
+        instr = g[p+2:p+2+il]
+    return comp, have_instr, instr
+
+def transform_glyf(tables):
+    glyf, loca, head = tables['glyf'], tables['loca'], tables['head']
+    index_format, = struct.unpack('>h', head[50:52])
+    num_glyphs, = struct.unpack('>H', tables['maxp'][4:6])
+    if index_format == 0:
+        offs = [2 * v for v in struct.unpack('>%dH' % (num_glyphs + 1), loca[:2*(num_glyphs+1)])]
+    else:
+        offs = list(struct.unpack('>%dI' % (num_glyphs + 1), loca[:4*(num_glyphs+1)]))
+    nContour = b''; nPoints = b''; flagS = b''; glyphS = b''; compS = b''; instrS = b''
+    bbox_bitmap = bytearray(4 * ((num_glyphs + 31) // 32)); bboxS = b''
+    overlap = bytearray((num_glyphs + 7) // 8); has_overlap = False
+    xmins = []
+    for i in range(num_glyphs):
+        g = glyf[offs[i]:offs[i+1]]
+        if not g:
+            nContour += struct.pack('>h', 0); xmins.append(0); continue
+        nc, xmin, ymin, xmax, ymax = struct.unpack('>hhhhh', g[:10])
+        xmins.append(xmin)
+        nContour += struct.pack('>h', nc)
+        if nc > 0:
+            end_pts, instr, flags, xs, ys = parse_simple(g, nc)
+            prev = -1
+            for e in end_pts:
+                nPoints += u255(e - prev); prev = e
+            px = py = 0
+            for f, x, y in zip(flags, xs, ys):
+                fl, dt = triplet(x - px, y - py, f & 1)
+                flagS += fl; glyphS += dt; px, py = x, y
+            if flags and flags[0] & 0x40:
+                overlap[i >> 3] |= 0x80 >> (i & 7); has_overlap = True
+            glyphS += u255(len(instr)); instrS += instr
+            if xs and (min(xs), min(ys), max(xs), max(ys)) == (xmin, ymin, xmax, ymax):
+                pass
+            else:
+                bbox_bitmap[i >> 3] |= 0x80 >> (i & 7); bboxS += g[2:10]
+        else:
+            comp, have_instr, instr = parse_composite(g)
+            compS += comp
+            bbox_bitmap[i >> 3] |= 0x80 >> (i & 7); bboxS += g[2:10]
+            if have_instr:
+                glyphS += u255(len(instr)); instrS += instr
+    bboxS = bytes(bbox_bitmap) + bboxS
+    streams = [nContour, nPoints, flagS, glyphS, compS, bboxS, instrS]
+    out = struct.pack('>HHHH', 0, 1 if has_overlap else 0, num_glyphs, index_format)
+    out += b''.join(struct.pack('>I', len(s)) for s in streams)
+    out += b''.join(streams)
+    if has_overlap: out += bytes(overlap)
+    return out, xmins
+
+def transform_hmtx(tables, xmins):
+    num_glyphs, = struct.unpack('>H', tables['maxp'][4:6])
+    nh, = struct.unpack('>H', tables['hhea'][34:36])
+    hmtx = tables['hmtx']
+    adv = [struct.unpack('>H', hmtx[4*i:4*i+2])[0] for i in range(nh)]
+    lsb = [struct.unpack('>h', hmtx[4*i+2:4*i+4])[0] for i in range(nh)]
+    lsb2 = [struct.unpack('>h', hmtx[4*nh+2*i:4*nh+2*i+2])[0] for i in range(num_glyphs - nh)]
+    flags = 0
+    if lsb == xmins[:nh]: flags |= 1
+    if lsb2 == xmins[nh:]: flags |= 2
+    if flags == 0: return None
+    out = bytes([flags]) + b''.join(struct.pack('>H', a) for a in adv)
+    if not flags & 1: out += b''.join(struct.pack('>h', v) for v in lsb)
+    if not flags & 2: out += b''.join(struct.pack('>h', v) for v in lsb2)
+    return out
+
+def order(tags):
+    tags = sorted(tags)
+    if 'glyf' in tags and 'loca' in tags:
+        tags.remove('loca'); tags.insert(tags.index('glyf') + 1, 'loca')
+    return tags
+
+def entry(tag, version, orig, transformed=None):
+    if tag in KNOWN:
+        out = bytes([KNOWN.index(tag) | (version << 6)])
+    else:
+        out = bytes([63 | (version << 6)]) + tag.encode('latin1')
+    out += base128(orig)
+    if transformed is not None: out += base128(transformed)
+    return out
+
+def encode_tables(tables, transform_hmtx_table=True):
+    """Returns list of (tag, directory entry bytes, data) in order."""
+    result = []
+    glyf_t = None
+    if 'glyf' in tables:
+        glyf_t, xmins = transform_glyf(tables)
+    for tag in order(tables):
+        data = tables[tag]
+        if tag == 'glyf':
+            result.append((tag, entry(tag, 0, len(data), len(glyf_t)), glyf_t))
+        elif tag == 'loca':
+            result.append((tag, entry(tag, 0, len(data), 0), b''))
+        elif tag == 'hmtx' and glyf_t is not None and transform_hmtx_table and transform_hmtx(tables, xmins) is not None:
+            h = transform_hmtx(tables, xmins)
+            result.append((tag, entry(tag, 1, len(data), len(h)), h))
+        else:
+            result.append((tag, entry(tag, 0, len(data)), data))
+    return result
+
+def header(flavor, length, num_tables, sfnt_size, comp_size):
+    return struct.pack('>4sIIHHIIHHIIIII', b'wOF2', flavor, length, num_tables, 0, sfnt_size, comp_size, 1, 0, 0, 0, 0, 0, 0)
+
+def encode(font_bytes):
+    flavor, tables = read_font(font_bytes)
+    enc_tables = encode_tables(tables)
+    directory = b''.join(e for _, e, _ in enc_tables)
+    comp = brotli(b''.join(d for _, _, d in enc_tables))
+    sfnt_size = 12 + 16 * len(tables) + sum((len(t) + 3) & ~3 for t in tables.values())
+    body = directory + comp
+    body += b'\0' * ((-(48 + len(body))) % 4)
+    return header(flavor, 48 + len(body), len(enc_tables), sfnt_size, len(comp)) + body
+
+def encode_collection(font_list, version=1):
+    fonts = [read_font(f) for f in font_list]
+    entries = []  # (tag, entry, data)
+    font_indices = []
+    for flavor, tables in fonts:
+        enc_tables = encode_tables(tables)
+        idx = []
+        for tag, e, d in enc_tables:
+            key = (tag, tables[tag])
+            found = None
+            for j, (t2, e2, d2, k2) in enumerate(entries):
+                if k2 == key and tag not in ('glyf', 'loca', 'hmtx'):
+                    found = j
+            if found is None:
+                entries.append((tag, e, d, key)); found = len(entries) - 1
+            idx.append(found)
+        font_indices.append((flavor, idx))
+    directory = b''.join(e for _, e, _, _ in entries)
+    coll = struct.pack('>I', 0x00010000 if version == 1 else 0x00020000) + u255(len(fonts))
+    for flavor, idx in font_indices:
+        coll += u255(len(idx)) + struct.pack('>I', flavor) + b''.join(u255(i) for i in idx)
+    comp = brotli(b''.join(d for _, _, d, _ in entries))
+    # Each font with its own copy of the tables, as upper bound of the shared ones
+    sfnt_size = 12 + 4 * len(fonts) + sum(12 + 16 * len(t) + sum((len(d) + 3) & ~3 for d in t.values()) for _, t in fonts)
+    body = directory + coll + comp
+    body += b'\0' * ((-(48 + len(body))) % 4)
+    return header(0x74746366, 48 + len(body), len(entries), sfnt_size, len(comp)) + body
+
+if __name__ == '__main__':
+    fonts = os.path.join(os.path.dirname(os.path.abspath(__file__)), '..')
+    golos = open(os.path.join(fonts, 'GolosText-Regular.ttf'), 'rb').read()
+    zeyada = open(os.path.join(fonts, 'Zeyada_1.ttf'), 'rb').read()
+    open(os.path.join(fonts, 'Zeyada_1.woff2'), 'wb').write(encode(zeyada))
+    open(os.path.join(fonts, 'GolosText-Zeyada.woff2'), 'wb').write(encode_collection([golos, zeyada]))
@@ -0,0 +1,359 @@
+use crate::hinting::HintedGlyph;
+
+use std::collections::HashMap;
+
+// Contours of (x, y, on curve) points in font units
+pub type Outline = Vec<Vec<(f32, f32, bool)>>;
+
+// Tolerances in font units at 1000 units per em
+const FLAT_TOLERANCE: f32 = 4.0; // y spread of the points of a segment
+const MIN_SEGMENT_LENGTH: f32 = 20.0;
+const BLUE_TOLERANCE: f32 = 20.0; // beyond the reference and overshoot of a zone
+const MAX_STEM_WIDTH: f32 = 250.0;
+const STANDARD_STEM_TOLERANCE: f32 = 0.2; // stems within 20% use the standard width
+
+// Reference glyphs for the blue zones, with flat and round edges
+const BASELINE_FLAT: &str = "HIxz";
+const BASELINE_ROUND: &str = "oOc";
+const X_HEIGHT_FLAT: &str = "xz";
+const X_HEIGHT_ROUND: &str = "oc";
+const CAP_HEIGHT_FLAT: &str = "HIE";
+const CAP_HEIGHT_ROUND: &str = "OC";
+pub const REFERENCE_CHARS: &str = "HIExzocOC";
+
+// Height shared by the flat edges of a set of glyphs, and the overshoot of
+// their round edges
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BlueZone {
+    pub reference: f32,
+    pub overshoot: f32,
+}
+
+impl BlueZone {
+    fn new(samples: &[(char, Outline)], flat: &str, round: &str, top: bool) -> Option<BlueZone> {
+        let extremes = |chars: &str| {
+            let values = samples
+                .iter()
+                .filter(|(c, _)| chars.contains(*c))
+                .filter_map(|(_, outline)| {
+                    let ys = outline.iter().flatten().map(|(_, y, _)| *y);
+                    if top {
+                        ys.reduce(f32::max)
+                    } else {
+                        ys.reduce(f32::min)
+                    }
+                })
+                .collect();
+            median(values)
+        };
+        let reference = extremes(flat)?;
+        Some(BlueZone {
+            reference,
+            overshoot: extremes(round).unwrap_or(reference),
+        })
+    }
+
+    fn distance(&self, y: f32) -> f32 {
+        let (low, high) = if self.reference <= self.overshoot {
+            (self.reference, self.overshoot)
+        } else {
+            (self.overshoot, self.reference)
+        };
+        (low - y).max(y - high).max(0.0)
+    }
+
+    // Position in pixels of an edge at `y`, overshoots under half a pixel are
+    // flattened to the reference
+    fn fit(&self, y: f32, scale: f32) -> f32 {
+        let reference = (self.reference * scale).round();
+        let overshoot = (self.overshoot - self.reference) * scale;
+        if overshoot.abs() < 0.5 || (y - self.reference).abs() <= (y - self.overshoot).abs() {
+            reference
+        } else {
+            reference + overshoot.round()
+        }
+    }
+}
+
+// A run of points at about the same height, the edge of a horizontal stroke
+#[derive(Debug)]
+struct Segment {
+    y: f32,
+    x_min: f32,
+    x_max: f32,
+    bottom: bool,                // lower edge of the ink, upper edge otherwise
+    points: Vec<(usize, usize)>, // contour and point indices
+}
+
+// Light autohinting in the spirit of FreeType's: only heights are grid fitted,
+// horizontal stems get whole pixel widths and edges in blue zones snap to them
+#[derive(Debug, Clone, PartialEq)]
+pub struct Autohinter {
+    units_per_em: f32,
+    pub baseline: Option<BlueZone>,
+    pub x_height: Option<BlueZone>,
+    pub cap_height: Option<BlueZone>,
+    pub standard_stem: Option<f32>, // median horizontal stem width of the samples
+}
+
+impl Autohinter {
+    // From the outlines of the `REFERENCE_CHARS` found in the font
+    pub fn new(units_per_em: u16, samples: &[(char, Outline)]) -> Autohinter {
+        let units_per_em = units_per_em as f32;
+        let stem_widths = samples
+            .iter()
+            .flat_map(|(_, outline)| {
+                let segments = segments(outline, units_per_em);
+                stems(&segments, units_per_em)
+                    .into_iter()
+                    .map(move |(bottom, top)| segments[top].y - segments[bottom].y)
+            })
+            .collect();
+
+        Autohinter {
+            units_per_em,
+            baseline: BlueZone::new(samples, BASELINE_FLAT, BASELINE_ROUND, false),
+            x_height: BlueZone::new(samples, X_HEIGHT_FLAT, X_HEIGHT_ROUND, true),
+            cap_height: BlueZone::new(samples, CAP_HEIGHT_FLAT, CAP_HEIGHT_ROUND, true),
+            standard_stem: median(stem_widths),
+        }
+    }
+
+    // `outline` with x relative to the glyph origin, `cubic` when control
+    // points come in pairs of a cubic curve
+    pub fn hint(
+        &self,
+        outline: &Outline,
+        advance_width: f32,
+        ppem: u16,
+        cubic: bool,
+    ) -> HintedGlyph {
+        let scale = ppem as f32 / self.units_per_em;
+        let tolerance = |value: f32| value * self.units_per_em / 1000.0;
+        let segments = segments(outline, self.units_per_em);
+        let mut fitted: Vec<Option<f32>> = vec![None; segments.len()];
+
+        // Edges in a blue zone take its position
+        segments.iter().enumerate().for_each(|(index, segment)| {
+            let zones = if segment.bottom {
+                vec![self.baseline]
+            } else {
+                vec![self.x_height, self.cap_height]
+            };
+            fitted[index] = zones
+                .iter()
+                .flatten()
+                .map(|zone| (zone, zone.distance(segment.y)))
+                .filter(|(_, distance)| *distance <= tolerance(BLUE_TOLERANCE))
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(zone, _)| zone.fit(segment.y, scale));
+        });
+
+        // Stems get a whole number of pixels, from a fitted edge or around
+        // their center
+        stems(&segments, self.units_per_em)
+            .into_iter()
+            .for_each(|(bottom, top)| {
+                let width = segments[top].y - segments[bottom].y;
+                let width = match self.standard_stem {
+                    Some(standard)
+                        if (width - standard).abs() <= standard * STANDARD_STEM_TOLERANCE =>
+                    {
+                        standard
+                    }
+                    _ => width,
+                };
+                let width = (width * scale).round().max(1.0);
+                match (fitted[bottom], fitted[top]) {
+                    (Some(_), Some(_)) => {}
+                    (Some(y), None) => fitted[top] = Some(y + width),
+                    (None, Some(y)) => fitted[bottom] = Some(y - width),
+                    (None, None) => {
+                        let center = (segments[bottom].y + segments[top].y) / 2.0 * scale;
+                        let y = (center - width / 2.0).round();
+                        fitted[bottom] = Some(y);
+                        fitted[top] = Some(y + width);
+                    }
+                }
+            });
+
+        // Other edges are rounded, then all of them kept in order
+        let mut edges: Vec<(f32, f32)> = segments
+            .iter()
+            .zip(&fitted)
+            .map(|(segment, fitted)| {
+                let y = segment.y * scale;
+                (y, fitted.unwrap_or(y.round()))
+            })
+            .collect();
+        let mut order: Vec<usize> = (0..edges.len()).collect();
+        order.sort_by(|a, b| edges[*a].0.total_cmp(&edges[*b].0));
+        (1..order.len()).for_each(|index| {
+            let previous = edges[order[index - 1]].1;
+            let edge = &mut edges[order[index]];
+            edge.1 = edge.1.max(previous);
+        });
+
+        let on_segment: HashMap<(usize, usize), usize> = segments
+            .iter()
+            .enumerate()
+            .flat_map(|(index, segment)| segment.points.iter().map(move |point| (*point, index)))
+            .collect();
+        let sorted_edges: Vec<(f32, f32)> = order.iter().map(|index| edges[*index]).collect();
+
+        // Points follow their edge, or are interpolated between the edges
+        // around them
+        let contours = outline
+            .iter()
+            .enumerate()
+            .map(|(contour_index, contour)| {
+                contour
+                    .iter()
+                    .enumerate()
+                    .map(|(point_index, (x, y, on_curve))| {
+                        let y = match on_segment.get(&(contour_index, point_index)) {
+                            Some(segment) => edges[*segment].1 + (y - segments[*segment].y) * scale,
+                            None => interpolate(&sorted_edges, y * scale),
+                        };
+                        (x * scale, y, *on_curve)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        HintedGlyph {
+            contours,
+            advance_width: advance_width * scale,
+            cubic,
+        }
+    }
+}
+
+// Moves `y` like the edges below and above it
+fn interpolate(edges: &[(f32, f32)], y: f32) -> f32 {
+    let above = edges.partition_point(|(original, _)| *original <= y);
+    match (
+        above.checked_sub(1).map(|index| edges[index]),
+        edges.get(above).copied(),
+    ) {
+        (None, None) => y,
+        (Some((original, fitted)), None) | (None, Some((original, fitted))) => {
+            y + fitted - original
+        }
+        (Some((original1, fitted1)), Some((original2, fitted2))) => {
+            if original2 - original1 <= f32::EPSILON {
+                y + fitted1 - original1
+            } else {
+                fitted1 + (y - original1) * (fitted2 - fitted1) / (original2 - original1)
+            }
+        }
+    }
+}
+
+// Runs of consecutive points within FLAT_TOLERANCE in y, long enough to be
+// the edge of a stroke
+fn segments(outline: &Outline, units_per_em: f32) -> Vec<Segment> {
+    let tolerance = |value: f32| value * units_per_em / 1000.0;
+
+    // The lower edges of the ink run right to left in clockwise outlines
+    let area: f32 = outline
+        .iter()
+        .map(|contour| {
+            (0..contour.len())
+                .map(|index| {
+                    let (x1, y1, _) = contour[index];
+                    let (x2, y2, _) = contour[(index + 1) % contour.len()];
+                    x1 * y2 - x2 * y1
+                })
+                .sum::<f32>()
+        })
+        .sum();
+    let bottom_direction = if area < 0.0 { -1.0 } else { 1.0 };
+
+    let mut segments = vec![];
+    outline
+        .iter()
+        .enumerate()
+        .for_each(|(contour_index, contour)| {
+            let count = contour.len();
+            // Start after a change of height so that no run wraps around
+            let start = match (0..count).find(|index| {
+                let previous = contour[(index + count - 1) % count].1;
+                (contour[*index].1 - previous).abs() > tolerance(FLAT_TOLERANCE)
+            }) {
+                Some(start) => start,
+                None => return,
+            };
+
+            let mut run: Vec<usize> = vec![];
+            (0..=count).for_each(|offset| {
+                let index = (start + offset) % count;
+                let (low, high) = run.iter().fold((f32::MAX, f32::MIN), |(low, high), point| {
+                    (low.min(contour[*point].1), high.max(contour[*point].1))
+                });
+                let y = contour[index].1;
+                let flat = run.is_empty()
+                    || (high.max(y) - low.min(y) <= tolerance(FLAT_TOLERANCE) && offset < count);
+                if !flat {
+                    let first = contour[run[0]];
+                    let last = contour[run[run.len() - 1]];
+                    let x_min = run
+                        .iter()
+                        .map(|point| contour[*point].0)
+                        .fold(f32::MAX, f32::min);
+                    let x_max = run
+                        .iter()
+                        .map(|point| contour[*point].0)
+                        .fold(f32::MIN, f32::max);
+                    if run.len() >= 2
+                        && x_max - x_min >= tolerance(MIN_SEGMENT_LENGTH)
+                        && last.0 != first.0
+                    {
+                        segments.push(Segment {
+                            y: run.iter().map(|point| contour[*point].1).sum::<f32>()
+                                / run.len() as f32,
+                            x_min,
+                            x_max,
+                            bottom: (last.0 - first.0).signum() == bottom_direction,
+                            points: run.iter().map(|point| (contour_index, *point)).collect(),
+                        });
+                    }
+                    run.clear();
+                }
+                run.push(index);
+            });
+        });
+    segments
+}
+
+// Lower and upper edges of horizontal stems, each lower edge paired with the
+// closest upper edge above it that overlaps it
+fn stems(segments: &[Segment], units_per_em: f32) -> Vec<(usize, usize)> {
+    let max_width = MAX_STEM_WIDTH * units_per_em / 1000.0;
+    segments
+        .iter()
+        .enumerate()
+        .filter(|(_, segment)| segment.bottom)
+        .filter_map(|(bottom_index, bottom)| {
+            segments
+                .iter()
+                .enumerate()
+                .filter(|(_, top)| {
+                    !top.bottom
+                        && top.y > bottom.y
+                        && top.y - bottom.y <= max_width
+                        && top.x_max.min(bottom.x_max) > top.x_min.max(bottom.x_min)
+                })
+                .min_by(|(_, a), (_, b)| a.y.total_cmp(&b.y))
+                .map(|(top_index, _)| (bottom_index, top_index))
+        })
+        .collect()
+}
+
+fn median(mut values: Vec<f32>) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    Some(values[values.len() / 2])
+}
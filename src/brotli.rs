@@ -0,0 +1,1164 @@
+// Compression and decompression of Brotli streams (RFC 7932), as used for
+// WOFF2 table data.
+
+use crate::deflate::{canonical_codes, lz77_tokens, prefix_code_lengths, BitWriter, Lz77Token};
+use Transform::*;
+
+// Static dictionary words, grouped by length from 4 to 24 bytes
+const DICTIONARY: &[u8] = include_bytes!("brotli_dictionary.bin");
+// log2 of the number of words of each length
+const DICTIONARY_SIZE_BITS_BY_LENGTH: [u8; 25] = [
+    0, 0, 0, 0, 10, 10, 11, 11, 10, 10, 10, 10, 10, 9, 9, 8, 7, 7, 8, 7, 7, 6, 6, 5, 5,
+];
+const MIN_DICTIONARY_WORD_LENGTH: usize = 4;
+const MAX_DICTIONARY_WORD_LENGTH: usize = 24;
+
+const MAX_CODE_LENGTH: usize = 15;
+const NUM_LITERAL_SYMBOLS: usize = 256;
+const NUM_INSERT_AND_COPY_SYMBOLS: usize = 704;
+const NUM_BLOCK_COUNT_SYMBOLS: usize = 26;
+const NUM_DISTANCE_SHORT_CODES: u32 = 16;
+const LITERAL_CONTEXT_BITS: usize = 6;
+const DISTANCE_CONTEXT_BITS: usize = 2;
+
+// Encoder settings: a 4 MB window, no postfix bits or direct distance codes
+const WINDOW_BITS: u32 = 22;
+const MIN_COPY_LENGTH: usize = 3;
+const MAX_COPY_LENGTH: usize = 4096;
+const MAX_META_BLOCK_LENGTH: usize = 1 << 24;
+const NUM_DISTANCE_SYMBOLS: usize = 64;
+const MAX_SIMPLE_CODE_SYMBOLS: usize = 4;
+const MAX_CODE_LENGTH_CODE_LENGTH: u8 = 5;
+
+// Order in which code length code lengths are stored
+const CODE_LENGTH_CODE_ORDER: [usize; 18] =
+    [1, 2, 3, 4, 0, 5, 17, 6, 16, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+// Static code for code length code lengths, indexed by the next 4 bits
+const CODE_LENGTH_PREFIX_LENGTH: [u8; 16] = [2, 2, 2, 3, 2, 2, 2, 4, 2, 2, 2, 3, 2, 2, 2, 4];
+const CODE_LENGTH_PREFIX_VALUE: [u8; 16] = [0, 4, 3, 2, 0, 4, 3, 1, 0, 4, 3, 2, 0, 4, 3, 5];
+
+// (base, extra bits) of each block count symbol
+#[rustfmt::skip]
+const BLOCK_COUNT_CODES: [(u32, u8); NUM_BLOCK_COUNT_SYMBOLS] = [
+    (1, 2), (5, 2), (9, 2), (13, 2), (17, 3), (25, 3), (33, 3), (41, 3), (49, 4), (65, 4),
+    (81, 4), (97, 4), (113, 5), (145, 5), (177, 5), (209, 5), (241, 6), (305, 6), (369, 7),
+    (497, 8), (753, 9), (1265, 10), (2289, 11), (4337, 12), (8433, 13), (16625, 24),
+];
+// (base, extra bits) of each insert length code
+#[rustfmt::skip]
+const INSERT_LENGTH_CODES: [(u32, u8); 24] = [
+    (0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0), (6, 1), (8, 1), (10, 2), (14, 2), (18, 3),
+    (26, 3), (34, 4), (50, 4), (66, 5), (98, 5), (130, 6), (194, 7), (322, 8), (578, 9),
+    (1090, 10), (2114, 12), (6210, 14), (22594, 24),
+];
+// (base, extra bits) of each copy length code
+#[rustfmt::skip]
+const COPY_LENGTH_CODES: [(u32, u8); 24] = [
+    (2, 0), (3, 0), (4, 0), (5, 0), (6, 0), (7, 0), (8, 0), (9, 0), (10, 1), (12, 1), (14, 2),
+    (18, 2), (22, 3), (30, 3), (38, 4), (54, 4), (70, 5), (102, 5), (134, 6), (198, 7),
+    (326, 8), (582, 9), (1094, 10), (2118, 24),
+];
+// First insert and copy length codes of each group of 64 insert-and-copy symbols
+#[rustfmt::skip]
+const INSERT_AND_COPY_RANGES: [(u32, u32); 11] = [
+    (0, 0), (0, 8), (0, 0), (0, 8), (8, 0), (8, 8), (0, 16), (16, 0), (8, 16), (16, 8), (16, 16),
+];
+// (index in the last distances, delta) of the first 16 distance codes
+#[rustfmt::skip]
+const DISTANCE_SHORT_CODES: [(usize, i32); NUM_DISTANCE_SHORT_CODES as usize] = [
+    (0, 0), (1, 0), (2, 0), (3, 0), (0, -1), (0, 1), (0, -2), (0, 2), (0, -3), (0, 3),
+    (1, -1), (1, 1), (1, -2), (1, 2), (1, -3), (1, 3),
+];
+
+#[derive(Debug, Copy, Clone)]
+enum Transform {
+    Identity,
+    OmitLast(usize),
+    OmitFirst(usize),
+    UppercaseFirst,
+    UppercaseAll,
+}
+
+// Dictionary word transforms: prefix, transform and suffix
+#[rustfmt::skip]
+const TRANSFORMS: [(&[u8], Transform, &[u8]); 121] = [
+    (b"", Identity, b""),
+    (b"", Identity, b" "),
+    (b" ", Identity, b" "),
+    (b"", OmitFirst(1), b""),
+    (b"", UppercaseFirst, b" "),
+    (b"", Identity, b" the "),
+    (b" ", Identity, b""),
+    (b"s ", Identity, b" "),
+    (b"", Identity, b" of "),
+    (b"", UppercaseFirst, b""),
+    (b"", Identity, b" and "),
+    (b"", OmitFirst(2), b""),
+    (b"", OmitLast(1), b""),
+    (b", ", Identity, b" "),
+    (b"", Identity, b", "),
+    (b" ", UppercaseFirst, b" "),
+    (b"", Identity, b" in "),
+    (b"", Identity, b" to "),
+    (b"e ", Identity, b" "),
+    (b"", Identity, b"\""),
+    (b"", Identity, b"."),
+    (b"", Identity, b"\">"),
+    (b"", Identity, b"\x0a"),
+    (b"", OmitLast(3), b""),
+    (b"", Identity, b"]"),
+    (b"", Identity, b" for "),
+    (b"", OmitFirst(3), b""),
+    (b"", OmitLast(2), b""),
+    (b"", Identity, b" a "),
+    (b"", Identity, b" that "),
+    (b" ", UppercaseFirst, b""),
+    (b"", Identity, b". "),
+    (b".", Identity, b""),
+    (b" ", Identity, b", "),
+    (b"", OmitFirst(4), b""),
+    (b"", Identity, b" with "),
+    (b"", Identity, b"'"),
+    (b"", Identity, b" from "),
+    (b"", Identity, b" by "),
+    (b"", OmitFirst(5), b""),
+    (b"", OmitFirst(6), b""),
+    (b" the ", Identity, b""),
+    (b"", OmitLast(4), b""),
+    (b"", Identity, b". The "),
+    (b"", UppercaseAll, b""),
+    (b"", Identity, b" on "),
+    (b"", Identity, b" as "),
+    (b"", Identity, b" is "),
+    (b"", OmitLast(7), b""),
+    (b"", OmitLast(1), b"ing "),
+    (b"", Identity, b"\x0a\x09"),
+    (b"", Identity, b":"),
+    (b" ", Identity, b". "),
+    (b"", Identity, b"ed "),
+    (b"", OmitFirst(9), b""),
+    (b"", OmitFirst(7), b""),
+    (b"", OmitLast(6), b""),
+    (b"", Identity, b"("),
+    (b"", UppercaseFirst, b", "),
+    (b"", OmitLast(8), b""),
+    (b"", Identity, b" at "),
+    (b"", Identity, b"ly "),
+    (b" the ", Identity, b" of "),
+    (b"", OmitLast(5), b""),
+    (b"", OmitLast(9), b""),
+    (b" ", UppercaseFirst, b", "),
+    (b"", UppercaseFirst, b"\""),
+    (b".", Identity, b"("),
+    (b"", UppercaseAll, b" "),
+    (b"", UppercaseFirst, b"\">"),
+    (b"", Identity, b"=\""),
+    (b" ", Identity, b"."),
+    (b".com/", Identity, b""),
+    (b" the ", Identity, b" of the "),
+    (b"", UppercaseFirst, b"'"),
+    (b"", Identity, b". This "),
+    (b"", Identity, b","),
+    (b".", Identity, b" "),
+    (b"", UppercaseFirst, b"("),
+    (b"", UppercaseFirst, b"."),
+    (b"", Identity, b" not "),
+    (b" ", Identity, b"=\""),
+    (b"", Identity, b"er "),
+    (b" ", UppercaseAll, b" "),
+    (b"", Identity, b"al "),
+    (b" ", UppercaseAll, b""),
+    (b"", Identity, b"='"),
+    (b"", UppercaseAll, b"\""),
+    (b"", UppercaseFirst, b". "),
+    (b" ", Identity, b"("),
+    (b"", Identity, b"ful "),
+    (b" ", UppercaseFirst, b". "),
+    (b"", Identity, b"ive "),
+    (b"", Identity, b"less "),
+    (b"", UppercaseAll, b"'"),
+    (b"", Identity, b"est "),
+    (b" ", UppercaseFirst, b"."),
+    (b"", UppercaseAll, b"\">"),
+    (b" ", Identity, b"='"),
+    (b"", UppercaseFirst, b","),
+    (b"", Identity, b"ize "),
+    (b"", UppercaseAll, b"."),
+    (b"\xc2\xa0", Identity, b""),
+    (b" ", Identity, b","),
+    (b"", UppercaseFirst, b"=\""),
+    (b"", UppercaseAll, b"=\""),
+    (b"", Identity, b"ous "),
+    (b"", UppercaseAll, b", "),
+    (b"", UppercaseFirst, b"='"),
+    (b" ", UppercaseFirst, b","),
+    (b" ", UppercaseAll, b"=\""),
+    (b" ", UppercaseAll, b", "),
+    (b"", UppercaseAll, b","),
+    (b"", UppercaseAll, b"("),
+    (b"", UppercaseAll, b". "),
+    (b" ", UppercaseAll, b"."),
+    (b"", UppercaseAll, b"='"),
+    (b" ", UppercaseAll, b". "),
+    (b" ", UppercaseFirst, b"=\""),
+    (b" ", UppercaseAll, b"='"),
+    (b" ", UppercaseFirst, b"='"),
+];
+
+// Context of the last byte in UTF8 mode
+const UTF8_LUT0: [u8; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 4, 4, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    8, 12, 16, 12, 12, 20, 12, 16, 24, 28, 12, 12, 32, 12, 36, 12, 44, 44, 44, 44, 44, 44, 44, 44,
+    44, 44, 32, 32, 24, 40, 28, 12, 12, 48, 52, 52, 52, 48, 52, 52, 52, 48, 52, 52, 52, 52, 52, 48,
+    52, 52, 52, 52, 52, 48, 52, 52, 52, 52, 52, 24, 12, 28, 12, 12, 12, 56, 60, 60, 60, 56, 60, 60,
+    60, 56, 60, 60, 60, 60, 60, 56, 60, 60, 60, 60, 60, 56, 60, 60, 60, 60, 60, 24, 12, 28, 12, 0,
+    0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1,
+    0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1,
+    2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3,
+    2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3,
+];
+// Context of the second to last byte in UTF8 mode
+const UTF8_LUT1: [u8; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1,
+    1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1,
+    1, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 1, 1, 1, 1, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BrotliError {
+    UnexpectedEnd,
+    InvalidData, // a prefix code, context map, distance or length the stream does not allow
+    Unsupported, // large window streams
+    LimitExceeded, // more output than the caller expects
+}
+
+// Reads bits least significant first, as Brotli packs them
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize, // in bits
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> BitReader<'a> {
+        BitReader { data, position: 0 }
+    }
+
+    fn bit(&mut self) -> Result<u32, BrotliError> {
+        let byte = *self
+            .data
+            .get(self.position / 8)
+            .ok_or(BrotliError::UnexpectedEnd)?;
+        let bit = (byte >> (self.position % 8)) & 1;
+        self.position += 1;
+        Ok(bit as u32)
+    }
+
+    fn bits(&mut self, count: u8) -> Result<u32, BrotliError> {
+        (0..count).try_fold(0, |value, index| Ok(value | (self.bit()? << index)))
+    }
+
+    // The next bits without consuming them, zero past the end of the stream
+    fn peek(&self, count: u8) -> u32 {
+        (0..count as usize).fold(0, |value, index| {
+            let position = self.position + index;
+            let byte = self.data.get(position / 8).copied().unwrap_or(0);
+            value | ((((byte >> (position % 8)) & 1) as u32) << index)
+        })
+    }
+
+    fn align_to_byte(&mut self) -> Result<(), BrotliError> {
+        if self.bits((8 - self.position % 8) as u8 % 8)? != 0 {
+            return Err(BrotliError::InvalidData);
+        }
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], BrotliError> {
+        let start = self.position / 8;
+        let bytes = self
+            .data
+            .get(start..start + length)
+            .ok_or(BrotliError::UnexpectedEnd)?;
+        self.position += length * 8;
+        Ok(bytes)
+    }
+}
+
+// Canonical prefix code, decoded one bit at a time
+#[derive(Debug)]
+struct PrefixCode {
+    counts: [u16; MAX_CODE_LENGTH + 1], // number of codes of each length
+    symbols: Vec<u16>,                  // symbols ordered by code
+}
+
+impl PrefixCode {
+    // A single symbol with a non zero length is decoded without reading any bit
+    fn new(lengths: &[u8]) -> PrefixCode {
+        let mut counts = [0u16; MAX_CODE_LENGTH + 1];
+        lengths
+            .iter()
+            .for_each(|length| counts[*length as usize] += 1);
+        counts[0] = 0;
+
+        let mut offsets = [0u16; MAX_CODE_LENGTH + 2];
+        (1..=MAX_CODE_LENGTH)
+            .for_each(|length| offsets[length + 1] = offsets[length] + counts[length]);
+
+        let mut symbols = vec![0; offsets[MAX_CODE_LENGTH + 1] as usize];
+        lengths
+            .iter()
+            .enumerate()
+            .filter(|(_, length)| **length != 0)
+            .for_each(|(symbol, length)| {
+                symbols[offsets[*length as usize] as usize] = symbol as u16;
+                offsets[*length as usize] += 1;
+            });
+
+        PrefixCode { counts, symbols }
+    }
+
+    fn single(symbol: u16) -> PrefixCode {
+        PrefixCode {
+            counts: [0; MAX_CODE_LENGTH + 1],
+            symbols: vec![symbol],
+        }
+    }
+
+    fn decode(&self, bit_reader: &mut BitReader) -> Result<u16, BrotliError> {
+        if self.symbols.len() == 1 {
+            return Ok(self.symbols[0]);
+        }
+        let mut code: i32 = 0; // code of the current length
+        let mut first: i32 = 0; // first code of the current length
+        let mut index: i32 = 0; // index of the first code of the current length in symbols
+        for length in 1..=MAX_CODE_LENGTH {
+            code |= bit_reader.bit()? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(BrotliError::InvalidData)
+    }
+
+    fn from_stream(
+        bit_reader: &mut BitReader,
+        alphabet_size: usize,
+    ) -> Result<PrefixCode, BrotliError> {
+        match bit_reader.bits(2)? {
+            1 => Self::simple_from_stream(bit_reader, alphabet_size),
+            skip => Self::complex_from_stream(bit_reader, alphabet_size, skip as usize),
+        }
+    }
+
+    // Up to 4 symbols with implied code lengths
+    fn simple_from_stream(
+        bit_reader: &mut BitReader,
+        alphabet_size: usize,
+    ) -> Result<PrefixCode, BrotliError> {
+        let symbol_bits = (alphabet_size - 1).ilog2() as u8 + 1;
+        let num_symbols = bit_reader.bits(2)? as usize + 1;
+        let symbols: Vec<usize> = (0..num_symbols)
+            .map(|_| Ok(bit_reader.bits(symbol_bits)? as usize))
+            .collect::<Result<_, BrotliError>>()?;
+        let invalid = symbols
+            .iter()
+            .enumerate()
+            .any(|(index, symbol)| *symbol >= alphabet_size || symbols[..index].contains(symbol));
+        if invalid {
+            return Err(BrotliError::InvalidData);
+        }
+
+        let code_lengths: &[u8] = match num_symbols {
+            1 => return Ok(PrefixCode::single(symbols[0] as u16)),
+            2 => &[1, 1],
+            3 => &[1, 2, 2],
+            _ if bit_reader.bit()? == 0 => &[2, 2, 2, 2],
+            _ => &[1, 2, 3, 3],
+        };
+        let mut lengths = vec![0u8; alphabet_size];
+        symbols
+            .iter()
+            .zip(code_lengths)
+            .for_each(|(symbol, length)| lengths[*symbol] = *length);
+        Ok(PrefixCode::new(&lengths))
+    }
+
+    // Code lengths coded with a code length code
+    fn complex_from_stream(
+        bit_reader: &mut BitReader,
+        alphabet_size: usize,
+        skip: usize,
+    ) -> Result<PrefixCode, BrotliError> {
+        let mut code_length_code_lengths = [0u8; 18];
+        let mut space: i32 = 32;
+        let mut num_codes = 0;
+        for index in CODE_LENGTH_CODE_ORDER.iter().skip(skip) {
+            let next = bit_reader.peek(4) as usize;
+            bit_reader.bits(CODE_LENGTH_PREFIX_LENGTH[next])?;
+            let length = CODE_LENGTH_PREFIX_VALUE[next];
+            code_length_code_lengths[*index] = length;
+            if length != 0 {
+                space -= 32 >> length;
+                num_codes += 1;
+                if space <= 0 {
+                    break;
+                }
+            }
+        }
+        if num_codes != 1 && space != 0 {
+            return Err(BrotliError::InvalidData);
+        }
+        let code_length_code = PrefixCode::new(&code_length_code_lengths);
+
+        let mut lengths = vec![0u8; alphabet_size];
+        let mut symbol = 0;
+        let mut previous_length = 8; // last non zero code length
+        let mut repeat = 0;
+        let mut repeat_length = 0;
+        let mut space: i32 = 1 << 15;
+        while symbol < alphabet_size && space > 0 {
+            let length = code_length_code.decode(bit_reader)? as u8;
+            if length < 16 {
+                repeat = 0;
+                lengths[symbol] = length;
+                symbol += 1;
+                if length != 0 {
+                    previous_length = length;
+                    space -= (1 << 15) >> length;
+                }
+                continue;
+            }
+
+            // 16 repeats the previous non zero length, 17 repeats zeros, consecutive repeat codes multiply
+            let (extra_bits, new_length) = if length == 16 {
+                (2, previous_length)
+            } else {
+                (3, 0)
+            };
+            if repeat_length != new_length {
+                repeat = 0;
+                repeat_length = new_length;
+            }
+            let old_repeat = repeat;
+            if repeat > 0 {
+                repeat = (repeat - 2) << extra_bits;
+            }
+            repeat += bit_reader.bits(extra_bits)? as usize + 3;
+            let repeat_delta = repeat - old_repeat;
+            if symbol + repeat_delta > alphabet_size {
+                return Err(BrotliError::InvalidData);
+            }
+            lengths[symbol..symbol + repeat_delta].fill(repeat_length);
+            symbol += repeat_delta;
+            if repeat_length != 0 {
+                space -= (repeat_delta as i32) << (15 - repeat_length);
+            }
+        }
+        if space != 0 {
+            return Err(BrotliError::InvalidData);
+        }
+        Ok(PrefixCode::new(&lengths))
+    }
+}
+
+// Block types and counts of one category: literals, insert-and-copy commands or distances
+struct BlockSwitch {
+    num_types: u32,
+    type_code: Option<PrefixCode>,
+    count_code: Option<PrefixCode>,
+    current_type: u32,
+    previous_type: u32,
+    remaining: u32, // in the current block
+}
+
+impl BlockSwitch {
+    fn from_stream(bit_reader: &mut BitReader) -> Result<BlockSwitch, BrotliError> {
+        let num_types = read_var_len_u8(bit_reader)? + 1;
+        if num_types < 2 {
+            return Ok(BlockSwitch {
+                num_types,
+                type_code: None,
+                count_code: None,
+                current_type: 0,
+                previous_type: 1,
+                remaining: u32::MAX,
+            });
+        }
+
+        let type_code = PrefixCode::from_stream(bit_reader, num_types as usize + 2)?;
+        let count_code = PrefixCode::from_stream(bit_reader, NUM_BLOCK_COUNT_SYMBOLS)?;
+        let remaining = read_block_count(bit_reader, &count_code)?;
+        Ok(BlockSwitch {
+            num_types,
+            type_code: Some(type_code),
+            count_code: Some(count_code),
+            current_type: 0,
+            previous_type: 1,
+            remaining,
+        })
+    }
+
+    // The block type of the next symbol, switching to the next block when the current one is done
+    fn next(&mut self, bit_reader: &mut BitReader) -> Result<u32, BrotliError> {
+        if self.remaining == 0 {
+            let type_code = self.type_code.as_ref().ok_or(BrotliError::InvalidData)?;
+            let count_code = self.count_code.as_ref().ok_or(BrotliError::InvalidData)?;
+            let new_type = match type_code.decode(bit_reader)? as u32 {
+                0 => self.previous_type,
+                1 => self.current_type + 1,
+                symbol => symbol - 2,
+            } % self.num_types;
+            self.previous_type = self.current_type;
+            self.current_type = new_type;
+            self.remaining = read_block_count(bit_reader, count_code)?;
+        }
+        self.remaining -= 1;
+        Ok(self.current_type)
+    }
+}
+
+fn read_var_len_u8(bit_reader: &mut BitReader) -> Result<u32, BrotliError> {
+    if bit_reader.bit()? == 0 {
+        return Ok(0);
+    }
+    Ok(match bit_reader.bits(3)? as u8 {
+        0 => 1,
+        bits => bit_reader.bits(bits)? + (1 << bits),
+    })
+}
+
+fn read_block_count(
+    bit_reader: &mut BitReader,
+    count_code: &PrefixCode,
+) -> Result<u32, BrotliError> {
+    let (base, extra_bits) = BLOCK_COUNT_CODES[count_code.decode(bit_reader)? as usize];
+    Ok(base + bit_reader.bits(extra_bits)?)
+}
+
+// Tree index of each context of each block type
+fn read_context_map(
+    bit_reader: &mut BitReader,
+    num_trees: usize,
+    size: usize,
+) -> Result<Vec<u8>, BrotliError> {
+    if num_trees < 2 {
+        return Ok(vec![0; size]);
+    }
+
+    let max_run_length_prefix = if bit_reader.bit()? == 1 {
+        bit_reader.bits(4)? + 1
+    } else {
+        0
+    };
+    let code = PrefixCode::from_stream(bit_reader, num_trees + max_run_length_prefix as usize)?;
+    let mut context_map: Vec<u8> = Vec::with_capacity(size);
+    while context_map.len() < size {
+        let symbol = code.decode(bit_reader)? as u32;
+        if symbol == 0 {
+            context_map.push(0);
+        } else if symbol <= max_run_length_prefix {
+            // A run of zeros
+            let run_length = (1 << symbol) + bit_reader.bits(symbol as u8)? as usize;
+            if context_map.len() + run_length > size {
+                return Err(BrotliError::InvalidData);
+            }
+            context_map.extend(std::iter::repeat_n(0, run_length));
+        } else {
+            context_map.push((symbol - max_run_length_prefix) as u8);
+        }
+    }
+
+    // Inverse move-to-front transform
+    if bit_reader.bit()? == 1 {
+        let mut mtf: Vec<u8> = (0..=255).collect();
+        context_map.iter_mut().for_each(|value| {
+            let index = *value as usize;
+            *value = mtf[index];
+            mtf.remove(index);
+            mtf.insert(0, *value);
+        });
+    }
+    Ok(context_map)
+}
+
+fn literal_context(mode: u32, p1: u8, p2: u8) -> usize {
+    let signed = |byte: u8| -> u8 {
+        match byte {
+            0 => 0,
+            1..=15 => 1,
+            16..=63 => 2,
+            64..=127 => 3,
+            128..=191 => 4,
+            192..=239 => 5,
+            240..=254 => 6,
+            255 => 7,
+        }
+    };
+    (match mode {
+        0 => p1 & 0x3F,
+        1 => p1 >> 2,
+        2 => UTF8_LUT0[p1 as usize] | UTF8_LUT1[p2 as usize],
+        _ => (signed(p1) << 3) | signed(p2),
+    }) as usize
+}
+
+fn read_window_bits(bit_reader: &mut BitReader) -> Result<u32, BrotliError> {
+    if bit_reader.bit()? == 0 {
+        return Ok(16);
+    }
+    Ok(match bit_reader.bits(3)? {
+        0 => match bit_reader.bits(3)? {
+            0 => 17,
+            1 => return Err(BrotliError::Unsupported),
+            bits => 8 + bits,
+        },
+        bits => 17 + bits,
+    })
+}
+
+// Uppercases the UTF-8 character at `position`, returns its length
+fn to_upper_case(word: &mut [u8], position: usize) -> usize {
+    if word[position] < 0xC0 {
+        if word[position].is_ascii_lowercase() {
+            word[position] ^= 0x20;
+        }
+        1
+    } else if word[position] < 0xE0 {
+        if position + 1 < word.len() {
+            word[position + 1] ^= 0x20;
+        }
+        2
+    } else {
+        if position + 2 < word.len() {
+            word[position + 2] ^= 0x05;
+        }
+        3
+    }
+}
+
+fn dictionary_word(length: usize, word_id: usize) -> Result<Vec<u8>, BrotliError> {
+    let size_bits = DICTIONARY_SIZE_BITS_BY_LENGTH[length];
+    let index = word_id & ((1 << size_bits) - 1);
+    let transform_id = word_id >> size_bits;
+    let (prefix, transform, suffix) = *TRANSFORMS
+        .get(transform_id)
+        .ok_or(BrotliError::InvalidData)?;
+
+    let offset: usize = (MIN_DICTIONARY_WORD_LENGTH..length)
+        .map(|length| length << DICTIONARY_SIZE_BITS_BY_LENGTH[length])
+        .sum::<usize>()
+        + index * length;
+    let word = &DICTIONARY[offset..offset + length];
+    let mut word = match transform {
+        OmitLast(count) => word[..length.saturating_sub(count)].to_vec(),
+        OmitFirst(count) => word[count.min(length)..].to_vec(),
+        _ => word.to_vec(),
+    };
+    match transform {
+        UppercaseFirst => {
+            to_upper_case(&mut word, 0);
+        }
+        UppercaseAll => {
+            let mut position = 0;
+            while position < word.len() {
+                position += to_upper_case(&mut word, position);
+            }
+        }
+        _ => {}
+    }
+
+    Ok([prefix, &word, suffix].concat())
+}
+
+// Decompression fails with LimitExceeded as soon as a meta-block would take the
+// output past `max_length`
+pub fn brotli_decompress(data: &[u8], max_length: usize) -> Result<Vec<u8>, BrotliError> {
+    let mut bit_reader = BitReader::new(data);
+    let max_distance = (1usize << read_window_bits(&mut bit_reader)?) - 16;
+    let mut output: Vec<u8> = vec![];
+    let mut last_distances: [usize; 4] = [16, 15, 11, 4]; // most recent last
+
+    loop {
+        let is_last = bit_reader.bit()? == 1;
+        if is_last && bit_reader.bit()? == 1 {
+            return Ok(output);
+        }
+
+        let num_nibbles = match bit_reader.bits(2)? {
+            3 => 0,
+            nibbles => nibbles + 4,
+        };
+        if num_nibbles == 0 {
+            // Metadata, skipped
+            if bit_reader.bit()? != 0 {
+                return Err(BrotliError::InvalidData);
+            }
+            let skip_bytes = bit_reader.bits(2)? as u8;
+            let skip_length = if skip_bytes == 0 {
+                0
+            } else {
+                bit_reader.bits(skip_bytes * 8)? as usize + 1
+            };
+            bit_reader.align_to_byte()?;
+            bit_reader.read_bytes(skip_length)?;
+            if is_last {
+                return Ok(output);
+            }
+            continue;
+        }
+        let meta_block_length = bit_reader.bits(num_nibbles as u8 * 4)? as usize + 1;
+        if output.len() + meta_block_length > max_length {
+            return Err(BrotliError::LimitExceeded);
+        }
+
+        if !is_last && bit_reader.bit()? == 1 {
+            bit_reader.align_to_byte()?;
+            output.extend_from_slice(bit_reader.read_bytes(meta_block_length)?);
+            continue;
+        }
+
+        let mut literal_blocks = BlockSwitch::from_stream(&mut bit_reader)?;
+        let mut command_blocks = BlockSwitch::from_stream(&mut bit_reader)?;
+        let mut distance_blocks = BlockSwitch::from_stream(&mut bit_reader)?;
+
+        let postfix_bits = bit_reader.bits(2)?;
+        let num_direct_distance_codes = bit_reader.bits(4)? << postfix_bits;
+        let postfix_mask = (1 << postfix_bits) - 1;
+
+        let context_modes: Vec<u32> = (0..literal_blocks.num_types)
+            .map(|_| bit_reader.bits(2))
+            .collect::<Result<_, BrotliError>>()?;
+        let num_literal_trees = read_var_len_u8(&mut bit_reader)? as usize + 1;
+        let literal_context_map = read_context_map(
+            &mut bit_reader,
+            num_literal_trees,
+            (literal_blocks.num_types as usize) << LITERAL_CONTEXT_BITS,
+        )?;
+        let num_distance_trees = read_var_len_u8(&mut bit_reader)? as usize + 1;
+        let distance_context_map = read_context_map(
+            &mut bit_reader,
+            num_distance_trees,
+            (distance_blocks.num_types as usize) << DISTANCE_CONTEXT_BITS,
+        )?;
+
+        let literal_codes: Vec<PrefixCode> = (0..num_literal_trees)
+            .map(|_| PrefixCode::from_stream(&mut bit_reader, NUM_LITERAL_SYMBOLS))
+            .collect::<Result<_, BrotliError>>()?;
+        let command_codes: Vec<PrefixCode> = (0..command_blocks.num_types)
+            .map(|_| PrefixCode::from_stream(&mut bit_reader, NUM_INSERT_AND_COPY_SYMBOLS))
+            .collect::<Result<_, BrotliError>>()?;
+        let distance_alphabet_size =
+            (NUM_DISTANCE_SHORT_CODES + num_direct_distance_codes + (48 << postfix_bits)) as usize;
+        let distance_codes: Vec<PrefixCode> = (0..num_distance_trees)
+            .map(|_| PrefixCode::from_stream(&mut bit_reader, distance_alphabet_size))
+            .collect::<Result<_, BrotliError>>()?;
+
+        let end = output.len() + meta_block_length;
+        while output.len() < end {
+            let command_type = command_blocks.next(&mut bit_reader)?;
+            let command = command_codes[command_type as usize].decode(&mut bit_reader)? as usize;
+            let (insert_range, copy_range) = INSERT_AND_COPY_RANGES[command >> 6];
+            let (insert_base, insert_bits) =
+                INSERT_LENGTH_CODES[(insert_range as usize) + ((command >> 3) & 7)];
+            let (copy_base, copy_bits) = COPY_LENGTH_CODES[(copy_range as usize) + (command & 7)];
+            let insert_length = (insert_base + bit_reader.bits(insert_bits)?) as usize;
+            let copy_length = (copy_base + bit_reader.bits(copy_bits)?) as usize;
+
+            if output.len() + insert_length > end {
+                return Err(BrotliError::InvalidData);
+            }
+            for _ in 0..insert_length {
+                let literal_type = literal_blocks.next(&mut bit_reader)?;
+                let p1 = output.last().copied().unwrap_or(0);
+                let p2 = output.len().checked_sub(2).map_or(0, |index| output[index]);
+                let context = literal_context(context_modes[literal_type as usize], p1, p2);
+                let tree = literal_context_map
+                    [((literal_type as usize) << LITERAL_CONTEXT_BITS) + context];
+                output.push(literal_codes[tree as usize].decode(&mut bit_reader)? as u8);
+            }
+            if output.len() == end {
+                break;
+            }
+
+            // The first 128 commands reuse the last distance
+            let distance_code = if command < 128 {
+                0
+            } else {
+                let distance_type = distance_blocks.next(&mut bit_reader)?;
+                let context = copy_length.min(5) - 2;
+                let tree = distance_context_map
+                    [((distance_type as usize) << DISTANCE_CONTEXT_BITS) + context];
+                distance_codes[tree as usize].decode(&mut bit_reader)? as u32
+            };
+
+            let distance = if distance_code < NUM_DISTANCE_SHORT_CODES {
+                let (index, delta) = DISTANCE_SHORT_CODES[distance_code as usize];
+                let distance = last_distances[3 - index] as i64 + delta as i64;
+                if distance <= 0 {
+                    return Err(BrotliError::InvalidData);
+                }
+                distance as usize
+            } else if distance_code < NUM_DISTANCE_SHORT_CODES + num_direct_distance_codes {
+                (distance_code - NUM_DISTANCE_SHORT_CODES + 1) as usize
+            } else {
+                let code = distance_code - NUM_DISTANCE_SHORT_CODES - num_direct_distance_codes;
+                let extra_bits = 1 + (code >> (postfix_bits + 1));
+                let extra = bit_reader.bits(extra_bits as u8)? as usize;
+                let high = (code >> postfix_bits) as usize;
+                let low = (code & postfix_mask) as usize;
+                let offset = ((2 + (high & 1)) << extra_bits) - 4;
+                ((offset + extra) << postfix_bits) + low + num_direct_distance_codes as usize + 1
+            };
+
+            let max_backward_distance = output.len().min(max_distance);
+            if distance > max_backward_distance {
+                // A static dictionary word, possibly transformed
+                if !(MIN_DICTIONARY_WORD_LENGTH..=MAX_DICTIONARY_WORD_LENGTH).contains(&copy_length)
+                {
+                    return Err(BrotliError::InvalidData);
+                }
+                let word = dictionary_word(copy_length, distance - max_backward_distance - 1)?;
+                if output.len() + word.len() > end {
+                    return Err(BrotliError::InvalidData);
+                }
+                output.extend_from_slice(&word);
+                continue;
+            }
+
+            if distance_code != 0 {
+                last_distances.rotate_left(1);
+                last_distances[3] = distance;
+            }
+            if output.len() + copy_length > end {
+                return Err(BrotliError::InvalidData);
+            }
+            // The copy can overlap the bytes it produces
+            let start = output.len() - distance;
+            (0..copy_length).for_each(|offset| output.push(output[start + offset]));
+        }
+
+        if is_last {
+            return Ok(output);
+        }
+    }
+}
+
+// A prefix code for the symbol frequencies, written to the stream. Returns the
+// code lengths and codes of the symbols.
+fn write_prefix_code(
+    frequencies: &[u32],
+    alphabet_size: usize,
+    writer: &mut BitWriter,
+) -> (Vec<u8>, Vec<u32>) {
+    let symbol_bits = (alphabet_size - 1).ilog2() as u8 + 1;
+    let used: Vec<usize> = (0..alphabet_size)
+        .filter(|symbol| frequencies[*symbol] > 0)
+        .collect();
+    if used.len() <= 1 {
+        // A single symbol is decoded without reading any bit
+        writer.write_bits(1, 2);
+        writer.write_bits(0, 2);
+        writer.write_bits(used.first().copied().unwrap_or(0) as u32, symbol_bits);
+        return (vec![0; alphabet_size], vec![0; alphabet_size]);
+    }
+
+    if used.len() <= MAX_SIMPLE_CODE_SYMBOLS {
+        let lengths = prefix_code_lengths(frequencies, 3);
+        let mut symbols = used.clone();
+        symbols.sort_by_key(|symbol| (lengths[*symbol], *symbol));
+        writer.write_bits(1, 2);
+        writer.write_bits(symbols.len() as u32 - 1, 2);
+        symbols
+            .iter()
+            .for_each(|symbol| writer.write_bits(*symbol as u32, symbol_bits));
+        if symbols.len() == 4 {
+            writer.write_bits((lengths[symbols[0]] == 1) as u32, 1);
+        }
+        let codes = canonical_codes(&lengths);
+        return (lengths, codes);
+    }
+
+    let lengths = prefix_code_lengths(frequencies, MAX_CODE_LENGTH as u8);
+    let code_length_symbols = run_length_encode(&lengths);
+    let mut code_length_frequencies = [0u32; 18];
+    code_length_symbols
+        .iter()
+        .for_each(|(symbol, _)| code_length_frequencies[*symbol as usize] += 1);
+    let code_length_lengths =
+        prefix_code_lengths(&code_length_frequencies, MAX_CODE_LENGTH_CODE_LENGTH);
+    let code_length_codes = canonical_codes(&code_length_lengths);
+
+    // The decoder stops at the last code length that completes the code,
+    // a single code length code is followed by all the others
+    let code_length_count = if code_length_lengths.iter().filter(|l| **l != 0).count() == 1 {
+        CODE_LENGTH_CODE_ORDER.len()
+    } else {
+        CODE_LENGTH_CODE_ORDER
+            .iter()
+            .rposition(|symbol| code_length_lengths[*symbol] != 0)
+            .unwrap()
+            + 1
+    };
+    writer.write_bits(0, 2); // no code length codes skipped
+    CODE_LENGTH_CODE_ORDER[..code_length_count]
+        .iter()
+        .for_each(|symbol| {
+            let length = code_length_lengths[*symbol];
+            let prefix = CODE_LENGTH_PREFIX_VALUE
+                .iter()
+                .position(|value| *value == length)
+                .unwrap();
+            writer.write_bits(prefix as u32, CODE_LENGTH_PREFIX_LENGTH[prefix]);
+        });
+    code_length_symbols.iter().for_each(|(symbol, extra)| {
+        let symbol = *symbol as usize;
+        writer.write_code(code_length_codes[symbol], code_length_lengths[symbol]);
+        match symbol {
+            16 => writer.write_bits(*extra, 2),
+            17 => writer.write_bits(*extra, 3),
+            _ => {}
+        }
+    });
+
+    let codes = canonical_codes(&lengths);
+    (lengths, codes)
+}
+
+// Code lengths as (symbol, extra bits), trailing zeros omitted. Consecutive
+// repeat codes multiply, so a run is written as its digits, most significant first.
+fn run_length_encode(lengths: &[u8]) -> Vec<(u8, u32)> {
+    let end = lengths.iter().rposition(|length| *length != 0).unwrap() + 1;
+    let mut symbols: Vec<(u8, u32)> = vec![];
+    let mut previous_length = 8;
+    let mut index = 0;
+    while index < end {
+        let length = lengths[index];
+        let mut run = lengths[index..end]
+            .iter()
+            .take_while(|next| **next == length)
+            .count();
+        index += run;
+
+        // A length that differs from the previous one is written once before its repeats
+        let (repeat_code, repeat_bits, single_run) =
+            if length == 0 { (17, 3, 11) } else { (16, 2, 7) };
+        if length != 0 && length != previous_length {
+            symbols.push((length, 0));
+            run -= 1;
+            previous_length = length;
+        }
+        if run == single_run {
+            symbols.push((length, 0));
+            run -= 1;
+        }
+        if run < 3 {
+            (0..run).for_each(|_| symbols.push((length, 0)));
+            continue;
+        }
+
+        let start = symbols.len();
+        let mut repeat = run - 3;
+        loop {
+            symbols.push((repeat_code, (repeat & ((1 << repeat_bits) - 1)) as u32));
+            repeat >>= repeat_bits;
+            if repeat == 0 {
+                break;
+            }
+            repeat -= 1;
+        }
+        symbols[start..].reverse();
+    }
+    symbols
+}
+
+// (code, extra bits, extra bit count) of a length in an insert or copy length code table
+fn length_code(codes: &[(u32, u8)], length: usize) -> (usize, u32, u8) {
+    let code = codes
+        .iter()
+        .rposition(|(base, _)| *base as usize <= length)
+        .unwrap();
+    let (base, extra_bits) = codes[code];
+    (code, length as u32 - base, extra_bits)
+}
+
+// Insert-and-copy symbol of the insert and copy length codes. The first two
+// groups of 64 symbols imply the last distance.
+fn insert_and_copy_symbol(insert_code: usize, copy_code: usize, last_distance: bool) -> usize {
+    let (group, (insert_range, copy_range)) = INSERT_AND_COPY_RANGES
+        .iter()
+        .enumerate()
+        .skip(if last_distance { 0 } else { 2 })
+        .find(|(_, (insert_range, copy_range))| {
+            (*insert_range as usize..*insert_range as usize + 8).contains(&insert_code)
+                && (*copy_range as usize..*copy_range as usize + 8).contains(&copy_code)
+        })
+        .unwrap();
+    (group << 6)
+        | ((insert_code - *insert_range as usize) << 3)
+        | (copy_code - *copy_range as usize)
+}
+
+// (code, extra bits, extra bit count) of a distance without postfix bits or direct codes
+fn distance_code(distance: usize) -> (usize, u32, u8) {
+    let value = distance + 3;
+    let extra_bits = value.ilog2() - 1;
+    let prefix = (value >> extra_bits) & 1;
+    let code = 2 * (extra_bits as usize - 1) + prefix;
+    let extra = value - ((2 + prefix) << extra_bits);
+    (
+        NUM_DISTANCE_SHORT_CODES as usize + code,
+        extra as u32,
+        extra_bits as u8,
+    )
+}
+
+// A Brotli command: literals followed by a copy, the copy is omitted at the end of the stream
+struct Command<'a> {
+    literals: &'a [u8],
+    copy_length: usize,
+    distance: usize,
+}
+
+// The prefix coded symbols of a command, extra bits as (value, bit count)
+struct CommandCode {
+    symbol: usize,
+    insert_extra: (u32, u8),
+    copy_extra: (u32, u8),
+    distance: Option<(usize, u32, u8)>, // omitted when the symbol implies the last distance
+}
+
+fn write_meta_block(commands: &[Command], length: usize, writer: &mut BitWriter) {
+    writer.write_bits(0, 1); // ISLAST, the stream ends with an empty meta-block
+    let num_nibbles = (((length - 1).max(1).ilog2() + 1).div_ceil(4)).max(4);
+    writer.write_bits(num_nibbles - 4, 2);
+    writer.write_bits(length as u32 - 1, num_nibbles as u8 * 4);
+    writer.write_bits(0, 1); // ISUNCOMPRESSED
+    (0..3).for_each(|_| writer.write_bits(0, 1)); // one block type of each category
+    writer.write_bits(0, 2); // NPOSTFIX
+    writer.write_bits(0, 4); // NDIRECT
+    writer.write_bits(0, 2); // literal context mode LSB6
+    writer.write_bits(0, 1); // one literal prefix code
+    writer.write_bits(0, 1); // one distance prefix code
+
+    let mut last_distance = 4;
+    let codes: Vec<CommandCode> = commands
+        .iter()
+        .map(|command| {
+            let (insert_code, insert_extra, insert_bits) =
+                length_code(&INSERT_LENGTH_CODES, command.literals.len());
+            let (copy_code, copy_extra, copy_bits) =
+                length_code(&COPY_LENGTH_CODES, command.copy_length.max(2));
+            let reuse_distance = command.copy_length == 0 || command.distance == last_distance;
+            let symbol = insert_and_copy_symbol(insert_code, copy_code, reuse_distance);
+            // Distance code 0 repeats the last distance when the symbol does not imply it
+            let distance = if command.copy_length == 0 || symbol < 128 {
+                None
+            } else if reuse_distance {
+                Some((0, 0, 0))
+            } else {
+                last_distance = command.distance;
+                Some(distance_code(command.distance))
+            };
+            CommandCode {
+                symbol,
+                insert_extra: (insert_extra, insert_bits),
+                copy_extra: (copy_extra, copy_bits),
+                distance,
+            }
+        })
+        .collect();
+
+    let mut literal_frequencies = vec![0u32; NUM_LITERAL_SYMBOLS];
+    let mut command_frequencies = vec![0u32; NUM_INSERT_AND_COPY_SYMBOLS];
+    let mut distance_frequencies = vec![0u32; NUM_DISTANCE_SYMBOLS];
+    commands.iter().zip(&codes).for_each(|(command, code)| {
+        command
+            .literals
+            .iter()
+            .for_each(|literal| literal_frequencies[*literal as usize] += 1);
+        command_frequencies[code.symbol] += 1;
+        if let Some((distance_code, _, _)) = code.distance {
+            distance_frequencies[distance_code] += 1;
+        }
+    });
+    let (literal_lengths, literal_codes) =
+        write_prefix_code(&literal_frequencies, NUM_LITERAL_SYMBOLS, writer);
+    let (command_lengths, command_codes) =
+        write_prefix_code(&command_frequencies, NUM_INSERT_AND_COPY_SYMBOLS, writer);
+    let (distance_lengths, distance_codes) =
+        write_prefix_code(&distance_frequencies, NUM_DISTANCE_SYMBOLS, writer);
+
+    commands.iter().zip(&codes).for_each(|(command, code)| {
+        writer.write_code(command_codes[code.symbol], command_lengths[code.symbol]);
+        writer.write_bits(code.insert_extra.0, code.insert_extra.1);
+        writer.write_bits(code.copy_extra.0, code.copy_extra.1);
+        command.literals.iter().for_each(|literal| {
+            let literal = *literal as usize;
+            writer.write_code(literal_codes[literal], literal_lengths[literal]);
+        });
+        if let Some((distance_code, extra, extra_bits)) = code.distance {
+            writer.write_code(
+                distance_codes[distance_code],
+                distance_lengths[distance_code],
+            );
+            writer.write_bits(extra, extra_bits);
+        }
+    });
+}
+
+pub fn brotli_compress(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    writer.write_bits(1, 1);
+    writer.write_bits(WINDOW_BITS - 17, 3);
+
+    let tokens = lz77_tokens(
+        data,
+        (1 << WINDOW_BITS) - 16,
+        MIN_COPY_LENGTH,
+        MAX_COPY_LENGTH,
+    );
+    let mut position = 0;
+    let mut token_index = 0;
+    while token_index < tokens.len() {
+        // Each meta-block covers whole tokens
+        let start = position;
+        let mut commands: Vec<Command> = vec![];
+        let mut literal_start = position;
+        while token_index < tokens.len()
+            && position + tokens[token_index].length() - start <= MAX_META_BLOCK_LENGTH
+        {
+            if let Lz77Token::Match { length, distance } = tokens[token_index] {
+                commands.push(Command {
+                    literals: &data[literal_start..position],
+                    copy_length: length,
+                    distance,
+                });
+                literal_start = position + length;
+            }
+            position += tokens[token_index].length();
+            token_index += 1;
+        }
+        if literal_start < position {
+            commands.push(Command {
+                literals: &data[literal_start..position],
+                copy_length: 0,
+                distance: 0,
+            });
+        }
+        write_meta_block(&commands, position - start, &mut writer);
+    }
+
+    writer.write_bits(1, 1); // ISLAST
+    writer.write_bits(1, 1); // ISLASTEMPTY
+    writer.align_to_byte();
+    writer.into_vec()
+}
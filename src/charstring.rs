@@ -0,0 +1,645 @@
+use crate::model::{Contour, Point, PointType};
+
+// Interpreter for Type 2 charstrings (Adobe Technical Note #5177), the glyph
+// programs of CFF fonts, and for their CFF2 flavor. Hints only matter for the
+// number of stems, which sets the size of the hint masks.
+
+const MAX_STACK_SIZE: usize = 48;
+const MAX_CFF2_STACK_SIZE: usize = 513;
+const MAX_SUBR_DEPTH: usize = 10;
+const TRANSIENT_ARRAY_SIZE: usize = 32;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CharstringError {
+    StackOverflow,
+    StackUnderflow,
+    InvalidOperator(u16), // escaped operators are 1200 + their second byte
+    InvalidSubroutine(i32),
+    SubroutineDepth,
+    UnexpectedEnd,   // operand data past the end of the charstring
+    NoCurrentPoint,  // a path drawn before the first moveto
+    InvalidSeac(u8), // accent or base code missing from the charset
+}
+
+// Accented character built from two glyphs of the standard encoding, given by
+// endchar with four arguments
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Seac {
+    pub adx: f32, // offset of the accent
+    pub ady: f32,
+    pub base_code: u8,
+    pub accent_code: u8,
+}
+
+#[derive(Debug, Default)]
+pub struct CharstringOutline {
+    pub width: Option<f32>, // relative to nominalWidthX, None for defaultWidthX
+    pub contours: Vec<Vec<(f32, f32, bool)>>, // (x, y, on curve)
+    pub seac: Option<Seac>,
+}
+
+impl CharstringOutline {
+    // Contours rounded to font units. The closing point of a contour is left
+    // out when it is back on the starting point, as in glyf.
+    pub fn to_contours(&self, dx: f32, dy: f32) -> Vec<Contour> {
+        self.contours
+            .iter()
+            .filter(|points| points.len() > 1)
+            .map(|points| {
+                let mut points: Vec<Point> = points
+                    .iter()
+                    .map(|(x, y, on_curve)| {
+                        Point::new(
+                            (x + dx).round() as i16,
+                            (y + dy).round() as i16,
+                            PointType::from(*on_curve),
+                        )
+                    })
+                    .collect();
+                if points.len() > 1 && points.last() == points.first() {
+                    points.pop();
+                }
+                Contour { points }
+            })
+            .collect()
+    }
+}
+
+// Variation state of CFF2 charstrings, for the blend operator
+#[derive(Debug)]
+pub struct Blend {
+    pub vsindex: usize, // ItemVariationData used unless the charstring sets one
+    pub region_scalars: Vec<Vec<f32>>, // scalar of each region of each ItemVariationData
+}
+
+// Subroutine number bias, set by the number of subroutines
+pub fn subr_bias(count: usize) -> i32 {
+    if count < 1240 {
+        107
+    } else if count < 33900 {
+        1131
+    } else {
+        32768
+    }
+}
+
+pub fn execute_charstring(
+    charstring: &[u8],
+    global_subrs: &[Vec<u8>],
+    local_subrs: &[Vec<u8>],
+    blend: Option<&Blend>, // CFF2 charstrings, which have no width, endchar or seac
+) -> Result<CharstringOutline, CharstringError> {
+    let mut interpreter = Interpreter {
+        global_subrs,
+        local_subrs,
+        blend,
+        vsindex: blend.map_or(0, |blend| blend.vsindex),
+        stack: vec![],
+        transient_array: [0.0; TRANSIENT_ARRAY_SIZE],
+        x: 0.0,
+        y: 0.0,
+        stem_count: 0,
+        width_parsed: blend.is_some(),
+        random_seed: 0,
+        ended: false,
+        outline: CharstringOutline::default(),
+    };
+    interpreter.execute(charstring, 0)?;
+    Ok(interpreter.outline)
+}
+
+struct Interpreter<'a> {
+    global_subrs: &'a [Vec<u8>],
+    local_subrs: &'a [Vec<u8>],
+    blend: Option<&'a Blend>,
+    vsindex: usize,
+    stack: Vec<f32>,
+    transient_array: [f32; TRANSIENT_ARRAY_SIZE],
+    x: f32, // current point
+    y: f32,
+    stem_count: usize,
+    width_parsed: bool, // the first stack clearing operator can carry the width
+    random_seed: u32,
+    ended: bool,
+    outline: CharstringOutline,
+}
+
+impl Interpreter<'_> {
+    fn execute(&mut self, charstring: &[u8], depth: usize) -> Result<(), CharstringError> {
+        if depth > MAX_SUBR_DEPTH {
+            return Err(CharstringError::SubroutineDepth);
+        }
+        let mut position = 0;
+        while position < charstring.len() && !self.ended {
+            let b0 = charstring[position];
+            position += 1;
+            let operand = |position: &mut usize, length: usize| -> Result<&[u8], CharstringError> {
+                let bytes = charstring
+                    .get(*position..*position + length)
+                    .ok_or(CharstringError::UnexpectedEnd)?;
+                *position += length;
+                Ok(bytes)
+            };
+
+            match b0 {
+                28 => {
+                    let bytes = operand(&mut position, 2)?;
+                    self.push(i16::from_be_bytes([bytes[0], bytes[1]]) as f32)?;
+                }
+                32..=246 => self.push(b0 as f32 - 139.0)?,
+                247..=250 => {
+                    let b1 = operand(&mut position, 1)?[0];
+                    self.push(((b0 as f32 - 247.0) * 256.0) + b1 as f32 + 108.0)?;
+                }
+                251..=254 => {
+                    let b1 = operand(&mut position, 1)?[0];
+                    self.push(-((b0 as f32 - 251.0) * 256.0) - b1 as f32 - 108.0)?;
+                }
+                255 => {
+                    // 16.16 fixed point
+                    let bytes = operand(&mut position, 4)?;
+                    let value = i32::from_be_bytes(bytes.try_into().unwrap());
+                    self.push(value as f32 / 65536.0)?;
+                }
+                10 | 29 => {
+                    let subrs = if b0 == 10 {
+                        self.local_subrs
+                    } else {
+                        self.global_subrs
+                    };
+                    let index = self.pop()? as i32 + subr_bias(subrs.len());
+                    let subr = usize::try_from(index)
+                        .ok()
+                        .and_then(|index| subrs.get(index))
+                        .ok_or(CharstringError::InvalidSubroutine(index))?;
+                    self.execute(subr, depth + 1)?;
+                }
+                11 => return Ok(()),
+                19 | 20 => {
+                    // hintmask and cntrmask, the arguments are vertical stems
+                    self.stems();
+                    operand(&mut position, self.stem_count.div_ceil(8))?;
+                }
+                12 => {
+                    let b1 = operand(&mut position, 1)?[0];
+                    self.escaped_operator(b1)?;
+                }
+                15 | 16 if self.blend.is_some() => self.blend_operator(b0)?,
+                _ => self.operator(b0)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, value: f32) -> Result<(), CharstringError> {
+        let max_stack_size = if self.blend.is_some() {
+            MAX_CFF2_STACK_SIZE
+        } else {
+            MAX_STACK_SIZE
+        };
+        if self.stack.len() >= max_stack_size {
+            return Err(CharstringError::StackOverflow);
+        }
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<f32, CharstringError> {
+        self.stack.pop().ok_or(CharstringError::StackUnderflow)
+    }
+
+    // The arguments of an operator that takes at least `count` of them
+    fn arguments(&self, count: usize) -> Result<Vec<f32>, CharstringError> {
+        if self.stack.len() < count {
+            return Err(CharstringError::StackUnderflow);
+        }
+        Ok(self.stack.clone())
+    }
+
+    // Takes the width off the stack when the first stack clearing operator has
+    // one argument more than it needs
+    fn parse_width(&mut self, has_extra_argument: bool) {
+        if !self.width_parsed {
+            self.width_parsed = true;
+            if has_extra_argument && !self.stack.is_empty() {
+                self.outline.width = Some(self.stack.remove(0));
+            }
+        }
+    }
+
+    fn stems(&mut self) {
+        self.parse_width(self.stack.len() % 2 == 1);
+        self.stem_count += self.stack.len() / 2;
+        self.stack.clear();
+    }
+
+    fn move_to(&mut self, dx: f32, dy: f32) {
+        self.x += dx;
+        self.y += dy;
+        self.outline.contours.push(vec![(self.x, self.y, true)]);
+    }
+
+    fn line_to(&mut self, dx: f32, dy: f32) -> Result<(), CharstringError> {
+        self.x += dx;
+        self.y += dy;
+        self.add_point(self.x, self.y, true)
+    }
+
+    fn curve_to(
+        &mut self,
+        dx1: f32,
+        dy1: f32,
+        dx2: f32,
+        dy2: f32,
+        dx3: f32,
+        dy3: f32,
+    ) -> Result<(), CharstringError> {
+        let (x1, y1) = (self.x + dx1, self.y + dy1);
+        let (x2, y2) = (x1 + dx2, y1 + dy2);
+        self.x = x2 + dx3;
+        self.y = y2 + dy3;
+        self.add_point(x1, y1, false)?;
+        self.add_point(x2, y2, false)?;
+        self.add_point(self.x, self.y, true)
+    }
+
+    fn add_point(&mut self, x: f32, y: f32, on_curve: bool) -> Result<(), CharstringError> {
+        let contour = self
+            .outline
+            .contours
+            .last_mut()
+            .ok_or(CharstringError::NoCurrentPoint)?;
+        contour.push((x, y, on_curve));
+        Ok(())
+    }
+
+    fn operator(&mut self, operator: u8) -> Result<(), CharstringError> {
+        let args = self.stack.clone();
+        match operator {
+            1 | 3 | 18 | 23 => {
+                // hstem, vstem, hstemhm, vstemhm
+                self.stems();
+                return Ok(());
+            }
+            21 => {
+                self.arguments(2)?;
+                self.parse_width(args.len() > 2);
+                let args = &self.stack;
+                let (dx, dy) = (args[args.len() - 2], args[args.len() - 1]);
+                self.move_to(dx, dy);
+            }
+            22 | 4 => {
+                self.arguments(1)?;
+                self.parse_width(args.len() > 1);
+                let value = self.stack[self.stack.len() - 1];
+                if operator == 22 {
+                    self.move_to(value, 0.0);
+                } else {
+                    self.move_to(0.0, value);
+                }
+            }
+            5 => args
+                .chunks_exact(2)
+                .try_for_each(|arg| self.line_to(arg[0], arg[1]))?,
+            6 | 7 => {
+                // Alternating horizontal and vertical lines
+                let mut horizontal = operator == 6;
+                for value in &args {
+                    if horizontal {
+                        self.line_to(*value, 0.0)?;
+                    } else {
+                        self.line_to(0.0, *value)?;
+                    }
+                    horizontal = !horizontal;
+                }
+            }
+            8 => args.chunks_exact(6).try_for_each(|arg| {
+                self.curve_to(arg[0], arg[1], arg[2], arg[3], arg[4], arg[5])
+            })?,
+            24 => {
+                // rcurveline
+                self.arguments(2)?;
+                let curves = (args.len() - 2) / 6;
+                args[..curves * 6].chunks_exact(6).try_for_each(|arg| {
+                    self.curve_to(arg[0], arg[1], arg[2], arg[3], arg[4], arg[5])
+                })?;
+                self.line_to(args[curves * 6], args[curves * 6 + 1])?;
+            }
+            25 => {
+                // rlinecurve
+                self.arguments(6)?;
+                let lines = (args.len() - 6) / 2;
+                args[..lines * 2]
+                    .chunks_exact(2)
+                    .try_for_each(|arg| self.line_to(arg[0], arg[1]))?;
+                let arg = &args[lines * 2..];
+                self.curve_to(arg[0], arg[1], arg[2], arg[3], arg[4], arg[5])?;
+            }
+            26 => {
+                // vvcurveto, an odd argument count starts with dx1
+                let (mut dx1, rest) = if args.len() % 2 == 1 {
+                    (args[0], &args[1..])
+                } else {
+                    (0.0, &args[..])
+                };
+                for arg in rest.chunks_exact(4) {
+                    self.curve_to(dx1, arg[0], arg[1], arg[2], 0.0, arg[3])?;
+                    dx1 = 0.0;
+                }
+            }
+            27 => {
+                // hhcurveto, an odd argument count starts with dy1
+                let (mut dy1, rest) = if args.len() % 2 == 1 {
+                    (args[0], &args[1..])
+                } else {
+                    (0.0, &args[..])
+                };
+                for arg in rest.chunks_exact(4) {
+                    self.curve_to(arg[0], dy1, arg[1], arg[2], arg[3], 0.0)?;
+                    dy1 = 0.0;
+                }
+            }
+            30 | 31 => {
+                // vhcurveto and hvcurveto alternate between curves starting
+                // vertically and horizontally, the last one can end off axis
+                let mut horizontal = operator == 31;
+                let mut index = 0;
+                while index + 4 <= args.len() {
+                    let arg = &args[index..];
+                    let last = if args.len() - index == 5 { arg[4] } else { 0.0 };
+                    if horizontal {
+                        self.curve_to(arg[0], 0.0, arg[1], arg[2], last, arg[3])?;
+                    } else {
+                        self.curve_to(0.0, arg[0], arg[1], arg[2], arg[3], last)?;
+                    }
+                    index += 4;
+                    horizontal = !horizontal;
+                }
+            }
+            14 => {
+                // endchar, four arguments make an accented character
+                self.parse_width(args.len() == 1 || args.len() == 5);
+                let args = &self.stack;
+                if args.len() >= 4 {
+                    self.outline.seac = Some(Seac {
+                        adx: args[args.len() - 4],
+                        ady: args[args.len() - 3],
+                        base_code: args[args.len() - 2] as u8,
+                        accent_code: args[args.len() - 1] as u8,
+                    });
+                }
+                self.ended = true;
+            }
+            _ => return Err(CharstringError::InvalidOperator(operator as u16)),
+        }
+        self.stack.clear();
+        Ok(())
+    }
+
+    fn escaped_operator(&mut self, operator: u8) -> Result<(), CharstringError> {
+        match operator {
+            34..=37 => {
+                self.flex(operator)?;
+                self.stack.clear();
+            }
+            3 => self.binary(|a, b| (a != 0.0 && b != 0.0) as u8 as f32)?, // and
+            4 => self.binary(|a, b| (a != 0.0 || b != 0.0) as u8 as f32)?, // or
+            5 => self.unary(|a| (a == 0.0) as u8 as f32)?,                 // not
+            9 => self.unary(f32::abs)?,
+            10 => self.binary(|a, b| a + b)?,
+            11 => self.binary(|a, b| a - b)?,
+            12 => self.binary(|a, b| if b == 0.0 { 0.0 } else { a / b })?,
+            14 => self.unary(|a| -a)?,
+            15 => self.binary(|a, b| (a == b) as u8 as f32)?, // eq
+            18 => {
+                self.pop()?; // drop
+            }
+            20 => {
+                // put
+                let index = self.pop()? as usize;
+                let value = self.pop()?;
+                if index < TRANSIENT_ARRAY_SIZE {
+                    self.transient_array[index] = value;
+                }
+            }
+            21 => {
+                // get
+                let index = self.pop()? as usize;
+                let value = self.transient_array.get(index).copied().unwrap_or(0.0);
+                self.push(value)?;
+            }
+            22 => {
+                // ifelse
+                let v2 = self.pop()?;
+                let v1 = self.pop()?;
+                let s2 = self.pop()?;
+                let s1 = self.pop()?;
+                self.push(if v1 <= v2 { s1 } else { s2 })?;
+            }
+            23 => {
+                // random, in (0, 1], reproducible from one glyph to the next
+                self.random_seed = self
+                    .random_seed
+                    .wrapping_mul(1103515245)
+                    .wrapping_add(12345);
+                self.push(((self.random_seed >> 16) & 0x7FFF) as f32 / 32768.0 + 1.0 / 32768.0)?;
+            }
+            24 => self.binary(|a, b| a * b)?,
+            26 => self.unary(|a| a.max(0.0).sqrt())?,
+            27 => {
+                // dup
+                let value = self.pop()?;
+                self.push(value)?;
+                self.push(value)?;
+            }
+            28 => {
+                // exch
+                let b = self.pop()?;
+                let a = self.pop()?;
+                self.push(b)?;
+                self.push(a)?;
+            }
+            29 => {
+                // index, a negative index copies the top element
+                let index = self.pop()?.max(0.0) as usize;
+                let value = *self
+                    .stack
+                    .iter()
+                    .rev()
+                    .nth(index)
+                    .ok_or(CharstringError::StackUnderflow)?;
+                self.push(value)?;
+            }
+            30 => {
+                // roll the top `count` elements by `shift` positions
+                let shift = self.pop()? as i32;
+                let count = self.pop()? as usize;
+                if count > self.stack.len() {
+                    return Err(CharstringError::StackUnderflow);
+                }
+                if count > 0 {
+                    let start = self.stack.len() - count;
+                    let shift = shift.rem_euclid(count as i32) as usize;
+                    self.stack[start..].rotate_right(shift);
+                }
+            }
+            _ => return Err(CharstringError::InvalidOperator(1200 + operator as u16)),
+        }
+        Ok(())
+    }
+
+    // vsindex selects the ItemVariationData, blend adds its deltas to the
+    // preceding n default values
+    fn blend_operator(&mut self, operator: u8) -> Result<(), CharstringError> {
+        if operator == 15 {
+            self.vsindex = self.pop()? as usize;
+            return Ok(());
+        }
+        let region_scalars = self
+            .blend
+            .and_then(|blend| blend.region_scalars.get(self.vsindex))
+            .map_or(&[][..], |scalars| scalars.as_slice());
+        let count = self.pop()? as usize;
+        let region_count = region_scalars.len();
+        let operand_count = count * (region_count + 1);
+        if operand_count > self.stack.len() {
+            return Err(CharstringError::StackUnderflow);
+        }
+        let start = self.stack.len() - operand_count;
+        let deltas = self.stack[start + count..].to_vec();
+        for (index, deltas) in deltas.chunks(region_count.max(1)).take(count).enumerate() {
+            let delta: f32 = deltas
+                .iter()
+                .zip(region_scalars)
+                .map(|(delta, scalar)| delta * scalar)
+                .sum();
+            self.stack[start + index] += delta;
+        }
+        self.stack.truncate(start + count);
+        Ok(())
+    }
+
+    fn unary(&mut self, operation: impl Fn(f32) -> f32) -> Result<(), CharstringError> {
+        let a = self.pop()?;
+        self.push(operation(a))
+    }
+
+    fn binary(&mut self, operation: impl Fn(f32, f32) -> f32) -> Result<(), CharstringError> {
+        let b = self.pop()?;
+        let a = self.pop()?;
+        self.push(operation(a, b))
+    }
+
+    // flex, hflex, hflex1 and flex1 draw two curves, the flex depth is ignored
+    fn flex(&mut self, operator: u8) -> Result<(), CharstringError> {
+        let a = self.arguments(match operator {
+            34 => 7,
+            35 => 13,
+            36 => 9,
+            _ => 11,
+        })?;
+        match operator {
+            34 => {
+                // hflex
+                self.curve_to(a[0], 0.0, a[1], a[2], a[3], 0.0)?;
+                self.curve_to(a[4], 0.0, a[5], -a[2], a[6], 0.0)?;
+            }
+            35 => {
+                self.curve_to(a[0], a[1], a[2], a[3], a[4], a[5])?;
+                self.curve_to(a[6], a[7], a[8], a[9], a[10], a[11])?;
+            }
+            36 => {
+                // hflex1
+                self.curve_to(a[0], a[1], a[2], a[3], a[4], 0.0)?;
+                self.curve_to(a[5], 0.0, a[6], a[7], a[8], -(a[1] + a[3] + a[7]))?;
+            }
+            _ => {
+                // flex1, the last point is on the axis of the larger extent
+                let dx: f32 = a[..10].iter().step_by(2).sum();
+                let dy: f32 = a[1..10].iter().step_by(2).sum();
+                let (dx6, dy6) = if dx.abs() > dy.abs() {
+                    (a[10], -dy)
+                } else {
+                    (-dx, a[10])
+                };
+                self.curve_to(a[0], a[1], a[2], a[3], a[4], a[5])?;
+                self.curve_to(a[6], a[7], a[8], a[9], dx6, dy6)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Bounding box of cubic contours, including the extrema of the curves, rounded
+// to the nearest unit
+pub fn cubic_bounding_box(contours: &[Contour]) -> Option<(i16, i16, i16, i16)> {
+    let mut bounds: Option<(f32, f32, f32, f32)> = None;
+    let mut include = |x: f32, y: f32| {
+        bounds = Some(match bounds {
+            Some((x_min, y_min, x_max, y_max)) => {
+                (x_min.min(x), y_min.min(y), x_max.max(x), y_max.max(y))
+            }
+            None => (x, y, x, y),
+        });
+    };
+
+    for contour in contours {
+        let points = &contour.points;
+        for (i, point) in points.iter().enumerate() {
+            if point.tpe != PointType::OnCurve {
+                continue;
+            }
+            include(point.x as f32, point.y as f32);
+            let next = |offset: usize| points[(i + offset) % points.len()];
+            if points.len() >= 3 && next(1).tpe == PointType::Control {
+                let curve = [*point, next(1), next(2), next(3)];
+                for t in cubic_extrema(curve.map(|p| p.x as f32))
+                    .into_iter()
+                    .chain(cubic_extrema(curve.map(|p| p.y as f32)))
+                {
+                    include(
+                        cubic_at(curve.map(|p| p.x as f32), t),
+                        cubic_at(curve.map(|p| p.y as f32), t),
+                    );
+                }
+            }
+        }
+    }
+
+    bounds.map(|(x_min, y_min, x_max, y_max)| {
+        (
+            x_min.round() as i16,
+            y_min.round() as i16,
+            x_max.round() as i16,
+            y_max.round() as i16,
+        )
+    })
+}
+
+fn cubic_at(p: [f32; 4], t: f32) -> f32 {
+    let s = 1.0 - t;
+    s * s * s * p[0] + 3.0 * s * s * t * p[1] + 3.0 * s * t * t * p[2] + t * t * t * p[3]
+}
+
+// Parameters in (0, 1) where the derivative of the curve is zero
+fn cubic_extrema(p: [f32; 4]) -> Vec<f32> {
+    let a = -p[0] + 3.0 * p[1] - 3.0 * p[2] + p[3];
+    let b = 2.0 * (p[0] - 2.0 * p[1] + p[2]);
+    let c = p[1] - p[0];
+    let roots = if a.abs() < 1e-6 {
+        if b.abs() < 1e-6 {
+            vec![]
+        } else {
+            vec![-c / b]
+        }
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            vec![]
+        } else {
+            let root = discriminant.sqrt();
+            vec![(-b + root) / (2.0 * a), (-b - root) / (2.0 * a)]
+        }
+    };
+    roots.into_iter().filter(|t| *t > 0.0 && *t < 1.0).collect()
+}
@@ -0,0 +1,296 @@
+use crate::font_builder::{binary_search_header, FontTable};
+use crate::table::cmap_table::Segment;
+use crate::writer::Writer;
+use std::collections::BTreeMap;
+
+const SEGMENT_SIZE: usize = 8; // end code, start code, id delta and id range offset
+const GLYPH_INDEX_SIZE: usize = 2;
+const LAST_CHAR_CODE: u32 = 0xFFFF;
+
+// Builds a cmap table from a char code to glyph id map: a format 4 subtable
+// for the BMP, and a format 12 subtable when characters beyond it are mapped
+// or the BMP does not fit in the 64K of a format 4 subtable.
+#[derive(Debug, Default)]
+pub struct CmapBuilder {
+    mapping: BTreeMap<u32, u16>,
+}
+
+// A format 4 segment, id_range_offset is resolved once all segments are known
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum SegmentKind {
+    Delta(u16),   // consecutive glyph ids, given by id_delta
+    GlyphIndices, // glyph ids from glyphIdArray
+}
+
+impl CmapBuilder {
+    pub fn new() -> CmapBuilder {
+        CmapBuilder {
+            mapping: BTreeMap::new(),
+        }
+    }
+
+    // Maps a character to a glyph, glyph 0 leaves it unmapped
+    pub fn insert(&mut self, c: char, glyph_id: u16) -> &mut CmapBuilder {
+        if glyph_id == 0 {
+            self.mapping.remove(&(c as u32));
+        } else {
+            self.mapping.insert(c as u32, glyph_id);
+        }
+        self
+    }
+
+    // The format 4 segments, 0xFFFF closing segment included
+    pub fn segments(&self) -> Vec<Segment> {
+        let (segments, _, _) = self.format_4_segments();
+        segments
+    }
+
+    // Segments, glyphIdArray and whether segments were left out to fit in 64K
+    fn format_4_segments(&self) -> (Vec<Segment>, Vec<u16>, bool) {
+        let bmp: Vec<(u16, u16)> = self
+            .mapping
+            .range(..LAST_CHAR_CODE)
+            .map(|(char_code, glyph_id)| (*char_code as u16, *glyph_id))
+            .collect();
+
+        let mut ranges = split_segments(&bmp);
+        let is_truncated = truncate_segments(&mut ranges);
+        // 0xFFFF + 1 wraps around to .notdef
+        ranges.push((
+            LAST_CHAR_CODE as u16,
+            LAST_CHAR_CODE as u16,
+            SegmentKind::Delta(1),
+        ));
+
+        // id_range_offset counts from its own position in the idRangeOffset array
+        let seg_count = ranges.len();
+        let mut glyph_id_array: Vec<u16> = vec![];
+        let segments = ranges
+            .iter()
+            .enumerate()
+            .map(|(index, (start_code, end_code, kind))| match kind {
+                SegmentKind::Delta(id_delta) => {
+                    Segment::new(index, *start_code, *end_code, *id_delta, 0)
+                }
+                SegmentKind::GlyphIndices => {
+                    let id_range_offset =
+                        (seg_count - index + glyph_id_array.len()) * GLYPH_INDEX_SIZE;
+                    glyph_id_array.extend((*start_code..=*end_code).map(|char_code| {
+                        self.mapping.get(&(char_code as u32)).copied().unwrap_or(0)
+                    }));
+                    Segment::new(index, *start_code, *end_code, 0, id_range_offset as u16)
+                }
+            })
+            .collect();
+
+        (segments, glyph_id_array, is_truncated)
+    }
+
+    fn encode_format_4(&self) -> (Vec<u8>, bool) {
+        let (segments, glyph_id_array, is_truncated) = self.format_4_segments();
+        let seg_count = segments.len() as u16;
+        let (search_range, entry_selector, range_shift) = binary_search_header(seg_count, 2);
+        let length = 16 + segments.len() * SEGMENT_SIZE + glyph_id_array.len() * GLYPH_INDEX_SIZE;
+
+        let mut writer = Writer::new();
+        writer.write_u16(4);
+        writer.write_u16(length as u16);
+        writer.write_u16(0); // language
+        writer.write_u16(seg_count * 2);
+        writer.write_u16(search_range);
+        writer.write_u16(entry_selector);
+        writer.write_u16(range_shift);
+        segments
+            .iter()
+            .for_each(|segment| writer.write_u16(segment.end_code));
+        writer.write_u16(0); // reservedPad
+        segments
+            .iter()
+            .for_each(|segment| writer.write_u16(segment.start_code));
+        segments
+            .iter()
+            .for_each(|segment| writer.write_u16(segment.id_delta()));
+        segments
+            .iter()
+            .for_each(|segment| writer.write_u16(segment.id_range_offset()));
+        glyph_id_array
+            .iter()
+            .for_each(|glyph_id| writer.write_u16(*glyph_id));
+        (writer.into_vec(), is_truncated)
+    }
+
+    // Groups of consecutive char codes mapped to consecutive glyph ids
+    fn encode_format_12(&self) -> Vec<u8> {
+        let mut groups: Vec<(u32, u32, u32)> = vec![]; // start char code, end char code, start glyph id
+        self.mapping.iter().for_each(|(char_code, glyph_id)| {
+            let glyph_id = *glyph_id as u32;
+            match groups.last_mut() {
+                Some((start, end, start_glyph_id))
+                    if *end + 1 == *char_code
+                        && *start_glyph_id + (*char_code - *start) == glyph_id =>
+                {
+                    *end = *char_code
+                }
+                _ => groups.push((*char_code, *char_code, glyph_id)),
+            }
+        });
+
+        let mut writer = Writer::new();
+        writer.write_u16(12);
+        writer.write_u16(0); // reserved
+        writer.write_u32(16 + groups.len() as u32 * 12);
+        writer.write_u32(0); // language
+        writer.write_u32(groups.len() as u32);
+        groups.iter().for_each(|(start, end, start_glyph_id)| {
+            writer.write_u32(*start);
+            writer.write_u32(*end);
+            writer.write_u32(*start_glyph_id);
+        });
+        writer.into_vec()
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let (format_4, is_truncated) = self.encode_format_4();
+        let format_12 = (is_truncated
+            || self
+                .mapping
+                .keys()
+                .any(|char_code| *char_code > LAST_CHAR_CODE))
+        .then(|| self.encode_format_12());
+
+        // (platform id, encoding id, is format 12), sorted
+        let mut records: Vec<(u16, u16, bool)> = vec![(0, 3, false), (3, 1, false)];
+        if format_12.is_some() {
+            records.insert(1, (0, 4, true));
+            records.push((3, 10, true));
+        }
+
+        let format_4_offset = 4 + records.len() as u32 * 8;
+        let format_12_offset = format_4_offset + format_4.len() as u32;
+
+        let mut writer = Writer::new();
+        writer.write_u16(0); // version
+        writer.write_u16(records.len() as u16);
+        records
+            .iter()
+            .for_each(|(platform_id, encoding_id, is_format_12)| {
+                writer.write_u16(*platform_id);
+                writer.write_u16(*encoding_id);
+                writer.write_u32(if *is_format_12 {
+                    format_12_offset
+                } else {
+                    format_4_offset
+                });
+            });
+        writer.write_bytes(&format_4);
+        if let Some(format_12) = format_12 {
+            writer.write_bytes(&format_12);
+        }
+        writer.into_vec()
+    }
+}
+
+impl FontTable for CmapBuilder {
+    fn tag(&self) -> &'static str {
+        "cmap"
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.build()
+    }
+}
+
+// Leaves out the segments past the 64K of a format 4 subtable, true when
+// some were. The last glyphIdArray segment may be cut short to fill the
+// space. The length is 16 bits, which also bounds segCountX2 and the
+// id_range_offset values, as they point within the subtable.
+fn truncate_segments(ranges: &mut Vec<(u16, u16, SegmentKind)>) -> bool {
+    let mut length = 16 + SEGMENT_SIZE; // with the closing segment
+    for index in 0..ranges.len() {
+        let (start_code, end_code, kind) = ranges[index];
+        length += SEGMENT_SIZE;
+        if kind == SegmentKind::GlyphIndices {
+            length += (end_code - start_code) as usize * GLYPH_INDEX_SIZE + GLYPH_INDEX_SIZE;
+        }
+        if length > u16::MAX as usize {
+            let excess = (length - u16::MAX as usize).div_ceil(GLYPH_INDEX_SIZE);
+            match kind {
+                SegmentKind::GlyphIndices if excess <= (end_code - start_code) as usize => {
+                    ranges[index].1 = end_code - excess as u16;
+                    ranges.truncate(index + 1);
+                }
+                _ => ranges.truncate(index),
+            }
+            return true;
+        }
+    }
+    false
+}
+
+// Cheapest split of the mapped char codes into segments. A segment takes 8
+// bytes, plus 2 bytes per char when its glyph ids are not consecutive and go
+// into glyphIdArray. Such a segment may span unmapped chars, which get glyph 0
+// in the array. Among splits of the same size the one with fewer segments wins.
+fn split_segments(mapping: &[(u16, u16)]) -> Vec<(u16, u16, SegmentKind)> {
+    // (size, segment count) of the cheapest split of the first i chars, and where its last segment starts
+    let mut costs: Vec<(usize, usize)> = vec![(0, 0)];
+    let mut splits: Vec<(usize, SegmentKind)> = vec![(0, SegmentKind::GlyphIndices)];
+
+    // Best start for a glyphIdArray segment, compared on size minus the array it saves
+    let mut best_array_start = 0;
+    // Best start for an id_delta segment within the current run of consecutive chars and glyph ids
+    let mut best_delta_start = 0;
+
+    let array_key = |costs: &[(usize, usize)], start: usize| {
+        (
+            costs[start].0 as isize - mapping[start].0 as isize * GLYPH_INDEX_SIZE as isize,
+            costs[start].1,
+        )
+    };
+
+    for end in 0..mapping.len() {
+        if end > 0 {
+            if array_key(&costs, end) < array_key(&costs, best_array_start) {
+                best_array_start = end;
+            }
+            let (previous_char_code, previous_glyph_id) = mapping[end - 1];
+            let (char_code, glyph_id) = mapping[end];
+            if previous_char_code + 1 != char_code
+                || previous_glyph_id.wrapping_add(1) != glyph_id
+                || costs[end] < costs[best_delta_start]
+            {
+                best_delta_start = end;
+            }
+        }
+
+        let (array_size, array_count) = costs[best_array_start];
+        let array_length = (mapping[end].0 - mapping[best_array_start].0) as usize + 1;
+        let array_cost = (
+            array_size + SEGMENT_SIZE + array_length * GLYPH_INDEX_SIZE,
+            array_count + 1,
+        );
+        let (delta_size, delta_count) = costs[best_delta_start];
+        let delta_cost = (delta_size + SEGMENT_SIZE, delta_count + 1);
+
+        if delta_cost <= array_cost {
+            let id_delta = mapping[best_delta_start]
+                .1
+                .wrapping_sub(mapping[best_delta_start].0);
+            costs.push(delta_cost);
+            splits.push((best_delta_start, SegmentKind::Delta(id_delta)));
+        } else {
+            costs.push(array_cost);
+            splits.push((best_array_start, SegmentKind::GlyphIndices));
+        }
+    }
+
+    let mut segments: Vec<(u16, u16, SegmentKind)> = vec![];
+    let mut end = mapping.len();
+    while end > 0 {
+        let (start, kind) = splits[end];
+        segments.push((mapping[start].0, mapping[end - 1].0, kind));
+        end = start;
+    }
+    segments.reverse();
+    segments
+}
@@ -0,0 +1,87 @@
+use crate::rasterizer::Coverage;
+use crate::table::cpal_table::Rgba;
+
+// Colors of the pixels of a color glyph, rows from top to bottom. Colors are
+// not premultiplied, transparent where no layer is drawn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorCoverage {
+    pub width: usize,
+    pub height: usize,
+    pub left: i32, // x of the first column from the origin, in pixels
+    pub top: i32,  // y of the top of the first row above the baseline
+    pub pixels: Vec<Rgba>,
+    pub advance_width: f32, // in pixels
+}
+
+// Draws the coverage of each layer in its color over the layers below it,
+// bottom first, as `GlyphReader::color_layers` returns them
+pub fn composite_layers(layers: &[(Coverage, Rgba)], advance_width: f32) -> ColorCoverage {
+    let drawn = || {
+        layers
+            .iter()
+            .map(|(coverage, _)| coverage)
+            .filter(|coverage| coverage.width > 0 && coverage.height > 0)
+    };
+    let left = drawn().map(|coverage| coverage.left).min().unwrap_or(0);
+    let top = drawn().map(|coverage| coverage.top).max().unwrap_or(0);
+    let right = drawn()
+        .map(|coverage| coverage.left + coverage.width as i32)
+        .max()
+        .unwrap_or(0);
+    let bottom = drawn()
+        .map(|coverage| coverage.top - coverage.height as i32)
+        .min()
+        .unwrap_or(0);
+    let width = (right - left) as usize;
+    let height = (top - bottom) as usize;
+
+    // Premultiplied red, green, blue and alpha in [0, 1]
+    let mut canvas: Vec<[f32; 4]> = vec![[0.0; 4]; width * height];
+    layers.iter().for_each(|(coverage, color)| {
+        let channel = |value: u8| value as f32 / 255.0;
+        (0..coverage.height).for_each(|row| {
+            let canvas_row = (top - coverage.top) as usize + row;
+            (0..coverage.width).for_each(|column| {
+                let canvas_column = (coverage.left - left) as usize + column;
+                let alpha =
+                    channel(coverage.values[row * coverage.width + column]) * channel(color.alpha);
+                let pixel = &mut canvas[canvas_row * width + canvas_column];
+                let source = [color.red, color.green, color.blue].map(channel);
+                (0..3).for_each(|index| {
+                    pixel[index] = source[index] * alpha + pixel[index] * (1.0 - alpha);
+                });
+                pixel[3] = alpha + pixel[3] * (1.0 - alpha);
+            });
+        });
+    });
+
+    let to_u8 = |value: f32| (value * 255.0).round().clamp(0.0, 255.0) as u8;
+    let pixels = canvas
+        .iter()
+        .map(|[red, green, blue, alpha]| {
+            if *alpha == 0.0 {
+                return Rgba {
+                    red: 0,
+                    green: 0,
+                    blue: 0,
+                    alpha: 0,
+                };
+            }
+            Rgba {
+                red: to_u8(red / alpha),
+                green: to_u8(green / alpha),
+                blue: to_u8(blue / alpha),
+                alpha: to_u8(*alpha),
+            }
+        })
+        .collect();
+
+    ColorCoverage {
+        width,
+        height,
+        left,
+        top,
+        pixels,
+        advance_width,
+    }
+}
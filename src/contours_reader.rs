@@ -6,7 +6,7 @@ pub struct ContoursReader<'a> {
 }
 
 impl<'a> ContoursReader<'a> {
-    pub fn new(reader: &mut Box<dyn Reader>) -> ContoursReader {
+    pub fn new(reader: &mut Box<dyn Reader>) -> ContoursReader<'_> {
         ContoursReader { reader }
     }
 
@@ -41,12 +41,11 @@ impl<'a> ContoursReader<'a> {
     }
 
     pub fn read_contours(&mut self, n: i16) -> Contours {
-        let mut end_pts_of_contours: Vec<u16> =
-            (0..n).into_iter().map(|_| self.reader.read_u16()).collect();
+        let mut end_pts_of_contours: Vec<u16> = (0..n).map(|_| self.reader.read_u16()).collect();
 
         let instruction_length: u16 = self.reader.read_u16();
 
-        self.reader.seek_from_current(instruction_length as i32); // Skip instructions
+        let instructions = self.reader.read_bytes(instruction_length as usize);
 
         end_pts_of_contours.insert(0, 0);
 
@@ -59,6 +58,11 @@ impl<'a> ContoursReader<'a> {
         let y_coordinates =
             self.read_coordinates(&flags, |cf| cf.y_short_vector(), |cf| cf.y_is_same());
 
+        let overlap_simple = flags
+            .contour_flags
+            .first()
+            .is_some_and(|flags| flags.overlap_simple());
+
         let mut points: Vec<Point> = x_coordinates
             .into_iter()
             .zip(y_coordinates)
@@ -90,7 +94,11 @@ impl<'a> ContoursReader<'a> {
             })
             .collect();
 
-        Contours { contours }
+        Contours {
+            overlap_simple,
+            contours,
+            instructions,
+        }
     }
 }
 #[derive(Debug, Copy, Clone)]
@@ -130,6 +138,11 @@ impl ControlPointsFlags {
         self.is_set(5)
     }
 
+    // Only meaningful on the first flag of the glyph
+    fn overlap_simple(&self) -> bool {
+        self.is_set(6)
+    }
+
     #[allow(unused)]
     fn pretty_print(&self, id: &str) {
         println!("[{}], on_curve      : {:?}", id, self.on_curve());
@@ -0,0 +1,439 @@
+use crate::inflate::adler32;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+// Compression into DEFLATE streams (RFC 1951) wrapped in zlib (RFC 1950), for
+// WOFF tables. The LZ77 matcher, prefix code lengths and bit writer are shared
+// with the Brotli encoder.
+
+const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH_LENGTH: usize = 3;
+const MAX_MATCH_LENGTH: usize = 258;
+const MAX_CODE_LENGTH: u8 = 15;
+const MAX_CODE_LENGTH_CODE_LENGTH: u8 = 7;
+const MAX_BLOCK_TOKENS: usize = 1 << 16;
+const END_OF_BLOCK: usize = 256;
+
+const HASH_BITS: u32 = 15;
+const MAX_CHAIN_LENGTH: usize = 128;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+// Writes bits least significant first, as DEFLATE and Brotli pack them
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    bit_count: usize,
+}
+
+impl BitWriter {
+    pub fn new() -> BitWriter {
+        BitWriter {
+            bytes: vec![],
+            bit_count: 0,
+        }
+    }
+
+    pub fn write_bits(&mut self, value: u32, count: u8) {
+        (0..count).for_each(|index| {
+            if self.bit_count.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            let bit = ((value >> index) & 1) as u8;
+            *self.bytes.last_mut().unwrap() |= bit << (self.bit_count % 8);
+            self.bit_count += 1;
+        });
+    }
+
+    // A prefix code is packed starting with its most significant bit
+    pub fn write_code(&mut self, code: u32, length: u8) {
+        (0..length)
+            .rev()
+            .for_each(|index| self.write_bits(code >> index, 1));
+    }
+
+    pub fn align_to_byte(&mut self) {
+        self.bit_count = self.bytes.len() * 8;
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Lz77Token {
+    Literal(u8),
+    Match { length: usize, distance: usize },
+}
+
+impl Lz77Token {
+    pub fn length(&self) -> usize {
+        match self {
+            Lz77Token::Literal(_) => 1,
+            Lz77Token::Match { length, .. } => *length,
+        }
+    }
+}
+
+// Positions of earlier occurrences of each 3 byte sequence, most recent first
+struct HashChains {
+    head: Vec<usize>,
+    previous: Vec<usize>,
+}
+
+impl HashChains {
+    fn new(length: usize) -> HashChains {
+        HashChains {
+            head: vec![usize::MAX; 1 << HASH_BITS],
+            previous: vec![usize::MAX; length],
+        }
+    }
+
+    fn hash(data: &[u8], position: usize) -> usize {
+        let value = u32::from_le_bytes([data[position], data[position + 1], data[position + 2], 0]);
+        (value.wrapping_mul(0x9E3779B1) >> (32 - HASH_BITS)) as usize
+    }
+
+    fn insert(&mut self, data: &[u8], position: usize) {
+        if position + 3 <= data.len() {
+            let hash = Self::hash(data, position);
+            self.previous[position] = self.head[hash];
+            self.head[hash] = position;
+        }
+    }
+
+    // Longest match and its distance, the nearest one among equally long matches
+    fn find(
+        &self,
+        data: &[u8],
+        position: usize,
+        window_size: usize,
+        max_length: usize,
+    ) -> (usize, usize) {
+        if position + 3 > data.len() {
+            return (0, 0);
+        }
+        let max_length = max_length.min(data.len() - position);
+        let (mut best_length, mut best_distance) = (0, 0);
+        let mut candidate = self.head[Self::hash(data, position)];
+        let mut chain_length = 0;
+        while candidate != usize::MAX
+            && position - candidate <= window_size
+            && chain_length < MAX_CHAIN_LENGTH
+        {
+            // Only a longer match is of interest, check its last byte first
+            let last = best_length.min(max_length - 1);
+            if data[candidate + last] == data[position + last] {
+                let length = data[candidate..]
+                    .iter()
+                    .zip(&data[position..position + max_length])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if length > best_length {
+                    best_length = length;
+                    best_distance = position - candidate;
+                    if length == max_length {
+                        break;
+                    }
+                }
+            }
+            candidate = self.previous[candidate];
+            chain_length += 1;
+        }
+        (best_length, best_distance)
+    }
+}
+
+// Greedy LZ77 parse, a match is deferred when the next position starts a longer one
+pub fn lz77_tokens(
+    data: &[u8],
+    window_size: usize,
+    min_length: usize,
+    max_length: usize,
+) -> Vec<Lz77Token> {
+    let mut hash_chains = HashChains::new(data.len());
+    let mut tokens: Vec<Lz77Token> = vec![];
+    let mut position = 0;
+    let mut next_match: Option<(usize, usize)> = None;
+    while position < data.len() {
+        let (length, distance) = next_match
+            .take()
+            .unwrap_or_else(|| hash_chains.find(data, position, window_size, max_length));
+        hash_chains.insert(data, position);
+        if length < min_length {
+            tokens.push(Lz77Token::Literal(data[position]));
+            position += 1;
+            continue;
+        }
+
+        let (next_length, next_distance) =
+            hash_chains.find(data, position + 1, window_size, max_length);
+        if next_length > length {
+            tokens.push(Lz77Token::Literal(data[position]));
+            next_match = Some((next_length, next_distance));
+            position += 1;
+            continue;
+        }
+
+        tokens.push(Lz77Token::Match { length, distance });
+        (position + 1..position + length).for_each(|position| hash_chains.insert(data, position));
+        position += length;
+    }
+    tokens
+}
+
+// Code lengths of a prefix code for the symbol frequencies, no longer than
+// `max_length`. Rare symbols are made more frequent until the code fits. A
+// code is only built when at least two symbols occur.
+pub fn prefix_code_lengths(frequencies: &[u32], max_length: u8) -> Vec<u8> {
+    let mut lengths = vec![0u8; frequencies.len()];
+    let used: Vec<usize> = (0..frequencies.len())
+        .filter(|symbol| frequencies[*symbol] > 0)
+        .collect();
+    if used.len() < 2 {
+        used.iter().for_each(|symbol| lengths[*symbol] = 1);
+        return lengths;
+    }
+
+    let mut count_limit = 1;
+    loop {
+        // Nodes are leaves, then internal nodes with their two children
+        let mut children: Vec<(usize, usize)> = vec![];
+        let mut heap: BinaryHeap<Reverse<(u32, usize)>> = used
+            .iter()
+            .enumerate()
+            .map(|(leaf, symbol)| Reverse((frequencies[*symbol].max(count_limit), leaf)))
+            .collect();
+        while heap.len() > 1 {
+            let Reverse((weight_a, a)) = heap.pop().unwrap();
+            let Reverse((weight_b, b)) = heap.pop().unwrap();
+            children.push((a, b));
+            heap.push(Reverse((
+                weight_a + weight_b,
+                used.len() + children.len() - 1,
+            )));
+        }
+
+        let mut depths = vec![0u8; used.len() + children.len()];
+        (0..children.len()).rev().for_each(|index| {
+            let (a, b) = children[index];
+            let depth = depths[used.len() + index] + 1;
+            depths[a] = depth;
+            depths[b] = depth;
+        });
+        if depths[..used.len()]
+            .iter()
+            .all(|depth| *depth <= max_length)
+        {
+            used.iter()
+                .zip(&depths)
+                .for_each(|(symbol, depth)| lengths[*symbol] = *depth);
+            return lengths;
+        }
+        count_limit *= 2;
+    }
+}
+
+// Canonical codes for the code lengths: shorter codes first, then by symbol
+pub fn canonical_codes(lengths: &[u8]) -> Vec<u32> {
+    let max_length = lengths.iter().copied().max().unwrap_or(0) as usize;
+    let mut counts = vec![0u32; max_length + 1];
+    lengths
+        .iter()
+        .filter(|length| **length != 0)
+        .for_each(|length| counts[*length as usize] += 1);
+
+    let mut next_code = vec![0u32; max_length + 2];
+    (1..=max_length).for_each(|length| {
+        next_code[length + 1] = (next_code[length] + counts[length]) << 1;
+    });
+
+    lengths
+        .iter()
+        .map(|length| {
+            if *length == 0 {
+                return 0;
+            }
+            let code = next_code[*length as usize];
+            next_code[*length as usize] += 1;
+            code
+        })
+        .collect()
+}
+
+// Index of the last base not greater than the value
+fn code_index(bases: &[u16], value: usize) -> usize {
+    bases
+        .iter()
+        .rposition(|base| *base as usize <= value)
+        .unwrap()
+}
+
+// Code lengths and codes of a complete prefix code, padding the frequencies
+// when fewer than two symbols occur
+fn complete_prefix_code(mut frequencies: Vec<u32>, max_length: u8) -> (Vec<u8>, Vec<u32>) {
+    let mut padding = 0;
+    while frequencies
+        .iter()
+        .filter(|frequency| **frequency > 0)
+        .count()
+        < 2
+    {
+        if frequencies[padding] == 0 {
+            frequencies[padding] = 1;
+        }
+        padding += 1;
+    }
+    let lengths = prefix_code_lengths(&frequencies, max_length);
+    let codes = canonical_codes(&lengths);
+    (lengths, codes)
+}
+
+// Run length encodes code lengths as (symbol, extra bits, extra bit count)
+fn run_length_encode(lengths: &[u8]) -> Vec<(u8, u32, u8)> {
+    let mut symbols: Vec<(u8, u32, u8)> = vec![];
+    let mut index = 0;
+    while index < lengths.len() {
+        let length = lengths[index];
+        let run = lengths[index..]
+            .iter()
+            .take_while(|next| **next == length)
+            .count();
+        if length == 0 && run >= 11 {
+            let run = run.min(138);
+            symbols.push((18, run as u32 - 11, 7));
+            index += run;
+        } else if length == 0 && run >= 3 {
+            let run = run.min(10);
+            symbols.push((17, run as u32 - 3, 3));
+            index += run;
+        } else if length != 0 && run >= 4 {
+            let run = (run - 1).min(6);
+            symbols.push((length, 0, 0));
+            symbols.push((16, run as u32 - 3, 2));
+            index += run + 1;
+        } else {
+            symbols.push((length, 0, 0));
+            index += 1;
+        }
+    }
+    symbols
+}
+
+fn write_block(tokens: &[Lz77Token], is_final: bool, writer: &mut BitWriter) {
+    let mut literal_frequencies = vec![0u32; 286];
+    let mut distance_frequencies = vec![0u32; 30];
+    literal_frequencies[END_OF_BLOCK] = 1;
+    tokens.iter().for_each(|token| match token {
+        Lz77Token::Literal(literal) => literal_frequencies[*literal as usize] += 1,
+        Lz77Token::Match { length, distance } => {
+            literal_frequencies[257 + code_index(&LENGTH_BASE, *length)] += 1;
+            distance_frequencies[code_index(&DISTANCE_BASE, *distance)] += 1;
+        }
+    });
+    let (literal_lengths, literal_codes) =
+        complete_prefix_code(literal_frequencies, MAX_CODE_LENGTH);
+    let (distance_lengths, distance_codes) =
+        complete_prefix_code(distance_frequencies, MAX_CODE_LENGTH);
+
+    let literal_count = 257.max(literal_lengths.iter().rposition(|l| *l != 0).unwrap() + 1);
+    let distance_count = 1.max(distance_lengths.iter().rposition(|l| *l != 0).unwrap() + 1);
+    let code_length_symbols = run_length_encode(
+        &[
+            &literal_lengths[..literal_count],
+            &distance_lengths[..distance_count],
+        ]
+        .concat(),
+    );
+
+    let mut code_length_frequencies = vec![0u32; 19];
+    code_length_symbols
+        .iter()
+        .for_each(|(symbol, _, _)| code_length_frequencies[*symbol as usize] += 1);
+    let (code_length_lengths, code_length_codes) =
+        complete_prefix_code(code_length_frequencies, MAX_CODE_LENGTH_CODE_LENGTH);
+    let code_length_count = 4.max(
+        CODE_LENGTH_ORDER
+            .iter()
+            .rposition(|symbol| code_length_lengths[*symbol] != 0)
+            .unwrap()
+            + 1,
+    );
+
+    writer.write_bits(is_final as u32, 1);
+    writer.write_bits(2, 2); // dynamic prefix codes
+    writer.write_bits(literal_count as u32 - 257, 5);
+    writer.write_bits(distance_count as u32 - 1, 5);
+    writer.write_bits(code_length_count as u32 - 4, 4);
+    CODE_LENGTH_ORDER[..code_length_count]
+        .iter()
+        .for_each(|symbol| writer.write_bits(code_length_lengths[*symbol] as u32, 3));
+    code_length_symbols
+        .iter()
+        .for_each(|(symbol, extra, extra_bits)| {
+            let symbol = *symbol as usize;
+            writer.write_code(code_length_codes[symbol], code_length_lengths[symbol]);
+            writer.write_bits(*extra, *extra_bits);
+        });
+
+    tokens.iter().for_each(|token| match token {
+        Lz77Token::Literal(literal) => {
+            let symbol = *literal as usize;
+            writer.write_code(literal_codes[symbol], literal_lengths[symbol]);
+        }
+        Lz77Token::Match { length, distance } => {
+            let index = code_index(&LENGTH_BASE, *length);
+            writer.write_code(literal_codes[257 + index], literal_lengths[257 + index]);
+            writer.write_bits(
+                (*length - LENGTH_BASE[index] as usize) as u32,
+                LENGTH_EXTRA_BITS[index],
+            );
+            let index = code_index(&DISTANCE_BASE, *distance);
+            writer.write_code(distance_codes[index], distance_lengths[index]);
+            writer.write_bits(
+                (*distance - DISTANCE_BASE[index] as usize) as u32,
+                DISTANCE_EXTRA_BITS[index],
+            );
+        }
+    });
+    writer.write_code(literal_codes[END_OF_BLOCK], literal_lengths[END_OF_BLOCK]);
+}
+
+pub fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    let tokens = lz77_tokens(data, WINDOW_SIZE, MIN_MATCH_LENGTH, MAX_MATCH_LENGTH);
+
+    let mut writer = BitWriter::new();
+    // Deflate with a 32K window, maximum compression
+    writer.write_bits(0x78, 8);
+    writer.write_bits(0xDA, 8);
+    let blocks: Vec<&[Lz77Token]> = tokens.chunks(MAX_BLOCK_TOKENS).collect();
+    if blocks.is_empty() {
+        write_block(&[], true, &mut writer);
+    }
+    blocks
+        .iter()
+        .enumerate()
+        .for_each(|(index, block)| write_block(block, index + 1 == blocks.len(), &mut writer));
+    writer.align_to_byte();
+
+    let mut output = writer.into_vec();
+    output.extend_from_slice(&adler32(data).to_be_bytes());
+    output
+}
@@ -0,0 +1,189 @@
+use crate::hinting::instruction_length;
+
+// TrueType instructions as text, one line per instruction like "MIRP[01101]"
+// or "PUSHB[3] 0 12 7", indented inside IF and function definitions
+pub fn disassemble(instructions: &[u8]) -> Vec<String> {
+    let mut lines = vec![];
+    let mut depth = 0;
+    let mut ip = 0;
+    while ip < instructions.len() {
+        let opcode = instructions[ip];
+        if matches!(opcode, 0x1B | 0x2D | 0x59) {
+            depth = usize::saturating_sub(depth, 1);
+        }
+        let indent = "  ".repeat(depth);
+        let length = match instruction_length(instructions, ip) {
+            Ok(length) => length,
+            Err(_) => {
+                lines.push(format!("{}{} <truncated>", indent, mnemonic(opcode)));
+                break;
+            }
+        };
+
+        let operands: Vec<String> = match opcode {
+            0x40 => instructions[ip + 2..ip + length]
+                .iter()
+                .map(|value| value.to_string())
+                .collect(),
+            0xB0..=0xB7 => instructions[ip + 1..ip + length]
+                .iter()
+                .map(|value| value.to_string())
+                .collect(),
+            0x41 => words(&instructions[ip + 2..ip + length]),
+            0xB8..=0xBF => words(&instructions[ip + 1..ip + length]),
+            _ => vec![],
+        };
+        let line = [vec![mnemonic(opcode)], operands].concat().join(" ");
+        lines.push(format!("{}{}", indent, line));
+
+        if matches!(opcode, 0x1B | 0x2C | 0x58 | 0x89) {
+            depth += 1;
+        }
+        ip += length;
+    }
+    lines
+}
+
+fn words(data: &[u8]) -> Vec<String> {
+    data.chunks_exact(2)
+        .map(|word| i16::from_be_bytes([word[0], word[1]]).to_string())
+        .collect()
+}
+
+// Name of the instruction with its flag bits, most significant first
+fn mnemonic(opcode: u8) -> String {
+    let (name, flag_bits) = match opcode {
+        0x00..=0x01 => ("SVTCA", 1),
+        0x02..=0x03 => ("SPVTCA", 1),
+        0x04..=0x05 => ("SFVTCA", 1),
+        0x06..=0x07 => ("SPVTL", 1),
+        0x08..=0x09 => ("SFVTL", 1),
+        0x0A => ("SPVFS", 0),
+        0x0B => ("SFVFS", 0),
+        0x0C => ("GPV", 0),
+        0x0D => ("GFV", 0),
+        0x0E => ("SFVTPV", 0),
+        0x0F => ("ISECT", 0),
+        0x10 => ("SRP0", 0),
+        0x11 => ("SRP1", 0),
+        0x12 => ("SRP2", 0),
+        0x13 => ("SZP0", 0),
+        0x14 => ("SZP1", 0),
+        0x15 => ("SZP2", 0),
+        0x16 => ("SZPS", 0),
+        0x17 => ("SLOOP", 0),
+        0x18 => ("RTG", 0),
+        0x19 => ("RTHG", 0),
+        0x1A => ("SMD", 0),
+        0x1B => ("ELSE", 0),
+        0x1C => ("JMPR", 0),
+        0x1D => ("SCVTCI", 0),
+        0x1E => ("SSWCI", 0),
+        0x1F => ("SSW", 0),
+        0x20 => ("DUP", 0),
+        0x21 => ("POP", 0),
+        0x22 => ("CLEAR", 0),
+        0x23 => ("SWAP", 0),
+        0x24 => ("DEPTH", 0),
+        0x25 => ("CINDEX", 0),
+        0x26 => ("MINDEX", 0),
+        0x27 => ("ALIGNPTS", 0),
+        0x29 => ("UTP", 0),
+        0x2A => ("LOOPCALL", 0),
+        0x2B => ("CALL", 0),
+        0x2C => ("FDEF", 0),
+        0x2D => ("ENDF", 0),
+        0x2E..=0x2F => ("MDAP", 1),
+        0x30..=0x31 => ("IUP", 1),
+        0x32..=0x33 => ("SHP", 1),
+        0x34..=0x35 => ("SHC", 1),
+        0x36..=0x37 => ("SHZ", 1),
+        0x38 => ("SHPIX", 0),
+        0x39 => ("IP", 0),
+        0x3A..=0x3B => ("MSIRP", 1),
+        0x3C => ("ALIGNRP", 0),
+        0x3D => ("RTDG", 0),
+        0x3E..=0x3F => ("MIAP", 1),
+        0x40 => ("NPUSHB", 0),
+        0x41 => ("NPUSHW", 0),
+        0x42 => ("WS", 0),
+        0x43 => ("RS", 0),
+        0x44 => ("WCVTP", 0),
+        0x45 => ("RCVT", 0),
+        0x46..=0x47 => ("GC", 1),
+        0x48 => ("SCFS", 0),
+        0x49..=0x4A => ("MD", 1),
+        0x4B => ("MPPEM", 0),
+        0x4C => ("MPS", 0),
+        0x4D => ("FLIPON", 0),
+        0x4E => ("FLIPOFF", 0),
+        0x4F => ("DEBUG", 0),
+        0x50 => ("LT", 0),
+        0x51 => ("LTEQ", 0),
+        0x52 => ("GT", 0),
+        0x53 => ("GTEQ", 0),
+        0x54 => ("EQ", 0),
+        0x55 => ("NEQ", 0),
+        0x56 => ("ODD", 0),
+        0x57 => ("EVEN", 0),
+        0x58 => ("IF", 0),
+        0x59 => ("EIF", 0),
+        0x5A => ("AND", 0),
+        0x5B => ("OR", 0),
+        0x5C => ("NOT", 0),
+        0x5D => ("DELTAP1", 0),
+        0x5E => ("SDB", 0),
+        0x5F => ("SDS", 0),
+        0x60 => ("ADD", 0),
+        0x61 => ("SUB", 0),
+        0x62 => ("DIV", 0),
+        0x63 => ("MUL", 0),
+        0x64 => ("ABS", 0),
+        0x65 => ("NEG", 0),
+        0x66 => ("FLOOR", 0),
+        0x67 => ("CEILING", 0),
+        0x68..=0x6B => ("ROUND", 2),
+        0x6C..=0x6F => ("NROUND", 2),
+        0x70 => ("WCVTF", 0),
+        0x71 => ("DELTAP2", 0),
+        0x72 => ("DELTAP3", 0),
+        0x73 => ("DELTAC1", 0),
+        0x74 => ("DELTAC2", 0),
+        0x75 => ("DELTAC3", 0),
+        0x76 => ("SROUND", 0),
+        0x77 => ("S45ROUND", 0),
+        0x78 => ("JROT", 0),
+        0x79 => ("JROF", 0),
+        0x7A => ("ROFF", 0),
+        0x7C => ("RUTG", 0),
+        0x7D => ("RDTG", 0),
+        0x7E => ("SANGW", 0),
+        0x7F => ("AA", 0),
+        0x80 => ("FLIPPT", 0),
+        0x81 => ("FLIPRGON", 0),
+        0x82 => ("FLIPRGOFF", 0),
+        0x85 => ("SCANCTRL", 0),
+        0x86..=0x87 => ("SDPVTL", 1),
+        0x88 => ("GETINFO", 0),
+        0x89 => ("IDEF", 0),
+        0x8A => ("ROLL", 0),
+        0x8B => ("MAX", 0),
+        0x8C => ("MIN", 0),
+        0x8D => ("SCANTYPE", 0),
+        0x8E => ("INSTCTRL", 0),
+        0x91 => ("GETVARIATION", 0),
+        0x92 => ("GETDATA", 0),
+        0xB0..=0xB7 => return format!("PUSHB[{}]", opcode - 0xAF),
+        0xB8..=0xBF => return format!("PUSHW[{}]", opcode - 0xB7),
+        0xC0..=0xDF => ("MDRP", 5),
+        0xE0..=0xFF => ("MIRP", 5),
+        // Free for instructions defined by IDEF
+        _ => return format!("0x{:02X}", opcode),
+    };
+    if flag_bits == 0 {
+        name.to_string()
+    } else {
+        let flags = opcode & ((1 << flag_bits) - 1);
+        format!("{}[{:0width$b}]", name, flags, width = flag_bits)
+    }
+}
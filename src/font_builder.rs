@@ -0,0 +1,145 @@
+use crate::glyph_reader::GlyphReader;
+use crate::writer::Writer;
+use std::collections::BTreeMap;
+
+pub const TRUE_TYPE_SFNT_VERSION: u32 = 0x00010000;
+
+const CHECKSUM_MAGIC: u32 = 0xB1B0AFBA;
+const CHECKSUM_ADJUSTMENT_OFFSET: usize = 8; // in head
+
+// A table that can be serialized back into a font
+pub trait FontTable {
+    fn tag(&self) -> &'static str;
+    fn to_bytes(&self) -> Vec<u8>;
+}
+
+// Assembles tables into an sfnt: table directory sorted by tag, 4 byte aligned
+// table data, table checksums and head.checkSumAdjustment.
+#[derive(Debug)]
+pub struct FontBuilder {
+    sfnt_version: u32,
+    tables: BTreeMap<String, Vec<u8>>,
+}
+
+impl Default for FontBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FontBuilder {
+    pub fn new() -> FontBuilder {
+        FontBuilder {
+            sfnt_version: TRUE_TYPE_SFNT_VERSION,
+            tables: BTreeMap::new(),
+        }
+    }
+
+    // Starts from all the tables of an existing font, as raw bytes
+    pub fn from_glyph_reader(glyph_reader: &mut GlyphReader) -> FontBuilder {
+        let mut font_builder = FontBuilder::new();
+        glyph_reader.table_tags().iter().for_each(|tag| {
+            if let Some(data) = glyph_reader.table_data(tag) {
+                font_builder.add_table(tag, data);
+            }
+        });
+        font_builder
+    }
+
+    pub fn sfnt_version(&mut self, sfnt_version: u32) -> &mut FontBuilder {
+        self.sfnt_version = sfnt_version;
+        self
+    }
+
+    // Adds the raw bytes of a table, replacing any table with the same tag
+    pub fn add_table(&mut self, tag: &str, data: Vec<u8>) -> &mut FontBuilder {
+        self.tables.insert(format!("{:<4}", tag), data);
+        self
+    }
+
+    pub fn add(&mut self, table: &impl FontTable) -> &mut FontBuilder {
+        self.add_table(table.tag(), table.to_bytes())
+    }
+
+    pub fn remove_table(&mut self, tag: &str) -> &mut FontBuilder {
+        self.tables.remove(&format!("{:<4}", tag));
+        self
+    }
+
+    pub fn has_table(&self, tag: &str) -> bool {
+        self.tables.contains_key(&format!("{:<4}", tag))
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let num_tables = self.tables.len() as u16;
+        let (search_range, entry_selector, range_shift) = binary_search_header(num_tables, 16);
+
+        let mut writer = Writer::new();
+        writer.write_u32(self.sfnt_version);
+        writer.write_u16(num_tables);
+        writer.write_u16(search_range);
+        writer.write_u16(entry_selector);
+        writer.write_u16(range_shift);
+
+        // The head checksum is computed with checkSumAdjustment set to 0
+        let tables: Vec<(&String, Vec<u8>)> = self
+            .tables
+            .iter()
+            .map(|(tag, data)| {
+                let mut data = data.clone();
+                if tag == "head" && data.len() >= CHECKSUM_ADJUSTMENT_OFFSET + 4 {
+                    data[CHECKSUM_ADJUSTMENT_OFFSET..CHECKSUM_ADJUSTMENT_OFFSET + 4]
+                        .copy_from_slice(&[0; 4]);
+                }
+                (tag, data)
+            })
+            .collect();
+
+        let mut offset = 12 + 16 * tables.len() as u32;
+        let mut head_offset: Option<usize> = None;
+        tables.iter().for_each(|(tag, data)| {
+            if tag.as_str() == "head" {
+                head_offset = Some(offset as usize);
+            }
+            writer.write_table_name(tag);
+            writer.write_u32(checksum(data));
+            writer.write_u32(offset);
+            writer.write_u32(data.len() as u32);
+            offset += (data.len() as u32).next_multiple_of(4);
+        });
+
+        tables.iter().for_each(|(_, data)| {
+            writer.write_bytes(data);
+            writer.align(4);
+        });
+
+        let mut font = writer.into_vec();
+        if let Some(head_offset) = head_offset {
+            let adjustment = CHECKSUM_MAGIC.wrapping_sub(checksum(&font));
+            let offset = head_offset + CHECKSUM_ADJUSTMENT_OFFSET;
+            font[offset..offset + 4].copy_from_slice(&adjustment.to_be_bytes());
+        }
+        font
+    }
+}
+
+// searchRange, entrySelector and rangeShift of a binary searchable array of
+// `count` items of `item_size` bytes, as found in the table directory and cmap
+pub fn binary_search_header(count: u16, item_size: u16) -> (u16, u16, u16) {
+    if count == 0 {
+        return (0, 0, 0);
+    }
+    let entry_selector = count.ilog2() as u16;
+    let search_range = (1 << entry_selector) * item_size;
+    let range_shift = count * item_size - search_range;
+    (search_range, entry_selector, range_shift)
+}
+
+// Sum of the big endian u32 words of the table, zero padded
+pub fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
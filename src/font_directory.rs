@@ -7,11 +7,22 @@ pub struct FontDirectory {
 }
 
 impl FontDirectory {
-    pub fn from_file(reader: &mut Box<dyn Reader>) -> FontDirectory {
+    // The directory of the font at `index` in a collection, a single font only has index 0.
+    // Table offsets are from the start of the file, so tables shared by fonts need nothing special.
+    pub fn from_collection(reader: &mut Box<dyn Reader>, index: usize) -> FontDirectory {
+        let offset = match CollectionHeader::from_file(reader) {
+            Some(collection_header) => *collection_header
+                .table_directory_offsets
+                .get(index)
+                .unwrap_or_else(|| panic!("Font {} not found in collection", index)),
+            None if index == 0 => 0,
+            None => panic!("Font {} not found, not a collection", index),
+        };
+        reader.seek_from_start(offset);
+
         let offset_subtable: OffsetSubtable = OffsetSubtable::from_file(reader);
 
         let table_dictionary: Vec<TableDirectory> = (0..offset_subtable.num_tables)
-            .into_iter()
             .map(|_| TableDirectory::from_file(reader))
             .collect();
 
@@ -22,10 +33,63 @@ impl FontDirectory {
     }
 
     pub fn table_directory(&self, name: &str) -> &TableDirectory {
-        let maybe_loca_table: Option<&TableDirectory> =
-            self.table_dictionary.iter().find(|td| td.tag == name);
+        let maybe_loca_table: Option<&TableDirectory> = self.find_table_directory(name);
         maybe_loca_table.unwrap_or_else(|| panic!("'{name}' table not found"))
     }
+
+    pub fn find_table_directory(&self, name: &str) -> Option<&TableDirectory> {
+        self.table_dictionary.iter().find(|td| td.tag == name)
+    }
+
+    pub fn table_tags(&self) -> Vec<String> {
+        self.table_dictionary
+            .iter()
+            .map(|td| td.tag.clone())
+            .collect()
+    }
+}
+
+pub const TTC_TAG: u32 = 0x74746366; // 'ttcf'
+
+// Header of a TrueType or OpenType collection
+#[allow(unused)]
+#[derive(Debug)]
+pub struct CollectionHeader {
+    major_version: u16,
+    minor_version: u16,
+    pub table_directory_offsets: Vec<u32>, // offset of the OffsetSubtable of each font
+    dsig_tag: u32,                         // 'DSIG' when signed, 0 otherwise, version 2 only
+    dsig_length: u32,                      // length of the DSIG table, version 2 only
+    dsig_offset: u32, // offset of the DSIG table from the start of the file, version 2 only
+}
+
+impl CollectionHeader {
+    // None when the file holds a single font
+    pub fn from_file(reader: &mut Box<dyn Reader>) -> Option<CollectionHeader> {
+        reader.seek_from_start(0);
+        if reader.read_u32() != TTC_TAG {
+            return None;
+        }
+
+        let major_version = reader.read_u16();
+        let minor_version = reader.read_u16();
+        let num_fonts = reader.read_u32();
+        let table_directory_offsets: Vec<u32> = (0..num_fonts).map(|_| reader.read_u32()).collect();
+        let (dsig_tag, dsig_length, dsig_offset) = if major_version >= 2 {
+            (reader.read_u32(), reader.read_u32(), reader.read_u32())
+        } else {
+            (0, 0, 0)
+        };
+
+        Some(CollectionHeader {
+            major_version,
+            minor_version,
+            table_directory_offsets,
+            dsig_tag,
+            dsig_length,
+            dsig_offset,
+        })
+    }
 }
 
 #[allow(unused)]
@@ -62,7 +126,7 @@ pub struct TableDirectory {
     tag: String,     // 4-byte identifier
     checksum: u32,   // checksum for this table
     pub offset: u32, // offset from beginning of sfnt
-    length: u32,     // length of this table in byte (actual length not padded length)
+    pub length: u32, // length of this table in byte (actual length not padded length)
 }
 
 impl TableDirectory {
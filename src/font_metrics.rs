@@ -0,0 +1,162 @@
+use crate::table::hhea_table::HheaTable;
+use crate::table::mvar_table::MvarTable;
+use crate::table::os2_table::Os2Table;
+use crate::table::post_table::PostTable;
+
+// Font wide metrics in font units
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontMetrics {
+    pub units_per_em: u16,
+    pub ascender: i16,  // distance from the baseline up to the top of the line
+    pub descender: i16, // distance to the bottom of the line, usually negative
+    pub line_gap: i16,
+    pub caret_slope_rise: i16,
+    pub caret_slope_run: i16,
+    pub caret_offset: i16,
+    pub x_height: Option<i16>,   // only present in OS/2 version 2 and later
+    pub cap_height: Option<i16>, // only present in OS/2 version 2 and later
+    pub underline_position: Option<i16>, // from post, top of the underline
+    pub underline_thickness: Option<i16>, // from post
+    pub strikeout_position: Option<i16>, // from OS/2, top of the strikeout stroke
+    pub strikeout_size: Option<i16>, // from OS/2
+}
+
+// Font wide metrics scaled to a pixel size
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScaledFontMetrics {
+    pub ascender: f32,
+    pub descender: f32,
+    pub line_gap: f32,
+    pub line_height: f32,
+    pub caret_offset: f32,
+    pub x_height: Option<f32>,
+    pub cap_height: Option<f32>,
+    pub underline_position: Option<f32>,
+    pub underline_thickness: Option<f32>,
+    pub strikeout_position: Option<f32>,
+    pub strikeout_size: Option<f32>,
+}
+
+impl FontMetrics {
+    pub fn new(
+        units_per_em: u16,
+        hhea_table: &HheaTable,
+        os2_table: Option<&Os2Table>,
+        post_table: Option<&PostTable>,
+    ) -> Self {
+        let typo_metrics = os2_table.and_then(|os2| os2.typo_metrics.as_ref());
+
+        let use_typo_metrics = os2_table.is_some_and(|os2| os2.use_typo_metrics());
+        let hhea_is_empty = hhea_table.ascent.0 == 0 && hhea_table.descent.0 == 0;
+
+        // hhea is authoritative unless OS/2 asks for its typographic metrics,
+        // or hhea was left empty by the font tooling.
+        let (ascender, descender, line_gap) = match typo_metrics {
+            Some(typo) if use_typo_metrics || hhea_is_empty => (
+                typo.s_typo_ascender.0,
+                typo.s_typo_descender.0,
+                typo.s_typo_line_gap.0,
+            ),
+            _ => (
+                hhea_table.ascent.0,
+                hhea_table.descent.0,
+                hhea_table.line_gap.0,
+            ),
+        };
+
+        let x_height = os2_table
+            .and_then(|os2| os2.x_height)
+            .map(|x_height| x_height.0)
+            .filter(|x_height| *x_height != 0);
+        let cap_height = os2_table
+            .and_then(|os2| os2.cap_height)
+            .map(|cap_height| cap_height.0)
+            .filter(|cap_height| *cap_height != 0);
+
+        FontMetrics {
+            units_per_em,
+            ascender,
+            descender,
+            line_gap,
+            caret_slope_rise: hhea_table.caret_slope_rise,
+            caret_slope_run: hhea_table.caret_slope_run,
+            caret_offset: hhea_table.caret_offset.0,
+            x_height,
+            cap_height,
+            underline_position: post_table.map(|post| post.underline_position.0),
+            underline_thickness: post_table.map(|post| post.underline_thickness.0),
+            strikeout_position: os2_table.map(|os2| os2.y_strikeout_position.0),
+            strikeout_size: os2_table.map(|os2| os2.y_strikeout_size.0),
+        }
+    }
+
+    // The metrics at the normalized variation `coordinates`, moved by the MVAR deltas.
+    // hhea has no tags of its own, the typographic line metrics deltas apply to it as well.
+    pub fn varied(&self, mvar_table: &MvarTable, coordinates: &[f32]) -> FontMetrics {
+        let vary = |value: i16, tag: &str| {
+            (value as f32 + mvar_table.delta(tag, coordinates)).round() as i16
+        };
+        let vary_option = |value: Option<i16>, tag: &str| value.map(|value| vary(value, tag));
+
+        FontMetrics {
+            units_per_em: self.units_per_em,
+            ascender: vary(self.ascender, "hasc"),
+            descender: vary(self.descender, "hdsc"),
+            line_gap: vary(self.line_gap, "hlgp"),
+            caret_slope_rise: vary(self.caret_slope_rise, "hcrs"),
+            caret_slope_run: vary(self.caret_slope_run, "hcrn"),
+            caret_offset: vary(self.caret_offset, "hcof"),
+            x_height: vary_option(self.x_height, "xhgt"),
+            cap_height: vary_option(self.cap_height, "cpht"),
+            underline_position: vary_option(self.underline_position, "undo"),
+            underline_thickness: vary_option(self.underline_thickness, "unds"),
+            strikeout_position: vary_option(self.strikeout_position, "stro"),
+            strikeout_size: vary_option(self.strikeout_size, "strs"),
+        }
+    }
+
+    // Distance between two consecutive baselines in font units
+    pub fn line_height(&self) -> i32 {
+        self.ascender as i32 - self.descender as i32 + self.line_gap as i32
+    }
+
+    // Horizontal caret displacement per unit of height, 0 for an upright font
+    pub fn caret_slant(&self) -> f32 {
+        if self.caret_slope_rise == 0 {
+            0.0
+        } else {
+            self.caret_slope_run as f32 / self.caret_slope_rise as f32
+        }
+    }
+
+    // Factor converting font units to pixels at `pixel_size` pixels per em
+    pub fn scale(&self, pixel_size: f32) -> f32 {
+        pixel_size / self.units_per_em as f32
+    }
+
+    pub fn scaled(&self, pixel_size: f32) -> ScaledFontMetrics {
+        let scale = self.scale(pixel_size);
+
+        ScaledFontMetrics {
+            ascender: self.ascender as f32 * scale,
+            descender: self.descender as f32 * scale,
+            line_gap: self.line_gap as f32 * scale,
+            line_height: self.line_height() as f32 * scale,
+            caret_offset: self.caret_offset as f32 * scale,
+            x_height: self.x_height.map(|x_height| x_height as f32 * scale),
+            cap_height: self.cap_height.map(|cap_height| cap_height as f32 * scale),
+            underline_position: self
+                .underline_position
+                .map(|underline_position| underline_position as f32 * scale),
+            underline_thickness: self
+                .underline_thickness
+                .map(|underline_thickness| underline_thickness as f32 * scale),
+            strikeout_position: self
+                .strikeout_position
+                .map(|strikeout_position| strikeout_position as f32 * scale),
+            strikeout_size: self
+                .strikeout_size
+                .map(|strikeout_size| strikeout_size as f32 * scale),
+        }
+    }
+}
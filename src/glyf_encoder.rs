@@ -0,0 +1,315 @@
+use crate::model::{ArgumentTypes, ComponentData, Glyph, GlyphId, PointType};
+use crate::writer::Writer;
+
+// Inverse of `ContoursReader` and `GlyphComponent`
+
+const ON_CURVE_POINT: u8 = 0x01;
+const X_SHORT_VECTOR: u8 = 0x02;
+const Y_SHORT_VECTOR: u8 = 0x04;
+const REPEAT_FLAG: u8 = 0x08;
+const X_IS_SAME_OR_POSITIVE_X_SHORT_VECTOR: u8 = 0x10;
+const Y_IS_SAME_OR_POSITIVE_Y_SHORT_VECTOR: u8 = 0x20;
+const OVERLAP_SIMPLE: u8 = 0x40;
+
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const ARGS_ARE_XY_VALUES: u16 = 0x0002;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+
+// Component flags derived from the component data, the other ones
+// (ROUND_XY_TO_GRID, USE_MY_METRICS, OVERLAP_COMPOUND, ...) are kept as read
+const ENCODED_COMPONENT_FLAGS: u16 = ARG_1_AND_2_ARE_WORDS
+    | ARGS_ARE_XY_VALUES
+    | WE_HAVE_A_SCALE
+    | MORE_COMPONENTS
+    | WE_HAVE_AN_X_AND_Y_SCALE
+    | WE_HAVE_A_TWO_BY_TWO
+    | WE_HAVE_INSTRUCTIONS;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GlyfEncodeError {
+    CubicOutline(GlyphId), // glyf holds quadratic outlines only
+}
+
+#[derive(Debug)]
+pub struct EncodedGlyf {
+    pub glyf: Vec<u8>,
+    pub loca: Vec<u8>,
+    pub index_to_loc_format: i16, // 0 for short offsets, 1 for long
+}
+
+// Encodes the glyphs, indexed by glyph id, padding each one to `alignment`
+// bytes. loca uses short offsets whenever they can address the glyf table.
+pub fn encode_glyf_and_loca(
+    glyphs: &[Glyph],
+    alignment: usize,
+) -> Result<EncodedGlyf, GlyfEncodeError> {
+    let mut glyf = Writer::new();
+    let mut offsets: Vec<u32> = vec![0];
+
+    for glyph in glyphs {
+        glyf.write_bytes(&encode_glyph(glyph)?);
+        glyf.align(alignment);
+        offsets.push(glyf.offset() as u32);
+    }
+
+    let glyf = glyf.into_vec();
+    let fits_short_offsets = offsets
+        .iter()
+        .all(|offset| offset % 2 == 0 && offset / 2 <= u16::MAX as u32);
+
+    let mut loca = Writer::new();
+    let index_to_loc_format = if fits_short_offsets {
+        offsets
+            .iter()
+            .for_each(|offset| loca.write_u16((offset / 2) as u16));
+        0
+    } else {
+        offsets.iter().for_each(|offset| loca.write_u32(*offset));
+        1
+    };
+
+    Ok(EncodedGlyf {
+        glyf,
+        loca: loca.into_vec(),
+        index_to_loc_format,
+    })
+}
+
+// The glyf data of a single glyph, empty glyphs have none
+pub fn encode_glyph(glyph: &Glyph) -> Result<Vec<u8>, GlyfEncodeError> {
+    let mut writer = Writer::new();
+
+    match glyph {
+        Glyph::Empty { .. } => {}
+        Glyph::Simple {
+            x_min,
+            y_min,
+            x_max,
+            y_max,
+            overlap_simple,
+            contours,
+            instructions,
+            ..
+        } => {
+            writer.write_i16(contours.len() as i16);
+            writer.write_fword(*x_min);
+            writer.write_fword(*y_min);
+            writer.write_fword(*x_max);
+            writer.write_fword(*y_max);
+
+            let mut end_point: usize = 0;
+            contours.iter().for_each(|contour| {
+                end_point += contour.points.len();
+                writer.write_u16((end_point as u16).wrapping_sub(1));
+            });
+            writer.write_u16(instructions.len() as u16);
+            writer.write_bytes(instructions);
+
+            let points: Vec<(i32, i32, bool)> = contours
+                .iter()
+                .flat_map(|contour| contour.points.iter())
+                .map(|point| {
+                    (
+                        point.x as i32,
+                        point.y as i32,
+                        point.tpe == PointType::OnCurve,
+                    )
+                })
+                .collect();
+            encode_points(&points, *overlap_simple, &mut writer);
+        }
+        Glyph::Compound {
+            x_min,
+            y_min,
+            x_max,
+            y_max,
+            components,
+            instructions,
+            ..
+        } => {
+            writer.write_i16(-1);
+            writer.write_fword(*x_min);
+            writer.write_fword(*y_min);
+            writer.write_fword(*x_max);
+            writer.write_fword(*y_max);
+
+            components
+                .iter()
+                .enumerate()
+                .for_each(|(index, component)| {
+                    let has_more = index + 1 < components.len();
+                    let has_instructions = !has_more && !instructions.is_empty();
+                    encode_component(component, has_more, has_instructions, &mut writer);
+                });
+            if !instructions.is_empty() {
+                writer.write_u16(instructions.len() as u16);
+                writer.write_bytes(instructions);
+            }
+        }
+        Glyph::Cubic { glyph_id, .. } => return Err(GlyfEncodeError::CubicOutline(*glyph_id)),
+    }
+
+    Ok(writer.into_vec())
+}
+
+// Flags, x and y coordinates of the (x, y, on curve) points of a simple glyph
+pub fn encode_points(points: &[(i32, i32, bool)], overlap_simple: bool, writer: &mut Writer) {
+    let mut flags: Vec<u8> = vec![];
+    let mut x_coordinates = Writer::new();
+    let mut y_coordinates = Writer::new();
+    let (mut x, mut y) = (0i32, 0i32);
+
+    points.iter().for_each(|(point_x, point_y, on_curve)| {
+        let on_curve = if *on_curve { ON_CURVE_POINT } else { 0 };
+        let x_flags = encode_coordinate(
+            point_x - x,
+            X_SHORT_VECTOR,
+            X_IS_SAME_OR_POSITIVE_X_SHORT_VECTOR,
+            &mut x_coordinates,
+        );
+        let y_flags = encode_coordinate(
+            point_y - y,
+            Y_SHORT_VECTOR,
+            Y_IS_SAME_OR_POSITIVE_Y_SHORT_VECTOR,
+            &mut y_coordinates,
+        );
+        flags.push(on_curve | x_flags | y_flags);
+        x = *point_x;
+        y = *point_y;
+    });
+
+    if overlap_simple {
+        if let Some(flag) = flags.first_mut() {
+            *flag |= OVERLAP_SIMPLE;
+        }
+    }
+
+    encode_flags(&flags, writer);
+    writer.write_bytes(&x_coordinates.into_vec());
+    writer.write_bytes(&y_coordinates.into_vec());
+}
+
+// Writes the coordinate delta and returns its flag bits, a short vector
+// holds the magnitude and the flag its sign
+fn encode_coordinate(
+    delta: i32,
+    short_vector: u8,
+    same_or_positive: u8,
+    writer: &mut Writer,
+) -> u8 {
+    if delta == 0 {
+        same_or_positive
+    } else if delta.abs() <= u8::MAX as i32 {
+        writer.write_u8(delta.unsigned_abs() as u8);
+        if delta > 0 {
+            short_vector | same_or_positive
+        } else {
+            short_vector
+        }
+    } else {
+        writer.write_i16(delta as i16);
+        0
+    }
+}
+
+// A run of three or more equal flags is written once with REPEAT_FLAG and a
+// count, shorter runs take as many bytes written out.
+fn encode_flags(flags: &[u8], writer: &mut Writer) {
+    let mut index = 0;
+    while index < flags.len() {
+        let flag = flags[index];
+        let repeat = flags[index + 1..]
+            .iter()
+            .take(u8::MAX as usize)
+            .take_while(|next| **next == flag)
+            .count();
+        if repeat > 1 {
+            writer.write_u8(flag | REPEAT_FLAG);
+            writer.write_u8(repeat as u8);
+            index += repeat + 1;
+        } else {
+            writer.write_u8(flag);
+            index += 1;
+        }
+    }
+}
+
+// The last component flags the instructions following the components
+fn encode_component(
+    component: &ComponentData,
+    has_more: bool,
+    has_instructions: bool,
+    writer: &mut Writer,
+) {
+    let mut flags = component.flags & !ENCODED_COMPONENT_FLAGS;
+    if has_more {
+        flags |= MORE_COMPONENTS;
+    }
+    if has_instructions {
+        flags |= WE_HAVE_INSTRUCTIONS;
+    }
+
+    let mut arguments = Writer::new();
+    match component.argument_types {
+        ArgumentTypes::XYValue16(x, y) => {
+            flags |= ARGS_ARE_XY_VALUES;
+            match (i8::try_from(x), i8::try_from(y)) {
+                (Ok(x), Ok(y)) => {
+                    arguments.write_i8(x);
+                    arguments.write_i8(y);
+                }
+                _ => {
+                    flags |= ARG_1_AND_2_ARE_WORDS;
+                    arguments.write_i16(x);
+                    arguments.write_i16(y);
+                }
+            }
+        }
+        ArgumentTypes::XYValue8(x, y) => {
+            flags |= ARGS_ARE_XY_VALUES;
+            arguments.write_i8(x);
+            arguments.write_i8(y);
+        }
+        ArgumentTypes::Point16(parent_point, child_point) => {
+            match (u8::try_from(parent_point), u8::try_from(child_point)) {
+                (Ok(parent_point), Ok(child_point)) => {
+                    arguments.write_u8(parent_point);
+                    arguments.write_u8(child_point);
+                }
+                _ => {
+                    flags |= ARG_1_AND_2_ARE_WORDS;
+                    arguments.write_u16(parent_point);
+                    arguments.write_u16(child_point);
+                }
+            }
+        }
+        ArgumentTypes::Point8(parent_point, child_point) => {
+            arguments.write_u8(parent_point);
+            arguments.write_u8(child_point);
+        }
+    }
+
+    // The flags tell whether there is a transform, its values pick the shortest form
+    let (a, b, c, d) = (component.a, component.b, component.c, component.d);
+    let transform: Vec<i16> = if !component.has_transform() {
+        vec![]
+    } else if b == 0 && c == 0 && a == d {
+        flags |= WE_HAVE_A_SCALE;
+        vec![a]
+    } else if b == 0 && c == 0 {
+        flags |= WE_HAVE_AN_X_AND_Y_SCALE;
+        vec![a, d]
+    } else {
+        flags |= WE_HAVE_A_TWO_BY_TWO;
+        vec![a, b, c, d]
+    };
+
+    writer.write_u16(flags);
+    writer.write_u16(component.glyph_index);
+    writer.write_bytes(&arguments.into_vec());
+    transform.iter().for_each(|value| writer.write_i16(*value));
+}
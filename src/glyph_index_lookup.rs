@@ -12,7 +12,7 @@ impl<'a> GlyphIndexLookup<'a> {
         reader: &'a mut Box<dyn Reader>,
         seg_count_x2: u16,
         char_code: u16,
-    ) -> GlyphIndexLookup {
+    ) -> GlyphIndexLookup<'a> {
         GlyphIndexLookup {
             reader,
             seg_count_x2,
@@ -46,7 +46,9 @@ impl<'a> GlyphIndexLookup<'a> {
         match maybe_glyph_id {
             Some(glyph_id) => glyph_id,
             None => {
-                let end_code = self.reader.read_end_code(search_range as i32);
+                // With a power of two segment count, searchRange points past the last end code
+                let probe = search_range.min(self.seg_count_x2 - 2);
+                let end_code = self.reader.read_end_code(probe as i32);
 
                 if self.char_code > end_code {
                     self.sequential_search()
@@ -63,6 +65,9 @@ impl<'a> GlyphIndexLookup<'a> {
         if next_end_code >= self.char_code {
             self.reader.seek_from_current(-2);
             let start_code = self.read_start_code();
+            if self.char_code < start_code {
+                return GlyphId::MISSING_CHARACTER_GLYPH;
+            }
             let id_delta = self.read_id_delta();
             let id_range_offset = self.read_id_range_offset();
 
@@ -1,26 +1,108 @@
+use crate::autohinter::{Autohinter, Outline, REFERENCE_CHARS};
+use crate::charstring::cubic_bounding_box;
+use crate::compositing::{composite_layers, ColorCoverage};
 use crate::contours_reader::ContoursReader;
-use crate::font_directory::FontDirectory;
-use crate::model::{ArgumentTypes, ComponentData, Glyph, GlyphId};
+use crate::font_directory::{FontDirectory, TTC_TAG};
+use crate::font_metrics::FontMetrics;
+use crate::glyph_variation::apply_glyph_variations;
+use crate::hinting::{HintedGlyph, Hinter, HintingError, PHANTOM_POINTS};
+use crate::inflate::gzip_decompress;
+use crate::instancer;
+use crate::instancer::InstanceError;
+use crate::lcd::{rasterize_lcd, LcdCoverage, LcdFilter, SubpixelLayout};
+use crate::model::{ArgumentTypes, ComponentData, Contour, FWord, Glyph, GlyphId, PointType};
+use crate::rasterizer::{rasterize, Coverage};
 use crate::reader::{FileOps, Reader, VecOps};
+use crate::table::avar_table::AvarTable;
+use crate::table::cff2_table::Cff2Table;
+use crate::table::cff_table::CffTable;
 use crate::table::cmap_table::CMapSubtable;
+use crate::table::colr_table::{ClipBox, ColrTable, LayerRecord, PaintError, PaintVisitor};
+use crate::table::cpal_table::{CpalTable, Rgba};
+use crate::table::cvar_table::CvarTable;
+use crate::table::cvt_table::CvtTable;
+use crate::table::ebdt_table::{read_bitmap, Bitmap};
+use crate::table::eblc_table::EblcTable;
+use crate::table::fpgm_table::FpgmTable;
+use crate::table::fvar_table::{FvarTable, NamedInstance, VariationAxis};
+use crate::table::gasp_table::GaspTable;
+use crate::table::gvar_table::GvarTable;
+use crate::table::hdmx_table::HdmxTable;
 use crate::table::head_table::HeadTable;
 use crate::table::hhea_table::HheaTable;
 use crate::table::htmx_table::LongHorMetricLookup;
+use crate::table::hvar_table::HvarTable;
 use crate::table::loca_table::GlyphIdOffsetLookup;
+use crate::table::ltsh_table::LtshTable;
 use crate::table::maxp_table::MaximumProfileTable;
+use crate::table::mvar_table::MvarTable;
 use crate::table::name_table;
+use crate::table::os2_table::Os2Table;
+use crate::table::post_table::PostTable;
+use crate::table::prep_table::PrepTable;
+use crate::table::sbix_table::{SbixImage, SbixTable};
+use crate::table::stat_table::StatTable;
+use crate::table::svg_table::SvgTable;
+use crate::table::vdmx_table::VdmxTable;
+use crate::table::vhea_table::VheaTable;
+use crate::table::vmtx_table::LongVerMetricLookup;
+use crate::table::vorg_table::VorgTable;
+use crate::table::vvar_table::VvarTable;
 use crate::Segment;
 
 use std::fs::File;
 
+const MAX_COMPONENT_DEPTH: usize = 16;
+
+// TrueType outlines from glyf and loca, or PostScript outlines from CFF or CFF2
+enum Outlines {
+    Glyf {
+        glyf_table_offset: u32,
+        glyph_id_offset_lookup: GlyphIdOffsetLookup,
+    },
+    Cff(CffTable),
+    Cff2(Cff2Table),
+}
+
 pub struct GlyphReader {
     reader: Box<dyn Reader>,
-    glyf_table_offset: u32,
-    glyph_id_offset_lookup: GlyphIdOffsetLookup,
+    outlines: Outlines,
     cmap_subtable: CMapSubtable,
     long_hor_metric_lookup: LongHorMetricLookup,
     font_directory: FontDirectory,
     head_table: HeadTable,
+    maximum_profile_table: MaximumProfileTable,
+    hhea_table: HheaTable,
+    os2_table: Option<Os2Table>,
+    post_table: Option<PostTable>,
+    long_ver_metric_lookup: Option<LongVerMetricLookup>,
+    vorg_table: Option<VorgTable>,
+    fvar_table: Option<FvarTable>,
+    avar_table: Option<AvarTable>,
+    stat_table: Option<StatTable>,
+    gvar_table: Option<GvarTable>,
+    hvar_table: Option<HvarTable>,
+    vvar_table: Option<VvarTable>,
+    mvar_table: Option<MvarTable>,
+    colr_table: Option<ColrTable>,
+    cpal_table: Option<CpalTable>,
+    eblc_table: Option<EblcTable>, // CBLC, or EBLC without color bitmaps
+    ebdt_table_offset: u32,        // CBDT or EBDT
+    sbix_table: Option<SbixTable>,
+    svg_table: Option<SvgTable>,
+    cvt_table: Option<CvtTable>,
+    cvar_table: Option<CvarTable>,
+    fpgm_table: Option<FpgmTable>,
+    prep_table: Option<PrepTable>,
+    gasp_table: Option<GaspTable>,
+    hdmx_table: Option<HdmxTable>,
+    ltsh_table: Option<LtshTable>,
+    vdmx_table: Option<VdmxTable>,
+    hinter: Option<Hinter>,           // created on the first hinted glyph
+    pedantic_hinting: bool,           // see `set_pedantic_hinting`
+    autohinter: Option<Autohinter>,   // created on the first autohinted glyph
+    user_coordinates: Vec<f32>,       // design space value for each fvar axis
+    normalized_coordinates: Vec<f32>, // normalized value in [-1, 1] for each fvar axis
 }
 
 impl GlyphReader {
@@ -28,21 +110,36 @@ impl GlyphReader {
         let vec_ops: VecOps = VecOps::from_vec(v);
         let reader: Box<dyn Reader> = Box::new(vec_ops);
 
-        Self::from_reader(reader)
+        Self::from_reader(reader, 0)
     }
 
     pub fn from_file(file: File) -> GlyphReader {
         let file_ops: FileOps = FileOps::from_file(file);
         let reader: Box<dyn Reader> = Box::new(file_ops);
 
-        Self::from_reader(reader)
+        Self::from_reader(reader, 0)
+    }
+
+    // The font at `index` in a TrueType collection, see font_count
+    pub fn from_collection(data: Vec<u8>, index: usize) -> GlyphReader {
+        let vec_ops: VecOps = VecOps::from_vec(data);
+        let reader: Box<dyn Reader> = Box::new(vec_ops);
+
+        Self::from_reader(reader, index)
+    }
+
+    // Number of fonts in a collection, 1 for a single font
+    pub fn font_count(data: &[u8]) -> usize {
+        if data.len() >= 12 && data[0..4] == TTC_TAG.to_be_bytes() {
+            u32::from_be_bytes([data[8], data[9], data[10], data[11]]) as usize
+        } else {
+            1
+        }
     }
 
-    fn from_reader(mut reader: Box<dyn Reader>) -> GlyphReader {
-        let font_directory: FontDirectory = FontDirectory::from_file(&mut reader);
+    fn from_reader(mut reader: Box<dyn Reader>, index: usize) -> GlyphReader {
+        let font_directory: FontDirectory = FontDirectory::from_collection(&mut reader, index);
 
-        let loca_table = font_directory.table_directory("loca");
-        let glyf_table = font_directory.table_directory("glyf");
         let cmap_table = font_directory.table_directory("cmap");
         let head_table = font_directory.table_directory("head");
         let maxp_table = font_directory.table_directory("maxp");
@@ -55,33 +152,228 @@ impl GlyphReader {
 
         let hhea_table = HheaTable::from_file(&mut reader, hhea_table.offset);
 
+        let os2_table = font_directory
+            .find_table_directory("OS/2")
+            .map(|os2_table| Os2Table::from_file(&mut reader, os2_table));
+
+        let post_table = font_directory
+            .find_table_directory("post")
+            .map(|post_table| PostTable::from_file(&mut reader, post_table.offset));
+
         let long_hor_metric_lookup = LongHorMetricLookup::from_file(
             &mut reader,
             htmx_table.offset,
-            hhea_table,
+            &hhea_table,
             &maximum_profile_table,
         );
 
-        let glyph_id_offset_lookup: GlyphIdOffsetLookup =
-            GlyphIdOffsetLookup::mk_glyph_id_to_offset(
-                &mut reader,
-                loca_table.offset,
-                &head_table,
-                &maximum_profile_table,
-            );
+        let long_ver_metric_lookup = match (
+            font_directory.find_table_directory("vhea"),
+            font_directory.find_table_directory("vmtx"),
+        ) {
+            (Some(vhea_table), Some(vmtx_table)) => {
+                let vhea_table = VheaTable::from_file(&mut reader, vhea_table.offset);
+                Some(LongVerMetricLookup::from_file(
+                    &mut reader,
+                    vmtx_table.offset,
+                    &vhea_table,
+                    &maximum_profile_table,
+                ))
+            }
+            _ => None,
+        };
 
-        let cmap_subtable: CMapSubtable = CMapSubtable::find_cmap_subtable(&mut reader, cmap_table);
+        let vorg_table = font_directory
+            .find_table_directory("VORG")
+            .map(|vorg_table| VorgTable::from_file(&mut reader, vorg_table.offset));
+
+        let fvar_table = font_directory
+            .find_table_directory("fvar")
+            .map(|fvar_table| FvarTable::from_file(&mut reader, fvar_table.offset));
 
-        let glyf_table_offset = glyf_table.offset;
+        let avar_table = font_directory
+            .find_table_directory("avar")
+            .map(|avar_table| AvarTable::from_file(&mut reader, avar_table.offset));
+
+        let stat_table = font_directory
+            .find_table_directory("STAT")
+            .map(|stat_table| StatTable::from_file(&mut reader, stat_table.offset));
+
+        let gvar_table = font_directory
+            .find_table_directory("gvar")
+            .map(|gvar_table| GvarTable::from_file(&mut reader, gvar_table.offset));
+
+        let hvar_table = font_directory
+            .find_table_directory("HVAR")
+            .map(|hvar_table| HvarTable::from_file(&mut reader, hvar_table.offset));
+
+        let vvar_table = font_directory
+            .find_table_directory("VVAR")
+            .map(|vvar_table| VvarTable::from_file(&mut reader, vvar_table.offset));
+
+        let mvar_table = font_directory
+            .find_table_directory("MVAR")
+            .map(|mvar_table| MvarTable::from_file(&mut reader, mvar_table.offset));
+
+        let colr_table = font_directory
+            .find_table_directory("COLR")
+            .map(|colr_table| ColrTable::from_file(&mut reader, colr_table));
+
+        let cpal_table = font_directory
+            .find_table_directory("CPAL")
+            .map(|cpal_table| CpalTable::from_file(&mut reader, cpal_table.offset));
+
+        let (eblc_table, ebdt_table_offset) = match (
+            font_directory.find_table_directory("CBLC"),
+            font_directory.find_table_directory("CBDT"),
+        ) {
+            (Some(cblc_table), Some(cbdt_table)) => (Some(cblc_table), cbdt_table.offset),
+            _ => match (
+                font_directory.find_table_directory("EBLC"),
+                font_directory.find_table_directory("EBDT"),
+            ) {
+                (Some(eblc_table), Some(ebdt_table)) => (Some(eblc_table), ebdt_table.offset),
+                _ => (None, 0),
+            },
+        };
+        let eblc_table =
+            eblc_table.map(|eblc_table| EblcTable::from_file(&mut reader, eblc_table.offset));
+
+        let sbix_table = font_directory
+            .find_table_directory("sbix")
+            .map(|sbix_table| {
+                SbixTable::from_file(
+                    &mut reader,
+                    sbix_table.offset,
+                    maximum_profile_table.num_glyphs,
+                )
+            });
+
+        let svg_table = font_directory
+            .find_table_directory("SVG ")
+            .map(|svg_table| SvgTable::from_file(&mut reader, svg_table.offset));
+
+        let cvt_table = font_directory
+            .find_table_directory("cvt ")
+            .map(|cvt_table| CvtTable::from_file(&mut reader, cvt_table));
+
+        let cvar_table =
+            match (&fvar_table, &cvt_table) {
+                (Some(fvar_table), Some(cvt_table)) => font_directory
+                    .find_table_directory("cvar")
+                    .map(|cvar_table| {
+                        CvarTable::from_file(
+                            &mut reader,
+                            cvar_table.offset,
+                            fvar_table.axes.len() as u16,
+                            cvt_table.values.len(),
+                        )
+                    }),
+                _ => None,
+            };
+
+        let fpgm_table = font_directory
+            .find_table_directory("fpgm")
+            .map(|fpgm_table| FpgmTable::from_file(&mut reader, fpgm_table));
+
+        let prep_table = font_directory
+            .find_table_directory("prep")
+            .map(|prep_table| PrepTable::from_file(&mut reader, prep_table));
+
+        let gasp_table = font_directory
+            .find_table_directory("gasp")
+            .map(|gasp_table| GaspTable::from_file(&mut reader, gasp_table.offset));
+
+        let hdmx_table = font_directory
+            .find_table_directory("hdmx")
+            .map(|hdmx_table| {
+                HdmxTable::from_file(
+                    &mut reader,
+                    hdmx_table.offset,
+                    maximum_profile_table.num_glyphs,
+                )
+            });
+
+        let ltsh_table = font_directory
+            .find_table_directory("LTSH")
+            .map(|ltsh_table| LtshTable::from_file(&mut reader, ltsh_table.offset));
+
+        let vdmx_table = font_directory
+            .find_table_directory("VDMX")
+            .map(|vdmx_table| VdmxTable::from_file(&mut reader, vdmx_table.offset));
+
+        let user_coordinates: Vec<f32> = fvar_table
+            .iter()
+            .flat_map(|fvar_table| fvar_table.axes.iter().map(|axis| axis.default_value))
+            .collect();
+        let normalized_coordinates: Vec<f32> = vec![0.0; user_coordinates.len()];
+
+        let glyf_table = font_directory.find_table_directory("glyf");
+        let cff_table = font_directory.find_table_directory("CFF ");
+        let cff2_table = font_directory.find_table_directory("CFF2");
+        let outlines = match (glyf_table, cff_table, cff2_table) {
+            (None, Some(cff_table), _) => {
+                Outlines::Cff(CffTable::from_file(&mut reader, cff_table))
+            }
+            (None, None, Some(cff2_table)) => {
+                Outlines::Cff2(Cff2Table::from_file(&mut reader, cff2_table))
+            }
+            _ => {
+                let loca_table = font_directory.table_directory("loca");
+                let glyf_table = font_directory.table_directory("glyf");
+                Outlines::Glyf {
+                    glyf_table_offset: glyf_table.offset,
+                    glyph_id_offset_lookup: GlyphIdOffsetLookup::mk_glyph_id_to_offset(
+                        &mut reader,
+                        loca_table.offset,
+                        &head_table,
+                        &maximum_profile_table,
+                    ),
+                }
+            }
+        };
+
+        let cmap_subtable: CMapSubtable = CMapSubtable::find_cmap_subtable(&mut reader, cmap_table);
 
         GlyphReader {
             reader,
-            glyf_table_offset,
-            glyph_id_offset_lookup,
+            outlines,
             cmap_subtable,
             long_hor_metric_lookup,
             font_directory,
             head_table,
+            maximum_profile_table,
+            hhea_table,
+            os2_table,
+            post_table,
+            long_ver_metric_lookup,
+            vorg_table,
+            fvar_table,
+            avar_table,
+            stat_table,
+            gvar_table,
+            hvar_table,
+            vvar_table,
+            mvar_table,
+            colr_table,
+            cpal_table,
+            eblc_table,
+            ebdt_table_offset,
+            sbix_table,
+            svg_table,
+            cvt_table,
+            cvar_table,
+            fpgm_table,
+            prep_table,
+            gasp_table,
+            hdmx_table,
+            ltsh_table,
+            vdmx_table,
+            hinter: None,
+            pedantic_hinting: false,
+            autohinter: None,
+            user_coordinates,
+            normalized_coordinates,
         }
     }
 
@@ -94,6 +386,687 @@ impl GlyphReader {
         )
     }
 
+    pub fn units_per_em(&self) -> u16 {
+        self.head_table.units_per_em
+    }
+
+    pub fn head_table(&self) -> &HeadTable {
+        &self.head_table
+    }
+
+    pub fn maximum_profile_table(&self) -> &MaximumProfileTable {
+        &self.maximum_profile_table
+    }
+
+    pub fn hhea_table(&self) -> &HheaTable {
+        &self.hhea_table
+    }
+
+    pub fn os2_table(&self) -> Option<&Os2Table> {
+        self.os2_table.as_ref()
+    }
+
+    // Font wide metrics at the current variation, see `set_variation`
+    pub fn font_metrics(&self) -> FontMetrics {
+        let font_metrics = FontMetrics::new(
+            self.head_table.units_per_em,
+            &self.hhea_table,
+            self.os2_table.as_ref(),
+            self.post_table.as_ref(),
+        );
+
+        match &self.mvar_table {
+            Some(mvar_table) if !self.is_default_location() => {
+                font_metrics.varied(mvar_table, &self.normalized_coordinates)
+            }
+            _ => font_metrics,
+        }
+    }
+
+    // MVAR delta at the current variation for any value tag, including the ones
+    // not covered by `font_metrics` like 'gsp0' or 'sbxo'
+    pub fn metric_variation(&self, value_tag: &str) -> f32 {
+        self.mvar_table.as_ref().map_or(0.0, |mvar_table| {
+            mvar_table.delta(value_tag, &self.normalized_coordinates)
+        })
+    }
+
+    pub fn variation_axes(&self) -> &[VariationAxis] {
+        self.fvar_table
+            .as_ref()
+            .map_or(&[], |fvar_table| fvar_table.axes.as_slice())
+    }
+
+    pub fn named_instances(&self) -> &[NamedInstance] {
+        self.fvar_table
+            .as_ref()
+            .map_or(&[], |fvar_table| fvar_table.instances.as_slice())
+    }
+
+    pub fn stat_table(&self) -> Option<&StatTable> {
+        self.stat_table.as_ref()
+    }
+
+    pub fn cpal_table(&self) -> Option<&CpalTable> {
+        self.cpal_table.as_ref()
+    }
+
+    // The COLR layers of a color glyph, bottom first, with their colors from the
+    // CPAL palette at `palette_index`. Layers in the foreground color are black,
+    // see `color_layers_with_foreground`. Empty for glyphs without color, and
+    // when the font has no such palette.
+    pub fn color_layers(&self, glyph_id: GlyphId, palette_index: usize) -> Vec<(GlyphId, Rgba)> {
+        self.color_layers_with_foreground(glyph_id, palette_index, Rgba::BLACK)
+    }
+
+    pub fn color_layers_with_foreground(
+        &self,
+        glyph_id: GlyphId,
+        palette_index: usize,
+        foreground: Rgba,
+    ) -> Vec<(GlyphId, Rgba)> {
+        let layers = match &self.colr_table {
+            Some(colr_table) => colr_table.layers(glyph_id),
+            None => return vec![],
+        };
+        let Some(palette) = self
+            .cpal_table
+            .as_ref()
+            .and_then(|cpal_table| cpal_table.palettes.get(palette_index))
+        else {
+            return vec![];
+        };
+
+        layers
+            .iter()
+            .map(|layer| {
+                let color = match layer.palette_index {
+                    LayerRecord::FOREGROUND_COLOR => foreground,
+                    palette_index => palette
+                        .colors
+                        .get(palette_index as usize)
+                        .copied()
+                        .unwrap_or(foreground),
+                };
+                (layer.glyph_id, color)
+            })
+            .collect()
+    }
+
+    // Walks the COLR version 1 paint graph of a glyph, false when it has none.
+    // Palette indices of the paints refer to `cpal_table()`.
+    pub fn walk_color_paint(
+        &self,
+        glyph_id: GlyphId,
+        visitor: &mut dyn PaintVisitor,
+    ) -> Result<bool, PaintError> {
+        match &self.colr_table {
+            Some(colr_table) => colr_table.walk_paint(glyph_id, visitor),
+            None => Ok(false),
+        }
+    }
+
+    pub fn color_clip_box(&self, glyph_id: GlyphId) -> Option<ClipBox> {
+        self.colr_table
+            .as_ref()
+            .and_then(|colr_table| colr_table.clip_box(glyph_id))
+    }
+
+    // The embedded bitmap of a glyph from the strike closest to `ppem`, see
+    // `EblcTable::best_strike`. Composite bitmaps refer to other glyphs of the
+    // same strike.
+    pub fn bitmap(&mut self, glyph_id: GlyphId, ppem: u16) -> Option<Bitmap> {
+        let (strike, location) = self.eblc_table.as_ref()?.best_strike(glyph_id, ppem)?;
+        read_bitmap(&mut self.reader, self.ebdt_table_offset, strike, &location)
+    }
+
+    // The sbix image of a glyph from the strike closest to `ppem`, see
+    // `SbixTable::image`
+    pub fn sbix_image(&mut self, glyph_id: GlyphId, ppem: u16) -> Option<SbixImage> {
+        self.sbix_table
+            .as_ref()?
+            .image(&mut self.reader, glyph_id, ppem)
+    }
+
+    // The SVG document holding the glyph, decompressed when gzip encoded. None
+    // as well for a broken gzip stream.
+    pub fn svg_document(&mut self, glyph_id: GlyphId) -> Option<Vec<u8>> {
+        let (offset, length) = self.svg_table.as_ref()?.document_location(glyph_id)?;
+        self.reader.seek_from_start(offset);
+        let document = self.reader.read_bytes(length as usize);
+        if document.starts_with(&[0x1F, 0x8B, 8]) {
+            gzip_decompress(&document).ok()
+        } else {
+            Some(document)
+        }
+    }
+
+    pub fn cvt_table(&self) -> Option<&CvtTable> {
+        self.cvt_table.as_ref()
+    }
+
+    pub(crate) fn cvar_table(&self) -> Option<&CvarTable> {
+        self.cvar_table.as_ref()
+    }
+
+    pub fn fpgm_table(&self) -> Option<&FpgmTable> {
+        self.fpgm_table.as_ref()
+    }
+
+    pub fn prep_table(&self) -> Option<&PrepTable> {
+        self.prep_table.as_ref()
+    }
+
+    pub fn gasp_table(&self) -> Option<&GaspTable> {
+        self.gasp_table.as_ref()
+    }
+
+    pub fn hdmx_table(&self) -> Option<&HdmxTable> {
+        self.hdmx_table.as_ref()
+    }
+
+    pub fn ltsh_table(&self) -> Option<&LtshTable> {
+        self.ltsh_table.as_ref()
+    }
+
+    pub fn vdmx_table(&self) -> Option<&VdmxTable> {
+        self.vdmx_table.as_ref()
+    }
+
+    // Instructions popping an empty stack get zeros and ones referring to
+    // points that do not exist are skipped, as in FreeType. With pedantic
+    // hinting they fail with StackUnderflow or InvalidPoint instead.
+    pub fn set_pedantic_hinting(&mut self, pedantic: bool) {
+        self.pedantic_hinting = pedantic;
+        self.hinter = None; // fpgm and prep run again in the new mode
+    }
+
+    // The glyph grid fitted at `ppem` by its TrueType instructions, after fpgm
+    // and prep. Glyphs without instructions keep their scaled outline with the
+    // advance rounded to whole pixels.
+    pub fn hinted_glyph(
+        &mut self,
+        glyph_id: GlyphId,
+        ppem: u16,
+    ) -> Result<HintedGlyph, HintingError> {
+        if !matches!(self.outlines, Outlines::Glyf { .. }) {
+            return Err(HintingError::UnsupportedOutlines);
+        }
+        let mut hinter = match self.hinter.take() {
+            Some(hinter) => hinter,
+            None => self.mk_hinter(),
+        };
+        hinter.set_coordinates(&self.normalized_coordinates);
+        let outline = hinter
+            .set_ppem(ppem)
+            .and_then(|_| self.hinted_outline(&mut hinter, glyph_id, 0));
+        self.hinter = Some(hinter);
+
+        let outline = outline?;
+        Ok(HintedGlyph::new(
+            &outline.points,
+            &outline.on_curve,
+            &outline.end_points,
+        ))
+    }
+
+    fn mk_hinter(&self) -> Hinter {
+        let cvt = self
+            .cvt_table
+            .as_ref()
+            .map_or(vec![], |cvt_table| cvt_table.values.clone());
+        let font_program = self
+            .fpgm_table
+            .as_ref()
+            .map_or(vec![], |fpgm_table| fpgm_table.instructions.clone());
+        let control_value_program = self
+            .prep_table
+            .as_ref()
+            .map_or(vec![], |prep_table| prep_table.instructions.clone());
+        let mut hinter = Hinter::new(
+            &self.maximum_profile_table,
+            self.units_per_em(),
+            cvt,
+            font_program,
+            control_value_program,
+        );
+        if let Some(cvar_table) = &self.cvar_table {
+            hinter.set_cvt_variations(cvar_table.clone());
+        }
+        hinter.set_pedantic(self.pedantic_hinting);
+        hinter
+    }
+
+    // Components are hinted on their own, then the instructions of the
+    // compound run over the assembled points
+    fn hinted_outline(
+        &mut self,
+        hinter: &mut Hinter,
+        glyph_id: GlyphId,
+        depth: usize,
+    ) -> Result<HintedOutline, HintingError> {
+        if depth > MAX_COMPONENT_DEPTH {
+            return Err(HintingError::ComponentDepth);
+        }
+        let glyph = self.glyph_for_glyph_id(glyph_id);
+
+        let (x_min, y_max) = glyph
+            .bounding_box()
+            .map_or((glyph.left_side_bearing(), 0), |(x_min, _, _, y_max)| {
+                (x_min, y_max)
+            });
+        let left = x_min as i32 - glyph.left_side_bearing() as i32;
+        let top = y_max as i32 + glyph.top_side_bearing() as i32;
+        let phantom_points = [
+            (left, 0),
+            (left + glyph.advance_width() as i32, 0),
+            (0, top),
+            (0, top - glyph.advance_height() as i32),
+        ];
+
+        let mut outline = HintedOutline::default();
+        match glyph {
+            Glyph::Simple {
+                contours,
+                instructions,
+                ..
+            } => {
+                contours.iter().for_each(|contour| {
+                    contour.points.iter().for_each(|point| {
+                        outline.points.push((point.x as i32, point.y as i32));
+                        outline.on_curve.push(point.tpe == PointType::OnCurve);
+                    });
+                    outline.end_points.push(outline.points.len() - 1);
+                });
+                outline.points.extend(phantom_points);
+                outline.on_curve.extend([false; PHANTOM_POINTS]);
+                outline.points = hinter.hint_glyph(
+                    &outline.points,
+                    &outline.on_curve,
+                    &outline.end_points,
+                    &instructions,
+                )?;
+            }
+            Glyph::Compound {
+                components,
+                instructions,
+                ..
+            } => {
+                let mut phantom_points: Vec<(i32, i32)> = phantom_points
+                    .iter()
+                    .map(|(x, y)| (hinter.scale(*x), hinter.scale(*y)))
+                    .collect();
+                for component in &components {
+                    let component_glyph_id = GlyphId::new(component.glyph_index);
+                    let child = self.hinted_outline(hinter, component_glyph_id, depth + 1)?;
+                    let flag = ComponentFlag(component.flags);
+                    let (a, b, c, d) = component.transform();
+                    let points: Vec<(i32, i32)> = child
+                        .points
+                        .iter()
+                        .map(|(x, y)| {
+                            let (x, y) = (*x as f32, *y as f32);
+                            (
+                                (a * x + c * y).round() as i32,
+                                (b * x + d * y).round() as i32,
+                            )
+                        })
+                        .collect();
+                    let count = points.len() - PHANTOM_POINTS;
+
+                    let offset = match component.argument_types {
+                        ArgumentTypes::XYValue16(x, y) => (x as i32, y as i32),
+                        ArgumentTypes::XYValue8(x, y) => (x as i32, y as i32),
+                        ArgumentTypes::Point16(parent, child) => {
+                            matched_offset(&outline.points, &points[..count], parent, child)?
+                        }
+                        ArgumentTypes::Point8(parent, child) => matched_offset(
+                            &outline.points,
+                            &points[..count],
+                            parent as u16,
+                            child as u16,
+                        )?,
+                    };
+                    let offset = if flag.args_are_xy_values() {
+                        let (x, y) = (hinter.scale(offset.0), hinter.scale(offset.1));
+                        if flag.round_xy_to_grid() {
+                            ((x + 32) & !63, (y + 32) & !63)
+                        } else {
+                            (x, y)
+                        }
+                    } else {
+                        offset
+                    };
+
+                    if flag.use_my_metrics() {
+                        phantom_points = points[count..].to_vec();
+                    }
+                    let start = outline.points.len();
+                    outline
+                        .end_points
+                        .extend(child.end_points.iter().map(|end| end + start));
+                    outline.points.extend(
+                        points[..count]
+                            .iter()
+                            .map(|(x, y)| (x + offset.0, y + offset.1)),
+                    );
+                    outline.on_curve.extend(&child.on_curve[..count]);
+                }
+                outline.points.extend(phantom_points);
+                outline.on_curve.extend([false; PHANTOM_POINTS]);
+                outline.points = hinter.hint_composite(
+                    &outline.points,
+                    &outline.on_curve,
+                    &outline.end_points,
+                    &instructions,
+                )?;
+            }
+            _ => {
+                outline.points.extend(phantom_points);
+                outline.on_curve.extend([false; PHANTOM_POINTS]);
+                outline.points = hinter.hint_glyph(&outline.points, &outline.on_curve, &[], &[])?;
+            }
+        }
+        Ok(outline)
+    }
+
+    // The glyph grid fitted at `ppem` without its instructions, for fonts that
+    // have none. Heights of stems and blue zones are snapped to whole pixels
+    // while x and the advance are only scaled.
+    pub fn autohinted_glyph(&mut self, glyph_id: GlyphId, ppem: u16) -> HintedGlyph {
+        let glyph = self.glyph_for_glyph_id(glyph_id);
+        let cubic = matches!(glyph, Glyph::Cubic { .. });
+        let origin = glyph.bounding_box().map_or(0.0, |(x_min, _, _, _)| {
+            x_min as f32 - glyph.left_side_bearing() as f32
+        });
+        let outline: Outline = self
+            .outline(glyph_id, 0)
+            .into_iter()
+            .map(|contour| {
+                contour
+                    .into_iter()
+                    .map(|(x, y, on_curve)| (x - origin, y, on_curve))
+                    .collect()
+            })
+            .collect();
+        let advance_width = glyph.advance_width() as f32;
+        self.autohinter().hint(&outline, advance_width, ppem, cubic)
+    }
+
+    // Grayscale coverage of the glyph at `ppem`
+    pub fn rendered_glyph(&mut self, glyph_id: GlyphId, ppem: u16) -> Coverage {
+        let glyph = self.grid_fitted_glyph(glyph_id, ppem);
+        rasterize(&glyph)
+    }
+
+    // The COLR layers of the glyph at `ppem` composited in their colors, see
+    // `color_layers_with_foreground`. None for glyphs without color.
+    pub fn rendered_color_glyph(
+        &mut self,
+        glyph_id: GlyphId,
+        ppem: u16,
+        palette_index: usize,
+        foreground: Rgba,
+    ) -> Option<ColorCoverage> {
+        let layers = self.color_layers_with_foreground(glyph_id, palette_index, foreground);
+        if layers.is_empty() {
+            return None;
+        }
+        let advance_width = self.grid_fitted_glyph(glyph_id, ppem).advance_width;
+        let layers: Vec<(Coverage, Rgba)> = layers
+            .iter()
+            .map(|(layer, color)| (self.rendered_glyph(*layer, ppem), *color))
+            .collect();
+        Some(composite_layers(&layers, advance_width))
+    }
+
+    // Coverage of each color of the pixels of the glyph at `ppem`, for LCD
+    // screens with the stripes of `layout`
+    pub fn lcd_glyph(
+        &mut self,
+        glyph_id: GlyphId,
+        ppem: u16,
+        layout: SubpixelLayout,
+        filter: LcdFilter,
+    ) -> LcdCoverage {
+        let glyph = self.grid_fitted_glyph(glyph_id, ppem);
+        rasterize_lcd(&glyph, layout, filter)
+    }
+
+    // Hinted by its instructions when the glyph or one of its components has
+    // some, autohinted otherwise, as fonts often come with fpgm and prep but
+    // unhinted glyphs
+    fn grid_fitted_glyph(&mut self, glyph_id: GlyphId, ppem: u16) -> HintedGlyph {
+        if self.has_instructions(glyph_id, 0) {
+            if let Ok(glyph) = self.hinted_glyph(glyph_id, ppem) {
+                return glyph;
+            }
+        }
+        self.autohinted_glyph(glyph_id, ppem)
+    }
+
+    fn has_instructions(&mut self, glyph_id: GlyphId, depth: usize) -> bool {
+        if depth > MAX_COMPONENT_DEPTH {
+            return false;
+        }
+        match self.glyph_for_glyph_id(glyph_id) {
+            Glyph::Simple { instructions, .. } => !instructions.is_empty(),
+            Glyph::Compound {
+                components,
+                instructions,
+                ..
+            } => {
+                !instructions.is_empty()
+                    || components.iter().any(|component| {
+                        self.has_instructions(GlyphId::new(component.glyph_index), depth + 1)
+                    })
+            }
+            _ => false,
+        }
+    }
+
+    // Blue zones and stem width measured on the reference glyphs of the font
+    pub fn autohinter(&mut self) -> &Autohinter {
+        if self.autohinter.is_none() {
+            let samples: Vec<(char, Outline)> = REFERENCE_CHARS
+                .chars()
+                .filter_map(|c| {
+                    let sample_glyph_id = self.char_code_to_glyph_id(c as u16);
+                    if sample_glyph_id.id() == 0 {
+                        return None;
+                    }
+                    Some((c, self.outline(sample_glyph_id, 0)))
+                })
+                .collect();
+            self.autohinter = Some(Autohinter::new(self.units_per_em(), &samples));
+        }
+        self.autohinter.as_ref().unwrap()
+    }
+
+    // Contours in font units with the components of compounds placed
+    fn outline(&mut self, glyph_id: GlyphId, depth: usize) -> Outline {
+        if depth > MAX_COMPONENT_DEPTH {
+            return vec![];
+        }
+        match self.glyph_for_glyph_id(glyph_id) {
+            Glyph::Simple { contours, .. } | Glyph::Cubic { contours, .. } => contours
+                .iter()
+                .map(|contour| {
+                    contour
+                        .points
+                        .iter()
+                        .map(|point| {
+                            let on_curve = point.tpe == PointType::OnCurve;
+                            (point.x as f32, point.y as f32, on_curve)
+                        })
+                        .collect()
+                })
+                .collect(),
+            Glyph::Compound { components, .. } => {
+                let mut outline: Outline = vec![];
+                for component in &components {
+                    let child = self.outline(GlyphId::new(component.glyph_index), depth + 1);
+                    let (a, b, c, d) = component.transform();
+                    let child: Outline = child
+                        .iter()
+                        .map(|contour| {
+                            contour
+                                .iter()
+                                .map(|(x, y, on_curve)| (a * x + c * y, b * x + d * y, *on_curve))
+                                .collect()
+                        })
+                        .collect();
+
+                    let point = |outline: &Outline, index: u16| {
+                        outline
+                            .iter()
+                            .flatten()
+                            .nth(index as usize)
+                            .map_or((0.0, 0.0), |(x, y, _)| (*x, *y))
+                    };
+                    let (parent, child_point) = match component.argument_types {
+                        ArgumentTypes::XYValue16(x, y) => ((x as f32, y as f32), (0.0, 0.0)),
+                        ArgumentTypes::XYValue8(x, y) => ((x as f32, y as f32), (0.0, 0.0)),
+                        ArgumentTypes::Point16(parent, child_index) => {
+                            (point(&outline, parent), point(&child, child_index))
+                        }
+                        ArgumentTypes::Point8(parent, child_index) => (
+                            point(&outline, parent as u16),
+                            point(&child, child_index as u16),
+                        ),
+                    };
+                    let offset = (parent.0 - child_point.0, parent.1 - child_point.1);
+                    outline.extend(child.into_iter().map(|contour| {
+                        contour
+                            .into_iter()
+                            .map(|(x, y, on_curve)| (x + offset.0, y + offset.1, on_curve))
+                            .collect()
+                    }));
+                }
+                outline
+            }
+            Glyph::Empty { .. } => vec![],
+        }
+    }
+
+    pub fn is_variable(&self) -> bool {
+        !self.variation_axes().is_empty()
+    }
+
+    // Selects a location in the design space, given as (axis tag, value) pairs
+    // like [("wght", 650.0), ("wdth", 90.0)]. Axes not listed are set to their
+    // default value, values are clamped to the axis range and unknown tags are ignored.
+    pub fn set_variation(&mut self, settings: &[(&str, f32)]) {
+        let axes = self.variation_axes();
+
+        let user_coordinates: Vec<f32> = axes
+            .iter()
+            .map(|axis| {
+                settings
+                    .iter()
+                    .rev()
+                    .find(|(tag, _)| *tag == axis.tag)
+                    .map_or(axis.default_value, |(_, value)| {
+                        // Not clamp, which panics when a broken fvar has min > max
+                        value.max(axis.min_value).min(axis.max_value)
+                    })
+            })
+            .collect();
+
+        let normalized_coordinates: Vec<f32> = axes
+            .iter()
+            .zip(&user_coordinates)
+            .enumerate()
+            .map(|(axis_index, (axis, value))| {
+                let normalized = axis.normalize(*value);
+                let normalized = match &self.avar_table {
+                    Some(avar_table) => avar_table.map(axis_index, normalized),
+                    None => normalized,
+                };
+                // Coordinates are stored as F2DOT14 in the font, round to that precision
+                (normalized * 16384.0).round() / 16384.0
+            })
+            .collect();
+
+        self.user_coordinates = user_coordinates;
+        self.normalized_coordinates = normalized_coordinates;
+        // Blue zones and stems are measured at the current location
+        self.autohinter = None;
+    }
+
+    pub fn set_named_instance(&mut self, instance_index: usize) {
+        let settings: Vec<(String, f32)> = match self.named_instances().get(instance_index) {
+            Some(instance) => self
+                .variation_axes()
+                .iter()
+                .zip(&instance.coordinates)
+                .map(|(axis, value)| (axis.tag.clone(), *value))
+                .collect(),
+            None => panic!("Named instance {} not found in fvar", instance_index),
+        };
+        let settings: Vec<(&str, f32)> = settings
+            .iter()
+            .map(|(tag, value)| (tag.as_str(), *value))
+            .collect();
+
+        self.set_variation(&settings);
+    }
+
+    pub fn variation_coordinates(&self) -> &[f32] {
+        &self.user_coordinates
+    }
+
+    pub fn normalized_coordinates(&self) -> &[f32] {
+        &self.normalized_coordinates
+    }
+
+    fn is_default_location(&self) -> bool {
+        self.normalized_coordinates.iter().all(|c| *c == 0.0)
+    }
+
+    // Static font at the location given as (axis tag, value) pairs, see `set_variation`.
+    // The current variation of this reader is left unchanged.
+    pub fn instantiate(&mut self, settings: &[(&str, f32)]) -> Result<Vec<u8>, InstanceError> {
+        instancer::instantiate(self, settings)
+    }
+
+    pub fn table_tags(&self) -> Vec<String> {
+        self.font_directory.table_tags()
+    }
+
+    // Raw bytes of the table with the given tag
+    pub fn table_data(&mut self, tag: &str) -> Option<Vec<u8>> {
+        let table_directory = self.font_directory.find_table_directory(tag)?;
+
+        self.reader.seek_from_start(table_directory.offset);
+        Some(self.reader.read_bytes(table_directory.length as usize))
+    }
+
+    pub fn name(&mut self, name_id: u16) -> Option<String> {
+        let name_table = self.font_directory.find_table_directory("name")?;
+
+        name_table::find_name(&mut self.reader, name_table, name_id)
+    }
+
+    // Style name of the current variation, composed from the STAT axis value names
+    pub fn style_name(&mut self) -> Option<String> {
+        let location: Vec<(String, f32)> = self
+            .variation_axes()
+            .iter()
+            .zip(&self.user_coordinates)
+            .map(|(axis, value)| (axis.tag.clone(), *value))
+            .collect();
+
+        let name_ids = self.stat_table.as_ref()?.style_name_ids(&location);
+
+        let names: Vec<String> = name_ids
+            .into_iter()
+            .filter_map(|name_id| self.name(name_id))
+            .collect();
+
+        Some(names.join(" "))
+    }
+
     pub fn display_font_info(&mut self) {
         let name_table = self.font_directory.table_directory("name");
 
@@ -123,13 +1096,215 @@ impl GlyphReader {
             .collect::<Vec<u16>>()
     }
 
-    pub fn glyph_for_glyph_id(&mut self, glyph_id: GlyphId) -> Glyph {
-        let glyph_offset = self
-            .glyph_id_offset_lookup
+    // Advance width at the current variation. HVAR gives it directly, otherwise
+    // it comes from the phantom points of the varied glyph.
+    pub fn advance_width(&mut self, glyph_id: GlyphId) -> u16 {
+        let advance_width = self
+            .long_hor_metric_lookup
             .0
             .get(&glyph_id)
-            .unwrap_or_else(|| panic!("{:?} not found in lookup map", glyph_id));
+            .map(|long_hor_metric| long_hor_metric.advance_width)
+            .unwrap_or_else(|| {
+                panic!("long_hor_metric for {:?} not found in lookup map", glyph_id)
+            });
+
+        if self.is_default_location() {
+            return advance_width;
+        }
+
+        match &self.hvar_table {
+            Some(hvar_table) => {
+                let delta = hvar_table.advance_width_delta(glyph_id, &self.normalized_coordinates);
+                vary_advance(advance_width, delta)
+            }
+            None if self.gvar_table.is_some() => self.glyph_for_glyph_id(glyph_id).advance_width(),
+            None => advance_width,
+        }
+    }
+
+    // Advance height and top side bearing from vmtx. Fonts without vertical
+    // metrics fall back to the OS/2 typographic ascender and descender, as
+    // recommended by the OpenType specification.
+    fn vertical_metrics(&self, glyph_id: GlyphId, y_max: Option<i16>) -> (u16, i16) {
+        let long_ver_metric = self
+            .long_ver_metric_lookup
+            .as_ref()
+            .and_then(|lookup| lookup.0.get(&glyph_id));
+
+        match long_ver_metric {
+            Some(long_ver_metric) => (
+                long_ver_metric.advance_height,
+                long_ver_metric.top_side_bearing,
+            ),
+            None => {
+                let (ascender, descender) = match self
+                    .os2_table
+                    .as_ref()
+                    .and_then(|os2_table| os2_table.typo_metrics.as_ref())
+                {
+                    Some(typo_metrics) => (
+                        typo_metrics.s_typo_ascender.0,
+                        typo_metrics.s_typo_descender.0,
+                    ),
+                    None => (self.hhea_table.ascent.0, self.hhea_table.descent.0),
+                };
+                let advance_height = (ascender as i32 - descender as i32) as u16;
+                let top_side_bearing = y_max.map_or(0, |y_max| ascender - y_max);
+                (advance_height, top_side_bearing)
+            }
+        }
+    }
+
+    // Advance height at the current variation, from VVAR or from the varied glyph
+    pub fn advance_height(&mut self, glyph_id: GlyphId) -> u16 {
+        let advance_height = self.vertical_metrics(glyph_id, None).0;
+
+        if self.is_default_location() {
+            return advance_height;
+        }
+
+        match &self.vvar_table {
+            Some(vvar_table) => {
+                let delta = vvar_table.advance_height_delta(glyph_id, &self.normalized_coordinates);
+                vary_advance(advance_height, delta)
+            }
+            None if self.gvar_table.is_some() => self.glyph_for_glyph_id(glyph_id).advance_height(),
+            None => advance_height,
+        }
+    }
+
+    // The y coordinate of the origin used when laying the glyph out vertically,
+    // from VORG when present and otherwise from the top side bearing.
+    pub fn vertical_origin_y(&mut self, glyph_id: GlyphId) -> i16 {
+        match &self.vorg_table {
+            Some(vorg_table) => {
+                let vert_origin_y = vorg_table.vert_origin_y(glyph_id);
+                let delta = match &self.vvar_table {
+                    Some(vvar_table) if !self.is_default_location() => vvar_table
+                        .vert_origin_y_delta(glyph_id, &self.normalized_coordinates)
+                        .unwrap_or(0.0),
+                    _ => 0.0,
+                };
+                (vert_origin_y as f32 + delta).round() as i16
+            }
+            None => {
+                let glyph = self.glyph_for_glyph_id(glyph_id);
+                match glyph.bounding_box() {
+                    Some((_, _, _, y_max)) => glyph.top_side_bearing() + y_max,
+                    None => self.font_metrics().ascender,
+                }
+            }
+        }
+    }
+
+    // The glyph at the current variation, see `set_variation`
+    pub fn glyph_for_glyph_id(&mut self, glyph_id: GlyphId) -> Glyph {
+        let glyph = self.default_glyph_for_glyph_id(glyph_id);
+
+        if self.is_default_location() {
+            return glyph;
+        }
+
+        let metrics = (
+            glyph.advance_width(),
+            glyph.left_side_bearing(),
+            glyph.advance_height(),
+            glyph.top_side_bearing(),
+        );
+        let glyph = self.apply_outline_variations(glyph_id, glyph);
+        self.apply_metrics_variations(glyph_id, glyph, metrics)
+    }
+
+    fn apply_outline_variations(&mut self, glyph_id: GlyphId, glyph: Glyph) -> Glyph {
+        // CFF2 charstrings blend their own deltas
+        if let Outlines::Cff2(cff2_table) = &self.outlines {
+            let contours = cff2_table
+                .contours(glyph_id, &self.normalized_coordinates)
+                .unwrap_or_default();
+            return self.cubic_glyph(
+                glyph_id,
+                contours,
+                glyph.advance_width(),
+                glyph.left_side_bearing(),
+            );
+        }
+
+        match &self.gvar_table {
+            Some(gvar_table) => {
+                let point_count = match &glyph {
+                    Glyph::Empty { .. } => 0,
+                    Glyph::Simple { contours, .. } | Glyph::Cubic { contours, .. } => {
+                        contours.iter().map(|contour| contour.points.len()).sum()
+                    }
+                    Glyph::Compound { components, .. } => components.len(),
+                };
+                // Four phantom points follow the outline points
+                let variations =
+                    gvar_table.tuple_variations(&mut self.reader, glyph_id, point_count + 4);
+
+                if variations.is_empty() {
+                    glyph
+                } else {
+                    apply_glyph_variations(glyph, &variations, &self.normalized_coordinates)
+                }
+            }
+            None => glyph,
+        }
+    }
+
+    // HVAR and VVAR take precedence over the metrics from the gvar phantom points.
+    // `default_metrics` are the advance width, left side bearing, advance height
+    // and top side bearing before any variation.
+    fn apply_metrics_variations(
+        &self,
+        glyph_id: GlyphId,
+        glyph: Glyph,
+        default_metrics: (u16, i16, u16, i16),
+    ) -> Glyph {
+        let coordinates = &self.normalized_coordinates;
+        let (default_advance_width, default_lsb, default_advance_height, default_tsb) =
+            default_metrics;
 
+        let (advance_width, left_side_bearing) = match &self.hvar_table {
+            Some(hvar_table) => (
+                vary_advance(
+                    default_advance_width,
+                    hvar_table.advance_width_delta(glyph_id, coordinates),
+                ),
+                hvar_table
+                    .left_side_bearing_delta(glyph_id, coordinates)
+                    .map_or(glyph.left_side_bearing(), |delta| {
+                        (default_lsb as f32 + delta).round() as i16
+                    }),
+            ),
+            None => (glyph.advance_width(), glyph.left_side_bearing()),
+        };
+
+        let (advance_height, top_side_bearing) = match &self.vvar_table {
+            Some(vvar_table) => (
+                vary_advance(
+                    default_advance_height,
+                    vvar_table.advance_height_delta(glyph_id, coordinates),
+                ),
+                vvar_table
+                    .top_side_bearing_delta(glyph_id, coordinates)
+                    .map_or(glyph.top_side_bearing(), |delta| {
+                        (default_tsb as f32 + delta).round() as i16
+                    }),
+            ),
+            None => (glyph.advance_height(), glyph.top_side_bearing()),
+        };
+
+        glyph.with_metrics(
+            advance_width,
+            left_side_bearing,
+            advance_height,
+            top_side_bearing,
+        )
+    }
+
+    // The glyph as stored in glyf or CFF, without variations
+    pub fn default_glyph_for_glyph_id(&mut self, glyph_id: GlyphId) -> Glyph {
         let long_hor_metric = self
             .long_hor_metric_lookup
             .0
@@ -141,15 +1316,40 @@ impl GlyphReader {
         let advance_width = long_hor_metric.advance_width;
         let left_side_bearing = long_hor_metric.left_side_bearing;
 
+        let (glyf_table_offset, glyph_id_offset_lookup) = match &self.outlines {
+            Outlines::Glyf {
+                glyf_table_offset,
+                glyph_id_offset_lookup,
+            } => (*glyf_table_offset, glyph_id_offset_lookup),
+            // An invalid charstring gives an empty glyph
+            Outlines::Cff(cff_table) => {
+                let contours = cff_table.contours(glyph_id).unwrap_or_default();
+                return self.cubic_glyph(glyph_id, contours, advance_width, left_side_bearing);
+            }
+            Outlines::Cff2(cff2_table) => {
+                let contours = cff2_table.contours(glyph_id, &[]).unwrap_or_default();
+                return self.cubic_glyph(glyph_id, contours, advance_width, left_side_bearing);
+            }
+        };
+
+        let glyph_offset = glyph_id_offset_lookup
+            .0
+            .get(&glyph_id)
+            .unwrap_or_else(|| panic!("{:?} not found in lookup map", glyph_id));
+
         if glyph_offset.is_empty() {
+            let (advance_height, top_side_bearing) = self.vertical_metrics(glyph_id, None);
+
             Glyph::Empty {
                 glyph_id,
                 advance_width,
                 left_side_bearing,
+                advance_height,
+                top_side_bearing,
             }
         } else {
             self.reader
-                .seek_from_start(self.glyf_table_offset + glyph_offset.offset());
+                .seek_from_start(glyf_table_offset + glyph_offset.offset());
 
             let number_of_contours = self.reader.read_i16();
             let x_min = self.reader.read_fword();
@@ -157,6 +1357,8 @@ impl GlyphReader {
             let x_max = self.reader.read_fword();
             let y_max = self.reader.read_fword();
 
+            let (advance_height, top_side_bearing) = self.vertical_metrics(glyph_id, Some(y_max.0));
+
             // if >= 0 it is a single glyph; if < 0 the glyph is compound
             if number_of_contours >= 0 {
                 let mut contours_reader = ContoursReader::new(&mut self.reader);
@@ -170,12 +1372,25 @@ impl GlyphReader {
                     y_max,
                     advance_width,
                     left_side_bearing,
+                    advance_height,
+                    top_side_bearing,
+                    overlap_simple: simple_glyph.overlap_simple,
                     contours,
+                    instructions: simple_glyph.instructions,
                 }
             } else {
                 let gc = GlyphComponent::new(&mut self.reader);
 
                 let components: Vec<ComponentData> = gc.collect();
+                let has_instructions = components
+                    .iter()
+                    .any(|component| ComponentFlag(component.flags).we_have_instructions());
+                let instructions = if has_instructions {
+                    let instruction_length = self.reader.read_u16();
+                    self.reader.read_bytes(instruction_length as usize)
+                } else {
+                    vec![]
+                };
 
                 Glyph::Compound {
                     glyph_id,
@@ -185,11 +1400,84 @@ impl GlyphReader {
                     y_max,
                     advance_width,
                     left_side_bearing,
+                    advance_height,
+                    top_side_bearing,
                     components,
+                    instructions,
                 }
             }
         }
     }
+
+    // Glyphs with PostScript outlines, empty when there are no contours
+    fn cubic_glyph(
+        &self,
+        glyph_id: GlyphId,
+        contours: Vec<Contour>,
+        advance_width: u16,
+        left_side_bearing: i16,
+    ) -> Glyph {
+        match cubic_bounding_box(&contours) {
+            Some((x_min, y_min, x_max, y_max)) => {
+                let (advance_height, top_side_bearing) =
+                    self.vertical_metrics(glyph_id, Some(y_max));
+                Glyph::Cubic {
+                    glyph_id,
+                    x_min: FWord(x_min),
+                    y_min: FWord(y_min),
+                    x_max: FWord(x_max),
+                    y_max: FWord(y_max),
+                    advance_width,
+                    left_side_bearing,
+                    advance_height,
+                    top_side_bearing,
+                    contours,
+                }
+            }
+            None => {
+                let (advance_height, top_side_bearing) = self.vertical_metrics(glyph_id, None);
+                Glyph::Empty {
+                    glyph_id,
+                    advance_width,
+                    left_side_bearing,
+                    advance_height,
+                    top_side_bearing,
+                }
+            }
+        }
+    }
+}
+
+// Points of a glyph being hinted, in 26.6 pixels and followed by its phantom points
+#[derive(Default)]
+struct HintedOutline {
+    points: Vec<(i32, i32)>,
+    on_curve: Vec<bool>,
+    end_points: Vec<usize>,
+}
+
+// The offset that puts point `child` of a component on point `parent` of the
+// outline assembled so far
+fn matched_offset(
+    parent_points: &[(i32, i32)],
+    child_points: &[(i32, i32)],
+    parent: u16,
+    child: u16,
+) -> Result<(i32, i32), HintingError> {
+    let parent_point = parent_points
+        .get(parent as usize)
+        .ok_or(HintingError::InvalidPoint(parent as i32))?;
+    let child_point = child_points
+        .get(child as usize)
+        .ok_or(HintingError::InvalidPoint(child as i32))?;
+    Ok((
+        parent_point.0 - child_point.0,
+        parent_point.1 - child_point.1,
+    ))
+}
+
+fn vary_advance(advance: u16, delta: f32) -> u16 {
+    (advance as f32 + delta).round().max(0.0) as u16
 }
 
 struct GlyphComponent<'a> {
@@ -252,6 +1540,7 @@ impl<'a> Iterator for GlyphComponent<'a> {
             };
 
             let cd = ComponentData {
+                flags: component_flag.0,
                 glyph_index,
                 a,
                 b,
@@ -0,0 +1,417 @@
+use crate::model::{ArgumentTypes, ComponentData, Contour, FWord, Glyph, Point};
+use crate::tuple_variation::TupleVariation;
+
+// Applies gvar deltas at the normalized `coordinates` to the outline of a
+// simple glyph, or to the component offsets of a compound glyph. The four
+// phantom points following the outline points carry the variations of the
+// horizontal and vertical metrics.
+pub fn apply_glyph_variations(
+    glyph: Glyph,
+    variations: &[TupleVariation],
+    coordinates: &[f32],
+) -> Glyph {
+    match glyph {
+        Glyph::Empty {
+            glyph_id,
+            advance_width,
+            left_side_bearing,
+            advance_height,
+            top_side_bearing,
+        } => {
+            let phantom_points = PhantomPoints::new(
+                0,
+                0,
+                advance_width,
+                left_side_bearing,
+                advance_height,
+                top_side_bearing,
+            );
+            let deltas = glyph_deltas(
+                &phantom_points.points(),
+                &[],
+                variations,
+                coordinates,
+                false,
+            );
+            let metrics = phantom_points.varied_metrics(&deltas, 0, 0);
+
+            Glyph::Empty {
+                glyph_id,
+                advance_width: metrics.advance_width,
+                left_side_bearing: metrics.left_side_bearing,
+                advance_height: metrics.advance_height,
+                top_side_bearing: metrics.top_side_bearing,
+            }
+        }
+        Glyph::Simple {
+            glyph_id,
+            x_min,
+            y_max,
+            advance_width,
+            left_side_bearing,
+            advance_height,
+            top_side_bearing,
+            overlap_simple,
+            contours,
+            instructions,
+            ..
+        } => {
+            let phantom_points = PhantomPoints::new(
+                x_min.0,
+                y_max.0,
+                advance_width,
+                left_side_bearing,
+                advance_height,
+                top_side_bearing,
+            );
+
+            let mut points: Vec<(f32, f32)> = contours
+                .iter()
+                .flat_map(|contour| contour.points.iter().map(|p| (p.x as f32, p.y as f32)))
+                .collect();
+            let contour_ends: Vec<usize> = contours
+                .iter()
+                .scan(0, |end, contour| {
+                    *end += contour.points.len();
+                    Some(*end)
+                })
+                .collect();
+            points.extend(phantom_points.points());
+
+            let deltas = glyph_deltas(&points, &contour_ends, variations, coordinates, true);
+
+            // The outline moves with the left phantom point so that the origin stays in place
+            let shift = deltas[deltas.len() - 4].0.round() as i16;
+
+            let mut varied = deltas.iter().zip(&points);
+            let contours: Vec<Contour> = contours
+                .iter()
+                .map(|contour| Contour {
+                    points: contour
+                        .points
+                        .iter()
+                        .zip(&mut varied)
+                        .map(|(point, ((dx, dy), (x, y)))| {
+                            let x = (x + dx).round() as i16 - shift;
+                            let y = (y + dy).round() as i16;
+                            Point::new(x, y, point.tpe)
+                        })
+                        .collect(),
+                })
+                .collect();
+
+            let all_points = contours.iter().flat_map(|contour| contour.points.iter());
+            let (x_min, y_min, x_max, y_max) = all_points.fold(
+                (i16::MAX, i16::MAX, i16::MIN, i16::MIN),
+                |(x_min, y_min, x_max, y_max), point| {
+                    (
+                        x_min.min(point.x),
+                        y_min.min(point.y),
+                        x_max.max(point.x),
+                        y_max.max(point.y),
+                    )
+                },
+            );
+            let (x_min, y_min, x_max, y_max) = if contours.iter().all(|c| c.points.is_empty()) {
+                (0, 0, 0, 0)
+            } else {
+                (x_min, y_min, x_max, y_max)
+            };
+
+            let metrics = phantom_points.varied_metrics(&deltas, x_min, y_max);
+
+            Glyph::Simple {
+                glyph_id,
+                x_min: FWord(x_min),
+                y_min: FWord(y_min),
+                x_max: FWord(x_max),
+                y_max: FWord(y_max),
+                advance_width: metrics.advance_width,
+                left_side_bearing: metrics.left_side_bearing,
+                advance_height: metrics.advance_height,
+                top_side_bearing: metrics.top_side_bearing,
+                overlap_simple,
+                contours,
+                instructions,
+            }
+        }
+        Glyph::Compound {
+            glyph_id,
+            x_min,
+            y_min,
+            x_max,
+            y_max,
+            advance_width,
+            left_side_bearing,
+            advance_height,
+            top_side_bearing,
+            components,
+            instructions,
+        } => {
+            let phantom_points = PhantomPoints::new(
+                x_min.0,
+                y_max.0,
+                advance_width,
+                left_side_bearing,
+                advance_height,
+                top_side_bearing,
+            );
+
+            // Each component contributes one point, its offset
+            let mut points: Vec<(f32, f32)> = components
+                .iter()
+                .map(|component| match component.argument_types {
+                    ArgumentTypes::XYValue16(x, y) => (x as f32, y as f32),
+                    ArgumentTypes::XYValue8(x, y) => (x as f32, y as f32),
+                    _ => (0.0, 0.0),
+                })
+                .collect();
+            points.extend(phantom_points.points());
+
+            let deltas = glyph_deltas(&points, &[], variations, coordinates, false);
+            let shift = deltas[deltas.len() - 4].0.round() as i16;
+
+            let components: Vec<ComponentData> = components
+                .into_iter()
+                .zip(&deltas)
+                .map(|(component, (dx, dy))| {
+                    let argument_types = match component.argument_types {
+                        ArgumentTypes::XYValue16(x, y) => Some((x, y)),
+                        ArgumentTypes::XYValue8(x, y) => Some((x as i16, y as i16)),
+                        _ => None,
+                    }
+                    .map_or(component.argument_types, |(x, y)| {
+                        ArgumentTypes::XYValue16(
+                            (x as f32 + dx).round() as i16 - shift,
+                            (y as f32 + dy).round() as i16,
+                        )
+                    });
+                    ComponentData {
+                        argument_types,
+                        ..component
+                    }
+                })
+                .collect();
+
+            let metrics = phantom_points.varied_metrics(&deltas, x_min.0, y_max.0);
+
+            Glyph::Compound {
+                glyph_id,
+                x_min,
+                y_min,
+                x_max,
+                y_max,
+                advance_width: metrics.advance_width,
+                left_side_bearing: metrics.left_side_bearing,
+                advance_height: metrics.advance_height,
+                top_side_bearing: metrics.top_side_bearing,
+                components,
+                instructions,
+            }
+        }
+        // CFF outlines are varied by CFF2 blends, not by gvar
+        Glyph::Cubic { .. } => glyph,
+    }
+}
+
+struct PhantomPoints {
+    left: (f32, f32),
+    right: (f32, f32),
+    top: (f32, f32),
+    bottom: (f32, f32),
+}
+
+struct VariedMetrics {
+    advance_width: u16,
+    left_side_bearing: i16,
+    advance_height: u16,
+    top_side_bearing: i16,
+}
+
+impl PhantomPoints {
+    fn new(
+        x_min: i16,
+        y_max: i16,
+        advance_width: u16,
+        left_side_bearing: i16,
+        advance_height: u16,
+        top_side_bearing: i16,
+    ) -> PhantomPoints {
+        let left_x = x_min as f32 - left_side_bearing as f32;
+        let top_y = y_max as f32 + top_side_bearing as f32;
+        PhantomPoints {
+            left: (left_x, 0.0),
+            right: (left_x + advance_width as f32, 0.0),
+            top: (0.0, top_y),
+            bottom: (0.0, top_y - advance_height as f32),
+        }
+    }
+
+    fn points(&self) -> [(f32, f32); 4] {
+        [self.left, self.right, self.top, self.bottom]
+    }
+
+    // Metrics from the phantom points moved by the last four `deltas`, given
+    // the bounds of the varied outline, already shifted back to the origin.
+    fn varied_metrics(&self, deltas: &[(f32, f32)], x_min: i16, y_max: i16) -> VariedMetrics {
+        let phantom_deltas = &deltas[deltas.len() - 4..];
+        let left = self.left.0 + phantom_deltas[0].0;
+        let right = self.right.0 + phantom_deltas[1].0;
+        let top = self.top.1 + phantom_deltas[2].1;
+        let bottom = self.bottom.1 + phantom_deltas[3].1;
+
+        VariedMetrics {
+            advance_width: (right - left).round().max(0.0) as u16,
+            left_side_bearing: (x_min as f32 - self.left.0).round() as i16,
+            advance_height: (top - bottom).round().max(0.0) as u16,
+            top_side_bearing: (top - y_max as f32).round() as i16,
+        }
+    }
+}
+
+// Sum of the scaled deltas of all the tuple variations for each point
+fn glyph_deltas(
+    points: &[(f32, f32)],
+    contour_ends: &[usize],
+    variations: &[TupleVariation],
+    coordinates: &[f32],
+    infer_untouched: bool,
+) -> Vec<(f32, f32)> {
+    let mut deltas: Vec<(f32, f32)> = vec![(0.0, 0.0); points.len()];
+
+    variations.iter().for_each(|variation| {
+        let scalar = variation.scalar(coordinates);
+        if scalar == 0.0 {
+            return;
+        }
+
+        match &variation.point_numbers {
+            None => deltas.iter_mut().enumerate().for_each(|(index, delta)| {
+                delta.0 += scalar * variation.x_deltas[index] as f32;
+                delta.1 += scalar * variation.y_deltas[index] as f32;
+            }),
+            Some(point_numbers) => {
+                let mut tuple_deltas: Vec<Option<(f32, f32)>> = vec![None; points.len()];
+                point_numbers
+                    .iter()
+                    .enumerate()
+                    .for_each(|(index, point_number)| {
+                        if let Some(delta) = tuple_deltas.get_mut(*point_number as usize) {
+                            let (x, y) = delta.unwrap_or((0.0, 0.0));
+                            *delta = Some((
+                                x + variation.x_deltas[index] as f32,
+                                y + variation.y_deltas[index] as f32,
+                            ));
+                        }
+                    });
+
+                if infer_untouched {
+                    let mut start = 0;
+                    contour_ends.iter().for_each(|end| {
+                        interpolate_untouched_points(
+                            &points[start..*end],
+                            &mut tuple_deltas[start..*end],
+                        );
+                        start = *end;
+                    });
+                }
+
+                deltas
+                    .iter_mut()
+                    .zip(tuple_deltas)
+                    .for_each(|(delta, tuple_delta)| {
+                        if let Some((x, y)) = tuple_delta {
+                            delta.0 += scalar * x;
+                            delta.1 += scalar * y;
+                        }
+                    });
+            }
+        }
+    });
+
+    deltas
+}
+
+// Interpolate Untouched Points (IUP), infers the deltas of the points of a
+// contour without explicit deltas from the touched points around them.
+fn interpolate_untouched_points(points: &[(f32, f32)], deltas: &mut [Option<(f32, f32)>]) {
+    let touched: Vec<usize> = (0..points.len())
+        .filter(|index| deltas[*index].is_some())
+        .collect();
+
+    match touched.len() {
+        0 => {}
+        1 => {
+            // A single touched point shifts the whole contour
+            let delta = deltas[touched[0]];
+            deltas.iter_mut().for_each(|d| *d = delta);
+        }
+        _ => {
+            let inferred: Vec<(usize, (f32, f32))> = (0..points.len())
+                .filter(|index| deltas[*index].is_none())
+                .map(|index| {
+                    // Closest touched points before and after, wrapping around the contour
+                    let next = *touched.iter().find(|t| **t > index).unwrap_or(&touched[0]);
+                    let previous = *touched
+                        .iter()
+                        .rev()
+                        .find(|t| **t < index)
+                        .unwrap_or(&touched[touched.len() - 1]);
+
+                    let previous_delta = deltas[previous].unwrap_or((0.0, 0.0));
+                    let next_delta = deltas[next].unwrap_or((0.0, 0.0));
+
+                    let dx = interpolate(
+                        points[index].0,
+                        points[previous].0,
+                        points[next].0,
+                        previous_delta.0,
+                        next_delta.0,
+                    );
+                    let dy = interpolate(
+                        points[index].1,
+                        points[previous].1,
+                        points[next].1,
+                        previous_delta.1,
+                        next_delta.1,
+                    );
+                    (index, (dx, dy))
+                })
+                .collect();
+
+            inferred
+                .into_iter()
+                .for_each(|(index, delta)| deltas[index] = Some(delta));
+        }
+    }
+}
+
+fn interpolate(
+    coordinate: f32,
+    reference_1: f32,
+    reference_2: f32,
+    delta_1: f32,
+    delta_2: f32,
+) -> f32 {
+    if reference_1 == reference_2 {
+        if delta_1 == delta_2 {
+            delta_1
+        } else {
+            0.0
+        }
+    } else {
+        let (low, high, low_delta, high_delta) = if reference_1 < reference_2 {
+            (reference_1, reference_2, delta_1, delta_2)
+        } else {
+            (reference_2, reference_1, delta_2, delta_1)
+        };
+
+        if coordinate <= low {
+            low_delta
+        } else if coordinate >= high {
+            high_delta
+        } else {
+            low_delta + (coordinate - low) * (high_delta - low_delta) / (high - low)
+        }
+    }
+}
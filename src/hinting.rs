@@ -0,0 +1,1745 @@
+use crate::table::cvar_table::CvarTable;
+use crate::table::maxp_table::MaximumProfileTable;
+
+use std::rc::Rc;
+
+// TrueType bytecode interpreter. Coordinates are 26.6 fixed point pixels,
+// vectors 2.14 fixed point unit vectors.
+
+// Limits against hostile fonts, stack and storage sizes come from maxp
+const MAX_CALL_DEPTH: usize = 64;
+const MAX_INSTRUCTIONS: usize = 1_000_000; // per program run, endless loops end here
+const STACK_SLACK: usize = 32; // fonts often understate their stack depth in maxp
+
+const TWILIGHT_ZONE: usize = 0;
+const GLYPH_ZONE: usize = 1;
+pub const PHANTOM_POINTS: usize = 4; // left, right, top and bottom side bearing points
+
+const FONT_PROGRAM: usize = 0; // fpgm
+const CONTROL_VALUE_PROGRAM: usize = 1; // prep
+const GLYPH_PROGRAM: usize = 2;
+
+// INSTCTRL flags
+const INHIBIT_GRID_FITTING: i32 = 0x01;
+const IGNORE_CVT_PARAMETERS: i32 = 0x02;
+
+const X_AXIS: (i32, i32) = (0x4000, 0);
+const Y_AXIS: (i32, i32) = (0, 0x4000);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HintingError {
+    StackOverflow,
+    StackUnderflow,
+    ExecutionLimit, // more than MAX_INSTRUCTIONS instructions in a program run
+    CallStackOverflow,
+    InvalidOpcode(u8),
+    InvalidFunction(i32),
+    InvalidPoint(i32),
+    InvalidContour(i32),
+    InvalidZone(i32),
+    DivideByZero,
+    UnexpectedEnd, // missing EIF or ENDF, or push data past the end of the program
+    ComponentDepth,
+    UnsupportedOutlines, // CFF and CFF2 fonts have no TrueType instructions
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum RoundState {
+    ToHalfGrid,
+    ToGrid,
+    ToDoubleGrid,
+    DownToGrid,
+    UpToGrid,
+    Off,
+    Super {
+        period: i32,
+        phase: i32,
+        threshold: i32,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct GraphicsState {
+    projection_vector: (i32, i32),
+    freedom_vector: (i32, i32),
+    dual_vector: (i32, i32), // projection vector of the original outline
+    reference_points: [usize; 3],
+    zone_pointers: [usize; 3],
+    loop_value: i32,
+    minimum_distance: i32,
+    round_state: RoundState,
+    control_value_cut_in: i32,
+    single_width_cut_in: i32,
+    single_width_value: i32,
+    delta_base: i32,
+    delta_shift: i32,
+    auto_flip: bool,
+    instruct_control: i32,
+}
+
+impl Default for GraphicsState {
+    fn default() -> Self {
+        GraphicsState {
+            projection_vector: X_AXIS,
+            freedom_vector: X_AXIS,
+            dual_vector: X_AXIS,
+            reference_points: [0; 3],
+            zone_pointers: [GLYPH_ZONE; 3],
+            loop_value: 1,
+            minimum_distance: 64,
+            round_state: RoundState::ToGrid,
+            control_value_cut_in: 68, // 17/16 pixel
+            single_width_cut_in: 0,
+            single_width_value: 0,
+            delta_base: 9,
+            delta_shift: 3,
+            auto_flip: true,
+            instruct_control: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Zone {
+    unscaled: Vec<(i32, i32)>, // font units, or the original outline of composites
+    original: Vec<(i32, i32)>,
+    current: Vec<(i32, i32)>,
+    touched: Vec<(bool, bool)>, // x and y
+    on_curve: Vec<bool>,
+    end_points: Vec<usize>, // last point of each contour
+}
+
+impl Zone {
+    fn new(
+        unscaled: Vec<(i32, i32)>,
+        original: Vec<(i32, i32)>,
+        on_curve: Vec<bool>,
+        end_points: Vec<usize>,
+    ) -> Zone {
+        Zone {
+            current: original.clone(),
+            touched: vec![(false, false); original.len()],
+            unscaled,
+            original,
+            on_curve,
+            end_points,
+        }
+    }
+
+    fn twilight(size: usize) -> Zone {
+        Zone::new(
+            vec![(0, 0); size],
+            vec![(0, 0); size],
+            vec![false; size],
+            vec![],
+        )
+    }
+}
+
+// A function or instruction definition, running from after FDEF or IDEF to ENDF
+#[derive(Debug, Copy, Clone)]
+struct Definition {
+    program: usize,
+    start: usize,
+}
+
+#[derive(Debug)]
+struct CallFrame {
+    program: usize,
+    return_ip: usize,
+    definition: Definition,
+    remaining: i32, // LOOPCALL iterations left
+}
+
+pub struct Hinter {
+    units_per_em: i32,
+    ppem: u16,
+    scale: i32, // 16.16 factor from font units to 26.6 pixels
+    programs: [Rc<Vec<u8>>; 3],
+    cvt_default: Vec<i16>,
+    cvt_variations: Option<CvarTable>,
+    cvt_unscaled: Vec<i16>, // at the current coordinates
+    cvt: Vec<i32>,
+    storage: Vec<i32>,
+    functions: Vec<Option<Definition>>,
+    instruction_defs: Vec<(u8, Definition)>,
+    max_instruction_defs: usize,
+    stack: Vec<i32>,
+    stack_limit: usize,
+    twilight_points: usize,
+    zones: [Zone; 2],
+    graphics_state: GraphicsState,
+    default_graphics_state: GraphicsState, // as left by prep
+    font_program_result: Option<Result<(), HintingError>>,
+    control_value_program_result: Result<(), HintingError>,
+    coordinates: Vec<f32>, // normalized variation coordinates for GETVARIATION
+    instruction_count: usize,
+    pedantic: bool, // see `set_pedantic`
+}
+
+impl Hinter {
+    pub fn new(
+        maximum_profile_table: &MaximumProfileTable,
+        units_per_em: u16,
+        cvt: Vec<i16>,
+        font_program: Vec<u8>,
+        control_value_program: Vec<u8>,
+    ) -> Hinter {
+        let twilight_points = maximum_profile_table.max_twilight_points as usize;
+        Hinter {
+            units_per_em: units_per_em.max(1) as i32,
+            ppem: 0,
+            scale: 0,
+            programs: [
+                Rc::new(font_program),
+                Rc::new(control_value_program),
+                Rc::new(vec![]),
+            ],
+            cvt: vec![0; cvt.len()],
+            cvt_default: cvt.clone(),
+            cvt_variations: None,
+            cvt_unscaled: cvt,
+            storage: vec![0; maximum_profile_table.max_storage as usize],
+            functions: vec![None; maximum_profile_table.max_function_defs as usize],
+            instruction_defs: vec![],
+            max_instruction_defs: maximum_profile_table.max_instruction_defs as usize,
+            stack: vec![],
+            stack_limit: maximum_profile_table.max_stack_elements as usize + STACK_SLACK,
+            twilight_points,
+            zones: [Zone::twilight(twilight_points), Zone::default()],
+            graphics_state: GraphicsState::default(),
+            default_graphics_state: GraphicsState::default(),
+            font_program_result: None,
+            control_value_program_result: Ok(()),
+            coordinates: vec![],
+            instruction_count: 0,
+            pedantic: false,
+        }
+    }
+
+    // See `GlyphReader::set_pedantic_hinting`, set before the programs run
+    pub fn set_pedantic(&mut self, pedantic: bool) {
+        self.pedantic = pedantic;
+    }
+
+    pub fn set_cvt_variations(&mut self, cvar_table: CvarTable) {
+        self.cvt_variations = Some(cvar_table);
+        self.cvt_unscaled = self.varied_cvt();
+        self.ppem = 0;
+    }
+
+    // New coordinates vary the control values and run prep again at the next size
+    pub fn set_coordinates(&mut self, coordinates: &[f32]) {
+        if self.coordinates == coordinates {
+            return;
+        }
+        self.coordinates = coordinates.to_vec();
+        self.cvt_unscaled = self.varied_cvt();
+        self.ppem = 0;
+    }
+
+    fn varied_cvt(&self) -> Vec<i16> {
+        match &self.cvt_variations {
+            Some(cvar_table) => cvar_table.varied_values(&self.cvt_default, &self.coordinates),
+            None => self.cvt_default.clone(),
+        }
+    }
+
+    // Runs fpgm the first time, then prep whenever the size changes
+    pub fn set_ppem(&mut self, ppem: u16) -> Result<(), HintingError> {
+        if self.font_program_result.is_none() {
+            self.graphics_state = GraphicsState::default();
+            self.font_program_result = Some(self.execute(FONT_PROGRAM));
+        }
+        self.font_program_result.unwrap()?;
+
+        if ppem != self.ppem {
+            self.ppem = ppem;
+            self.scale = (((ppem as i64 * 64) << 16) / self.units_per_em as i64) as i32;
+            self.cvt = self
+                .cvt_unscaled
+                .iter()
+                .map(|value| self.scale(*value as i32))
+                .collect();
+            self.zones[TWILIGHT_ZONE] = Zone::twilight(self.twilight_points);
+            self.graphics_state = GraphicsState::default();
+            self.control_value_program_result = self.execute(CONTROL_VALUE_PROGRAM);
+            self.default_graphics_state = self.graphics_state.clone();
+        }
+        self.control_value_program_result
+    }
+
+    // Font units to 26.6 pixels at the current size
+    pub fn scale(&self, value: i32) -> i32 {
+        mul_div(value, self.scale, 0x10000)
+    }
+
+    // Grid fits the points of a simple glyph, given in font units and followed
+    // by its phantom points
+    pub fn hint_glyph(
+        &mut self,
+        points: &[(i32, i32)],
+        on_curve: &[bool],
+        end_points: &[usize],
+        instructions: &[u8],
+    ) -> Result<Vec<(i32, i32)>, HintingError> {
+        let unscaled = points.to_vec();
+        let original = points
+            .iter()
+            .map(|(x, y)| (self.scale(*x), self.scale(*y)))
+            .collect();
+        let zone = Zone::new(unscaled, original, on_curve.to_vec(), end_points.to_vec());
+        self.run_glyph_program(zone, instructions)
+    }
+
+    // Runs the instructions of a composite glyph over its assembled, already
+    // hinted points, given in 26.6 pixels and followed by its phantom points
+    pub fn hint_composite(
+        &mut self,
+        points: &[(i32, i32)],
+        on_curve: &[bool],
+        end_points: &[usize],
+        instructions: &[u8],
+    ) -> Result<Vec<(i32, i32)>, HintingError> {
+        let zone = Zone::new(
+            points.to_vec(),
+            points.to_vec(),
+            on_curve.to_vec(),
+            end_points.to_vec(),
+        );
+        self.run_glyph_program(zone, instructions)
+    }
+
+    fn run_glyph_program(
+        &mut self,
+        mut zone: Zone,
+        instructions: &[u8],
+    ) -> Result<Vec<(i32, i32)>, HintingError> {
+        // The phantom points start on the grid
+        let count = zone.current.len();
+        if count >= PHANTOM_POINTS {
+            let phantom = count - PHANTOM_POINTS;
+            zone.current[phantom].0 = round_to_grid(zone.current[phantom].0);
+            zone.current[phantom + 1].0 = round_to_grid(zone.current[phantom + 1].0);
+            zone.current[phantom + 2].1 = round_to_grid(zone.current[phantom + 2].1);
+            zone.current[phantom + 3].1 = round_to_grid(zone.current[phantom + 3].1);
+        }
+        self.zones[GLYPH_ZONE] = zone;
+
+        let default_graphics_state = &self.default_graphics_state;
+        if instructions.is_empty()
+            || default_graphics_state.instruct_control & INHIBIT_GRID_FITTING != 0
+        {
+            return Ok(self.zones[GLYPH_ZONE].current.clone());
+        }
+        self.graphics_state =
+            if default_graphics_state.instruct_control & IGNORE_CVT_PARAMETERS != 0 {
+                GraphicsState {
+                    instruct_control: default_graphics_state.instruct_control,
+                    ..GraphicsState::default()
+                }
+            } else {
+                GraphicsState {
+                    projection_vector: X_AXIS,
+                    freedom_vector: X_AXIS,
+                    dual_vector: X_AXIS,
+                    reference_points: [0; 3],
+                    zone_pointers: [GLYPH_ZONE; 3],
+                    loop_value: 1,
+                    ..default_graphics_state.clone()
+                }
+            };
+
+        self.programs[GLYPH_PROGRAM] = Rc::new(instructions.to_vec());
+        self.execute(GLYPH_PROGRAM)?;
+        Ok(self.zones[GLYPH_ZONE].current.clone())
+    }
+
+    fn execute(&mut self, program: usize) -> Result<(), HintingError> {
+        self.stack.clear();
+        self.instruction_count = 0;
+        let mut calls: Vec<CallFrame> = vec![];
+        let mut current = program;
+        let mut code = self.programs[current].clone();
+        let mut ip = 0;
+
+        loop {
+            if ip >= code.len() {
+                return if calls.is_empty() {
+                    Ok(())
+                } else {
+                    Err(HintingError::UnexpectedEnd)
+                };
+            }
+            self.count_instructions(1)?;
+            let opcode = code[ip];
+            let length = instruction_length(&code, ip)?;
+            let mut next_ip = ip + length;
+
+            match opcode {
+                // NPUSHB, NPUSHW, PUSHB, PUSHW
+                0x40 | 0x41 | 0xB0..=0xBF => {
+                    let (words, count, data) = match opcode {
+                        0x40 => (false, code[ip + 1] as usize, ip + 2),
+                        0x41 => (true, code[ip + 1] as usize, ip + 2),
+                        0xB0..=0xB7 => (false, (opcode - 0xAF) as usize, ip + 1),
+                        _ => (true, (opcode - 0xB7) as usize, ip + 1),
+                    };
+                    for index in 0..count {
+                        let value = if words {
+                            i16::from_be_bytes([code[data + 2 * index], code[data + 2 * index + 1]])
+                                as i32
+                        } else {
+                            code[data + index] as i32
+                        };
+                        self.push(value)?;
+                    }
+                }
+                // IF
+                0x58 => {
+                    if self.pop()? == 0 {
+                        let (position, found) = skip_to_else_or_eif(&code, ip)?;
+                        next_ip = position + if found == 0x1B { 1 } else { 0 };
+                    }
+                }
+                // ELSE, reached at the end of a taken IF branch
+                0x1B => {
+                    next_ip = skip_to_else_or_eif(&code, ip)?.0;
+                }
+                // EIF
+                0x59 => {}
+                // JMPR, JROT, JROF
+                0x1C | 0x78 | 0x79 => {
+                    let jump = match opcode {
+                        0x1C => true,
+                        0x78 => self.pop()? != 0,
+                        _ => self.pop()? == 0,
+                    };
+                    let offset = self.pop()?;
+                    if jump {
+                        let target = ip as i64 + offset as i64;
+                        if target < 0 || target > code.len() as i64 {
+                            return Err(HintingError::UnexpectedEnd);
+                        }
+                        next_ip = target as usize;
+                    }
+                }
+                // FDEF, IDEF
+                0x2C | 0x89 => {
+                    if program == GLYPH_PROGRAM {
+                        return Err(HintingError::InvalidOpcode(opcode));
+                    }
+                    let number = self.pop()?;
+                    let end = skip_to_endf(&code, ip)?;
+                    let definition = Definition {
+                        program: current,
+                        start: ip + 1,
+                    };
+                    if opcode == 0x2C {
+                        let function = self
+                            .functions
+                            .get_mut(number as usize)
+                            .filter(|_| number >= 0)
+                            .ok_or(HintingError::InvalidFunction(number))?;
+                        *function = Some(definition);
+                    } else {
+                        let opcode = number as u8;
+                        self.instruction_defs
+                            .retain(|(defined, _)| *defined != opcode);
+                        if self.instruction_defs.len() >= self.max_instruction_defs {
+                            return Err(HintingError::InvalidOpcode(opcode));
+                        }
+                        self.instruction_defs.push((opcode, definition));
+                    }
+                    next_ip = end + 1;
+                }
+                // ENDF
+                0x2D => {
+                    let frame = calls
+                        .last_mut()
+                        .ok_or(HintingError::InvalidOpcode(opcode))?;
+                    frame.remaining -= 1;
+                    if frame.remaining > 0 {
+                        next_ip = frame.definition.start;
+                    } else {
+                        let frame = calls.pop().unwrap();
+                        current = frame.program;
+                        code = self.programs[current].clone();
+                        next_ip = frame.return_ip;
+                    }
+                }
+                // CALL, LOOPCALL and instructions defined by IDEF
+                _ if matches!(opcode, 0x2A | 0x2B) || self.instruction_def(opcode).is_some() => {
+                    let (definition, remaining) = match opcode {
+                        0x2A | 0x2B => {
+                            let number = self.pop()?;
+                            let remaining = if opcode == 0x2A { self.pop()? } else { 1 };
+                            let definition = self
+                                .functions
+                                .get(number as usize)
+                                .filter(|_| number >= 0)
+                                .copied()
+                                .flatten()
+                                .ok_or(HintingError::InvalidFunction(number))?;
+                            (definition, remaining)
+                        }
+                        _ => (self.instruction_def(opcode).unwrap(), 1),
+                    };
+                    if remaining > 0 {
+                        if calls.len() >= MAX_CALL_DEPTH {
+                            return Err(HintingError::CallStackOverflow);
+                        }
+                        calls.push(CallFrame {
+                            program: current,
+                            return_ip: next_ip,
+                            definition,
+                            remaining,
+                        });
+                        current = definition.program;
+                        code = self.programs[current].clone();
+                        next_ip = definition.start;
+                    }
+                }
+                _ => match self.instruction(opcode) {
+                    Err(HintingError::InvalidPoint(_)) if !self.pedantic => {
+                        self.graphics_state.loop_value = 1;
+                    }
+                    result => result?,
+                },
+            }
+            ip = next_ip;
+        }
+    }
+
+    fn instruction_def(&self, opcode: u8) -> Option<Definition> {
+        self.instruction_defs
+            .iter()
+            .find(|(defined, _)| *defined == opcode)
+            .map(|(_, definition)| *definition)
+    }
+
+    // Instructions without control flow
+    fn instruction(&mut self, opcode: u8) -> Result<(), HintingError> {
+        match opcode {
+            // SVTCA, SPVTCA, SFVTCA
+            0x00..=0x05 => {
+                let axis = if opcode & 1 == 1 { X_AXIS } else { Y_AXIS };
+                let gs = &mut self.graphics_state;
+                if opcode <= 0x03 {
+                    gs.projection_vector = axis;
+                    gs.dual_vector = axis;
+                }
+                if opcode <= 0x01 || opcode >= 0x04 {
+                    gs.freedom_vector = axis;
+                }
+            }
+            // SPVTL, SFVTL
+            0x06..=0x09 => {
+                let p2 = self.pop()?;
+                let p1 = self.pop()?;
+                let a = self.point(1, p1)?;
+                let b = self.point(2, p2)?;
+                let (zone1, zone2) = (self.zone(1), self.zone(2));
+                let vector = line_vector(
+                    self.zones[zone1].current[a],
+                    self.zones[zone2].current[b],
+                    opcode & 1 == 1,
+                );
+                if opcode <= 0x07 {
+                    self.graphics_state.projection_vector = vector;
+                    self.graphics_state.dual_vector = vector;
+                } else {
+                    self.graphics_state.freedom_vector = vector;
+                }
+            }
+            // SPVFS, SFVFS
+            0x0A | 0x0B => {
+                let y = self.pop()?;
+                let x = self.pop()?;
+                let vector = normalize(x, y);
+                if opcode == 0x0A {
+                    self.graphics_state.projection_vector = vector;
+                    self.graphics_state.dual_vector = vector;
+                } else {
+                    self.graphics_state.freedom_vector = vector;
+                }
+            }
+            // GPV, GFV
+            0x0C | 0x0D => {
+                let (x, y) = if opcode == 0x0C {
+                    self.graphics_state.projection_vector
+                } else {
+                    self.graphics_state.freedom_vector
+                };
+                self.push(x)?;
+                self.push(y)?;
+            }
+            // SFVTPV
+            0x0E => self.graphics_state.freedom_vector = self.graphics_state.projection_vector,
+            // ISECT
+            0x0F => self.intersect()?,
+            // SRP0, SRP1, SRP2
+            0x10..=0x12 => {
+                let point = self.pop()?;
+                self.graphics_state.reference_points[(opcode - 0x10) as usize] =
+                    point.max(0) as usize;
+            }
+            // SZP0, SZP1, SZP2, SZPS
+            0x13..=0x16 => {
+                let zone = self.pop()?;
+                if zone != 0 && zone != 1 {
+                    return Err(HintingError::InvalidZone(zone));
+                }
+                match opcode {
+                    0x16 => self.graphics_state.zone_pointers = [zone as usize; 3],
+                    _ => {
+                        self.graphics_state.zone_pointers[(opcode - 0x13) as usize] = zone as usize
+                    }
+                }
+            }
+            // SLOOP
+            0x17 => {
+                let value = self.pop()?;
+                self.graphics_state.loop_value = value.clamp(0, 0xFFFF);
+            }
+            0x18 => self.graphics_state.round_state = RoundState::ToGrid,
+            0x19 => self.graphics_state.round_state = RoundState::ToHalfGrid,
+            0x3D => self.graphics_state.round_state = RoundState::ToDoubleGrid,
+            0x7A => self.graphics_state.round_state = RoundState::Off,
+            0x7C => self.graphics_state.round_state = RoundState::UpToGrid,
+            0x7D => self.graphics_state.round_state = RoundState::DownToGrid,
+            // SMD, SCVTCI, SSWCI
+            0x1A => self.graphics_state.minimum_distance = self.pop()?,
+            0x1D => self.graphics_state.control_value_cut_in = self.pop()?,
+            0x1E => self.graphics_state.single_width_cut_in = self.pop()?,
+            // SSW, in font units
+            0x1F => {
+                let value = self.pop()?;
+                self.graphics_state.single_width_value = self.scale(value);
+            }
+            // DUP
+            0x20 => {
+                let value = self.pop()?;
+                self.push(value)?;
+                self.push(value)?;
+            }
+            // POP
+            0x21 => {
+                self.pop()?;
+            }
+            // CLEAR
+            0x22 => self.stack.clear(),
+            // SWAP
+            0x23 => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                self.push(b)?;
+                self.push(a)?;
+            }
+            // DEPTH
+            0x24 => self.push(self.stack.len() as i32)?,
+            // CINDEX, MINDEX
+            0x25 | 0x26 => {
+                let index = self.pop()?;
+                if index <= 0 || index as usize > self.stack.len() {
+                    if self.pedantic {
+                        return Err(HintingError::StackUnderflow);
+                    }
+                    return self.push(0);
+                }
+                let position = self.stack.len() - index as usize;
+                let value = if opcode == 0x25 {
+                    self.stack[position]
+                } else {
+                    self.stack.remove(position)
+                };
+                self.push(value)?;
+            }
+            // ALIGNPTS
+            0x27 => {
+                let p2 = self.pop()?;
+                let p1 = self.pop()?;
+                let a = self.point(1, p1)?;
+                let b = self.point(0, p2)?;
+                let distance = self.project(
+                    self.zones[self.zone(0)].current[b],
+                    self.zones[self.zone(1)].current[a],
+                ) / 2;
+                self.move_point(self.zone(1), a, distance, true);
+                self.move_point(self.zone(0), b, -distance, true);
+            }
+            // UTP
+            0x29 => {
+                let point = self.pop()?;
+                let point = self.point(0, point)?;
+                let zone = self.zone(0);
+                let (fx, fy) = self.graphics_state.freedom_vector;
+                let touched = &mut self.zones[zone].touched[point];
+                if fx != 0 {
+                    touched.0 = false;
+                }
+                if fy != 0 {
+                    touched.1 = false;
+                }
+            }
+            // MDAP
+            0x2E | 0x2F => {
+                let point = self.pop()?;
+                let point = self.point(0, point)?;
+                let zone = self.zone(0);
+                let distance = if opcode == 0x2F {
+                    let current = self.project(self.zones[zone].current[point], (0, 0));
+                    self.round(current) - current
+                } else {
+                    0
+                };
+                self.move_point(zone, point, distance, true);
+                self.graphics_state.reference_points[0] = point;
+                self.graphics_state.reference_points[1] = point;
+            }
+            // IUP
+            0x30 | 0x31 => self.interpolate_untouched(opcode == 0x31),
+            // SHP
+            0x32 | 0x33 => {
+                let (dx, dy) = self.reference_displacement(opcode)?;
+                self.for_each_loop_point(|hinter, point| {
+                    let point = hinter.point(2, point)?;
+                    hinter.shift_point(hinter.zone(2), point, dx, dy, true);
+                    Ok(())
+                })?;
+            }
+            // SHC
+            0x34 | 0x35 => {
+                let (dx, dy) = self.reference_displacement(opcode)?;
+                let contour = self.pop()?;
+                let zone = self.zone(2);
+                let end_points = &self.zones[zone].end_points;
+                if contour < 0 || contour as usize >= end_points.len() {
+                    return Err(HintingError::InvalidContour(contour));
+                }
+                let start = match contour {
+                    0 => 0,
+                    _ => end_points[contour as usize - 1] + 1,
+                };
+                let end = end_points[contour as usize];
+                let reference = self.reference_point(opcode);
+                (start..=end)
+                    .filter(|point| (zone, *point) != reference)
+                    .for_each(|point| self.shift_point(zone, point, dx, dy, true));
+            }
+            // SHZ
+            0x36 | 0x37 => {
+                let (dx, dy) = self.reference_displacement(opcode)?;
+                let zone = self.pop()?;
+                if zone != 0 && zone != 1 {
+                    return Err(HintingError::InvalidZone(zone));
+                }
+                let zone = zone as usize;
+                let count = match zone {
+                    GLYPH_ZONE => self.zones[zone]
+                        .current
+                        .len()
+                        .saturating_sub(PHANTOM_POINTS),
+                    _ => self.zones[zone].current.len(),
+                };
+                let reference = self.reference_point(opcode);
+                (0..count)
+                    .filter(|point| (zone, *point) != reference)
+                    .for_each(|point| self.shift_point(zone, point, dx, dy, false));
+            }
+            // SHPIX
+            0x38 => {
+                let amount = self.pop()?;
+                let (fx, fy) = self.graphics_state.freedom_vector;
+                let (dx, dy) = (mul_div(amount, fx, 0x4000), mul_div(amount, fy, 0x4000));
+                self.for_each_loop_point(|hinter, point| {
+                    let point = hinter.point(2, point)?;
+                    hinter.shift_point(hinter.zone(2), point, dx, dy, true);
+                    Ok(())
+                })?;
+            }
+            // IP
+            0x39 => self.interpolate_points()?,
+            // MSIRP
+            0x3A | 0x3B => {
+                let distance = self.pop()?;
+                let point = self.pop()?;
+                let point = self.point(1, point)?;
+                let rp0 = self.reference(0, 0)?;
+                let (zone0, zone1) = (self.zone(0), self.zone(1));
+                if zone1 == TWILIGHT_ZONE {
+                    self.zones[zone1].original[point] = self.zones[zone0].original[rp0];
+                    self.move_original(zone1, point, distance);
+                    self.zones[zone1].current[point] = self.zones[zone1].original[point];
+                }
+                let current = self.project(
+                    self.zones[zone1].current[point],
+                    self.zones[zone0].current[rp0],
+                );
+                self.move_point(zone1, point, distance - current, true);
+                let gs = &mut self.graphics_state;
+                gs.reference_points[1] = gs.reference_points[0];
+                gs.reference_points[2] = point;
+                if opcode == 0x3B {
+                    gs.reference_points[0] = point;
+                }
+            }
+            // ALIGNRP
+            0x3C => {
+                let rp0 = self.reference(0, 0)?;
+                self.for_each_loop_point(|hinter, point| {
+                    let point = hinter.point(1, point)?;
+                    let (zone0, zone1) = (hinter.zone(0), hinter.zone(1));
+                    let distance = hinter.project(
+                        hinter.zones[zone1].current[point],
+                        hinter.zones[zone0].current[rp0],
+                    );
+                    hinter.move_point(zone1, point, -distance, true);
+                    Ok(())
+                })?;
+            }
+            // MIAP
+            0x3E | 0x3F => {
+                let cvt_index = self.pop()?;
+                let point = self.pop()?;
+                let point = self.point(0, point)?;
+                let zone = self.zone(0);
+                let mut distance = self.read_cvt(cvt_index);
+                if zone == TWILIGHT_ZONE {
+                    let (fx, fy) = self.graphics_state.freedom_vector;
+                    let position = (mul_div(distance, fx, 0x4000), mul_div(distance, fy, 0x4000));
+                    self.zones[zone].original[point] = position;
+                    self.zones[zone].current[point] = position;
+                }
+                let original = self.project(self.zones[zone].current[point], (0, 0));
+                if opcode == 0x3F {
+                    if (distance - original).abs() > self.graphics_state.control_value_cut_in {
+                        distance = original;
+                    }
+                    distance = self.round(distance);
+                }
+                self.move_point(zone, point, distance - original, true);
+                self.graphics_state.reference_points[0] = point;
+                self.graphics_state.reference_points[1] = point;
+            }
+            // WS, RS
+            0x42 => {
+                let value = self.pop()?;
+                let index = self.pop()?;
+                if let Some(location) = self.storage.get_mut(index as usize).filter(|_| index >= 0)
+                {
+                    *location = value;
+                }
+            }
+            0x43 => {
+                let index = self.pop()?;
+                let value = self.storage.get(index as usize).filter(|_| index >= 0);
+                self.push(value.copied().unwrap_or(0))?;
+            }
+            // WCVTP, WCVTF
+            0x44 | 0x70 => {
+                let value = self.pop()?;
+                let index = self.pop()?;
+                let value = if opcode == 0x70 {
+                    self.scale(value)
+                } else {
+                    value
+                };
+                if let Some(location) = self.cvt.get_mut(index as usize).filter(|_| index >= 0) {
+                    *location = value;
+                }
+            }
+            // RCVT
+            0x45 => {
+                let index = self.pop()?;
+                self.push(self.read_cvt(index))?;
+            }
+            // GC
+            0x46 | 0x47 => {
+                let point = self.pop()?;
+                let point = self.point(2, point)?;
+                let zone = &self.zones[self.zone(2)];
+                let value = if opcode == 0x46 {
+                    self.project(zone.current[point], (0, 0))
+                } else {
+                    self.dual_project(zone.original[point], (0, 0))
+                };
+                self.push(value)?;
+            }
+            // SCFS
+            0x48 => {
+                let value = self.pop()?;
+                let point = self.pop()?;
+                let point = self.point(2, point)?;
+                let zone = self.zone(2);
+                let current = self.project(self.zones[zone].current[point], (0, 0));
+                self.move_point(zone, point, value - current, true);
+                if zone == TWILIGHT_ZONE {
+                    self.zones[zone].original[point] = self.zones[zone].current[point];
+                }
+            }
+            // MD, measuring the original outline with 0x49 like other rasterizers
+            0x49 | 0x4A => {
+                let p2 = self.pop()?;
+                let p1 = self.pop()?;
+                let a = self.point(0, p1)?;
+                let b = self.point(1, p2)?;
+                let (zone0, zone1) = (&self.zones[self.zone(0)], &self.zones[self.zone(1)]);
+                let distance = if opcode == 0x4A {
+                    self.project(zone0.current[a], zone1.current[b])
+                } else {
+                    self.dual_project(zone0.original[a], zone1.original[b])
+                };
+                self.push(distance)?;
+            }
+            // MPPEM, MPS, at 72 dpi the point size is the ppem
+            0x4B | 0x4C => self.push(self.ppem as i32)?,
+            // FLIPON, FLIPOFF
+            0x4D => self.graphics_state.auto_flip = true,
+            0x4E => self.graphics_state.auto_flip = false,
+            // DEBUG, SANGW, AA, SCANCTRL, SCANTYPE
+            0x4F | 0x7E | 0x7F | 0x85 | 0x8D => {
+                self.pop()?;
+            }
+            // LT, LTEQ, GT, GTEQ, EQ, NEQ, AND, OR
+            0x50..=0x55 | 0x5A | 0x5B => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                let result = match opcode {
+                    0x50 => a < b,
+                    0x51 => a <= b,
+                    0x52 => a > b,
+                    0x53 => a >= b,
+                    0x54 => a == b,
+                    0x55 => a != b,
+                    0x5A => a != 0 && b != 0,
+                    _ => a != 0 || b != 0,
+                };
+                self.push(result as i32)?;
+            }
+            // ODD, EVEN
+            0x56 | 0x57 => {
+                let value = self.pop()?;
+                let value = self.round(value) & 127;
+                let expected = if opcode == 0x56 { 64 } else { 0 };
+                self.push((value == expected) as i32)?;
+            }
+            // NOT
+            0x5C => {
+                let value = self.pop()?;
+                self.push((value == 0) as i32)?;
+            }
+            // DELTAP1, DELTAP2, DELTAP3
+            0x5D | 0x71 | 0x72 => {
+                let range = match opcode {
+                    0x5D => 0,
+                    0x71 => 16,
+                    _ => 32,
+                };
+                let count = self.pop()?;
+                self.count_instructions(count.max(0) as usize)?;
+                for _ in 0..count.max(0) {
+                    let point = self.pop()?;
+                    let argument = self.pop()?;
+                    let point = self.point(0, point)?;
+                    if let Some(delta) = self.delta(argument, range) {
+                        self.move_point(self.zone(0), point, delta, true);
+                    }
+                }
+            }
+            // DELTAC1, DELTAC2, DELTAC3
+            0x73..=0x75 => {
+                let range = (opcode - 0x73) as i32 * 16;
+                let count = self.pop()?;
+                self.count_instructions(count.max(0) as usize)?;
+                for _ in 0..count.max(0) {
+                    let index = self.pop()?;
+                    let argument = self.pop()?;
+                    if let Some(delta) = self.delta(argument, range) {
+                        if let Some(value) = self.cvt.get_mut(index as usize).filter(|_| index >= 0)
+                        {
+                            *value += delta;
+                        }
+                    }
+                }
+            }
+            // SDB, SDS
+            0x5E => self.graphics_state.delta_base = self.pop()?,
+            0x5F => self.graphics_state.delta_shift = self.pop()?.clamp(0, 6),
+            // ADD, SUB, DIV, MUL, MAX, MIN
+            0x60..=0x63 | 0x8B | 0x8C => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                let result = match opcode {
+                    0x60 => a.wrapping_add(b),
+                    0x61 => a.wrapping_sub(b),
+                    0x62 => {
+                        if b == 0 {
+                            return Err(HintingError::DivideByZero);
+                        }
+                        (a as i64 * 64 / b as i64) as i32
+                    }
+                    0x63 => mul_div(a, b, 64),
+                    0x8B => a.max(b),
+                    _ => a.min(b),
+                };
+                self.push(result)?;
+            }
+            // ABS, NEG, FLOOR, CEILING
+            0x64..=0x67 => {
+                let value = self.pop()?;
+                let result = match opcode {
+                    0x64 => value.wrapping_abs(),
+                    0x65 => value.wrapping_neg(),
+                    0x66 => value & !63,
+                    _ => value.wrapping_add(63) & !63,
+                };
+                self.push(result)?;
+            }
+            // ROUND, NROUND
+            0x68..=0x6F => {
+                let value = self.pop()?;
+                let result = if opcode <= 0x6B {
+                    self.round(value)
+                } else {
+                    value
+                };
+                self.push(result)?;
+            }
+            // SROUND, S45ROUND
+            0x76 | 0x77 => {
+                let selector = self.pop()?;
+                self.graphics_state.round_state = super_round(selector, opcode == 0x77);
+            }
+            // FLIPPT
+            0x80 => {
+                self.for_each_loop_point(|hinter, point| {
+                    let point = hinter.point_in(GLYPH_ZONE, point)?;
+                    let on_curve = &mut hinter.zones[GLYPH_ZONE].on_curve[point];
+                    *on_curve = !*on_curve;
+                    Ok(())
+                })?;
+            }
+            // FLIPRGON, FLIPRGOFF
+            0x81 | 0x82 => {
+                let high = self.pop()?;
+                let low = self.pop()?;
+                let high = self.point_in(GLYPH_ZONE, high)?;
+                let low = self.point_in(GLYPH_ZONE, low)?;
+                (low..=high).for_each(|point| {
+                    self.zones[GLYPH_ZONE].on_curve[point] = opcode == 0x81;
+                });
+            }
+            // SDPVTL
+            0x86 | 0x87 => {
+                let p2 = self.pop()?;
+                let p1 = self.pop()?;
+                let a = self.point(1, p1)?;
+                let b = self.point(2, p2)?;
+                let (zone1, zone2) = (&self.zones[self.zone(1)], &self.zones[self.zone(2)]);
+                let perpendicular = opcode == 0x87;
+                self.graphics_state.dual_vector =
+                    line_vector(zone1.original[a], zone2.original[b], perpendicular);
+                self.graphics_state.projection_vector =
+                    line_vector(zone1.current[a], zone2.current[b], perpendicular);
+            }
+            // GETINFO, a version 35 engine rendering in grayscale
+            0x88 => {
+                let selector = self.pop()?;
+                let mut result = 0;
+                if selector & 0x01 != 0 {
+                    result |= 35;
+                }
+                if selector & 0x20 != 0 {
+                    result |= 0x1000;
+                }
+                self.push(result)?;
+            }
+            // ROLL
+            0x8A => {
+                let a = self.pop()?;
+                let b = self.pop()?;
+                let c = self.pop()?;
+                self.push(b)?;
+                self.push(a)?;
+                self.push(c)?;
+            }
+            // INSTCTRL
+            0x8E => {
+                let selector = self.pop()?;
+                let value = self.pop()?;
+                if (1..=3).contains(&selector) {
+                    let flag = 1 << (selector - 1);
+                    let control = &mut self.graphics_state.instruct_control;
+                    *control = (*control & !flag) | (value & flag);
+                }
+            }
+            // GETVARIATION
+            0x91 if !self.coordinates.is_empty() => {
+                for index in 0..self.coordinates.len() {
+                    self.push((self.coordinates[index] * 16384.0).round() as i32)?;
+                }
+            }
+            // MDRP
+            0xC0..=0xDF => self.move_direct_relative_point(opcode)?,
+            // MIRP
+            0xE0..=0xFF => self.move_indirect_relative_point(opcode)?,
+            _ => return Err(HintingError::InvalidOpcode(opcode)),
+        }
+        Ok(())
+    }
+
+    fn move_direct_relative_point(&mut self, opcode: u8) -> Result<(), HintingError> {
+        let point = self.pop()?;
+        let point = self.point(1, point)?;
+        let rp0 = self.reference(0, 0)?;
+        let (zone0, zone1) = (self.zone(0), self.zone(1));
+        let gs = &self.graphics_state;
+
+        let mut original = self.dual_project(
+            self.zones[zone1].original[point],
+            self.zones[zone0].original[rp0],
+        );
+        if (original - gs.single_width_value).abs() < gs.single_width_cut_in {
+            original = if original >= 0 {
+                gs.single_width_value
+            } else {
+                -gs.single_width_value
+            };
+        }
+        let mut distance = if opcode & 0x04 != 0 {
+            self.round(original)
+        } else {
+            original
+        };
+        if opcode & 0x08 != 0 {
+            distance = self.minimum_distance(original, distance);
+        }
+
+        let current = self.project(
+            self.zones[zone1].current[point],
+            self.zones[zone0].current[rp0],
+        );
+        self.move_point(zone1, point, distance - current, true);
+        self.set_relative_reference_points(point, opcode);
+        Ok(())
+    }
+
+    fn move_indirect_relative_point(&mut self, opcode: u8) -> Result<(), HintingError> {
+        let cvt_index = self.pop()?;
+        let point = self.pop()?;
+        let point = self.point(1, point)?;
+        let rp0 = self.reference(0, 0)?;
+        let (zone0, zone1) = (self.zone(0), self.zone(1));
+        let gs = self.graphics_state.clone();
+
+        let mut cvt_distance = if cvt_index == -1 {
+            0
+        } else {
+            self.read_cvt(cvt_index)
+        };
+        if (cvt_distance - gs.single_width_value).abs() < gs.single_width_cut_in {
+            cvt_distance = if cvt_distance >= 0 {
+                gs.single_width_value
+            } else {
+                -gs.single_width_value
+            };
+        }
+        if zone1 == TWILIGHT_ZONE {
+            let (fx, fy) = gs.freedom_vector;
+            let (x, y) = self.zones[zone0].original[rp0];
+            let position = (
+                x + mul_div(cvt_distance, fx, 0x4000),
+                y + mul_div(cvt_distance, fy, 0x4000),
+            );
+            self.zones[zone1].original[point] = position;
+            self.zones[zone1].current[point] = position;
+        }
+
+        let original = self.dual_project(
+            self.zones[zone1].original[point],
+            self.zones[zone0].original[rp0],
+        );
+        let current = self.project(
+            self.zones[zone1].current[point],
+            self.zones[zone0].current[rp0],
+        );
+        if gs.auto_flip && (original ^ cvt_distance) < 0 {
+            cvt_distance = -cvt_distance;
+        }
+        let mut distance = if opcode & 0x04 != 0 {
+            if zone0 == zone1 && (cvt_distance - original).abs() > gs.control_value_cut_in {
+                cvt_distance = original;
+            }
+            self.round(cvt_distance)
+        } else {
+            cvt_distance
+        };
+        if opcode & 0x08 != 0 {
+            distance = self.minimum_distance(original, distance);
+        }
+
+        self.move_point(zone1, point, distance - current, true);
+        self.set_relative_reference_points(point, opcode);
+        Ok(())
+    }
+
+    fn minimum_distance(&self, original: i32, distance: i32) -> i32 {
+        let minimum = self.graphics_state.minimum_distance;
+        if original >= 0 {
+            distance.max(minimum)
+        } else {
+            distance.min(-minimum)
+        }
+    }
+
+    fn set_relative_reference_points(&mut self, point: usize, opcode: u8) {
+        let gs = &mut self.graphics_state;
+        gs.reference_points[1] = gs.reference_points[0];
+        gs.reference_points[2] = point;
+        if opcode & 0x10 != 0 {
+            gs.reference_points[0] = point;
+        }
+    }
+
+    // ISECT moves a point to the intersection of lines a0 a1 and b0 b1
+    fn intersect(&mut self) -> Result<(), HintingError> {
+        let b1 = self.pop()?;
+        let b0 = self.pop()?;
+        let a1 = self.pop()?;
+        let a0 = self.pop()?;
+        let point = self.pop()?;
+        let (b0, b1) = (self.point(0, b0)?, self.point(0, b1)?);
+        let (a0, a1) = (self.point(1, a0)?, self.point(1, a1)?);
+        let point = self.point(2, point)?;
+        let (zone0, zone1, zone2) = (self.zone(0), self.zone(1), self.zone(2));
+
+        let (b0, b1) = (self.zones[zone0].current[b0], self.zones[zone0].current[b1]);
+        let (a0, a1) = (self.zones[zone1].current[a0], self.zones[zone1].current[a1]);
+        let (dbx, dby) = (b1.0 - b0.0, b1.1 - b0.1);
+        let (dax, day) = (a1.0 - a0.0, a1.1 - a0.1);
+        let (dx, dy) = (b0.0 - a0.0, b0.1 - a0.1);
+
+        let discriminant = mul_div(dax, -dby, 64) + mul_div(day, dbx, 64);
+        let dot_product = mul_div(dax, dbx, 64) + mul_div(day, dby, 64);
+        // Nearly parallel lines meet in the middle of their points
+        let position = if 19 * discriminant.abs() > dot_product.abs() {
+            let value = mul_div(dx, -dby, 64) + mul_div(dy, dbx, 64);
+            (
+                a0.0 + mul_div(value, dax, discriminant),
+                a0.1 + mul_div(value, day, discriminant),
+            )
+        } else {
+            (
+                (a0.0 + a1.0 + b0.0 + b1.0) / 4,
+                (a0.1 + a1.1 + b0.1 + b1.1) / 4,
+            )
+        };
+        self.zones[zone2].current[point] = position;
+        self.zones[zone2].touched[point] = (true, true);
+        Ok(())
+    }
+
+    // IP keeps the relative position of points between rp1 and rp2
+    fn interpolate_points(&mut self) -> Result<(), HintingError> {
+        let rp1 = self.reference(1, 0)?;
+        let rp2 = self.reference(2, 1)?;
+        let (zone0, zone1) = (self.zone(0), self.zone(1));
+        let original_base = self.zones[zone0].original[rp1];
+        let current_base = self.zones[zone0].current[rp1];
+        let original_range = self.dual_project(self.zones[zone1].original[rp2], original_base);
+        let current_range = self.project(self.zones[zone1].current[rp2], current_base);
+
+        self.for_each_loop_point(|hinter, point| {
+            let point = hinter.point(2, point)?;
+            let zone2 = hinter.zone(2);
+            let original = hinter.dual_project(hinter.zones[zone2].original[point], original_base);
+            let current = hinter.project(hinter.zones[zone2].current[point], current_base);
+            let distance = if original == 0 {
+                0
+            } else if original_range == 0 {
+                original
+            } else {
+                mul_div(original, current_range, original_range)
+            };
+            hinter.move_point(zone2, point, distance - current, true);
+            Ok(())
+        })
+    }
+
+    // IUP moves the untouched points of each contour along with their
+    // touched neighbors
+    fn interpolate_untouched(&mut self, x_axis: bool) {
+        let zone = &mut self.zones[GLYPH_ZONE];
+        let coordinate = |point: (i32, i32)| if x_axis { point.0 } else { point.1 };
+        let is_touched = |touched: (bool, bool)| if x_axis { touched.0 } else { touched.1 };
+
+        let mut start = 0;
+        for end in zone.end_points.clone() {
+            if end >= zone.current.len() || end < start {
+                break;
+            }
+            let contour: Vec<usize> = (start..=end).collect();
+            start = end + 1;
+            let touched: Vec<usize> = contour
+                .iter()
+                .copied()
+                .filter(|point| is_touched(zone.touched[*point]))
+                .collect();
+
+            match touched.len() {
+                0 => {}
+                1 => {
+                    let reference = touched[0];
+                    let delta =
+                        coordinate(zone.current[reference]) - coordinate(zone.original[reference]);
+                    contour
+                        .iter()
+                        .filter(|point| **point != reference)
+                        .for_each(|point| {
+                            let current = &mut zone.current[*point];
+                            if x_axis {
+                                current.0 += delta;
+                            } else {
+                                current.1 += delta;
+                            }
+                        });
+                }
+                count => {
+                    (0..count).for_each(|index| {
+                        let reference1 = touched[index];
+                        let reference2 = touched[(index + 1) % count];
+                        // Untouched points from reference1 to reference2, wrapping around
+                        let mut points = vec![];
+                        let mut point = reference1;
+                        loop {
+                            point = if point == *contour.last().unwrap() {
+                                contour[0]
+                            } else {
+                                point + 1
+                            };
+                            if point == reference2 {
+                                break;
+                            }
+                            points.push(point);
+                        }
+                        interpolate(zone, x_axis, &points, reference1, reference2);
+                    });
+                }
+            }
+        }
+    }
+
+    // The move of rp2 (or rp1 for odd opcodes) along the freedom vector
+    fn reference_displacement(&self, opcode: u8) -> Result<(i32, i32), HintingError> {
+        let (zone, point) = if opcode & 1 == 1 {
+            (self.zone(0), self.reference(1, 0)?)
+        } else {
+            (self.zone(1), self.reference(2, 1)?)
+        };
+        let distance = self.project(
+            self.zones[zone].current[point],
+            self.zones[zone].original[point],
+        );
+        let (fx, fy) = self.graphics_state.freedom_vector;
+        let f_dot_p = self.f_dot_p();
+        Ok((
+            mul_div(distance, fx, f_dot_p),
+            mul_div(distance, fy, f_dot_p),
+        ))
+    }
+
+    // Zone and number of the reference point of SHC and SHZ, left in place
+    fn reference_point(&self, opcode: u8) -> (usize, usize) {
+        let gs = &self.graphics_state;
+        if opcode & 1 == 1 {
+            (gs.zone_pointers[0], gs.reference_points[1])
+        } else {
+            (gs.zone_pointers[1], gs.reference_points[2])
+        }
+    }
+
+    fn for_each_loop_point(
+        &mut self,
+        mut action: impl FnMut(&mut Hinter, i32) -> Result<(), HintingError>,
+    ) -> Result<(), HintingError> {
+        let count = self.graphics_state.loop_value;
+        self.graphics_state.loop_value = 1;
+        self.count_instructions(count as usize)?;
+        for _ in 0..count {
+            let point = self.pop()?;
+            match action(self, point) {
+                Err(HintingError::InvalidPoint(_)) if !self.pedantic => {}
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+
+    // DELTAP and DELTAC amount at the current size, None at other sizes
+    fn delta(&self, argument: i32, range: i32) -> Option<i32> {
+        let gs = &self.graphics_state;
+        let ppem = gs.delta_base + range + ((argument >> 4) & 0xF);
+        if ppem != self.ppem as i32 {
+            return None;
+        }
+        let mut steps = (argument & 0xF) - 8;
+        if steps >= 0 {
+            steps += 1;
+        }
+        Some(steps * (1 << (6 - gs.delta_shift)))
+    }
+
+    fn round(&self, distance: i32) -> i32 {
+        round(distance, self.graphics_state.round_state)
+    }
+
+    fn read_cvt(&self, index: i32) -> i32 {
+        self.cvt
+            .get(index as usize)
+            .filter(|_| index >= 0)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn f_dot_p(&self) -> i32 {
+        let (px, py) = self.graphics_state.projection_vector;
+        let (fx, fy) = self.graphics_state.freedom_vector;
+        let f_dot_p = ((px as i64 * fx as i64 + py as i64 * fy as i64) >> 14) as i32;
+        if f_dot_p.abs() < 0x400 {
+            0x4000
+        } else {
+            f_dot_p
+        }
+    }
+
+    fn project(&self, a: (i32, i32), b: (i32, i32)) -> i32 {
+        dot14(a.0 - b.0, a.1 - b.1, self.graphics_state.projection_vector)
+    }
+
+    fn dual_project(&self, a: (i32, i32), b: (i32, i32)) -> i32 {
+        dot14(a.0 - b.0, a.1 - b.1, self.graphics_state.dual_vector)
+    }
+
+    // Moves a point along the freedom vector until its projection changed by `distance`
+    fn move_point(&mut self, zone: usize, point: usize, distance: i32, touch: bool) {
+        let (fx, fy) = self.graphics_state.freedom_vector;
+        let f_dot_p = self.f_dot_p();
+        let zone = &mut self.zones[zone];
+        if fx != 0 {
+            zone.current[point].0 += mul_div(distance, fx, f_dot_p);
+            zone.touched[point].0 |= touch;
+        }
+        if fy != 0 {
+            zone.current[point].1 += mul_div(distance, fy, f_dot_p);
+            zone.touched[point].1 |= touch;
+        }
+    }
+
+    fn move_original(&mut self, zone: usize, point: usize, distance: i32) {
+        let (fx, fy) = self.graphics_state.freedom_vector;
+        let f_dot_p = self.f_dot_p();
+        let original = &mut self.zones[zone].original[point];
+        original.0 += mul_div(distance, fx, f_dot_p);
+        original.1 += mul_div(distance, fy, f_dot_p);
+    }
+
+    fn shift_point(&mut self, zone: usize, point: usize, dx: i32, dy: i32, touch: bool) {
+        let (fx, fy) = self.graphics_state.freedom_vector;
+        let zone = &mut self.zones[zone];
+        if fx != 0 {
+            zone.current[point].0 += dx;
+            zone.touched[point].0 |= touch;
+        }
+        if fy != 0 {
+            zone.current[point].1 += dy;
+            zone.touched[point].1 |= touch;
+        }
+    }
+
+    // The zone of zone pointer zp0, zp1 or zp2
+    fn zone(&self, pointer: usize) -> usize {
+        self.graphics_state.zone_pointers[pointer]
+    }
+
+    // A point of the zone of zone pointer `pointer`
+    fn point(&self, pointer: usize, point: i32) -> Result<usize, HintingError> {
+        self.point_in(self.zone(pointer), point)
+    }
+
+    fn point_in(&self, zone: usize, point: i32) -> Result<usize, HintingError> {
+        if point >= 0 && (point as usize) < self.zones[zone].current.len() {
+            Ok(point as usize)
+        } else {
+            Err(HintingError::InvalidPoint(point))
+        }
+    }
+
+    // Reference point rp0, rp1 or rp2 in the zone of zone pointer `pointer`
+    fn reference(&self, reference: usize, pointer: usize) -> Result<usize, HintingError> {
+        let point = self.graphics_state.reference_points[reference];
+        self.point(pointer, point as i32)
+    }
+
+    fn count_instructions(&mut self, count: usize) -> Result<(), HintingError> {
+        self.instruction_count += count;
+        if self.instruction_count > MAX_INSTRUCTIONS {
+            Err(HintingError::ExecutionLimit)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn push(&mut self, value: i32) -> Result<(), HintingError> {
+        if self.stack.len() >= self.stack_limit {
+            return Err(HintingError::StackOverflow);
+        }
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<i32, HintingError> {
+        match self.stack.pop() {
+            Some(value) => Ok(value),
+            None if !self.pedantic => Ok(0),
+            None => Err(HintingError::StackUnderflow),
+        }
+    }
+}
+
+// Interpolates `points` between two touched points, by their unscaled
+// position, and shifts the ones outside along with the nearest
+fn interpolate(
+    zone: &mut Zone,
+    x_axis: bool,
+    points: &[usize],
+    reference1: usize,
+    reference2: usize,
+) {
+    let coordinate = |point: (i32, i32)| if x_axis { point.0 } else { point.1 };
+    let (mut reference1, mut reference2) = (reference1, reference2);
+    if coordinate(zone.unscaled[reference1]) > coordinate(zone.unscaled[reference2]) {
+        std::mem::swap(&mut reference1, &mut reference2);
+    }
+    let (unscaled1, unscaled2) = (
+        coordinate(zone.unscaled[reference1]),
+        coordinate(zone.unscaled[reference2]),
+    );
+    let (original1, original2) = (
+        coordinate(zone.original[reference1]),
+        coordinate(zone.original[reference2]),
+    );
+    let (current1, current2) = (
+        coordinate(zone.current[reference1]),
+        coordinate(zone.current[reference2]),
+    );
+    let (delta1, delta2) = (current1 - original1, current2 - original2);
+
+    points.iter().for_each(|point| {
+        let original = coordinate(zone.original[*point]);
+        let value = if original <= original1 {
+            original + delta1
+        } else if original >= original2 {
+            original + delta2
+        } else if current1 == current2 || unscaled1 == unscaled2 {
+            current1
+        } else {
+            let unscaled = coordinate(zone.unscaled[*point]);
+            current1
+                + mul_div(
+                    unscaled - unscaled1,
+                    current2 - current1,
+                    unscaled2 - unscaled1,
+                )
+        };
+        let current = &mut zone.current[*point];
+        if x_axis {
+            current.0 = value;
+        } else {
+            current.1 = value;
+        }
+    });
+}
+
+// Bytes taken by the instruction at `ip` with its push data
+pub fn instruction_length(code: &[u8], ip: usize) -> Result<usize, HintingError> {
+    let opcode = code[ip];
+    let length = match opcode {
+        0x40 => 2 + *code.get(ip + 1).ok_or(HintingError::UnexpectedEnd)? as usize,
+        0x41 => 2 + 2 * *code.get(ip + 1).ok_or(HintingError::UnexpectedEnd)? as usize,
+        0xB0..=0xB7 => 1 + (opcode - 0xAF) as usize,
+        0xB8..=0xBF => 1 + 2 * (opcode - 0xB7) as usize,
+        _ => 1,
+    };
+    if ip + length > code.len() {
+        Err(HintingError::UnexpectedEnd)
+    } else {
+        Ok(length)
+    }
+}
+
+// Position and opcode of the ELSE or EIF matching the IF or ELSE at `ip`
+fn skip_to_else_or_eif(code: &[u8], ip: usize) -> Result<(usize, u8), HintingError> {
+    let mut nesting = 0;
+    let mut position = ip + instruction_length(code, ip)?;
+    while position < code.len() {
+        match code[position] {
+            0x58 => nesting += 1,
+            0x1B if nesting == 0 && code[ip] == 0x58 => return Ok((position, 0x1B)),
+            0x59 if nesting == 0 => return Ok((position, 0x59)),
+            0x59 => nesting -= 1,
+            _ => {}
+        }
+        position += instruction_length(code, position)?;
+    }
+    Err(HintingError::UnexpectedEnd)
+}
+
+// Position of the ENDF closing the FDEF or IDEF at `ip`
+fn skip_to_endf(code: &[u8], ip: usize) -> Result<usize, HintingError> {
+    let mut position = ip + 1;
+    while position < code.len() {
+        match code[position] {
+            0x2D => return Ok(position),
+            0x2C | 0x89 => return Err(HintingError::InvalidOpcode(code[position])),
+            _ => position += instruction_length(code, position)?,
+        }
+    }
+    Err(HintingError::UnexpectedEnd)
+}
+
+fn round(distance: i32, round_state: RoundState) -> i32 {
+    let rounded = match round_state {
+        RoundState::ToGrid => (distance.abs() + 32) & !63,
+        RoundState::ToHalfGrid => (distance.abs() & !63) + 32,
+        RoundState::ToDoubleGrid => (distance.abs() + 16) & !31,
+        RoundState::DownToGrid => distance.abs() & !63,
+        RoundState::UpToGrid => (distance.abs() + 63) & !63,
+        RoundState::Off => return distance,
+        RoundState::Super {
+            period,
+            phase,
+            threshold,
+        } => {
+            let rounded = (distance.abs() - phase + threshold).div_euclid(period) * period + phase;
+            if rounded < 0 {
+                phase
+            } else {
+                rounded
+            }
+        }
+    };
+    if distance >= 0 {
+        rounded
+    } else {
+        -rounded
+    }
+}
+
+fn round_to_grid(value: i32) -> i32 {
+    (value + 32) & !63
+}
+
+// SROUND and S45ROUND, the grid period is 1 or √2/2 pixel
+fn super_round(selector: i32, diagonal: bool) -> RoundState {
+    let grid_period = if diagonal { 0x2D41 } else { 0x4000 };
+    let period = match selector & 0xC0 {
+        0x00 => grid_period / 2,
+        0x80 => grid_period * 2,
+        _ => grid_period,
+    };
+    let phase = match selector & 0x30 {
+        0x00 => 0,
+        0x10 => period / 4,
+        0x20 => period / 2,
+        _ => period * 3 / 4,
+    };
+    let threshold = match selector & 0x0F {
+        0 => period - 1,
+        value => (value - 4) * period / 8,
+    };
+    RoundState::Super {
+        period: (period >> 8).max(1),
+        phase: phase >> 8,
+        threshold: threshold >> 8,
+    }
+}
+
+// Unit vector along the line from b to a, or perpendicular to it
+fn line_vector(a: (i32, i32), b: (i32, i32), perpendicular: bool) -> (i32, i32) {
+    let (dx, dy) = (a.0 - b.0, a.1 - b.1);
+    if dx == 0 && dy == 0 {
+        return X_AXIS;
+    }
+    if perpendicular {
+        normalize(-dy, dx)
+    } else {
+        normalize(dx, dy)
+    }
+}
+
+fn normalize(x: i32, y: i32) -> (i32, i32) {
+    let length = ((x as f64).powi(2) + (y as f64).powi(2)).sqrt();
+    if length == 0.0 {
+        return X_AXIS;
+    }
+    (
+        (x as f64 * 16384.0 / length).round() as i32,
+        (y as f64 * 16384.0 / length).round() as i32,
+    )
+}
+
+fn dot14(x: i32, y: i32, vector: (i32, i32)) -> i32 {
+    ((x as i64 * vector.0 as i64 + y as i64 * vector.1 as i64 + 0x2000) >> 14) as i32
+}
+
+// a * b / c rounded to the nearest, 0 when dividing by 0
+fn mul_div(a: i32, b: i32, c: i32) -> i32 {
+    if c == 0 {
+        return 0;
+    }
+    let product = a as i64 * b as i64;
+    let c = c as i64;
+    let result = (product.abs() + c.abs() / 2) / c.abs();
+    let result = if (product < 0) != (c < 0) {
+        -result
+    } else {
+        result
+    };
+    result.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+}
+
+// A grid fitted outline in pixels, x relative to the left phantom point
+#[derive(Debug, Clone, PartialEq)]
+pub struct HintedGlyph {
+    pub contours: Vec<Vec<(f32, f32, bool)>>, // x, y, on curve
+    pub advance_width: f32,
+    pub cubic: bool, // off curve points are cubic control points
+}
+
+impl HintedGlyph {
+    // From hinted 26.6 points followed by the phantom points
+    pub fn new(points: &[(i32, i32)], on_curve: &[bool], end_points: &[usize]) -> HintedGlyph {
+        let phantom = points.len() - PHANTOM_POINTS;
+        let origin = points[phantom].0;
+        let mut start = 0;
+        let contours = end_points
+            .iter()
+            .map(|end| {
+                let contour = (start..=*end)
+                    .map(|point| {
+                        let (x, y) = points[point];
+                        ((x - origin) as f32 / 64.0, y as f32 / 64.0, on_curve[point])
+                    })
+                    .collect();
+                start = end + 1;
+                contour
+            })
+            .collect();
+        HintedGlyph {
+            contours,
+            advance_width: (points[phantom + 1].0 - origin) as f32 / 64.0,
+            cubic: false,
+        }
+    }
+}
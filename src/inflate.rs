@@ -0,0 +1,371 @@
+// Decompression of DEFLATE streams (RFC 1951), zlib streams (RFC 1950) as
+// used for WOFF tables, and gzip streams (RFC 1952) as used for SVG documents.
+
+const MAX_BITS: usize = 15;
+
+// gzip header flags
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+const END_OF_BLOCK: u16 = 256;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// Order in which code length code lengths are stored in a dynamic block header
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InflateError {
+    UnexpectedEnd,
+    InvalidHeader,    // zlib or gzip header, or a preset dictionary
+    InvalidData,      // a code, block type or distance DEFLATE does not allow
+    LimitExceeded,    // more output than the caller expects
+    ChecksumMismatch, // Adler-32, CRC-32 or size of the output
+}
+
+// Reads bits least significant first, as DEFLATE packs them
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize, // in bits
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> BitReader<'a> {
+        BitReader { data, position: 0 }
+    }
+
+    fn bit(&mut self) -> Result<u32, InflateError> {
+        let byte = *self
+            .data
+            .get(self.position / 8)
+            .ok_or(InflateError::UnexpectedEnd)?;
+        let bit = (byte >> (self.position % 8)) & 1;
+        self.position += 1;
+        Ok(bit as u32)
+    }
+
+    fn bits(&mut self, count: u8) -> Result<u32, InflateError> {
+        (0..count).try_fold(0, |value, index| Ok(value | (self.bit()? << index)))
+    }
+
+    fn align_to_byte(&mut self) {
+        self.position = self.position.next_multiple_of(8);
+    }
+
+    fn byte_position(&self) -> usize {
+        self.position.div_ceil(8)
+    }
+}
+
+// Canonical Huffman code, decoded one bit at a time
+struct Huffman {
+    counts: [u16; MAX_BITS + 1], // number of codes of each length
+    symbols: Vec<u16>,           // symbols ordered by code
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0u16; MAX_BITS + 1];
+        lengths
+            .iter()
+            .for_each(|length| counts[*length as usize] += 1);
+        counts[0] = 0;
+
+        let mut offsets = [0u16; MAX_BITS + 2];
+        (1..=MAX_BITS).for_each(|length| offsets[length + 1] = offsets[length] + counts[length]);
+
+        let mut symbols = vec![0; lengths.len()];
+        lengths
+            .iter()
+            .enumerate()
+            .filter(|(_, length)| **length != 0)
+            .for_each(|(symbol, length)| {
+                symbols[offsets[*length as usize] as usize] = symbol as u16;
+                offsets[*length as usize] += 1;
+            });
+
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, bit_reader: &mut BitReader) -> Result<u16, InflateError> {
+        let mut code: i32 = 0; // code of the current length
+        let mut first: i32 = 0; // first code of the current length
+        let mut index: i32 = 0; // index of the first code of the current length in symbols
+        for length in 1..=MAX_BITS {
+            code |= bit_reader.bit()? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(InflateError::InvalidData)
+    }
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[0..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..288].fill(8);
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+fn dynamic_codes(bit_reader: &mut BitReader) -> Result<(Huffman, Huffman), InflateError> {
+    let literal_count = bit_reader.bits(5)? as usize + 257;
+    let distance_count = bit_reader.bits(5)? as usize + 1;
+    let code_length_count = bit_reader.bits(4)? as usize + 4;
+
+    let mut code_length_lengths = [0u8; 19];
+    for index in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_length_lengths[*index] = bit_reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_length_lengths);
+
+    // Literal/length and distance code lengths form a single sequence, repeats can cross over
+    let mut lengths: Vec<u8> = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let symbol = code_length_code.decode(bit_reader)?;
+        let (length, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                // Repeats the previous code length
+                let previous = *lengths.last().ok_or(InflateError::InvalidData)?;
+                (previous, 3 + bit_reader.bits(2)?)
+            }
+            17 => (0, 3 + bit_reader.bits(3)?),
+            18 => (0, 11 + bit_reader.bits(7)?),
+            _ => return Err(InflateError::InvalidData),
+        };
+        lengths.extend(std::iter::repeat_n(length, repeat as usize));
+    }
+    // Code lengths overflowing the dynamic block header
+    if lengths.len() != literal_count + distance_count {
+        return Err(InflateError::InvalidData);
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+fn inflate_block(
+    bit_reader: &mut BitReader,
+    literal_code: &Huffman,
+    distance_code: &Huffman,
+    output: &mut Vec<u8>,
+    max_length: usize,
+) -> Result<(), InflateError> {
+    loop {
+        let symbol = literal_code.decode(bit_reader)?;
+        match symbol {
+            0..=255 => {
+                if output.len() >= max_length {
+                    return Err(InflateError::LimitExceeded);
+                }
+                output.push(symbol as u8)
+            }
+            END_OF_BLOCK => return Ok(()),
+            _ => {
+                let index = (symbol - 257) as usize;
+                if index >= LENGTH_BASE.len() {
+                    return Err(InflateError::InvalidData);
+                }
+                let length = LENGTH_BASE[index] as usize
+                    + bit_reader.bits(LENGTH_EXTRA_BITS[index])? as usize;
+
+                let index = distance_code.decode(bit_reader)? as usize;
+                if index >= DISTANCE_BASE.len() {
+                    return Err(InflateError::InvalidData);
+                }
+                let distance = DISTANCE_BASE[index] as usize
+                    + bit_reader.bits(DISTANCE_EXTRA_BITS[index])? as usize;
+                // Before the start of the output
+                if distance > output.len() {
+                    return Err(InflateError::InvalidData);
+                }
+                if output.len() + length > max_length {
+                    return Err(InflateError::LimitExceeded);
+                }
+
+                // The copy can overlap the bytes it produces
+                let start = output.len() - distance;
+                (0..length).for_each(|offset| output.push(output[start + offset]));
+            }
+        }
+    }
+}
+
+// Decompresses a raw DEFLATE stream of at most `max_length` bytes of output,
+// returns the output and the number of bytes read
+fn inflate_stream(data: &[u8], max_length: usize) -> Result<(Vec<u8>, usize), InflateError> {
+    let mut bit_reader = BitReader::new(data);
+    let mut output: Vec<u8> = vec![];
+    loop {
+        let is_final = bit_reader.bit()? == 1;
+        match bit_reader.bits(2)? {
+            0 => {
+                bit_reader.align_to_byte();
+                let start = bit_reader.byte_position();
+                if start + 4 > data.len() {
+                    return Err(InflateError::UnexpectedEnd);
+                }
+                let length = u16::from_le_bytes([data[start], data[start + 1]]) as usize;
+                let complement = u16::from_le_bytes([data[start + 2], data[start + 3]]) as usize;
+                if length != !complement & 0xFFFF {
+                    return Err(InflateError::InvalidData);
+                }
+                let stored = data
+                    .get(start + 4..start + 4 + length)
+                    .ok_or(InflateError::UnexpectedEnd)?;
+                if output.len() + length > max_length {
+                    return Err(InflateError::LimitExceeded);
+                }
+                output.extend_from_slice(stored);
+                bit_reader.position = (start + 4 + length) * 8;
+            }
+            1 => {
+                let (literal_code, distance_code) = fixed_codes();
+                inflate_block(
+                    &mut bit_reader,
+                    &literal_code,
+                    &distance_code,
+                    &mut output,
+                    max_length,
+                )?;
+            }
+            2 => {
+                let (literal_code, distance_code) = dynamic_codes(&mut bit_reader)?;
+                inflate_block(
+                    &mut bit_reader,
+                    &literal_code,
+                    &distance_code,
+                    &mut output,
+                    max_length,
+                )?;
+            }
+            _ => return Err(InflateError::InvalidData),
+        }
+        if is_final {
+            return Ok((output, bit_reader.byte_position()));
+        }
+    }
+}
+
+// A zlib stream: 2 byte header, DEFLATE data, Adler-32 of the output. Stops
+// with LimitExceeded past `max_length` bytes of output.
+pub fn zlib_decompress(data: &[u8], max_length: usize) -> Result<Vec<u8>, InflateError> {
+    if data.len() < 6 {
+        return Err(InflateError::UnexpectedEnd);
+    }
+    let (cmf, flags) = (data[0], data[1]);
+    if cmf & 0x0F != 8 || !(((cmf as u16) << 8) | flags as u16).is_multiple_of(31) {
+        return Err(InflateError::InvalidHeader);
+    }
+    // Preset dictionaries are not supported
+    if flags & 0x20 != 0 {
+        return Err(InflateError::InvalidHeader);
+    }
+
+    let (output, length) = inflate_stream(&data[2..], max_length)?;
+    let checksum_offset = 2 + length;
+    let expected = data
+        .get(checksum_offset..checksum_offset + 4)
+        .map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()))
+        .ok_or(InflateError::UnexpectedEnd)?;
+    if adler32(&output) != expected {
+        return Err(InflateError::ChecksumMismatch);
+    }
+    Ok(output)
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), byte| {
+        let a = (a + *byte as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+    (b << 16) | a
+}
+
+// A gzip member: 10 byte header with optional fields, DEFLATE data, CRC-32 and
+// size of the output. The size at the end of the data bounds the output.
+pub fn gzip_decompress(data: &[u8]) -> Result<Vec<u8>, InflateError> {
+    if data.len() < 18 || data[0..3] != [0x1F, 0x8B, 8] {
+        return Err(InflateError::InvalidHeader);
+    }
+    let flags = data[3];
+    let mut position = 10;
+    if flags & FEXTRA != 0 {
+        let extra_length = data
+            .get(position..position + 2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+            .ok_or(InflateError::UnexpectedEnd)?;
+        position += 2 + extra_length;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            // Zero terminated
+            let length = data
+                .get(position..)
+                .and_then(|rest| rest.iter().position(|byte| *byte == 0))
+                .ok_or(InflateError::UnexpectedEnd)?;
+            position += length + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        position += 2;
+    }
+
+    let size = u32::from_le_bytes(data[data.len() - 4..].try_into().unwrap());
+    let (output, length) = inflate_stream(
+        data.get(position..).ok_or(InflateError::UnexpectedEnd)?,
+        size as usize,
+    )?;
+    let trailer = position + length;
+    let (expected_crc, expected_size) = data
+        .get(trailer..trailer + 8)
+        .map(|bytes| {
+            (
+                u32::from_le_bytes(bytes[0..4].try_into().unwrap()),
+                u32::from_le_bytes(bytes[4..8].try_into().unwrap()),
+            )
+        })
+        .ok_or(InflateError::UnexpectedEnd)?;
+    if crc32(&output) != expected_crc || output.len() as u32 != expected_size {
+        return Err(InflateError::ChecksumMismatch);
+    }
+    Ok(output)
+}
+
+// CRC-32 with the reflected polynomial 0xEDB88320
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(0xFFFFFFFFu32, |crc, byte| {
+        (0..8).fold(crc ^ *byte as u32, |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
@@ -0,0 +1,573 @@
+use crate::font_builder::FontBuilder;
+use crate::glyf_encoder::{encode_glyf_and_loca, EncodedGlyf};
+use crate::glyph_reader::GlyphReader;
+use crate::model::{ArgumentTypes, Glyph, GlyphId};
+use std::collections::HashMap;
+
+// Tables that only make sense in a variable font, or whose content no longer
+// matches the instanced outlines and metrics
+const DROPPED_TABLES: [&str; 11] = [
+    "fvar", "gvar", "avar", "cvar", "HVAR", "VVAR", "MVAR", "STAT", "hdmx", "VDMX", "LTSH",
+];
+
+// Subfamilies that fit the legacy family model of name ids 1 and 2
+const RIBBI_SUBFAMILIES: [&str; 4] = ["Regular", "Italic", "Bold", "Bold Italic"];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InstanceError {
+    UnsupportedOutlines, // CFF2 outlines are not written as a static font
+}
+
+// Writes a static font at the location given by (axis tag, value) pairs, see
+// `GlyphReader::set_variation`. The outlines, metrics and control values are
+// baked from the variation tables, which are then dropped. Glyph instructions
+// are kept.
+pub fn instantiate(
+    glyph_reader: &mut GlyphReader,
+    settings: &[(&str, f32)],
+) -> Result<Vec<u8>, InstanceError> {
+    if !glyph_reader.table_tags().iter().any(|tag| tag == "glyf") {
+        return Err(InstanceError::UnsupportedOutlines);
+    }
+    let previous_coordinates: Vec<(String, f32)> = variation_settings(glyph_reader);
+
+    glyph_reader.set_variation(settings);
+    let font = write_instance(glyph_reader);
+
+    let previous_coordinates: Vec<(&str, f32)> = previous_coordinates
+        .iter()
+        .map(|(tag, value)| (tag.as_str(), *value))
+        .collect();
+    glyph_reader.set_variation(&previous_coordinates);
+
+    font
+}
+
+fn variation_settings(glyph_reader: &GlyphReader) -> Vec<(String, f32)> {
+    glyph_reader
+        .variation_axes()
+        .iter()
+        .zip(glyph_reader.variation_coordinates())
+        .map(|(axis, value)| (axis.tag.clone(), *value))
+        .collect()
+}
+
+fn write_instance(glyph_reader: &mut GlyphReader) -> Result<Vec<u8>, InstanceError> {
+    let maxp = glyph_reader
+        .table_data("maxp")
+        .expect("maxp table not found");
+    let num_glyphs = read_u16(&maxp, 4);
+
+    let glyphs: Vec<Glyph> = (0..num_glyphs)
+        .map(|glyph_id| glyph_reader.glyph_for_glyph_id(GlyphId::new(glyph_id)))
+        .collect();
+    let bounds = glyph_bounds(&glyphs);
+
+    let mut tables: Vec<(String, Vec<u8>)> = glyph_reader
+        .table_tags()
+        .into_iter()
+        .filter(|tag| !DROPPED_TABLES.contains(&tag.as_str()))
+        .filter_map(|tag| glyph_reader.table_data(&tag).map(|data| (tag, data)))
+        .collect();
+
+    // Compound glyph bounds change with their components
+    let glyphs: Vec<Glyph> = glyphs
+        .into_iter()
+        .zip(&bounds)
+        .map(|(glyph, bounds)| match bounds {
+            Some(bounds) => glyph.with_bounding_box(*bounds),
+            None => glyph,
+        })
+        .collect();
+    let EncodedGlyf {
+        glyf,
+        loca,
+        index_to_loc_format,
+    } = encode_glyf_and_loca(&glyphs, 2).map_err(|_| InstanceError::UnsupportedOutlines)?;
+    let hmtx = encode_long_metrics(
+        glyphs
+            .iter()
+            .zip(&bounds)
+            .map(|(glyph, bounds)| (glyph.advance_width(), left_side_bearing(glyph, bounds))),
+    );
+
+    let names = instance_names(glyph_reader);
+
+    tables
+        .iter_mut()
+        .for_each(|(tag, data)| match tag.as_str() {
+            "glyf" => *data = glyf.clone(),
+            "loca" => *data = loca.clone(),
+            "hmtx" => *data = hmtx.clone(),
+            "cvt " => update_cvt(data, glyph_reader),
+            "head" => update_head(data, &bounds, index_to_loc_format),
+            "hhea" => update_hhea(data, glyph_reader, &glyphs, &bounds),
+            "vhea" => update_vhea(data, glyph_reader, &glyphs, &bounds),
+            "vmtx" => {
+                *data = encode_long_metrics(
+                    glyphs
+                        .iter()
+                        .map(|glyph| (glyph.advance_height(), glyph.top_side_bearing())),
+                )
+            }
+            "OS/2" => update_os2(data, glyph_reader, &glyphs),
+            "post" => {
+                vary_i16(data, 8, glyph_reader, "undo");
+                vary_i16(data, 10, glyph_reader, "unds");
+            }
+            "gasp" => update_gasp(data, glyph_reader),
+            "name" => {
+                if let Some(names) = &names {
+                    *data = rename(data, names);
+                }
+            }
+            _ => {}
+        });
+
+    let mut font_builder = FontBuilder::new();
+    tables.into_iter().for_each(|(tag, data)| {
+        font_builder.add_table(&tag, data);
+    });
+    Ok(font_builder.build())
+}
+
+// Bounds of each glyph, compound glyphs included, None for empty glyphs
+fn glyph_bounds(glyphs: &[Glyph]) -> Vec<Option<(i16, i16, i16, i16)>> {
+    let mut points_cache: HashMap<u16, Vec<(f32, f32)>> = HashMap::new();
+
+    (0..glyphs.len())
+        .map(|glyph_id| {
+            let points = glyph_points(glyphs, glyph_id as u16, &mut points_cache, 0);
+            if points.is_empty() {
+                None
+            } else {
+                Some(points.iter().fold(
+                    (i16::MAX, i16::MAX, i16::MIN, i16::MIN),
+                    |(x_min, y_min, x_max, y_max), (x, y)| {
+                        let (x, y) = (x.round() as i16, y.round() as i16);
+                        (x_min.min(x), y_min.min(y), x_max.max(x), y_max.max(y))
+                    },
+                ))
+            }
+        })
+        .collect()
+}
+
+const MAX_COMPONENT_DEPTH: u8 = 16;
+
+fn glyph_points(
+    glyphs: &[Glyph],
+    glyph_id: u16,
+    points_cache: &mut HashMap<u16, Vec<(f32, f32)>>,
+    depth: u8,
+) -> Vec<(f32, f32)> {
+    if let Some(points) = points_cache.get(&glyph_id) {
+        return points.clone();
+    }
+
+    let points: Vec<(f32, f32)> = match glyphs.get(glyph_id as usize) {
+        Some(Glyph::Simple { contours, .. }) => contours
+            .iter()
+            .flat_map(|contour| contour.points.iter().map(|p| (p.x as f32, p.y as f32)))
+            .collect(),
+        Some(Glyph::Compound { components, .. }) if depth < MAX_COMPONENT_DEPTH => components
+            .iter()
+            .flat_map(|component| {
+                let (a, b, c, d) = component.transform();
+                let (e, f) = match component.argument_types {
+                    ArgumentTypes::XYValue16(x, y) => (x as f32, y as f32),
+                    ArgumentTypes::XYValue8(x, y) => (x as f32, y as f32),
+                    // Anchor point matching is not resolved, the component is left in place
+                    _ => (0.0, 0.0),
+                };
+                glyph_points(glyphs, component.glyph_index, points_cache, depth + 1)
+                    .into_iter()
+                    .map(move |(x, y)| (a * x + c * y + e, b * x + d * y + f))
+            })
+            .collect(),
+        _ => vec![],
+    };
+
+    points_cache.insert(glyph_id, points.clone());
+    points
+}
+
+// Side bearings are written so that the outline starts at x_min, as glyf fonts expect
+fn left_side_bearing(glyph: &Glyph, bounds: &Option<(i16, i16, i16, i16)>) -> i16 {
+    match bounds {
+        Some((x_min, _, _, _)) => *x_min,
+        None => glyph.left_side_bearing(),
+    }
+}
+
+fn encode_long_metrics(metrics: impl Iterator<Item = (u16, i16)>) -> Vec<u8> {
+    let mut data: Vec<u8> = vec![];
+    metrics.for_each(|(advance, side_bearing)| {
+        data.extend_from_slice(&advance.to_be_bytes());
+        data.extend_from_slice(&side_bearing.to_be_bytes());
+    });
+    data
+}
+
+fn union_bounds(bounds: &[Option<(i16, i16, i16, i16)>]) -> (i16, i16, i16, i16) {
+    bounds
+        .iter()
+        .flatten()
+        .fold(None, |union, (x_min, y_min, x_max, y_max)| match union {
+            None => Some((*x_min, *y_min, *x_max, *y_max)),
+            Some((u_x_min, u_y_min, u_x_max, u_y_max)) => Some((
+                u_x_min.min(*x_min),
+                u_y_min.min(*y_min),
+                u_x_max.max(*x_max),
+                u_y_max.max(*y_max),
+            )),
+        })
+        .unwrap_or((0, 0, 0, 0))
+}
+
+fn update_head(head: &mut [u8], bounds: &[Option<(i16, i16, i16, i16)>], index_to_loc_format: i16) {
+    let (x_min, y_min, x_max, y_max) = union_bounds(bounds);
+    write_i16(head, 36, x_min);
+    write_i16(head, 38, y_min);
+    write_i16(head, 40, x_max);
+    write_i16(head, 42, y_max);
+    write_i16(head, 50, index_to_loc_format);
+}
+
+fn update_hhea(
+    hhea: &mut [u8],
+    glyph_reader: &GlyphReader,
+    glyphs: &[Glyph],
+    bounds: &[Option<(i16, i16, i16, i16)>],
+) {
+    let advance_width_max = glyphs.iter().map(|g| g.advance_width()).max().unwrap_or(0);
+    let outlines: Vec<(i32, i32, i32)> = glyphs
+        .iter()
+        .zip(bounds)
+        .filter_map(|(glyph, bounds)| {
+            bounds.map(|(x_min, _, x_max, _)| {
+                let lsb = x_min as i32;
+                let extent = lsb + (x_max as i32 - x_min as i32);
+                (lsb, glyph.advance_width() as i32 - extent, extent)
+            })
+        })
+        .collect();
+
+    write_u16(hhea, 10, advance_width_max);
+    write_i16(hhea, 12, min_of(outlines.iter().map(|o| o.0)));
+    write_i16(hhea, 14, min_of(outlines.iter().map(|o| o.1)));
+    write_i16(hhea, 16, max_of(outlines.iter().map(|o| o.2)));
+    vary_i16(hhea, 18, glyph_reader, "hcrs");
+    vary_i16(hhea, 20, glyph_reader, "hcrn");
+    vary_i16(hhea, 22, glyph_reader, "hcof");
+    write_u16(hhea, 34, glyphs.len() as u16);
+}
+
+fn update_vhea(
+    vhea: &mut [u8],
+    glyph_reader: &GlyphReader,
+    glyphs: &[Glyph],
+    bounds: &[Option<(i16, i16, i16, i16)>],
+) {
+    let advance_height_max = glyphs.iter().map(|g| g.advance_height()).max().unwrap_or(0);
+    let outlines: Vec<(i32, i32, i32)> = glyphs
+        .iter()
+        .zip(bounds)
+        .filter_map(|(glyph, bounds)| {
+            bounds.map(|(_, y_min, _, y_max)| {
+                let tsb = glyph.top_side_bearing() as i32;
+                let extent = tsb + (y_max as i32 - y_min as i32);
+                (tsb, glyph.advance_height() as i32 - extent, extent)
+            })
+        })
+        .collect();
+
+    vary_i16(vhea, 4, glyph_reader, "vasc");
+    vary_i16(vhea, 6, glyph_reader, "vdsc");
+    vary_i16(vhea, 8, glyph_reader, "vlgp");
+    write_u16(vhea, 10, advance_height_max);
+    write_i16(vhea, 12, min_of(outlines.iter().map(|o| o.0)));
+    write_i16(vhea, 14, min_of(outlines.iter().map(|o| o.1)));
+    write_i16(vhea, 16, max_of(outlines.iter().map(|o| o.2)));
+    vary_i16(vhea, 18, glyph_reader, "vcrs");
+    vary_i16(vhea, 20, glyph_reader, "vcrn");
+    vary_i16(vhea, 22, glyph_reader, "vcof");
+    write_u16(vhea, 34, glyphs.len() as u16);
+}
+
+fn update_os2(os2: &mut [u8], glyph_reader: &GlyphReader, glyphs: &[Glyph]) {
+    // Average of the non zero advances, as defined since OS/2 version 3
+    let advances: Vec<u32> = glyphs
+        .iter()
+        .map(|glyph| glyph.advance_width() as u32)
+        .filter(|advance| *advance > 0)
+        .collect();
+    if !advances.is_empty() {
+        let average = advances.iter().sum::<u32>() as f32 / advances.len() as f32;
+        write_i16(os2, 2, average.round() as i16);
+    }
+
+    let axis_value = |tag: &str| {
+        glyph_reader
+            .variation_axes()
+            .iter()
+            .zip(glyph_reader.variation_coordinates())
+            .find(|(axis, _)| axis.tag == tag)
+            .map(|(_, value)| *value)
+    };
+    if let Some(weight) = axis_value("wght") {
+        write_u16(os2, 4, weight.round().clamp(1.0, 1000.0) as u16);
+    }
+    if let Some(width) = axis_value("wdth") {
+        write_u16(os2, 6, width_class(width));
+    }
+
+    [
+        (10, "sbxs"),
+        (12, "sbys"),
+        (14, "sbxo"),
+        (16, "sbyo"),
+        (18, "spxs"),
+        (20, "spys"),
+        (22, "spxo"),
+        (24, "spyo"),
+        (26, "strs"),
+        (28, "stro"),
+        (68, "hasc"),
+        (70, "hdsc"),
+        (72, "hlgp"),
+        (74, "hcla"),
+        (76, "hcld"),
+        (86, "xhgt"),
+        (88, "cpht"),
+    ]
+    .iter()
+    .for_each(|(offset, tag)| vary_i16(os2, *offset, glyph_reader, tag));
+}
+
+// usWidthClass closest to a wdth axis value, given in percent of the normal width
+fn width_class(width: f32) -> u16 {
+    let widths = [50.0, 62.5, 75.0, 87.5, 100.0, 112.5, 125.0, 150.0, 200.0];
+    widths
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| (width - **a).abs().total_cmp(&(width - **b).abs()))
+        .map_or(5, |(index, _)| index as u16 + 1)
+}
+
+fn update_gasp(gasp: &mut [u8], glyph_reader: &GlyphReader) {
+    if gasp.len() < 4 {
+        return;
+    }
+    let num_ranges = read_u16(gasp, 2) as usize;
+    (0..num_ranges.min(10)).for_each(|index| {
+        let tag = format!("gsp{}", index);
+        let offset = 4 + index * 4;
+        if offset + 2 <= gasp.len() {
+            let range_max_ppem = read_u16(gasp, offset) as f32;
+            let varied = range_max_ppem + glyph_reader.metric_variation(&tag);
+            write_u16(
+                gasp,
+                offset,
+                varied.round().clamp(0.0, u16::MAX as f32) as u16,
+            );
+        }
+    });
+}
+
+// The control values varied by cvar, the instructions of the static font read
+// them at this location
+fn update_cvt(cvt: &mut [u8], glyph_reader: &GlyphReader) {
+    if let (Some(cvt_table), Some(cvar_table)) =
+        (glyph_reader.cvt_table(), glyph_reader.cvar_table())
+    {
+        let values =
+            cvar_table.varied_values(&cvt_table.values, glyph_reader.normalized_coordinates());
+        values
+            .iter()
+            .enumerate()
+            .for_each(|(index, value)| write_i16(cvt, index * 2, *value));
+    }
+}
+
+// Family and subfamily names of the instance, from the named instance at this
+// location or from STAT. None when neither describes it.
+struct InstanceNames {
+    family: String,
+    subfamily: String,
+    post_script_name: String,
+}
+
+fn instance_names(glyph_reader: &mut GlyphReader) -> Option<InstanceNames> {
+    let coordinates = glyph_reader.variation_coordinates().to_vec();
+    let named_instance = glyph_reader
+        .named_instances()
+        .iter()
+        .find(|instance| {
+            instance
+                .coordinates
+                .iter()
+                .zip(&coordinates)
+                .all(|(a, b)| (a - b).abs() < 0.001)
+        })
+        .map(|instance| (instance.subfamily_name_id, instance.post_script_name_id));
+
+    let (subfamily, post_script_name) = match named_instance {
+        Some((subfamily_name_id, post_script_name_id)) => (
+            glyph_reader.name(subfamily_name_id)?,
+            post_script_name_id.and_then(|name_id| glyph_reader.name(name_id)),
+        ),
+        None => (glyph_reader.style_name()?, None),
+    };
+
+    let family = glyph_reader.name(16).or_else(|| glyph_reader.name(1))?;
+    let post_script_name = post_script_name.unwrap_or_else(|| {
+        let prefix = glyph_reader
+            .name(25)
+            .unwrap_or_else(|| family.replace(' ', ""));
+        format!("{}-{}", prefix, subfamily.replace(' ', ""))
+    });
+
+    Some(InstanceNames {
+        family,
+        subfamily,
+        post_script_name,
+    })
+}
+
+// Rewrites the family, subfamily, full and PostScript names of every platform
+// and language that has a family name, as a format 0 name table.
+fn rename(name: &[u8], names: &InstanceNames) -> Vec<u8> {
+    let count = read_u16(name, 2) as usize;
+    let string_offset = read_u16(name, 4) as usize;
+
+    let mut records: Vec<(u16, u16, u16, u16, Vec<u8>)> = (0..count)
+        .map(|index| {
+            let record = 6 + index * 12;
+            let offset = string_offset + read_u16(name, record + 10) as usize;
+            let length = read_u16(name, record + 8) as usize;
+            (
+                read_u16(name, record),
+                read_u16(name, record + 2),
+                read_u16(name, record + 4),
+                read_u16(name, record + 6),
+                name[offset..offset + length].to_vec(),
+            )
+        })
+        .collect();
+
+    let is_ribbi = RIBBI_SUBFAMILIES.contains(&names.subfamily.as_str());
+    let full_name = format!("{} {}", names.family, names.subfamily);
+    let new_names: Vec<(u16, &str)> = if is_ribbi {
+        vec![
+            (1, &names.family),
+            (2, &names.subfamily),
+            (3, &names.post_script_name),
+            (4, &full_name),
+            (6, &names.post_script_name),
+        ]
+    } else {
+        vec![
+            (1, &full_name),
+            (2, "Regular"),
+            (3, &names.post_script_name),
+            (4, &full_name),
+            (6, &names.post_script_name),
+            (16, &names.family),
+            (17, &names.subfamily),
+        ]
+    };
+
+    let languages: Vec<(u16, u16, u16)> = records
+        .iter()
+        .filter(|record| record.3 == 1)
+        .map(|(platform_id, encoding_id, language_id, _, _)| {
+            (*platform_id, *encoding_id, *language_id)
+        })
+        .collect();
+
+    records.retain(|(platform_id, encoding_id, language_id, name_id, _)| {
+        let renamed = [1, 2, 3, 4, 6, 16, 17, 25].contains(name_id);
+        !(renamed && languages.contains(&(*platform_id, *encoding_id, *language_id)))
+    });
+    languages
+        .iter()
+        .for_each(|(platform_id, encoding_id, language_id)| {
+            new_names.iter().for_each(|(name_id, value)| {
+                // Macintosh records are single byte, the others UTF-16BE
+                let string: Vec<u8> = if *platform_id == 1 {
+                    value
+                        .chars()
+                        .map(|c| if c.is_ascii() { c as u8 } else { b'?' })
+                        .collect()
+                } else {
+                    value
+                        .encode_utf16()
+                        .flat_map(|unit| unit.to_be_bytes())
+                        .collect()
+                };
+                records.push((*platform_id, *encoding_id, *language_id, *name_id, string));
+            });
+        });
+    records.sort_by_key(|(platform_id, encoding_id, language_id, name_id, _)| {
+        (*platform_id, *encoding_id, *language_id, *name_id)
+    });
+
+    let mut data: Vec<u8> = vec![];
+    let mut strings: Vec<u8> = vec![];
+    [0u16, records.len() as u16, 6 + 12 * records.len() as u16]
+        .iter()
+        .for_each(|value| data.extend_from_slice(&value.to_be_bytes()));
+    records
+        .iter()
+        .for_each(|(platform_id, encoding_id, language_id, name_id, string)| {
+            [
+                *platform_id,
+                *encoding_id,
+                *language_id,
+                *name_id,
+                string.len() as u16,
+                strings.len() as u16,
+            ]
+            .iter()
+            .for_each(|value| data.extend_from_slice(&value.to_be_bytes()));
+            strings.extend_from_slice(string);
+        });
+    data.extend(strings);
+    data
+}
+
+fn vary_i16(data: &mut [u8], offset: usize, glyph_reader: &GlyphReader, tag: &str) {
+    if offset + 2 <= data.len() {
+        let value = read_u16(data, offset) as i16 as f32 + glyph_reader.metric_variation(tag);
+        write_i16(data, offset, value.round() as i16);
+    }
+}
+
+fn min_of(values: impl Iterator<Item = i32>) -> i16 {
+    values
+        .min()
+        .unwrap_or(0)
+        .clamp(i16::MIN as i32, i16::MAX as i32) as i16
+}
+
+fn max_of(values: impl Iterator<Item = i32>) -> i16 {
+    values
+        .max()
+        .unwrap_or(0)
+        .clamp(i16::MIN as i32, i16::MAX as i32) as i16
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+fn write_u16(data: &mut [u8], offset: usize, value: u16) {
+    if offset + 2 <= data.len() {
+        data[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn write_i16(data: &mut [u8], offset: usize, value: i16) {
+    write_u16(data, offset, value as u16);
+}
@@ -0,0 +1,225 @@
+use crate::reader::Reader;
+
+// Shared by HVAR, VVAR, MVAR, GDEF and CFF2
+// https://learn.microsoft.com/en-us/typography/opentype/spec/otvarcommonformats#item-variation-store
+
+const LONG_WORDS: u16 = 0x8000;
+const WORD_DELTA_COUNT_MASK: u16 = 0x7FFF;
+
+const INNER_INDEX_BIT_COUNT_MASK: u8 = 0x0F;
+const MAP_ENTRY_SIZE_MASK: u8 = 0x30;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionAxisCoordinates {
+    pub start_coord: f32,
+    pub peak_coord: f32,
+    pub end_coord: f32,
+}
+
+impl RegionAxisCoordinates {
+    fn scalar(&self, coordinate: f32) -> f32 {
+        let (start, peak, end) = (self.start_coord, self.peak_coord, self.end_coord);
+
+        if start > peak || peak > end || (start < 0.0 && end > 0.0) || peak == 0.0 {
+            // Invalid or axis independent region
+            1.0
+        } else if coordinate < start || coordinate > end {
+            0.0
+        } else if coordinate == peak {
+            1.0
+        } else if coordinate < peak {
+            (coordinate - start) / (peak - start)
+        } else {
+            (end - coordinate) / (end - peak)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ItemVariationData {
+    pub region_indexes: Vec<u16>,
+    pub delta_sets: Vec<Vec<i32>>, // one delta per region index, for each item
+}
+
+#[derive(Debug)]
+pub struct ItemVariationStore {
+    pub variation_regions: Vec<Vec<RegionAxisCoordinates>>,
+    pub item_variation_data: Vec<Option<ItemVariationData>>,
+}
+
+impl ItemVariationStore {
+    pub fn from_file(reader: &mut Box<dyn Reader>, offset: u32) -> ItemVariationStore {
+        reader.seek_from_start(offset);
+        let _format = reader.read_u16();
+        let variation_region_list_offset = reader.read_u32();
+        let item_variation_data_count = reader.read_u16();
+        let item_variation_data_offsets: Vec<u32> = (0..item_variation_data_count)
+            .map(|_| reader.read_u32())
+            .collect();
+
+        reader.seek_from_start(offset + variation_region_list_offset);
+        let axis_count = reader.read_u16();
+        let region_count = reader.read_u16();
+        let variation_regions: Vec<Vec<RegionAxisCoordinates>> = (0..region_count)
+            .map(|_| {
+                (0..axis_count)
+                    .map(|_| RegionAxisCoordinates {
+                        start_coord: reader.read_f2dot14(),
+                        peak_coord: reader.read_f2dot14(),
+                        end_coord: reader.read_f2dot14(),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let item_variation_data = item_variation_data_offsets
+            .iter()
+            .map(|data_offset| {
+                // A null offset leaves a hole in the outer indices
+                if *data_offset == 0 {
+                    return None;
+                }
+                reader.seek_from_start(offset + data_offset);
+                Some(Self::read_item_variation_data(reader))
+            })
+            .collect();
+
+        ItemVariationStore {
+            variation_regions,
+            item_variation_data,
+        }
+    }
+
+    fn read_item_variation_data(reader: &mut Box<dyn Reader>) -> ItemVariationData {
+        let item_count = reader.read_u16();
+        let word_delta_count = reader.read_u16();
+        let region_index_count = reader.read_u16();
+        let region_indexes: Vec<u16> = (0..region_index_count).map(|_| reader.read_u16()).collect();
+
+        let long_words = word_delta_count & LONG_WORDS != 0;
+        let word_count = word_delta_count & WORD_DELTA_COUNT_MASK;
+
+        let delta_sets: Vec<Vec<i32>> = (0..item_count)
+            .map(|_| {
+                (0..region_index_count)
+                    .map(|index| match (index < word_count, long_words) {
+                        (true, true) => reader.read_u32() as i32,
+                        (true, false) => reader.read_i16() as i32,
+                        (false, true) => reader.read_i16() as i32,
+                        (false, false) => reader.read_i8() as i32,
+                    })
+                    .collect()
+            })
+            .collect();
+
+        ItemVariationData {
+            region_indexes,
+            delta_sets,
+        }
+    }
+
+    // Scalar of each region at the normalized `coordinates`
+    pub fn region_scalars(&self, coordinates: &[f32]) -> Vec<f32> {
+        self.variation_regions
+            .iter()
+            .map(|region| {
+                region
+                    .iter()
+                    .enumerate()
+                    .map(|(axis_index, axis)| {
+                        axis.scalar(coordinates.get(axis_index).copied().unwrap_or(0.0))
+                    })
+                    .product()
+            })
+            .collect()
+    }
+
+    pub fn delta(&self, outer_index: u16, inner_index: u16, coordinates: &[f32]) -> f32 {
+        let region_scalars = self.region_scalars(coordinates);
+        self.delta_with_scalars(outer_index, inner_index, &region_scalars)
+    }
+
+    pub fn delta_with_scalars(
+        &self,
+        outer_index: u16,
+        inner_index: u16,
+        region_scalars: &[f32],
+    ) -> f32 {
+        let data = match self.item_variation_data.get(outer_index as usize) {
+            Some(Some(data)) => data,
+            _ => return 0.0,
+        };
+        let deltas = match data.delta_sets.get(inner_index as usize) {
+            Some(deltas) => deltas,
+            None => return 0.0,
+        };
+
+        deltas
+            .iter()
+            .zip(&data.region_indexes)
+            .map(|(delta, region_index)| {
+                let scalar = region_scalars
+                    .get(*region_index as usize)
+                    .copied()
+                    .unwrap_or(0.0);
+                *delta as f32 * scalar
+            })
+            .sum()
+    }
+}
+
+// Maps glyph ids, or other indices, to (outer, inner) indices of an item variation store
+#[derive(Debug)]
+pub struct DeltaSetIndexMap {
+    pub map_data: Vec<(u16, u16)>,
+}
+
+impl DeltaSetIndexMap {
+    pub fn from_file(reader: &mut Box<dyn Reader>, offset: u32) -> DeltaSetIndexMap {
+        reader.seek_from_start(offset);
+        let format = reader.read_u8();
+        let entry_format = reader.read_u8();
+        let map_count = if format == 0 {
+            reader.read_u16() as u32
+        } else {
+            reader.read_u32()
+        };
+
+        let inner_bit_count = (entry_format & INNER_INDEX_BIT_COUNT_MASK) as u32 + 1;
+        let entry_size = ((entry_format & MAP_ENTRY_SIZE_MASK) >> 4) + 1;
+
+        let map_data = (0..map_count)
+            .map(|_| {
+                let entry: u32 =
+                    (0..entry_size).fold(0, |entry, _| (entry << 8) | reader.read_u8() as u32);
+                let outer_index = (entry >> inner_bit_count) as u16;
+                let inner_index = (entry & ((1 << inner_bit_count) - 1)) as u16;
+                (outer_index, inner_index)
+            })
+            .collect();
+
+        DeltaSetIndexMap { map_data }
+    }
+
+    // Mappings are optional, a null offset relative to `table_offset` means there is none
+    pub fn from_optional_offset(
+        reader: &mut Box<dyn Reader>,
+        table_offset: u32,
+        mapping_offset: u32,
+    ) -> Option<DeltaSetIndexMap> {
+        if mapping_offset == 0 {
+            None
+        } else {
+            Some(Self::from_file(reader, table_offset + mapping_offset))
+        }
+    }
+
+    // Indices past the end of the map use the last entry
+    pub fn get(&self, index: u32) -> (u16, u16) {
+        self.map_data
+            .get(index as usize)
+            .or(self.map_data.last())
+            .copied()
+            .unwrap_or((0, index as u16))
+    }
+}
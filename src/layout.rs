@@ -0,0 +1,323 @@
+use crate::glyph_reader::GlyphReader;
+use crate::line_break::{break_opportunities, BreakClass, BreakOpportunity};
+use crate::model::GlyphId;
+use std::collections::HashMap;
+
+const SOFT_HYPHEN: char = '\u{00AD}';
+const HYPHEN: char = '-';
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Alignment {
+    Left,
+    Right,
+    Center,
+    Justify,
+}
+
+#[derive(Debug)]
+pub struct PositionedGlyph {
+    pub glyph_id: GlyphId,
+    pub character: char,
+    pub x: f32,       // pen position in pixels from the left edge of the box
+    pub advance: f32, // advance in pixels, including justification space
+}
+
+#[derive(Debug)]
+pub struct Line {
+    pub glyphs: Vec<PositionedGlyph>,
+    pub width: f32,    // width of the line before alignment, trailing spaces excluded
+    pub baseline: f32, // distance in pixels from the top of the box down to the baseline
+}
+
+#[derive(Debug)]
+pub struct Paragraph {
+    pub lines: Vec<Line>,
+    pub line_height: f32,
+    pub height: f32,
+}
+
+struct Item {
+    character: char,
+    glyph_id: GlyphId,
+    advance: f32,
+    class: BreakClass,
+}
+
+impl Item {
+    fn is_trailing_space(&self) -> bool {
+        matches!(
+            self.class,
+            BreakClass::Space | BreakClass::MandatoryBreak | BreakClass::ZeroWidthSpace
+        )
+    }
+
+    fn is_invisible(&self) -> bool {
+        matches!(
+            self.class,
+            BreakClass::MandatoryBreak | BreakClass::ZeroWidthSpace
+        ) || self.character == SOFT_HYPHEN
+    }
+}
+
+struct GlyphCache<'a> {
+    glyph_reader: &'a mut GlyphReader,
+    scale: f32,
+    glyph_ids: HashMap<char, GlyphId>,
+}
+
+impl<'a> GlyphCache<'a> {
+    fn item(&mut self, character: char) -> Item {
+        let glyph_id = self.glyph_id(character);
+        let advance = self.glyph_reader.advance_width(glyph_id) as f32 * self.scale;
+        Item {
+            character,
+            glyph_id,
+            advance,
+            class: BreakClass::from(character),
+        }
+    }
+
+    fn glyph_id(&mut self, character: char) -> GlyphId {
+        if let Some(glyph_id) = self.glyph_ids.get(&character) {
+            return *glyph_id;
+        }
+        // Only the Basic Multilingual Plane is reachable through the format 4 cmap subtable
+        let glyph_id = match u16::try_from(character as u32) {
+            Ok(char_code) => self.glyph_reader.char_code_to_glyph_id(char_code),
+            Err(_) => GlyphId::MISSING_CHARACTER_GLYPH,
+        };
+        self.glyph_ids.insert(character, glyph_id);
+        glyph_id
+    }
+}
+
+// Lays out `text` into lines no wider than `max_width` pixels, breaking at the
+// opportunities given by `line_break` and aligning each line inside the box.
+// Baselines are spaced by the font line height, see `FontMetrics`.
+pub fn layout_paragraph(
+    text: &str,
+    glyph_reader: &mut GlyphReader,
+    font_size: f32,
+    max_width: f32,
+    alignment: Alignment,
+) -> Paragraph {
+    let font_metrics = glyph_reader.font_metrics();
+    let scale = font_metrics.scale(font_size);
+    let scaled_metrics = font_metrics.scaled(font_size);
+    let ascent = scaled_metrics.ascender;
+    let line_height = scaled_metrics.line_height;
+
+    let chars: Vec<char> = text.chars().collect();
+    let opportunities = break_opportunities(&chars);
+
+    let mut glyph_cache = GlyphCache {
+        glyph_reader,
+        scale,
+        glyph_ids: HashMap::new(),
+    };
+    let items: Vec<Item> = chars.iter().map(|ch| glyph_cache.item(*ch)).collect();
+    let hyphen = glyph_cache.item(HYPHEN);
+
+    // Greedy first fit: break at the last opportunity seen when the line overflows,
+    // or in the middle of a word when there was none.
+    let mut ranges: Vec<(usize, usize, bool)> = vec![];
+    let mut line_start = 0;
+    let mut last_break: Option<usize> = None;
+    let mut index = 0;
+    let mut width = LineWidth::default();
+
+    while index < items.len() {
+        if index > line_start {
+            match opportunities[index] {
+                BreakOpportunity::Mandatory => {
+                    ranges.push((line_start, index, true));
+                    line_start = index;
+                    last_break = None;
+                    width = LineWidth::default();
+                }
+                BreakOpportunity::Allowed => last_break = Some(index),
+                BreakOpportunity::Prohibited => {}
+            }
+        }
+
+        width.push(&items[index], &hyphen);
+        if width.width(&hyphen) > max_width && index > line_start {
+            let break_at = last_break.unwrap_or(index);
+            ranges.push((line_start, break_at, false));
+            line_start = break_at;
+            last_break = None;
+            index = break_at;
+            width = LineWidth::default();
+        } else {
+            index += 1;
+        }
+    }
+    if line_start < items.len() || ranges.is_empty() {
+        ranges.push((line_start, items.len(), true));
+    }
+
+    let lines: Vec<Line> = ranges
+        .iter()
+        .enumerate()
+        .map(|(line_index, (start, end, is_last_of_paragraph))| {
+            let baseline = ascent + line_index as f32 * line_height;
+            let justify = alignment == Alignment::Justify && !is_last_of_paragraph;
+
+            mk_line(
+                &items, &hyphen, *start, *end, baseline, max_width, alignment, justify,
+            )
+        })
+        .collect();
+
+    let height = lines.len() as f32 * line_height;
+
+    Paragraph {
+        lines,
+        line_height,
+        height,
+    }
+}
+
+fn visible_end(items: &[Item], start: usize, end: usize) -> usize {
+    let mut end = end;
+    while end > start && items[end - 1].is_trailing_space() {
+        end -= 1;
+    }
+    end
+}
+
+fn line_width(items: &[Item], hyphen: &Item, start: usize, end: usize) -> f32 {
+    let end = visible_end(items, start, end);
+
+    items[start..end]
+        .iter()
+        .enumerate()
+        .map(|(index, item)| advance(item, hyphen, start + index + 1 == end))
+        .sum()
+}
+
+// Width of a line growing one item at a time, the same as `line_width`
+#[derive(Default)]
+struct LineWidth {
+    total: f32,       // of every item so far
+    visible: f32,     // up to the last item that is not a trailing space
+    hyphenated: bool, // that item is a soft hyphen
+}
+
+impl LineWidth {
+    fn push(&mut self, item: &Item, hyphen: &Item) {
+        self.total += advance(item, hyphen, false);
+        if !item.is_trailing_space() {
+            self.visible = self.total;
+            self.hyphenated = item.character == SOFT_HYPHEN;
+        }
+    }
+
+    fn width(&self, hyphen: &Item) -> f32 {
+        if self.hyphenated {
+            self.visible + hyphen.advance
+        } else {
+            self.visible
+        }
+    }
+}
+
+// A soft hyphen is only displayed, as a regular hyphen, when a line ends on it
+fn advance(item: &Item, hyphen: &Item, is_line_end: bool) -> f32 {
+    if item.character == SOFT_HYPHEN {
+        if is_line_end {
+            hyphen.advance
+        } else {
+            0.0
+        }
+    } else if item.is_invisible() {
+        0.0
+    } else {
+        item.advance
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn mk_line(
+    items: &[Item],
+    hyphen: &Item,
+    start: usize,
+    end: usize,
+    baseline: f32,
+    max_width: f32,
+    alignment: Alignment,
+    justify: bool,
+) -> Line {
+    let end = visible_end(items, start, end);
+    let width = line_width(items, hyphen, start, end);
+
+    let visible: Vec<&Item> = items[start..end]
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| {
+            if item.character == SOFT_HYPHEN && start + index + 1 == end {
+                Some(hyphen)
+            } else if item.is_invisible() {
+                None
+            } else {
+                Some(item)
+            }
+        })
+        .collect();
+
+    let extra = (max_width - width).max(0.0);
+
+    // Justification stretches spaces, or every gap between glyphs when there are
+    // no spaces, as in CJK text.
+    let spaces = visible
+        .iter()
+        .filter(|item| item.class == BreakClass::Space)
+        .count();
+    let gaps = if spaces > 0 {
+        spaces
+    } else {
+        visible.len().saturating_sub(1)
+    };
+    let stretch = if justify && gaps > 0 {
+        extra / gaps as f32
+    } else {
+        0.0
+    };
+
+    let mut x = match alignment {
+        Alignment::Left | Alignment::Justify => 0.0,
+        Alignment::Right => extra,
+        Alignment::Center => extra / 2.0,
+    };
+
+    let glyphs: Vec<PositionedGlyph> = visible
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let is_stretched = if spaces > 0 {
+                item.class == BreakClass::Space
+            } else {
+                index + 1 < visible.len()
+            };
+            let advance = if is_stretched {
+                item.advance + stretch
+            } else {
+                item.advance
+            };
+            let glyph = PositionedGlyph {
+                glyph_id: item.glyph_id,
+                character: item.character,
+                x,
+                advance,
+            };
+            x += advance;
+            glyph
+        })
+        .collect();
+
+    Line {
+        glyphs,
+        width,
+        baseline,
+    }
+}
@@ -0,0 +1,1377 @@
+use crate::font_builder::binary_search_header;
+use crate::subsetter::GlyphMap;
+use crate::writer::Writer;
+use std::collections::{HashMap, VecDeque};
+
+// Prunes GSUB, GPOS, GDEF and kern to the glyphs kept by a subset.
+//
+// Glyph ids are renumbered in their original order, so coverage and class
+// definitions stay sorted and coverage indexed records keep their order.
+// Lookups are never removed, a lookup that loses all its subtables stays
+// empty so that the lookup indices of features and nested lookups still hold.
+// The tables are rebuilt as a graph of subtables, then packed breadth first;
+// None when an offset no longer fits or nothing is left.
+
+const GSUB_CONTEXT: u16 = 5;
+const GSUB_CHAINED_CONTEXT: u16 = 6;
+const GSUB_EXTENSION: u16 = 7;
+const GPOS_CONTEXT: u16 = 7;
+const GPOS_CHAINED_CONTEXT: u16 = 8;
+const GPOS_EXTENSION: u16 = 9;
+
+const USE_MARK_FILTERING_SET: u16 = 0x0010;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum LayoutTable {
+    Gsub,
+    Gpos,
+}
+
+// A subtable being rebuilt, the offsets to its children are written when packed
+struct Node {
+    writer: Writer,
+    links: Vec<Link>,
+}
+
+struct Link {
+    position: usize, // of the offset, from the start of the parent
+    size: usize,     // 2 for Offset16, 4 for Offset32
+    child: Node,
+}
+
+impl Node {
+    fn new() -> Node {
+        Node {
+            writer: Writer::new(),
+            links: vec![],
+        }
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Node {
+        let mut node = Node::new();
+        node.writer.write_bytes(bytes);
+        node
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.writer.write_u16(value);
+    }
+
+    // A null offset when there is no child
+    fn offset16(&mut self, child: Option<Node>) {
+        self.link(child, 2);
+    }
+
+    fn offset32(&mut self, child: Option<Node>) {
+        self.link(child, 4);
+    }
+
+    fn link(&mut self, child: Option<Node>, size: usize) {
+        if let Some(child) = child {
+            self.links.push(Link {
+                position: self.writer.offset(),
+                size,
+                child,
+            });
+        }
+        self.writer.write_bytes(&vec![0; size]);
+    }
+}
+
+struct PackedNode {
+    data: Vec<u8>,
+    links: Vec<(usize, usize, usize)>, // position, size, index of the child
+}
+
+// Index of the parent, position and size of the offset to the child
+type ParentLink = (usize, usize, usize);
+
+// Breadth first keeps the offsets from a table to its children short. Leaves
+// with the same bytes, typically coverage tables, are shared when they come
+// after the table that refers to them.
+fn pack(root: Node) -> Option<Vec<u8>> {
+    let mut packed: Vec<PackedNode> = vec![];
+    let mut leaves: HashMap<Vec<u8>, usize> = HashMap::new();
+    let mut queue: VecDeque<(Node, Option<ParentLink>)> = VecDeque::new();
+    queue.push_back((root, None));
+
+    while let Some((node, parent)) = queue.pop_front() {
+        let data = node.writer.into_vec();
+        let shared = match (node.links.is_empty(), parent) {
+            (true, Some((parent_index, _, _))) => leaves
+                .get(&data)
+                .copied()
+                .filter(|index| *index > parent_index),
+            _ => None,
+        };
+
+        let index = shared.unwrap_or_else(|| {
+            let index = packed.len();
+            if node.links.is_empty() {
+                leaves.insert(data.clone(), index);
+            }
+            packed.push(PackedNode {
+                data,
+                links: vec![],
+            });
+            node.links.into_iter().for_each(|link| {
+                queue.push_back((link.child, Some((index, link.position, link.size))));
+            });
+            index
+        });
+
+        if let Some((parent_index, position, size)) = parent {
+            packed[parent_index].links.push((position, size, index));
+        }
+    }
+
+    let mut starts: Vec<usize> = vec![];
+    let mut length = 0;
+    packed.iter().for_each(|node| {
+        starts.push(length);
+        length += node.data.len().next_multiple_of(2);
+    });
+
+    let mut data: Vec<u8> = vec![];
+    for (index, node) in packed.iter().enumerate() {
+        let mut bytes = node.data.clone();
+        for (position, size, child) in &node.links {
+            let offset = starts[*child] - starts[index];
+            if *size == 2 {
+                let offset = u16::try_from(offset).ok()?;
+                bytes[*position..*position + 2].copy_from_slice(&offset.to_be_bytes());
+            } else {
+                let offset = u32::try_from(offset).ok()?;
+                bytes[*position..*position + 4].copy_from_slice(&offset.to_be_bytes());
+            }
+        }
+        data.extend(bytes);
+        data.resize(data.len().next_multiple_of(2), 0);
+    }
+    Some(data)
+}
+
+pub fn subset_gsub(data: &[u8], glyph_map: &GlyphMap) -> Option<Vec<u8>> {
+    subset_layout_table(LayoutTable::Gsub, data, glyph_map)
+}
+
+pub fn subset_gpos(data: &[u8], glyph_map: &GlyphMap) -> Option<Vec<u8>> {
+    subset_layout_table(LayoutTable::Gpos, data, glyph_map)
+}
+
+// FeatureVariations, found in version 1.1, are dropped: the table is written as version 1.0
+fn subset_layout_table(table: LayoutTable, data: &[u8], glyph_map: &GlyphMap) -> Option<Vec<u8>> {
+    let script_list = offset16(data, 0, 4)?;
+    let feature_list = offset16(data, 0, 6)?;
+    let lookup_list = offset16(data, 0, 8)?;
+
+    let mut node = Node::new();
+    node.u16(1);
+    node.u16(0);
+    node.offset16(Some(script_list_node(data, script_list)));
+    node.offset16(Some(feature_list_node(data, feature_list)));
+    node.offset16(Some(lookup_list_node(table, data, lookup_list, glyph_map)));
+    pack(node)
+}
+
+fn script_list_node(data: &[u8], at: usize) -> Node {
+    let count = read_u16(data, at) as usize;
+    let mut node = Node::new();
+    node.u16(count as u16);
+    (0..count).for_each(|index| {
+        let record = at + 2 + index * 6;
+        node.writer.write_bytes(&data[record..record + 4]);
+        node.offset16(offset16(data, at, record + 4).map(|script| script_node(data, script)));
+    });
+    node
+}
+
+fn script_node(data: &[u8], at: usize) -> Node {
+    let count = read_u16(data, at + 2) as usize;
+    let mut node = Node::new();
+    node.offset16(offset16(data, at, at).map(|lang_sys| lang_sys_node(data, lang_sys)));
+    node.u16(count as u16);
+    (0..count).for_each(|index| {
+        let record = at + 4 + index * 6;
+        node.writer.write_bytes(&data[record..record + 4]);
+        node.offset16(offset16(data, at, record + 4).map(|lang_sys| lang_sys_node(data, lang_sys)));
+    });
+    node
+}
+
+fn lang_sys_node(data: &[u8], at: usize) -> Node {
+    let feature_index_count = read_u16(data, at + 4) as usize;
+    Node::from_bytes(&data[at..at + 6 + feature_index_count * 2])
+}
+
+fn feature_list_node(data: &[u8], at: usize) -> Node {
+    let count = read_u16(data, at) as usize;
+    let mut node = Node::new();
+    node.u16(count as u16);
+    (0..count).for_each(|index| {
+        let record = at + 2 + index * 6;
+        let tag = &data[record..record + 4];
+        node.writer.write_bytes(tag);
+        node.offset16(
+            offset16(data, at, record + 4).map(|feature| feature_node(data, feature, tag)),
+        );
+    });
+    node
+}
+
+fn feature_node(data: &[u8], at: usize, tag: &[u8]) -> Node {
+    let lookup_index_count = read_u16(data, at + 2) as usize;
+    let mut node = Node::new();
+    node.offset16(offset16(data, at, at).and_then(|params| feature_params_node(data, params, tag)));
+    node.writer
+        .write_bytes(&data[at + 2..at + 4 + lookup_index_count * 2]);
+    node
+}
+
+// The feature parameters defined for 'size', 'ssXX' and 'cvXX', others are dropped
+fn feature_params_node(data: &[u8], at: usize, tag: &[u8]) -> Option<Node> {
+    let length = match tag {
+        b"size" => 10,
+        [b's', b's', _, _] => 4,
+        [b'c', b'v', _, _] => 14 + read_u16(data, at + 12) as usize * 3,
+        _ => return None,
+    };
+    Some(Node::from_bytes(&data[at..at + length]))
+}
+
+fn lookup_list_node(table: LayoutTable, data: &[u8], at: usize, glyph_map: &GlyphMap) -> Node {
+    let count = read_u16(data, at) as usize;
+    let mut node = Node::new();
+    node.u16(count as u16);
+    (0..count).for_each(|index| {
+        node.offset16(
+            offset16(data, at, at + 2 + index * 2)
+                .map(|lookup| lookup_node(table, data, lookup, glyph_map)),
+        );
+    });
+    node
+}
+
+// Extension subtables are unwrapped, the lookup takes the type of the extended subtables
+fn lookup_node(table: LayoutTable, data: &[u8], at: usize, glyph_map: &GlyphMap) -> Node {
+    let mut lookup_type = read_u16(data, at);
+    let lookup_flag = read_u16(data, at + 2);
+    let subtable_count = read_u16(data, at + 4) as usize;
+
+    let extension = match table {
+        LayoutTable::Gsub => GSUB_EXTENSION,
+        LayoutTable::Gpos => GPOS_EXTENSION,
+    };
+
+    let subtable_offsets: Vec<usize> = (0..subtable_count)
+        .filter_map(|index| offset16(data, at, at + 6 + index * 2))
+        .collect();
+    let is_extension = lookup_type == extension;
+    if is_extension {
+        if let Some(subtable) = subtable_offsets.first() {
+            lookup_type = read_u16(data, subtable + 2);
+        }
+    }
+
+    let subtables: Vec<Node> = subtable_offsets
+        .into_iter()
+        .filter_map(|subtable| {
+            let subtable = if is_extension {
+                subtable + read_u32(data, subtable + 4) as usize
+            } else {
+                subtable
+            };
+            match table {
+                LayoutTable::Gsub => gsub_subtable_node(lookup_type, data, subtable, glyph_map),
+                LayoutTable::Gpos => gpos_subtable_node(lookup_type, data, subtable, glyph_map),
+            }
+        })
+        .collect();
+
+    let mut node = Node::new();
+    node.u16(lookup_type);
+    node.u16(lookup_flag);
+    node.u16(subtables.len() as u16);
+    subtables
+        .into_iter()
+        .for_each(|subtable| node.offset16(Some(subtable)));
+    if lookup_flag & USE_MARK_FILTERING_SET != 0 {
+        node.u16(read_u16(data, at + 6 + subtable_count * 2));
+    }
+    node
+}
+
+fn gsub_subtable_node(
+    lookup_type: u16,
+    data: &[u8],
+    at: usize,
+    glyph_map: &GlyphMap,
+) -> Option<Node> {
+    match lookup_type {
+        1 => single_substitution_node(data, at, glyph_map),
+        2 => sequence_substitution_node(data, at, glyph_map, false),
+        3 => sequence_substitution_node(data, at, glyph_map, true),
+        4 => ligature_substitution_node(data, at, glyph_map),
+        GSUB_CONTEXT => context_node(data, at, glyph_map, false),
+        GSUB_CHAINED_CONTEXT => context_node(data, at, glyph_map, true),
+        8 => reverse_chained_substitution_node(data, at, glyph_map),
+        _ => None,
+    }
+}
+
+fn gpos_subtable_node(
+    lookup_type: u16,
+    data: &[u8],
+    at: usize,
+    glyph_map: &GlyphMap,
+) -> Option<Node> {
+    match lookup_type {
+        1 => single_positioning_node(data, at, glyph_map),
+        2 => pair_positioning_node(data, at, glyph_map),
+        3 => cursive_positioning_node(data, at, glyph_map),
+        4 | 6 => mark_to_base_positioning_node(data, at, glyph_map),
+        5 => mark_to_ligature_positioning_node(data, at, glyph_map),
+        GPOS_CONTEXT => context_node(data, at, glyph_map, false),
+        GPOS_CHAINED_CONTEXT => context_node(data, at, glyph_map, true),
+        _ => None,
+    }
+}
+
+// Written as format 1 when all the kept substitutes share the same delta
+fn single_substitution_node(data: &[u8], at: usize, glyph_map: &GlyphMap) -> Option<Node> {
+    let format = read_u16(data, at);
+    let coverage = read_coverage(data, offset16(data, at, at + 2)?);
+    let substitutes: Vec<u16> = match format {
+        1 => {
+            let delta = read_u16(data, at + 4);
+            coverage
+                .iter()
+                .map(|glyph| glyph.wrapping_add(delta))
+                .collect()
+        }
+        2 => (0..coverage.len())
+            .map(|index| read_u16(data, at + 6 + index * 2))
+            .collect(),
+        _ => return None,
+    };
+
+    let substitutions: Vec<(u16, u16)> = coverage
+        .iter()
+        .zip(substitutes)
+        .filter_map(|(glyph, substitute)| {
+            Some((glyph_map.get(*glyph)?, glyph_map.get(substitute)?))
+        })
+        .collect();
+    let (first_glyph, first_substitute) = *substitutions.first()?;
+    let delta = first_substitute.wrapping_sub(first_glyph);
+    let glyphs: Vec<u16> = substitutions.iter().map(|(glyph, _)| *glyph).collect();
+
+    let mut node = Node::new();
+    if substitutions
+        .iter()
+        .all(|(glyph, substitute)| substitute.wrapping_sub(*glyph) == delta)
+    {
+        node.u16(1);
+        node.offset16(Some(coverage_node(&glyphs)));
+        node.u16(delta);
+    } else {
+        node.u16(2);
+        node.offset16(Some(coverage_node(&glyphs)));
+        node.u16(substitutions.len() as u16);
+        substitutions
+            .iter()
+            .for_each(|(_, substitute)| node.u16(*substitute));
+    }
+    Some(node)
+}
+
+// Multiple substitutions keep a sequence only when all its glyphs are kept,
+// alternate substitutions keep the alternates left.
+fn sequence_substitution_node(
+    data: &[u8],
+    at: usize,
+    glyph_map: &GlyphMap,
+    is_alternate: bool,
+) -> Option<Node> {
+    let coverage = read_coverage(data, offset16(data, at, at + 2)?);
+    let sequences: Vec<(u16, Vec<u16>)> = coverage
+        .iter()
+        .enumerate()
+        .filter_map(|(index, glyph)| {
+            let glyph = glyph_map.get(*glyph)?;
+            let sequence = offset16(data, at, at + 6 + index * 2)?;
+            let glyphs = read_u16_array(data, sequence + 2, read_u16(data, sequence) as usize);
+            let glyphs: Vec<u16> = if is_alternate {
+                let alternates: Vec<u16> = glyphs
+                    .into_iter()
+                    .filter_map(|g| glyph_map.get(g))
+                    .collect();
+                if alternates.is_empty() {
+                    return None;
+                }
+                alternates
+            } else {
+                remap_glyphs(&glyphs, glyph_map)?
+            };
+            Some((glyph, glyphs))
+        })
+        .collect();
+    if sequences.is_empty() {
+        return None;
+    }
+
+    let glyphs: Vec<u16> = sequences.iter().map(|(glyph, _)| *glyph).collect();
+    let mut node = Node::new();
+    node.u16(1);
+    node.offset16(Some(coverage_node(&glyphs)));
+    node.u16(sequences.len() as u16);
+    sequences.iter().for_each(|(_, glyphs)| {
+        node.offset16(Some(glyph_array_node(glyphs)));
+    });
+    Some(node)
+}
+
+fn ligature_substitution_node(data: &[u8], at: usize, glyph_map: &GlyphMap) -> Option<Node> {
+    let coverage = read_coverage(data, offset16(data, at, at + 2)?);
+    let ligature_sets: Vec<(u16, Vec<Node>)> = coverage
+        .iter()
+        .enumerate()
+        .filter_map(|(index, glyph)| {
+            let glyph = glyph_map.get(*glyph)?;
+            let ligature_set = offset16(data, at, at + 6 + index * 2)?;
+            let count = read_u16(data, ligature_set) as usize;
+            let ligatures: Vec<Node> = (0..count)
+                .filter_map(|index| offset16(data, ligature_set, ligature_set + 2 + index * 2))
+                .filter_map(|ligature| {
+                    let ligature_glyph = glyph_map.get(read_u16(data, ligature))?;
+                    let component_count = read_u16(data, ligature + 2) as usize;
+                    let components =
+                        read_u16_array(data, ligature + 4, component_count.saturating_sub(1));
+                    let components = remap_glyphs(&components, glyph_map)?;
+
+                    let mut node = Node::new();
+                    node.u16(ligature_glyph);
+                    node.u16(component_count as u16);
+                    components.iter().for_each(|component| node.u16(*component));
+                    Some(node)
+                })
+                .collect();
+            if ligatures.is_empty() {
+                None
+            } else {
+                Some((glyph, ligatures))
+            }
+        })
+        .collect();
+    if ligature_sets.is_empty() {
+        return None;
+    }
+
+    let glyphs: Vec<u16> = ligature_sets.iter().map(|(glyph, _)| *glyph).collect();
+    let mut node = Node::new();
+    node.u16(1);
+    node.offset16(Some(coverage_node(&glyphs)));
+    node.u16(ligature_sets.len() as u16);
+    ligature_sets.into_iter().for_each(|(_, ligatures)| {
+        let mut ligature_set = Node::new();
+        ligature_set.u16(ligatures.len() as u16);
+        ligatures
+            .into_iter()
+            .for_each(|ligature| ligature_set.offset16(Some(ligature)));
+        node.offset16(Some(ligature_set));
+    });
+    Some(node)
+}
+
+fn reverse_chained_substitution_node(data: &[u8], at: usize, glyph_map: &GlyphMap) -> Option<Node> {
+    let coverage = read_coverage(data, offset16(data, at, at + 2)?);
+    let backtrack_count = read_u16(data, at + 4) as usize;
+    let backtrack = coverage_nodes(data, at, at + 6, backtrack_count, glyph_map)?;
+    let lookahead_position = at + 6 + backtrack_count * 2;
+    let lookahead_count = read_u16(data, lookahead_position) as usize;
+    let lookahead = coverage_nodes(data, at, lookahead_position + 2, lookahead_count, glyph_map)?;
+    let substitutes_position = lookahead_position + 4 + lookahead_count * 2;
+
+    let substitutions: Vec<(u16, u16)> = coverage
+        .iter()
+        .enumerate()
+        .filter_map(|(index, glyph)| {
+            let substitute = read_u16(data, substitutes_position + index * 2);
+            Some((glyph_map.get(*glyph)?, glyph_map.get(substitute)?))
+        })
+        .collect();
+    if substitutions.is_empty() {
+        return None;
+    }
+
+    let glyphs: Vec<u16> = substitutions.iter().map(|(glyph, _)| *glyph).collect();
+    let mut node = Node::new();
+    node.u16(1);
+    node.offset16(Some(coverage_node(&glyphs)));
+    node.u16(backtrack_count as u16);
+    backtrack
+        .into_iter()
+        .for_each(|coverage| node.offset16(Some(coverage)));
+    node.u16(lookahead_count as u16);
+    lookahead
+        .into_iter()
+        .for_each(|coverage| node.offset16(Some(coverage)));
+    node.u16(substitutions.len() as u16);
+    substitutions
+        .iter()
+        .for_each(|(_, substitute)| node.u16(*substitute));
+    Some(node)
+}
+
+// Sequence context and chained sequence context subtables, shared by GSUB and GPOS
+fn context_node(data: &[u8], at: usize, glyph_map: &GlyphMap, is_chained: bool) -> Option<Node> {
+    match read_u16(data, at) {
+        1 => {
+            let coverage = read_coverage(data, offset16(data, at, at + 2)?);
+            let rule_sets: Vec<(u16, Node)> = coverage
+                .iter()
+                .enumerate()
+                .filter_map(|(index, glyph)| {
+                    let glyph = glyph_map.get(*glyph)?;
+                    let rule_set = offset16(data, at, at + 6 + index * 2)?;
+                    Some((
+                        glyph,
+                        rule_set_node(data, rule_set, Some(glyph_map), is_chained)?,
+                    ))
+                })
+                .collect();
+            if rule_sets.is_empty() {
+                return None;
+            }
+
+            let glyphs: Vec<u16> = rule_sets.iter().map(|(glyph, _)| *glyph).collect();
+            let mut node = Node::new();
+            node.u16(1);
+            node.offset16(Some(coverage_node(&glyphs)));
+            node.u16(rule_sets.len() as u16);
+            rule_sets
+                .into_iter()
+                .for_each(|(_, rule_set)| node.offset16(Some(rule_set)));
+            Some(node)
+        }
+        2 => {
+            // Rules match classes, only the coverage and class definitions change
+            let glyphs = remap_coverage(data, offset16(data, at, at + 2)?, glyph_map);
+            if glyphs.is_empty() {
+                return None;
+            }
+            let class_def_count = if is_chained { 3 } else { 1 };
+            let count_position = at + 4 + class_def_count * 2;
+            let count = read_u16(data, count_position) as usize;
+
+            let mut node = Node::new();
+            node.u16(2);
+            node.offset16(Some(coverage_node(&glyphs)));
+            (0..class_def_count).for_each(|index| {
+                let class_def = offset16(data, at, at + 4 + index * 2);
+                node.offset16(
+                    class_def.map(|class_def| class_def_node(data, class_def, glyph_map)),
+                );
+            });
+            node.u16(count as u16);
+            (0..count).for_each(|index| {
+                let rule_set = offset16(data, at, count_position + 2 + index * 2);
+                node.offset16(
+                    rule_set.and_then(|rule_set| rule_set_node(data, rule_set, None, is_chained)),
+                );
+            });
+            Some(node)
+        }
+        3 => {
+            let mut node = Node::new();
+            node.u16(3);
+            let mut position = at + 2;
+            let (sequence_count, seq_lookup_count) = if is_chained {
+                (3, None)
+            } else {
+                let seq_lookup_count = read_u16(data, at + 4);
+                (1, Some(seq_lookup_count))
+            };
+            for _ in 0..sequence_count {
+                let count = read_u16(data, position) as usize;
+                node.u16(count as u16);
+                position += 2;
+                if let Some(seq_lookup_count) = seq_lookup_count {
+                    node.u16(seq_lookup_count);
+                    position += 2;
+                }
+                coverage_nodes(data, at, position, count, glyph_map)?
+                    .into_iter()
+                    .for_each(|coverage| node.offset16(Some(coverage)));
+                position += count * 2;
+            }
+            let seq_lookup_count = match seq_lookup_count {
+                Some(seq_lookup_count) => seq_lookup_count as usize,
+                None => {
+                    let seq_lookup_count = read_u16(data, position);
+                    node.u16(seq_lookup_count);
+                    position += 2;
+                    seq_lookup_count as usize
+                }
+            };
+            node.writer
+                .write_bytes(&data[position..position + seq_lookup_count * 4]);
+            Some(node)
+        }
+        _ => None,
+    }
+}
+
+// Rules of a rule set, their sequences hold glyphs when a `glyph_map` is
+// given, classes otherwise. Rules that refer to removed glyphs are dropped.
+fn rule_set_node(
+    data: &[u8],
+    at: usize,
+    glyph_map: Option<&GlyphMap>,
+    is_chained: bool,
+) -> Option<Node> {
+    let count = read_u16(data, at) as usize;
+    let rules: Vec<Node> = (0..count)
+        .filter_map(|index| offset16(data, at, at + 2 + index * 2))
+        .filter_map(|rule| rule_node(data, rule, glyph_map, is_chained))
+        .collect();
+    if rules.is_empty() {
+        return None;
+    }
+
+    let mut node = Node::new();
+    node.u16(rules.len() as u16);
+    rules.into_iter().for_each(|rule| node.offset16(Some(rule)));
+    Some(node)
+}
+
+// Chained rules hold backtrack, input and lookahead sequences, the input
+// sequence leaves out the first glyph, matched by the coverage
+fn rule_node(
+    data: &[u8],
+    at: usize,
+    glyph_map: Option<&GlyphMap>,
+    is_chained: bool,
+) -> Option<Node> {
+    let mut node = Node::new();
+    let mut position = at;
+    let mut seq_lookup_count: Option<usize> = None;
+
+    let sequence_count = if is_chained { 3 } else { 1 };
+    for sequence in 0..sequence_count {
+        let count = read_u16(data, position);
+        node.u16(count);
+        position += 2;
+        let is_input = !is_chained || sequence == 1;
+        let length = if is_input {
+            count.saturating_sub(1)
+        } else {
+            count
+        } as usize;
+        if !is_chained {
+            let count = read_u16(data, position);
+            node.u16(count);
+            position += 2;
+            seq_lookup_count = Some(count as usize);
+        }
+
+        let values = read_u16_array(data, position, length);
+        let values = match glyph_map {
+            Some(glyph_map) => remap_glyphs(&values, glyph_map)?,
+            None => values,
+        };
+        values.iter().for_each(|value| node.u16(*value));
+        position += length * 2;
+    }
+
+    let seq_lookup_count = seq_lookup_count.unwrap_or_else(|| {
+        let count = read_u16(data, position);
+        node.u16(count);
+        position += 2;
+        count as usize
+    });
+    node.writer
+        .write_bytes(&data[position..position + seq_lookup_count * 4]);
+    Some(node)
+}
+
+fn single_positioning_node(data: &[u8], at: usize, glyph_map: &GlyphMap) -> Option<Node> {
+    let format = read_u16(data, at);
+    let coverage = read_coverage(data, offset16(data, at, at + 2)?);
+    let value_format = read_u16(data, at + 4);
+    let kept: Vec<(usize, u16)> = coverage
+        .iter()
+        .enumerate()
+        .filter_map(|(index, glyph)| glyph_map.get(*glyph).map(|glyph| (index, glyph)))
+        .collect();
+    if kept.is_empty() {
+        return None;
+    }
+
+    let glyphs: Vec<u16> = kept.iter().map(|(_, glyph)| *glyph).collect();
+    let mut node = Node::new();
+    node.u16(format);
+    node.offset16(Some(coverage_node(&glyphs)));
+    node.u16(value_format);
+    match format {
+        1 => copy_value_record(data, at, at + 6, value_format, &mut node),
+        2 => {
+            let size = value_record_size(value_format);
+            node.u16(kept.len() as u16);
+            kept.iter().for_each(|(index, _)| {
+                copy_value_record(data, at, at + 8 + index * size, value_format, &mut node)
+            });
+        }
+        _ => return None,
+    }
+    Some(node)
+}
+
+fn pair_positioning_node(data: &[u8], at: usize, glyph_map: &GlyphMap) -> Option<Node> {
+    let format = read_u16(data, at);
+    let coverage = read_coverage(data, offset16(data, at, at + 2)?);
+    let value_format_1 = read_u16(data, at + 4);
+    let value_format_2 = read_u16(data, at + 6);
+    let size_1 = value_record_size(value_format_1);
+    let size_2 = value_record_size(value_format_2);
+
+    match format {
+        1 => {
+            let pair_sets: Vec<(u16, Node)> = coverage
+                .iter()
+                .enumerate()
+                .filter_map(|(index, glyph)| {
+                    let glyph = glyph_map.get(*glyph)?;
+                    let pair_set = offset16(data, at, at + 10 + index * 2)?;
+                    let count = read_u16(data, pair_set) as usize;
+                    let records: Vec<(u16, usize)> = (0..count)
+                        .map(|index| pair_set + 2 + index * (2 + size_1 + size_2))
+                        .filter_map(|record| {
+                            glyph_map
+                                .get(read_u16(data, record))
+                                .map(|second_glyph| (second_glyph, record))
+                        })
+                        .collect();
+                    if records.is_empty() {
+                        return None;
+                    }
+
+                    let mut node = Node::new();
+                    node.u16(records.len() as u16);
+                    records.iter().for_each(|(second_glyph, record)| {
+                        node.u16(*second_glyph);
+                        copy_value_record(data, pair_set, record + 2, value_format_1, &mut node);
+                        copy_value_record(
+                            data,
+                            pair_set,
+                            record + 2 + size_1,
+                            value_format_2,
+                            &mut node,
+                        );
+                    });
+                    Some((glyph, node))
+                })
+                .collect();
+            if pair_sets.is_empty() {
+                return None;
+            }
+
+            let glyphs: Vec<u16> = pair_sets.iter().map(|(glyph, _)| *glyph).collect();
+            let mut node = Node::new();
+            node.u16(1);
+            node.offset16(Some(coverage_node(&glyphs)));
+            node.u16(value_format_1);
+            node.u16(value_format_2);
+            node.u16(pair_sets.len() as u16);
+            pair_sets
+                .into_iter()
+                .for_each(|(_, pair_set)| node.offset16(Some(pair_set)));
+            Some(node)
+        }
+        2 => {
+            let glyphs: Vec<u16> = coverage
+                .iter()
+                .filter_map(|glyph| glyph_map.get(*glyph))
+                .collect();
+            if glyphs.is_empty() {
+                return None;
+            }
+            let class_1_count = read_u16(data, at + 12) as usize;
+            let class_2_count = read_u16(data, at + 14) as usize;
+
+            let mut node = Node::new();
+            node.u16(2);
+            node.offset16(Some(coverage_node(&glyphs)));
+            node.u16(value_format_1);
+            node.u16(value_format_2);
+            node.offset16(
+                offset16(data, at, at + 8)
+                    .map(|class_def| class_def_node(data, class_def, glyph_map)),
+            );
+            node.offset16(
+                offset16(data, at, at + 10)
+                    .map(|class_def| class_def_node(data, class_def, glyph_map)),
+            );
+            node.u16(class_1_count as u16);
+            node.u16(class_2_count as u16);
+            (0..class_1_count * class_2_count).for_each(|index| {
+                let record = at + 16 + index * (size_1 + size_2);
+                copy_value_record(data, at, record, value_format_1, &mut node);
+                copy_value_record(data, at, record + size_1, value_format_2, &mut node);
+            });
+            Some(node)
+        }
+        _ => None,
+    }
+}
+
+fn cursive_positioning_node(data: &[u8], at: usize, glyph_map: &GlyphMap) -> Option<Node> {
+    let coverage = read_coverage(data, offset16(data, at, at + 2)?);
+    let kept: Vec<(usize, u16)> = coverage
+        .iter()
+        .enumerate()
+        .filter_map(|(index, glyph)| glyph_map.get(*glyph).map(|glyph| (index, glyph)))
+        .collect();
+    if kept.is_empty() {
+        return None;
+    }
+
+    let glyphs: Vec<u16> = kept.iter().map(|(_, glyph)| *glyph).collect();
+    let mut node = Node::new();
+    node.u16(1);
+    node.offset16(Some(coverage_node(&glyphs)));
+    node.u16(kept.len() as u16);
+    kept.iter().for_each(|(index, _)| {
+        let record = at + 6 + index * 4;
+        node.offset16(offset16(data, at, record).map(|anchor| anchor_node(data, anchor)));
+        node.offset16(offset16(data, at, record + 2).map(|anchor| anchor_node(data, anchor)));
+    });
+    Some(node)
+}
+
+// Mark-to-base and mark-to-mark attachment share the same layout
+fn mark_to_base_positioning_node(data: &[u8], at: usize, glyph_map: &GlyphMap) -> Option<Node> {
+    let mark_coverage = read_coverage(data, offset16(data, at, at + 2)?);
+    let base_coverage = read_coverage(data, offset16(data, at, at + 4)?);
+    let mark_class_count = read_u16(data, at + 6) as usize;
+    let mark_array = offset16(data, at, at + 8)?;
+    let base_array = offset16(data, at, at + 10)?;
+
+    let marks = kept_indices(&mark_coverage, glyph_map);
+    let bases = kept_indices(&base_coverage, glyph_map);
+    if marks.is_empty() || bases.is_empty() {
+        return None;
+    }
+
+    let mut base_array_node = Node::new();
+    base_array_node.u16(bases.len() as u16);
+    bases.iter().for_each(|(index, _)| {
+        let record = base_array + 2 + index * mark_class_count * 2;
+        (0..mark_class_count).for_each(|class| {
+            let anchor = offset16(data, base_array, record + class * 2);
+            base_array_node.offset16(anchor.map(|anchor| anchor_node(data, anchor)));
+        });
+    });
+
+    let mut node = Node::new();
+    node.u16(1);
+    node.offset16(Some(coverage_node(&glyphs_of(&marks))));
+    node.offset16(Some(coverage_node(&glyphs_of(&bases))));
+    node.u16(mark_class_count as u16);
+    node.offset16(Some(mark_array_node(data, mark_array, &marks)));
+    node.offset16(Some(base_array_node));
+    Some(node)
+}
+
+fn mark_to_ligature_positioning_node(data: &[u8], at: usize, glyph_map: &GlyphMap) -> Option<Node> {
+    let mark_coverage = read_coverage(data, offset16(data, at, at + 2)?);
+    let ligature_coverage = read_coverage(data, offset16(data, at, at + 4)?);
+    let mark_class_count = read_u16(data, at + 6) as usize;
+    let mark_array = offset16(data, at, at + 8)?;
+    let ligature_array = offset16(data, at, at + 10)?;
+
+    let marks = kept_indices(&mark_coverage, glyph_map);
+    let ligatures = kept_indices(&ligature_coverage, glyph_map);
+    if marks.is_empty() || ligatures.is_empty() {
+        return None;
+    }
+
+    let mut ligature_array_node = Node::new();
+    ligature_array_node.u16(ligatures.len() as u16);
+    ligatures.iter().for_each(|(index, _)| {
+        let ligature_attach = offset16(data, ligature_array, ligature_array + 2 + index * 2);
+        ligature_array_node.offset16(ligature_attach.map(|ligature_attach| {
+            let component_count = read_u16(data, ligature_attach) as usize;
+            let mut node = Node::new();
+            node.u16(component_count as u16);
+            (0..component_count * mark_class_count).for_each(|anchor_index| {
+                let anchor = offset16(
+                    data,
+                    ligature_attach,
+                    ligature_attach + 2 + anchor_index * 2,
+                );
+                node.offset16(anchor.map(|anchor| anchor_node(data, anchor)));
+            });
+            node
+        }));
+    });
+
+    let mut node = Node::new();
+    node.u16(1);
+    node.offset16(Some(coverage_node(&glyphs_of(&marks))));
+    node.offset16(Some(coverage_node(&glyphs_of(&ligatures))));
+    node.u16(mark_class_count as u16);
+    node.offset16(Some(mark_array_node(data, mark_array, &marks)));
+    node.offset16(Some(ligature_array_node));
+    Some(node)
+}
+
+fn mark_array_node(data: &[u8], at: usize, marks: &[(usize, u16)]) -> Node {
+    let mut node = Node::new();
+    node.u16(marks.len() as u16);
+    marks.iter().for_each(|(index, _)| {
+        let record = at + 2 + index * 4;
+        node.u16(read_u16(data, record));
+        node.offset16(offset16(data, at, record + 2).map(|anchor| anchor_node(data, anchor)));
+    });
+    node
+}
+
+// Format 3 anchors carry device tables, relative to the anchor
+fn anchor_node(data: &[u8], at: usize) -> Node {
+    match read_u16(data, at) {
+        2 => Node::from_bytes(&data[at..at + 8]),
+        3 => {
+            let mut node = Node::from_bytes(&data[at..at + 6]);
+            node.offset16(offset16(data, at, at + 6).map(|device| device_node(data, device)));
+            node.offset16(offset16(data, at, at + 8).map(|device| device_node(data, device)));
+            node
+        }
+        _ => Node::from_bytes(&data[at..at + 6]),
+    }
+}
+
+fn value_record_size(value_format: u16) -> usize {
+    (value_format & 0x00FF).count_ones() as usize * 2
+}
+
+// Copies a value record, the offsets to its device tables are relative to `base`
+fn copy_value_record(data: &[u8], base: usize, at: usize, value_format: u16, node: &mut Node) {
+    let mut position = at;
+    (0..8)
+        .filter(|bit| value_format & (1 << bit) != 0)
+        .for_each(|bit| {
+            if bit < 4 {
+                node.u16(read_u16(data, position));
+            } else {
+                node.offset16(
+                    offset16(data, base, position).map(|device| device_node(data, device)),
+                );
+            }
+            position += 2;
+        });
+}
+
+// Device tables, or the VariationIndex tables of variable fonts
+fn device_node(data: &[u8], at: usize) -> Node {
+    let start_size = read_u16(data, at) as usize;
+    let end_size = read_u16(data, at + 2) as usize;
+    let length = match read_u16(data, at + 4) {
+        format @ 1..=3 => {
+            let bits_per_value = 1 << format;
+            let count = end_size.saturating_sub(start_size) + 1;
+            6 + (count * bits_per_value).div_ceil(16) * 2
+        }
+        _ => 6,
+    };
+    Node::from_bytes(&data[at..at + length])
+}
+
+pub fn read_coverage(data: &[u8], at: usize) -> Vec<u16> {
+    let count = read_u16(data, at + 2) as usize;
+    match read_u16(data, at) {
+        1 => read_u16_array(data, at + 4, count),
+        2 => (0..count)
+            .flat_map(|index| {
+                let range = at + 4 + index * 6;
+                read_u16(data, range)..=read_u16(data, range + 2)
+            })
+            .collect(),
+        format => panic!("Unsupported coverage format: {}", format),
+    }
+}
+
+// Format 2 when ranges of consecutive glyphs make it smaller
+fn coverage_node(glyphs: &[u16]) -> Node {
+    let ranges = glyph_ranges(glyphs.iter().map(|glyph| (*glyph, 0)));
+    let mut node = Node::new();
+    if ranges.len() * 6 < glyphs.len() * 2 {
+        node.u16(2);
+        node.u16(ranges.len() as u16);
+        let mut coverage_index: u16 = 0;
+        ranges.iter().for_each(|(start, end, _)| {
+            node.u16(*start);
+            node.u16(*end);
+            node.u16(coverage_index);
+            coverage_index += end - start + 1;
+        });
+    } else {
+        node.u16(1);
+        node.u16(glyphs.len() as u16);
+        glyphs.iter().for_each(|glyph| node.u16(*glyph));
+    }
+    node
+}
+
+fn remap_coverage(data: &[u8], at: usize, glyph_map: &GlyphMap) -> Vec<u16> {
+    read_coverage(data, at)
+        .into_iter()
+        .filter_map(|glyph| glyph_map.get(glyph))
+        .collect()
+}
+
+// The coverage tables of a context rule, None when one of them is left empty
+// since the rule can no longer match
+fn coverage_nodes(
+    data: &[u8],
+    base: usize,
+    at: usize,
+    count: usize,
+    glyph_map: &GlyphMap,
+) -> Option<Vec<Node>> {
+    (0..count)
+        .map(|index| {
+            let glyphs = remap_coverage(data, offset16(data, base, at + index * 2)?, glyph_map);
+            if glyphs.is_empty() {
+                None
+            } else {
+                Some(coverage_node(&glyphs))
+            }
+        })
+        .collect()
+}
+
+// (glyph, class) pairs, glyphs missing are in class 0
+fn read_class_def(data: &[u8], at: usize) -> Vec<(u16, u16)> {
+    match read_u16(data, at) {
+        1 => {
+            let start_glyph = read_u16(data, at + 2);
+            let count = read_u16(data, at + 4) as usize;
+            read_u16_array(data, at + 6, count)
+                .into_iter()
+                .enumerate()
+                .map(|(index, class)| (start_glyph.wrapping_add(index as u16), class))
+                .collect()
+        }
+        2 => {
+            let count = read_u16(data, at + 2) as usize;
+            (0..count)
+                .flat_map(|index| {
+                    let range = at + 4 + index * 6;
+                    let class = read_u16(data, range + 4);
+                    (read_u16(data, range)..=read_u16(data, range + 2))
+                        .map(move |glyph| (glyph, class))
+                })
+                .collect()
+        }
+        format => panic!("Unsupported class definition format: {}", format),
+    }
+}
+
+// Format 1 when an array from the first to the last classified glyph is smaller
+fn class_def_node(data: &[u8], at: usize, glyph_map: &GlyphMap) -> Node {
+    let classes: Vec<(u16, u16)> = read_class_def(data, at)
+        .into_iter()
+        .filter(|(_, class)| *class != 0)
+        .filter_map(|(glyph, class)| glyph_map.get(glyph).map(|glyph| (glyph, class)))
+        .collect();
+    let ranges = glyph_ranges(classes.iter().copied());
+
+    let mut node = Node::new();
+    match (classes.first(), classes.last()) {
+        (Some((first, _)), Some((last, _)))
+            if (last - first + 1) as usize * 2 + 2 <= ranges.len() * 6 =>
+        {
+            node.u16(1);
+            node.u16(*first);
+            node.u16(last - first + 1);
+            let mut classes = classes.iter().peekable();
+            (*first..=*last).for_each(|glyph| {
+                let class = classes
+                    .next_if(|(g, _)| *g == glyph)
+                    .map_or(0, |(_, class)| *class);
+                node.u16(class);
+            });
+        }
+        _ => {
+            node.u16(2);
+            node.u16(ranges.len() as u16);
+            ranges.iter().for_each(|(start, end, class)| {
+                node.u16(*start);
+                node.u16(*end);
+                node.u16(*class);
+            });
+        }
+    }
+    node
+}
+
+// Runs of consecutive glyphs with the same value, as (start, end, value)
+fn glyph_ranges(glyphs: impl Iterator<Item = (u16, u16)>) -> Vec<(u16, u16, u16)> {
+    let mut ranges: Vec<(u16, u16, u16)> = vec![];
+    glyphs.for_each(|(glyph, value)| match ranges.last_mut() {
+        Some((_, end, range_value))
+            if end.checked_add(1) == Some(glyph) && *range_value == value =>
+        {
+            *end = glyph
+        }
+        _ => ranges.push((glyph, glyph, value)),
+    });
+    ranges
+}
+
+fn kept_indices(coverage: &[u16], glyph_map: &GlyphMap) -> Vec<(usize, u16)> {
+    coverage
+        .iter()
+        .enumerate()
+        .filter_map(|(index, glyph)| glyph_map.get(*glyph).map(|glyph| (index, glyph)))
+        .collect()
+}
+
+fn glyphs_of(kept: &[(usize, u16)]) -> Vec<u16> {
+    kept.iter().map(|(_, glyph)| *glyph).collect()
+}
+
+fn glyph_array_node(glyphs: &[u16]) -> Node {
+    let mut node = Node::new();
+    node.u16(glyphs.len() as u16);
+    glyphs.iter().for_each(|glyph| node.u16(*glyph));
+    node
+}
+
+// None as soon as one of the glyphs is not kept
+fn remap_glyphs(glyphs: &[u16], glyph_map: &GlyphMap) -> Option<Vec<u16>> {
+    glyphs.iter().map(|glyph| glyph_map.get(*glyph)).collect()
+}
+
+// Version 1.3 is kept with its item variation store, used by the device
+// tables of GPOS in variable fonts
+pub fn subset_gdef(data: &[u8], glyph_map: &GlyphMap) -> Option<Vec<u8>> {
+    let minor_version = read_u16(data, 2);
+
+    let mut node = Node::new();
+    node.u16(1);
+    node.u16(minor_version);
+    node.offset16(offset16(data, 0, 4).map(|class_def| class_def_node(data, class_def, glyph_map)));
+    node.offset16(
+        offset16(data, 0, 6).and_then(|attach_list| attach_list_node(data, attach_list, glyph_map)),
+    );
+    node.offset16(
+        offset16(data, 0, 8)
+            .and_then(|lig_caret_list| lig_caret_list_node(data, lig_caret_list, glyph_map)),
+    );
+    node.offset16(
+        offset16(data, 0, 10).map(|class_def| class_def_node(data, class_def, glyph_map)),
+    );
+    if minor_version >= 2 {
+        node.offset16(offset16(data, 0, 12).map(|mark_glyph_sets| {
+            let count = read_u16(data, mark_glyph_sets + 2) as usize;
+            let mut node = Node::new();
+            node.u16(1);
+            node.u16(count as u16);
+            (0..count).for_each(|index| {
+                // Mark glyph sets are referred to by index, empty ones are kept
+                let coverage =
+                    mark_glyph_sets + read_u32(data, mark_glyph_sets + 4 + index * 4) as usize;
+                node.offset32(Some(coverage_node(&remap_coverage(
+                    data, coverage, glyph_map,
+                ))));
+            });
+            node
+        }));
+    }
+    if minor_version >= 3 {
+        let item_variation_store = read_u32(data, 14) as usize;
+        node.offset32(
+            (item_variation_store != 0)
+                .then(|| item_variation_store_node(data, item_variation_store)),
+        );
+    }
+    pack(node)
+}
+
+fn attach_list_node(data: &[u8], at: usize, glyph_map: &GlyphMap) -> Option<Node> {
+    let coverage = read_coverage(data, offset16(data, at, at)?);
+    let kept = kept_indices(&coverage, glyph_map);
+    if kept.is_empty() {
+        return None;
+    }
+
+    let mut node = Node::new();
+    node.offset16(Some(coverage_node(&glyphs_of(&kept))));
+    node.u16(kept.len() as u16);
+    kept.iter().for_each(|(index, _)| {
+        node.offset16(offset16(data, at, at + 4 + index * 2).map(|attach_point| {
+            let point_count = read_u16(data, attach_point) as usize;
+            Node::from_bytes(&data[attach_point..attach_point + 2 + point_count * 2])
+        }));
+    });
+    Some(node)
+}
+
+fn lig_caret_list_node(data: &[u8], at: usize, glyph_map: &GlyphMap) -> Option<Node> {
+    let coverage = read_coverage(data, offset16(data, at, at)?);
+    let kept = kept_indices(&coverage, glyph_map);
+    if kept.is_empty() {
+        return None;
+    }
+
+    let mut node = Node::new();
+    node.offset16(Some(coverage_node(&glyphs_of(&kept))));
+    node.u16(kept.len() as u16);
+    kept.iter().for_each(|(index, _)| {
+        node.offset16(offset16(data, at, at + 4 + index * 2).map(|lig_glyph| {
+            let caret_count = read_u16(data, lig_glyph) as usize;
+            let mut node = Node::new();
+            node.u16(caret_count as u16);
+            (0..caret_count).for_each(|caret| {
+                node.offset16(offset16(data, lig_glyph, lig_glyph + 2 + caret * 2).map(
+                    |caret_value| {
+                        let mut node = Node::from_bytes(&data[caret_value..caret_value + 4]);
+                        if read_u16(data, caret_value) == 3 {
+                            let device = offset16(data, caret_value, caret_value + 4);
+                            node.offset16(device.map(|device| device_node(data, device)));
+                        }
+                        node
+                    },
+                ));
+            });
+            node
+        }));
+    });
+    Some(node)
+}
+
+// Copied as is, see `ItemVariationStore` for the layout
+fn item_variation_store_node(data: &[u8], at: usize) -> Node {
+    let region_list = at + read_u32(data, at + 2) as usize;
+    let axis_count = read_u16(data, region_list) as usize;
+    let region_count = read_u16(data, region_list + 2) as usize;
+    let item_variation_data_count = read_u16(data, at + 6) as usize;
+
+    let mut node = Node::new();
+    node.u16(read_u16(data, at));
+    node.offset32(Some(Node::from_bytes(
+        &data[region_list..region_list + 4 + axis_count * region_count * 6],
+    )));
+    node.u16(item_variation_data_count as u16);
+    (0..item_variation_data_count).for_each(|index| {
+        let item_variation_data = read_u32(data, at + 8 + index * 4) as usize;
+        node.offset32((item_variation_data != 0).then(|| {
+            let item_variation_data = at + item_variation_data;
+            let item_count = read_u16(data, item_variation_data) as usize;
+            let word_delta_count = read_u16(data, item_variation_data + 2);
+            let region_index_count = read_u16(data, item_variation_data + 4) as usize;
+            let word_count = (word_delta_count & 0x7FFF) as usize;
+            let row_size = if word_delta_count & 0x8000 != 0 {
+                word_count * 4 + (region_index_count - word_count) * 2
+            } else {
+                word_count * 2 + (region_index_count - word_count)
+            };
+            let length = 6 + region_index_count * 2 + item_count * row_size;
+            Node::from_bytes(&data[item_variation_data..item_variation_data + length])
+        }));
+    });
+    node
+}
+
+// Only the format 0 subtables of the OpenType kern table are kept, as pairs of kept glyphs
+pub fn subset_kern(data: &[u8], glyph_map: &GlyphMap) -> Option<Vec<u8>> {
+    if read_u16(data, 0) != 0 {
+        return None;
+    }
+    let table_count = read_u16(data, 2) as usize;
+
+    let mut subtables: Vec<Writer> = vec![];
+    let mut position = 4;
+    for _ in 0..table_count {
+        let length = read_u16(data, position + 2) as usize;
+        let coverage = read_u16(data, position + 4);
+        if coverage >> 8 != 0 {
+            position += length;
+            continue;
+        }
+
+        let pair_count = read_u16(data, position + 6) as usize;
+        let pairs: Vec<(u16, u16, u16)> = (0..pair_count)
+            .filter_map(|index| {
+                let pair = position + 14 + index * 6;
+                Some((
+                    glyph_map.get(read_u16(data, pair))?,
+                    glyph_map.get(read_u16(data, pair + 2))?,
+                    read_u16(data, pair + 4),
+                ))
+            })
+            .collect();
+        // The length of large subtables overflows, the pair count tells where they end
+        position += 14 + pair_count * 6;
+        if pairs.is_empty() {
+            continue;
+        }
+
+        let (search_range, entry_selector, range_shift) =
+            binary_search_header(pairs.len() as u16, 6);
+        let mut subtable = Writer::new();
+        subtable.write_u16(0);
+        subtable.write_u16((14 + pairs.len() * 6) as u16);
+        subtable.write_u16(coverage);
+        subtable.write_u16(pairs.len() as u16);
+        subtable.write_u16(search_range);
+        subtable.write_u16(entry_selector);
+        subtable.write_u16(range_shift);
+        pairs.iter().for_each(|(left, right, value)| {
+            subtable.write_u16(*left);
+            subtable.write_u16(*right);
+            subtable.write_u16(*value);
+        });
+        subtables.push(subtable);
+    }
+    if subtables.is_empty() {
+        return None;
+    }
+
+    let mut writer = Writer::new();
+    writer.write_u16(0);
+    writer.write_u16(subtables.len() as u16);
+    subtables
+        .into_iter()
+        .for_each(|subtable| writer.write_bytes(&subtable.into_vec()));
+    Some(writer.into_vec())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn read_u16_array(data: &[u8], offset: usize, count: usize) -> Vec<u16> {
+    (0..count)
+        .map(|index| read_u16(data, offset + index * 2))
+        .collect()
+}
+
+// Position of the table at the Offset16 stored at `field`, relative to `base`
+fn offset16(data: &[u8], base: usize, field: usize) -> Option<usize> {
+    match read_u16(data, field) {
+        0 => None,
+        offset => Some(base + offset as usize),
+    }
+}
@@ -0,0 +1,122 @@
+use crate::hinting::HintedGlyph;
+use crate::rasterizer::{coverage, Bounds};
+
+// Order of the color stripes of a pixel, left to right or top to bottom
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SubpixelLayout {
+    Rgb,
+    Bgr,
+    VerticalRgb,
+    VerticalBgr,
+}
+
+impl SubpixelLayout {
+    fn is_vertical(&self) -> bool {
+        matches!(
+            self,
+            SubpixelLayout::VerticalRgb | SubpixelLayout::VerticalBgr
+        )
+    }
+}
+
+// Five tap FIR filter spreading each subpixel over its neighbours to reduce
+// color fringes, weights in 1/256
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LcdFilter {
+    pub weights: [u8; 5],
+}
+
+impl LcdFilter {
+    // FreeType's FT_LCD_FILTER_DEFAULT and FT_LCD_FILTER_LIGHT
+    pub const DEFAULT: LcdFilter = LcdFilter {
+        weights: [0x08, 0x4D, 0x56, 0x4D, 0x08],
+    };
+    pub const LIGHT: LcdFilter = LcdFilter {
+        weights: [0x00, 0x55, 0x56, 0x55, 0x00],
+    };
+
+    // Filters `count` values `stride` apart starting at `start`
+    fn apply(&self, values: &mut [u8], start: usize, stride: usize, count: usize) {
+        let source: Vec<u32> = (0..count)
+            .map(|index| values[start + index * stride] as u32)
+            .collect();
+        (0..count).for_each(|index| {
+            let sum: u32 = self
+                .weights
+                .iter()
+                .enumerate()
+                .filter_map(|(tap, weight)| {
+                    let source_index = (index + tap).checked_sub(2)?;
+                    source.get(source_index).map(|value| value * *weight as u32)
+                })
+                .sum();
+            values[start + index * stride] = (sum / 256).min(255) as u8;
+        });
+    }
+}
+
+// Coverage of each color of each pixel, rows from top to bottom
+#[derive(Debug, Clone, PartialEq)]
+pub struct LcdCoverage {
+    pub width: usize,
+    pub height: usize,
+    pub left: i32,          // x of the first column from the origin, in pixels
+    pub top: i32,           // y of the top of the first row above the baseline
+    pub values: Vec<u8>,    // red, green and blue of each pixel
+    pub advance_width: f32, // in pixels, also in vertical layouts
+}
+
+// Renders at three times the resolution across the stripes of `layout`, then
+// filters and splits each pixel into its colors. The bitmap has one more
+// pixel on both sides across the stripes for the spread of the filter.
+pub fn rasterize_lcd(
+    glyph: &HintedGlyph,
+    layout: SubpixelLayout,
+    filter: LcdFilter,
+) -> LcdCoverage {
+    let vertical = layout.is_vertical();
+    let bounds = if vertical {
+        Bounds::of(glyph, 0, 1)
+    } else {
+        Bounds::of(glyph, 1, 0)
+    };
+    let (x_scale, y_scale) = if vertical { (1, 3) } else { (3, 1) };
+    let mut subpixels = coverage(glyph, bounds, x_scale, y_scale);
+
+    let (width, height) = (bounds.width * x_scale, bounds.height * y_scale);
+    if vertical {
+        (0..width).for_each(|column| filter.apply(&mut subpixels, column, width, height));
+    } else {
+        (0..height).for_each(|row| filter.apply(&mut subpixels, row * width, 1, width));
+    }
+
+    let values = (0..bounds.height)
+        .flat_map(|row| (0..bounds.width).map(move |column| (row, column)))
+        .flat_map(|(row, column)| {
+            let subpixel = |index: usize| {
+                if vertical {
+                    subpixels[(row * 3 + index) * width + column]
+                } else {
+                    subpixels[row * width + column * 3 + index]
+                }
+            };
+            match layout {
+                SubpixelLayout::Rgb | SubpixelLayout::VerticalRgb => {
+                    [subpixel(0), subpixel(1), subpixel(2)]
+                }
+                SubpixelLayout::Bgr | SubpixelLayout::VerticalBgr => {
+                    [subpixel(2), subpixel(1), subpixel(0)]
+                }
+            }
+        })
+        .collect();
+
+    LcdCoverage {
+        width: bounds.width,
+        height: bounds.height,
+        left: bounds.left,
+        top: bounds.top,
+        values,
+        advance_width: glyph.advance_width,
+    }
+}
@@ -1,14 +1,74 @@
+pub use crate::autohinter::{Autohinter, BlueZone, Outline};
+pub use crate::cmap_builder::CmapBuilder;
+pub use crate::compositing::{composite_layers, ColorCoverage};
+pub use crate::disassembler::disassemble;
+pub use crate::font_builder::{FontBuilder, FontTable};
+pub use crate::font_metrics::{FontMetrics, ScaledFontMetrics};
 pub use crate::glyph_reader::GlyphReader;
-pub use crate::model::{Contour, Glyph, GlyphId, Point, PointType};
+pub use crate::hinting::{HintedGlyph, HintingError};
+pub use crate::instancer::InstanceError;
+pub use crate::layout::{layout_paragraph, Alignment, Line, Paragraph, PositionedGlyph};
+pub use crate::lcd::{rasterize_lcd, LcdCoverage, LcdFilter, SubpixelLayout};
+pub use crate::model::{Contour, FWord, Glyph, GlyphId, Point, PointType, UFWord};
+pub use crate::rasterizer::{rasterize, Coverage};
+pub use crate::subsetter::{subset, subset_with_options, SubsetError, SubsetOptions};
 use crate::table::cmap_table::Segment;
+pub use crate::table::colr_table::{
+    Affine, ClipBox, ColorLine, ColorStop, CompositeMode, Extend, Paint, PaintError, PaintId,
+    PaintVisitor,
+};
+pub use crate::table::cpal_table::{CpalTable, Palette, Rgba};
+pub use crate::table::cvt_table::CvtTable;
+pub use crate::table::ebdt_table::{Bitmap, BitmapComponent, BitmapData};
+pub use crate::table::eblc_table::BigGlyphMetrics;
+pub use crate::table::fpgm_table::FpgmTable;
+pub use crate::table::fvar_table::{NamedInstance, VariationAxis};
+pub use crate::table::gasp_table::{GaspRange, GaspTable};
+pub use crate::table::hdmx_table::{DeviceRecord, HdmxTable};
+pub use crate::table::head_table::HeadTable;
+pub use crate::table::hhea_table::HheaTable;
+pub use crate::table::ltsh_table::LtshTable;
+pub use crate::table::maxp_table::MaximumProfileTable;
+pub use crate::table::os2_table::{Os2Table, TypoMetrics};
+pub use crate::table::prep_table::PrepTable;
+pub use crate::table::sbix_table::SbixImage;
+pub use crate::table::stat_table::{AxisRecord, AxisValue, StatTable};
+pub use crate::table::vdmx_table::{VdmxRatio, VdmxRecord, VdmxTable};
+pub use crate::woff::{decode_woff, encode_woff, WoffError};
+pub use crate::woff2::{decode_woff2, encode_woff2, Woff2Error};
 
+mod autohinter;
+mod brotli;
+mod charstring;
+mod cmap_builder;
+mod compositing;
 mod contours_reader;
+mod deflate;
+mod disassembler;
+mod font_builder;
 mod font_directory;
+mod font_metrics;
+mod glyf_encoder;
 mod glyph_index_lookup;
 mod glyph_reader;
+mod glyph_variation;
+mod hinting;
+mod inflate;
+mod instancer;
+mod item_variation_store;
+mod layout;
+mod layout_subsetter;
+mod lcd;
+mod line_break;
 mod model;
+mod rasterizer;
 mod reader;
+mod subsetter;
 mod table;
+mod tuple_variation;
+mod woff;
+mod woff2;
+mod writer;
 
 pub fn all_glyphs(mut glyph_reader: GlyphReader) -> Vec<Glyph> {
     let all_chars: Vec<u16> = glyph_reader.all_char_codes();
@@ -28,7 +88,13 @@ pub fn all_glyphs(mut glyph_reader: GlyphReader) -> Vec<Glyph> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::model::{ArgumentTypes, Contour, Point, PointType};
+    use crate::brotli::{brotli_compress, brotli_decompress, BrotliError};
+    use crate::charstring::{execute_charstring, CharstringError};
+    use crate::deflate::zlib_compress;
+    use crate::glyf_encoder::{encode_glyf_and_loca, encode_glyph, GlyfEncodeError};
+    use crate::inflate::{crc32, gzip_decompress, zlib_decompress, InflateError};
+    use crate::model::{ArgumentTypes, ComponentData, Contour, Point, PointType};
+    use crate::table::cff_table::{parse_dict, read_index};
     use std::fs;
     use std::fs::File;
 
@@ -86,6 +152,466 @@ mod tests {
         glyph_reader.glyph_for_glyph_id(glyph_id)
     }
 
+    // Rewrites the font at `file_path` with `tables` added, or replacing the
+    // tables with the same tag. Checksums are not computed.
+    fn mk_font_with_tables(file_path: &str, tables: Vec<(&str, Vec<u8>)>) -> Vec<u8> {
+        let font = fs::read(file_path).unwrap();
+        let be_u16 = |offset: usize| u16::from_be_bytes([font[offset], font[offset + 1]]);
+        let be_u32 =
+            |offset: usize| u32::from_be_bytes(font[offset..offset + 4].try_into().unwrap());
+
+        let num_tables = be_u16(4) as usize;
+        let mut all_tables: Vec<(String, Vec<u8>)> = (0..num_tables)
+            .map(|index| {
+                let record = 12 + 16 * index;
+                let tag = String::from_utf8_lossy(&font[record..record + 4]).to_string();
+                let offset = be_u32(record + 8) as usize;
+                let length = be_u32(record + 12) as usize;
+                (tag, font[offset..offset + length].to_vec())
+            })
+            .filter(|(tag, _)| tables.iter().all(|(new_tag, _)| new_tag != tag))
+            .collect();
+        tables
+            .into_iter()
+            .for_each(|(tag, data)| all_tables.push((tag.to_string(), data)));
+        all_tables.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut result: Vec<u8> = vec![];
+        result.extend_from_slice(&font[0..4]);
+        result.extend_from_slice(&(all_tables.len() as u16).to_be_bytes());
+        result.extend_from_slice(&[0; 6]);
+        let mut offset = 12 + 16 * all_tables.len();
+        all_tables.iter().for_each(|(tag, data)| {
+            result.extend_from_slice(tag.as_bytes());
+            result.extend_from_slice(&0u32.to_be_bytes());
+            result.extend_from_slice(&(offset as u32).to_be_bytes());
+            result.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            offset += (data.len() + 3) & !3;
+        });
+        all_tables.iter().for_each(|(_, data)| {
+            result.extend_from_slice(data);
+            result.resize((result.len() + 3) & !3, 0);
+        });
+        result
+    }
+
+    // The font at `file_path` with its head table renamed, so that it has none
+    fn mk_font_without_head(file_path: &str) -> Vec<u8> {
+        let mut font = fs::read(file_path).unwrap();
+        let num_tables = u16::from_be_bytes([font[4], font[5]]) as usize;
+        let record = (0..num_tables)
+            .map(|index| 12 + 16 * index)
+            .find(|record| &font[*record..*record + 4] == b"head")
+            .unwrap();
+        font[record..record + 4].copy_from_slice(b"heaX");
+        font
+    }
+
+    // Packs the fonts at `file_paths` into a collection, tables with the same
+    // data are stored once and shared. Version 2 headers carry an empty DSIG.
+    fn mk_collection(file_paths: &[&str], major_version: u16) -> Vec<u8> {
+        let fonts: Vec<Vec<(String, Vec<u8>)>> = file_paths
+            .iter()
+            .map(|file_path| {
+                let font = fs::read(file_path).unwrap();
+                let be_u32 = |offset: usize| {
+                    u32::from_be_bytes(font[offset..offset + 4].try_into().unwrap())
+                };
+                let num_tables = u16::from_be_bytes([font[4], font[5]]) as usize;
+                (0..num_tables)
+                    .map(|index| {
+                        let record = 12 + 16 * index;
+                        let tag = String::from_utf8_lossy(&font[record..record + 4]).to_string();
+                        let offset = be_u32(record + 8) as usize;
+                        let length = be_u32(record + 12) as usize;
+                        (tag, font[offset..offset + length].to_vec())
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let header_size = 12 + 4 * fonts.len() + if major_version >= 2 { 12 } else { 0 };
+        let directory_offsets: Vec<usize> = fonts
+            .iter()
+            .scan(header_size, |offset, tables| {
+                let directory_offset = *offset;
+                *offset += 12 + 16 * tables.len();
+                Some(directory_offset)
+            })
+            .collect();
+
+        let mut table_data: Vec<u8> = vec![];
+        let mut table_offsets: Vec<(Vec<u8>, usize)> = vec![];
+        let data_start = header_size + fonts.iter().map(|t| 12 + 16 * t.len()).sum::<usize>();
+
+        let mut result: Vec<u8> = vec![];
+        result.extend_from_slice(b"ttcf");
+        result.extend_from_slice(&major_version.to_be_bytes());
+        result.extend_from_slice(&0u16.to_be_bytes());
+        result.extend_from_slice(&(fonts.len() as u32).to_be_bytes());
+        directory_offsets
+            .iter()
+            .for_each(|offset| result.extend_from_slice(&(*offset as u32).to_be_bytes()));
+        if major_version >= 2 {
+            result.extend_from_slice(&[0; 12]);
+        }
+        fonts.iter().for_each(|tables| {
+            result.extend_from_slice(&0x00010000u32.to_be_bytes());
+            result.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+            result.extend_from_slice(&[0; 6]);
+            tables.iter().for_each(|(tag, data)| {
+                let offset = match table_offsets.iter().find(|(shared, _)| shared == data) {
+                    Some((_, offset)) => *offset,
+                    None => {
+                        let offset = data_start + table_data.len();
+                        table_offsets.push((data.clone(), offset));
+                        table_data.extend_from_slice(data);
+                        table_data.resize((table_data.len() + 3) & !3, 0);
+                        offset
+                    }
+                };
+                result.extend_from_slice(tag.as_bytes());
+                result.extend_from_slice(&0u32.to_be_bytes());
+                result.extend_from_slice(&(offset as u32).to_be_bytes());
+                result.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            });
+        });
+        result.extend_from_slice(&table_data);
+        result
+    }
+
+    fn fixed(value: f32) -> [u8; 4] {
+        ((value * 65536.0).round() as i32).to_be_bytes()
+    }
+
+    fn f2dot14(value: f32) -> [u8; 2] {
+        ((value * 16384.0).round() as i16).to_be_bytes()
+    }
+
+    // fvar with a wght axis (100, 400, 900), a wdth axis (75, 100, 100) and
+    // two named instances: Regular (400, 100) and Bold Condensed (700, 75)
+    fn mk_fvar_table() -> Vec<u8> {
+        let mut fvar: Vec<u8> = vec![];
+        [1u16, 0, 16, 2, 2, 20, 2, 14]
+            .iter()
+            .for_each(|v| fvar.extend_from_slice(&v.to_be_bytes()));
+        [
+            ("wght", 100.0, 400.0, 900.0, 256u16),
+            ("wdth", 75.0, 100.0, 100.0, 257),
+        ]
+        .iter()
+        .for_each(|(tag, min, default, max, name_id)| {
+            fvar.extend_from_slice(tag.as_bytes());
+            fvar.extend_from_slice(&fixed(*min));
+            fvar.extend_from_slice(&fixed(*default));
+            fvar.extend_from_slice(&fixed(*max));
+            fvar.extend_from_slice(&0u16.to_be_bytes());
+            fvar.extend_from_slice(&name_id.to_be_bytes());
+        });
+        [(2u16, 400.0, 100.0, 6u16), (258, 700.0, 75.0, 6)]
+            .iter()
+            .for_each(|(name_id, wght, wdth, post_script_name_id)| {
+                fvar.extend_from_slice(&name_id.to_be_bytes());
+                fvar.extend_from_slice(&0u16.to_be_bytes());
+                fvar.extend_from_slice(&fixed(*wght));
+                fvar.extend_from_slice(&fixed(*wdth));
+                fvar.extend_from_slice(&post_script_name_id.to_be_bytes());
+            });
+        fvar
+    }
+
+    // avar mapping wght 0.5 to 0.75, wdth left with the default normalization
+    fn mk_avar_table() -> Vec<u8> {
+        let mut avar: Vec<u8> = vec![];
+        [1u16, 0, 0, 2, 4]
+            .iter()
+            .for_each(|v| avar.extend_from_slice(&v.to_be_bytes()));
+        [(-1.0, -1.0), (0.0, 0.0), (0.5, 0.75), (1.0, 1.0)]
+            .iter()
+            .for_each(|(from, to)| {
+                avar.extend_from_slice(&f2dot14(*from));
+                avar.extend_from_slice(&f2dot14(*to));
+            });
+        avar.extend_from_slice(&0u16.to_be_bytes());
+        avar
+    }
+
+    // gvar with 32 bits offsets and `glyph_data` as the variation data of `glyph_id`
+    fn mk_gvar_table(
+        axis_count: u16,
+        shared_tuples: Vec<Vec<f32>>,
+        glyph_count: u16,
+        glyph_id: u16,
+        glyph_data: Vec<u8>,
+    ) -> Vec<u8> {
+        let shared_tuples_offset = 20 + 4 * (glyph_count as u32 + 1);
+        let data_array_offset =
+            shared_tuples_offset + 2 * axis_count as u32 * shared_tuples.len() as u32;
+
+        let mut gvar: Vec<u8> = vec![];
+        [1u16, 0, axis_count, shared_tuples.len() as u16]
+            .iter()
+            .for_each(|v| gvar.extend_from_slice(&v.to_be_bytes()));
+        gvar.extend_from_slice(&shared_tuples_offset.to_be_bytes());
+        gvar.extend_from_slice(&glyph_count.to_be_bytes());
+        gvar.extend_from_slice(&1u16.to_be_bytes());
+        gvar.extend_from_slice(&data_array_offset.to_be_bytes());
+        (0..=glyph_count).for_each(|index| {
+            let offset = if index <= glyph_id {
+                0
+            } else {
+                glyph_data.len() as u32
+            };
+            gvar.extend_from_slice(&offset.to_be_bytes());
+        });
+        shared_tuples
+            .iter()
+            .flatten()
+            .for_each(|value| gvar.extend_from_slice(&f2dot14(*value)));
+        gvar.extend_from_slice(&glyph_data);
+        gvar
+    }
+
+    // Variations of 'I' (glyph 38, 4 points) in Golos Text:
+    // - at wght max, points 1, 2 and the right phantom point move, IUP infers 0 and 3
+    // - at wdth min (shared tuple), every point moves 10 units left and the advance shrinks by 20
+    fn mk_golos_variable_font() -> Vec<u8> {
+        mk_golos_variable_font_with_tables(vec![])
+    }
+
+    // The variable font of `mk_golos_variable_font` with extra `tables`
+    fn mk_golos_variable_font_with_tables(tables: Vec<(&str, Vec<u8>)>) -> Vec<u8> {
+        #[rustfmt::skip]
+        let glyph_data: Vec<u8> = vec![
+            0x80, 0x02,             // SHARED_POINT_NUMBERS, 2 tuples
+            0x00, 0x10,             // serialized data offset
+            0x00, 0x0D,             // tuple 1: size
+            0xA0, 0x00,             // EMBEDDED_PEAK_TUPLE | PRIVATE_POINT_NUMBERS
+            0x40, 0x00, 0x00, 0x00, // peak (1.0, 0.0)
+            0x00, 0x12,             // tuple 2: size
+            0x00, 0x00,             // shared tuple 0
+            0x00,                   // shared point numbers: all points
+            0x03, 0x02, 1, 1, 3,    // tuple 1 points 1, 2 and 5
+            0x02, 0, 40, 40,        // x deltas
+            0x02, 50, 50, 0,        // y deltas
+            0x47,                   // tuple 2: 8 x deltas as words
+            0xFF, 0xF6, 0xFF, 0xF6, 0xFF, 0xF6, 0xFF, 0xF6,
+            0x00, 0x00, 0xFF, 0xEC, 0x00, 0x00, 0x00, 0x00,
+            0x87,                   // 8 zero y deltas
+        ];
+
+        let mut all_tables = vec![
+            ("fvar", mk_fvar_table()),
+            (
+                "gvar",
+                mk_gvar_table(2, vec![vec![0.0, -1.0]], 617, 38, glyph_data),
+            ),
+        ];
+        all_tables.extend(tables);
+
+        mk_font_with_tables("fonts/GolosText-Regular.ttf", all_tables)
+    }
+
+    // Item variation store with a single region peaking at wght 1.0 and
+    // one 8 bit delta per item
+    fn mk_item_variation_store(deltas: Vec<i8>) -> Vec<u8> {
+        let mut store: Vec<u8> = vec![];
+        store.extend_from_slice(&1u16.to_be_bytes()); // format
+        store.extend_from_slice(&12u32.to_be_bytes()); // variation region list offset
+        store.extend_from_slice(&1u16.to_be_bytes()); // item variation data count
+        store.extend_from_slice(&28u32.to_be_bytes());
+        [2u16, 1]
+            .iter()
+            .for_each(|v| store.extend_from_slice(&v.to_be_bytes()));
+        [0.0, 1.0, 1.0, 0.0, 0.0, 0.0]
+            .iter()
+            .for_each(|v| store.extend_from_slice(&f2dot14(*v)));
+        [deltas.len() as u16, 0, 1, 0]
+            .iter()
+            .for_each(|v| store.extend_from_slice(&v.to_be_bytes()));
+        deltas.iter().for_each(|delta| store.push(*delta as u8));
+        store
+    }
+
+    // HVAR widening glyph 38 ('I') by 60 units at wght 900
+    fn mk_hvar_table() -> Vec<u8> {
+        let mut hvar: Vec<u8> = vec![];
+        [1u16, 0]
+            .iter()
+            .for_each(|v| hvar.extend_from_slice(&v.to_be_bytes()));
+        [20u32, 58, 0, 0]
+            .iter()
+            .for_each(|v| hvar.extend_from_slice(&v.to_be_bytes()));
+        hvar.extend(mk_item_variation_store(vec![0, 60]));
+        // Delta set index map, format 0 with 1 byte entries and 1 inner index bit
+        hvar.extend_from_slice(&[0, 0]);
+        hvar.extend_from_slice(&40u16.to_be_bytes());
+        (0..40).for_each(|glyph_id| hvar.push(if glyph_id == 38 { 1 } else { 0 }));
+        hvar
+    }
+
+    // MVAR moving hasc by 20, undo by -10 and xhgt by 30 at wght 900
+    fn mk_mvar_table() -> Vec<u8> {
+        let mut mvar: Vec<u8> = vec![];
+        [1u16, 0, 0, 8, 3, 36]
+            .iter()
+            .for_each(|v| mvar.extend_from_slice(&v.to_be_bytes()));
+        ["hasc", "undo", "xhgt"]
+            .iter()
+            .enumerate()
+            .for_each(|(index, tag)| {
+                mvar.extend_from_slice(tag.as_bytes());
+                mvar.extend_from_slice(&0u16.to_be_bytes());
+                mvar.extend_from_slice(&(index as u16).to_be_bytes());
+            });
+        mvar.extend(mk_item_variation_store(vec![20, -10, 30]));
+        mvar
+    }
+
+    // VVAR growing glyph 38 ('I') by 40 units, moving its top side bearing by
+    // -30 and its vertical origin by 25 at wght 900
+    fn mk_vvar_table() -> Vec<u8> {
+        let mut vvar: Vec<u8> = vec![];
+        [1u16, 0]
+            .iter()
+            .for_each(|v| vvar.extend_from_slice(&v.to_be_bytes()));
+        [24u32, 64, 108, 0, 152]
+            .iter()
+            .for_each(|v| vvar.extend_from_slice(&v.to_be_bytes()));
+        vvar.extend(mk_item_variation_store(vec![0, 40, -30, 25]));
+        // Delta set index maps, format 0 with 1 byte entries and 2 inner index bits
+        (1..=3).for_each(|inner_index| {
+            vvar.extend_from_slice(&[0, 1]);
+            vvar.extend_from_slice(&40u16.to_be_bytes());
+            (0..40).for_each(|glyph_id| vvar.push(if glyph_id == 38 { inner_index } else { 0 }));
+        });
+        vvar
+    }
+
+    // VORG with a default origin of 880 and 800 for glyph 38 ('I')
+    fn mk_vorg_table() -> Vec<u8> {
+        let mut vorg: Vec<u8> = vec![];
+        [1u16, 0, 880, 1, 38, 800]
+            .iter()
+            .for_each(|v| vorg.extend_from_slice(&v.to_be_bytes()));
+        vorg
+    }
+
+    fn simple_glyph_points(glyph: &Glyph) -> Vec<(i16, i16)> {
+        match glyph {
+            Glyph::Simple { contours, .. } => contours
+                .iter()
+                .flat_map(|contour| contour.points.iter().map(|point| (point.x, point.y)))
+                .collect(),
+            _ => panic!("Expected Simple glyph"),
+        }
+    }
+
+    fn glyph_id_of(glyph: &Glyph) -> u16 {
+        match glyph {
+            Glyph::Empty { glyph_id, .. } => glyph_id.id(),
+            Glyph::Simple { glyph_id, .. } => glyph_id.id(),
+            Glyph::Compound { glyph_id, .. } => glyph_id.id(),
+            Glyph::Cubic { glyph_id, .. } => glyph_id.id(),
+        }
+    }
+
+    // Format 4 cmap with one segment per (start code, end code, first glyph)
+    fn mk_format4_cmap_table(segments: &[(u16, u16, u16)]) -> Vec<u8> {
+        let seg_count = segments.len() as u16;
+        let entry_selector = 15 - seg_count.leading_zeros() as u16;
+        let search_range = 2 << entry_selector;
+
+        let mut table: Vec<u8> = vec![];
+        [0, 1, 0, 3]
+            .iter()
+            .for_each(|value: &u16| table.extend(value.to_be_bytes()));
+        table.extend(12u32.to_be_bytes());
+        [
+            4,
+            16 + 8 * seg_count,
+            0,
+            2 * seg_count,
+            search_range,
+            entry_selector,
+            2 * seg_count - search_range,
+        ]
+        .iter()
+        .for_each(|value| table.extend(value.to_be_bytes()));
+        segments
+            .iter()
+            .for_each(|(_, end_code, _)| table.extend(end_code.to_be_bytes()));
+        table.extend(0u16.to_be_bytes());
+        segments
+            .iter()
+            .for_each(|(start_code, _, _)| table.extend(start_code.to_be_bytes()));
+        segments.iter().for_each(|(start_code, _, first_glyph)| {
+            table.extend(first_glyph.wrapping_sub(*start_code).to_be_bytes())
+        });
+        segments
+            .iter()
+            .for_each(|_| table.extend(0u16.to_be_bytes()));
+        table
+    }
+
+    #[test]
+    fn format4_lookup_between_segments() {
+        // With four segments searchRange is 8 and points past the last end code
+        let cmap_table = mk_format4_cmap_table(&[
+            (0x20, 0x20, 3),
+            (0x41, 0x43, 10),
+            (0x61, 0x62, 20),
+            (0xFFFF, 0xFFFF, 0),
+        ]);
+        let font = mk_font_with_tables("fonts/GolosText-Regular.ttf", vec![("cmap", cmap_table)]);
+        let mut glyph_reader = GlyphReader::from_vec(font);
+
+        let expected = [
+            (' ', 3),
+            ('A', 10),
+            ('C', 12),
+            ('a', 20),
+            ('b', 21),
+            // Codes before a segment start are not mapped
+            ('!', 0),
+            ('D', 0),
+            ('`', 0),
+            ('c', 0),
+            ('z', 0),
+        ];
+        expected.iter().for_each(|(c, glyph_id)| {
+            assert_eq!(
+                glyph_reader.char_code_to_glyph_id(*c as u16),
+                GlyphId::new(*glyph_id),
+                "{:?}",
+                c
+            );
+        });
+    }
+
+    #[test]
+    fn format4_lookup_ignores_stored_search_range() {
+        let mut cmap_table = mk_format4_cmap_table(&[
+            (0x20, 0x20, 3),
+            (0x41, 0x43, 10),
+            (0x61, 0x62, 20),
+            (0xFFFF, 0xFFFF, 0),
+        ]);
+        // searchRange 4 and entrySelector 0 would stop the binary search early
+        cmap_table[20..24].copy_from_slice(&[0, 4, 0, 0]);
+        let font = mk_font_with_tables("fonts/GolosText-Regular.ttf", vec![("cmap", cmap_table)]);
+        let mut glyph_reader = GlyphReader::from_vec(font);
+
+        assert_eq!(
+            glyph_reader.char_code_to_glyph_id(b'B' as u16),
+            GlyphId::new(11)
+        );
+        assert_eq!(
+            glyph_reader.char_code_to_glyph_id(b'b' as u16),
+            GlyphId::new(21)
+        );
+    }
+
     fn cmap_table_segments(file_path: &str) -> Vec<Segment> {
         let mut glyph_reader = mk_glyph_reader_file(file_path);
         glyph_reader.cmap_table_segments()
@@ -95,7 +621,11 @@ mod tests {
     fn golos_text_cmap_segments() {
         let segments = cmap_table_segments("fonts/GolosText-Regular.ttf");
 
-        let expected_segments = vec![
+        assert_eq!(segments, golos_text_expected_segments());
+    }
+
+    fn golos_text_expected_segments() -> Vec<Segment> {
+        vec![
             mk_segments!(0, 0, 0, 457, 0),
             mk_segments!(1, 13, 13, 443, 0),
             mk_segments!(2, 32, 47, 0, 180),
@@ -188,9 +718,7 @@ mod tests {
             mk_segments!(89, 63188, 63188, 2964, 0),
             mk_segments!(90, 65279, 65279, 712, 0),
             mk_segments!(91, 65535, 65535, 1, 0),
-        ];
-
-        assert_eq!(segments, expected_segments);
+        ]
     }
 
     #[test]
@@ -634,9 +1162,10 @@ mod tests {
         let result = read_glyph_id_file(GlyphId::new(0), "fonts/Zeyada_1.ttf");
 
         match result {
-            Glyph::Empty { .. } => assert!(true),
+            Glyph::Empty { .. } => {}
             Glyph::Compound { .. } => panic!("Expected Empty glyph"),
             Glyph::Simple { .. } => panic!("Expected Empty glyph"),
+            Glyph::Cubic { .. } => panic!("Expected Empty glyph"),
         }
     }
 
@@ -645,9 +1174,10 @@ mod tests {
         let result = read_glyph_id_vec(GlyphId::new(0), "fonts/Zeyada_1.ttf");
 
         match result {
-            Glyph::Empty { .. } => assert!(true),
+            Glyph::Empty { .. } => {}
             Glyph::Compound { .. } => panic!("Expected Empty glyph"),
             Glyph::Simple { .. } => panic!("Expected Empty glyph"),
+            Glyph::Cubic { .. } => panic!("Expected Empty glyph"),
         }
     }
 
@@ -656,9 +1186,10 @@ mod tests {
         let result = read_glyph_id_file(GlyphId::new(98), "fonts/Zeyada_1.ttf");
 
         match result {
-            Glyph::Empty { .. } => assert!(true),
+            Glyph::Empty { .. } => {}
             Glyph::Compound { .. } => panic!("Expected Empty glyph"),
             Glyph::Simple { .. } => panic!("Expected Empty glyph"),
+            Glyph::Cubic { .. } => panic!("Expected Empty glyph"),
         }
     }
 
@@ -743,6 +1274,7 @@ mod tests {
         match glyph {
             Glyph::Empty { .. } => panic!("Expected Simple glyph"),
             Glyph::Compound { .. } => panic!("Expected Simple glyph"),
+            Glyph::Cubic { .. } => panic!("Expected Simple glyph"),
             Glyph::Simple {
                 glyph_id,
                 x_min,
@@ -751,7 +1283,10 @@ mod tests {
                 y_max,
                 advance_width,
                 left_side_bearing,
+                advance_height,
+                top_side_bearing,
                 contours,
+                ..
             } => {
                 assert_eq!(glyph_id.id(), 4);
                 assert_eq!(x_min.0, 84);
@@ -760,6 +1295,8 @@ mod tests {
                 assert_eq!(y_max.0, 732);
                 assert_eq!(advance_width, 190);
                 assert_eq!(left_side_bearing, 84);
+                assert_eq!(advance_height, 1614);
+                assert_eq!(top_side_bearing, 202);
 
                 #[rustfmt::skip]
                 let expected_contours = vec![
@@ -823,6 +1360,7 @@ mod tests {
         match glyph {
             Glyph::Empty { .. } => panic!("Expected Simple glyph"),
             Glyph::Compound { .. } => panic!("Expected Simple glyph"),
+            Glyph::Cubic { .. } => panic!("Expected Simple glyph"),
             Glyph::Simple {
                 glyph_id,
                 x_min,
@@ -831,7 +1369,10 @@ mod tests {
                 y_max,
                 advance_width,
                 left_side_bearing,
+                advance_height,
+                top_side_bearing,
                 contours,
+                ..
             } => {
                 assert_eq!(glyph_id.id(), 0);
                 assert_eq!(x_min.0, 50);
@@ -840,6 +1381,8 @@ mod tests {
                 assert_eq!(y_max.0, 512);
                 assert_eq!(advance_width, 500);
                 assert_eq!(left_side_bearing, 50);
+                assert_eq!(advance_height, 0);
+                assert_eq!(top_side_bearing, 468);
 
                 #[rustfmt::skip]
                 let expected_contours = vec![
@@ -865,6 +1408,7 @@ mod tests {
         match glyph {
             Glyph::Empty { .. } => panic!("Expected Simple glyph"),
             Glyph::Compound { .. } => panic!("Expected Simple glyph"),
+            Glyph::Cubic { .. } => panic!("Expected Simple glyph"),
             Glyph::Simple {
                 glyph_id,
                 x_min,
@@ -873,7 +1417,10 @@ mod tests {
                 y_max,
                 advance_width,
                 left_side_bearing,
+                advance_height,
+                top_side_bearing,
                 contours,
+                ..
             } => {
                 assert_eq!(glyph_id.id(), 4);
                 assert_eq!(x_min.0, 84);
@@ -882,6 +1429,8 @@ mod tests {
                 assert_eq!(y_max.0, 732);
                 assert_eq!(advance_width, 190);
                 assert_eq!(left_side_bearing, 84);
+                assert_eq!(advance_height, 1614);
+                assert_eq!(top_side_bearing, 202);
 
                 #[rustfmt::skip]
                 let expected_contours = vec![
@@ -946,6 +1495,7 @@ mod tests {
         match glyph {
             Glyph::Empty { .. } => panic!("Expected Simple glyph"),
             Glyph::Compound { .. } => panic!("Expected Simple glyph"),
+            Glyph::Cubic { .. } => panic!("Expected Simple glyph"),
             Glyph::Simple {
                 glyph_id,
                 x_min,
@@ -954,7 +1504,10 @@ mod tests {
                 y_max,
                 advance_width,
                 left_side_bearing,
+                advance_height,
+                top_side_bearing,
                 contours,
+                ..
             } => {
                 assert_eq!(glyph_id.id(), 467);
                 assert_eq!(x_min.0, 80);
@@ -963,6 +1516,8 @@ mod tests {
                 assert_eq!(y_max.0, 700);
                 assert_eq!(advance_width, 300);
                 assert_eq!(left_side_bearing, 80);
+                assert_eq!(advance_height, 0);
+                assert_eq!(top_side_bearing, 280);
 
                 #[rustfmt::skip]
                 let expected_contours = vec![
@@ -998,6 +1553,7 @@ mod tests {
         match glyph {
             Glyph::Empty { .. } => panic!("Expected Simple glyph"),
             Glyph::Compound { .. } => panic!("Expected Simple glyph"),
+            Glyph::Cubic { .. } => panic!("Expected Simple glyph"),
             Glyph::Simple {
                 glyph_id,
                 x_min,
@@ -1006,7 +1562,10 @@ mod tests {
                 y_max,
                 advance_width,
                 left_side_bearing,
+                advance_height,
+                top_side_bearing,
                 contours,
+                ..
             } => {
                 assert_eq!(glyph_id.id(), 114);
                 assert_eq!(x_min.0, 50);
@@ -1015,6 +1574,8 @@ mod tests {
                 assert_eq!(y_max.0, 540);
                 assert_eq!(advance_width, 565);
                 assert_eq!(left_side_bearing, 50);
+                assert_eq!(advance_height, 0);
+                assert_eq!(top_side_bearing, 440);
 
                 #[rustfmt::skip]
                 let expected_contours = vec![
@@ -1085,15 +1646,21 @@ mod tests {
                 y_max,
                 advance_width,
                 left_side_bearing,
+                advance_height,
+                top_side_bearing,
                 components,
+                instructions,
             } => {
                 assert_eq!(glyph_id.id(), 115);
+                assert!(instructions.is_empty());
                 assert_eq!(x_min.0, 50);
                 assert_eq!(x_max.0, 495);
                 assert_eq!(y_min.0, -10);
                 assert_eq!(y_max.0, 740);
                 assert_eq!(advance_width, 565);
                 assert_eq!(left_side_bearing, 50);
+                assert_eq!(advance_height, 1200);
+                assert_eq!(top_side_bearing, 240);
                 assert_eq!(components.len(), 2);
 
                 let c1 = &components[0];
@@ -1114,6 +1681,3507 @@ mod tests {
             }
 
             Glyph::Simple { .. } => panic!("Expected Compound glyph"),
+            Glyph::Cubic { .. } => panic!("Expected Compound glyph"),
+        }
+    }
+
+    #[test]
+    fn line_break_opportunities() {
+        use crate::line_break::{break_opportunities, BreakOpportunity::*};
+
+        let chars: Vec<char> = "ab cd-ef (x)\n日本語。".chars().collect();
+        let opportunities = break_opportunities(&chars);
+
+        let expected = vec![
+            Prohibited, // a
+            Prohibited, // b
+            Prohibited, // ' '
+            Allowed,    // c
+            Prohibited, // d
+            Prohibited, // -
+            Allowed,    // e
+            Prohibited, // f
+            Prohibited, // ' '
+            Allowed,    // (
+            Prohibited, // x
+            Prohibited, // )
+            Prohibited, // \n
+            Mandatory,  // 日
+            Allowed,    // 本
+            Allowed,    // 語
+            Prohibited, // 。
+            Mandatory,  // end of text
+        ];
+
+        assert_eq!(opportunities, expected);
+    }
+
+    fn line_text(line: &Line) -> String {
+        line.glyphs.iter().map(|glyph| glyph.character).collect()
+    }
+
+    #[test]
+    fn layout_paragraph_golos_left() {
+        let mut glyph_reader = mk_glyph_reader_vec("fonts/GolosText-Regular.ttf");
+
+        let text = "The quick brown fox jumps over the lazy dog";
+        let paragraph = layout_paragraph(text, &mut glyph_reader, 20.0, 150.0, Alignment::Left);
+
+        let lines: Vec<String> = paragraph.lines.iter().map(line_text).collect();
+        assert_eq!(
+            lines,
+            vec!["The quick", "brown fox", "jumps over the", "lazy dog"]
+        );
+
+        // Golos Text: units per em 1000, ascent 980, descent -220, line gap 0
+        assert!((paragraph.line_height - 24.0).abs() < 0.001);
+        assert!((paragraph.height - 4.0 * 24.0).abs() < 0.001);
+        assert!((paragraph.lines[0].baseline - 19.6).abs() < 0.001);
+        assert!((paragraph.lines[1].baseline - (19.6 + 24.0)).abs() < 0.001);
+
+        paragraph.lines.iter().for_each(|line| {
+            assert!(line.width <= 150.0);
+            assert_eq!(line.glyphs[0].x, 0.0);
+        });
+    }
+
+    #[test]
+    fn layout_paragraph_golos_alignment() {
+        let mut glyph_reader = mk_glyph_reader_vec("fonts/GolosText-Regular.ttf");
+
+        let text = "The quick brown fox jumps over the lazy dog";
+
+        let right = layout_paragraph(text, &mut glyph_reader, 20.0, 150.0, Alignment::Right);
+        right.lines.iter().for_each(|line| {
+            let last = line.glyphs.last().unwrap();
+            assert!((last.x + last.advance - 150.0).abs() < 0.001);
+        });
+
+        let center = layout_paragraph(text, &mut glyph_reader, 20.0, 150.0, Alignment::Center);
+        center.lines.iter().for_each(|line| {
+            assert!((line.glyphs[0].x - (150.0 - line.width) / 2.0).abs() < 0.001);
+        });
+
+        let justify = layout_paragraph(text, &mut glyph_reader, 20.0, 150.0, Alignment::Justify);
+        let (last_line, lines) = justify.lines.split_last().unwrap();
+        lines.iter().for_each(|line| {
+            let last = line.glyphs.last().unwrap();
+            assert!((last.x + last.advance - 150.0).abs() < 0.001);
+        });
+        let last = last_line.glyphs.last().unwrap();
+        assert_eq!(last.x + last.advance, last_line.width);
+    }
+
+    #[test]
+    fn layout_paragraph_mandatory_and_emergency_breaks() {
+        let mut glyph_reader = mk_glyph_reader_vec("fonts/GolosText-Regular.ttf");
+
+        let paragraph = layout_paragraph(
+            "Hello\nworld\u{00AD}wide",
+            &mut glyph_reader,
+            20.0,
+            1000.0,
+            Alignment::Left,
+        );
+        let lines: Vec<String> = paragraph.lines.iter().map(line_text).collect();
+        assert_eq!(lines, vec!["Hello", "worldwide"]);
+
+        let paragraph = layout_paragraph(
+            "world\u{00AD}wide",
+            &mut glyph_reader,
+            20.0,
+            70.0,
+            Alignment::Left,
+        );
+        let lines: Vec<String> = paragraph.lines.iter().map(line_text).collect();
+        assert_eq!(lines, vec!["world-", "wide"]);
+
+        let paragraph =
+            layout_paragraph("abcdefghij", &mut glyph_reader, 20.0, 40.0, Alignment::Left);
+        let lines: Vec<String> = paragraph.lines.iter().map(line_text).collect();
+        assert!(lines.len() > 1);
+        assert_eq!(lines.concat(), "abcdefghij");
+    }
+
+    #[test]
+    fn font_metrics_golos() {
+        let glyph_reader = mk_glyph_reader_vec("fonts/GolosText-Regular.ttf");
+
+        let font_metrics = glyph_reader.font_metrics();
+
+        let expected = FontMetrics {
+            units_per_em: 1000,
+            ascender: 980,
+            descender: -220,
+            line_gap: 0,
+            caret_slope_rise: 1,
+            caret_slope_run: 0,
+            caret_offset: 0,
+            x_height: Some(530),
+            cap_height: Some(700),
+            underline_position: Some(-75),
+            underline_thickness: Some(50),
+            strikeout_position: Some(250),
+            strikeout_size: Some(50),
+        };
+
+        assert_eq!(font_metrics, expected);
+        assert_eq!(font_metrics.line_height(), 1200);
+        assert_eq!(font_metrics.caret_slant(), 0.0);
+
+        let scaled = font_metrics.scaled(50.0);
+        assert_eq!(scaled.ascender, 49.0);
+        assert_eq!(scaled.descender, -11.0);
+        assert_eq!(scaled.line_height, 60.0);
+        assert_eq!(scaled.x_height, Some(26.5));
+        assert_eq!(scaled.cap_height, Some(35.0));
+        assert_eq!(scaled.underline_position, Some(-3.75));
+        assert_eq!(scaled.strikeout_position, Some(12.5));
+        assert_eq!(scaled.strikeout_size, Some(2.5));
+    }
+
+    #[test]
+    fn font_metrics_zeyada() {
+        let glyph_reader = mk_glyph_reader_file("fonts/Zeyada_1.ttf");
+
+        let font_metrics = glyph_reader.font_metrics();
+
+        let expected = FontMetrics {
+            units_per_em: 1024,
+            ascender: 934,
+            descender: -680,
+            line_gap: 0,
+            caret_slope_rise: 1,
+            caret_slope_run: 0,
+            caret_offset: 0,
+            x_height: Some(312),
+            cap_height: Some(602),
+            underline_position: Some(-77),
+            underline_thickness: Some(51),
+            strikeout_position: Some(256),
+            strikeout_size: Some(51),
+        };
+
+        assert_eq!(font_metrics, expected);
+        assert_eq!(font_metrics.scale(32.0), 0.03125);
+        assert_eq!(font_metrics.scaled(32.0).line_height, 50.4375);
+    }
+
+    #[test]
+    fn vertical_metrics_golos() {
+        let mut glyph_reader = mk_glyph_reader_vec("fonts/GolosText-Regular.ttf");
+
+        assert_eq!(glyph_reader.advance_height(GlyphId::new(4)), 1200);
+
+        let glyph = glyph_reader.glyph_for_glyph_id(GlyphId::new(4));
+        assert_eq!(glyph.advance_height(), 1200);
+        assert_eq!(glyph.top_side_bearing(), 80);
+
+        // No VORG table, the origin is derived from the top side bearing and y_max
+        let (_, _, _, y_max) = glyph.bounding_box().unwrap();
+        assert_eq!(glyph_reader.vertical_origin_y(GlyphId::new(4)), 80 + y_max);
+    }
+
+    #[test]
+    fn vertical_metrics_zeyada_fallback() {
+        let mut glyph_reader = mk_glyph_reader_vec("fonts/Zeyada_1.ttf");
+
+        // No vhea/vmtx: advance height is sTypoAscender - sTypoDescender = 934 + 680
+        let glyph = glyph_reader.read_glyph('!' as u16);
+        assert_eq!(glyph.advance_height(), 1614);
+        assert_eq!(glyph.top_side_bearing(), 934 - 732);
+        assert_eq!(glyph_reader.vertical_origin_y(GlyphId::new(4)), 934);
+
+        let space = glyph_reader.read_glyph(' ' as u16);
+        assert_eq!(space.advance_height(), 1614);
+        assert_eq!(space.top_side_bearing(), 0);
+    }
+
+    #[test]
+    fn stat_table_golos() {
+        let mut glyph_reader = mk_glyph_reader_vec("fonts/GolosText-Regular.ttf");
+
+        assert!(!glyph_reader.is_variable());
+
+        let stat_table = glyph_reader.stat_table().unwrap();
+        assert_eq!(
+            stat_table.design_axes,
+            vec![AxisRecord {
+                axis_tag: "wght".to_string(),
+                axis_name_id: 256,
+                axis_ordering: 0,
+            }]
+        );
+        assert_eq!(
+            stat_table.axis_values,
+            vec![AxisValue::Format3 {
+                axis_index: 0,
+                flags: 2,
+                value_name_id: 257,
+                value: 400.0,
+                linked_value: 700.0,
+            }]
+        );
+        assert_eq!(stat_table.elided_fallback_name_id, Some(2));
+
+        assert_eq!(glyph_reader.name(256), Some("Weight".to_string()));
+        assert_eq!(glyph_reader.style_name(), Some("Regular".to_string()));
+    }
+
+    #[test]
+    fn variation_with_inverted_axis_range() {
+        // A wght axis with its minimum above its maximum
+        let mut fvar_table = mk_fvar_table();
+        fvar_table[20..24].copy_from_slice(&fixed(1000.0));
+        let font = mk_font_with_tables("fonts/GolosText-Regular.ttf", vec![("fvar", fvar_table)]);
+        let mut glyph_reader = GlyphReader::from_vec(font);
+
+        glyph_reader.set_variation(&[("wght", 650.0)]);
+        assert_eq!(glyph_reader.variation_coordinates(), &[900.0, 100.0]);
+        assert_eq!(glyph_reader.normalized_coordinates(), &[1.0, 0.0]);
+    }
+
+    #[test]
+    fn variation_axes_and_named_instances() {
+        let font = mk_font_with_tables(
+            "fonts/GolosText-Regular.ttf",
+            vec![("fvar", mk_fvar_table()), ("avar", mk_avar_table())],
+        );
+        let mut glyph_reader = GlyphReader::from_vec(font);
+
+        assert!(glyph_reader.is_variable());
+        let tags: Vec<&str> = glyph_reader
+            .variation_axes()
+            .iter()
+            .map(|axis| axis.tag.as_str())
+            .collect();
+        assert_eq!(tags, vec!["wght", "wdth"]);
+        assert_eq!(
+            glyph_reader.named_instances()[1],
+            NamedInstance {
+                subfamily_name_id: 258,
+                flags: 0,
+                coordinates: vec![700.0, 75.0],
+                post_script_name_id: Some(6),
+            }
+        );
+        assert_eq!(glyph_reader.normalized_coordinates(), &[0.0, 0.0]);
+
+        glyph_reader.set_variation(&[("wght", 650.0), ("wdth", 90.0)]);
+        assert_eq!(glyph_reader.variation_coordinates(), &[650.0, 90.0]);
+        let normalized = glyph_reader.normalized_coordinates();
+        assert_eq!(normalized[0], 0.75);
+        assert_eq!(normalized[1], -6554.0 / 16384.0);
+
+        // Values are clamped and missing axes go back to their default
+        glyph_reader.set_variation(&[("wght", 50.0), ("opsz", 12.0)]);
+        assert_eq!(glyph_reader.variation_coordinates(), &[100.0, 100.0]);
+        assert_eq!(glyph_reader.normalized_coordinates(), &[-1.0, 0.0]);
+
+        glyph_reader.set_named_instance(1);
+        assert_eq!(glyph_reader.variation_coordinates(), &[700.0, 75.0]);
+        // wght 700 normalizes to 0.6, which avar maps to 0.8
+        assert_eq!(
+            glyph_reader.normalized_coordinates(),
+            &[13107.0 / 16384.0, -1.0]
+        );
+    }
+
+    #[test]
+    fn gvar_glyph_variations() {
+        let mut glyph_reader = GlyphReader::from_vec(mk_golos_variable_font());
+
+        let glyph = glyph_reader.read_glyph('I' as u16);
+        assert_eq!(
+            simple_glyph_points(&glyph),
+            vec![(110, 0), (110, 700), (204, 700), (204, 0)]
+        );
+
+        glyph_reader.set_variation(&[("wght", 900.0)]);
+        let glyph = glyph_reader.read_glyph('I' as u16);
+        assert_eq!(
+            simple_glyph_points(&glyph),
+            vec![(110, 50), (110, 750), (244, 750), (244, 50)]
+        );
+        assert_eq!(glyph.advance_width(), 354);
+        assert_eq!(glyph.bounding_box(), Some((110, 50, 244, 750)));
+        assert_eq!(glyph.top_side_bearing(), 230);
+
+        glyph_reader.set_variation(&[("wght", 650.0)]);
+        let glyph = glyph_reader.read_glyph('I' as u16);
+        assert_eq!(
+            simple_glyph_points(&glyph),
+            vec![(110, 25), (110, 725), (224, 725), (224, 25)]
+        );
+        assert_eq!(glyph.advance_width(), 334);
+
+        glyph_reader.set_variation(&[("wdth", 75.0)]);
+        let glyph = glyph_reader.read_glyph('I' as u16);
+        assert_eq!(
+            simple_glyph_points(&glyph),
+            vec![(100, 0), (100, 700), (194, 700), (194, 0)]
+        );
+        assert_eq!(glyph.advance_width(), 294);
+        match glyph {
+            Glyph::Simple {
+                left_side_bearing, ..
+            } => assert_eq!(left_side_bearing, 100),
+            _ => panic!("Expected Simple glyph"),
+        }
+
+        glyph_reader.set_variation(&[("wght", 900.0), ("wdth", 75.0)]);
+        let glyph = glyph_reader.read_glyph('I' as u16);
+        assert_eq!(
+            simple_glyph_points(&glyph),
+            vec![(100, 50), (100, 750), (234, 750), (234, 50)]
+        );
+        assert_eq!(glyph.advance_width(), 334);
+
+        // Glyphs without variation data are left untouched
+        let glyph = glyph_reader.read_glyph('H' as u16);
+        let default_glyph =
+            glyph_reader.default_glyph_for_glyph_id(GlyphId::new(glyph_id_of(&glyph)));
+        assert_eq!(
+            simple_glyph_points(&glyph),
+            simple_glyph_points(&default_glyph)
+        );
+    }
+
+    #[test]
+    fn gvar_advance_width_without_hvar() {
+        let mut glyph_reader = GlyphReader::from_vec(mk_golos_variable_font());
+        let glyph_id = glyph_reader.char_code_to_glyph_id('I' as u16);
+
+        assert_eq!(glyph_reader.advance_width(glyph_id), 314);
+        glyph_reader.set_variation(&[("wght", 900.0)]);
+        assert_eq!(glyph_reader.advance_width(glyph_id), 354);
+    }
+
+    #[test]
+    fn hvar_and_mvar_variations() {
+        let font = mk_golos_variable_font_with_tables(vec![
+            ("HVAR", mk_hvar_table()),
+            ("MVAR", mk_mvar_table()),
+        ]);
+        let mut glyph_reader = GlyphReader::from_vec(font);
+        let glyph_id = glyph_reader.char_code_to_glyph_id('I' as u16);
+        let default_metrics = glyph_reader.font_metrics();
+
+        glyph_reader.set_variation(&[("wght", 900.0)]);
+        // HVAR takes precedence over the gvar phantom points for the advance
+        assert_eq!(glyph_reader.advance_width(glyph_id), 374);
+        let glyph = glyph_reader.glyph_for_glyph_id(glyph_id);
+        assert_eq!(glyph.advance_width(), 374);
+        assert_eq!(glyph.left_side_bearing(), 110);
+        assert_eq!(
+            simple_glyph_points(&glyph),
+            vec![(110, 50), (110, 750), (244, 750), (244, 50)]
+        );
+
+        let h = glyph_reader.char_code_to_glyph_id('H' as u16);
+        let default_advance = glyph_reader.default_glyph_for_glyph_id(h).advance_width();
+        assert_eq!(glyph_reader.advance_width(h), default_advance);
+
+        let font_metrics = glyph_reader.font_metrics();
+        assert_eq!(font_metrics.ascender, 1000);
+        assert_eq!(font_metrics.x_height, Some(560));
+        assert_eq!(font_metrics.underline_position, Some(-85));
+        assert_eq!(font_metrics.cap_height, default_metrics.cap_height);
+        assert_eq!(glyph_reader.metric_variation("xhgt"), 30.0);
+        assert_eq!(glyph_reader.metric_variation("gsp0"), 0.0);
+
+        glyph_reader.set_variation(&[("wght", 650.0)]);
+        assert_eq!(glyph_reader.advance_width(glyph_id), 344);
+        assert_eq!(glyph_reader.font_metrics().ascender, 990);
+    }
+
+    #[test]
+    fn vvar_and_vorg_variations() {
+        let font = mk_golos_variable_font_with_tables(vec![
+            ("VVAR", mk_vvar_table()),
+            ("VORG", mk_vorg_table()),
+        ]);
+        let mut glyph_reader = GlyphReader::from_vec(font);
+        let glyph_id = glyph_reader.char_code_to_glyph_id('I' as u16);
+        let h = glyph_reader.char_code_to_glyph_id('H' as u16);
+        let default_glyph = glyph_reader.default_glyph_for_glyph_id(glyph_id);
+        let default_advance = glyph_reader.advance_height(glyph_id);
+        assert_eq!(glyph_reader.vertical_origin_y(glyph_id), 800);
+        assert_eq!(glyph_reader.vertical_origin_y(h), 880);
+
+        glyph_reader.set_variation(&[("wght", 900.0)]);
+        assert_eq!(glyph_reader.advance_height(glyph_id), default_advance + 40);
+        let glyph = glyph_reader.glyph_for_glyph_id(glyph_id);
+        assert_eq!(glyph.advance_height(), default_advance + 40);
+        assert_eq!(
+            glyph.top_side_bearing(),
+            default_glyph.top_side_bearing() - 30
+        );
+        assert_eq!(glyph_reader.vertical_origin_y(glyph_id), 825);
+
+        // Glyphs mapped to the zero delta keep their default metrics
+        assert_eq!(glyph_reader.vertical_origin_y(h), 880);
+        let default_h = glyph_reader.default_glyph_for_glyph_id(h);
+        assert_eq!(glyph_reader.advance_height(h), default_h.advance_height());
+
+        glyph_reader.set_variation(&[("wght", 650.0)]);
+        assert_eq!(glyph_reader.advance_height(glyph_id), default_advance + 20);
+        assert_eq!(glyph_reader.vertical_origin_y(glyph_id), 813);
+    }
+
+    #[test]
+    fn instantiate_static_font() {
+        let mut variable_reader = GlyphReader::from_vec(mk_golos_variable_font());
+        variable_reader.set_variation(&[("wdth", 75.0)]);
+
+        let font = variable_reader.instantiate(&[("wght", 900.0)]).unwrap();
+        // The variation of the variable font reader is left unchanged
+        assert_eq!(variable_reader.variation_coordinates(), &[400.0, 75.0]);
+
+        let checksum = font.chunks(4).fold(0u32, |sum, chunk| {
+            let mut word = [0; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            sum.wrapping_add(u32::from_be_bytes(word))
+        });
+        assert_eq!(checksum, 0xB1B0AFBA);
+
+        let mut glyph_reader = GlyphReader::from_vec(font);
+        assert!(!glyph_reader.is_variable());
+        let tags = glyph_reader.table_tags();
+        assert!(!tags.contains(&"fvar".to_string()));
+        assert!(!tags.contains(&"gvar".to_string()));
+        assert!(!tags.contains(&"STAT".to_string()));
+
+        let glyph = glyph_reader.read_glyph('I' as u16);
+        assert_eq!(
+            simple_glyph_points(&glyph),
+            vec![(110, 50), (110, 750), (244, 750), (244, 50)]
+        );
+        assert_eq!(glyph.advance_width(), 354);
+        assert_eq!(glyph.bounding_box(), Some((110, 50, 244, 750)));
+        assert_eq!(glyph_reader.os2_table().unwrap().us_weight_class, 900);
+        assert_eq!(glyph_reader.hhea_table().num_of_long_hor_metrics, 617);
+    }
+
+    #[test]
+    fn instantiate_keeps_instructions_and_varies_cvt() {
+        let mut glyph_reader = GlyphReader::from_vec(mk_golos_variable_font());
+        let glyph_id = glyph_reader.char_code_to_glyph_id(b'H' as u16);
+        let mut tables = mk_glyf_with_instructions(&[glyph_id], &[0xB1, 0, 0, 0x3E]);
+        tables.push(("maxp", mk_hinting_maxp_table()));
+        tables.push(("cvt ", be_u16s(&[100])));
+        tables.push(("cvar", mk_cvar_table(100)));
+        let mut variable_reader = GlyphReader::from_vec(mk_golos_variable_font_with_tables(tables));
+
+        let font = variable_reader.instantiate(&[("wght", 650.0)]).unwrap();
+        let mut glyph_reader = GlyphReader::from_vec(font);
+        assert!(!glyph_reader.table_tags().contains(&"cvar".to_string()));
+        assert_eq!(glyph_reader.cvt_table().unwrap().values, vec![150]);
+        let Glyph::Simple { instructions, .. } = glyph_reader.glyph_for_glyph_id(glyph_id) else {
+            panic!("Expected a simple glyph");
+        };
+        assert_eq!(instructions, vec![0xB1, 0, 0, 0x3E]);
+        let hinted = glyph_reader.hinted_glyph(glyph_id, 20).unwrap();
+        assert_eq!(hinted.contours[0][0].0, 3.0);
+    }
+
+    #[test]
+    fn component_transform_from_flags() {
+        let component = |flags: u16, a: i16, d: i16| ComponentData {
+            flags,
+            glyph_index: 1,
+            a,
+            b: 0,
+            c: 0,
+            d,
+            argument_types: ArgumentTypes::XYValue8(0, 0),
+        };
+        // (1, 0, 0, 1) as read for a component without a transform
+        assert!(!component(0, 1, 1).has_transform());
+        assert_eq!(component(0, 1, 1).transform(), (1.0, 0.0, 0.0, 1.0));
+        // A scale of 1 in F2DOT14 is not the identity
+        let scale = 1.0 / 16384.0;
+        assert_eq!(
+            component(0x0008, 1, 1).transform(),
+            (scale, 0.0, 0.0, scale)
+        );
+        assert_eq!(
+            component(0x0040, 8192, -16384).transform(),
+            (0.5, 0.0, 0.0, -1.0)
+        );
+    }
+
+    #[test]
+    fn instantiate_cff2_font() {
+        let mut glyph_reader = mk_glyph_reader_vec("fonts/Zeyada_1-CFF2.otf");
+        assert_eq!(
+            glyph_reader.instantiate(&[("wght", 700.0)]),
+            Err(InstanceError::UnsupportedOutlines)
+        );
+    }
+
+    #[test]
+    fn instantiate_default_location_keeps_glyphs() {
+        let mut variable_reader = GlyphReader::from_vec(mk_golos_variable_font());
+        let mut glyph_reader = GlyphReader::from_vec(variable_reader.instantiate(&[]).unwrap());
+
+        (0..617).map(GlyphId::new).for_each(|glyph_id| {
+            let expected = variable_reader.glyph_for_glyph_id(glyph_id);
+            let glyph = glyph_reader.glyph_for_glyph_id(glyph_id);
+            assert_eq!(glyph.advance_width(), expected.advance_width());
+            assert_eq!(glyph.bounding_box(), expected.bounding_box());
+            match (&glyph, &expected) {
+                (Glyph::Simple { .. }, Glyph::Simple { .. }) => {
+                    assert_eq!(simple_glyph_points(&glyph), simple_glyph_points(&expected))
+                }
+                (
+                    Glyph::Compound { components, .. },
+                    Glyph::Compound {
+                        components: expected_components,
+                        ..
+                    },
+                ) => {
+                    let indices = |components: &[ComponentData]| -> Vec<u16> {
+                        components.iter().map(|c| c.glyph_index).collect()
+                    };
+                    assert_eq!(indices(components), indices(expected_components));
+                }
+                (Glyph::Empty { .. }, Glyph::Empty { .. }) => {}
+                _ => panic!("Glyph {:?} changed kind", glyph_id),
+            }
+        });
+        assert_eq!(glyph_reader.bounding_box(), variable_reader.bounding_box());
+        assert_eq!(glyph_reader.name(2), Some("Regular".to_string()));
+    }
+
+    #[test]
+    fn font_builder_round_trip() {
+        let original = fs::read("fonts/GolosText-Regular.ttf").unwrap();
+        let mut original_reader = mk_glyph_reader_vec("fonts/GolosText-Regular.ttf");
+
+        let mut font_builder = FontBuilder::from_glyph_reader(&mut original_reader);
+        font_builder
+            .add(original_reader.head_table())
+            .add(original_reader.maximum_profile_table())
+            .add(original_reader.hhea_table());
+        let font = font_builder.build();
+
+        // Same offset subtable and table checksums as the original
+        assert_eq!(font[0..12], original[0..12]);
+        let checksums = |font: &[u8]| -> Vec<(Vec<u8>, Vec<u8>)> {
+            (0..18)
+                .map(|index| 12 + 16 * index)
+                .map(|record| {
+                    (
+                        font[record..record + 4].to_vec(),
+                        font[record + 4..record + 8].to_vec(),
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(checksums(&font), checksums(&original));
+
+        let font_checksum = font.chunks(4).fold(0u32, |sum, chunk| {
+            let mut word = [0; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            sum.wrapping_add(u32::from_be_bytes(word))
+        });
+        assert_eq!(font_checksum, 0xB1B0AFBA);
+
+        let mut glyph_reader = GlyphReader::from_vec(font);
+        original_reader.table_tags().iter().for_each(|tag| {
+            let data = glyph_reader.table_data(tag).unwrap();
+            let original_data = original_reader.table_data(tag).unwrap();
+            if tag == "head" {
+                // Only checkSumAdjustment may differ, the tables are laid out in tag order
+                assert_eq!(data[..8], original_data[..8]);
+                assert_eq!(data[12..], original_data[12..]);
+            } else {
+                assert_eq!(data, original_data, "table {}", tag);
+            }
+        });
+        assert_eq!(
+            simple_glyph_points(&glyph_reader.read_glyph('I' as u16)),
+            vec![(110, 0), (110, 700), (204, 700), (204, 0)]
+        );
+    }
+
+    fn all_glyphs_of(glyph_reader: &mut GlyphReader) -> Vec<Glyph> {
+        let num_glyphs = glyph_reader.maximum_profile_table().num_glyphs;
+        (0..num_glyphs)
+            .map(|glyph_id| glyph_reader.glyph_for_glyph_id(GlyphId::new(glyph_id)))
+            .collect()
+    }
+
+    #[test]
+    fn glyf_encoder_round_trip_golos() {
+        let mut glyph_reader = mk_glyph_reader_vec("fonts/GolosText-Regular.ttf");
+        let glyphs = all_glyphs_of(&mut glyph_reader);
+
+        let encoded = encode_glyf_and_loca(&glyphs, 2).unwrap();
+
+        assert_eq!(encoded.index_to_loc_format, 0);
+        assert_eq!(encoded.glyf, glyph_reader.table_data("glyf").unwrap());
+        assert_eq!(encoded.loca, glyph_reader.table_data("loca").unwrap());
+    }
+
+    #[test]
+    fn glyf_encoder_round_trip_zeyada() {
+        let mut glyph_reader = mk_glyph_reader_vec("fonts/Zeyada_1.ttf");
+        let glyphs = all_glyphs_of(&mut glyph_reader);
+
+        // Zeyada writes a repeat count for runs of two flags, which takes the same space
+        let encoded = encode_glyf_and_loca(&glyphs, 4).unwrap();
+        assert_eq!(
+            encoded.glyf.len(),
+            glyph_reader.table_data("glyf").unwrap().len()
+        );
+
+        let font = mk_font_with_tables(
+            "fonts/Zeyada_1.ttf",
+            vec![("glyf", encoded.glyf), ("loca", encoded.loca)],
+        );
+        let mut encoded_reader = GlyphReader::from_vec(font);
+        all_glyphs_of(&mut encoded_reader)
+            .iter()
+            .zip(&glyphs)
+            .for_each(|(glyph, expected)| {
+                assert_eq!(glyph.bounding_box(), expected.bounding_box());
+                if let Glyph::Simple { .. } = expected {
+                    assert_eq!(simple_glyph_points(glyph), simple_glyph_points(expected));
+                }
+            });
+    }
+
+    #[test]
+    fn glyf_encoder_cubic_glyphs() {
+        let mut glyph_reader = mk_glyph_reader_vec("fonts/Zeyada_1.otf");
+        let glyph_id = glyph_reader.char_code_to_glyph_id('a' as u16);
+        let glyph = glyph_reader.glyph_for_glyph_id(glyph_id);
+
+        assert_eq!(
+            encode_glyph(&glyph),
+            Err(GlyfEncodeError::CubicOutline(glyph_id))
+        );
+        assert_eq!(
+            encode_glyf_and_loca(&[glyph], 2).err(),
+            Some(GlyfEncodeError::CubicOutline(glyph_id))
+        );
+    }
+
+    #[test]
+    fn glyf_encoder_compound_glyph() {
+        let glyph = Glyph::Compound {
+            glyph_id: GlyphId::new(1),
+            x_min: FWord(0),
+            y_min: FWord(0),
+            x_max: FWord(500),
+            y_max: FWord(700),
+            advance_width: 500,
+            left_side_bearing: 0,
+            advance_height: 1000,
+            top_side_bearing: 300,
+            components: vec![
+                ComponentData {
+                    flags: 0x0200, // USE_MY_METRICS
+                    glyph_index: 2,
+                    a: 1,
+                    b: 0,
+                    c: 0,
+                    d: 1,
+                    argument_types: ArgumentTypes::XYValue16(10, -300),
+                },
+                ComponentData {
+                    flags: 0x0080, // WE_HAVE_A_TWO_BY_TWO
+                    glyph_index: 3,
+                    a: 8192,
+                    b: 0,
+                    c: 0,
+                    d: -16384,
+                    argument_types: ArgumentTypes::Point16(4, 2),
+                },
+            ],
+            instructions: vec![],
+        };
+
+        #[rustfmt::skip]
+        let expected: Vec<u8> = vec![
+            0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x01, 0xF4, 0x02, 0xBC,
+            0x02, 0x23, 0x00, 0x02, // USE_MY_METRICS | MORE_COMPONENTS | ARGS_ARE_XY_VALUES | WORDS
+            0x00, 0x0A, 0xFE, 0xD4,
+            0x00, 0x40, 0x00, 0x03, // WE_HAVE_AN_X_AND_Y_SCALE, byte point numbers
+            0x04, 0x02,
+            0x20, 0x00, 0xC0, 0x00,
+        ];
+        assert_eq!(encode_glyph(&glyph).unwrap(), expected);
+
+        // A scale of 1 in F2DOT14 is kept, the values alone do not make an identity
+        let mut glyph = glyph;
+        if let Glyph::Compound { components, .. } = &mut glyph {
+            components[1] = ComponentData {
+                flags: 0x0008, // WE_HAVE_A_SCALE
+                glyph_index: 3,
+                a: 1,
+                b: 0,
+                c: 0,
+                d: 1,
+                argument_types: ArgumentTypes::Point8(4, 2),
+            };
         }
+        let encoded = encode_glyph(&glyph).unwrap();
+        assert_eq!(
+            &encoded[18..],
+            &[0x00, 0x08, 0x00, 0x03, 0x04, 0x02, 0x00, 0x01]
+        );
+    }
+
+    // Lookup count of a GSUB or GPOS table, and the subtable count of each lookup
+    fn lookup_subtable_counts(data: &[u8]) -> Vec<u16> {
+        let read_u16 = |offset: usize| u16::from_be_bytes([data[offset], data[offset + 1]]);
+        let lookup_list = read_u16(8) as usize;
+        (0..read_u16(lookup_list) as usize)
+            .map(|index| lookup_list + read_u16(lookup_list + 2 + index * 2) as usize)
+            .map(|lookup| read_u16(lookup + 4))
+            .collect()
+    }
+
+    #[test]
+    fn subset_golos() {
+        let font = fs::read("fonts/GolosText-Regular.ttf").unwrap();
+        let mut original_reader = GlyphReader::from_vec(font.clone());
+        let chars = ['H', 'e', 'l', 'o', 'é', 'A', 'V', '中'];
+
+        let subset_font = subset(&font, &chars).unwrap();
+        assert!(subset_font.len() < font.len() / 4);
+        let mut glyph_reader = GlyphReader::from_vec(subset_font);
+
+        // .notdef, the glyphs of the characters and the components of é
+        let num_glyphs = glyph_reader.maximum_profile_table().num_glyphs;
+        assert_eq!(num_glyphs, 9);
+        assert_eq!(glyph_reader.char_code_to_glyph_id('中' as u16).id(), 0);
+        assert_eq!(glyph_reader.char_code_to_glyph_id('x' as u16).id(), 0);
+
+        chars[..7].iter().for_each(|c| {
+            let glyph_id = glyph_reader.char_code_to_glyph_id(*c as u16);
+            assert_ne!(glyph_id.id(), 0);
+            let original_glyph_id = original_reader.char_code_to_glyph_id(*c as u16);
+            assert_eq!(
+                glyph_reader.advance_width(glyph_id),
+                original_reader.advance_width(original_glyph_id)
+            );
+            assert_eq!(
+                glyph_reader.advance_height(glyph_id),
+                original_reader.advance_height(original_glyph_id)
+            );
+
+            let glyph = glyph_reader.glyph_for_glyph_id(glyph_id);
+            let original_glyph = original_reader.glyph_for_glyph_id(original_glyph_id);
+            assert_eq!(glyph.bounding_box(), original_glyph.bounding_box());
+            assert_eq!(
+                glyph.left_side_bearing(),
+                original_glyph.left_side_bearing()
+            );
+            match (&glyph, &original_glyph) {
+                (Glyph::Simple { .. }, Glyph::Simple { .. }) => {
+                    assert_eq!(
+                        simple_glyph_points(&glyph),
+                        simple_glyph_points(&original_glyph)
+                    )
+                }
+                (
+                    Glyph::Compound { components, .. },
+                    Glyph::Compound {
+                        components: original_components,
+                        ..
+                    },
+                ) => components.iter().zip(original_components).for_each(
+                    |(component, original_component)| {
+                        let component_glyph =
+                            glyph_reader.glyph_for_glyph_id(GlyphId::new(component.glyph_index));
+                        let original_component_glyph = original_reader
+                            .glyph_for_glyph_id(GlyphId::new(original_component.glyph_index));
+                        assert_eq!(
+                            component_glyph.bounding_box(),
+                            original_component_glyph.bounding_box()
+                        );
+                    },
+                ),
+                _ => panic!("Glyph kinds differ for {}", c),
+            }
+        });
+
+        // Lookup indices stay stable, pair kerning survives for the kept glyphs
+        ["GSUB", "GPOS"].iter().for_each(|tag| {
+            let subset_counts = lookup_subtable_counts(&glyph_reader.table_data(tag).unwrap());
+            let original_counts = lookup_subtable_counts(&original_reader.table_data(tag).unwrap());
+            assert_eq!(subset_counts.len(), original_counts.len());
+        });
+        let gpos = glyph_reader.table_data("GPOS").unwrap();
+        assert!(lookup_subtable_counts(&gpos)[0] > 0);
+        assert!(glyph_reader.table_data("GDEF").is_some());
+        assert!(glyph_reader.table_data("prep").is_some());
+    }
+
+    #[test]
+    fn subset_with_options_drops_hinting_and_names() {
+        let font = fs::read("fonts/Zeyada_1.ttf").unwrap();
+        let options = SubsetOptions {
+            drop_hinting: true,
+            drop_names: true,
+        };
+        let subset_font = subset_with_options(&font, &['a', 'b'], &options).unwrap();
+        let mut glyph_reader = GlyphReader::from_vec(subset_font);
+
+        assert_eq!(glyph_reader.maximum_profile_table().num_glyphs, 3);
+        assert!(glyph_reader.table_data("prep").is_none());
+        assert!(glyph_reader.table_data("FFTM").is_none());
+        assert_eq!(glyph_reader.name(1), Some("Zeyada".to_string()));
+        assert_eq!(glyph_reader.name(13), None);
+
+        let glyph_id = glyph_reader.char_code_to_glyph_id('b' as u16);
+        assert_eq!(glyph_id.id(), 2);
+        let post = glyph_reader.table_data("post").unwrap();
+        assert_eq!(u16::from_be_bytes([post[32], post[33]]), 3);
+    }
+
+    #[test]
+    fn subset_cff_fonts() {
+        ["fonts/Zeyada_1.otf", "fonts/Zeyada_1-CFF2.otf"]
+            .iter()
+            .for_each(|path| {
+                let font = fs::read(path).unwrap();
+                assert_eq!(
+                    subset(&font, &['a', 'b']),
+                    Err(SubsetError::UnsupportedOutlines)
+                );
+            });
+    }
+
+    fn golos_text_cmap_builder(glyph_reader: &mut GlyphReader) -> CmapBuilder {
+        let mut cmap_builder = CmapBuilder::new();
+        glyph_reader
+            .all_char_codes()
+            .into_iter()
+            .filter_map(|char_code| char::from_u32(char_code as u32))
+            .for_each(|c| {
+                let glyph_id = glyph_reader.char_code_to_glyph_id(c as u16);
+                cmap_builder.insert(c, glyph_id.id());
+            });
+        cmap_builder
+    }
+
+    #[test]
+    fn cmap_builder_round_trip_golos() {
+        let mut original_reader = mk_glyph_reader_vec("fonts/GolosText-Regular.ttf");
+        let cmap_builder = golos_text_cmap_builder(&mut original_reader);
+
+        // glyphIdArray segments bridge the gaps between the font's own segments
+        // when that is cheaper than a segment per run, e.g. 1025..1045 takes in
+        // the unmapped 1037 and 1060..1077 ends where 5 consecutive glyph ids start
+        let segments = cmap_builder.segments();
+        assert_eq!(golos_text_expected_segments().len(), 92);
+        assert_eq!(segments.len(), 56);
+        assert_eq!(
+            segments[15..21]
+                .iter()
+                .map(|segment| (segment.start_code, segment.end_code, segment.id_delta()))
+                .collect::<Vec<(u16, u16, u16)>>(),
+            vec![
+                (1025, 1045, 0),
+                (1046, 1050, 64732),
+                (1051, 1059, 64733),
+                (1060, 1077, 0),
+                (1078, 1082, 64786),
+                (1083, 1091, 64787),
+            ]
+        );
+
+        let mut font_builder = FontBuilder::from_glyph_reader(&mut original_reader);
+        font_builder.add(&cmap_builder);
+        let mut glyph_reader = GlyphReader::from_vec(font_builder.build());
+
+        assert_eq!(glyph_reader.cmap_table_segments(), segments);
+        let cmap = glyph_reader.table_data("cmap").unwrap();
+        let original_cmap = original_reader.table_data("cmap").unwrap();
+        assert_eq!(cmap.len() + 146, original_cmap.len());
+
+        original_reader
+            .all_char_codes()
+            .iter()
+            .for_each(|char_code| {
+                assert_eq!(
+                    glyph_reader.char_code_to_glyph_id(*char_code),
+                    original_reader.char_code_to_glyph_id(*char_code)
+                );
+            });
+
+        // Unmapped chars within glyphIdArray segments
+        let char_codes = original_reader.all_char_codes();
+        segments
+            .iter()
+            .flat_map(|segment| segment.start_code..segment.end_code)
+            .filter(|char_code| !char_codes.contains(char_code))
+            .for_each(|char_code| {
+                assert_eq!(
+                    glyph_reader.char_code_to_glyph_id(char_code),
+                    GlyphId::MISSING_CHARACTER_GLYPH
+                );
+            });
+    }
+
+    #[test]
+    fn cmap_builder_format_12() {
+        let mut cmap_builder = CmapBuilder::new();
+        cmap_builder
+            .insert('a', 1)
+            .insert('b', 2)
+            .insert('c', 3)
+            .insert('d', 4)
+            .insert('e', 10)
+            .insert('😀', 4)
+            .insert('😁', 5);
+
+        // 'a'..'d' and 'e' as two id_delta segments take less than one glyphIdArray segment
+        let segments = cmap_builder.segments();
+        assert_eq!(
+            segments,
+            vec![
+                mk_segments!(0, 97, 100, 65440, 0),
+                mk_segments!(1, 101, 101, 65445, 0),
+                mk_segments!(2, 65535, 65535, 1, 0),
+            ]
+        );
+
+        #[rustfmt::skip]
+        let expected: Vec<u8> = vec![
+            0x00, 0x00, 0x00, 0x04,
+            0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x24, // Unicode BMP, format 4
+            0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x4C, // Unicode full, format 12
+            0x00, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00, 0x24, // Windows BMP, format 4
+            0x00, 0x03, 0x00, 0x0A, 0x00, 0x00, 0x00, 0x4C, // Windows full, format 12
+            // format 4: length, language, segCountX2, searchRange, entrySelector, rangeShift
+            0x00, 0x04, 0x00, 0x28, 0x00, 0x00, 0x00, 0x06, 0x00, 0x04, 0x00, 0x01, 0x00, 0x02,
+            0x00, 0x64, 0x00, 0x65, 0xFF, 0xFF, 0x00, 0x00, // end codes, reservedPad
+            0x00, 0x61, 0x00, 0x65, 0xFF, 0xFF, // start codes
+            0xFF, 0xA0, 0xFF, 0xA5, 0x00, 0x01, // id deltas
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // id range offsets
+            // format 12: one group for 'a'..'d', one for 'e' and one for both emojis
+            0x00, 0x0C, 0x00, 0x00, 0x00, 0x00, 0x00, 0x34, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03,
+            0x00, 0x00, 0x00, 0x61, 0x00, 0x00, 0x00, 0x64, 0x00, 0x00, 0x00, 0x01,
+            0x00, 0x00, 0x00, 0x65, 0x00, 0x00, 0x00, 0x65, 0x00, 0x00, 0x00, 0x0A,
+            0x00, 0x01, 0xF6, 0x00, 0x00, 0x01, 0xF6, 0x01, 0x00, 0x00, 0x00, 0x04,
+        ];
+        assert_eq!(cmap_builder.build(), expected);
+    }
+
+    #[test]
+    fn cmap_builder_truncated_format_4() {
+        // Scattered glyph ids for the whole BMP need more than 64K of glyphIdArray
+        let mut cmap_builder = CmapBuilder::new();
+        (0x20..0xFFFF).filter_map(char::from_u32).for_each(|c| {
+            cmap_builder.insert(c, (c as u32 * 7919 % 60000) as u16 + 1);
+        });
+        let segments = cmap_builder.segments();
+        let last_code = segments[segments.len() - 2].end_code;
+        assert_eq!(segments.last().unwrap().start_code, 0xFFFF);
+        assert!(last_code < 0xD800);
+
+        let cmap = cmap_builder.build();
+        let read_u16 = |offset: usize| u16::from_be_bytes([cmap[offset], cmap[offset + 1]]);
+        let read_u32 =
+            |offset: usize| u32::from_be_bytes(cmap[offset..offset + 4].try_into().unwrap());
+        // Format 12 follows the format 4 subtable and maps every char
+        assert_eq!(read_u16(2), 4);
+        let format_4_offset = read_u32(8) as usize;
+        let format_12_offset = read_u32(16) as usize;
+        assert_eq!(read_u16(format_4_offset), 4);
+        assert_eq!(
+            format_4_offset + read_u16(format_4_offset + 2) as usize,
+            format_12_offset
+        );
+        assert_eq!(read_u16(format_12_offset), 12);
+        assert_eq!(read_u32(format_12_offset + 12), 0xFFFF - 0x20 - 0x800);
+
+        let font = mk_font_with_tables("fonts/GolosText-Regular.ttf", vec![("cmap", cmap)]);
+        let mut glyph_reader = GlyphReader::from_vec(font);
+        assert_eq!(glyph_reader.cmap_table_segments(), segments);
+        [0x20, 0x4E2D, last_code].iter().for_each(|char_code| {
+            assert_eq!(
+                glyph_reader.char_code_to_glyph_id(*char_code).id(),
+                (*char_code as u32 * 7919 % 60000) as u16 + 1
+            );
+        });
+        assert_eq!(glyph_reader.char_code_to_glyph_id(last_code + 1).id(), 0);
+    }
+
+    #[test]
+    fn collection_fonts() {
+        let collection = mk_collection(&["fonts/GolosText-Regular.ttf", "fonts/Zeyada_1.ttf"], 1);
+        assert_eq!(GlyphReader::font_count(&collection), 2);
+        assert_eq!(
+            GlyphReader::font_count(&fs::read("fonts/Zeyada_1.ttf").unwrap()),
+            1
+        );
+
+        let mut golos_text = GlyphReader::from_collection(collection.clone(), 0);
+        let mut zeyada = GlyphReader::from_collection(collection, 1);
+        let mut golos_text_file = mk_glyph_reader_vec("fonts/GolosText-Regular.ttf");
+        let mut zeyada_file = mk_glyph_reader_vec("fonts/Zeyada_1.ttf");
+
+        assert_eq!(golos_text.table_tags(), golos_text_file.table_tags());
+        assert_eq!(zeyada.table_tags(), zeyada_file.table_tags());
+        assert_eq!(
+            zeyada.table_data("glyf").unwrap(),
+            zeyada_file.table_data("glyf").unwrap()
+        );
+        ['H', 'a', 'é'].iter().for_each(|c| {
+            let char_code = *c as u16;
+            assert_eq!(
+                format!("{:?}", golos_text.read_glyph(char_code)),
+                format!("{:?}", golos_text_file.read_glyph(char_code))
+            );
+            assert_eq!(
+                format!("{:?}", zeyada.read_glyph(char_code)),
+                format!("{:?}", zeyada_file.read_glyph(char_code))
+            );
+        });
+    }
+
+    #[test]
+    fn collection_version_2_shared_tables() {
+        let collection = mk_collection(&["fonts/Zeyada_1.ttf", "fonts/Zeyada_1.ttf"], 2);
+        assert_eq!(GlyphReader::font_count(&collection), 2);
+
+        // Both directories point to the same table data
+        let font_directory_offset = |index: usize| {
+            let offset = 12 + 4 * index;
+            u32::from_be_bytes(collection[offset..offset + 4].try_into().unwrap()) as usize
+        };
+        let first = font_directory_offset(0);
+        let second = font_directory_offset(1);
+        assert_eq!(second - first, 12 + 16 * 13);
+        assert_eq!(
+            collection[first + 12..second],
+            collection[second + 12..second + 12 + 16 * 13]
+        );
+
+        let mut zeyada_file = mk_glyph_reader_vec("fonts/Zeyada_1.ttf");
+        (0..2).for_each(|index| {
+            let mut zeyada = GlyphReader::from_collection(collection.clone(), index);
+            assert_eq!(
+                format!("{:?}", zeyada.glyph_for_glyph_id(GlyphId::new(98))),
+                format!("{:?}", zeyada_file.glyph_for_glyph_id(GlyphId::new(98)))
+            );
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Font 2 not found in collection")]
+    fn collection_font_index_out_of_range() {
+        let collection = mk_collection(&["fonts/Zeyada_1.ttf", "fonts/Zeyada_1.ttf"], 1);
+        GlyphReader::from_collection(collection, 2);
+    }
+
+    #[test]
+    fn decode_woff_zeyada() {
+        let woff = fs::read("fonts/Zeyada_1.woff").unwrap();
+        let mut glyph_reader = GlyphReader::from_vec(decode_woff(&woff).unwrap());
+        let mut original_reader = mk_glyph_reader_vec("fonts/Zeyada_1.ttf");
+
+        // Tables are laid out in tag order, so only head.checkSumAdjustment changes
+        let tags = original_reader.table_tags();
+        assert_eq!(glyph_reader.table_tags().len(), tags.len());
+        tags.iter().filter(|tag| *tag != "head").for_each(|tag| {
+            assert_eq!(
+                glyph_reader.table_data(tag),
+                original_reader.table_data(tag)
+            );
+        });
+        assert_eq!(
+            format!("{:?}", glyph_reader.read_glyph('a' as u16)),
+            format!("{:?}", original_reader.read_glyph('a' as u16))
+        );
+    }
+
+    #[test]
+    fn decode_woff_malformed() {
+        let woff = fs::read("fonts/Zeyada_1.woff").unwrap();
+        let patched = |offset: usize, value: u32| {
+            let mut woff = woff.clone();
+            woff[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+            woff
+        };
+        let orig_length = |index: usize| {
+            let offset = 44 + index * 20 + 12;
+            u32::from_be_bytes(woff[offset..offset + 4].try_into().unwrap())
+        };
+        let comp_length = |index: usize| {
+            let offset = 44 + index * 20 + 8;
+            u32::from_be_bytes(woff[offset..offset + 4].try_into().unwrap())
+        };
+        let compressed = (0..13)
+            .find(|index| comp_length(*index) < orig_length(*index))
+            .unwrap();
+
+        assert_eq!(decode_woff(&woff[..40]), Err(WoffError::NotWoff));
+        assert_eq!(decode_woff(&woff[..100]), Err(WoffError::OutOfBounds));
+        assert_eq!(
+            decode_woff(&woff[..woff.len() - 100]),
+            Err(WoffError::OutOfBounds)
+        );
+        assert_eq!(
+            decode_woff(&patched(16, 1000)),
+            Err(WoffError::SizeLimitExceeded)
+        );
+        // Inflating stops at the declared length
+        let offset = 44 + compressed * 20 + 12;
+        assert_eq!(
+            decode_woff(&patched(offset, orig_length(compressed) - 1)),
+            Err(WoffError::InvalidTable)
+        );
+    }
+
+    #[test]
+    fn encode_woff_round_trip() {
+        ["fonts/Zeyada_1.ttf", "fonts/GolosText-Regular.ttf"]
+            .iter()
+            .for_each(|file_path| {
+                let font = fs::read(file_path).unwrap();
+                let woff = encode_woff(&font).unwrap();
+                assert!(woff.len() < font.len());
+                let mut glyph_reader = GlyphReader::from_vec(decode_woff(&woff).unwrap());
+                let mut original_reader = mk_glyph_reader_vec(file_path);
+
+                let tags = original_reader.table_tags();
+                assert_eq!(glyph_reader.table_tags(), tags);
+                tags.iter().filter(|tag| *tag != "head").for_each(|tag| {
+                    assert_eq!(
+                        glyph_reader.table_data(tag),
+                        original_reader.table_data(tag)
+                    );
+                });
+            });
+    }
+
+    #[test]
+    fn encode_woff_invalid_fonts() {
+        let font = fs::read("fonts/Zeyada_1.ttf").unwrap();
+        assert_eq!(encode_woff(&font[..10]), Err(WoffError::NotSfnt));
+        assert_eq!(encode_woff(&font[..100]), Err(WoffError::OutOfBounds));
+        assert_eq!(encode_woff(&font[..1000]), Err(WoffError::OutOfBounds));
+        let collection = mk_collection(&["fonts/Zeyada_1.ttf"], 1);
+        assert_eq!(encode_woff(&collection), Err(WoffError::NotSfnt));
+
+        // A table whose offset plus length overflows
+        let mut patched = font.clone();
+        patched[12 + 8..12 + 16].copy_from_slice(&[0xFF; 8]);
+        assert_eq!(encode_woff(&patched), Err(WoffError::OutOfBounds));
+
+        // Tables are copied as they are, head is not needed
+        let without_head = mk_font_without_head("fonts/Zeyada_1.ttf");
+        let woff = encode_woff(&without_head).unwrap();
+        assert_eq!(decode_woff(&woff).unwrap().len(), without_head.len());
+    }
+
+    #[test]
+    fn zlib_decompress_block_types() {
+        // Stored block
+        let stored: Vec<u8> = vec![
+            120, 1, 1, 12, 0, 243, 255, 115, 116, 111, 114, 101, 100, 32, 98, 108, 111, 99, 107,
+            31, 128, 4, 189,
+        ];
+        assert_eq!(zlib_decompress(&stored, 12).unwrap(), b"stored block");
+        assert_eq!(
+            zlib_decompress(&stored, 11),
+            Err(InflateError::LimitExceeded)
+        );
+
+        // Fixed Huffman codes with an overlapping back reference
+        let fixed: Vec<u8> = vec![120, 218, 75, 76, 74, 78, 68, 69, 0, 65, 124, 6, 229];
+        assert_eq!(zlib_decompress(&fixed, 18).unwrap(), b"abcabcabcabcabcabc");
+        assert_eq!(
+            zlib_decompress(&fixed, 17),
+            Err(InflateError::LimitExceeded)
+        );
+    }
+
+    #[test]
+    fn zlib_compress_round_trip() {
+        let font = fs::read("fonts/GolosText-Regular.ttf").unwrap();
+        let repeated: Vec<u8> = (0..100_000)
+            .map(|index| (index % 7 * index % 3) as u8)
+            .collect();
+        [&b""[..], b"a", &font, &repeated].iter().for_each(|data| {
+            let compressed = zlib_compress(data);
+            assert_eq!(zlib_decompress(&compressed, data.len()).unwrap(), *data);
+        });
+        assert!(zlib_compress(&font).len() < font.len() * 2 / 3);
+    }
+
+    #[test]
+    fn zlib_decompress_malformed() {
+        let fixed: Vec<u8> = vec![120, 218, 75, 76, 74, 78, 68, 69, 0, 65, 124, 6, 228];
+        assert_eq!(
+            zlib_decompress(&fixed, 18),
+            Err(InflateError::ChecksumMismatch)
+        );
+        assert_eq!(
+            zlib_decompress(&fixed[..8], 18),
+            Err(InflateError::UnexpectedEnd)
+        );
+        assert_eq!(
+            zlib_decompress(&[120, 218, 0xFF, 0xFF, 0xFF, 0xFF], 18),
+            Err(InflateError::InvalidData)
+        );
+        assert_eq!(
+            zlib_decompress(&[0, 0, 0, 0, 0, 0], 18),
+            Err(InflateError::InvalidHeader)
+        );
+    }
+
+    #[test]
+    fn decode_woff2_zeyada() {
+        let woff2 = fs::read("fonts/Zeyada_1.woff2").unwrap();
+        let mut glyph_reader = GlyphReader::from_vec(decode_woff2(&woff2).unwrap());
+        let mut original_reader = mk_glyph_reader_vec("fonts/Zeyada_1.ttf");
+
+        // hmtx is rebuilt from its transformed version, glyf and loca can encode glyphs differently
+        let tags = original_reader.table_tags();
+        assert_eq!(glyph_reader.table_tags().len(), tags.len());
+        tags.iter()
+            .filter(|tag| !["head", "glyf", "loca"].contains(&tag.as_str()))
+            .for_each(|tag| {
+                assert_eq!(
+                    glyph_reader.table_data(tag),
+                    original_reader.table_data(tag)
+                );
+            });
+        assert_same_glyphs(&mut glyph_reader, &mut original_reader);
+    }
+
+    #[test]
+    fn decode_woff2_malformed() {
+        let woff2 = fs::read("fonts/Zeyada_1.woff2").unwrap();
+        let patched = |offset: usize, value: u32| {
+            let mut woff2 = woff2.clone();
+            woff2[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+            woff2
+        };
+        let total_compressed_size = u32::from_be_bytes(woff2[20..24].try_into().unwrap());
+
+        assert_eq!(decode_woff2(&woff2[..40]), Err(Woff2Error::NotWoff2));
+        assert_eq!(decode_woff2(&woff2[..60]), Err(Woff2Error::OutOfBounds));
+        assert_eq!(
+            decode_woff2(&woff2[..woff2.len() - 100]),
+            Err(Woff2Error::OutOfBounds)
+        );
+        assert_eq!(
+            decode_woff2(&patched(16, 1000)),
+            Err(Woff2Error::SizeLimitExceeded)
+        );
+        // A truncated Brotli stream
+        assert_eq!(
+            decode_woff2(&patched(20, total_compressed_size - 100)),
+            Err(Woff2Error::InvalidData)
+        );
+    }
+
+    fn assert_same_glyphs(glyph_reader: &mut GlyphReader, original_reader: &mut GlyphReader) {
+        let num_glyphs = original_reader.maximum_profile_table().num_glyphs;
+        (0..num_glyphs).for_each(|glyph_id| {
+            assert_eq!(
+                format!(
+                    "{:?}",
+                    glyph_reader.glyph_for_glyph_id(GlyphId::new(glyph_id))
+                ),
+                format!(
+                    "{:?}",
+                    original_reader.glyph_for_glyph_id(GlyphId::new(glyph_id))
+                )
+            );
+        });
+    }
+
+    #[test]
+    fn encode_woff2_round_trip() {
+        ["fonts/Zeyada_1.ttf", "fonts/GolosText-Regular.ttf"]
+            .iter()
+            .for_each(|file_path| {
+                let font = fs::read(file_path).unwrap();
+                let woff2 = encode_woff2(&font).unwrap();
+                assert!(woff2.len() < encode_woff(&font).unwrap().len());
+                let mut glyph_reader = GlyphReader::from_vec(decode_woff2(&woff2).unwrap());
+                let mut original_reader = mk_glyph_reader_vec(file_path);
+
+                // glyf and loca are rebuilt, head is flagged as transformed
+                let tags = original_reader.table_tags();
+                assert_eq!(glyph_reader.table_tags(), tags);
+                tags.iter()
+                    .filter(|tag| !["head", "glyf", "loca"].contains(&tag.as_str()))
+                    .for_each(|tag| {
+                        assert_eq!(
+                            glyph_reader.table_data(tag),
+                            original_reader.table_data(tag)
+                        );
+                    });
+                let head = glyph_reader.table_data("head").unwrap();
+                assert_eq!(head[16] & 0x08, 0x08);
+                assert_same_glyphs(&mut glyph_reader, &mut original_reader);
+            });
+    }
+
+    #[test]
+    fn encode_woff2_invalid_fonts() {
+        let font = fs::read("fonts/Zeyada_1.ttf").unwrap();
+        assert_eq!(encode_woff2(&font[..10]), Err(Woff2Error::NotSfnt));
+        assert_eq!(encode_woff2(&font[..100]), Err(Woff2Error::OutOfBounds));
+        assert_eq!(encode_woff2(&font[..1000]), Err(Woff2Error::OutOfBounds));
+        let collection = mk_collection(&["fonts/Zeyada_1.ttf"], 1);
+        assert_eq!(encode_woff2(&collection), Err(Woff2Error::NotSfnt));
+
+        let mut patched = font.clone();
+        patched[12 + 8..12 + 16].copy_from_slice(&[0xFF; 8]);
+        assert_eq!(encode_woff2(&patched), Err(Woff2Error::OutOfBounds));
+
+        // glyf is not transformed without head, which holds the loca format
+        let without_head = mk_font_without_head("fonts/Zeyada_1.ttf");
+        let woff2 = encode_woff2(&without_head).unwrap();
+        assert_eq!(decode_woff2(&woff2).unwrap().len(), without_head.len());
+
+        let short_head = mk_font_with_tables("fonts/Zeyada_1.ttf", vec![("head", vec![0; 20])]);
+        assert_eq!(encode_woff2(&short_head), Err(Woff2Error::InvalidTable));
+        let mut glyph_reader = mk_glyph_reader_vec("fonts/Zeyada_1.ttf");
+        let glyf = glyph_reader.table_data("glyf").unwrap()[..100].to_vec();
+        let short_glyf = mk_font_with_tables("fonts/Zeyada_1.ttf", vec![("glyf", glyf)]);
+        assert_eq!(encode_woff2(&short_glyf), Err(Woff2Error::OutOfBounds));
+    }
+
+    #[test]
+    fn decode_woff2_collection() {
+        let woff2 = fs::read("fonts/GolosText-Zeyada.woff2").unwrap();
+        let collection = decode_woff2(&woff2).unwrap();
+        assert_eq!(GlyphReader::font_count(&collection), 2);
+
+        ["fonts/GolosText-Regular.ttf", "fonts/Zeyada_1.ttf"]
+            .iter()
+            .enumerate()
+            .for_each(|(index, file_path)| {
+                let mut glyph_reader = GlyphReader::from_collection(collection.clone(), index);
+                let mut original_reader = mk_glyph_reader_vec(file_path);
+                let tags = original_reader.table_tags();
+                assert_eq!(glyph_reader.table_tags(), tags);
+                tags.iter()
+                    .filter(|tag| !["glyf", "loca"].contains(&tag.as_str()))
+                    .for_each(|tag| {
+                        assert_eq!(
+                            glyph_reader.table_data(tag),
+                            original_reader.table_data(tag)
+                        );
+                    });
+                assert_same_glyphs(&mut glyph_reader, &mut original_reader);
+            });
+
+        // gasp and prep are the same in both fonts and stored once
+        let table_offset = |font_directory: usize, tag: &str| {
+            let read_u32 = |offset: usize| {
+                u32::from_be_bytes(collection[offset..offset + 4].try_into().unwrap()) as usize
+            };
+            let directory = read_u32(12 + 4 * font_directory);
+            let num_tables =
+                u16::from_be_bytes([collection[directory + 4], collection[directory + 5]]);
+            (0..num_tables as usize)
+                .map(|index| directory + 12 + 16 * index)
+                .find(|record| &collection[*record..*record + 4] == tag.as_bytes())
+                .map(|record| read_u32(record + 8))
+                .unwrap()
+        };
+        assert_eq!(table_offset(0, "gasp"), table_offset(1, "gasp"));
+        assert_eq!(table_offset(0, "prep"), table_offset(1, "prep"));
+        assert_ne!(table_offset(0, "glyf"), table_offset(1, "glyf"));
+    }
+
+    // SHA-256 (FIPS 180-4), only to check the bundled Brotli dictionary
+    fn sha256(data: &[u8]) -> [u8; 32] {
+        #[rustfmt::skip]
+        const K: [u32; 64] = [
+            0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+            0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+            0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+            0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+            0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+            0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+            0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+            0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+        ];
+        let mut hash: [u32; 8] = [
+            0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+            0x5be0cd19,
+        ];
+        let mut message = data.to_vec();
+        message.push(0x80);
+        message.resize((message.len() + 8).div_ceil(64) * 64, 0);
+        let length = message.len();
+        message[length - 8..].copy_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+        message.chunks_exact(64).for_each(|block| {
+            let mut w = [0u32; 64];
+            (0..64).for_each(|i| {
+                w[i] = if i < 16 {
+                    u32::from_be_bytes(block[i * 4..i * 4 + 4].try_into().unwrap())
+                } else {
+                    let s0 =
+                        w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+                    let s1 =
+                        w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+                    w[i - 16]
+                        .wrapping_add(s0)
+                        .wrapping_add(w[i - 7])
+                        .wrapping_add(s1)
+                };
+            });
+            let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = hash;
+            (0..64).for_each(|i| {
+                let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+                let ch = (e & f) ^ (!e & g);
+                let t1 = h
+                    .wrapping_add(s1)
+                    .wrapping_add(ch)
+                    .wrapping_add(K[i])
+                    .wrapping_add(w[i]);
+                let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+                let maj = (a & b) ^ (a & c) ^ (b & c);
+                let t2 = s0.wrapping_add(maj);
+                (h, g, f, e, d, c, b, a) =
+                    (g, f, e, d.wrapping_add(t1), c, b, a, t1.wrapping_add(t2));
+            });
+            [a, b, c, d, e, f, g, h]
+                .iter()
+                .enumerate()
+                .for_each(|(index, value)| hash[index] = hash[index].wrapping_add(*value));
+        });
+
+        let mut digest = [0u8; 32];
+        hash.iter().enumerate().for_each(|(index, value)| {
+            digest[index * 4..index * 4 + 4].copy_from_slice(&value.to_be_bytes())
+        });
+        digest
+    }
+
+    #[test]
+    fn brotli_dictionary_checksum() {
+        let hex = |digest: [u8; 32]| -> String {
+            digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+        };
+        assert_eq!(
+            hex(sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        // The hash given by RFC 7932 Appendix A
+        let dictionary = include_bytes!("brotli_dictionary.bin");
+        assert_eq!(dictionary.len(), 122784);
+        assert_eq!(
+            hex(sha256(dictionary)),
+            "20e42eb1b511c21806d4d227d07e5dd06877d8ce7b3a817f378f313653f35c70"
+        );
+    }
+
+    #[test]
+    fn brotli_decompress_dictionary_words() {
+        // Mostly static dictionary references, some of them uppercased
+        let compressed: Vec<u8> = vec![
+            27, 83, 0, 224, 37, 92, 90, 17, 96, 130, 17, 6, 108, 192, 25, 71, 65, 246, 111, 34,
+            157, 147, 212, 153, 88, 174, 163, 161, 0,
+        ];
+        assert_eq!(
+            brotli_decompress(&compressed, 85).unwrap(),
+            b"Information about the Government of the UNITED STATES, for example the Constitution."
+        );
+    }
+
+    #[test]
+    fn brotli_decompress_malformed() {
+        let compressed = brotli_compress(b"abababababababab");
+        assert_eq!(
+            brotli_decompress(&compressed, 15),
+            Err(BrotliError::LimitExceeded)
+        );
+        assert_eq!(
+            brotli_decompress(&compressed[..compressed.len() - 1], 16),
+            Err(BrotliError::UnexpectedEnd)
+        );
+        // Large window
+        assert_eq!(
+            brotli_decompress(&[0x11, 0, 0, 0], 16),
+            Err(BrotliError::Unsupported)
+        );
+        // Metadata block with its reserved bit set
+        assert_eq!(
+            brotli_decompress(&[0x1C, 0, 0, 0], 16),
+            Err(BrotliError::InvalidData)
+        );
+    }
+
+    #[test]
+    fn brotli_compress_round_trip() {
+        let font = fs::read("fonts/GolosText-Regular.ttf").unwrap();
+        let repeated: Vec<u8> = (0..100_000)
+            .map(|index| (index % 7 * index % 3) as u8)
+            .collect();
+        [&b""[..], b"a", b"abababababababab", &font, &repeated]
+            .iter()
+            .for_each(|data| {
+                let compressed = brotli_compress(data);
+                assert_eq!(brotli_decompress(&compressed, data.len()).unwrap(), *data);
+            });
+        assert!(brotli_compress(&font).len() < zlib_compress(&font).len());
+    }
+
+    // Zeyada_1.otf holds the Zeyada outlines converted to cubic curves, rounded
+    // half away from zero, with the closing line of each contour left implicit
+    fn quadratic_to_cubic(contour: &Contour) -> Vec<Point> {
+        let mut points: Vec<(f64, f64, bool)> = contour
+            .points
+            .iter()
+            .map(|point| {
+                (
+                    point.x as f64,
+                    point.y as f64,
+                    point.tpe == PointType::OnCurve,
+                )
+            })
+            .collect();
+        if points.iter().all(|point| !point.2) {
+            let (last, first) = (points[points.len() - 1], points[0]);
+            points.insert(
+                0,
+                ((last.0 + first.0) / 2.0, (last.1 + first.1) / 2.0, true),
+            );
+        }
+        let start = points.iter().position(|point| point.2).unwrap();
+        points.rotate_left(start);
+
+        let mut expanded = vec![];
+        for (index, point) in points.iter().enumerate() {
+            let next = points[(index + 1) % points.len()];
+            expanded.push(*point);
+            if !point.2 && !next.2 {
+                expanded.push(((point.0 + next.0) / 2.0, (point.1 + next.1) / 2.0, true));
+            }
+        }
+
+        let point = |(x, y): (f64, f64), on_curve: bool| {
+            Point::new(
+                x.round() as i16,
+                y.round() as i16,
+                PointType::from(on_curve),
+            )
+        };
+        let mut cubic = vec![point((expanded[0].0, expanded[0].1), true)];
+        let mut current = expanded[0];
+        let mut index = 1;
+        while index <= expanded.len() {
+            let p = expanded[index % expanded.len()];
+            if p.2 {
+                cubic.push(point((p.0, p.1), true));
+                current = p;
+                index += 1;
+            } else {
+                let end = expanded[(index + 1) % expanded.len()];
+                let control = |from: (f64, f64, bool)| {
+                    (
+                        from.0 + 2.0 / 3.0 * (p.0 - from.0),
+                        from.1 + 2.0 / 3.0 * (p.1 - from.1),
+                    )
+                };
+                cubic.push(point(control(current), false));
+                cubic.push(point(control(end), false));
+                cubic.push(point((end.0, end.1), true));
+                current = end;
+                index += 2;
+            }
+        }
+        if cubic.last() == cubic.first() {
+            cubic.pop();
+        }
+        cubic
+    }
+
+    #[test]
+    fn cff_glyphs_zeyada() {
+        let mut ttf_reader = mk_glyph_reader_vec("fonts/Zeyada_1.ttf");
+        let mut otf_reader = mk_glyph_reader_file("fonts/Zeyada_1.otf");
+        assert_eq!(otf_reader.maximum_profile_table().num_glyphs, 346);
+
+        for glyph_id in 0..346 {
+            let glyph_id = GlyphId::new(glyph_id);
+            let quadratic = ttf_reader.glyph_for_glyph_id(glyph_id);
+            let cubic = otf_reader.glyph_for_glyph_id(glyph_id);
+            assert_eq!(glyph_id_of(&cubic), glyph_id.id());
+            assert_eq!(cubic.advance_width(), quadratic.advance_width());
+
+            match (&quadratic, &cubic) {
+                (Glyph::Empty { .. }, Glyph::Empty { .. }) => {}
+                (
+                    Glyph::Simple {
+                        contours: quadratic_contours,
+                        ..
+                    },
+                    Glyph::Cubic { contours, .. },
+                ) => {
+                    let expected: Vec<Vec<Point>> =
+                        quadratic_contours.iter().map(quadratic_to_cubic).collect();
+                    let points: Vec<Vec<Point>> = contours
+                        .iter()
+                        .map(|contour| contour.points.clone())
+                        .collect();
+                    assert_eq!(points, expected, "{:?}", glyph_id);
+                }
+                (Glyph::Compound { .. }, Glyph::Cubic { .. }) => {}
+                _ => panic!("{:?} differs: {:?} {:?}", glyph_id, quadratic, cubic),
+            }
+
+            // The curves pass through the on curve points and stay within the
+            // hull of their control points
+            if let Glyph::Cubic { contours, .. } = &cubic {
+                let bounds = |on_curve_only: bool| {
+                    let points = contours.iter().flat_map(|contour| contour.points.iter());
+                    points
+                        .filter(|point| !on_curve_only || point.tpe == PointType::OnCurve)
+                        .fold((i16::MAX, i16::MAX, i16::MIN, i16::MIN), |b, point| {
+                            (
+                                b.0.min(point.x),
+                                b.1.min(point.y),
+                                b.2.max(point.x),
+                                b.3.max(point.y),
+                            )
+                        })
+                };
+                let (inner, outer) = (bounds(true), bounds(false));
+                let (x_min, y_min, x_max, y_max) = cubic.bounding_box().unwrap();
+                assert!(outer.0 <= x_min && x_min <= inner.0, "{:?}", glyph_id);
+                assert!(outer.1 <= y_min && y_min <= inner.1, "{:?}", glyph_id);
+                assert!(inner.2 <= x_max && x_max <= outer.2, "{:?}", glyph_id);
+                assert!(inner.3 <= y_max && y_max <= outer.3, "{:?}", glyph_id);
+            }
+        }
+    }
+
+    #[test]
+    fn cff_seac_zeyada() {
+        // aacute is made of a and acute by endchar with four arguments
+        let mut otf_reader = mk_glyph_reader_vec("fonts/Zeyada_1.otf");
+        let contours = |glyph: Glyph| match glyph {
+            Glyph::Cubic { contours, .. } => contours,
+            _ => panic!("Expected Cubic glyph"),
+        };
+        let aacute = contours(otf_reader.read_glyph(0xE1));
+        let a = contours(otf_reader.read_glyph(b'a' as u16));
+        let acute = contours(otf_reader.read_glyph(0xB4));
+
+        assert_eq!(aacute.len(), a.len() + acute.len());
+        assert_eq!(aacute[..a.len()], a[..]);
+        let (dx, dy) = (
+            aacute[a.len()].points[0].x - acute[0].points[0].x,
+            aacute[a.len()].points[0].y - acute[0].points[0].y,
+        );
+        aacute[a.len()..]
+            .iter()
+            .zip(acute.iter())
+            .for_each(|(shifted, accent)| {
+                shifted
+                    .points
+                    .iter()
+                    .zip(accent.points.iter())
+                    .for_each(|(shifted, point)| {
+                        assert_eq!((shifted.x, shifted.y), (point.x + dx, point.y + dy));
+                    });
+            });
+    }
+
+    #[test]
+    fn charstring_operators() {
+        let operand = |value: i32| (value + 139) as u8;
+        let mut charstring = vec![];
+        let mut push = |operands: &[i32], operator: &[u8]| {
+            charstring.extend(operands.iter().map(|value| operand(*value)));
+            charstring.extend(operator);
+        };
+        push(&[100, 10, 20], &[21]); // rmoveto with width
+        push(&[5, 10, 20, 30, 40], &[27]); // hhcurveto
+        push(&[10, 20, 30, 40], &[26]); // vvcurveto
+        push(&[10, 20, 30, 40, 50], &[31]); // hvcurveto
+        push(&[10, 20, 30, 40], &[30]); // vhcurveto
+        push(&[1, 2, 3, 4, 5, 6, 7, 8], &[24]); // rcurveline
+        push(&[1, 2, 1, 2, 3, 4, 5, 6], &[25]); // rlinecurve
+        push(&[10, 20, 30, 40, 50, 60, 70], &[12, 34]); // hflex
+        push(&[10, 10, 10, 10, 10, 10, 10, -10, 10, -10, 5], &[12, 37]); // flex1
+        push(&[3, 4], &[12, 10]); // add
+        push(&[2], &[12, 24]); // mul
+        push(&[0], &[5]); // rlineto
+        push(&[5], &[12, 27]); // dup
+        push(&[], &[12, 11]); // sub
+        push(&[-9], &[5]); // rlineto
+        push(&[], &[14]); // endchar
+
+        let outline = execute_charstring(&charstring, &[], &[], None).unwrap();
+        assert_eq!(outline.width, Some(100.0));
+        assert_eq!(outline.seac, None);
+        let points: Vec<(i16, i16, bool)> = outline.to_contours(0.0, 0.0)[0]
+            .points
+            .iter()
+            .map(|point| (point.x, point.y, point.tpe == PointType::OnCurve))
+            .collect();
+        #[rustfmt::skip]
+        let expected = vec![
+            (10, 20, true),
+            (20, 25, false), (40, 55, false), (80, 55, true),
+            (80, 65, false), (100, 95, false), (100, 135, true),
+            (110, 135, false), (130, 165, false), (180, 205, true),
+            (180, 215, false), (200, 245, false), (240, 245, true),
+            (241, 247, false), (244, 251, false), (249, 257, true), (256, 265, true),
+            (257, 267, true), (258, 269, false), (261, 273, false), (266, 279, true),
+            (276, 279, false), (296, 309, false), (336, 309, true),
+            (386, 309, false), (446, 279, false), (516, 279, true),
+            (526, 289, false), (536, 299, false), (546, 309, true),
+            (556, 299, false), (566, 289, false), (571, 279, true),
+            (585, 279, true), (585, 270, true),
+        ];
+        assert_eq!(points, expected);
+    }
+
+    #[test]
+    fn charstring_errors() {
+        let operand = |value: i32| (value + 139) as u8;
+        let execute = |charstring: &[u8]| execute_charstring(charstring, &[], &[], None).err();
+        assert_eq!(
+            execute(&[operand(1), operand(2), 5]),
+            Some(CharstringError::NoCurrentPoint)
+        );
+        assert_eq!(
+            execute(&[operand(1), 21]),
+            Some(CharstringError::StackUnderflow)
+        );
+        assert_eq!(
+            execute(&[operand(0); 49]),
+            Some(CharstringError::StackOverflow)
+        );
+        assert_eq!(execute(&[0]), Some(CharstringError::InvalidOperator(0)));
+        assert_eq!(
+            execute(&[12, 0]),
+            Some(CharstringError::InvalidOperator(1200))
+        );
+        assert_eq!(execute(&[28, 1]), Some(CharstringError::UnexpectedEnd));
+        assert_eq!(
+            execute(&[operand(0), 10]),
+            Some(CharstringError::InvalidSubroutine(107))
+        );
+        // A subroutine calling itself
+        let subrs = vec![vec![operand(-107), 10]];
+        assert_eq!(
+            execute_charstring(&[operand(-107), 10], &[], &subrs, None).err(),
+            Some(CharstringError::SubroutineDepth)
+        );
+
+        // The glyph of an invalid charstring is empty
+        let mut font = fs::read("fonts/Zeyada_1.otf").unwrap();
+        let read_u32 = |font: &[u8], offset: usize| {
+            u32::from_be_bytes(font[offset..offset + 4].try_into().unwrap()) as usize
+        };
+        let num_tables = u16::from_be_bytes([font[4], font[5]]) as usize;
+        let cff_offset = (0..num_tables)
+            .map(|index| 12 + 16 * index)
+            .find(|record| &font[*record..*record + 4] == b"CFF ")
+            .map(|record| read_u32(&font, record + 8))
+            .unwrap();
+        let glyph = read_char_code_file('a' as u16, "fonts/Zeyada_1.otf");
+        let Glyph::Cubic { glyph_id, .. } = glyph else {
+            panic!("Expected a cubic glyph");
+        };
+
+        let cff = &font[cff_offset..];
+        let (_, top_dict_index) = read_index(cff, cff[2] as usize);
+        let (top_dicts, _) = read_index(cff, top_dict_index);
+        let char_strings = parse_dict(&top_dicts[0])[&17][0] as usize;
+        let count = u16::from_be_bytes([cff[char_strings], cff[char_strings + 1]]) as usize;
+        let offset_size = cff[char_strings + 2] as usize;
+        let glyph_offset = cff[char_strings + 3 + glyph_id.id() as usize * offset_size..]
+            [..offset_size]
+            .iter()
+            .fold(0, |value, byte| (value << 8) | *byte as usize);
+        let data_start = char_strings + 3 + (count + 1) * offset_size - 1;
+        font[cff_offset + data_start + glyph_offset] = 0; // reserved operator
+
+        let mut glyph_reader = GlyphReader::from_vec(font);
+        let invalid_glyph = glyph_reader.glyph_for_glyph_id(glyph_id);
+        assert!(matches!(invalid_glyph, Glyph::Empty { .. }));
+        assert_eq!(invalid_glyph.advance_width(), glyph.advance_width());
+    }
+
+    fn shifted_contours(glyph: &Glyph, dx: i16) -> Vec<Vec<(i16, i16)>> {
+        match glyph {
+            Glyph::Cubic { contours, .. } => contours
+                .iter()
+                .map(|contour| {
+                    contour
+                        .points
+                        .iter()
+                        .map(|point| (point.x + dx, point.y))
+                        .collect()
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn cff2_variations_zeyada() {
+        // Zeyada_1-CFF2.otf blends the first rmoveto of each glyph: even glyphs
+        // use vsindex 0 and move 100 units right at wght 900, odd glyphs use
+        // vsindex 1 and also move 50 units left at wght 100. HVAR has the same
+        // deltas for the advance widths.
+        let mut ttf_reader = mk_glyph_reader_vec("fonts/Zeyada_1.ttf");
+        let mut otf_reader = mk_glyph_reader_vec("fonts/Zeyada_1.otf");
+        let mut cff2_reader = mk_glyph_reader_file("fonts/Zeyada_1-CFF2.otf");
+        assert!(cff2_reader.is_variable());
+
+        let default_glyphs: Vec<Glyph> = (0..346)
+            .map(|glyph_id| {
+                let glyph_id = GlyphId::new(glyph_id);
+                let glyph = cff2_reader.glyph_for_glyph_id(glyph_id);
+                let cff_glyph = otf_reader.glyph_for_glyph_id(glyph_id);
+                // Flattened components round differently from seac accents
+                if let Glyph::Compound { .. } = ttf_reader.glyph_for_glyph_id(glyph_id) {
+                    assert_eq!(glyph.advance_width(), cff_glyph.advance_width());
+                } else {
+                    assert_eq!(format!("{:?}", glyph), format!("{:?}", cff_glyph));
+                }
+                glyph
+            })
+            .collect();
+
+        for (wght, even_dx, odd_dx) in [(900.0, 100, 100), (650.0, 50, 50), (100.0, 0, -50)] {
+            cff2_reader.set_variation(&[("wght", wght)]);
+            default_glyphs.iter().for_each(|default_glyph| {
+                let glyph_id = GlyphId::new(glyph_id_of(default_glyph));
+                let glyph = cff2_reader.glyph_for_glyph_id(glyph_id);
+                let dx = if glyph_id.id().is_multiple_of(2) {
+                    even_dx
+                } else {
+                    odd_dx
+                };
+                assert_eq!(
+                    shifted_contours(&glyph, 0),
+                    shifted_contours(default_glyph, dx),
+                    "{:?} at wght {}",
+                    glyph_id,
+                    wght
+                );
+                assert_eq!(
+                    glyph.advance_width() as i16,
+                    (default_glyph.advance_width() as i16 + dx).max(0)
+                );
+                if let (Some(varied), Some(default)) =
+                    (glyph.bounding_box(), default_glyph.bounding_box())
+                {
+                    assert_eq!(varied.0, default.0 + dx);
+                }
+            });
+        }
+    }
+
+    // Two palettes of three colors, stored BGRA, with types and labels
+    fn mk_cpal_table() -> Vec<u8> {
+        let mut cpal: Vec<u8> = vec![];
+        cpal.extend(1u16.to_be_bytes()); // version
+        cpal.extend(3u16.to_be_bytes()); // numPaletteEntries
+        cpal.extend(2u16.to_be_bytes()); // numPalettes
+        cpal.extend(6u16.to_be_bytes()); // numColorRecords
+        cpal.extend(28u32.to_be_bytes()); // colorRecordsArrayOffset
+        cpal.extend([0, 0, 0, 3]); // colorRecordIndices
+        cpal.extend(
+            [52u32, 60, 64]
+                .iter()
+                .flat_map(|offset| offset.to_be_bytes()),
+        );
+        cpal.extend([
+            0, 0, 255, 255, 0, 255, 0, 255, 255, 0, 0, 128, // red, green, translucent blue
+            10, 20, 30, 255, 40, 50, 60, 255, 70, 80, 90, 0,
+        ]);
+        cpal.extend([1u32, 2].iter().flat_map(|flags| flags.to_be_bytes()));
+        cpal.extend(
+            [256u16, 0xFFFF]
+                .iter()
+                .flat_map(|label| label.to_be_bytes()),
+        );
+        cpal.extend(
+            [257u16, 258, 259]
+                .iter()
+                .flat_map(|label| label.to_be_bytes()),
+        );
+        cpal
+    }
+
+    // Glyph 36 has three layers, the middle one in the foreground color, glyph 40 has one
+    fn mk_colr_table() -> Vec<u8> {
+        let mut colr: Vec<u8> = vec![];
+        colr.extend(0u16.to_be_bytes()); // version
+        colr.extend(2u16.to_be_bytes()); // numBaseGlyphRecords
+        colr.extend(14u32.to_be_bytes()); // baseGlyphRecordsOffset
+        colr.extend(26u32.to_be_bytes()); // layerRecordsOffset
+        colr.extend(4u16.to_be_bytes()); // numLayerRecords
+        [36u16, 0, 3, 40, 3, 1, 50, 2, 51, 0xFFFF, 52, 0, 60, 1]
+            .iter()
+            .for_each(|value| colr.extend(value.to_be_bytes()));
+        colr
+    }
+
+    #[test]
+    fn colr_layers_with_cpal_palettes() {
+        let font = mk_font_with_tables(
+            "fonts/GolosText-Regular.ttf",
+            vec![("COLR", mk_colr_table()), ("CPAL", mk_cpal_table())],
+        );
+        let mut glyph_reader = GlyphReader::from_vec(font);
+        let rgba = |red, green, blue, alpha| Rgba {
+            red,
+            green,
+            blue,
+            alpha,
+        };
+
+        let cpal_table = glyph_reader.cpal_table().unwrap();
+        assert_eq!(
+            cpal_table.palettes,
+            vec![
+                Palette {
+                    colors: vec![
+                        rgba(255, 0, 0, 255),
+                        rgba(0, 255, 0, 255),
+                        rgba(0, 0, 255, 128)
+                    ],
+                    palette_type: Palette::USABLE_WITH_LIGHT_BACKGROUND,
+                    label: Some(256),
+                },
+                Palette {
+                    colors: vec![
+                        rgba(30, 20, 10, 255),
+                        rgba(60, 50, 40, 255),
+                        rgba(90, 80, 70, 0)
+                    ],
+                    palette_type: Palette::USABLE_WITH_DARK_BACKGROUND,
+                    label: None,
+                },
+            ]
+        );
+        assert_eq!(
+            cpal_table.palette_entry_labels,
+            vec![Some(257), Some(258), Some(259)]
+        );
+
+        assert_eq!(
+            glyph_reader.color_layers(GlyphId::new(36), 0),
+            vec![
+                (GlyphId::new(50), rgba(0, 0, 255, 128)),
+                (GlyphId::new(51), Rgba::BLACK),
+                (GlyphId::new(52), rgba(255, 0, 0, 255)),
+            ]
+        );
+        let white = rgba(255, 255, 255, 255);
+        assert_eq!(
+            glyph_reader.color_layers_with_foreground(GlyphId::new(36), 1, white),
+            vec![
+                (GlyphId::new(50), rgba(90, 80, 70, 0)),
+                (GlyphId::new(51), white),
+                (GlyphId::new(52), rgba(30, 20, 10, 255)),
+            ]
+        );
+        assert_eq!(
+            glyph_reader.color_layers(GlyphId::new(40), 1),
+            vec![(GlyphId::new(60), rgba(60, 50, 40, 255))]
+        );
+        assert!(glyph_reader.color_layers(GlyphId::new(37), 0).is_empty());
+        assert!(glyph_reader.color_layers(GlyphId::new(36), 2).is_empty());
+
+        // The single layer of glyph 40 colors its coverage
+        let color_coverage = glyph_reader
+            .rendered_color_glyph(GlyphId::new(40), 16, 1, Rgba::BLACK)
+            .unwrap();
+        let coverage = glyph_reader.rendered_glyph(GlyphId::new(60), 16);
+        assert!(!coverage.values.is_empty());
+        assert_eq!(
+            (color_coverage.width, color_coverage.height),
+            (coverage.width, coverage.height)
+        );
+        assert_eq!(
+            (color_coverage.left, color_coverage.top),
+            (coverage.left, coverage.top)
+        );
+        let expected: Vec<Rgba> = coverage
+            .values
+            .iter()
+            .map(|value| match value {
+                0 => rgba(0, 0, 0, 0),
+                _ => rgba(60, 50, 40, *value),
+            })
+            .collect();
+        assert_eq!(color_coverage.pixels, expected);
+        assert_eq!(
+            color_coverage.advance_width,
+            glyph_reader
+                .rendered_glyph(GlyphId::new(40), 16)
+                .advance_width
+        );
+        assert_eq!(
+            glyph_reader.rendered_color_glyph(GlyphId::new(37), 16, 0, Rgba::BLACK),
+            None
+        );
+        assert_eq!(
+            glyph_reader.rendered_color_glyph(GlyphId::new(36), 16, 2, Rgba::BLACK),
+            None
+        );
+
+        // COLR without CPAL
+        let font = mk_font_with_tables(
+            "fonts/GolosText-Regular.ttf",
+            vec![("COLR", mk_colr_table())],
+        );
+        let glyph_reader = GlyphReader::from_vec(font);
+        assert!(glyph_reader.color_layers(GlyphId::new(36), 0).is_empty());
+    }
+
+    #[test]
+    fn composite_color_layers() {
+        let rgba = |red, green, blue, alpha| Rgba {
+            red,
+            green,
+            blue,
+            alpha,
+        };
+        // An opaque red square under a translucent blue one
+        let layers = vec![
+            (
+                rasterize(&mk_square_glyph(1.0, 1.0, 3.0, 3.0)),
+                rgba(255, 0, 0, 255),
+            ),
+            (
+                rasterize(&mk_square_glyph(2.0, 0.0, 4.0, 2.0)),
+                rgba(0, 0, 255, 128),
+            ),
+        ];
+        let color_coverage = composite_layers(&layers, 5.0);
+        assert_eq!((color_coverage.width, color_coverage.height), (3, 3));
+        assert_eq!((color_coverage.left, color_coverage.top), (1, 3));
+        let (red, blue, blended, none) = (
+            rgba(255, 0, 0, 255),
+            rgba(0, 0, 255, 128),
+            rgba(127, 0, 128, 255),
+            rgba(0, 0, 0, 0),
+        );
+        assert_eq!(
+            color_coverage.pixels,
+            vec![red, red, none, red, blended, blue, none, blue, blue]
+        );
+        assert_eq!(color_coverage.advance_width, 5.0);
+
+        let color_coverage = composite_layers(&[], 3.0);
+        assert_eq!((color_coverage.width, color_coverage.height), (0, 0));
+        assert!(color_coverage.pixels.is_empty());
+    }
+
+    // COLR version 1 with paint graphs for glyphs 36 to 39: two layers, a
+    // composite, a cycle through PaintColrGlyph and 70 nested rotations
+    fn mk_colr_v1_table() -> Vec<u8> {
+        let patch = |colr: &mut Vec<u8>, position: usize, value: usize, size: usize| {
+            colr[position..position + size]
+                .copy_from_slice(&(value as u32).to_be_bytes()[4 - size..])
+        };
+        let f2dot14 = |value: f32| ((value * 16384.0) as i16).to_be_bytes();
+
+        let mut colr: Vec<u8> = vec![0; 34];
+        colr[1] = 1; // version
+
+        let base_glyph_list = colr.len();
+        patch(&mut colr, 14, base_glyph_list, 4);
+        colr.extend(4u32.to_be_bytes());
+        let records = colr.len();
+        [36u16, 37, 38, 39].iter().for_each(|glyph_id| {
+            colr.extend(glyph_id.to_be_bytes());
+            colr.extend([0; 4]);
+        });
+
+        let layer_list = colr.len();
+        patch(&mut colr, 18, layer_list, 4);
+        colr.extend(2u32.to_be_bytes());
+        let layer_offsets = colr.len();
+        colr.extend([0; 8]);
+
+        let clip_list = colr.len();
+        patch(&mut colr, 22, clip_list, 4);
+        colr.push(1);
+        colr.extend(1u32.to_be_bytes());
+        [36u16, 37]
+            .iter()
+            .for_each(|glyph_id| colr.extend(glyph_id.to_be_bytes()));
+        colr.extend([0, 0, 12]); // clipBoxOffset
+        colr.push(1);
+        [0i16, -10, 100, 90]
+            .iter()
+            .for_each(|value| colr.extend(value.to_be_bytes()));
+
+        // Glyph 36: PaintColrLayers of both layers
+        let paint = colr.len();
+        patch(&mut colr, records + 2, paint - base_glyph_list, 4);
+        colr.extend([1, 2, 0, 0, 0, 0]);
+        // PaintGlyph 50 with PaintSolid
+        let layer = colr.len();
+        patch(&mut colr, layer_offsets, layer - layer_list, 4);
+        colr.extend([10, 0, 0, 6, 0, 50, 2, 0, 0]);
+        colr.extend(f2dot14(1.0));
+        // PaintTranslate of PaintGlyph 51 with PaintLinearGradient
+        let layer = colr.len();
+        patch(&mut colr, layer_offsets + 4, layer - layer_list, 4);
+        colr.extend([14, 0, 0, 8]);
+        [10i16, -20]
+            .iter()
+            .for_each(|value| colr.extend(value.to_be_bytes()));
+        colr.extend([10, 0, 0, 6, 0, 51, 4, 0, 0, 16]);
+        [0i16, 0, 100, 0, 0, 100]
+            .iter()
+            .for_each(|value| colr.extend(value.to_be_bytes()));
+        colr.extend([1, 0, 2]); // repeat, 2 stops
+        colr.extend(f2dot14(0.0));
+        colr.extend([0, 1]);
+        colr.extend(f2dot14(1.0));
+        colr.extend(f2dot14(1.0));
+        colr.extend([0, 2]);
+        colr.extend(f2dot14(0.5));
+
+        // Glyph 37: PaintComposite multiplying PaintColrGlyph 36 over PaintGlyph 52
+        // with PaintVarSolid
+        let paint = colr.len();
+        patch(&mut colr, records + 8, paint - base_glyph_list, 4);
+        colr.extend([32, 0, 0, 8, 23, 0, 0, 11]);
+        colr.extend([11, 0, 36]);
+        colr.extend([10, 0, 0, 6, 0, 52, 3, 0, 1]);
+        colr.extend(f2dot14(0.5));
+        colr.extend(7u32.to_be_bytes());
+
+        // Glyph 38: PaintTranslate of PaintColrGlyph 38
+        let paint = colr.len();
+        patch(&mut colr, records + 14, paint - base_glyph_list, 4);
+        colr.extend([14, 0, 0, 8, 0, 0, 0, 0, 11, 0, 38]);
+
+        // Glyph 39: 70 nested PaintRotate
+        let paint = colr.len();
+        patch(&mut colr, records + 20, paint - base_glyph_list, 4);
+        (0..70).for_each(|_| {
+            colr.extend([24, 0, 0, 6]);
+            colr.extend(f2dot14(0.25));
+        });
+        colr.extend([2, 0, 0]);
+        colr.extend(f2dot14(1.0));
+        colr
+    }
+
+    #[derive(Default)]
+    struct PaintRecorder {
+        paints: Vec<(usize, Paint)>,
+        leaves: usize,
+    }
+
+    impl PaintVisitor for PaintRecorder {
+        fn enter(&mut self, paint: &Paint, depth: usize) -> bool {
+            self.paints.push((depth, paint.clone()));
+            true
+        }
+
+        fn leave(&mut self, _paint: &Paint, _depth: usize) {
+            self.leaves += 1;
+        }
+    }
+
+    #[test]
+    fn colr_v1_paint_graph() {
+        let font = mk_font_with_tables(
+            "fonts/GolosText-Regular.ttf",
+            vec![("COLR", mk_colr_v1_table()), ("CPAL", mk_cpal_table())],
+        );
+        let glyph_reader = GlyphReader::from_vec(font);
+        let walk = |glyph_id: u16| {
+            let mut recorder = PaintRecorder::default();
+            let result = glyph_reader.walk_color_paint(GlyphId::new(glyph_id), &mut recorder);
+            (result, recorder)
+        };
+        let solid = Paint::Solid {
+            palette_index: 0,
+            alpha: 1.0,
+            var_index_base: None,
+        };
+
+        let (result, recorder) = walk(36);
+        assert_eq!(result, Ok(true));
+        assert_eq!(recorder.leaves, 6);
+        let paints = recorder.paints;
+        assert_eq!(
+            paints
+                .iter()
+                .map(|(depth, _)| *depth)
+                .collect::<Vec<usize>>(),
+            vec![0, 1, 2, 1, 2, 3]
+        );
+        assert_eq!(
+            paints[0].1,
+            Paint::ColrLayers {
+                first_layer_index: 0,
+                num_layers: 2
+            }
+        );
+        assert!(
+            matches!(paints[1].1, Paint::Glyph { glyph_id, .. } if glyph_id == GlyphId::new(50))
+        );
+        assert_eq!(paints[2].1, solid);
+        assert!(matches!(
+            paints[3].1,
+            Paint::Translate {
+                dx: 10,
+                dy: -20,
+                var_index_base: None,
+                ..
+            }
+        ));
+        assert!(
+            matches!(paints[4].1, Paint::Glyph { glyph_id, .. } if glyph_id == GlyphId::new(51))
+        );
+        assert_eq!(
+            paints[5].1,
+            Paint::LinearGradient {
+                color_line: ColorLine {
+                    extend: Extend::Repeat,
+                    color_stops: vec![
+                        ColorStop {
+                            stop_offset: 0.0,
+                            palette_index: 1,
+                            alpha: 1.0,
+                            var_index_base: None
+                        },
+                        ColorStop {
+                            stop_offset: 1.0,
+                            palette_index: 2,
+                            alpha: 0.5,
+                            var_index_base: None
+                        },
+                    ]
+                },
+                p0: (0, 0),
+                p1: (100, 0),
+                p2: (0, 100),
+                var_index_base: None,
+            }
+        );
+
+        // The backdrop is visited before the source, which expands glyph 36
+        let (result, recorder) = walk(37);
+        assert_eq!(result, Ok(true));
+        let paints = recorder.paints;
+        assert_eq!(paints.len(), 10);
+        assert!(matches!(
+            paints[0].1,
+            Paint::Composite {
+                mode: CompositeMode::Multiply,
+                ..
+            }
+        ));
+        assert!(
+            matches!(paints[1].1, Paint::Glyph { glyph_id, .. } if glyph_id == GlyphId::new(52))
+        );
+        assert_eq!(
+            paints[2].1,
+            Paint::Solid {
+                palette_index: 1,
+                alpha: 0.5,
+                var_index_base: Some(7)
+            }
+        );
+        assert_eq!(
+            paints[3],
+            (
+                1,
+                Paint::ColrGlyph {
+                    glyph_id: GlyphId::new(36)
+                }
+            )
+        );
+        assert_eq!(paints[4].0, 2);
+        assert_eq!(paints[9].0, 5);
+
+        let (result, recorder) = walk(38);
+        assert!(matches!(result, Err(PaintError::Cycle(_))));
+        assert_eq!(recorder.paints.len(), 2);
+
+        let (result, recorder) = walk(39);
+        assert_eq!(result, Err(PaintError::DepthLimitExceeded));
+        assert_eq!(recorder.paints.len(), 64);
+        assert!(
+            matches!(recorder.paints[0].1, Paint::Rotate { angle, center: None, .. } if angle == 45.0)
+        );
+
+        let (result, recorder) = walk(40);
+        assert_eq!(result, Ok(false));
+        assert!(recorder.paints.is_empty());
+
+        let clip_box = ClipBox {
+            x_min: 0,
+            y_min: -10,
+            x_max: 100,
+            y_max: 90,
+            var_index_base: None,
+        };
+        assert_eq!(
+            glyph_reader.color_clip_box(GlyphId::new(37)),
+            Some(clip_box)
+        );
+        assert_eq!(glyph_reader.color_clip_box(GlyphId::new(38)), None);
+    }
+
+    fn be_u16s(values: &[u16]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect()
+    }
+
+    fn be_u32s(values: &[u32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect()
+    }
+
+    fn mk_index_subtable(
+        index_format: u16,
+        image_format: u16,
+        image_data_offset: u32,
+        rest: Vec<u8>,
+    ) -> Vec<u8> {
+        let mut subtable = be_u16s(&[index_format, image_format]);
+        subtable.extend(image_data_offset.to_be_bytes());
+        subtable.extend(rest);
+        subtable
+    }
+
+    // (ppem, bit depth, [(first glyph, last glyph, index subtable)])
+    type TestStrike = (u8, u8, Vec<(u16, u16, Vec<u8>)>);
+
+    // EBLC or CBLC with the strikes
+    fn mk_eblc_table(major_version: u16, strikes: Vec<TestStrike>) -> Vec<u8> {
+        let mut eblc = be_u16s(&[major_version, 0]);
+        eblc.extend((strikes.len() as u32).to_be_bytes());
+        eblc.resize(8 + 48 * strikes.len(), 0);
+
+        strikes
+            .iter()
+            .enumerate()
+            .for_each(|(index, (ppem, bit_depth, subtables))| {
+                let list_offset = eblc.len();
+                let mut subtable_offset = 8 * subtables.len();
+                subtables.iter().for_each(|(first, last, subtable)| {
+                    eblc.extend(be_u16s(&[*first, *last]));
+                    eblc.extend((subtable_offset as u32).to_be_bytes());
+                    subtable_offset += subtable.len();
+                });
+                subtables
+                    .iter()
+                    .for_each(|(_, _, subtable)| eblc.extend(subtable));
+
+                let mut size = be_u32s(&[
+                    list_offset as u32,
+                    (eblc.len() - list_offset) as u32,
+                    subtables.len() as u32,
+                    0,
+                ]);
+                size.extend([0; 24]);
+                size.extend(be_u16s(&[subtables[0].0, subtables[subtables.len() - 1].1]));
+                size.extend([*ppem, *ppem, *bit_depth, 1]);
+                let record = 8 + 48 * index;
+                eblc[record..record + 48].copy_from_slice(&size);
+            });
+        eblc
+    }
+
+    // A strike of 12 ppem using index formats 1 to 5 and a strike of 20 ppem
+    fn mk_eblc_and_ebdt_tables() -> (Vec<u8>, Vec<u8>) {
+        let mut ebdt: Vec<u8> = vec![0, 2, 0, 0];
+        let mut image = |data: &[u8]| {
+            ebdt.extend(data);
+            (ebdt.len() - data.len()) as u32
+        };
+
+        // Format 1, small metrics and byte aligned rows, glyph 37 has no image
+        let glyph_36 = image(&[2, 10, 1, 8, 12, 0xFF, 0xC0, 0x80, 0x40]);
+        // Format 5, bit aligned rows of 5 pixels
+        let glyph_38 = image(&[0xFC, 0x7E, 0, 0]);
+        // Format 2, small metrics and bit aligned rows of 3 pixels
+        let glyph_40 = image(&[2, 3, 0, 2, 4, 0xA8]);
+        // Format 8, components of the same strike
+        let composite = [8, 12, 0, 8, 12, 0, 0, 2, 0, 36, 0, 0, 0, 40, 12, 0xFE];
+        let glyph_41 = image(&composite);
+        image(&composite);
+        // Format 5 with sparse glyph ids
+        let glyph_52 = image(&[0x90, 0x40]);
+        // Format 6, big metrics and byte aligned rows, 8 bits per pixel
+        let large_glyph_36 = image(&[1, 2, 0, 1, 3, 0, 0, 0, 0x10, 0xF0]);
+
+        let small_strike = vec![
+            (
+                36,
+                37,
+                mk_index_subtable(1, 1, glyph_36, be_u32s(&[0, 9, 9])),
+            ),
+            (
+                38,
+                39,
+                mk_index_subtable(
+                    2,
+                    5,
+                    glyph_38,
+                    [be_u32s(&[2]), vec![3, 5, 0, 3, 6, 0, 0, 0]].concat(),
+                ),
+            ),
+            (40, 40, mk_index_subtable(3, 2, glyph_40, be_u16s(&[0, 6]))),
+            (
+                41,
+                50,
+                mk_index_subtable(
+                    4,
+                    8,
+                    glyph_41,
+                    [be_u32s(&[2]), be_u16s(&[41, 0, 45, 16, 0, 32])].concat(),
+                ),
+            ),
+            (
+                51,
+                60,
+                mk_index_subtable(
+                    5,
+                    5,
+                    glyph_52,
+                    [
+                        be_u32s(&[1]),
+                        vec![2, 2, 0, 2, 3, 0, 0, 0],
+                        be_u32s(&[2]),
+                        be_u16s(&[52, 55]),
+                    ]
+                    .concat(),
+                ),
+            ),
+        ];
+        let large_strike = vec![(
+            36,
+            36,
+            mk_index_subtable(1, 6, large_glyph_36, be_u32s(&[0, 10])),
+        )];
+        let eblc = mk_eblc_table(2, vec![(12, 1, small_strike), (20, 8, large_strike)]);
+        (eblc, ebdt)
+    }
+
+    #[test]
+    fn ebdt_bitmaps() {
+        let (eblc, ebdt) = mk_eblc_and_ebdt_tables();
+        let font = mk_font_with_tables(
+            "fonts/GolosText-Regular.ttf",
+            vec![("EBLC", eblc), ("EBDT", ebdt)],
+        );
+        let mut glyph_reader = GlyphReader::from_vec(font);
+        let bitmap = |glyph_reader: &mut GlyphReader, glyph_id: u16, ppem: u16| {
+            glyph_reader.bitmap(GlyphId::new(glyph_id), ppem)
+        };
+        let metrics =
+            |height, width, hori_bearing_x, hori_bearing_y, hori_advance| BigGlyphMetrics {
+                height,
+                width,
+                hori_bearing_x,
+                hori_bearing_y,
+                hori_advance,
+                ..BigGlyphMetrics::default()
+            };
+
+        let glyph_36 = bitmap(&mut glyph_reader, 36, 12).unwrap();
+        assert_eq!(
+            glyph_36,
+            Bitmap {
+                ppem_x: 12,
+                ppem_y: 12,
+                bit_depth: 1,
+                image_format: 1,
+                metrics: metrics(2, 10, 1, 8, 12),
+                data: BitmapData::Pixels(vec![0xFF, 0xC0, 0x80, 0x40]),
+            }
+        );
+        assert_eq!(bitmap(&mut glyph_reader, 36, 4), Some(glyph_36));
+        let large_glyph_36 = Bitmap {
+            ppem_x: 20,
+            ppem_y: 20,
+            bit_depth: 8,
+            image_format: 6,
+            metrics: metrics(1, 2, 0, 1, 3),
+            data: BitmapData::Pixels(vec![0x10, 0xF0]),
+        };
+        assert_eq!(
+            bitmap(&mut glyph_reader, 36, 16),
+            Some(large_glyph_36.clone())
+        );
+        assert_eq!(
+            bitmap(&mut glyph_reader, 36, 40),
+            Some(large_glyph_36.clone())
+        );
+        assert_eq!(bitmap(&mut glyph_reader, 36, 1000), Some(large_glyph_36));
+        assert_eq!(bitmap(&mut glyph_reader, 37, 12), None);
+
+        let glyph_38 = bitmap(&mut glyph_reader, 38, 12).unwrap();
+        assert_eq!(glyph_38.metrics, metrics(3, 5, 0, 3, 6));
+        assert_eq!(glyph_38.data, BitmapData::Pixels(vec![0xF8, 0x88, 0xF8]));
+        assert_eq!(
+            bitmap(&mut glyph_reader, 39, 12).unwrap().data,
+            BitmapData::Pixels(vec![0; 3])
+        );
+
+        let glyph_40 = bitmap(&mut glyph_reader, 40, 12).unwrap();
+        assert_eq!(
+            (glyph_40.image_format, glyph_40.metrics),
+            (2, metrics(2, 3, 0, 2, 4))
+        );
+        assert_eq!(glyph_40.data, BitmapData::Pixels(vec![0xA0, 0x40]));
+
+        let components = BitmapData::Composite(vec![
+            BitmapComponent {
+                glyph_id: GlyphId::new(36),
+                x_offset: 0,
+                y_offset: 0,
+            },
+            BitmapComponent {
+                glyph_id: GlyphId::new(40),
+                x_offset: 12,
+                y_offset: -2,
+            },
+        ]);
+        assert_eq!(bitmap(&mut glyph_reader, 41, 12).unwrap().data, components);
+        assert_eq!(bitmap(&mut glyph_reader, 45, 12).unwrap().data, components);
+        assert_eq!(bitmap(&mut glyph_reader, 42, 12), None);
+
+        assert_eq!(
+            bitmap(&mut glyph_reader, 52, 12).unwrap().data,
+            BitmapData::Pixels(vec![0x80, 0x40])
+        );
+        assert_eq!(
+            bitmap(&mut glyph_reader, 55, 12).unwrap().data,
+            BitmapData::Pixels(vec![0x40, 0x00])
+        );
+        assert_eq!(bitmap(&mut glyph_reader, 53, 12), None);
+
+        let mut golos = GlyphReader::from_file(File::open("fonts/GolosText-Regular.ttf").unwrap());
+        assert_eq!(bitmap(&mut golos, 36, 12), None);
+    }
+
+    #[test]
+    fn ebdt_invalid_index_subtables() {
+        let ebdt: Vec<u8> = vec![0, 2, 0, 0, 2, 10, 1, 8, 12, 0xFF, 0xC0, 0x80, 0x40];
+        let strike = vec![
+            // Offsets going backwards
+            (36, 36, mk_index_subtable(1, 1, 4, be_u32s(&[9, 0]))),
+            // Unknown index format, skipped
+            (38, 38, mk_index_subtable(9, 1, 4, be_u32s(&[0, 9]))),
+            // Image offsets past 32 bits
+            (
+                40,
+                41,
+                mk_index_subtable(
+                    2,
+                    5,
+                    u32::MAX,
+                    [be_u32s(&[u32::MAX]), vec![3, 5, 0, 3, 6, 0, 0, 0]].concat(),
+                ),
+            ),
+            (42, 42, mk_index_subtable(1, 1, u32::MAX, be_u32s(&[1, 9]))),
+            (43, 43, mk_index_subtable(1, 1, 4, be_u32s(&[0, 9]))),
+        ];
+        let eblc = mk_eblc_table(2, vec![(12, 1, strike)]);
+        let font = mk_font_with_tables(
+            "fonts/GolosText-Regular.ttf",
+            vec![("EBLC", eblc), ("EBDT", ebdt)],
+        );
+        let mut glyph_reader = GlyphReader::from_vec(font);
+
+        [36, 38, 40, 41, 42].iter().for_each(|glyph_id| {
+            assert_eq!(glyph_reader.bitmap(GlyphId::new(*glyph_id), 12), None);
+        });
+        assert_eq!(
+            glyph_reader.bitmap(GlyphId::new(43), 12).unwrap().data,
+            BitmapData::Pixels(vec![0xFF, 0xC0, 0x80, 0x40])
+        );
+    }
+
+    #[test]
+    fn cbdt_png_bitmaps() {
+        let png = |id: u8| vec![0x89, b'P', b'N', b'G', id];
+        let mut cbdt: Vec<u8> = vec![0, 3, 0, 0];
+        cbdt.extend([16, 18, 1, 15, 20]);
+        cbdt.extend(be_u32s(&[5]));
+        cbdt.extend(png(17));
+        cbdt.extend([16, 18, 1, 15, 20, 0, 0, 0]);
+        cbdt.extend(be_u32s(&[5]));
+        cbdt.extend(png(18));
+        let glyph_38 = cbdt.len() as u32;
+        cbdt.extend(be_u32s(&[5]));
+        cbdt.extend(png(19));
+
+        let cblc = mk_eblc_table(
+            3,
+            vec![(
+                109,
+                32,
+                vec![
+                    (36, 36, mk_index_subtable(1, 17, 4, be_u32s(&[0, 14]))),
+                    (37, 37, mk_index_subtable(1, 18, 18, be_u32s(&[0, 17]))),
+                    (
+                        38,
+                        38,
+                        mk_index_subtable(
+                            2,
+                            19,
+                            glyph_38,
+                            [be_u32s(&[9]), vec![16, 18, 1, 15, 20, 0, 0, 0]].concat(),
+                        ),
+                    ),
+                ],
+            )],
+        );
+        let font = mk_font_with_tables(
+            "fonts/GolosText-Regular.ttf",
+            vec![("CBLC", cblc), ("CBDT", cbdt)],
+        );
+        let mut glyph_reader = GlyphReader::from_vec(font);
+
+        [(36, 17), (37, 18), (38, 19)]
+            .iter()
+            .for_each(|(glyph_id, image_format)| {
+                let bitmap = glyph_reader.bitmap(GlyphId::new(*glyph_id), 32).unwrap();
+                assert_eq!(
+                    (bitmap.ppem_y, bitmap.bit_depth, bitmap.image_format),
+                    (109, 32, *image_format)
+                );
+                assert_eq!(
+                    (
+                        bitmap.metrics.width,
+                        bitmap.metrics.height,
+                        bitmap.metrics.hori_advance
+                    ),
+                    (18, 16, 20)
+                );
+                assert_eq!(bitmap.data, BitmapData::Png(png(*image_format as u8)));
+            });
+    }
+
+    // gzip member with an extra field and a file name around the DEFLATE data of zlib_compress
+    fn mk_gzip(data: &[u8]) -> Vec<u8> {
+        let zlib = zlib_compress(data);
+        let mut gzip = vec![0x1F, 0x8B, 8, 0x0C, 0, 0, 0, 0, 0, 255, 3, 0, 1, 2, 3];
+        gzip.extend(b"glyphs.svg\0");
+        gzip.extend(&zlib[2..zlib.len() - 4]);
+        gzip.extend(crc32(data).to_le_bytes());
+        gzip.extend((data.len() as u32).to_le_bytes());
+        gzip
+    }
+
+    #[test]
+    fn gzip_round_trip() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+        assert_eq!(crc32(b""), 0);
+        let data = fs::read("fonts/Zeyada_1.ttf").unwrap();
+        let gzip = mk_gzip(&data);
+        assert_eq!(gzip_decompress(&gzip).unwrap(), data);
+        assert_eq!(
+            gzip_decompress(&gzip[..gzip.len() / 2]),
+            Err(InflateError::UnexpectedEnd)
+        );
+    }
+
+    // Strikes of 20 and 40 ppem, glyph 37 duplicates glyph 36 and glyph 38 itself
+    fn mk_sbix_table(num_glyphs: u16) -> Vec<u8> {
+        let glyph_data = |origin: (i16, i16), graphic_type: &[u8], data: &[u8]| {
+            [
+                be_u16s(&[origin.0 as u16, origin.1 as u16]),
+                graphic_type.to_vec(),
+                data.to_vec(),
+            ]
+            .concat()
+        };
+        let strike = |ppem: u16, glyphs: Vec<(u16, Vec<u8>)>| {
+            let mut offsets = vec![];
+            let mut data: Vec<u8> = vec![];
+            (0..=num_glyphs).for_each(|glyph_id| {
+                offsets.push(4 + 4 * (num_glyphs as u32 + 1) + data.len() as u32);
+                if let Some((_, glyph_data)) = glyphs.iter().find(|(id, _)| *id == glyph_id) {
+                    data.extend(glyph_data);
+                }
+            });
+            [be_u16s(&[ppem, 72]), be_u32s(&offsets), data].concat()
+        };
+
+        let small_strike = strike(
+            20,
+            vec![
+                (36, glyph_data((1, -2), b"png ", &[0x89, b'P', b'N', b'G'])),
+                (37, glyph_data((0, 0), b"dupe", &be_u16s(&[36]))),
+                (38, glyph_data((0, 0), b"dupe", &be_u16s(&[38]))),
+            ],
+        );
+        let large_strike = strike(40, vec![(36, glyph_data((2, -4), b"jpg ", &[0xFF, 0xD8]))]);
+        [
+            be_u16s(&[1, 3]),
+            be_u32s(&[2, 16, 16 + small_strike.len() as u32]),
+            small_strike,
+            large_strike,
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn sbix_images() {
+        let num_glyphs = GlyphReader::from_file(File::open("fonts/GolosText-Regular.ttf").unwrap())
+            .maximum_profile_table()
+            .num_glyphs;
+        let font = mk_font_with_tables(
+            "fonts/GolosText-Regular.ttf",
+            vec![("sbix", mk_sbix_table(num_glyphs))],
+        );
+        let mut glyph_reader = GlyphReader::from_vec(font);
+
+        let png = SbixImage {
+            ppem: 20,
+            ppi: 72,
+            origin_offset_x: 1,
+            origin_offset_y: -2,
+            graphic_type: "png ".to_string(),
+            data: vec![0x89, b'P', b'N', b'G'],
+        };
+        let jpg = SbixImage {
+            ppem: 40,
+            ppi: 72,
+            origin_offset_x: 2,
+            origin_offset_y: -4,
+            graphic_type: "jpg ".to_string(),
+            data: vec![0xFF, 0xD8],
+        };
+        assert_eq!(
+            glyph_reader.sbix_image(GlyphId::new(36), 20),
+            Some(png.clone())
+        );
+        assert_eq!(
+            glyph_reader.sbix_image(GlyphId::new(36), 12),
+            Some(png.clone())
+        );
+        assert_eq!(
+            glyph_reader.sbix_image(GlyphId::new(36), 30),
+            Some(jpg.clone())
+        );
+        assert_eq!(glyph_reader.sbix_image(GlyphId::new(36), 160), Some(jpg));
+        assert_eq!(glyph_reader.sbix_image(GlyphId::new(37), 40), Some(png));
+        assert_eq!(glyph_reader.sbix_image(GlyphId::new(38), 20), None);
+        assert_eq!(glyph_reader.sbix_image(GlyphId::new(39), 20), None);
+    }
+
+    #[test]
+    fn svg_documents() {
+        let plain = b"<svg xmlns=\"http://www.w3.org/2000/svg\"><path id=\"glyph36\"/><path id=\"glyph37\"/></svg>".to_vec();
+        let compressed =
+            b"<svg xmlns=\"http://www.w3.org/2000/svg\"><path id=\"glyph40\"/></svg>".to_vec();
+        let gzip = mk_gzip(&compressed);
+        let svg = [
+            be_u16s(&[0]),
+            be_u32s(&[10, 0]),
+            be_u16s(&[2, 36, 37]),
+            be_u32s(&[26, plain.len() as u32]),
+            be_u16s(&[40, 40]),
+            be_u32s(&[26 + plain.len() as u32, gzip.len() as u32]),
+            plain.clone(),
+            gzip,
+        ]
+        .concat();
+        let font = mk_font_with_tables("fonts/GolosText-Regular.ttf", vec![("SVG ", svg)]);
+        let mut glyph_reader = GlyphReader::from_vec(font);
+
+        assert_eq!(
+            glyph_reader.svg_document(GlyphId::new(36)),
+            Some(plain.clone())
+        );
+        assert_eq!(glyph_reader.svg_document(GlyphId::new(37)), Some(plain));
+        assert_eq!(
+            glyph_reader.svg_document(GlyphId::new(40)),
+            Some(compressed)
+        );
+        assert_eq!(glyph_reader.svg_document(GlyphId::new(38)), None);
+        assert_eq!(glyph_reader.svg_document(GlyphId::new(41)), None);
+    }
+
+    // Golos maxp with room for twilight points, storage, functions, instruction
+    // definitions and stack
+    fn mk_hinting_maxp_table() -> Vec<u8> {
+        let mut glyph_reader = mk_glyph_reader_vec("fonts/GolosText-Regular.ttf");
+        let mut maxp = glyph_reader.table_data("maxp").unwrap();
+        maxp[16..26].copy_from_slice(&be_u16s(&[4, 8, 4, 1, 16]));
+        maxp
+    }
+
+    // Golos glyf and loca with `instructions` on the glyphs of `glyph_ids`
+    fn mk_glyf_with_instructions(
+        glyph_ids: &[GlyphId],
+        instructions: &[u8],
+    ) -> Vec<(&'static str, Vec<u8>)> {
+        let mut glyph_reader = mk_glyph_reader_vec("fonts/GolosText-Regular.ttf");
+        let mut glyphs = all_glyphs_of(&mut glyph_reader);
+        glyphs.iter_mut().for_each(|glyph| match glyph {
+            Glyph::Simple {
+                glyph_id,
+                instructions: glyph_instructions,
+                ..
+            }
+            | Glyph::Compound {
+                glyph_id,
+                instructions: glyph_instructions,
+                ..
+            } if glyph_ids.contains(glyph_id) => *glyph_instructions = instructions.to_vec(),
+            _ => {}
+        });
+        let encoded = encode_glyf_and_loca(&glyphs, 2).unwrap();
+        assert_eq!(encoded.index_to_loc_format, 0);
+        vec![("glyf", encoded.glyf), ("loca", encoded.loca)]
+    }
+
+    #[test]
+    fn hinting_programs() {
+        use crate::hinting::Hinter;
+
+        let font = mk_font_with_tables(
+            "fonts/GolosText-Regular.ttf",
+            vec![("maxp", mk_hinting_maxp_table())],
+        );
+        let glyph_reader = GlyphReader::from_vec(font);
+        let maxp = glyph_reader.maximum_profile_table();
+        let points = [
+            (100, 0),
+            (180, 0),
+            (260, 0),
+            (0, 0),
+            (400, 0),
+            (0, 800),
+            (0, -200),
+        ];
+        let on_curve = [true, true, true, false, false, false, false];
+
+        // MDAP[round] puts point 0 on the grid, MIRP[rp0, min distance, round]
+        // gives point 2 the rounded stem width of cvt 0 and IUP[x] interpolates
+        // point 1 between them. At 12 ppem and 1000 units per em the points
+        // scale to 77, 138 and 200, the stem to 123.
+        let mut hinter = Hinter::new(maxp, 1000, vec![160], vec![], vec![]);
+        hinter.set_ppem(12).unwrap();
+        let program = [0x01, 0xB0, 0, 0x2F, 0xB1, 2, 0, 0xEC, 0x31];
+        assert_eq!(
+            hinter.hint_glyph(&points, &on_curve, &[2], &program),
+            Ok(vec![
+                (64, 0),
+                (128, 0),
+                (192, 0),
+                (0, 0),
+                (320, 0),
+                (0, 640),
+                (0, -128)
+            ])
+        );
+
+        // A jump back to its own push never ends
+        let mut hinter = Hinter::new(maxp, 1000, vec![], vec![], vec![0xB8, 0xFF, 0xFD, 0x1C]);
+        assert_eq!(hinter.set_ppem(12), Err(HintingError::ExecutionLimit));
+
+        // Function 0 calls itself
+        let font_program = vec![0xB0, 0, 0x2C, 0xB0, 0, 0x2B, 0x2D];
+        let mut hinter = Hinter::new(maxp, 1000, vec![], font_program, vec![0xB0, 0, 0x2B]);
+        assert_eq!(hinter.set_ppem(12), Err(HintingError::CallStackOverflow));
+        assert_eq!(hinter.set_ppem(16), Err(HintingError::CallStackOverflow));
+
+        // Like FreeType, an empty stack gives zeros and unknown points are
+        // skipped unless hinting is pedantic
+        let mut hinter = Hinter::new(maxp, 1000, vec![], vec![], vec![]);
+        hinter.set_ppem(12).unwrap();
+        let scaled = hinter.hint_glyph(&points, &on_curve, &[2], &[]);
+        assert_eq!(hinter.hint_glyph(&points, &on_curve, &[2], &[0x21]), scaled);
+        assert_eq!(
+            hinter.hint_glyph(&points, &on_curve, &[2], &[0xB0, 9, 0x2F]),
+            scaled
+        );
+        assert_eq!(
+            hinter.hint_glyph(&points, &on_curve, &[2], &[0xB0, 0, 0x2E, 0x26, 0x2F]),
+            hinter.hint_glyph(&points, &on_curve, &[2], &[0xB0, 0, 0x2E, 0xB0, 0, 0x2F])
+        );
+
+        hinter.set_pedantic(true);
+        let overflow = [vec![0x40, 64], vec![0; 64]].concat();
+        assert_eq!(
+            hinter.hint_glyph(&points, &on_curve, &[2], &overflow),
+            Err(HintingError::StackOverflow)
+        );
+        assert_eq!(
+            hinter.hint_glyph(&points, &on_curve, &[2], &[0x21]),
+            Err(HintingError::StackUnderflow)
+        );
+        assert_eq!(
+            hinter.hint_glyph(&points, &on_curve, &[2], &[0xB0, 0, 0x2C, 0x2D]),
+            Err(HintingError::InvalidOpcode(0x2C))
+        );
+        assert_eq!(
+            hinter.hint_glyph(&points, &on_curve, &[2], &[0xB0, 9, 0x2F]),
+            Err(HintingError::InvalidPoint(9))
+        );
+        assert_eq!(
+            hinter.hint_glyph(&points, &on_curve, &[2], &[0xB0, 0, 0x58, 0x21]),
+            Err(HintingError::UnexpectedEnd)
+        );
+    }
+
+    // cvar moving the control value 0 by `delta` at wght 900
+    fn mk_cvar_table(delta: i16) -> Vec<u8> {
+        let mut cvar = be_u16s(&[1, 0, 1, 16]);
+        cvar.extend(be_u16s(&[6, 0xA000])); // data size, EMBEDDED_PEAK_TUPLE | PRIVATE_POINT_NUMBERS
+        cvar.extend(f2dot14(1.0));
+        cvar.extend(f2dot14(0.0));
+        cvar.extend_from_slice(&[1, 0, 0]); // point 0
+        cvar.extend_from_slice(&[0x40]);
+        cvar.extend_from_slice(&delta.to_be_bytes());
+        cvar
+    }
+
+    #[test]
+    fn hinted_glyph_cvt_variations() {
+        let mut glyph_reader = GlyphReader::from_vec(mk_golos_variable_font());
+        let glyph_id = glyph_reader.char_code_to_glyph_id(b'H' as u16);
+
+        // MIAP moves point 0 to the x of control value 0, without the cut-in of MIAP[round]
+        let mut tables = mk_glyf_with_instructions(&[glyph_id], &[0xB1, 0, 0, 0x3E]);
+        tables.push(("maxp", mk_hinting_maxp_table()));
+        tables.push(("cvt ", be_u16s(&[100])));
+        tables.push(("cvar", mk_cvar_table(100)));
+        let mut hinted_reader = GlyphReader::from_vec(mk_golos_variable_font_with_tables(tables));
+        let point_0_x =
+            |reader: &mut GlyphReader| reader.hinted_glyph(glyph_id, 20).unwrap().contours[0][0].0;
+
+        assert_eq!(point_0_x(&mut hinted_reader), 2.0);
+        // The control value varies at the same size
+        hinted_reader.set_variation(&[("wght", 900.0)]);
+        assert_eq!(point_0_x(&mut hinted_reader), 4.0);
+        hinted_reader.set_variation(&[("wght", 650.0)]);
+        assert_eq!(point_0_x(&mut hinted_reader), 3.0);
+        hinted_reader.set_variation(&[("wght", 400.0)]);
+        assert_eq!(point_0_x(&mut hinted_reader), 2.0);
+    }
+
+    #[test]
+    fn hinted_glyph_golos() {
+        let mut glyph_reader = mk_glyph_reader_vec("fonts/GolosText-Regular.ttf");
+        let glyph_id = glyph_reader.char_code_to_glyph_id(b'H' as u16);
+        let glyph = glyph_reader.glyph_for_glyph_id(glyph_id);
+        let Glyph::Simple { contours, .. } = &glyph else {
+            panic!("Expected a simple glyph");
+        };
+        let point_count = contours
+            .iter()
+            .map(|contour| contour.points.len())
+            .sum::<usize>() as u8;
+
+        // Function 0 rounds the point numbered by storage 0 and counts it up,
+        // the glyph program calls it on every point in x, then in y
+        let font_program = vec![
+            0xB0, 0, 0x2C, 0xB0, 0, 0x43, 0x20, 0x2F, 0xB0, 1, 0x60, 0xB0, 0, 0x23, 0x42, 0x2D,
+        ];
+        let instructions = [
+            0xB1,
+            0,
+            0,
+            0x42,
+            0x01,
+            0xB1,
+            point_count,
+            0,
+            0x2A,
+            0xB1,
+            0,
+            0,
+            0x42,
+            0x00,
+            0xB1,
+            point_count,
+            0,
+            0x2A,
+        ];
+        let mut tables = mk_glyf_with_instructions(&[glyph_id], &instructions);
+        tables.push(("maxp", mk_hinting_maxp_table()));
+        tables.push(("fpgm", font_program));
+        let font = mk_font_with_tables("fonts/GolosText-Regular.ttf", tables);
+        let mut hinted_reader = GlyphReader::from_vec(font);
+
+        let (x_min, _, _, _) = glyph.bounding_box().unwrap();
+        let origin = (x_min - glyph.left_side_bearing()) as f32;
+        [9, 12, 16, 23].iter().for_each(|ppem| {
+            let scale = *ppem as f32 / 1000.0;
+            let hinted = hinted_reader.hinted_glyph(glyph_id, *ppem).unwrap();
+            assert_eq!(
+                hinted.advance_width,
+                (glyph.advance_width() as f32 * scale).round()
+            );
+            assert_eq!(hinted.contours.len(), contours.len());
+            hinted
+                .contours
+                .iter()
+                .flatten()
+                .zip(contours.iter().flat_map(|contour| &contour.points))
+                .for_each(|((x, y, on_curve), point)| {
+                    assert_eq!((x.fract(), y.fract()), (0.0, 0.0));
+                    assert!((x - (point.x as f32 - origin) * scale).abs() <= 33.0 / 64.0);
+                    assert!((y - point.y as f32 * scale).abs() <= 33.0 / 64.0);
+                    assert_eq!(*on_curve, point.tpe == PointType::OnCurve);
+                });
+        });
+
+        // Without instructions the outline is only scaled
+        let glyph_id = glyph_reader.char_code_to_glyph_id(b'o' as u16);
+        let glyph = glyph_reader.glyph_for_glyph_id(glyph_id);
+        let hinted = hinted_reader.hinted_glyph(glyph_id, 12).unwrap();
+        assert_eq!(hinted.advance_width.fract(), 0.0);
+        let Glyph::Simple { contours, .. } = &glyph else {
+            panic!("Expected a simple glyph");
+        };
+        hinted
+            .contours
+            .iter()
+            .flatten()
+            .zip(contours.iter().flat_map(|contour| &contour.points))
+            .for_each(|((_, y, _), point)| {
+                assert!((y - point.y as f32 * 0.012).abs() <= 1.0 / 64.0);
+            });
+
+        let mut otf_reader = mk_glyph_reader_vec("fonts/Zeyada_1.otf");
+        assert_eq!(
+            otf_reader.hinted_glyph(GlyphId::new(1), 12),
+            Err(HintingError::UnsupportedOutlines)
+        );
+    }
+
+    #[test]
+    fn glyph_instructions_and_hinting_tables() {
+        let mut glyph_reader = mk_glyph_reader_vec("fonts/GolosText-Regular.ttf");
+        let h = glyph_reader.char_code_to_glyph_id(b'H' as u16);
+        let aacute = GlyphId::new(115);
+        let num_glyphs = glyph_reader.maximum_profile_table().num_glyphs as usize;
+
+        let gasp_table = glyph_reader.gasp_table().unwrap();
+        assert_eq!(
+            gasp_table.ranges,
+            vec![GaspRange {
+                range_max_ppem: 0xFFFF,
+                range_gasp_behavior: 0x000F
+            }]
+        );
+        assert_eq!(
+            gasp_table.behavior(12) & (GaspTable::GRIDFIT | GaspTable::DOGRAY),
+            3
+        );
+        assert_eq!(
+            glyph_reader.prep_table().unwrap().instructions,
+            vec![0xB8, 0x01, 0xFF, 0x85, 0xB0, 0x04, 0x8D]
+        );
+        assert!(glyph_reader.cvt_table().is_none());
+
+        let mut widths = vec![0; num_glyphs];
+        widths[h.id() as usize] = 9;
+        let record_size = (2 + num_glyphs + 3) & !3;
+        let mut hdmx = [be_u16s(&[0, 2]), be_u32s(&[record_size as u32])].concat();
+        [(12, 9), (16, 12)]
+            .iter()
+            .for_each(|(pixel_size, max_width)| {
+                let start = hdmx.len();
+                hdmx.extend([*pixel_size, *max_width]);
+                hdmx.extend(&widths);
+                hdmx.resize(start + record_size, 0);
+            });
+        let mut y_pels = vec![1; num_glyphs];
+        y_pels[h.id() as usize] = 20;
+        let ltsh = [be_u16s(&[0, num_glyphs as u16]), y_pels].concat();
+        let vdmx = [
+            be_u16s(&[1, 1, 2]),
+            vec![1, 1, 1, 1, 0, 0, 0, 0],
+            be_u16s(&[18, 18, 2]),
+            vec![12, 16],
+            be_u16s(&[12, 11, (-3i16) as u16, 16, 15, (-4i16) as u16]),
+        ]
+        .concat();
+
+        let mut tables = mk_glyf_with_instructions(&[h, aacute], &[0xB0, 0, 0x2F]);
+        tables.extend([
+            ("cvt ", be_u16s(&[60, 80, (-10i16) as u16])),
+            ("fpgm", vec![0xB0, 0, 0x2C, 0x2D]),
+            ("maxp", mk_hinting_maxp_table()),
+            ("hdmx", hdmx),
+            ("LTSH", ltsh),
+            ("VDMX", vdmx),
+        ]);
+        let font = mk_font_with_tables("fonts/GolosText-Regular.ttf", tables);
+        let mut hinted_reader = GlyphReader::from_vec(font);
+
+        let Glyph::Simple { instructions, .. } = hinted_reader.glyph_for_glyph_id(h) else {
+            panic!("Expected a simple glyph");
+        };
+        assert_eq!(instructions, vec![0xB0, 0, 0x2F]);
+        let Glyph::Compound {
+            components,
+            instructions,
+            ..
+        } = hinted_reader.glyph_for_glyph_id(aacute)
+        else {
+            panic!("Expected a compound glyph");
+        };
+        assert_eq!(components.len(), 2);
+        assert_eq!(instructions, vec![0xB0, 0, 0x2F]);
+        let hinted = hinted_reader.hinted_glyph(aacute, 12).unwrap();
+        assert_eq!(hinted.contours[0][0].0.fract(), 0.0);
+
+        assert_eq!(hinted_reader.cvt_table().unwrap().values, vec![60, 80, -10]);
+        assert_eq!(
+            hinted_reader.fpgm_table().unwrap().instructions,
+            vec![0xB0, 0, 0x2C, 0x2D]
+        );
+        let hdmx_table = hinted_reader.hdmx_table().unwrap();
+        assert_eq!(hdmx_table.records[1].max_width, 12);
+        assert_eq!(hdmx_table.advance_width(h, 12), Some(9));
+        assert_eq!(hdmx_table.advance_width(h, 14), None);
+        let ltsh_table = hinted_reader.ltsh_table().unwrap();
+        assert_eq!(ltsh_table.y_pel(h), Some(20));
+        assert_eq!(ltsh_table.y_pel(aacute), Some(1));
+        let vdmx_table = hinted_reader.vdmx_table().unwrap();
+        assert_eq!(vdmx_table.extents(12, 1, 1), Some((11, -3)));
+        assert_eq!(vdmx_table.extents(16, 2, 1), Some((15, -4)));
+        assert_eq!(vdmx_table.extents(13, 1, 1), None);
+
+        // Subsets keep the instructions unless the hinting is dropped
+        let font_bytes = mk_font_with_tables(
+            "fonts/GolosText-Regular.ttf",
+            mk_glyf_with_instructions(&[h], &[0xB0, 0, 0x2F]),
+        );
+        let subset_instructions = |drop_hinting: bool| {
+            let options = SubsetOptions {
+                drop_hinting,
+                drop_names: false,
+            };
+            let subset_font = subset_with_options(&font_bytes, &['H'], &options).unwrap();
+            let mut subset_reader = GlyphReader::from_vec(subset_font);
+            let h = subset_reader.char_code_to_glyph_id(b'H' as u16);
+            let Glyph::Simple { instructions, .. } = subset_reader.glyph_for_glyph_id(h) else {
+                panic!("Expected a simple glyph");
+            };
+            (instructions, subset_reader.prep_table().is_some())
+        };
+        assert_eq!(subset_instructions(false), (vec![0xB0, 0, 0x2F], true));
+        assert_eq!(subset_instructions(true), (vec![], false));
+    }
+
+    #[test]
+    fn disassemble_instructions() {
+        let instructions = [
+            0xB1, 2, 0, 0x2C, 0xB0, 1, 0x58, 0x01, 0x1B, 0xEC, 0x59, 0x2D, 0x40, 2, 7, 9, 0xB8,
+            0xFF, 0xFD, 0x69, 0xC4, 0x83, 0x41, 2, 0,
+        ];
+        assert_eq!(
+            disassemble(&instructions),
+            vec![
+                "PUSHB[2] 2 0",
+                "FDEF",
+                "  PUSHB[1] 1",
+                "  IF",
+                "    SVTCA[1]",
+                "  ELSE",
+                "    MIRP[01100]",
+                "  EIF",
+                "ENDF",
+                "NPUSHB 7 9",
+                "PUSHW[1] -3",
+                "ROUND[01]",
+                "MDRP[00100]",
+                "0x83",
+                "NPUSHW <truncated>",
+            ]
+        );
+    }
+
+    #[test]
+    fn autohinter_blue_zones() {
+        let mut glyph_reader = mk_glyph_reader_vec("fonts/GolosText-Regular.ttf");
+        let os2_table = glyph_reader.os2_table().unwrap();
+        let x_height = os2_table.x_height.unwrap().0 as f32;
+        let cap_height = os2_table.cap_height.unwrap().0 as f32;
+
+        let autohinter = glyph_reader.autohinter();
+        assert_eq!(
+            autohinter.baseline,
+            Some(BlueZone {
+                reference: 0.0,
+                overshoot: -10.0
+            })
+        );
+        assert_eq!(autohinter.x_height.unwrap().reference, x_height);
+        assert_eq!(autohinter.cap_height.unwrap().reference, cap_height);
+        assert!(autohinter.x_height.unwrap().overshoot > x_height);
+        assert_eq!(autohinter.standard_stem, Some(82.0));
+    }
+
+    #[test]
+    fn autohinted_glyph_golos() {
+        let mut glyph_reader = mk_glyph_reader_vec("fonts/GolosText-Regular.ttf");
+        let glyph_id =
+            |glyph_reader: &mut GlyphReader, c: u8| glyph_reader.char_code_to_glyph_id(c as u16);
+        let (h, x, o) = (
+            glyph_id(&mut glyph_reader, b'H'),
+            glyph_id(&mut glyph_reader, b'x'),
+            glyph_id(&mut glyph_reader, b'o'),
+        );
+        let ys = |glyph: &HintedGlyph| -> Vec<f32> {
+            glyph
+                .contours
+                .iter()
+                .flatten()
+                .map(|(_, y, _)| *y)
+                .collect()
+        };
+
+        [9, 12, 16, 23, 40].iter().for_each(|ppem| {
+            let scale = *ppem as f32 / 1000.0;
+
+            // Every edge of H is flat, the baseline and cap height are whole pixels
+            let glyph = glyph_reader.autohinted_glyph(h, *ppem);
+            let h_ys = ys(&glyph);
+            assert!(h_ys.iter().all(|y| y.fract() == 0.0), "{:?}", h_ys);
+            assert_eq!(h_ys.iter().cloned().reduce(f32::min), Some(0.0));
+            assert_eq!(
+                h_ys.iter().cloned().reduce(f32::max),
+                Some((700.0 * scale).round())
+            );
+            assert!(!glyph.cubic);
+            assert!((glyph.advance_width - 760.0 * scale).abs() < 0.001);
+
+            // x keeps its scaled horizontal positions
+            let glyph = glyph_reader.autohinted_glyph(x, *ppem);
+            let x_ys = ys(&glyph);
+            assert_eq!(
+                x_ys.iter().cloned().reduce(f32::max),
+                Some((530.0 * scale).round())
+            );
+            let unhinted = glyph_reader.glyph_for_glyph_id(x);
+            let Glyph::Simple { contours, .. } = unhinted else {
+                panic!("Expected a simple glyph");
+            };
+            glyph
+                .contours
+                .iter()
+                .flatten()
+                .zip(contours.iter().flat_map(|contour| &contour.points))
+                .for_each(|((hinted_x, _, _), point)| {
+                    assert!((hinted_x - point.x as f32 * scale).abs() < 0.001);
+                });
+
+            // The overshoot of o is dropped until it reaches half a pixel
+            let o_ys = ys(&glyph_reader.autohinted_glyph(o, *ppem));
+            let top = o_ys.iter().cloned().reduce(f32::max).unwrap();
+            let bottom = o_ys.iter().cloned().reduce(f32::min).unwrap();
+            if 10.0 * scale < 0.5 {
+                assert_eq!(top, (530.0 * scale).round());
+                assert_eq!(bottom, 0.0);
+            } else {
+                assert_eq!(top, (530.0 * scale).round() + 1.0);
+                assert_eq!(bottom, -1.0);
+            }
+        });
+    }
+
+    #[test]
+    fn autohinted_glyph_zeyada() {
+        ["fonts/Zeyada_1.ttf", "fonts/Zeyada_1.otf"]
+            .iter()
+            .for_each(|path| {
+                let mut glyph_reader = mk_glyph_reader_vec(path);
+                (b'a'..=b'z').chain(b'A'..=b'Z').for_each(|c| {
+                    let glyph_id = glyph_reader.char_code_to_glyph_id(c as u16);
+                    let glyph = glyph_reader.glyph_for_glyph_id(glyph_id);
+                    let (_, y_min, _, y_max) = glyph.bounding_box().unwrap();
+                    let hinted = glyph_reader.autohinted_glyph(glyph_id, 16);
+                    assert_eq!(hinted.cubic, path.ends_with(".otf"));
+
+                    // Heights move by less than a pixel and a half
+                    let scale = 16.0 / 1024.0;
+                    hinted.contours.iter().flatten().for_each(|(_, y, _)| {
+                        assert!(*y >= y_min as f32 * scale - 1.5);
+                        assert!(*y <= y_max as f32 * scale + 1.5);
+                    });
+                });
+            });
+    }
+
+    fn mk_square_glyph(x_min: f32, y_min: f32, x_max: f32, y_max: f32) -> HintedGlyph {
+        HintedGlyph {
+            contours: vec![vec![
+                (x_min, y_min, true),
+                (x_min, y_max, true),
+                (x_max, y_max, true),
+                (x_max, y_min, true),
+            ]],
+            advance_width: 5.0,
+            cubic: false,
+        }
+    }
+
+    fn polygon_area(glyph: &HintedGlyph) -> f32 {
+        let area: f32 = glyph
+            .contours
+            .iter()
+            .map(|contour| {
+                (0..contour.len())
+                    .map(|index| {
+                        let (x1, y1, _) = contour[index];
+                        let (x2, y2, _) = contour[(index + 1) % contour.len()];
+                        x1 * y2 - x2 * y1
+                    })
+                    .sum::<f32>()
+            })
+            .sum();
+        area.abs() / 2.0
+    }
+
+    #[test]
+    fn rasterize_squares() {
+        let coverage = rasterize(&mk_square_glyph(1.0, 1.0, 3.0, 3.0));
+        assert_eq!((coverage.width, coverage.height), (2, 2));
+        assert_eq!((coverage.left, coverage.top), (1, 3));
+        assert_eq!(coverage.values, vec![255; 4]);
+        assert_eq!(coverage.advance_width, 5.0);
+
+        // Half covered edges and quarter covered corners
+        let coverage = rasterize(&mk_square_glyph(0.5, -0.5, 2.5, 1.5));
+        assert_eq!((coverage.width, coverage.height), (3, 3));
+        assert_eq!((coverage.left, coverage.top), (0, 2));
+        assert_eq!(
+            coverage.values,
+            vec![64, 128, 64, 128, 255, 128, 64, 128, 64]
+        );
+
+        let coverage = rasterize(&HintedGlyph {
+            contours: vec![],
+            advance_width: 3.0,
+            cubic: false,
+        });
+        assert_eq!((coverage.width, coverage.height), (0, 0));
+        assert!(coverage.values.is_empty());
+    }
+
+    #[test]
+    fn rendered_glyph_golos() {
+        let glyph_id =
+            mk_glyph_reader_vec("fonts/GolosText-Regular.ttf").char_code_to_glyph_id(b'H' as u16);
+        let mut tables = mk_glyf_with_instructions(&[glyph_id], &[0xB0, 0, 0x2F]);
+        tables.push(("maxp", mk_hinting_maxp_table()));
+        let mut glyph_reader =
+            GlyphReader::from_vec(mk_font_with_tables("fonts/GolosText-Regular.ttf", tables));
+        [9, 16, 32].iter().for_each(|ppem| {
+            let hinted = glyph_reader.hinted_glyph(glyph_id, *ppem).unwrap();
+            let coverage = glyph_reader.rendered_glyph(glyph_id, *ppem);
+            assert_eq!(coverage, rasterize(&hinted));
+            assert_eq!(coverage.values.len(), coverage.width * coverage.height);
+
+            // H is only made of straight lines, so its coverage adds up to its area
+            let total: f32 = coverage.values.iter().map(|value| *value as f32).sum();
+            let area = polygon_area(&hinted);
+            assert!((total / 255.0 - area).abs() < area * 0.01);
+        });
+
+        // Zeyada has a prep program but no glyph instructions
+        let mut glyph_reader = mk_glyph_reader_vec("fonts/Zeyada_1.ttf");
+        let glyph_id = glyph_reader.char_code_to_glyph_id(b'x' as u16);
+        let autohinted = glyph_reader.autohinted_glyph(glyph_id, 16);
+        assert_eq!(
+            glyph_reader.rendered_glyph(glyph_id, 16),
+            rasterize(&autohinted)
+        );
+    }
+
+    #[test]
+    fn rasterize_lcd_squares() {
+        let glyph = mk_square_glyph(1.0, 1.0, 3.0, 3.0);
+
+        // One pixel of padding across the stripes, the filter spreads into it
+        let rgb = rasterize_lcd(&glyph, SubpixelLayout::Rgb, LcdFilter::DEFAULT);
+        assert_eq!((rgb.width, rgb.height), (4, 2));
+        assert_eq!((rgb.left, rgb.top), (0, 3));
+        assert_eq!(
+            &rgb.values[..12],
+            &[0, 7, 84, 170, 247, 255, 255, 247, 170, 84, 7, 0]
+        );
+        assert_eq!(rgb.values[..12], rgb.values[12..]);
+
+        let bgr = rasterize_lcd(&glyph, SubpixelLayout::Bgr, LcdFilter::DEFAULT);
+        rgb.values
+            .chunks(3)
+            .zip(bgr.values.chunks(3))
+            .for_each(|(rgb, bgr)| assert_eq!(rgb, [bgr[2], bgr[1], bgr[0]]));
+
+        let light = rasterize_lcd(&glyph, SubpixelLayout::Rgb, LcdFilter::LIGHT);
+        assert_eq!(
+            &light.values[..12],
+            &[0, 0, 84, 170, 255, 255, 255, 255, 170, 84, 0, 0]
+        );
+
+        let vertical = rasterize_lcd(&glyph, SubpixelLayout::VerticalRgb, LcdFilter::DEFAULT);
+        assert_eq!((vertical.width, vertical.height), (2, 4));
+        assert_eq!((vertical.left, vertical.top), (1, 4));
+        let column: Vec<u8> = vertical.values.iter().step_by(6).cloned().collect();
+        assert_eq!(column, vec![0, 170, 255, 84]);
+    }
+
+    #[test]
+    fn lcd_glyph_zeyada() {
+        ["fonts/Zeyada_1.ttf", "fonts/Zeyada_1.otf"]
+            .iter()
+            .for_each(|path| {
+                let mut glyph_reader = mk_glyph_reader_vec(path);
+                let glyph_id = glyph_reader.char_code_to_glyph_id(b'g' as u16);
+                let gray = glyph_reader.rendered_glyph(glyph_id, 24);
+                let gray_total: u32 = gray.values.iter().map(|value| *value as u32).sum();
+
+                [
+                    SubpixelLayout::Rgb,
+                    SubpixelLayout::Bgr,
+                    SubpixelLayout::VerticalRgb,
+                    SubpixelLayout::VerticalBgr,
+                ]
+                .iter()
+                .for_each(|layout| {
+                    let lcd = glyph_reader.lcd_glyph(glyph_id, 24, *layout, LcdFilter::DEFAULT);
+                    assert_eq!(lcd.values.len(), lcd.width * lcd.height * 3);
+                    assert_eq!(lcd.advance_width, gray.advance_width);
+
+                    // The filter keeps the ink of the three times finer rendering
+                    let total: u32 = lcd.values.iter().map(|value| *value as u32).sum();
+                    let expected = 3 * gray_total;
+                    assert!(
+                        total.abs_diff(expected) < expected / 50,
+                        "{} {}",
+                        total,
+                        expected
+                    );
+                });
+            });
     }
 }
@@ -0,0 +1,199 @@
+// Simplified line breaking following the pair rules of UAX #14
+// https://www.unicode.org/reports/tr14/
+//
+// Only the classes needed for Latin, Cyrillic and CJK text are distinguished,
+// everything else is treated as alphabetic.
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BreakClass {
+    MandatoryBreak,   // BK, CR, LF, NL
+    Space,            // SP
+    ZeroWidthSpace,   // ZW
+    Glue,             // GL, non-breaking characters
+    Hyphen,           // HY
+    BreakAfter,       // BA
+    Ideographic,      // ID, H2, H3
+    Nonstarter,       // NS, CJ
+    OpenPunctuation,  // OP
+    ClosePunctuation, // CL, CP
+    Exclamation,      // EX
+    InfixSeparator,   // IS
+    Numeric,          // NU
+    Alphabetic,       // AL and everything else
+}
+
+impl BreakClass {
+    pub fn from(ch: char) -> BreakClass {
+        match ch {
+            '\n' | '\r' | '\u{000B}' | '\u{000C}' | '\u{0085}' | '\u{2028}' | '\u{2029}' => {
+                BreakClass::MandatoryBreak
+            }
+            ' ' => BreakClass::Space,
+            '\u{200B}' => BreakClass::ZeroWidthSpace,
+            '\u{00A0}' | '\u{2007}' | '\u{2011}' | '\u{202F}' | '\u{2060}' | '\u{FEFF}' => {
+                BreakClass::Glue
+            }
+            '-' => BreakClass::Hyphen,
+            '\t'
+            | '\u{00AD}'
+            | '\u{2010}'
+            | '\u{2012}'
+            | '\u{2013}'
+            | '\u{2000}'..='\u{2006}'
+            | '\u{2008}'..='\u{200A}'
+            | '\u{3000}' => BreakClass::BreakAfter,
+            '(' | '[' | '{' | '\u{00A1}' | '\u{00BF}' | '\u{2018}' | '\u{201C}' | '\u{3008}'
+            | '\u{300A}' | '\u{300C}' | '\u{300E}' | '\u{3010}' | '\u{3014}' | '\u{3016}'
+            | '\u{FF08}' | '\u{FF3B}' | '\u{FF5B}' => BreakClass::OpenPunctuation,
+            ')' | ']' | '}' | '\u{2019}' | '\u{201D}' | '\u{3001}' | '\u{3002}' | '\u{3009}'
+            | '\u{300B}' | '\u{300D}' | '\u{300F}' | '\u{3011}' | '\u{3015}' | '\u{3017}'
+            | '\u{FF09}' | '\u{FF0C}' | '\u{FF0E}' | '\u{FF3D}' | '\u{FF5D}' => {
+                BreakClass::ClosePunctuation
+            }
+            '!' | '?' | '\u{FF01}' | '\u{FF1F}' => BreakClass::Exclamation,
+            ',' | '.' | ':' | ';' | '/' | '\u{FF1A}' | '\u{FF1B}' => BreakClass::InfixSeparator,
+            '0'..='9' => BreakClass::Numeric,
+            // Small kana, prolonged sound mark and iteration marks must not start a line
+            '\u{3005}' | '\u{303B}' | '\u{309D}' | '\u{309E}' | '\u{30FB}' | '\u{30FC}'
+            | '\u{30FD}' | '\u{30FE}' | '\u{3041}' | '\u{3043}' | '\u{3045}' | '\u{3047}'
+            | '\u{3049}' | '\u{3063}' | '\u{3083}' | '\u{3085}' | '\u{3087}' | '\u{308E}'
+            | '\u{30A1}' | '\u{30A3}' | '\u{30A5}' | '\u{30A7}' | '\u{30A9}' | '\u{30C3}'
+            | '\u{30E3}' | '\u{30E5}' | '\u{30E7}' | '\u{30EE}' => BreakClass::Nonstarter,
+            '\u{1100}'..='\u{115F}'
+            | '\u{2E80}'..='\u{2FFF}'
+            | '\u{3003}'..='\u{3004}'
+            | '\u{3006}'..='\u{3007}'
+            | '\u{3012}'..='\u{3013}'
+            | '\u{3020}'..='\u{303A}'
+            | '\u{303C}'..='\u{303F}'
+            | '\u{3040}'..='\u{309F}'
+            | '\u{30A0}'..='\u{30FF}'
+            | '\u{3100}'..='\u{31FF}'
+            | '\u{3200}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{A000}'..='\u{A4CF}'
+            | '\u{AC00}'..='\u{D7AF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{FE30}'..='\u{FE4F}'
+            | '\u{FF00}'..='\u{FF60}'
+            | '\u{FFE0}'..='\u{FFE6}'
+            | '\u{20000}'..='\u{3FFFD}' => BreakClass::Ideographic,
+            _ => BreakClass::Alphabetic,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BreakOpportunity {
+    Mandatory,
+    Allowed,
+    Prohibited,
+}
+
+// Returns the break opportunity before each character of `chars`,
+// plus the one at the end of the text.
+pub fn break_opportunities(chars: &[char]) -> Vec<BreakOpportunity> {
+    let classes: Vec<BreakClass> = chars.iter().map(|ch| BreakClass::from(*ch)).collect();
+
+    let mut result = Vec::with_capacity(chars.len() + 1);
+
+    // LB2: never break at the start of text
+    result.push(BreakOpportunity::Prohibited);
+
+    for index in 1..chars.len() {
+        result.push(pair_break(chars, &classes, index));
+    }
+
+    // LB3: always break at the end of text
+    if !chars.is_empty() {
+        result.push(BreakOpportunity::Mandatory);
+    }
+
+    result
+}
+
+fn pair_break(chars: &[char], classes: &[BreakClass], index: usize) -> BreakOpportunity {
+    let before = classes[index - 1];
+    let after = classes[index];
+
+    // LB5: treat CR LF as a single mandatory break
+    if chars[index - 1] == '\r' && chars[index] == '\n' {
+        return BreakOpportunity::Prohibited;
+    }
+
+    // LB4, LB5: always break after hard line breaks
+    if before == BreakClass::MandatoryBreak {
+        return BreakOpportunity::Mandatory;
+    }
+
+    // LB6, LB7: do not break before hard line breaks, spaces or zero width space
+    if matches!(
+        after,
+        BreakClass::MandatoryBreak | BreakClass::Space | BreakClass::ZeroWidthSpace
+    ) {
+        return BreakOpportunity::Prohibited;
+    }
+
+    // The class of the last non space character before the break, needed by LB8, LB14 - LB17
+    let before_spaces = classes[..index]
+        .iter()
+        .rev()
+        .find(|class| **class != BreakClass::Space)
+        .copied();
+
+    // LB8: break before any character following a zero width space, even after spaces
+    if before_spaces == Some(BreakClass::ZeroWidthSpace) {
+        return BreakOpportunity::Allowed;
+    }
+
+    // LB11, LB12: do not break around glue
+    if before == BreakClass::Glue || after == BreakClass::Glue {
+        return BreakOpportunity::Prohibited;
+    }
+
+    // LB13: do not break before closing punctuation, '!' or '/' even after spaces
+    if matches!(
+        after,
+        BreakClass::ClosePunctuation | BreakClass::Exclamation | BreakClass::InfixSeparator
+    ) {
+        return BreakOpportunity::Prohibited;
+    }
+
+    // LB14: do not break after opening punctuation, even after spaces
+    if before_spaces == Some(BreakClass::OpenPunctuation) {
+        return BreakOpportunity::Prohibited;
+    }
+
+    // LB18: break after spaces
+    if before == BreakClass::Space {
+        return BreakOpportunity::Allowed;
+    }
+
+    // LB21: do not break before hyphens, small kana and other non starters
+    if matches!(
+        after,
+        BreakClass::Hyphen | BreakClass::BreakAfter | BreakClass::Nonstarter
+    ) {
+        return BreakOpportunity::Prohibited;
+    }
+
+    // LB25: do not break a hyphen preceding a number, like in "-1"
+    if before == BreakClass::Hyphen && after == BreakClass::Numeric {
+        return BreakOpportunity::Prohibited;
+    }
+
+    // LB21: break after hyphens
+    if matches!(before, BreakClass::Hyphen | BreakClass::BreakAfter) {
+        return BreakOpportunity::Allowed;
+    }
+
+    // Pair table for ID: break before and after ideographs
+    if matches!(before, BreakClass::Ideographic | BreakClass::Nonstarter)
+        || after == BreakClass::Ideographic
+    {
+        return BreakOpportunity::Allowed;
+    }
+
+    // LB31: do not break anywhere else
+    BreakOpportunity::Prohibited
+}
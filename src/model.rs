@@ -6,10 +6,10 @@ pub enum PlatformId {
     Microsoft,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct FWord(pub i16);
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct UFWord(pub u16);
 
 #[allow(unused)]
@@ -19,7 +19,14 @@ pub struct Fixed {
     pub minor: u16,
 }
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+impl Fixed {
+    // 16.16 signed fixed point number
+    pub fn to_f32(&self) -> f32 {
+        self.major as i16 as f32 + self.minor as f32 / 65536.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct GlyphId(u16);
 
 impl GlyphId {
@@ -40,6 +47,8 @@ pub enum Glyph {
         glyph_id: GlyphId,
         advance_width: u16,
         left_side_bearing: i16,
+        advance_height: u16,
+        top_side_bearing: i16,
     },
     Simple {
         glyph_id: GlyphId,
@@ -49,7 +58,11 @@ pub enum Glyph {
         y_max: FWord, // Maximum y for coordinate data
         advance_width: u16,
         left_side_bearing: i16,
+        advance_height: u16,
+        top_side_bearing: i16,
+        overlap_simple: bool, // contours may overlap, set on the first point flag
         contours: Vec<Contour>,
+        instructions: Vec<u8>, // TrueType bytecode grid fitting the outline
     },
     Compound {
         glyph_id: GlyphId,
@@ -59,7 +72,23 @@ pub enum Glyph {
         y_max: FWord,
         advance_width: u16,
         left_side_bearing: i16,
+        advance_height: u16,
+        top_side_bearing: i16,
         components: Vec<ComponentData>,
+        instructions: Vec<u8>, // run after the hinted components are assembled
+    },
+    // PostScript outline from CFF, control points come in pairs of a cubic curve
+    Cubic {
+        glyph_id: GlyphId,
+        x_min: FWord,
+        y_min: FWord,
+        x_max: FWord,
+        y_max: FWord,
+        advance_width: u16,
+        left_side_bearing: i16,
+        advance_height: u16,
+        top_side_bearing: i16,
+        contours: Vec<Contour>,
     },
 }
 
@@ -69,6 +98,168 @@ impl Glyph {
             Glyph::Empty { advance_width, .. } => *advance_width,
             Glyph::Simple { advance_width, .. } => *advance_width,
             Glyph::Compound { advance_width, .. } => *advance_width,
+            Glyph::Cubic { advance_width, .. } => *advance_width,
+        }
+    }
+
+    pub fn left_side_bearing(&self) -> i16 {
+        match self {
+            Glyph::Empty {
+                left_side_bearing, ..
+            } => *left_side_bearing,
+            Glyph::Simple {
+                left_side_bearing, ..
+            } => *left_side_bearing,
+            Glyph::Compound {
+                left_side_bearing, ..
+            } => *left_side_bearing,
+            Glyph::Cubic {
+                left_side_bearing, ..
+            } => *left_side_bearing,
+        }
+    }
+
+    pub fn advance_height(&self) -> u16 {
+        match self {
+            Glyph::Empty { advance_height, .. } => *advance_height,
+            Glyph::Simple { advance_height, .. } => *advance_height,
+            Glyph::Compound { advance_height, .. } => *advance_height,
+            Glyph::Cubic { advance_height, .. } => *advance_height,
+        }
+    }
+
+    pub fn top_side_bearing(&self) -> i16 {
+        match self {
+            Glyph::Empty {
+                top_side_bearing, ..
+            } => *top_side_bearing,
+            Glyph::Simple {
+                top_side_bearing, ..
+            } => *top_side_bearing,
+            Glyph::Compound {
+                top_side_bearing, ..
+            } => *top_side_bearing,
+            Glyph::Cubic {
+                top_side_bearing, ..
+            } => *top_side_bearing,
+        }
+    }
+
+    // The same glyph with its horizontal and vertical metrics replaced
+    pub fn with_metrics(
+        mut self,
+        advance_width: u16,
+        left_side_bearing: i16,
+        advance_height: u16,
+        top_side_bearing: i16,
+    ) -> Glyph {
+        match &mut self {
+            Glyph::Empty {
+                advance_width: aw,
+                left_side_bearing: lsb,
+                advance_height: ah,
+                top_side_bearing: tsb,
+                ..
+            }
+            | Glyph::Simple {
+                advance_width: aw,
+                left_side_bearing: lsb,
+                advance_height: ah,
+                top_side_bearing: tsb,
+                ..
+            }
+            | Glyph::Compound {
+                advance_width: aw,
+                left_side_bearing: lsb,
+                advance_height: ah,
+                top_side_bearing: tsb,
+                ..
+            }
+            | Glyph::Cubic {
+                advance_width: aw,
+                left_side_bearing: lsb,
+                advance_height: ah,
+                top_side_bearing: tsb,
+                ..
+            } => {
+                *aw = advance_width;
+                *lsb = left_side_bearing;
+                *ah = advance_height;
+                *tsb = top_side_bearing;
+            }
+        }
+        self
+    }
+
+    // The same glyph without TrueType instructions, for fonts written without
+    // fpgm, prep and cvt
+    pub fn without_instructions(mut self) -> Glyph {
+        if let Glyph::Simple { instructions, .. } | Glyph::Compound { instructions, .. } = &mut self
+        {
+            instructions.clear();
+        }
+        self
+    }
+
+    // The same glyph with its bounding box replaced, empty glyphs have none
+    pub fn with_bounding_box(mut self, bounding_box: (i16, i16, i16, i16)) -> Glyph {
+        match &mut self {
+            Glyph::Empty { .. } => {}
+            Glyph::Simple {
+                x_min,
+                y_min,
+                x_max,
+                y_max,
+                ..
+            }
+            | Glyph::Compound {
+                x_min,
+                y_min,
+                x_max,
+                y_max,
+                ..
+            }
+            | Glyph::Cubic {
+                x_min,
+                y_min,
+                x_max,
+                y_max,
+                ..
+            } => {
+                *x_min = FWord(bounding_box.0);
+                *y_min = FWord(bounding_box.1);
+                *x_max = FWord(bounding_box.2);
+                *y_max = FWord(bounding_box.3);
+            }
+        }
+        self
+    }
+
+    // (x_min, y_min, x_max, y_max), None for glyphs without outline
+    pub fn bounding_box(&self) -> Option<(i16, i16, i16, i16)> {
+        match self {
+            Glyph::Empty { .. } => None,
+            Glyph::Simple {
+                x_min,
+                y_min,
+                x_max,
+                y_max,
+                ..
+            } => Some((x_min.0, y_min.0, x_max.0, y_max.0)),
+            Glyph::Compound {
+                x_min,
+                y_min,
+                x_max,
+                y_max,
+                ..
+            } => Some((x_min.0, y_min.0, x_max.0, y_max.0)),
+            Glyph::Cubic {
+                x_min,
+                y_min,
+                x_max,
+                y_max,
+                ..
+            } => Some((x_min.0, y_min.0, x_max.0, y_max.0)),
         }
     }
 }
@@ -109,7 +300,9 @@ impl Point {
 
 #[derive(Debug)]
 pub struct Contours {
+    pub overlap_simple: bool,
     pub contours: Vec<Contour>,
+    pub instructions: Vec<u8>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -123,10 +316,38 @@ pub enum ArgumentTypes {
 #[allow(unused)]
 #[derive(Debug)]
 pub struct ComponentData {
+    pub flags: u16, // as read, see `ComponentFlag`
     pub glyph_index: u16,
-    pub a: i16,
+    pub a: i16, // F2DOT14 a, b, c, d are only meaningful with a transform flag
     pub b: i16,
     pub c: i16,
     pub d: i16,
     pub argument_types: ArgumentTypes, // encapsulates e, f
 }
+
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+impl ComponentData {
+    // Whether the flags give the component a scale or a 2x2 transform
+    pub fn has_transform(&self) -> bool {
+        self.flags & (WE_HAVE_A_SCALE | WE_HAVE_AN_X_AND_Y_SCALE | WE_HAVE_A_TWO_BY_TWO) != 0
+    }
+
+    // The 2x2 transform of the component from its F2DOT14 values, the identity
+    // when the flags have none
+    pub fn transform(&self) -> (f32, f32, f32, f32) {
+        if self.has_transform() {
+            let f2dot14 = |value: i16| value as f32 / 16384.0;
+            (
+                f2dot14(self.a),
+                f2dot14(self.b),
+                f2dot14(self.c),
+                f2dot14(self.d),
+            )
+        } else {
+            (1.0, 0.0, 0.0, 1.0)
+        }
+    }
+}
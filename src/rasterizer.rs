@@ -0,0 +1,304 @@
+use crate::hinting::HintedGlyph;
+
+// Tolerance of curve flattening, larger values give more line segments
+const FLATNESS: f32 = 3.0;
+const MAX_CURVE_SEGMENTS: usize = 64;
+
+// 8-bit coverage of a glyph, rows from top to bottom
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coverage {
+    pub width: usize,
+    pub height: usize,
+    pub left: i32, // x of the first column from the origin, in pixels
+    pub top: i32,  // y of the top of the first row above the baseline
+    pub values: Vec<u8>,
+    pub advance_width: f32, // in pixels
+}
+
+// Whole pixels around an outline
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Bounds {
+    pub left: i32,
+    pub top: i32,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Bounds {
+    // Padded by `padding_x` pixels left and right, `padding_y` above and below
+    pub fn of(glyph: &HintedGlyph, padding_x: i32, padding_y: i32) -> Bounds {
+        let points = glyph.contours.iter().flatten();
+        let (x_min, y_min, x_max, y_max) = points.fold(
+            (f32::MAX, f32::MAX, f32::MIN, f32::MIN),
+            |(x_min, y_min, x_max, y_max), (x, y, _)| {
+                (x_min.min(*x), y_min.min(*y), x_max.max(*x), y_max.max(*y))
+            },
+        );
+        if x_min > x_max {
+            return Bounds {
+                left: 0,
+                top: 0,
+                width: 0,
+                height: 0,
+            };
+        }
+        let left = x_min.floor() as i32 - padding_x;
+        let top = y_max.ceil() as i32 + padding_y;
+        Bounds {
+            left,
+            top,
+            width: (x_max.ceil() as i32 + padding_x - left) as usize,
+            height: (top - y_min.floor() as i32 + padding_y) as usize,
+        }
+    }
+}
+
+// Grayscale rendering of a hinted or scaled outline with the nonzero rule
+pub fn rasterize(glyph: &HintedGlyph) -> Coverage {
+    let bounds = Bounds::of(glyph, 0, 0);
+    Coverage {
+        width: bounds.width,
+        height: bounds.height,
+        left: bounds.left,
+        top: bounds.top,
+        values: coverage(glyph, bounds, 1, 1),
+        advance_width: glyph.advance_width,
+    }
+}
+
+// Coverage of `bounds` split in `x_scale` by `y_scale` cells per pixel, rows
+// of `bounds.width * x_scale` values from the top
+pub(crate) fn coverage(
+    glyph: &HintedGlyph,
+    bounds: Bounds,
+    x_scale: usize,
+    y_scale: usize,
+) -> Vec<u8> {
+    let mut accumulator = Accumulator::new(bounds.width * x_scale, bounds.height * y_scale);
+    let transform = |(x, y, _): &(f32, f32, bool)| {
+        (
+            (x - bounds.left as f32) * x_scale as f32,
+            (bounds.top as f32 - y) * y_scale as f32,
+        )
+    };
+    glyph.contours.iter().for_each(|contour| {
+        let points: Vec<((f32, f32), bool)> = contour
+            .iter()
+            .map(|point| (transform(point), point.2))
+            .collect();
+        if glyph.cubic {
+            draw_cubic_contour(&mut accumulator, &points);
+        } else {
+            draw_quadratic_contour(&mut accumulator, &points);
+        }
+    });
+    accumulator.values()
+}
+
+// Contour of on curve points and quadratic control points, two control points
+// in a row imply an on curve point between them
+fn draw_quadratic_contour(accumulator: &mut Accumulator, points: &[((f32, f32), bool)]) {
+    if points.is_empty() {
+        return;
+    }
+    let count = points.len();
+    let midpoint = |a: (f32, f32), b: (f32, f32)| ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+    let start_index = points.iter().position(|(_, on_curve)| *on_curve);
+    let (start, first) = match start_index {
+        Some(index) => (points[index].0, index + 1),
+        None => (midpoint(points[0].0, points[1 % count].0), 1),
+    };
+
+    let mut current = start;
+    let mut control: Option<(f32, f32)> = None;
+    (first..first + count).for_each(|index| {
+        let (point, on_curve) = points[index % count];
+        match (on_curve, control) {
+            (true, Some(control_point)) => {
+                accumulator.quadratic(current, control_point, point);
+                current = point;
+                control = None;
+            }
+            (true, None) => {
+                accumulator.line(current, point);
+                current = point;
+            }
+            (false, Some(control_point)) => {
+                let implied = midpoint(control_point, point);
+                accumulator.quadratic(current, control_point, implied);
+                current = implied;
+                control = Some(point);
+            }
+            (false, None) => control = Some(point),
+        }
+    });
+    match control {
+        Some(control_point) => accumulator.quadratic(current, control_point, start),
+        None => accumulator.line(current, start),
+    }
+}
+
+// Contour of on curve points and pairs of cubic control points
+fn draw_cubic_contour(accumulator: &mut Accumulator, points: &[((f32, f32), bool)]) {
+    let count = points.len();
+    let first = match points.iter().position(|(_, on_curve)| *on_curve) {
+        Some(first) => first,
+        None => return,
+    };
+
+    let mut current = points[first].0;
+    let mut controls: Vec<(f32, f32)> = vec![];
+    (first + 1..=first + count).for_each(|index| {
+        let (point, on_curve) = points[index % count];
+        if !on_curve {
+            controls.push(point);
+            return;
+        }
+        match controls[..] {
+            [] => accumulator.line(current, point),
+            [control] => accumulator.quadratic(current, control, point),
+            [control1, control2] => accumulator.cubic(current, control1, control2, point),
+            _ => {
+                controls.iter().chain([&point]).fold(current, |from, to| {
+                    accumulator.line(from, *to);
+                    *to
+                });
+            }
+        }
+        current = point;
+        controls.clear();
+    });
+}
+
+// Signed area covered in each cell by the lines drawn so far, summed along
+// rows into coverage
+struct Accumulator {
+    width: usize,
+    height: usize,
+    cells: Vec<f32>, // a spare cell at the end of each row
+}
+
+impl Accumulator {
+    fn new(width: usize, height: usize) -> Accumulator {
+        Accumulator {
+            width,
+            height,
+            cells: vec![0.0; (width + 2) * height],
+        }
+    }
+
+    fn add(&mut self, row: usize, x: i32, value: f32) {
+        let x = x.clamp(0, self.width as i32 + 1) as usize;
+        self.cells[row * (self.width + 2) + x] += value;
+    }
+
+    fn line(&mut self, from: (f32, f32), to: (f32, f32)) {
+        if from.1 == to.1 {
+            return;
+        }
+        let (direction, from, to) = if from.1 < to.1 {
+            (1.0, from, to)
+        } else {
+            (-1.0, to, from)
+        };
+        let dxdy = (to.0 - from.0) / (to.1 - from.1);
+        let mut x = from.0;
+        if from.1 < 0.0 {
+            x -= from.1 * dxdy;
+        }
+        let first_row = from.1.max(0.0) as usize;
+        let last_row = (to.1.ceil().max(0.0) as usize).min(self.height);
+        (first_row..last_row).for_each(|row| {
+            let dy = ((row + 1) as f32).min(to.1) - (row as f32).max(from.1);
+            let x_next = x + dxdy * dy;
+            let d = dy * direction;
+            let (x0, x1) = if x < x_next { (x, x_next) } else { (x_next, x) };
+            let x0_floor = x0.floor();
+            let x0_index = x0_floor as i32;
+            let x1_ceil = x1.ceil();
+            let x1_index = x1_ceil as i32;
+
+            if x1_index <= x0_index + 1 {
+                // Within one cell, the area right of the line spills to the next
+                let x_mid = 0.5 * (x + x_next) - x0_floor;
+                self.add(row, x0_index, d - d * x_mid);
+                self.add(row, x0_index + 1, d * x_mid);
+            } else {
+                let s = (x1 - x0).recip();
+                let x0_fraction = x0 - x0_floor;
+                let a0 = 0.5 * s * (1.0 - x0_fraction) * (1.0 - x0_fraction);
+                let x1_fraction = x1 - x1_ceil + 1.0;
+                let a_last = 0.5 * s * x1_fraction * x1_fraction;
+                self.add(row, x0_index, d * a0);
+                if x1_index == x0_index + 2 {
+                    self.add(row, x0_index + 1, d * (1.0 - a0 - a_last));
+                } else {
+                    let a1 = s * (1.5 - x0_fraction);
+                    self.add(row, x0_index + 1, d * (a1 - a0));
+                    (x0_index + 2..x1_index - 1).for_each(|x| self.add(row, x, d * s));
+                    let a2 = a1 + (x1_index - x0_index - 3) as f32 * s;
+                    self.add(row, x1_index - 1, d * (1.0 - a2 - a_last));
+                }
+                self.add(row, x1_index, d * a_last);
+            }
+            x = x_next;
+        });
+    }
+
+    fn quadratic(&mut self, p0: (f32, f32), p1: (f32, f32), p2: (f32, f32)) {
+        let deviation = (p0.0 - 2.0 * p1.0 + p2.0).hypot(p0.1 - 2.0 * p1.1 + p2.1);
+        let count = segment_count(deviation);
+        let mut from = p0;
+        (1..=count).for_each(|index| {
+            let t = index as f32 / count as f32;
+            let u = 1.0 - t;
+            let to = (
+                u * u * p0.0 + 2.0 * u * t * p1.0 + t * t * p2.0,
+                u * u * p0.1 + 2.0 * u * t * p1.1 + t * t * p2.1,
+            );
+            self.line(from, to);
+            from = to;
+        });
+    }
+
+    fn cubic(&mut self, p0: (f32, f32), p1: (f32, f32), p2: (f32, f32), p3: (f32, f32)) {
+        let deviation = (p0.0 - 2.0 * p1.0 + p2.0)
+            .hypot(p0.1 - 2.0 * p1.1 + p2.1)
+            .max((p1.0 - 2.0 * p2.0 + p3.0).hypot(p1.1 - 2.0 * p2.1 + p3.1));
+        let count = segment_count(deviation * 1.5);
+        let mut from = p0;
+        (1..=count).for_each(|index| {
+            let t = index as f32 / count as f32;
+            let u = 1.0 - t;
+            let to = (
+                u * u * u * p0.0
+                    + 3.0 * u * u * t * p1.0
+                    + 3.0 * u * t * t * p2.0
+                    + t * t * t * p3.0,
+                u * u * u * p0.1
+                    + 3.0 * u * u * t * p1.1
+                    + 3.0 * u * t * t * p2.1
+                    + t * t * t * p3.1,
+            );
+            self.line(from, to);
+            from = to;
+        });
+    }
+
+    fn values(&self) -> Vec<u8> {
+        self.cells
+            .chunks(self.width + 2)
+            .flat_map(|row| {
+                let mut sum = 0.0;
+                row[..self.width].iter().map(move |cell| {
+                    sum += cell;
+                    (sum.abs().min(1.0) * 255.0).round() as u8
+                })
+            })
+            .collect()
+    }
+}
+
+fn segment_count(deviation: f32) -> usize {
+    (1 + (FLATNESS * deviation).sqrt().sqrt() as usize).min(MAX_CURVE_SEGMENTS)
+}
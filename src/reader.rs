@@ -24,6 +24,15 @@ pub trait Reader {
     fn read_string(&mut self, length: u16) -> String;
     fn read_utf_16be(&mut self, length: u16) -> String;
 
+    // 2.14 signed fixed point number
+    fn read_f2dot14(&mut self) -> f32 {
+        self.read_i16() as f32 / 16384.0
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Vec<u8> {
+        (0..length).map(|_| self.read_u8()).collect()
+    }
+
     fn read_offset(&mut self, offset: i32) -> u16 {
         self.seek_from_current(offset);
 
@@ -141,16 +150,13 @@ impl Reader for FileOps {
     }
 
     fn read_string(&mut self, length: u16) -> String {
-        let bytes: Vec<u8> = (0..length).into_iter().map(|_| self.read_u8()).collect();
+        let bytes: Vec<u8> = (0..length).map(|_| self.read_u8()).collect();
 
         String::from_iter(bytes.iter().map(|ch| *ch as char))
     }
 
     fn read_utf_16be(&mut self, length: u16) -> String {
-        let bytes: Vec<u16> = (0..(length / 2))
-            .into_iter()
-            .map(|_| self.read_u16())
-            .collect();
+        let bytes: Vec<u16> = (0..(length / 2)).map(|_| self.read_u16()).collect();
 
         String::from_utf16(&bytes).unwrap()
     }
@@ -207,7 +213,7 @@ impl Reader for VecOps {
         buffer[5] = self.data[self.offset + 5];
         buffer[6] = self.data[self.offset + 6];
         buffer[7] = self.data[self.offset + 7];
-        self.offset = self.offset + 8;
+        self.offset += 8;
         i64::from_be_bytes(buffer)
     }
     fn read_fixed(&mut self) -> Fixed {
@@ -218,27 +224,27 @@ impl Reader for VecOps {
     fn read_u8(&mut self) -> u8 {
         let mut buffer = [0; 1];
         buffer[0] = self.data[self.offset];
-        self.offset = self.offset + 1;
+        self.offset += 1;
         u8::from_be_bytes(buffer)
     }
     fn read_i8(&mut self) -> i8 {
         let mut buffer = [0; 1];
         buffer[0] = self.data[self.offset];
-        self.offset = self.offset + 1;
+        self.offset += 1;
         i8::from_be_bytes(buffer)
     }
     fn read_u16(&mut self) -> u16 {
         let mut buffer = [0; 2];
         buffer[0] = self.data[self.offset];
         buffer[1] = self.data[self.offset + 1];
-        self.offset = self.offset + 2;
+        self.offset += 2;
         u16::from_be_bytes(buffer)
     }
     fn read_i16(&mut self) -> i16 {
         let mut buffer = [0; 2];
         buffer[0] = self.data[self.offset];
         buffer[1] = self.data[self.offset + 1];
-        self.offset = self.offset + 2;
+        self.offset += 2;
         i16::from_be_bytes(buffer)
     }
     fn read_u32(&mut self) -> u32 {
@@ -247,7 +253,7 @@ impl Reader for VecOps {
         buffer[1] = self.data[self.offset + 1];
         buffer[2] = self.data[self.offset + 2];
         buffer[3] = self.data[self.offset + 3];
-        self.offset = self.offset + 4;
+        self.offset += 4;
         u32::from_be_bytes(buffer)
     }
     fn read_table_name(&mut self) -> String {
@@ -256,21 +262,18 @@ impl Reader for VecOps {
         buffer[1] = self.data[self.offset + 1];
         buffer[2] = self.data[self.offset + 2];
         buffer[3] = self.data[self.offset + 3];
-        self.offset = self.offset + 4;
+        self.offset += 4;
         String::from_utf8_lossy(&buffer).to_string()
     }
     fn read_string(&mut self, length: u16) -> String {
-        let bytes: Vec<u8> = (0..length).into_iter().map(|_| self.read_u8()).collect();
-        self.offset = self.offset + length as usize;
+        let bytes: Vec<u8> = (0..length).map(|_| self.read_u8()).collect();
+        self.offset += length as usize;
         String::from_iter(bytes.iter().map(|ch| *ch as char))
     }
     fn read_utf_16be(&mut self, length: u16) -> String {
-        let bytes: Vec<u16> = (0..(length / 2))
-            .into_iter()
-            .map(|_| self.read_u16())
-            .collect();
+        let bytes: Vec<u16> = (0..(length / 2)).map(|_| self.read_u16()).collect();
 
-        self.offset = self.offset + length as usize;
+        self.offset += length as usize;
         String::from_utf16(&bytes).unwrap()
     }
 }
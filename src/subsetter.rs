@@ -0,0 +1,551 @@
+use crate::cmap_builder::CmapBuilder;
+use crate::font_builder::FontBuilder;
+use crate::glyf_encoder::{encode_glyf_and_loca, EncodedGlyf};
+use crate::glyph_reader::GlyphReader;
+use crate::layout_subsetter::{subset_gdef, subset_gpos, subset_gsub, subset_kern};
+use crate::model::{Glyph, GlyphId};
+use crate::writer::Writer;
+use std::collections::{BTreeSet, HashMap};
+
+// Tables copied as they are, they do not refer to glyph ids
+const KEPT_TABLES: [&str; 13] = [
+    "OS/2", "name", "cvt", "fpgm", "prep", "gasp", "VDMX", "fvar", "avar", "STAT", "MVAR", "cvar",
+    "meta",
+];
+
+const HINTING_TABLES: [&str; 5] = ["cvt", "fpgm", "prep", "cvar", "VDMX"];
+
+// Names kept with `drop_names`: copyright, family, subfamily, unique id, full
+// name, version and PostScript name
+const KEPT_NAME_IDS: [u16; 7] = [0, 1, 2, 3, 4, 5, 6];
+
+const POST_FORMAT_2: u32 = 0x00020000;
+const POST_FORMAT_3: u32 = 0x00030000;
+const STANDARD_MAC_GLYPH_NAMES: u16 = 258;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SubsetError {
+    UnsupportedOutlines, // CFF and CFF2 outlines are not subset
+}
+
+#[derive(Debug, Default)]
+pub struct SubsetOptions {
+    pub drop_hinting: bool, // drops glyph instructions, cvt, fpgm, prep, cvar and VDMX
+    pub drop_names: bool,   // keeps the name records listed in KEPT_NAME_IDS
+}
+
+// From the glyph ids of the original font to the ones of the subset. Kept
+// glyphs are renumbered in their original order.
+#[derive(Debug)]
+pub struct GlyphMap {
+    new_glyph_ids: HashMap<u16, u16>,
+}
+
+impl GlyphMap {
+    pub fn new(old_glyph_ids: &[u16]) -> GlyphMap {
+        GlyphMap {
+            new_glyph_ids: old_glyph_ids
+                .iter()
+                .enumerate()
+                .map(|(new_glyph_id, old_glyph_id)| (*old_glyph_id, new_glyph_id as u16))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, old_glyph_id: u16) -> Option<u16> {
+        self.new_glyph_ids.get(&old_glyph_id).copied()
+    }
+}
+
+pub fn subset(font_bytes: &[u8], chars: &[char]) -> Result<Vec<u8>, SubsetError> {
+    subset_with_options(font_bytes, chars, &SubsetOptions::default())
+}
+
+// A TrueType font with the glyphs of `chars`, .notdef and the components of
+// compound glyphs. GSUB is not followed: glyphs only reachable through
+// substitutions are left out, and the lookups pruned to the kept glyphs.
+// Tables which refer to glyphs and are not rewritten (hdmx, LTSH, HVAR,
+// VVAR, color and bitmap tables, ...) are dropped. Glyph instructions are
+// kept along with the font programs unless `drop_hinting` is set.
+pub fn subset_with_options(
+    font_bytes: &[u8],
+    chars: &[char],
+    options: &SubsetOptions,
+) -> Result<Vec<u8>, SubsetError> {
+    let mut glyph_reader = GlyphReader::from_vec(font_bytes.to_vec());
+    if !glyph_reader.table_tags().iter().any(|tag| tag == "glyf") {
+        return Err(SubsetError::UnsupportedOutlines);
+    }
+    let cmap = glyph_reader
+        .table_data("cmap")
+        .expect("cmap table not found");
+
+    let mut chars: Vec<char> = chars.to_vec();
+    chars.sort();
+    chars.dedup();
+    let char_glyph_ids: Vec<(char, u16)> = chars
+        .into_iter()
+        .map(|c| (c, find_glyph_id(&mut glyph_reader, &cmap, c)))
+        .filter(|(_, glyph_id)| *glyph_id != 0)
+        .collect();
+
+    let old_glyph_ids = glyph_closure(&mut glyph_reader, char_glyph_ids.iter().map(|(_, g)| *g));
+    let glyph_map = GlyphMap::new(&old_glyph_ids);
+
+    let glyphs: Vec<Glyph> = old_glyph_ids
+        .iter()
+        .map(|glyph_id| {
+            let glyph = glyph_reader.default_glyph_for_glyph_id(GlyphId::new(*glyph_id));
+            let glyph = remap_components(glyph, &glyph_map);
+            if options.drop_hinting {
+                glyph.without_instructions()
+            } else {
+                glyph
+            }
+        })
+        .collect();
+    let char_glyph_ids: Vec<(char, u16)> = char_glyph_ids
+        .into_iter()
+        .filter_map(|(c, glyph_id)| glyph_map.get(glyph_id).map(|glyph_id| (c, glyph_id)))
+        .collect();
+
+    let mut cmap_builder = CmapBuilder::new();
+    char_glyph_ids.iter().for_each(|(c, glyph_id)| {
+        cmap_builder.insert(*c, *glyph_id);
+    });
+
+    let EncodedGlyf {
+        glyf,
+        loca,
+        index_to_loc_format,
+    } = encode_glyf_and_loca(&glyphs, 2).map_err(|_| SubsetError::UnsupportedOutlines)?;
+    let (hmtx, number_of_h_metrics) = encode_metrics(
+        &glyphs
+            .iter()
+            .map(|glyph| (glyph.advance_width(), glyph.left_side_bearing()))
+            .collect::<Vec<(u16, i16)>>(),
+    );
+    let (vmtx, number_of_v_metrics) = encode_metrics(
+        &glyphs
+            .iter()
+            .map(|glyph| (glyph.advance_height(), glyph.top_side_bearing()))
+            .collect::<Vec<(u16, i16)>>(),
+    );
+
+    let mut font_builder = FontBuilder::new();
+    glyph_reader.table_tags().iter().for_each(|table_tag| {
+        let tag = table_tag.trim_end();
+        if options.drop_hinting && HINTING_TABLES.contains(&tag) {
+            return;
+        }
+        let data = glyph_reader.table_data(table_tag).expect("table not found");
+
+        let data: Option<Vec<u8>> = match tag {
+            "glyf" => Some(glyf.clone()),
+            "loca" => Some(loca.clone()),
+            "hmtx" => Some(hmtx.clone()),
+            "vmtx" => Some(vmtx.clone()),
+            "cmap" => Some(cmap_builder.build()),
+            "head" => Some(update_head(data, &glyphs, index_to_loc_format)),
+            "hhea" => Some(update_horizontal_header(data, &glyphs, number_of_h_metrics)),
+            "vhea" => Some(update_vertical_header(data, &glyphs, number_of_v_metrics)),
+            "maxp" => {
+                let mut data = data;
+                write_u16(&mut data, 4, glyphs.len() as u16);
+                Some(data)
+            }
+            "OS/2" => Some(update_os2(data, &char_glyph_ids)),
+            "post" => Some(subset_post(data, &old_glyph_ids)),
+            "name" if options.drop_names => Some(drop_names(&data)),
+            "VORG" => Some(subset_vorg(&data, &glyph_map)),
+            "gvar" => Some(subset_gvar(&data, &old_glyph_ids)),
+            "GSUB" => subset_gsub(&data, &glyph_map),
+            "GPOS" => subset_gpos(&data, &glyph_map),
+            "GDEF" => subset_gdef(&data, &glyph_map),
+            "kern" => subset_kern(&data, &glyph_map),
+            tag if KEPT_TABLES.contains(&tag) => Some(data),
+            _ => None,
+        };
+        if let Some(data) = data {
+            font_builder.add_table(tag, data);
+        }
+    });
+    Ok(font_builder.build())
+}
+
+// BMP characters go through the reader, the others through a format 12 subtable
+fn find_glyph_id(glyph_reader: &mut GlyphReader, cmap: &[u8], c: char) -> u16 {
+    match u16::try_from(c as u32) {
+        Ok(char_code) => glyph_reader.char_code_to_glyph_id(char_code).id(),
+        Err(_) => find_format_12_glyph_id(cmap, c as u32).unwrap_or(0),
+    }
+}
+
+fn find_format_12_glyph_id(cmap: &[u8], char_code: u32) -> Option<u16> {
+    let number_subtables = read_u16(cmap, 2) as usize;
+    (0..number_subtables)
+        .map(|index| read_u32(cmap, 4 + index * 8 + 4) as usize)
+        .find(|subtable| read_u16(cmap, *subtable) == 12)
+        .and_then(|subtable| {
+            let num_groups = read_u32(cmap, subtable + 12) as usize;
+            (0..num_groups)
+                .map(|index| subtable + 16 + index * 12)
+                .find(|group| {
+                    read_u32(cmap, *group) <= char_code && char_code <= read_u32(cmap, group + 4)
+                })
+                .map(|group| {
+                    let start_char_code = read_u32(cmap, group);
+                    (read_u32(cmap, group + 8) + char_code - start_char_code) as u16
+                })
+        })
+}
+
+// Glyph 0, .notdef, is always kept. The result is sorted.
+fn glyph_closure(glyph_reader: &mut GlyphReader, glyph_ids: impl Iterator<Item = u16>) -> Vec<u16> {
+    let num_glyphs = glyph_reader.maximum_profile_table().num_glyphs;
+    let mut kept: BTreeSet<u16> = BTreeSet::new();
+    let mut pending: Vec<u16> = glyph_ids.collect();
+    pending.push(0);
+
+    while let Some(glyph_id) = pending.pop() {
+        if glyph_id >= num_glyphs || !kept.insert(glyph_id) {
+            continue;
+        }
+        if let Glyph::Compound { components, .. } =
+            glyph_reader.default_glyph_for_glyph_id(GlyphId::new(glyph_id))
+        {
+            components
+                .iter()
+                .filter(|component| !kept.contains(&component.glyph_index))
+                .for_each(|component| pending.push(component.glyph_index));
+        }
+    }
+
+    kept.into_iter().collect()
+}
+
+fn remap_components(glyph: Glyph, glyph_map: &GlyphMap) -> Glyph {
+    match glyph {
+        Glyph::Compound {
+            glyph_id,
+            x_min,
+            y_min,
+            x_max,
+            y_max,
+            advance_width,
+            left_side_bearing,
+            advance_height,
+            top_side_bearing,
+            mut components,
+            instructions,
+        } => {
+            components.iter_mut().for_each(|component| {
+                component.glyph_index = glyph_map
+                    .get(component.glyph_index)
+                    .expect("component not in the glyph closure");
+            });
+            Glyph::Compound {
+                glyph_id,
+                x_min,
+                y_min,
+                x_max,
+                y_max,
+                advance_width,
+                left_side_bearing,
+                advance_height,
+                top_side_bearing,
+                components,
+                instructions,
+            }
+        }
+        glyph => glyph,
+    }
+}
+
+// Long metrics followed by side bearings: the trailing glyphs sharing the
+// advance of the last long metric only store their side bearing
+fn encode_metrics(metrics: &[(u16, i16)]) -> (Vec<u8>, u16) {
+    let mut number_of_long_metrics = metrics.len();
+    while number_of_long_metrics > 1
+        && metrics[number_of_long_metrics - 2].0 == metrics[number_of_long_metrics - 1].0
+    {
+        number_of_long_metrics -= 1;
+    }
+
+    let mut writer = Writer::new();
+    metrics
+        .iter()
+        .enumerate()
+        .for_each(|(index, (advance, side_bearing))| {
+            if index < number_of_long_metrics {
+                writer.write_u16(*advance);
+            }
+            writer.write_i16(*side_bearing);
+        });
+    (writer.into_vec(), number_of_long_metrics as u16)
+}
+
+fn union_bounds(glyphs: &[Glyph]) -> (i16, i16, i16, i16) {
+    glyphs
+        .iter()
+        .filter_map(|glyph| glyph.bounding_box())
+        .reduce(
+            |(x_min, y_min, x_max, y_max), (g_x_min, g_y_min, g_x_max, g_y_max)| {
+                (
+                    x_min.min(g_x_min),
+                    y_min.min(g_y_min),
+                    x_max.max(g_x_max),
+                    y_max.max(g_y_max),
+                )
+            },
+        )
+        .unwrap_or((0, 0, 0, 0))
+}
+
+fn update_head(mut head: Vec<u8>, glyphs: &[Glyph], index_to_loc_format: i16) -> Vec<u8> {
+    let (x_min, y_min, x_max, y_max) = union_bounds(glyphs);
+    write_u16(&mut head, 36, x_min as u16);
+    write_u16(&mut head, 38, y_min as u16);
+    write_u16(&mut head, 40, x_max as u16);
+    write_u16(&mut head, 42, y_max as u16);
+    write_u16(&mut head, 50, index_to_loc_format as u16);
+    head
+}
+
+// advanceWidthMax, minLeftSideBearing, minRightSideBearing, xMaxExtent and numberOfHMetrics
+fn update_horizontal_header(
+    mut hhea: Vec<u8>,
+    glyphs: &[Glyph],
+    number_of_h_metrics: u16,
+) -> Vec<u8> {
+    let extents: Vec<(i32, i32, i32)> = glyphs
+        .iter()
+        .filter_map(|glyph| {
+            glyph.bounding_box().map(|(x_min, _, x_max, _)| {
+                let lsb = glyph.left_side_bearing() as i32;
+                let extent = lsb + x_max as i32 - x_min as i32;
+                (lsb, glyph.advance_width() as i32 - extent, extent)
+            })
+        })
+        .collect();
+    let advance_max = glyphs.iter().map(|glyph| glyph.advance_width()).max();
+    update_metrics_header(&mut hhea, advance_max, &extents, number_of_h_metrics);
+    hhea
+}
+
+fn update_vertical_header(
+    mut vhea: Vec<u8>,
+    glyphs: &[Glyph],
+    number_of_v_metrics: u16,
+) -> Vec<u8> {
+    let extents: Vec<(i32, i32, i32)> = glyphs
+        .iter()
+        .filter_map(|glyph| {
+            glyph.bounding_box().map(|(_, y_min, _, y_max)| {
+                let tsb = glyph.top_side_bearing() as i32;
+                let extent = tsb + y_max as i32 - y_min as i32;
+                (tsb, glyph.advance_height() as i32 - extent, extent)
+            })
+        })
+        .collect();
+    let advance_max = glyphs.iter().map(|glyph| glyph.advance_height()).max();
+    update_metrics_header(&mut vhea, advance_max, &extents, number_of_v_metrics);
+    vhea
+}
+
+// hhea and vhea share their layout, extents are (side bearing, opposite side bearing, extent)
+fn update_metrics_header(
+    header: &mut [u8],
+    advance_max: Option<u16>,
+    extents: &[(i32, i32, i32)],
+    number_of_long_metrics: u16,
+) {
+    let clamp =
+        |value: Option<i32>| value.unwrap_or(0).clamp(i16::MIN as i32, i16::MAX as i32) as u16;
+    write_u16(header, 10, advance_max.unwrap_or(0));
+    write_u16(header, 12, clamp(extents.iter().map(|e| e.0).min()));
+    write_u16(header, 14, clamp(extents.iter().map(|e| e.1).min()));
+    write_u16(header, 16, clamp(extents.iter().map(|e| e.2).max()));
+    write_u16(header, 34, number_of_long_metrics);
+}
+
+// usFirstCharIndex and usLastCharIndex
+fn update_os2(mut os2: Vec<u8>, char_glyph_ids: &[(char, u16)]) -> Vec<u8> {
+    let char_codes = char_glyph_ids
+        .iter()
+        .map(|(c, _)| (*c as u32).min(0xFFFF) as u16);
+    write_u16(&mut os2, 64, char_codes.clone().min().unwrap_or(0));
+    write_u16(&mut os2, 66, char_codes.max().unwrap_or(0));
+    os2
+}
+
+// Format 2 glyph names are kept for the kept glyphs. The glyph order of format
+// 1 and 2.5 no longer holds, they become format 3, without names.
+fn subset_post(mut post: Vec<u8>, old_glyph_ids: &[u16]) -> Vec<u8> {
+    match read_u32(&post, 0) {
+        POST_FORMAT_2 => {
+            let num_glyphs = read_u16(&post, 32) as usize;
+            let name_indices: Vec<u16> = (0..num_glyphs)
+                .map(|index| read_u16(&post, 34 + index * 2))
+                .collect();
+            let mut names: Vec<&[u8]> = vec![];
+            let mut position = 34 + num_glyphs * 2;
+            while position < post.len() {
+                let length = post[position] as usize;
+                names.push(&post[position + 1..(position + 1 + length).min(post.len())]);
+                position += 1 + length;
+            }
+
+            let mut writer = Writer::new();
+            writer.write_bytes(&post[0..32]);
+            writer.write_u16(old_glyph_ids.len() as u16);
+            let mut kept_names: Vec<&[u8]> = vec![];
+            old_glyph_ids.iter().for_each(|glyph_id| {
+                let name_index = name_indices.get(*glyph_id as usize).copied().unwrap_or(0);
+                if name_index < STANDARD_MAC_GLYPH_NAMES {
+                    writer.write_u16(name_index);
+                } else {
+                    let name = names
+                        .get((name_index - STANDARD_MAC_GLYPH_NAMES) as usize)
+                        .copied()
+                        .unwrap_or(&[]);
+                    writer.write_u16(STANDARD_MAC_GLYPH_NAMES + kept_names.len() as u16);
+                    kept_names.push(name);
+                }
+            });
+            kept_names.iter().for_each(|name| {
+                writer.write_u8(name.len() as u8);
+                writer.write_bytes(name);
+            });
+            writer.into_vec()
+        }
+        POST_FORMAT_3 => post,
+        _ => {
+            post.truncate(32);
+            post[0..4].copy_from_slice(&POST_FORMAT_3.to_be_bytes());
+            post
+        }
+    }
+}
+
+// Rewritten as a format 0 name table, records using language tags are dropped too
+fn drop_names(name: &[u8]) -> Vec<u8> {
+    let count = read_u16(name, 2) as usize;
+    let string_offset = read_u16(name, 4) as usize;
+    let records: Vec<(&[u8], &[u8])> = (0..count)
+        .map(|index| 6 + index * 12)
+        .filter(|record| KEPT_NAME_IDS.contains(&read_u16(name, record + 6)))
+        .filter(|record| read_u16(name, record + 4) < 0x8000)
+        .map(|record| {
+            let length = read_u16(name, record + 8) as usize;
+            let offset = string_offset + read_u16(name, record + 10) as usize;
+            (&name[record..record + 8], &name[offset..offset + length])
+        })
+        .collect();
+
+    let mut writer = Writer::new();
+    writer.write_u16(0);
+    writer.write_u16(records.len() as u16);
+    writer.write_u16(6 + 12 * records.len() as u16);
+    let mut string_offset: u16 = 0;
+    records.iter().for_each(|(record, string)| {
+        writer.write_bytes(record);
+        writer.write_u16(string.len() as u16);
+        writer.write_u16(string_offset);
+        string_offset += string.len() as u16;
+    });
+    records
+        .iter()
+        .for_each(|(_, string)| writer.write_bytes(string));
+    writer.into_vec()
+}
+
+fn subset_vorg(vorg: &[u8], glyph_map: &GlyphMap) -> Vec<u8> {
+    let count = read_u16(vorg, 6) as usize;
+    let metrics: Vec<(u16, u16)> = (0..count)
+        .map(|index| 8 + index * 4)
+        .filter_map(|record| {
+            glyph_map
+                .get(read_u16(vorg, record))
+                .map(|glyph_id| (glyph_id, read_u16(vorg, record + 2)))
+        })
+        .collect();
+
+    let mut writer = Writer::new();
+    writer.write_bytes(&vorg[0..6]);
+    writer.write_u16(metrics.len() as u16);
+    metrics.iter().for_each(|(glyph_id, vert_origin_y)| {
+        writer.write_u16(*glyph_id);
+        writer.write_u16(*vert_origin_y);
+    });
+    writer.into_vec()
+}
+
+// The variation data of each glyph is self-contained, only the shared tuples are global
+fn subset_gvar(gvar: &[u8], old_glyph_ids: &[u16]) -> Vec<u8> {
+    let axis_count = read_u16(gvar, 4) as usize;
+    let shared_tuple_count = read_u16(gvar, 6) as usize;
+    let shared_tuples_offset = read_u32(gvar, 8) as usize;
+    let flags = read_u16(gvar, 14);
+    let data_offset = read_u32(gvar, 16) as usize;
+    let long_offsets = flags & 1 != 0;
+
+    let glyph_offset = |glyph_id: usize| {
+        if long_offsets {
+            read_u32(gvar, 20 + glyph_id * 4) as usize
+        } else {
+            read_u16(gvar, 20 + glyph_id * 2) as usize * 2
+        }
+    };
+
+    let mut data = Writer::new();
+    let mut offsets: Vec<usize> = vec![0];
+    old_glyph_ids.iter().for_each(|glyph_id| {
+        let start = data_offset + glyph_offset(*glyph_id as usize);
+        let end = data_offset + glyph_offset(*glyph_id as usize + 1);
+        data.write_bytes(&gvar[start..end]);
+        data.align(2);
+        offsets.push(data.offset());
+    });
+    let long_offsets = data.offset() / 2 > u16::MAX as usize;
+
+    let offsets_size = offsets.len() * if long_offsets { 4 } else { 2 };
+    let shared_tuples =
+        &gvar[shared_tuples_offset..shared_tuples_offset + shared_tuple_count * axis_count * 2];
+
+    let mut writer = Writer::new();
+    writer.write_bytes(&gvar[0..8]);
+    writer.write_u32((20 + offsets_size) as u32);
+    writer.write_u16(old_glyph_ids.len() as u16);
+    writer.write_u16(if long_offsets { flags | 1 } else { flags & !1 });
+    writer.write_u32((20 + offsets_size + shared_tuples.len()) as u32);
+    offsets.iter().for_each(|offset| {
+        if long_offsets {
+            writer.write_u32(*offset as u32);
+        } else {
+            writer.write_u16((*offset / 2) as u16);
+        }
+    });
+    writer.write_bytes(shared_tuples);
+    writer.write_bytes(&data.into_vec());
+    writer.into_vec()
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn write_u16(data: &mut [u8], offset: usize, value: u16) {
+    if offset + 2 <= data.len() {
+        data[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+    }
+}
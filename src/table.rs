@@ -1,7 +1,35 @@
+pub mod avar_table;
+pub mod cff2_table;
+pub mod cff_table;
 pub mod cmap_table;
+pub mod colr_table;
+pub mod cpal_table;
+pub mod cvar_table;
+pub mod cvt_table;
+pub mod ebdt_table;
+pub mod eblc_table;
+pub mod fpgm_table;
+pub mod fvar_table;
+pub mod gasp_table;
+pub mod gvar_table;
+pub mod hdmx_table;
 pub mod head_table;
 pub mod hhea_table;
 pub mod htmx_table;
+pub mod hvar_table;
 pub mod loca_table;
+pub mod ltsh_table;
 pub mod maxp_table;
+pub mod mvar_table;
 pub mod name_table;
+pub mod os2_table;
+pub mod post_table;
+pub mod prep_table;
+pub mod sbix_table;
+pub mod stat_table;
+pub mod svg_table;
+pub mod vdmx_table;
+pub mod vhea_table;
+pub mod vmtx_table;
+pub mod vorg_table;
+pub mod vvar_table;
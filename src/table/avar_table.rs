@@ -0,0 +1,77 @@
+use crate::reader::Reader;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AxisValueMap {
+    pub from_coordinate: f32,
+    pub to_coordinate: f32,
+}
+
+// Axis Variations table, piecewise linear maps refining the default
+// normalization of each axis
+#[allow(unused)]
+#[derive(Debug)]
+pub struct AvarTable {
+    major_version: u16,
+    minor_version: u16,
+    pub segment_maps: Vec<Vec<AxisValueMap>>, // one segment map per fvar axis
+}
+
+impl AvarTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, offset: u32) -> AvarTable {
+        reader.seek_from_start(offset);
+        let major_version = reader.read_u16();
+        let minor_version = reader.read_u16();
+        let _reserved = reader.read_u16();
+        let axis_count = reader.read_u16();
+
+        let segment_maps: Vec<Vec<AxisValueMap>> = (0..axis_count)
+            .map(|_| {
+                let position_map_count = reader.read_u16();
+                (0..position_map_count)
+                    .map(|_| AxisValueMap {
+                        from_coordinate: reader.read_f2dot14(),
+                        to_coordinate: reader.read_f2dot14(),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        AvarTable {
+            major_version,
+            minor_version,
+            segment_maps,
+        }
+    }
+
+    pub fn map(&self, axis_index: usize, normalized: f32) -> f32 {
+        match self.segment_maps.get(axis_index) {
+            Some(segment_map) => Self::map_segment(segment_map, normalized),
+            None => normalized,
+        }
+    }
+
+    fn map_segment(segment_map: &[AxisValueMap], normalized: f32) -> f32 {
+        // A valid map has at least the -1, 0 and 1 entries, anything shorter is ignored
+        if segment_map.len() < 3 {
+            return normalized;
+        }
+
+        match segment_map
+            .iter()
+            .position(|map| map.from_coordinate >= normalized)
+        {
+            None => normalized,
+            Some(index) => {
+                let upper = &segment_map[index];
+                if upper.from_coordinate == normalized || index == 0 {
+                    return upper.to_coordinate;
+                }
+                let lower = &segment_map[index - 1];
+                lower.to_coordinate
+                    + (upper.to_coordinate - lower.to_coordinate)
+                        * (normalized - lower.from_coordinate)
+                        / (upper.from_coordinate - lower.from_coordinate)
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use crate::charstring::{execute_charstring, Blend, CharstringError};
+use crate::font_directory::TableDirectory;
+use crate::item_variation_store::ItemVariationStore;
+use crate::model::{Contour, GlyphId};
+use crate::reader::Reader;
+use crate::table::cff_table::{dict_int, parse_dict, read_cff2_index, read_fd_select};
+
+// Top DICT, Font DICT and Private DICT operators
+const CHAR_STRINGS: u16 = 17;
+const PRIVATE: u16 = 18;
+const SUBRS: u16 = 19;
+const VSINDEX: u16 = 22;
+const VARIATION_STORE: u16 = 24;
+const FD_ARRAY: u16 = 1236;
+const FD_SELECT: u16 = 1237;
+
+// CFF2 table, PostScript outlines whose charstrings blend deltas from an item
+// variation store in variable fonts. Glyph widths come from hmtx and HVAR.
+#[allow(unused)]
+#[derive(Debug)]
+pub struct Cff2Table {
+    major_version: u8,
+    minor_version: u8,
+    char_strings: Vec<Vec<u8>>,
+    global_subrs: Vec<Vec<u8>>,
+    private_dicts: Vec<PrivateDict>, // one per Font DICT
+    fd_select: Vec<u8>,              // Font DICT of each glyph, empty with a single Font DICT
+    item_variation_store: Option<ItemVariationStore>,
+}
+
+#[derive(Debug)]
+struct PrivateDict {
+    local_subrs: Vec<Vec<u8>>,
+    vsindex: usize, // ItemVariationData of charstrings without vsindex
+}
+
+impl Cff2Table {
+    pub fn from_file(reader: &mut Box<dyn Reader>, table: &TableDirectory) -> Cff2Table {
+        reader.seek_from_start(table.offset);
+        let data = reader.read_bytes(table.length as usize);
+
+        let major_version = data[0];
+        let minor_version = data[1];
+        let header_size = data[2] as usize;
+        let top_dict_length = u16::from_be_bytes([data[3], data[4]]) as usize;
+
+        // The Top DICT is not in an INDEX, the Global Subr INDEX follows it
+        let top_dict = parse_dict(&data[header_size..header_size + top_dict_length]);
+        let (global_subrs, _) = read_cff2_index(&data, header_size + top_dict_length);
+
+        let char_strings_offset = dict_int(&top_dict, CHAR_STRINGS)
+            .unwrap_or_else(|| panic!("CFF2 table without CharStrings"));
+        let (char_strings, _) = read_cff2_index(&data, char_strings_offset);
+
+        let fd_array_offset =
+            dict_int(&top_dict, FD_ARRAY).unwrap_or_else(|| panic!("CFF2 table without FDArray"));
+        let (font_dicts, _) = read_cff2_index(&data, fd_array_offset);
+        let private_dicts = font_dicts
+            .iter()
+            .map(|font_dict| {
+                let font_dict = parse_dict(font_dict);
+                let (size, offset) =
+                    match font_dict.get(&PRIVATE).map(|operands| operands.as_slice()) {
+                        Some([size, offset]) => (*size as usize, *offset as usize),
+                        _ => {
+                            return PrivateDict {
+                                local_subrs: vec![],
+                                vsindex: 0,
+                            }
+                        }
+                    };
+                // Blended values are left unblended, only Subrs and vsindex are used
+                let private_dict = parse_dict(&data[offset..offset + size]);
+                PrivateDict {
+                    local_subrs: dict_int(&private_dict, SUBRS).map_or(vec![], |subrs_offset| {
+                        read_cff2_index(&data, offset + subrs_offset).0
+                    }),
+                    vsindex: dict_int(&private_dict, VSINDEX).unwrap_or(0),
+                }
+            })
+            .collect();
+
+        let fd_select = match dict_int(&top_dict, FD_SELECT) {
+            Some(fd_select_offset) => read_fd_select(&data, fd_select_offset, char_strings.len()),
+            None => vec![],
+        };
+
+        // The store is preceded by its length
+        let item_variation_store = dict_int(&top_dict, VARIATION_STORE).map(|store_offset| {
+            ItemVariationStore::from_file(reader, table.offset + store_offset as u32 + 2)
+        });
+
+        Cff2Table {
+            major_version,
+            minor_version,
+            char_strings,
+            global_subrs,
+            private_dicts,
+            fd_select,
+            item_variation_store,
+        }
+    }
+
+    // The outline of the glyph at the normalized variation `coordinates`
+    pub fn contours(
+        &self,
+        glyph_id: GlyphId,
+        coordinates: &[f32],
+    ) -> Result<Vec<Contour>, CharstringError> {
+        let charstring = self
+            .char_strings
+            .get(glyph_id.id() as usize)
+            .unwrap_or_else(|| panic!("{:?} not found in CharStrings", glyph_id));
+        let private_dict = match self.fd_select.get(glyph_id.id() as usize) {
+            Some(fd) => &self.private_dicts[*fd as usize],
+            None => &self.private_dicts[0],
+        };
+
+        let region_scalars = match &self.item_variation_store {
+            Some(item_variation_store) => {
+                let scalars = item_variation_store.region_scalars(coordinates);
+                item_variation_store
+                    .item_variation_data
+                    .iter()
+                    .map(|data| {
+                        data.iter()
+                            .flat_map(|data| data.region_indexes.iter())
+                            .map(|index| scalars.get(*index as usize).copied().unwrap_or(0.0))
+                            .collect()
+                    })
+                    .collect()
+            }
+            None => vec![],
+        };
+        let blend = Blend {
+            vsindex: private_dict.vsindex,
+            region_scalars,
+        };
+
+        execute_charstring(
+            charstring,
+            &self.global_subrs,
+            &private_dict.local_subrs,
+            Some(&blend),
+        )
+        .map(|outline| outline.to_contours(0.0, 0.0))
+    }
+}
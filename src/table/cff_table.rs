@@ -0,0 +1,462 @@
+use crate::charstring::{execute_charstring, CharstringError, CharstringOutline};
+use crate::font_directory::TableDirectory;
+use crate::model::{Contour, GlyphId};
+use crate::reader::Reader;
+
+use std::collections::HashMap;
+
+// Top DICT and Private DICT operators, two byte operators are 1200 + second byte
+const CHARSET: u16 = 15;
+const ENCODING: u16 = 16;
+const CHAR_STRINGS: u16 = 17;
+const PRIVATE: u16 = 18;
+const SUBRS: u16 = 19;
+const DEFAULT_WIDTH_X: u16 = 20;
+const NOMINAL_WIDTH_X: u16 = 21;
+const CHARSTRING_TYPE: u16 = 1206;
+const ROS: u16 = 1230;
+const FD_ARRAY: u16 = 1236;
+const FD_SELECT: u16 = 1237;
+
+// SIDs 1 to 95 are the printable ASCII codes 32 to 126, the remaining
+// characters of the Standard Encoding have the SIDs that follow
+const STANDARD_ENCODING_HIGH_CODES: [u8; 54] = [
+    161, 162, 163, 164, 165, 166, 167, 168, 169, 170, 171, 172, 173, 174, 175, 177, 178, 179, 180,
+    182, 183, 184, 185, 186, 187, 188, 189, 191, 193, 194, 195, 196, 197, 198, 199, 200, 202, 203,
+    205, 206, 207, 208, 225, 227, 232, 233, 234, 235, 241, 245, 248, 249, 250, 251,
+];
+
+// Compact Font Format table (Adobe Technical Note #5176), PostScript outlines
+// as Type 2 charstrings
+#[allow(unused)]
+#[derive(Debug)]
+pub struct CffTable {
+    major_version: u8,
+    minor_version: u8,
+    name: String,
+    strings: Vec<String>, // custom strings, SID 391 onwards
+    char_strings: Vec<Vec<u8>>,
+    global_subrs: Vec<Vec<u8>>,
+    private_dicts: Vec<PrivateDict>, // one per Font DICT in CID fonts
+    fd_select: Vec<u8>,              // Font DICT of each glyph, empty unless CID keyed
+    charset: Vec<u16>,               // SID of each glyph, CID in CID fonts
+    encoding: HashMap<u8, GlyphId>,  // character code to glyph
+}
+
+#[allow(unused)]
+#[derive(Debug)]
+struct PrivateDict {
+    local_subrs: Vec<Vec<u8>>,
+    default_width_x: f64, // width of glyphs whose charstring has none
+    nominal_width_x: f64, // added to the charstring widths
+}
+
+impl CffTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, table: &TableDirectory) -> CffTable {
+        reader.seek_from_start(table.offset);
+        let data = reader.read_bytes(table.length as usize);
+
+        let major_version = data[0];
+        let minor_version = data[1];
+        let header_size = data[2] as usize;
+
+        let (names, end) = read_index(&data, header_size);
+        let (top_dicts, end) = read_index(&data, end);
+        let (strings, end) = read_index(&data, end);
+        let (global_subrs, _) = read_index(&data, end);
+
+        let name = String::from_utf8_lossy(names.first().map_or(&[][..], |name| name)).to_string();
+        let strings: Vec<String> = strings
+            .iter()
+            .map(|string| String::from_utf8_lossy(string).to_string())
+            .collect();
+        let top_dict = parse_dict(
+            top_dicts
+                .first()
+                .unwrap_or_else(|| panic!("CFF table without Top DICT")),
+        );
+
+        let charstring_type = dict_int(&top_dict, CHARSTRING_TYPE).unwrap_or(2);
+        if charstring_type != 2 {
+            panic!("Unsupported charstring type {}", charstring_type);
+        }
+
+        let char_strings_offset = dict_int(&top_dict, CHAR_STRINGS)
+            .unwrap_or_else(|| panic!("CFF table without CharStrings"));
+        let (char_strings, _) = read_index(&data, char_strings_offset);
+        let glyph_count = char_strings.len();
+
+        let charset = read_charset(
+            &data,
+            dict_int(&top_dict, CHARSET).unwrap_or(0),
+            glyph_count,
+        );
+
+        let (private_dicts, fd_select) = if top_dict.contains_key(&ROS) {
+            let fd_array_offset = dict_int(&top_dict, FD_ARRAY)
+                .unwrap_or_else(|| panic!("CID keyed CFF table without FDArray"));
+            let fd_select_offset = dict_int(&top_dict, FD_SELECT)
+                .unwrap_or_else(|| panic!("CID keyed CFF table without FDSelect"));
+            let (font_dicts, _) = read_index(&data, fd_array_offset);
+            let private_dicts = font_dicts
+                .iter()
+                .map(|font_dict| read_private_dict(&data, &parse_dict(font_dict)))
+                .collect();
+            (
+                private_dicts,
+                read_fd_select(&data, fd_select_offset, glyph_count),
+            )
+        } else {
+            (vec![read_private_dict(&data, &top_dict)], vec![])
+        };
+
+        // CID fonts have no encoding
+        let encoding = if fd_select.is_empty() {
+            read_encoding(&data, dict_int(&top_dict, ENCODING).unwrap_or(0), &charset)
+        } else {
+            HashMap::new()
+        };
+
+        CffTable {
+            major_version,
+            minor_version,
+            name,
+            strings,
+            char_strings,
+            global_subrs,
+            private_dicts,
+            fd_select,
+            charset,
+            encoding,
+        }
+    }
+
+    // The outline of the glyph in font units, the components of accented
+    // characters built with seac are merged
+    pub fn contours(&self, glyph_id: GlyphId) -> Result<Vec<Contour>, CharstringError> {
+        let outline = self.outline(glyph_id)?;
+        Ok(match outline.seac {
+            Some(seac) => {
+                let base = self.standard_encoding_glyph(seac.base_code)?;
+                let accent = self.standard_encoding_glyph(seac.accent_code)?;
+                let mut contours = self.outline(base)?.to_contours(0.0, 0.0);
+                contours.extend(self.outline(accent)?.to_contours(seac.adx, seac.ady));
+                contours
+            }
+            None => outline.to_contours(0.0, 0.0),
+        })
+    }
+
+    fn outline(&self, glyph_id: GlyphId) -> Result<CharstringOutline, CharstringError> {
+        let charstring = self
+            .char_strings
+            .get(glyph_id.id() as usize)
+            .unwrap_or_else(|| panic!("{:?} not found in CharStrings", glyph_id));
+        let private_dict = match self.fd_select.get(glyph_id.id() as usize) {
+            Some(fd) => &self.private_dicts[*fd as usize],
+            None => &self.private_dicts[0],
+        };
+        execute_charstring(
+            charstring,
+            &self.global_subrs,
+            &private_dict.local_subrs,
+            None,
+        )
+    }
+
+    // seac refers to its components by their Standard Encoding code
+    fn standard_encoding_glyph(&self, code: u8) -> Result<GlyphId, CharstringError> {
+        let sid = match code {
+            32..=126 => Some(code as u16 - 31),
+            _ => STANDARD_ENCODING_HIGH_CODES
+                .iter()
+                .position(|high_code| *high_code == code)
+                .map(|index| index as u16 + 96),
+        };
+        sid.and_then(|sid| self.charset.iter().position(|s| *s == sid))
+            .map(|glyph_id| GlyphId::new(glyph_id as u16))
+            .ok_or(CharstringError::InvalidSeac(code))
+    }
+}
+
+fn read_private_dict(data: &[u8], dict: &HashMap<u16, Vec<f64>>) -> PrivateDict {
+    let (size, offset) = match dict.get(&PRIVATE).map(|operands| operands.as_slice()) {
+        Some([size, offset]) => (*size as usize, *offset as usize),
+        _ => {
+            return PrivateDict {
+                local_subrs: vec![],
+                default_width_x: 0.0,
+                nominal_width_x: 0.0,
+            }
+        }
+    };
+    let private_dict = parse_dict(&data[offset..offset + size]);
+
+    // Subrs is relative to the start of the Private DICT
+    let local_subrs = match dict_int(&private_dict, SUBRS) {
+        Some(subrs_offset) => read_index(data, offset + subrs_offset).0,
+        None => vec![],
+    };
+
+    PrivateDict {
+        local_subrs,
+        default_width_x: dict_number(&private_dict, DEFAULT_WIDTH_X).unwrap_or(0.0),
+        nominal_width_x: dict_number(&private_dict, NOMINAL_WIDTH_X).unwrap_or(0.0),
+    }
+}
+
+// An INDEX and the offset following it
+pub fn read_index(data: &[u8], offset: usize) -> (Vec<Vec<u8>>, usize) {
+    read_index_with_count_size(data, offset, 2)
+}
+
+// CFF2 INDEX structures have a 32 bit count
+pub fn read_cff2_index(data: &[u8], offset: usize) -> (Vec<Vec<u8>>, usize) {
+    read_index_with_count_size(data, offset, 4)
+}
+
+fn read_index_with_count_size(
+    data: &[u8],
+    offset: usize,
+    count_size: usize,
+) -> (Vec<Vec<u8>>, usize) {
+    let count = read_offset(data, offset, count_size);
+    if count == 0 {
+        return (vec![], offset + count_size);
+    }
+    let offset = offset + count_size - 2;
+    let offset_size = data[offset + 2] as usize;
+    let offsets: Vec<usize> = (0..=count)
+        .map(|i| read_offset(data, offset + 3 + i * offset_size, offset_size))
+        .collect();
+    // Offsets count from 1, from the byte before the object data
+    let data_start = offset + 3 + (count + 1) * offset_size - 1;
+    let objects = offsets
+        .windows(2)
+        .map(|window| data[data_start + window[0]..data_start + window[1]].to_vec())
+        .collect();
+    (objects, data_start + offsets[count])
+}
+
+fn read_offset(data: &[u8], offset: usize, offset_size: usize) -> usize {
+    data[offset..offset + offset_size]
+        .iter()
+        .fold(0, |value, byte| (value << 8) | *byte as usize)
+}
+
+// Operands of each operator in a DICT, CFF2 adds the operators 22 to 24
+pub fn parse_dict(data: &[u8]) -> HashMap<u16, Vec<f64>> {
+    let mut dict = HashMap::new();
+    let mut operands = vec![];
+    let mut position = 0;
+    while position < data.len() {
+        let b0 = data[position];
+        position += 1;
+        match b0 {
+            0..=11 | 13..=27 => {
+                dict.insert(b0 as u16, std::mem::take(&mut operands));
+            }
+            12 => {
+                dict.insert(1200 + data[position] as u16, std::mem::take(&mut operands));
+                position += 1;
+            }
+            28 => {
+                operands.push(read_u16(data, position) as i16 as f64);
+                position += 2;
+            }
+            29 => {
+                operands.push(read_u32(data, position) as i32 as f64);
+                position += 4;
+            }
+            30 => {
+                let (value, length) = read_real(&data[position..]);
+                operands.push(value);
+                position += length;
+            }
+            32..=246 => operands.push(b0 as f64 - 139.0),
+            247..=250 => {
+                operands.push((b0 as f64 - 247.0) * 256.0 + data[position] as f64 + 108.0);
+                position += 1;
+            }
+            251..=254 => {
+                operands.push(-(b0 as f64 - 251.0) * 256.0 - data[position] as f64 - 108.0);
+                position += 1;
+            }
+            _ => panic!("Invalid DICT byte {}", b0),
+        }
+    }
+    dict
+}
+
+// A real number in packed decimal nibbles and its length in bytes
+fn read_real(data: &[u8]) -> (f64, usize) {
+    let mut text = String::new();
+    for (i, byte) in data.iter().enumerate() {
+        for nibble in [byte >> 4, byte & 0xF] {
+            match nibble {
+                0..=9 => text.push((b'0' + nibble) as char),
+                0xA => text.push('.'),
+                0xB => text.push('E'),
+                0xC => text.push_str("E-"),
+                0xE => text.push('-'),
+                0xF => return (text.parse().unwrap_or(0.0), i + 1),
+                _ => {}
+            }
+        }
+    }
+    panic!("Unterminated real number in DICT")
+}
+
+pub fn dict_number(dict: &HashMap<u16, Vec<f64>>, operator: u16) -> Option<f64> {
+    dict.get(&operator)
+        .and_then(|operands| operands.first().copied())
+}
+
+pub fn dict_int(dict: &HashMap<u16, Vec<f64>>, operator: u16) -> Option<usize> {
+    dict_number(dict, operator).map(|value| value as usize)
+}
+
+// SID of each glyph, starting with .notdef
+fn read_charset(data: &[u8], offset: usize, glyph_count: usize) -> Vec<u16> {
+    match offset {
+        // ISOAdobe, the SID of each glyph is its glyph id
+        0 => (0..glyph_count as u16).collect(),
+        // The Expert charsets are not supported, seac cannot refer to them
+        1 | 2 => vec![0],
+        _ => {
+            let mut charset = vec![0];
+            let format = data[offset];
+            let mut position = offset + 1;
+            while charset.len() < glyph_count {
+                match format {
+                    0 => {
+                        charset.push(read_u16(data, position));
+                        position += 2;
+                    }
+                    1 | 2 => {
+                        let first = read_u16(data, position);
+                        let left = if format == 1 {
+                            position += 3;
+                            data[position - 1] as u16
+                        } else {
+                            position += 4;
+                            read_u16(data, position - 2)
+                        };
+                        charset.extend((0..=left).map(|i| first + i));
+                    }
+                    _ => panic!("Invalid charset format {}", format),
+                }
+            }
+            charset.truncate(glyph_count);
+            charset
+        }
+    }
+}
+
+fn read_encoding(data: &[u8], offset: usize, charset: &[u16]) -> HashMap<u8, GlyphId> {
+    let glyph_for_sid = |sid: u16| {
+        charset
+            .iter()
+            .position(|s| *s == sid)
+            .map(|glyph_id| GlyphId::new(glyph_id as u16))
+    };
+    let mut encoding = HashMap::new();
+    match offset {
+        0 => {
+            // Standard Encoding
+            let codes = (32..=126).chain(STANDARD_ENCODING_HIGH_CODES);
+            for (sid, code) in (1..).zip(codes) {
+                if let Some(glyph_id) = glyph_for_sid(sid) {
+                    encoding.insert(code, glyph_id);
+                }
+            }
+        }
+        // The Expert Encoding is not supported
+        1 => {}
+        _ => {
+            let format = data[offset];
+            let mut position = offset + 2;
+            let mut glyph_id = 1;
+            match format & 0x7F {
+                0 => {
+                    for _ in 0..data[offset + 1] {
+                        encoding.insert(data[position], GlyphId::new(glyph_id));
+                        glyph_id += 1;
+                        position += 1;
+                    }
+                }
+                1 => {
+                    for _ in 0..data[offset + 1] {
+                        let first = data[position];
+                        let left = data[position + 1];
+                        for code in first..=first.saturating_add(left) {
+                            encoding.insert(code, GlyphId::new(glyph_id));
+                            glyph_id += 1;
+                        }
+                        position += 2;
+                    }
+                }
+                _ => panic!("Invalid encoding format {}", format & 0x7F),
+            }
+            // Supplements give further codes to glyphs already encoded
+            if format & 0x80 != 0 {
+                let supplement_count = data[position];
+                position += 1;
+                for _ in 0..supplement_count {
+                    let code = data[position];
+                    if let Some(glyph_id) = glyph_for_sid(read_u16(data, position + 1)) {
+                        encoding.insert(code, glyph_id);
+                    }
+                    position += 3;
+                }
+            }
+        }
+    }
+    encoding
+}
+
+// Font DICT index of each glyph, format 4 is only found in CFF2
+pub fn read_fd_select(data: &[u8], offset: usize, glyph_count: usize) -> Vec<u8> {
+    match data[offset] {
+        0 => data[offset + 1..offset + 1 + glyph_count].to_vec(),
+        3 => {
+            let range_count = read_u16(data, offset + 1) as usize;
+            let mut fd_select = vec![0; glyph_count];
+            for i in 0..range_count {
+                let position = offset + 3 + i * 3;
+                let first = read_u16(data, position) as usize;
+                let fd = data[position + 2];
+                // The sentinel follows the last range
+                let end = read_u16(data, position + 3) as usize;
+                fd_select[first.min(glyph_count)..end.min(glyph_count)].fill(fd);
+            }
+            fd_select
+        }
+        4 => {
+            let range_count = read_u32(data, offset + 1) as usize;
+            let mut fd_select = vec![0; glyph_count];
+            for i in 0..range_count {
+                let position = offset + 5 + i * 6;
+                let first = read_u32(data, position) as usize;
+                let fd = read_u16(data, position + 4) as u8;
+                let end = read_u32(data, position + 6) as usize;
+                fd_select[first.min(glyph_count)..end.min(glyph_count)].fill(fd);
+            }
+            fd_select
+        }
+        format => panic!("Invalid FDSelect format {}", format),
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
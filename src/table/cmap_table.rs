@@ -1,3 +1,4 @@
+use crate::font_builder::binary_search_header;
 use crate::font_directory::TableDirectory;
 use crate::glyph_index_lookup::GlyphIndexLookup;
 use crate::model::{GlyphId, PlatformId};
@@ -20,7 +21,7 @@ impl CMapSubtable {
         let _version = reader.read_u16();
         let number_subtables = reader.read_u16();
 
-        let subtables = (0..number_subtables).into_iter().map(|_| {
+        let subtables = (0..number_subtables).map(|_| {
             let platform_id = reader.read_platform_id();
             let platform_specific_id = reader.read_u16();
             let offset = reader.read_u32();
@@ -67,9 +68,12 @@ impl CMapSubtable {
         let _version = reader.read_u16();
 
         let seg_count_x2 = reader.read_u16(); // The segCount is the number of contiguous code ranges in the font
-        let search_range = reader.read_u16(); // TODO compute this from seg_count_x2
-        let entry_selector = reader.read_u16(); // TODO compute this from seg_count_x2
-        let _range_shift = reader.read_u16(); // Do not use
+
+        // Computed rather than read, some fonts store wrong values
+        let _search_range = reader.read_u16();
+        let _entry_selector = reader.read_u16();
+        let _range_shift = reader.read_u16();
+        let (search_range, entry_selector, _) = binary_search_header(seg_count_x2 / 2, 2);
 
         let mut index_lookup = GlyphIndexLookup::new(reader, seg_count_x2, char_code);
 
@@ -77,10 +81,7 @@ impl CMapSubtable {
     }
 
     fn read_array(&self, reader: &mut Box<dyn Reader>, seg_count: u16) -> Vec<u16> {
-        (0..seg_count)
-            .into_iter()
-            .map(|_| reader.read_u16())
-            .collect()
+        (0..seg_count).map(|_| reader.read_u16()).collect()
     }
 
     fn read_whole_subtable(&self, reader: &mut Box<dyn Reader>, seg_count_x2: u16) -> Vec<Segment> {
@@ -142,4 +143,12 @@ impl Segment {
             id_range_offset,
         }
     }
+
+    pub fn id_delta(&self) -> u16 {
+        self.id_delta
+    }
+
+    pub fn id_range_offset(&self) -> u16 {
+        self.id_range_offset
+    }
 }
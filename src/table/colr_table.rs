@@ -0,0 +1,652 @@
+use crate::font_directory::TableDirectory;
+use crate::model::GlyphId;
+use crate::reader::Reader;
+
+// Limits for walking the paint graph of hostile fonts
+const MAX_PAINT_DEPTH: usize = 64;
+const MAX_PAINT_COUNT: usize = 100_000;
+
+#[derive(Debug)]
+pub struct BaseGlyphRecord {
+    pub glyph_id: GlyphId,
+    pub first_layer_index: u16,
+    pub num_layers: u16,
+}
+
+#[derive(Debug)]
+pub struct LayerRecord {
+    pub glyph_id: GlyphId,
+    pub palette_index: u16, // CPAL entry, 0xFFFF for the text foreground color
+}
+
+impl LayerRecord {
+    pub const FOREGROUND_COLOR: u16 = 0xFFFF;
+}
+
+// A Paint table, by its offset from the start of COLR
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PaintId(u32);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Extend {
+    Pad,
+    Repeat,
+    Reflect,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorStop {
+    pub stop_offset: f32,
+    pub palette_index: u16, // CPAL entry, 0xFFFF for the text foreground color
+    pub alpha: f32,
+    pub var_index_base: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorLine {
+    pub extend: Extend,
+    pub color_stops: Vec<ColorStop>,
+}
+
+// x' = xx * x + xy * y + dx, y' = yx * x + yy * y + dy
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Affine {
+    pub xx: f32,
+    pub yx: f32,
+    pub xy: f32,
+    pub yy: f32,
+    pub dx: f32,
+    pub dy: f32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CompositeMode {
+    Clear,
+    Src,
+    Dest,
+    SrcOver,
+    DestOver,
+    SrcIn,
+    DestIn,
+    SrcOut,
+    DestOut,
+    SrcAtop,
+    DestAtop,
+    Xor,
+    Plus,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    Multiply,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity,
+}
+
+// The Paint tables of COLR version 1. The variable formats carry the base of
+// their variation indices, angles are in degrees counterclockwise. The uniform
+// and around center formats of scale, rotate and skew share a variant.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Paint {
+    ColrLayers {
+        first_layer_index: u32,
+        num_layers: u8,
+    },
+    Solid {
+        palette_index: u16,
+        alpha: f32,
+        var_index_base: Option<u32>,
+    },
+    LinearGradient {
+        color_line: ColorLine,
+        p0: (i16, i16),
+        p1: (i16, i16),
+        p2: (i16, i16), // rotation point
+        var_index_base: Option<u32>,
+    },
+    RadialGradient {
+        color_line: ColorLine,
+        center0: (i16, i16),
+        radius0: u16,
+        center1: (i16, i16),
+        radius1: u16,
+        var_index_base: Option<u32>,
+    },
+    SweepGradient {
+        color_line: ColorLine,
+        center: (i16, i16),
+        start_angle: f32,
+        end_angle: f32,
+        var_index_base: Option<u32>,
+    },
+    // Fills the outline of `glyph_id` with `paint`
+    Glyph {
+        paint: PaintId,
+        glyph_id: GlyphId,
+    },
+    ColrGlyph {
+        glyph_id: GlyphId,
+    },
+    Transform {
+        paint: PaintId,
+        transform: Affine,
+        var_index_base: Option<u32>,
+    },
+    Translate {
+        paint: PaintId,
+        dx: i16,
+        dy: i16,
+        var_index_base: Option<u32>,
+    },
+    Scale {
+        paint: PaintId,
+        scale_x: f32,
+        scale_y: f32,
+        center: Option<(i16, i16)>,
+        var_index_base: Option<u32>,
+    },
+    Rotate {
+        paint: PaintId,
+        angle: f32,
+        center: Option<(i16, i16)>,
+        var_index_base: Option<u32>,
+    },
+    Skew {
+        paint: PaintId,
+        x_skew_angle: f32,
+        y_skew_angle: f32,
+        center: Option<(i16, i16)>,
+        var_index_base: Option<u32>,
+    },
+    Composite {
+        source: PaintId,
+        mode: CompositeMode,
+        backdrop: PaintId,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ClipBox {
+    pub x_min: i16,
+    pub y_min: i16,
+    pub x_max: i16,
+    pub y_max: i16,
+    pub var_index_base: Option<u32>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PaintError {
+    Cycle(PaintId),     // the paint is reached again from within itself
+    DepthLimitExceeded, // more than MAX_PAINT_DEPTH nested paints
+    PaintCountExceeded, // more than MAX_PAINT_COUNT paints visited
+    Malformed(PaintId), // unknown format or data past the end of the table
+}
+
+// Walks the paint graph of a color glyph depth first. The children of a paint
+// are visited between `enter` and `leave`: the layers of ColrLayers in order,
+// the base paint of the glyph of ColrGlyph, the backdrop of Composite before
+// its source, and the single child of the other paints.
+pub trait PaintVisitor {
+    // Returning false skips the children of `paint`
+    fn enter(&mut self, paint: &Paint, depth: usize) -> bool;
+
+    fn leave(&mut self, _paint: &Paint, _depth: usize) {}
+}
+
+// Color table, layers of glyphs drawn in palette colors, and the paint graphs
+// of version 1
+#[allow(unused)]
+#[derive(Debug)]
+pub struct ColrTable {
+    pub version: u16,
+    base_glyph_records: Vec<BaseGlyphRecord>, // sorted by glyph id
+    layer_records: Vec<LayerRecord>,
+    base_glyph_paints: Vec<(GlyphId, PaintId)>, // version 1, sorted by glyph id
+    layer_paints: Vec<PaintId>,                 // version 1
+    clips: Vec<(GlyphId, GlyphId, ClipBox)>,    // first and last glyph of each clip box
+    data: Vec<u8>,                              // version 1, the Paint tables are read on demand
+}
+
+impl ColrTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, table: &TableDirectory) -> ColrTable {
+        let offset = table.offset;
+        reader.seek_from_start(offset);
+        let version = reader.read_u16();
+        let num_base_glyph_records = reader.read_u16();
+        let base_glyph_records_offset = reader.read_u32();
+        let layer_records_offset = reader.read_u32();
+        let num_layer_records = reader.read_u16();
+        let (base_glyph_list_offset, layer_list_offset, clip_list_offset) = if version >= 1 {
+            (reader.read_u32(), reader.read_u32(), reader.read_u32())
+        } else {
+            (0, 0, 0)
+        };
+
+        reader.seek_from_start(offset + base_glyph_records_offset);
+        let base_glyph_records = (0..num_base_glyph_records)
+            .map(|_| BaseGlyphRecord {
+                glyph_id: GlyphId::new(reader.read_u16()),
+                first_layer_index: reader.read_u16(),
+                num_layers: reader.read_u16(),
+            })
+            .collect();
+
+        reader.seek_from_start(offset + layer_records_offset);
+        let layer_records = (0..num_layer_records)
+            .map(|_| LayerRecord {
+                glyph_id: GlyphId::new(reader.read_u16()),
+                palette_index: reader.read_u16(),
+            })
+            .collect();
+
+        let data = if version >= 1 {
+            reader.seek_from_start(offset);
+            reader.read_bytes(table.length as usize)
+        } else {
+            vec![]
+        };
+        let base_glyph_paints = read_base_glyph_paints(&data, base_glyph_list_offset as usize);
+        let layer_paints = read_layer_paints(&data, layer_list_offset as usize);
+        let clips = read_clips(&data, clip_list_offset as usize);
+
+        ColrTable {
+            version,
+            base_glyph_records,
+            layer_records,
+            base_glyph_paints,
+            layer_paints,
+            clips,
+            data,
+        }
+    }
+
+    // The layers of a base glyph, bottom first, empty for glyphs without color
+    pub fn layers(&self, glyph_id: GlyphId) -> &[LayerRecord] {
+        match self
+            .base_glyph_records
+            .binary_search_by_key(&glyph_id.id(), |record| record.glyph_id.id())
+        {
+            Ok(index) => {
+                let record = &self.base_glyph_records[index];
+                let first = record.first_layer_index as usize;
+                let last = first + record.num_layers as usize;
+                self.layer_records.get(first..last).unwrap_or(&[])
+            }
+            Err(_) => &[],
+        }
+    }
+}
+
+impl ColrTable {
+    // The root of the version 1 paint graph of a glyph
+    pub fn base_paint(&self, glyph_id: GlyphId) -> Option<PaintId> {
+        self.base_glyph_paints
+            .binary_search_by_key(&glyph_id.id(), |(glyph_id, _)| glyph_id.id())
+            .ok()
+            .map(|index| self.base_glyph_paints[index].1)
+    }
+
+    pub fn clip_box(&self, glyph_id: GlyphId) -> Option<ClipBox> {
+        self.clips
+            .iter()
+            .find(|(first, last, _)| (first.id()..=last.id()).contains(&glyph_id.id()))
+            .map(|(_, _, clip_box)| *clip_box)
+    }
+
+    // None for unknown formats and paints running past the end of the table
+    pub fn paint(&self, paint_id: PaintId) -> Option<Paint> {
+        let data = &self.data;
+        let start = paint_id.0 as usize;
+        let child = |position: usize| -> Option<PaintId> {
+            read_u24(data, start + position).map(|offset| PaintId(paint_id.0 + offset))
+        };
+        let point = |position: usize| -> Option<(i16, i16)> {
+            Some((
+                read_i16(data, start + position)?,
+                read_i16(data, start + position + 2)?,
+            ))
+        };
+        let f2dot14 = |position: usize| read_f2dot14(data, start + position);
+        let angle = |position: usize| f2dot14(position).map(|angle| angle * 180.0);
+        let var_index_base = |variable: bool, position: usize| -> Option<Option<u32>> {
+            if variable {
+                read_u32(data, start + position).map(Some)
+            } else {
+                Some(None)
+            }
+        };
+
+        let format = *data.get(start)?;
+        let variable = format % 2 == 1;
+        let paint = match format {
+            1 => Paint::ColrLayers {
+                num_layers: *data.get(start + 1)?,
+                first_layer_index: read_u32(data, start + 2)?,
+            },
+            2 | 3 => Paint::Solid {
+                palette_index: read_u16(data, start + 1)?,
+                alpha: f2dot14(3)?,
+                var_index_base: var_index_base(format == 3, 5)?,
+            },
+            4 | 5 => Paint::LinearGradient {
+                color_line: self.color_line(child(1)?.0 as usize, format == 5)?,
+                p0: point(4)?,
+                p1: point(8)?,
+                p2: point(12)?,
+                var_index_base: var_index_base(format == 5, 16)?,
+            },
+            6 | 7 => Paint::RadialGradient {
+                color_line: self.color_line(child(1)?.0 as usize, format == 7)?,
+                center0: point(4)?,
+                radius0: read_u16(data, start + 8)?,
+                center1: point(10)?,
+                radius1: read_u16(data, start + 14)?,
+                var_index_base: var_index_base(format == 7, 16)?,
+            },
+            8 | 9 => Paint::SweepGradient {
+                color_line: self.color_line(child(1)?.0 as usize, format == 9)?,
+                center: point(4)?,
+                start_angle: angle(8)?,
+                end_angle: angle(10)?,
+                var_index_base: var_index_base(format == 9, 12)?,
+            },
+            10 => Paint::Glyph {
+                paint: child(1)?,
+                glyph_id: GlyphId::new(read_u16(data, start + 4)?),
+            },
+            11 => Paint::ColrGlyph {
+                glyph_id: GlyphId::new(read_u16(data, start + 1)?),
+            },
+            12 | 13 => {
+                let transform = child(4)?.0 as usize;
+                let fixed = |index: usize| {
+                    read_u32(data, transform + index * 4).map(|value| value as i32 as f32 / 65536.0)
+                };
+                Paint::Transform {
+                    paint: child(1)?,
+                    transform: Affine {
+                        xx: fixed(0)?,
+                        yx: fixed(1)?,
+                        xy: fixed(2)?,
+                        yy: fixed(3)?,
+                        dx: fixed(4)?,
+                        dy: fixed(5)?,
+                    },
+                    var_index_base: if format == 13 {
+                        Some(read_u32(data, transform + 24)?)
+                    } else {
+                        None
+                    },
+                }
+            }
+            14 | 15 => Paint::Translate {
+                paint: child(1)?,
+                dx: read_i16(data, start + 4)?,
+                dy: read_i16(data, start + 6)?,
+                var_index_base: var_index_base(variable, 8)?,
+            },
+            16..=23 => {
+                // Scale, ScaleAroundCenter, ScaleUniform and ScaleUniformAroundCenter
+                let uniform = format >= 20;
+                let around_center = matches!(format, 18 | 19 | 22 | 23);
+                let scale_x = f2dot14(4)?;
+                let scale_y = if uniform { scale_x } else { f2dot14(6)? };
+                let center_position = if uniform { 6 } else { 8 };
+                let center = if around_center {
+                    Some(point(center_position)?)
+                } else {
+                    None
+                };
+                let var_position = center_position + if around_center { 4 } else { 0 };
+                Paint::Scale {
+                    paint: child(1)?,
+                    scale_x,
+                    scale_y,
+                    center,
+                    var_index_base: var_index_base(variable, var_position)?,
+                }
+            }
+            24..=27 => {
+                let center = if format >= 26 { Some(point(6)?) } else { None };
+                Paint::Rotate {
+                    paint: child(1)?,
+                    angle: angle(4)?,
+                    center,
+                    var_index_base: var_index_base(
+                        variable,
+                        if center.is_some() { 10 } else { 6 },
+                    )?,
+                }
+            }
+            28..=31 => {
+                let center = if format >= 30 { Some(point(8)?) } else { None };
+                Paint::Skew {
+                    paint: child(1)?,
+                    x_skew_angle: angle(4)?,
+                    y_skew_angle: angle(6)?,
+                    center,
+                    var_index_base: var_index_base(
+                        variable,
+                        if center.is_some() { 12 } else { 8 },
+                    )?,
+                }
+            }
+            32 => Paint::Composite {
+                source: child(1)?,
+                mode: composite_mode(*data.get(start + 4)?)?,
+                backdrop: child(5)?,
+            },
+            _ => return None,
+        };
+        Some(paint)
+    }
+
+    fn color_line(&self, offset: usize, variable: bool) -> Option<ColorLine> {
+        let data = &self.data;
+        let extend = match data.get(offset)? {
+            1 => Extend::Repeat,
+            2 => Extend::Reflect,
+            _ => Extend::Pad,
+        };
+        let stop_size = if variable { 10 } else { 6 };
+        let color_stops = (0..read_u16(data, offset + 1)? as usize)
+            .map(|index| {
+                let position = offset + 3 + index * stop_size;
+                Some(ColorStop {
+                    stop_offset: read_f2dot14(data, position)?,
+                    palette_index: read_u16(data, position + 2)?,
+                    alpha: read_f2dot14(data, position + 4)?,
+                    var_index_base: if variable {
+                        Some(read_u32(data, position + 6)?)
+                    } else {
+                        None
+                    },
+                })
+            })
+            .collect::<Option<Vec<ColorStop>>>()?;
+        Some(ColorLine {
+            extend,
+            color_stops,
+        })
+    }
+
+    // Walks the paint graph of `glyph_id`, false when the glyph has none. Stops
+    // at the first cycle, at MAX_PAINT_DEPTH nested paints or after
+    // MAX_PAINT_COUNT paints.
+    pub fn walk_paint(
+        &self,
+        glyph_id: GlyphId,
+        visitor: &mut dyn PaintVisitor,
+    ) -> Result<bool, PaintError> {
+        match self.base_paint(glyph_id) {
+            Some(paint_id) => {
+                let mut path = vec![];
+                let mut count = 0;
+                self.walk(paint_id, visitor, &mut path, &mut count)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // `path` holds the paints from the root to this one
+    fn walk(
+        &self,
+        paint_id: PaintId,
+        visitor: &mut dyn PaintVisitor,
+        path: &mut Vec<PaintId>,
+        count: &mut usize,
+    ) -> Result<(), PaintError> {
+        if path.contains(&paint_id) {
+            return Err(PaintError::Cycle(paint_id));
+        }
+        if path.len() >= MAX_PAINT_DEPTH {
+            return Err(PaintError::DepthLimitExceeded);
+        }
+        *count += 1;
+        if *count > MAX_PAINT_COUNT {
+            return Err(PaintError::PaintCountExceeded);
+        }
+        let paint = self
+            .paint(paint_id)
+            .ok_or(PaintError::Malformed(paint_id))?;
+
+        let depth = path.len();
+        if visitor.enter(&paint, depth) {
+            let children: Vec<PaintId> = match &paint {
+                Paint::ColrLayers {
+                    first_layer_index,
+                    num_layers,
+                } => (0..*num_layers as usize)
+                    .map(|index| {
+                        self.layer_paints
+                            .get(*first_layer_index as usize + index)
+                            .copied()
+                            .ok_or(PaintError::Malformed(paint_id))
+                    })
+                    .collect::<Result<Vec<PaintId>, PaintError>>()?,
+                Paint::ColrGlyph { glyph_id } => self.base_paint(*glyph_id).into_iter().collect(),
+                Paint::Composite {
+                    source, backdrop, ..
+                } => vec![*backdrop, *source],
+                Paint::Glyph { paint, .. }
+                | Paint::Transform { paint, .. }
+                | Paint::Translate { paint, .. }
+                | Paint::Scale { paint, .. }
+                | Paint::Rotate { paint, .. }
+                | Paint::Skew { paint, .. } => vec![*paint],
+                Paint::Solid { .. }
+                | Paint::LinearGradient { .. }
+                | Paint::RadialGradient { .. }
+                | Paint::SweepGradient { .. } => vec![],
+            };
+
+            path.push(paint_id);
+            for child in children {
+                self.walk(child, visitor, path, count)?;
+            }
+            path.pop();
+        }
+        visitor.leave(&paint, depth);
+        Ok(())
+    }
+}
+
+fn composite_mode(mode: u8) -> Option<CompositeMode> {
+    use CompositeMode::*;
+    let modes = [
+        Clear, Src, Dest, SrcOver, DestOver, SrcIn, DestIn, SrcOut, DestOut, SrcAtop, DestAtop,
+        Xor, Plus, Screen, Overlay, Darken, Lighten, ColorDodge, ColorBurn, HardLight, SoftLight,
+        Difference, Exclusion, Multiply, Hue, Saturation, Color, Luminosity,
+    ];
+    modes.get(mode as usize).copied()
+}
+
+// Glyph id and Paint of each BaseGlyphPaintRecord
+fn read_base_glyph_paints(data: &[u8], offset: usize) -> Vec<(GlyphId, PaintId)> {
+    if offset == 0 {
+        return vec![];
+    }
+    let count = read_u32(data, offset).unwrap_or(0) as usize;
+    (0..count)
+        .map_while(|index| {
+            let position = offset + 4 + index * 6;
+            let glyph_id = GlyphId::new(read_u16(data, position)?);
+            let paint_offset = read_u32(data, position + 2)? as usize;
+            Some((glyph_id, PaintId((offset + paint_offset) as u32)))
+        })
+        .collect()
+}
+
+fn read_layer_paints(data: &[u8], offset: usize) -> Vec<PaintId> {
+    if offset == 0 {
+        return vec![];
+    }
+    let count = read_u32(data, offset).unwrap_or(0) as usize;
+    (0..count)
+        .map_while(|index| {
+            let paint_offset = read_u32(data, offset + 4 + index * 4)? as usize;
+            Some(PaintId((offset + paint_offset) as u32))
+        })
+        .collect()
+}
+
+fn read_clips(data: &[u8], offset: usize) -> Vec<(GlyphId, GlyphId, ClipBox)> {
+    if offset == 0 {
+        return vec![];
+    }
+    let count = read_u32(data, offset + 1).unwrap_or(0) as usize;
+    (0..count)
+        .map_while(|index| {
+            let position = offset + 5 + index * 7;
+            let first = GlyphId::new(read_u16(data, position)?);
+            let last = GlyphId::new(read_u16(data, position + 2)?);
+            let clip_box = offset + read_u24(data, position + 4)? as usize;
+            let clip_box = ClipBox {
+                x_min: read_i16(data, clip_box + 1)?,
+                y_min: read_i16(data, clip_box + 3)?,
+                x_max: read_i16(data, clip_box + 5)?,
+                y_max: read_i16(data, clip_box + 7)?,
+                var_index_base: if *data.get(clip_box)? == 2 {
+                    Some(read_u32(data, clip_box + 9)?)
+                } else {
+                    None
+                },
+            };
+            Some((first, last, clip_box))
+        })
+        .collect()
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([
+        *data.get(offset)?,
+        *data.get(offset + 1)?,
+    ]))
+}
+
+fn read_i16(data: &[u8], offset: usize) -> Option<i16> {
+    read_u16(data, offset).map(|value| value as i16)
+}
+
+fn read_u24(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 3)?;
+    Some(u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_f2dot14(data: &[u8], offset: usize) -> Option<f32> {
+    read_i16(data, offset).map(|value| value as f32 / 16384.0)
+}
@@ -0,0 +1,131 @@
+use crate::reader::Reader;
+
+const NO_LABEL: u16 = 0xFFFF;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rgba {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+    pub alpha: u8,
+}
+
+impl Rgba {
+    pub const BLACK: Rgba = Rgba {
+        red: 0,
+        green: 0,
+        blue: 0,
+        alpha: 255,
+    };
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Palette {
+    pub colors: Vec<Rgba>,
+    pub palette_type: u32, // flags, see `USABLE_WITH_LIGHT_BACKGROUND`, 0 before version 1
+    pub label: Option<u16>, // name id
+}
+
+impl Palette {
+    pub const USABLE_WITH_LIGHT_BACKGROUND: u32 = 0x0001;
+    pub const USABLE_WITH_DARK_BACKGROUND: u32 = 0x0002;
+}
+
+// Color Palette table, the colors of COLR layers
+#[allow(unused)]
+#[derive(Debug)]
+pub struct CpalTable {
+    pub version: u16,
+    pub palettes: Vec<Palette>,
+    pub palette_entry_labels: Vec<Option<u16>>, // name id of each entry, version 1
+}
+
+impl CpalTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, offset: u32) -> CpalTable {
+        reader.seek_from_start(offset);
+        let version = reader.read_u16();
+        let num_palette_entries = reader.read_u16();
+        let num_palettes = reader.read_u16();
+        let num_color_records = reader.read_u16();
+        let color_records_array_offset = reader.read_u32();
+        let color_record_indices: Vec<u16> = (0..num_palettes).map(|_| reader.read_u16()).collect();
+
+        // Offsets of the version 1 arrays, 0 when absent
+        let (palette_types_offset, palette_labels_offset, palette_entry_labels_offset) =
+            if version >= 1 {
+                (reader.read_u32(), reader.read_u32(), reader.read_u32())
+            } else {
+                (0, 0, 0)
+            };
+
+        // Color records are stored as BGRA
+        reader.seek_from_start(offset + color_records_array_offset);
+        let color_records: Vec<Rgba> = (0..num_color_records)
+            .map(|_| {
+                let blue = reader.read_u8();
+                let green = reader.read_u8();
+                let red = reader.read_u8();
+                let alpha = reader.read_u8();
+                Rgba {
+                    red,
+                    green,
+                    blue,
+                    alpha,
+                }
+            })
+            .collect();
+
+        let palette_types: Vec<u32> = if palette_types_offset != 0 {
+            reader.seek_from_start(offset + palette_types_offset);
+            (0..num_palettes).map(|_| reader.read_u32()).collect()
+        } else {
+            vec![0; num_palettes as usize]
+        };
+        let palette_labels = read_labels(reader, offset, palette_labels_offset, num_palettes);
+        let palette_entry_labels = read_labels(
+            reader,
+            offset,
+            palette_entry_labels_offset,
+            num_palette_entries,
+        );
+
+        let palettes = color_record_indices
+            .iter()
+            .enumerate()
+            .map(|(index, first_color)| {
+                // A palette past the color records has no colors, its layers are
+                // drawn in the foreground color
+                let first_color = *first_color as usize;
+                let colors = color_records
+                    .get(first_color..first_color + num_palette_entries as usize)
+                    .map_or(vec![], |colors| colors.to_vec());
+                Palette {
+                    colors,
+                    palette_type: palette_types[index],
+                    label: palette_labels[index],
+                }
+            })
+            .collect();
+
+        CpalTable {
+            version,
+            palettes,
+            palette_entry_labels,
+        }
+    }
+}
+
+fn read_labels(
+    reader: &mut Box<dyn Reader>,
+    table_offset: u32,
+    labels_offset: u32,
+    count: u16,
+) -> Vec<Option<u16>> {
+    if labels_offset == 0 {
+        return vec![None; count as usize];
+    }
+    reader.seek_from_start(table_offset + labels_offset);
+    (0..count)
+        .map(|_| Some(reader.read_u16()).filter(|label| *label != NO_LABEL))
+        .collect()
+}
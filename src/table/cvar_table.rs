@@ -0,0 +1,63 @@
+use crate::reader::Reader;
+use crate::tuple_variation::{read_tuple_variations, TupleVariation};
+
+// CVT Variations table, deltas of the control values for variable fonts
+#[allow(unused)]
+#[derive(Debug, Clone)]
+pub struct CvarTable {
+    major_version: u16,
+    minor_version: u16,
+    pub tuple_variations: Vec<TupleVariation>,
+}
+
+impl CvarTable {
+    pub fn from_file(
+        reader: &mut Box<dyn Reader>,
+        offset: u32,
+        axis_count: u16,
+        cvt_count: usize,
+    ) -> CvarTable {
+        reader.seek_from_start(offset);
+        let major_version = reader.read_u16();
+        let minor_version = reader.read_u16();
+        // No shared tuples, each tuple variation header embeds its peak
+        let tuple_variations =
+            read_tuple_variations(reader, offset, axis_count, &[], cvt_count, false);
+
+        CvarTable {
+            major_version,
+            minor_version,
+            tuple_variations,
+        }
+    }
+
+    // The control values at the normalized `coordinates`. The deltas of all
+    // tuples are summed before rounding, as FreeType does.
+    pub fn varied_values(&self, values: &[i16], coordinates: &[f32]) -> Vec<i16> {
+        let mut deltas: Vec<f32> = vec![0.0; values.len()];
+        self.tuple_variations.iter().for_each(|variation| {
+            let scalar = variation.scalar(coordinates);
+            if scalar == 0.0 {
+                return;
+            }
+            let indices: Vec<usize> = match &variation.point_numbers {
+                Some(point_numbers) => point_numbers.iter().map(|index| *index as usize).collect(),
+                None => (0..values.len()).collect(),
+            };
+            indices
+                .iter()
+                .zip(&variation.x_deltas)
+                .for_each(|(index, delta)| {
+                    if let Some(sum) = deltas.get_mut(*index) {
+                        *sum += scalar * *delta as f32;
+                    }
+                });
+        });
+
+        values
+            .iter()
+            .zip(deltas)
+            .map(|(value, delta)| (*value as f32 + delta).round() as i16)
+            .collect()
+    }
+}
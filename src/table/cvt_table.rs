@@ -0,0 +1,19 @@
+use crate::font_directory::TableDirectory;
+use crate::reader::Reader;
+
+// Control Value Table, distances in font units that TrueType instructions
+// read and write at the current size
+#[derive(Debug)]
+pub struct CvtTable {
+    pub values: Vec<i16>,
+}
+
+impl CvtTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, table_directory: &TableDirectory) -> CvtTable {
+        reader.seek_from_start(table_directory.offset);
+        let values = (0..table_directory.length / 2)
+            .map(|_| reader.read_i16())
+            .collect();
+        CvtTable { values }
+    }
+}
@@ -0,0 +1,134 @@
+use crate::model::GlyphId;
+use crate::reader::Reader;
+use crate::table::eblc_table::{BigGlyphMetrics, BitmapStrike, GlyphImageLocation};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BitmapComponent {
+    pub glyph_id: GlyphId, // bitmap of the same strike
+    pub x_offset: i8,
+    pub y_offset: i8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BitmapData {
+    // Rows from top to bottom, each padded to a whole byte, `bit_depth` bits
+    // per pixel with the leftmost pixel in the most significant bits
+    Pixels(Vec<u8>),
+    Png(Vec<u8>),
+    Composite(Vec<BitmapComponent>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitmap {
+    pub ppem_x: u8,
+    pub ppem_y: u8,
+    pub bit_depth: u8,
+    pub image_format: u16,
+    pub metrics: BigGlyphMetrics, // small metrics fill the direction of the strike
+    pub data: BitmapData,
+}
+
+// Reads the image at `location` in CBDT or EBDT, None for the obsolete
+// formats 3 and 4 and unknown ones
+pub fn read_bitmap(
+    reader: &mut Box<dyn Reader>,
+    offset: u32,
+    strike: &BitmapStrike,
+    location: &GlyphImageLocation,
+) -> Option<Bitmap> {
+    reader.seek_from_start(offset.checked_add(location.offset)?);
+    let image_format = location.image_format;
+
+    let (metrics, metrics_size) = match image_format {
+        1 | 2 | 8 | 17 => (read_small_metrics(reader, strike.flags), 5),
+        6 | 7 | 9 | 18 => (BigGlyphMetrics::from_file(reader), 8),
+        5 | 19 => (location.metrics.unwrap_or_default(), 0),
+        _ => return None,
+    };
+    let data_length = location.length.saturating_sub(metrics_size) as usize;
+
+    let data = match image_format {
+        1 | 6 => BitmapData::Pixels(reader.read_bytes(data_length)),
+        2 | 5 | 7 => {
+            let bits = reader.read_bytes(data_length);
+            BitmapData::Pixels(byte_aligned_rows(&bits, &metrics, strike.bit_depth))
+        }
+        8 | 9 => {
+            if image_format == 8 {
+                reader.read_u8(); // pad
+            }
+            let num_components = reader.read_u16();
+            let components = (0..num_components)
+                .map(|_| BitmapComponent {
+                    glyph_id: GlyphId::new(reader.read_u16()),
+                    x_offset: reader.read_i8(),
+                    y_offset: reader.read_i8(),
+                })
+                .collect();
+            BitmapData::Composite(components)
+        }
+        _ => {
+            let length = reader.read_u32();
+            BitmapData::Png(reader.read_bytes(length as usize))
+        }
+    };
+
+    Some(Bitmap {
+        ppem_x: strike.ppem_x,
+        ppem_y: strike.ppem_y,
+        bit_depth: strike.bit_depth,
+        image_format,
+        metrics,
+        data,
+    })
+}
+
+fn read_small_metrics(reader: &mut Box<dyn Reader>, flags: i8) -> BigGlyphMetrics {
+    let height = reader.read_u8();
+    let width = reader.read_u8();
+    let bearing_x = reader.read_i8();
+    let bearing_y = reader.read_i8();
+    let advance = reader.read_u8();
+    let vertical = flags & BitmapStrike::VERTICAL_METRICS != 0
+        && flags & BitmapStrike::HORIZONTAL_METRICS == 0;
+
+    if vertical {
+        BigGlyphMetrics {
+            height,
+            width,
+            vert_bearing_x: bearing_x,
+            vert_bearing_y: bearing_y,
+            vert_advance: advance,
+            ..BigGlyphMetrics::default()
+        }
+    } else {
+        BigGlyphMetrics {
+            height,
+            width,
+            hori_bearing_x: bearing_x,
+            hori_bearing_y: bearing_y,
+            hori_advance: advance,
+            ..BigGlyphMetrics::default()
+        }
+    }
+}
+
+// Bit aligned images continue each row right after the previous one
+fn byte_aligned_rows(bits: &[u8], metrics: &BigGlyphMetrics, bit_depth: u8) -> Vec<u8> {
+    let row_bits = metrics.width as usize * bit_depth as usize;
+    let row_bytes = row_bits.div_ceil(8);
+    let mut rows = vec![0u8; row_bytes * metrics.height as usize];
+
+    (0..metrics.height as usize).for_each(|row| {
+        (0..row_bits).for_each(|bit| {
+            let source = row * row_bits + bit;
+            let set = bits
+                .get(source / 8)
+                .is_some_and(|byte| byte & (0x80 >> (source % 8)) != 0);
+            if set {
+                rows[row * row_bytes + bit / 8] |= 0x80 >> (bit % 8);
+            }
+        })
+    });
+    rows
+}
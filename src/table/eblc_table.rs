@@ -0,0 +1,318 @@
+use crate::model::GlyphId;
+use crate::reader::Reader;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct BigGlyphMetrics {
+    pub height: u8,
+    pub width: u8,
+    pub hori_bearing_x: i8,
+    pub hori_bearing_y: i8,
+    pub hori_advance: u8,
+    pub vert_bearing_x: i8,
+    pub vert_bearing_y: i8,
+    pub vert_advance: u8,
+}
+
+impl BigGlyphMetrics {
+    pub fn from_file(reader: &mut Box<dyn Reader>) -> BigGlyphMetrics {
+        BigGlyphMetrics {
+            height: reader.read_u8(),
+            width: reader.read_u8(),
+            hori_bearing_x: reader.read_i8(),
+            hori_bearing_y: reader.read_i8(),
+            hori_advance: reader.read_u8(),
+            vert_bearing_x: reader.read_i8(),
+            vert_bearing_y: reader.read_i8(),
+            vert_advance: reader.read_u8(),
+        }
+    }
+}
+
+// Where the image of a glyph is in CBDT or EBDT
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GlyphImageLocation {
+    pub image_format: u16,
+    pub offset: u32, // from the start of CBDT or EBDT
+    pub length: u32,
+    pub metrics: Option<BigGlyphMetrics>, // index formats 2 and 5, shared by all glyphs
+}
+
+#[allow(unused)]
+#[derive(Debug)]
+enum IndexSubtable {
+    // Formats 1 and 3, offsets of each glyph in first..=last and one past the last
+    Offsets {
+        image_format: u16,
+        image_data_offset: u32,
+        offsets: Vec<u32>,
+    },
+    // Format 2, glyphs in first..=last of the same size
+    Constant {
+        image_format: u16,
+        image_data_offset: u32,
+        image_size: u32,
+        metrics: BigGlyphMetrics,
+    },
+    // Format 4, sorted (glyph id, offset) pairs and one past the last
+    SparseOffsets {
+        image_format: u16,
+        image_data_offset: u32,
+        glyph_offsets: Vec<(u16, u32)>,
+    },
+    // Format 5, sorted glyph ids of the same size
+    SparseConstant {
+        image_format: u16,
+        image_data_offset: u32,
+        image_size: u32,
+        metrics: BigGlyphMetrics,
+        glyph_ids: Vec<u16>,
+    },
+}
+
+#[allow(unused)]
+#[derive(Debug)]
+pub struct BitmapStrike {
+    pub ppem_x: u8,
+    pub ppem_y: u8,
+    pub bit_depth: u8, // 1, 2, 4 or 8 for grayscale, 32 for color
+    pub flags: i8,     // see `HORIZONTAL_METRICS`
+    start_glyph_index: u16,
+    end_glyph_index: u16,
+    index_subtables: Vec<(u16, u16, IndexSubtable)>, // first and last glyph of each subtable
+}
+
+impl BitmapStrike {
+    pub const HORIZONTAL_METRICS: i8 = 0x01;
+    pub const VERTICAL_METRICS: i8 = 0x02;
+
+    pub fn location(&self, glyph_id: GlyphId) -> Option<GlyphImageLocation> {
+        let id = glyph_id.id();
+        if id < self.start_glyph_index || id > self.end_glyph_index {
+            return None;
+        }
+        let (first, _, index_subtable) = self
+            .index_subtables
+            .iter()
+            .find(|(first, last, _)| (*first..=*last).contains(&id))?;
+
+        let (image_format, image_data_offset, offset, length, metrics) = match index_subtable {
+            IndexSubtable::Offsets {
+                image_format,
+                image_data_offset,
+                offsets,
+            } => {
+                let index = (id - first) as usize;
+                let (start, end) = (offsets[index], offsets[index + 1]);
+                (
+                    *image_format,
+                    *image_data_offset,
+                    start,
+                    end.checked_sub(start)?,
+                    None,
+                )
+            }
+            IndexSubtable::Constant {
+                image_format,
+                image_data_offset,
+                image_size,
+                metrics,
+            } => (
+                *image_format,
+                *image_data_offset,
+                ((id - first) as u32).checked_mul(*image_size)?,
+                *image_size,
+                Some(*metrics),
+            ),
+            IndexSubtable::SparseOffsets {
+                image_format,
+                image_data_offset,
+                glyph_offsets,
+            } => {
+                let index = glyph_offsets[..glyph_offsets.len() - 1]
+                    .binary_search_by_key(&id, |(glyph_id, _)| *glyph_id)
+                    .ok()?;
+                let (start, end) = (glyph_offsets[index].1, glyph_offsets[index + 1].1);
+                (
+                    *image_format,
+                    *image_data_offset,
+                    start,
+                    end.checked_sub(start)?,
+                    None,
+                )
+            }
+            IndexSubtable::SparseConstant {
+                image_format,
+                image_data_offset,
+                image_size,
+                metrics,
+                glyph_ids,
+            } => {
+                let index = glyph_ids.binary_search(&id).ok()?;
+                (
+                    *image_format,
+                    *image_data_offset,
+                    (index as u32).checked_mul(*image_size)?,
+                    *image_size,
+                    Some(*metrics),
+                )
+            }
+        };
+
+        // A glyph without data has no image in this strike
+        if length == 0 {
+            return None;
+        }
+        Some(GlyphImageLocation {
+            image_format,
+            offset: image_data_offset.checked_add(offset)?,
+            length,
+            metrics,
+        })
+    }
+}
+
+// Embedded bitmap location table, CBLC and EBLC share the layout
+#[allow(unused)]
+#[derive(Debug)]
+pub struct EblcTable {
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub strikes: Vec<BitmapStrike>,
+}
+
+impl EblcTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, offset: u32) -> EblcTable {
+        reader.seek_from_start(offset);
+        let major_version = reader.read_u16();
+        let minor_version = reader.read_u16();
+        let num_sizes = reader.read_u32();
+
+        // BitmapSize records of 48 bytes, with the offset and count of their index subtables
+        let sizes: Vec<(u32, u32, BitmapStrike)> = (0..num_sizes)
+            .map(|_| {
+                let index_subtable_list_offset = reader.read_u32();
+                let _index_subtable_list_size = reader.read_u32();
+                let number_of_index_subtables = reader.read_u32();
+                let _color_ref = reader.read_u32();
+                reader.seek_from_current(24); // hori and vert SbitLineMetrics
+                let strike = BitmapStrike {
+                    start_glyph_index: reader.read_u16(),
+                    end_glyph_index: reader.read_u16(),
+                    ppem_x: reader.read_u8(),
+                    ppem_y: reader.read_u8(),
+                    bit_depth: reader.read_u8(),
+                    flags: reader.read_i8(),
+                    index_subtables: vec![],
+                };
+                (
+                    index_subtable_list_offset,
+                    number_of_index_subtables,
+                    strike,
+                )
+            })
+            .collect();
+
+        let strikes = sizes
+            .into_iter()
+            .map(|(list_offset, number_of_index_subtables, mut strike)| {
+                let list_offset = offset + list_offset;
+                reader.seek_from_start(list_offset);
+                let records: Vec<(u16, u16, u32)> = (0..number_of_index_subtables)
+                    .map(|_| (reader.read_u16(), reader.read_u16(), reader.read_u32()))
+                    .collect();
+                strike.index_subtables = records
+                    .into_iter()
+                    .filter_map(|(first, last, subtable_offset)| {
+                        reader.seek_from_start(list_offset + subtable_offset);
+                        Some((first, last, read_index_subtable(reader, first, last)?))
+                    })
+                    .collect();
+                strike
+            })
+            .collect();
+
+        EblcTable {
+            major_version,
+            minor_version,
+            strikes,
+        }
+    }
+
+    // The strike of `ppem` if it has the glyph, otherwise the smallest larger
+    // one, otherwise the largest smaller one. Strikes are at most 255 ppem.
+    pub fn best_strike(
+        &self,
+        glyph_id: GlyphId,
+        ppem: u16,
+    ) -> Option<(&BitmapStrike, GlyphImageLocation)> {
+        let ppem = ppem.min(u8::MAX as u16) as u8;
+        let mut candidates: Vec<(&BitmapStrike, GlyphImageLocation)> = self
+            .strikes
+            .iter()
+            .filter_map(|strike| strike.location(glyph_id).map(|location| (strike, location)))
+            .collect();
+        candidates.sort_by_key(|(strike, _)| strike.ppem_y);
+        let larger = candidates
+            .iter()
+            .position(|(strike, _)| strike.ppem_y >= ppem);
+        match larger {
+            Some(index) => Some(candidates.swap_remove(index)),
+            None => candidates.pop(),
+        }
+    }
+}
+
+// None for an index format this does not know, whose glyphs are then left out
+fn read_index_subtable(
+    reader: &mut Box<dyn Reader>,
+    first: u16,
+    last: u16,
+) -> Option<IndexSubtable> {
+    let index_format = reader.read_u16();
+    let image_format = reader.read_u16();
+    let image_data_offset = reader.read_u32();
+    let count = last.saturating_sub(first) as usize + 1;
+
+    let index_subtable = match index_format {
+        1 => IndexSubtable::Offsets {
+            image_format,
+            image_data_offset,
+            offsets: (0..=count).map(|_| reader.read_u32()).collect(),
+        },
+        2 => IndexSubtable::Constant {
+            image_format,
+            image_data_offset,
+            image_size: reader.read_u32(),
+            metrics: BigGlyphMetrics::from_file(reader),
+        },
+        3 => IndexSubtable::Offsets {
+            image_format,
+            image_data_offset,
+            offsets: (0..=count).map(|_| reader.read_u16() as u32).collect(),
+        },
+        4 => {
+            let num_glyphs = reader.read_u32();
+            IndexSubtable::SparseOffsets {
+                image_format,
+                image_data_offset,
+                glyph_offsets: (0..=num_glyphs)
+                    .map(|_| (reader.read_u16(), reader.read_u16() as u32))
+                    .collect(),
+            }
+        }
+        5 => {
+            let image_size = reader.read_u32();
+            let metrics = BigGlyphMetrics::from_file(reader);
+            let num_glyphs = reader.read_u32();
+            IndexSubtable::SparseConstant {
+                image_format,
+                image_data_offset,
+                image_size,
+                metrics,
+                glyph_ids: (0..num_glyphs).map(|_| reader.read_u16()).collect(),
+            }
+        }
+        _ => return None,
+    };
+    Some(index_subtable)
+}
@@ -0,0 +1,18 @@
+use crate::font_directory::TableDirectory;
+use crate::reader::Reader;
+
+// Font program, TrueType instructions run once to define the functions
+// called from prep and the glyph programs
+#[derive(Debug)]
+pub struct FpgmTable {
+    pub instructions: Vec<u8>,
+}
+
+impl FpgmTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, table_directory: &TableDirectory) -> FpgmTable {
+        reader.seek_from_start(table_directory.offset);
+        FpgmTable {
+            instructions: reader.read_bytes(table_directory.length as usize),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use crate::reader::Reader;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariationAxis {
+    pub tag: String,
+    pub min_value: f32,
+    pub default_value: f32,
+    pub max_value: f32,
+    pub flags: u16, // 0x0001 HIDDEN_AXIS, the axis should not be exposed in user interfaces
+    pub axis_name_id: u16,
+}
+
+impl VariationAxis {
+    // Maps a design space value of the axis to the normalized range [-1, 1],
+    // default normalization before any avar mapping.
+    pub fn normalize(&self, value: f32) -> f32 {
+        let value = value.max(self.min_value).min(self.max_value);
+
+        if value < self.default_value && self.default_value > self.min_value {
+            (value - self.default_value) / (self.default_value - self.min_value)
+        } else if value > self.default_value && self.max_value > self.default_value {
+            (value - self.default_value) / (self.max_value - self.default_value)
+        } else {
+            0.0
+        }
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.flags & 0x0001 == 0x0001
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedInstance {
+    pub subfamily_name_id: u16,
+    pub flags: u16,
+    pub coordinates: Vec<f32>, // design space value for each axis
+    pub post_script_name_id: Option<u16>,
+}
+
+// Font Variations table, the axes of a variable font and its named instances
+#[allow(unused)]
+#[derive(Debug)]
+pub struct FvarTable {
+    major_version: u16,
+    minor_version: u16,
+    pub axes: Vec<VariationAxis>,
+    pub instances: Vec<NamedInstance>,
+}
+
+impl FvarTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, offset: u32) -> FvarTable {
+        reader.seek_from_start(offset);
+        let major_version = reader.read_u16();
+        let minor_version = reader.read_u16();
+        let axes_array_offset = reader.read_u16();
+        let _reserved = reader.read_u16();
+        let axis_count = reader.read_u16();
+        let axis_size = reader.read_u16();
+        let instance_count = reader.read_u16();
+        let instance_size = reader.read_u16();
+
+        let axes: Vec<VariationAxis> = (0..axis_count)
+            .map(|index| {
+                reader.seek_from_start(
+                    offset + axes_array_offset as u32 + index as u32 * axis_size as u32,
+                );
+                let tag = reader.read_table_name();
+                let min_value = reader.read_fixed().to_f32();
+                let default_value = reader.read_fixed().to_f32();
+                let max_value = reader.read_fixed().to_f32();
+                let flags = reader.read_u16();
+                let axis_name_id = reader.read_u16();
+
+                VariationAxis {
+                    tag,
+                    min_value,
+                    default_value,
+                    max_value,
+                    flags,
+                    axis_name_id,
+                }
+            })
+            .collect();
+
+        // Instances follow the axes, the post script name id is only present
+        // when the instance record is large enough to hold it.
+        let instances_offset =
+            offset + axes_array_offset as u32 + axis_count as u32 * axis_size as u32;
+        let has_post_script_name_id = instance_size >= axis_count * 4 + 6;
+
+        let instances: Vec<NamedInstance> = (0..instance_count)
+            .map(|index| {
+                reader.seek_from_start(instances_offset + index as u32 * instance_size as u32);
+                let subfamily_name_id = reader.read_u16();
+                let flags = reader.read_u16();
+                let coordinates: Vec<f32> = (0..axis_count)
+                    .map(|_| reader.read_fixed().to_f32())
+                    .collect();
+                let post_script_name_id = if has_post_script_name_id {
+                    Some(reader.read_u16())
+                } else {
+                    None
+                };
+
+                NamedInstance {
+                    subfamily_name_id,
+                    flags,
+                    coordinates,
+                    post_script_name_id,
+                }
+            })
+            .collect();
+
+        FvarTable {
+            major_version,
+            minor_version,
+            axes,
+            instances,
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use crate::reader::Reader;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GaspRange {
+    pub range_max_ppem: u16,      // upper limit of the range, inclusive
+    pub range_gasp_behavior: u16, // see `GaspTable::GRIDFIT`
+}
+
+// Grid-fitting and scan-conversion procedure, how to render each range of sizes
+#[allow(unused)]
+#[derive(Debug)]
+pub struct GaspTable {
+    version: u16,
+    pub ranges: Vec<GaspRange>, // sorted by range_max_ppem, the last one usually 0xFFFF
+}
+
+impl GaspTable {
+    pub const GRIDFIT: u16 = 0x0001;
+    pub const DOGRAY: u16 = 0x0002;
+    pub const SYMMETRIC_GRIDFIT: u16 = 0x0004; // version 1
+    pub const SYMMETRIC_SMOOTHING: u16 = 0x0008; // version 1
+
+    pub fn from_file(reader: &mut Box<dyn Reader>, offset: u32) -> GaspTable {
+        reader.seek_from_start(offset);
+        let version = reader.read_u16();
+        let num_ranges = reader.read_u16();
+        let ranges = (0..num_ranges)
+            .map(|_| GaspRange {
+                range_max_ppem: reader.read_u16(),
+                range_gasp_behavior: reader.read_u16(),
+            })
+            .collect();
+        GaspTable { version, ranges }
+    }
+
+    // The behavior flags of the range holding `ppem`, none above the last range
+    pub fn behavior(&self, ppem: u16) -> u16 {
+        self.ranges
+            .iter()
+            .find(|range| ppem <= range.range_max_ppem)
+            .map_or(0, |range| range.range_gasp_behavior)
+    }
+}
@@ -0,0 +1,84 @@
+use crate::model::GlyphId;
+use crate::reader::Reader;
+use crate::tuple_variation::{read_tuple_variations, TupleVariation};
+
+// Glyph Variations table, deltas of the glyph outlines for variable fonts
+#[allow(unused)]
+#[derive(Debug)]
+pub struct GvarTable {
+    major_version: u16,
+    minor_version: u16,
+    pub axis_count: u16,
+    pub shared_tuples: Vec<Vec<f32>>,
+    glyph_variation_data_offsets: Vec<u32>, // from the start of the file, glyph_count + 1 entries
+}
+
+impl GvarTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, offset: u32) -> GvarTable {
+        reader.seek_from_start(offset);
+        let major_version = reader.read_u16();
+        let minor_version = reader.read_u16();
+        let axis_count = reader.read_u16();
+        let shared_tuple_count = reader.read_u16();
+        let shared_tuples_offset = reader.read_u32();
+        let glyph_count = reader.read_u16();
+        let flags = reader.read_u16();
+        let glyph_variation_data_array_offset = reader.read_u32();
+
+        // Bit 0 of flags: offsets are 32 bits, otherwise 16 bits divided by 2
+        let data_array_start = offset + glyph_variation_data_array_offset;
+        let glyph_variation_data_offsets: Vec<u32> = (0..glyph_count as u32 + 1)
+            .map(|_| {
+                let offset = if flags & 1 == 1 {
+                    reader.read_u32()
+                } else {
+                    reader.read_u16() as u32 * 2
+                };
+                data_array_start + offset
+            })
+            .collect();
+
+        reader.seek_from_start(offset + shared_tuples_offset);
+        let shared_tuples: Vec<Vec<f32>> = (0..shared_tuple_count)
+            .map(|_| (0..axis_count).map(|_| reader.read_f2dot14()).collect())
+            .collect();
+
+        GvarTable {
+            major_version,
+            minor_version,
+            axis_count,
+            shared_tuples,
+            glyph_variation_data_offsets,
+        }
+    }
+
+    // `point_count` includes the four phantom points
+    pub fn tuple_variations(
+        &self,
+        reader: &mut Box<dyn Reader>,
+        glyph_id: GlyphId,
+        point_count: usize,
+    ) -> Vec<TupleVariation> {
+        let index = glyph_id.id() as usize;
+        let (start, end) = match (
+            self.glyph_variation_data_offsets.get(index),
+            self.glyph_variation_data_offsets.get(index + 1),
+        ) {
+            (Some(start), Some(end)) => (*start, *end),
+            _ => return vec![],
+        };
+        if start >= end {
+            return vec![];
+        }
+
+        reader.seek_from_start(start);
+        read_tuple_variations(
+            reader,
+            start,
+            self.axis_count,
+            &self.shared_tuples,
+            point_count,
+            true,
+        )
+    }
+}
@@ -0,0 +1,46 @@
+use crate::model::GlyphId;
+use crate::reader::Reader;
+
+#[derive(Debug)]
+pub struct DeviceRecord {
+    pub pixel_size: u8,
+    pub max_width: u8,
+    pub widths: Vec<u8>, // hinted advance width in pixels of each glyph
+}
+
+// Horizontal device metrics, advance widths precomputed by the hinter
+#[allow(unused)]
+#[derive(Debug)]
+pub struct HdmxTable {
+    version: u16,
+    pub records: Vec<DeviceRecord>,
+}
+
+impl HdmxTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, offset: u32, num_glyphs: u16) -> HdmxTable {
+        reader.seek_from_start(offset);
+        let version = reader.read_u16();
+        let num_records = reader.read_i16();
+        let size_device_record = reader.read_u32();
+
+        // Records are padded to a multiple of 4 bytes
+        let records = (0..num_records.max(0) as u32)
+            .map(|index| {
+                reader.seek_from_start(offset + 8 + index * size_device_record);
+                DeviceRecord {
+                    pixel_size: reader.read_u8(),
+                    max_width: reader.read_u8(),
+                    widths: reader.read_bytes(num_glyphs as usize),
+                }
+            })
+            .collect();
+        HdmxTable { version, records }
+    }
+
+    pub fn advance_width(&self, glyph_id: GlyphId, ppem: u8) -> Option<u8> {
+        self.records
+            .iter()
+            .find(|record| record.pixel_size == ppem)
+            .and_then(|record| record.widths.get(glyph_id.id() as usize).copied())
+    }
+}
@@ -1,5 +1,9 @@
+use crate::font_builder::FontTable;
 use crate::model::{FWord, Fixed};
 use crate::reader::Reader;
+use crate::writer::Writer;
+
+const MAGIC_NUMBER: u32 = 0x5F0F3CF5;
 
 #[allow(unused)]
 #[derive(Debug)]
@@ -7,7 +11,7 @@ pub struct HeadTable {
     version: Fixed,
     font_revision: Fixed,
     flags: u16,
-    units_per_em: u16,
+    pub units_per_em: u16,
     created: i64,
     modified: i64,
     pub x_min: FWord,
@@ -29,7 +33,7 @@ impl HeadTable {
         let font_revision = reader.read_fixed();
 
         let _checksum = reader.read_u32();
-        let _magic_number = reader.read_u32(); // Must be 0x5F0F3CF5
+        let _magic_number = reader.read_u32(); // Must be MAGIC_NUMBER
         let flags = reader.read_u16();
         let units_per_em = reader.read_u16();
 
@@ -66,3 +70,32 @@ impl HeadTable {
         }
     }
 }
+
+impl FontTable for HeadTable {
+    fn tag(&self) -> &'static str {
+        "head"
+    }
+
+    // checkSumAdjustment is left to 0, `FontBuilder` computes it
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer.write_fixed(&self.version);
+        writer.write_fixed(&self.font_revision);
+        writer.write_u32(0);
+        writer.write_u32(MAGIC_NUMBER);
+        writer.write_u16(self.flags);
+        writer.write_u16(self.units_per_em);
+        writer.write_long_date_time(self.created);
+        writer.write_long_date_time(self.modified);
+        writer.write_fword(self.x_min);
+        writer.write_fword(self.y_min);
+        writer.write_fword(self.x_max);
+        writer.write_fword(self.y_max);
+        writer.write_u16(self.mac_style);
+        writer.write_u16(self.lowest_rec_ppem);
+        writer.write_i16(self.font_direction_hint);
+        writer.write_i16(self.index_to_loc_format);
+        writer.write_i16(self.glyph_data_format);
+        writer.into_vec()
+    }
+}
@@ -1,20 +1,22 @@
+use crate::font_builder::FontTable;
 use crate::model::{FWord, Fixed, UFWord};
 use crate::reader::Reader;
+use crate::writer::Writer;
 
 #[allow(unused)]
 #[derive(Debug)]
 pub struct HheaTable {
     version: Fixed,
-    ascent: FWord,
-    descent: FWord,
-    line_gap: FWord,
-    advance_width_mac: UFWord,
-    min_left_side_bearing: FWord,
-    min_right_side_bearing: FWord,
-    x_max_extent: FWord,
-    caret_slope_rise: i16,
-    caret_slope_run: i16,
-    caret_offset: FWord,
+    pub ascent: FWord,                 // Distance from baseline of highest ascender
+    pub descent: FWord,                // Distance from baseline of lowest descender
+    pub line_gap: FWord,               // typographic line gap
+    pub advance_width_mac: UFWord,     // must be consistent with horizontal metrics
+    pub min_left_side_bearing: FWord,  // must be consistent with horizontal metrics
+    pub min_right_side_bearing: FWord, // must be consistent with horizontal metrics
+    pub x_max_extent: FWord,           // max(lsb + (xMax-xMin))
+    pub caret_slope_rise: i16,         // slope of the caret (rise/run), 1 for vertical caret
+    pub caret_slope_run: i16,          // 0 for vertical
+    pub caret_offset: FWord,           // set value to 0 for non-slanted fonts
     metric_data_format: i16,
     pub num_of_long_hor_metrics: u16,
 }
@@ -58,3 +60,28 @@ impl HheaTable {
         }
     }
 }
+
+impl FontTable for HheaTable {
+    fn tag(&self) -> &'static str {
+        "hhea"
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer.write_fixed(&self.version);
+        writer.write_fword(self.ascent);
+        writer.write_fword(self.descent);
+        writer.write_fword(self.line_gap);
+        writer.write_ufword(self.advance_width_mac);
+        writer.write_fword(self.min_left_side_bearing);
+        writer.write_fword(self.min_right_side_bearing);
+        writer.write_fword(self.x_max_extent);
+        writer.write_i16(self.caret_slope_rise);
+        writer.write_i16(self.caret_slope_run);
+        writer.write_fword(self.caret_offset);
+        (0..4).for_each(|_| writer.write_i16(0)); // reserved
+        writer.write_i16(self.metric_data_format);
+        writer.write_u16(self.num_of_long_hor_metrics);
+        writer.into_vec()
+    }
+}
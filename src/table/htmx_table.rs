@@ -18,40 +18,36 @@ impl LongHorMetricLookup {
     pub fn from_file(
         reader: &mut Box<dyn Reader>,
         offset: u32,
-        hhea_table: HheaTable,
+        hhea_table: &HheaTable,
         maxp_table: &MaximumProfileTable,
     ) -> LongHorMetricLookup {
         reader.seek_from_start(offset);
         let mut result: HashMap<GlyphId, LongHorMetric> = HashMap::new();
         let mut last_advance_width = 0;
 
-        (0..hhea_table.num_of_long_hor_metrics)
-            .into_iter()
-            .for_each(|index| {
-                let advance_width: u16 = reader.read_u16();
-                let left_side_bearing: i16 = reader.read_i16();
-                let long_hor_matrics = LongHorMetric {
-                    advance_width,
-                    left_side_bearing,
-                };
-                last_advance_width = advance_width;
-                result.insert(GlyphId::new(index), long_hor_matrics);
-            });
+        (0..hhea_table.num_of_long_hor_metrics).for_each(|index| {
+            let advance_width: u16 = reader.read_u16();
+            let left_side_bearing: i16 = reader.read_i16();
+            let long_hor_matrics = LongHorMetric {
+                advance_width,
+                left_side_bearing,
+            };
+            last_advance_width = advance_width;
+            result.insert(GlyphId::new(index), long_hor_matrics);
+        });
 
         // If num_of_long_hor_metrics is less than the total number of glyphs,
         // then the h_metrics array is followed by an array for the left side
         // bearing values of the remaining glyphs.
-        (0..maxp_table.num_glyphs - hhea_table.num_of_long_hor_metrics)
-            .into_iter()
-            .for_each(|index| {
-                let left_side_bearing: i16 = reader.read_i16();
-                let long_hor_matrics = LongHorMetric {
-                    advance_width: last_advance_width,
-                    left_side_bearing,
-                };
-                let index = hhea_table.num_of_long_hor_metrics + index;
-                result.insert(GlyphId::new(index), long_hor_matrics);
-            });
+        (0..maxp_table.num_glyphs - hhea_table.num_of_long_hor_metrics).for_each(|index| {
+            let left_side_bearing: i16 = reader.read_i16();
+            let long_hor_matrics = LongHorMetric {
+                advance_width: last_advance_width,
+                left_side_bearing,
+            };
+            let index = hhea_table.num_of_long_hor_metrics + index;
+            result.insert(GlyphId::new(index), long_hor_matrics);
+        });
 
         LongHorMetricLookup(result)
     }
@@ -0,0 +1,64 @@
+use crate::item_variation_store::{DeltaSetIndexMap, ItemVariationStore};
+use crate::model::GlyphId;
+use crate::reader::Reader;
+
+// Horizontal Metrics Variations table, deltas for the hmtx advances and side bearings
+#[allow(unused)]
+#[derive(Debug)]
+pub struct HvarTable {
+    major_version: u16,
+    minor_version: u16,
+    item_variation_store: ItemVariationStore,
+    advance_width_mapping: Option<DeltaSetIndexMap>,
+    lsb_mapping: Option<DeltaSetIndexMap>,
+    rsb_mapping: Option<DeltaSetIndexMap>,
+}
+
+impl HvarTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, offset: u32) -> HvarTable {
+        reader.seek_from_start(offset);
+        let major_version = reader.read_u16();
+        let minor_version = reader.read_u16();
+        let item_variation_store_offset = reader.read_u32();
+        let advance_width_mapping_offset = reader.read_u32();
+        let lsb_mapping_offset = reader.read_u32();
+        let rsb_mapping_offset = reader.read_u32();
+
+        let item_variation_store =
+            ItemVariationStore::from_file(reader, offset + item_variation_store_offset);
+        let advance_width_mapping =
+            DeltaSetIndexMap::from_optional_offset(reader, offset, advance_width_mapping_offset);
+        let lsb_mapping =
+            DeltaSetIndexMap::from_optional_offset(reader, offset, lsb_mapping_offset);
+        let rsb_mapping =
+            DeltaSetIndexMap::from_optional_offset(reader, offset, rsb_mapping_offset);
+
+        HvarTable {
+            major_version,
+            minor_version,
+            item_variation_store,
+            advance_width_mapping,
+            lsb_mapping,
+            rsb_mapping,
+        }
+    }
+
+    // Without a mapping, advance deltas are indexed directly by glyph id
+    pub fn advance_width_delta(&self, glyph_id: GlyphId, coordinates: &[f32]) -> f32 {
+        let (outer_index, inner_index) = match &self.advance_width_mapping {
+            Some(mapping) => mapping.get(glyph_id.id() as u32),
+            None => (0, glyph_id.id()),
+        };
+        self.item_variation_store
+            .delta(outer_index, inner_index, coordinates)
+    }
+
+    // None when the side bearing variations are left to the glyph outline variations
+    pub fn left_side_bearing_delta(&self, glyph_id: GlyphId, coordinates: &[f32]) -> Option<f32> {
+        self.lsb_mapping.as_ref().map(|mapping| {
+            let (outer_index, inner_index) = mapping.get(glyph_id.id() as u32);
+            self.item_variation_store
+                .delta(outer_index, inner_index, coordinates)
+        })
+    }
+}
@@ -0,0 +1,27 @@
+use crate::model::GlyphId;
+use crate::reader::Reader;
+
+// Linear threshold, the size from which each glyph scales linearly, its
+// hinted advance width no longer deviating from the scaled one
+#[allow(unused)]
+#[derive(Debug)]
+pub struct LtshTable {
+    version: u16,
+    pub y_pels: Vec<u8>, // 1 when the glyph always scales linearly
+}
+
+impl LtshTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, offset: u32) -> LtshTable {
+        reader.seek_from_start(offset);
+        let version = reader.read_u16();
+        let num_glyphs = reader.read_u16();
+        LtshTable {
+            version,
+            y_pels: reader.read_bytes(num_glyphs as usize),
+        }
+    }
+
+    pub fn y_pel(&self, glyph_id: GlyphId) -> Option<u8> {
+        self.y_pels.get(glyph_id.id() as usize).copied()
+    }
+}
@@ -1,5 +1,7 @@
+use crate::font_builder::FontTable;
 use crate::model::Fixed;
 use crate::reader::Reader;
+use crate::writer::Writer;
 
 #[allow(unused)]
 #[derive(Debug)]
@@ -11,11 +13,11 @@ pub struct MaximumProfileTable {
     max_component_points: u16,     // points in compound glyph
     max_component_contours: u16,   // contours in compound glyph
     max_zones: u16,                // set to 2
-    max_twilight_points: u16,      // points used in Twilight Zone (Z0)
-    max_storage: u16,              // number of Storage Area locations
-    max_function_defs: u16,        // number of FDEFs
-    max_instruction_defs: u16,     // number of IDEFs
-    max_stack_elements: u16,       // maximum stack depth
+    pub max_twilight_points: u16,  // points used in Twilight Zone (Z0)
+    pub max_storage: u16,          // number of Storage Area locations
+    pub max_function_defs: u16,    // number of FDEFs
+    pub max_instruction_defs: u16, // number of IDEFs
+    pub max_stack_elements: u16,   // maximum stack depth
     max_size_of_instructions: u16, // byte count for glyph instructions
     max_component_elements: u16,   // number of glyphs referenced at top level
     max_component_depth: u16,      // levels of recursion, set to 0 if font has only simple glyphs
@@ -26,19 +28,27 @@ impl MaximumProfileTable {
         reader.seek_from_start(offset);
         let version: Fixed = reader.read_fixed();
         let num_glyphs: u16 = reader.read_u16();
-        let max_points: u16 = reader.read_u16();
-        let max_contours: u16 = reader.read_u16();
-        let max_component_points: u16 = reader.read_u16();
-        let max_component_contours: u16 = reader.read_u16();
-        let max_zones: u16 = reader.read_u16();
-        let max_twilight_points: u16 = reader.read_u16();
-        let max_storage: u16 = reader.read_u16();
-        let max_function_defs: u16 = reader.read_u16();
-        let max_instruction_defs: u16 = reader.read_u16();
-        let max_stack_elements: u16 = reader.read_u16();
-        let max_size_of_instructions: u16 = reader.read_u16();
-        let max_component_elements: u16 = reader.read_u16();
-        let max_component_depth: u16 = reader.read_u16();
+        // Version 0.5, used with CFF outlines, stops after num_glyphs
+        let mut read_u16 = || {
+            if version.major >= 1 {
+                reader.read_u16()
+            } else {
+                0
+            }
+        };
+        let max_points: u16 = read_u16();
+        let max_contours: u16 = read_u16();
+        let max_component_points: u16 = read_u16();
+        let max_component_contours: u16 = read_u16();
+        let max_zones: u16 = read_u16();
+        let max_twilight_points: u16 = read_u16();
+        let max_storage: u16 = read_u16();
+        let max_function_defs: u16 = read_u16();
+        let max_instruction_defs: u16 = read_u16();
+        let max_stack_elements: u16 = read_u16();
+        let max_size_of_instructions: u16 = read_u16();
+        let max_component_elements: u16 = read_u16();
+        let max_component_depth: u16 = read_u16();
         MaximumProfileTable {
             version,
             num_glyphs,
@@ -58,3 +68,36 @@ impl MaximumProfileTable {
         }
     }
 }
+
+impl FontTable for MaximumProfileTable {
+    fn tag(&self) -> &'static str {
+        "maxp"
+    }
+
+    // Version 0.5, used with CFF outlines, stops after num_glyphs
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer.write_fixed(&self.version);
+        writer.write_u16(self.num_glyphs);
+        if self.version.major >= 1 {
+            [
+                self.max_points,
+                self.max_contours,
+                self.max_component_points,
+                self.max_component_contours,
+                self.max_zones,
+                self.max_twilight_points,
+                self.max_storage,
+                self.max_function_defs,
+                self.max_instruction_defs,
+                self.max_stack_elements,
+                self.max_size_of_instructions,
+                self.max_component_elements,
+                self.max_component_depth,
+            ]
+            .iter()
+            .for_each(|value| writer.write_u16(*value));
+        }
+        writer.into_vec()
+    }
+}
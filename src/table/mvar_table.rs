@@ -0,0 +1,75 @@
+use crate::item_variation_store::ItemVariationStore;
+use crate::reader::Reader;
+
+#[derive(Debug)]
+pub struct ValueRecord {
+    pub value_tag: String, // 'hasc', 'xhgt', 'undo', ...
+    pub delta_set_outer_index: u16,
+    pub delta_set_inner_index: u16,
+}
+
+// Metrics Variations table, deltas for font wide metrics found in OS/2, hhea, post and gasp
+#[allow(unused)]
+#[derive(Debug)]
+pub struct MvarTable {
+    major_version: u16,
+    minor_version: u16,
+    pub value_records: Vec<ValueRecord>,
+    item_variation_store: Option<ItemVariationStore>,
+}
+
+impl MvarTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, offset: u32) -> MvarTable {
+        reader.seek_from_start(offset);
+        let major_version = reader.read_u16();
+        let minor_version = reader.read_u16();
+        let _reserved = reader.read_u16();
+        let value_record_size = reader.read_u16();
+        let value_record_count = reader.read_u16();
+        let item_variation_store_offset = reader.read_u16();
+
+        let value_records: Vec<ValueRecord> = (0..value_record_count)
+            .map(|index| {
+                reader.seek_from_start(offset + 12 + index as u32 * value_record_size as u32);
+                ValueRecord {
+                    value_tag: reader.read_table_name(),
+                    delta_set_outer_index: reader.read_u16(),
+                    delta_set_inner_index: reader.read_u16(),
+                }
+            })
+            .collect();
+
+        let item_variation_store = if item_variation_store_offset == 0 {
+            None
+        } else {
+            Some(ItemVariationStore::from_file(
+                reader,
+                offset + item_variation_store_offset as u32,
+            ))
+        };
+
+        MvarTable {
+            major_version,
+            minor_version,
+            value_records,
+            item_variation_store,
+        }
+    }
+
+    // 0 for tags without a value record
+    pub fn delta(&self, value_tag: &str, coordinates: &[f32]) -> f32 {
+        let value_record = self
+            .value_records
+            .iter()
+            .find(|value_record| value_record.value_tag == value_tag);
+
+        match (value_record, &self.item_variation_store) {
+            (Some(value_record), Some(item_variation_store)) => item_variation_store.delta(
+                value_record.delta_set_outer_index,
+                value_record.delta_set_inner_index,
+                coordinates,
+            ),
+            _ => 0.0,
+        }
+    }
+}
@@ -39,17 +39,19 @@ impl NameId {
     }
 }
 
-pub fn read_name(reader: &mut Box<dyn Reader>, name_table: &TableDirectory) {
+fn read_name_records(
+    reader: &mut Box<dyn Reader>,
+    name_table: &TableDirectory,
+) -> (u16, Vec<NameRecord>) {
     reader.seek_from_start(name_table.offset);
 
     let format = reader.read_u16();
     let count = reader.read_u16();
     let string_offset = reader.read_u16();
 
-    assert!(0 == format, "format of name table should be 0");
+    assert!(format <= 1, "format of name table should be 0 or 1");
 
     let name_records: Vec<NameRecord> = (0..count)
-        .into_iter()
         .map(|_| {
             let platform_id = reader.read_platform_id();
             let platform_specific_id = reader.read_u16();
@@ -70,20 +72,79 @@ pub fn read_name(reader: &mut Box<dyn Reader>, name_table: &TableDirectory) {
         })
         .collect();
 
+    (string_offset, name_records)
+}
+
+fn read_record_string(
+    reader: &mut Box<dyn Reader>,
+    name_table: &TableDirectory,
+    string_offset: u16,
+    nr: &NameRecord,
+) -> String {
+    reader.seek_from_start(name_table.offset);
+    reader.seek_from_current(string_offset as i32 + nr.offset as i32);
+
+    if nr.platform_id == PlatformId::Macintosh {
+        reader.read_string(nr.length)
+    } else {
+        reader.read_utf_16be(nr.length)
+    }
+}
+
+// Looks up a name, preferring the English Windows record, then Unicode and
+// finally Macintosh Roman records.
+pub fn find_name(
+    reader: &mut Box<dyn Reader>,
+    name_table: &TableDirectory,
+    name_id: u16,
+) -> Option<String> {
+    let (string_offset, name_records) = read_name_records(reader, name_table);
+
+    let candidates: Vec<&NameRecord> = name_records
+        .iter()
+        .filter(|nr| nr.name_id.0 == name_id)
+        .collect();
+
+    let preferred = candidates
+        .iter()
+        .find(|nr| nr.platform_id == PlatformId::Microsoft && nr.language_id == 1033)
+        .or_else(|| {
+            candidates
+                .iter()
+                .find(|nr| nr.platform_id == PlatformId::Microsoft)
+        })
+        .or_else(|| {
+            candidates
+                .iter()
+                .find(|nr| nr.platform_id == PlatformId::Unicode)
+        })
+        .or_else(|| {
+            candidates.iter().find(|nr| {
+                nr.platform_id == PlatformId::Macintosh
+                    && nr.platform_specific_id == 0
+                    && nr.language_id == 0
+            })
+        })?;
+
+    Some(read_record_string(
+        reader,
+        name_table,
+        string_offset,
+        preferred,
+    ))
+}
+
+pub fn read_name(reader: &mut Box<dyn Reader>, name_table: &TableDirectory) {
+    let (string_offset, name_records) = read_name_records(reader, name_table);
+
     name_records.iter().for_each(|nr| {
         if nr.platform_id == PlatformId::Macintosh && nr.language_id == 0 {
-            reader.seek_from_start(name_table.offset);
-            reader.seek_from_current((string_offset + nr.offset) as i32);
-
-            let str_value = reader.read_string(nr.length);
+            let str_value = read_record_string(reader, name_table, string_offset, nr);
             let info = nr.name_id.info();
 
             println!("[Macintosh] {}: {}", info, str_value);
         } else if nr.platform_id == PlatformId::Microsoft && nr.language_id == 1033 {
-            reader.seek_from_start(name_table.offset);
-            reader.seek_from_current((string_offset + nr.offset) as i32);
-
-            let str_value = reader.read_utf_16be(nr.length);
+            let str_value = read_record_string(reader, name_table, string_offset, nr);
             let info = nr.name_id.info();
             println!("[Microsolf] {}: {}", info, str_value);
         }
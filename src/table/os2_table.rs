@@ -0,0 +1,169 @@
+use crate::font_directory::TableDirectory;
+use crate::model::FWord;
+use crate::reader::Reader;
+
+#[allow(unused)]
+#[derive(Debug)]
+pub struct Os2Table {
+    pub version: u16,
+    pub x_avg_char_width: FWord,
+    pub us_weight_class: u16,
+    pub us_width_class: u16,
+    pub fs_type: u16,
+    pub y_subscript_x_size: FWord,
+    pub y_subscript_y_size: FWord,
+    pub y_subscript_x_offset: FWord,
+    pub y_subscript_y_offset: FWord,
+    pub y_superscript_x_size: FWord,
+    pub y_superscript_y_size: FWord,
+    pub y_superscript_x_offset: FWord,
+    pub y_superscript_y_offset: FWord,
+    pub y_strikeout_size: FWord,
+    pub y_strikeout_position: FWord,
+    pub s_family_class: i16,
+    pub panose: [u8; 10],
+    pub ul_unicode_range: [u32; 4],
+    pub ach_vend_id: String,
+    pub fs_selection: u16,
+    pub us_first_char_index: u16,
+    pub us_last_char_index: u16,
+    // Apple's version 0 tables can end before the typographic metrics
+    pub typo_metrics: Option<TypoMetrics>,
+    pub ul_code_page_range: Option<[u32; 2]>, // version 1
+    pub x_height: Option<FWord>,              // version 2
+    pub cap_height: Option<FWord>,            // version 2
+    pub us_default_char: Option<u16>,         // version 2
+    pub us_break_char: Option<u16>,           // version 2
+    pub us_max_context: Option<u16>,          // version 2
+    pub us_lower_optical_point_size: Option<u16>, // version 5
+    pub us_upper_optical_point_size: Option<u16>, // version 5
+}
+
+#[derive(Debug)]
+pub struct TypoMetrics {
+    pub s_typo_ascender: FWord,
+    pub s_typo_descender: FWord,
+    pub s_typo_line_gap: FWord,
+    pub us_win_ascent: u16,
+    pub us_win_descent: u16,
+}
+
+impl Os2Table {
+    // fsSelection bit 7: use sTypoAscender, sTypoDescender and sTypoLineGap for line metrics
+    pub const USE_TYPO_METRICS: u16 = 1 << 7;
+
+    pub fn from_file(reader: &mut Box<dyn Reader>, os2_table: &TableDirectory) -> Os2Table {
+        reader.seek_from_start(os2_table.offset);
+
+        let version = reader.read_u16();
+        let x_avg_char_width = reader.read_fword();
+        let us_weight_class = reader.read_u16();
+        let us_width_class = reader.read_u16();
+        let fs_type = reader.read_u16();
+        let y_subscript_x_size = reader.read_fword();
+        let y_subscript_y_size = reader.read_fword();
+        let y_subscript_x_offset = reader.read_fword();
+        let y_subscript_y_offset = reader.read_fword();
+        let y_superscript_x_size = reader.read_fword();
+        let y_superscript_y_size = reader.read_fword();
+        let y_superscript_x_offset = reader.read_fword();
+        let y_superscript_y_offset = reader.read_fword();
+        let y_strikeout_size = reader.read_fword();
+        let y_strikeout_position = reader.read_fword();
+        let s_family_class = reader.read_i16();
+        let mut panose = [0; 10];
+        panose.iter_mut().for_each(|p| *p = reader.read_u8());
+        let mut ul_unicode_range = [0; 4];
+        ul_unicode_range
+            .iter_mut()
+            .for_each(|r| *r = reader.read_u32());
+        let ach_vend_id = reader.read_table_name();
+        let fs_selection = reader.read_u16();
+        let us_first_char_index = reader.read_u16();
+        let us_last_char_index = reader.read_u16();
+
+        let length = os2_table.length;
+
+        let typo_metrics = if length >= 78 {
+            Some(TypoMetrics {
+                s_typo_ascender: reader.read_fword(),
+                s_typo_descender: reader.read_fword(),
+                s_typo_line_gap: reader.read_fword(),
+                us_win_ascent: reader.read_u16(),
+                us_win_descent: reader.read_u16(),
+            })
+        } else {
+            None
+        };
+
+        let ul_code_page_range = if version >= 1 && length >= 86 {
+            Some([reader.read_u32(), reader.read_u32()])
+        } else {
+            None
+        };
+
+        let has_version_2 = version >= 2 && length >= 96;
+        let read_fword = |reader: &mut Box<dyn Reader>, present: bool| {
+            if present {
+                Some(reader.read_fword())
+            } else {
+                None
+            }
+        };
+        let read_u16 = |reader: &mut Box<dyn Reader>, present: bool| {
+            if present {
+                Some(reader.read_u16())
+            } else {
+                None
+            }
+        };
+
+        let x_height = read_fword(reader, has_version_2);
+        let cap_height = read_fword(reader, has_version_2);
+        let us_default_char = read_u16(reader, has_version_2);
+        let us_break_char = read_u16(reader, has_version_2);
+        let us_max_context = read_u16(reader, has_version_2);
+
+        let has_version_5 = version >= 5 && length >= 100;
+        let us_lower_optical_point_size = read_u16(reader, has_version_5);
+        let us_upper_optical_point_size = read_u16(reader, has_version_5);
+
+        Os2Table {
+            version,
+            x_avg_char_width,
+            us_weight_class,
+            us_width_class,
+            fs_type,
+            y_subscript_x_size,
+            y_subscript_y_size,
+            y_subscript_x_offset,
+            y_subscript_y_offset,
+            y_superscript_x_size,
+            y_superscript_y_size,
+            y_superscript_x_offset,
+            y_superscript_y_offset,
+            y_strikeout_size,
+            y_strikeout_position,
+            s_family_class,
+            panose,
+            ul_unicode_range,
+            ach_vend_id,
+            fs_selection,
+            us_first_char_index,
+            us_last_char_index,
+            typo_metrics,
+            ul_code_page_range,
+            x_height,
+            cap_height,
+            us_default_char,
+            us_break_char,
+            us_max_context,
+            us_lower_optical_point_size,
+            us_upper_optical_point_size,
+        }
+    }
+
+    pub fn use_typo_metrics(&self) -> bool {
+        self.fs_selection & Self::USE_TYPO_METRICS == Self::USE_TYPO_METRICS
+    }
+}
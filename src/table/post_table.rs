@@ -0,0 +1,34 @@
+use crate::model::{FWord, Fixed};
+use crate::reader::Reader;
+
+// PostScript table header, the glyph names that may follow are not read
+#[allow(unused)]
+#[derive(Debug)]
+pub struct PostTable {
+    version: Fixed,
+    pub italic_angle: Fixed,
+    pub underline_position: FWord, // top of the underline, usually negative
+    pub underline_thickness: FWord,
+    pub is_fixed_pitch: u32, // 0 for proportionally spaced fonts
+    min_mem_type_42: u32,
+    max_mem_type_42: u32,
+    min_mem_type_1: u32,
+    max_mem_type_1: u32,
+}
+
+impl PostTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, offset: u32) -> PostTable {
+        reader.seek_from_start(offset);
+        PostTable {
+            version: reader.read_fixed(),
+            italic_angle: reader.read_fixed(),
+            underline_position: reader.read_fword(),
+            underline_thickness: reader.read_fword(),
+            is_fixed_pitch: reader.read_u32(),
+            min_mem_type_42: reader.read_u32(),
+            max_mem_type_42: reader.read_u32(),
+            min_mem_type_1: reader.read_u32(),
+            max_mem_type_1: reader.read_u32(),
+        }
+    }
+}
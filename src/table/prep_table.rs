@@ -0,0 +1,18 @@
+use crate::font_directory::TableDirectory;
+use crate::reader::Reader;
+
+// Control value program, TrueType instructions run whenever the size changes
+// to adjust the CVT and set the graphics state of the glyph programs
+#[derive(Debug)]
+pub struct PrepTable {
+    pub instructions: Vec<u8>,
+}
+
+impl PrepTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, table_directory: &TableDirectory) -> PrepTable {
+        reader.seek_from_start(table_directory.offset);
+        PrepTable {
+            instructions: reader.read_bytes(table_directory.length as usize),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::model::GlyphId;
+use crate::reader::Reader;
+
+const DUPE: &str = "dupe";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SbixImage {
+    pub ppem: u16,
+    pub ppi: u16,
+    pub origin_offset_x: i16, // of the bottom left corner, in pixels
+    pub origin_offset_y: i16,
+    pub graphic_type: String, // "png ", "jpg ", "tiff" or "mask"
+    pub data: Vec<u8>,
+}
+
+#[allow(unused)]
+#[derive(Debug)]
+pub struct SbixStrike {
+    pub ppem: u16,
+    pub ppi: u16,
+    glyph_data_offsets: Vec<u32>, // from the start of the font, one past the last glyph
+}
+
+impl SbixStrike {
+    // Offset and length of the glyph data, None when the glyph has none
+    fn glyph_data(&self, glyph_id: GlyphId) -> Option<(u32, u32)> {
+        let index = glyph_id.id() as usize;
+        let start = *self.glyph_data_offsets.get(index)?;
+        let end = *self.glyph_data_offsets.get(index + 1)?;
+        if end > start {
+            Some((start, end - start))
+        } else {
+            None
+        }
+    }
+}
+
+// Standard bitmap graphics table of Apple color fonts
+#[allow(unused)]
+#[derive(Debug)]
+pub struct SbixTable {
+    pub version: u16,
+    pub flags: u16, // bit 1 set to draw the outlines over the images
+    pub strikes: Vec<SbixStrike>,
+}
+
+impl SbixTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, offset: u32, num_glyphs: u16) -> SbixTable {
+        reader.seek_from_start(offset);
+        let version = reader.read_u16();
+        let flags = reader.read_u16();
+        let num_strikes = reader.read_u32();
+        let strike_offsets: Vec<u32> = (0..num_strikes).map(|_| reader.read_u32()).collect();
+
+        let strikes = strike_offsets
+            .iter()
+            .map(|strike_offset| {
+                let strike_offset = offset + strike_offset;
+                reader.seek_from_start(strike_offset);
+                SbixStrike {
+                    ppem: reader.read_u16(),
+                    ppi: reader.read_u16(),
+                    glyph_data_offsets: (0..=num_glyphs)
+                        .map(|_| strike_offset + reader.read_u32())
+                        .collect(),
+                }
+            })
+            .collect();
+
+        SbixTable {
+            version,
+            flags,
+            strikes,
+        }
+    }
+
+    // The image of the strike of `ppem` if it has the glyph, otherwise of the
+    // smallest larger one, otherwise of the largest smaller one. `dupe`
+    // records are replaced by the image of the glyph they refer to.
+    pub fn image(
+        &self,
+        reader: &mut Box<dyn Reader>,
+        glyph_id: GlyphId,
+        ppem: u16,
+    ) -> Option<SbixImage> {
+        let mut strikes: Vec<&SbixStrike> = self
+            .strikes
+            .iter()
+            .filter(|strike| strike.glyph_data(glyph_id).is_some())
+            .collect();
+        strikes.sort_by_key(|strike| strike.ppem);
+        let strike = match strikes.iter().position(|strike| strike.ppem >= ppem) {
+            Some(index) => strikes[index],
+            None => strikes.pop()?,
+        };
+
+        // A chain of duplicates is at most as long as the strike, longer ones loop
+        let mut glyph_id = glyph_id;
+        for _ in 0..strike.glyph_data_offsets.len() {
+            let (offset, length) = strike.glyph_data(glyph_id)?;
+            if length < 8 {
+                return None;
+            }
+            reader.seek_from_start(offset);
+            let origin_offset_x = reader.read_i16();
+            let origin_offset_y = reader.read_i16();
+            let graphic_type = reader.read_table_name();
+            if graphic_type == DUPE {
+                glyph_id = GlyphId::new(reader.read_u16());
+                continue;
+            }
+            return Some(SbixImage {
+                ppem: strike.ppem,
+                ppi: strike.ppi,
+                origin_offset_x,
+                origin_offset_y,
+                graphic_type,
+                data: reader.read_bytes(length as usize - 8),
+            });
+        }
+        None
+    }
+}
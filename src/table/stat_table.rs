@@ -0,0 +1,262 @@
+use crate::reader::Reader;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AxisRecord {
+    pub axis_tag: String,
+    pub axis_name_id: u16,
+    pub axis_ordering: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AxisValue {
+    // A single value on one axis
+    Format1 {
+        axis_index: u16,
+        flags: u16,
+        value_name_id: u16,
+        value: f32,
+    },
+    // A range of values on one axis
+    Format2 {
+        axis_index: u16,
+        flags: u16,
+        value_name_id: u16,
+        nominal_value: f32,
+        range_min_value: f32,
+        range_max_value: f32,
+    },
+    // A single value with a style linked to it, like Regular and Bold
+    Format3 {
+        axis_index: u16,
+        flags: u16,
+        value_name_id: u16,
+        value: f32,
+        linked_value: f32,
+    },
+    // A combination of values on several axes
+    Format4 {
+        flags: u16,
+        value_name_id: u16,
+        axis_values: Vec<(u16, f32)>,
+    },
+}
+
+impl AxisValue {
+    // The name of this value can be left out when composing a style name
+    pub const ELIDABLE_AXIS_VALUE_NAME: u16 = 0x0002;
+
+    pub fn flags(&self) -> u16 {
+        match self {
+            AxisValue::Format1 { flags, .. } => *flags,
+            AxisValue::Format2 { flags, .. } => *flags,
+            AxisValue::Format3 { flags, .. } => *flags,
+            AxisValue::Format4 { flags, .. } => *flags,
+        }
+    }
+
+    pub fn value_name_id(&self) -> u16 {
+        match self {
+            AxisValue::Format1 { value_name_id, .. } => *value_name_id,
+            AxisValue::Format2 { value_name_id, .. } => *value_name_id,
+            AxisValue::Format3 { value_name_id, .. } => *value_name_id,
+            AxisValue::Format4 { value_name_id, .. } => *value_name_id,
+        }
+    }
+
+    pub fn is_elidable(&self) -> bool {
+        self.flags() & Self::ELIDABLE_AXIS_VALUE_NAME == Self::ELIDABLE_AXIS_VALUE_NAME
+    }
+
+    // Whether the value applies to `value` on the design axis `axis_index`
+    fn matches(&self, axis_index: u16, value: f32) -> bool {
+        match self {
+            AxisValue::Format1 {
+                axis_index: index,
+                value: v,
+                ..
+            }
+            | AxisValue::Format3 {
+                axis_index: index,
+                value: v,
+                ..
+            } => *index == axis_index && *v == value,
+            AxisValue::Format2 {
+                axis_index: index,
+                range_min_value,
+                range_max_value,
+                ..
+            } => *index == axis_index && *range_min_value <= value && value <= *range_max_value,
+            AxisValue::Format4 { .. } => false,
+        }
+    }
+}
+
+// Style Attributes table, names the values of the design axes
+#[allow(unused)]
+#[derive(Debug)]
+pub struct StatTable {
+    major_version: u16,
+    minor_version: u16,
+    pub design_axes: Vec<AxisRecord>,
+    pub axis_values: Vec<AxisValue>,
+    pub elided_fallback_name_id: Option<u16>, // version 1.1 and later
+}
+
+impl StatTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, offset: u32) -> StatTable {
+        reader.seek_from_start(offset);
+        let major_version = reader.read_u16();
+        let minor_version = reader.read_u16();
+        let design_axis_size = reader.read_u16();
+        let design_axis_count = reader.read_u16();
+        let design_axes_offset = reader.read_u32();
+        let axis_value_count = reader.read_u16();
+        let offset_to_axis_value_offsets = reader.read_u32();
+        let elided_fallback_name_id = if minor_version >= 1 {
+            Some(reader.read_u16())
+        } else {
+            None
+        };
+
+        let design_axes: Vec<AxisRecord> = (0..design_axis_count)
+            .map(|index| {
+                reader.seek_from_start(
+                    offset + design_axes_offset + index as u32 * design_axis_size as u32,
+                );
+                AxisRecord {
+                    axis_tag: reader.read_table_name(),
+                    axis_name_id: reader.read_u16(),
+                    axis_ordering: reader.read_u16(),
+                }
+            })
+            .collect();
+
+        let axis_value_offsets_start = offset + offset_to_axis_value_offsets;
+        reader.seek_from_start(axis_value_offsets_start);
+        let axis_value_offsets: Vec<u16> =
+            (0..axis_value_count).map(|_| reader.read_u16()).collect();
+
+        let axis_values: Vec<AxisValue> = axis_value_offsets
+            .iter()
+            .filter_map(|axis_value_offset| {
+                reader.seek_from_start(axis_value_offsets_start + *axis_value_offset as u32);
+                Self::read_axis_value(reader)
+            })
+            .collect();
+
+        StatTable {
+            major_version,
+            minor_version,
+            design_axes,
+            axis_values,
+            elided_fallback_name_id,
+        }
+    }
+
+    fn read_axis_value(reader: &mut Box<dyn Reader>) -> Option<AxisValue> {
+        let format = reader.read_u16();
+        match format {
+            1 => Some(AxisValue::Format1 {
+                axis_index: reader.read_u16(),
+                flags: reader.read_u16(),
+                value_name_id: reader.read_u16(),
+                value: reader.read_fixed().to_f32(),
+            }),
+            2 => Some(AxisValue::Format2 {
+                axis_index: reader.read_u16(),
+                flags: reader.read_u16(),
+                value_name_id: reader.read_u16(),
+                nominal_value: reader.read_fixed().to_f32(),
+                range_min_value: reader.read_fixed().to_f32(),
+                range_max_value: reader.read_fixed().to_f32(),
+            }),
+            3 => Some(AxisValue::Format3 {
+                axis_index: reader.read_u16(),
+                flags: reader.read_u16(),
+                value_name_id: reader.read_u16(),
+                value: reader.read_fixed().to_f32(),
+                linked_value: reader.read_fixed().to_f32(),
+            }),
+            4 => {
+                let axis_count = reader.read_u16();
+                let flags = reader.read_u16();
+                let value_name_id = reader.read_u16();
+                let axis_values = (0..axis_count)
+                    .map(|_| (reader.read_u16(), reader.read_fixed().to_f32()))
+                    .collect();
+                Some(AxisValue::Format4 {
+                    flags,
+                    value_name_id,
+                    axis_values,
+                })
+            }
+            // Unknown formats must be ignored
+            _ => None,
+        }
+    }
+
+    // Name ids composing the style name of the design space location given as
+    // (axis tag, value) pairs, in axis ordering, elidable names left out.
+    pub fn style_name_ids(&self, location: &[(String, f32)]) -> Vec<u16> {
+        let value_of = |axis_index: u16| -> Option<f32> {
+            let axis = self.design_axes.get(axis_index as usize)?;
+            location
+                .iter()
+                .find(|(tag, _)| *tag == axis.axis_tag)
+                .map(|(_, value)| *value)
+        };
+
+        let mut named: Vec<(u16, &AxisValue)> = vec![];
+        let mut covered: Vec<u16> = vec![];
+
+        // Values combining several axes take precedence over single axis values
+        self.axis_values.iter().for_each(|axis_value| {
+            if let AxisValue::Format4 { axis_values, .. } = axis_value {
+                let all_match = axis_values
+                    .iter()
+                    .all(|(axis_index, value)| value_of(*axis_index) == Some(*value));
+                let overlaps = axis_values
+                    .iter()
+                    .any(|(axis_index, _)| covered.contains(axis_index));
+                if all_match && !overlaps && !axis_values.is_empty() {
+                    covered.extend(axis_values.iter().map(|(axis_index, _)| *axis_index));
+                    let first_axis = axis_values.iter().map(|(i, _)| *i).min().unwrap_or(0);
+                    named.push((first_axis, axis_value));
+                }
+            }
+        });
+
+        (0..self.design_axes.len() as u16)
+            .filter(|axis_index| !covered.contains(axis_index))
+            .for_each(|axis_index| {
+                if let Some(value) = value_of(axis_index) {
+                    if let Some(axis_value) = self
+                        .axis_values
+                        .iter()
+                        .find(|axis_value| axis_value.matches(axis_index, value))
+                    {
+                        named.push((axis_index, axis_value));
+                    }
+                }
+            });
+
+        named.sort_by_key(|(axis_index, _)| {
+            self.design_axes
+                .get(*axis_index as usize)
+                .map_or(u16::MAX, |axis| axis.axis_ordering)
+        });
+
+        let name_ids: Vec<u16> = named
+            .iter()
+            .filter(|(_, axis_value)| !axis_value.is_elidable())
+            .map(|(_, axis_value)| axis_value.value_name_id())
+            .collect();
+
+        if name_ids.is_empty() {
+            // Version 1.0 tables fall back to the font subfamily name
+            vec![self.elided_fallback_name_id.unwrap_or(2)]
+        } else {
+            name_ids
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use crate::model::GlyphId;
+use crate::reader::Reader;
+
+// SVG glyph table, each document holds the glyphs of a range as elements with
+// id "glyph<glyph id>"
+#[allow(unused)]
+#[derive(Debug)]
+pub struct SvgTable {
+    pub version: u16,
+    document_records: Vec<(u16, u16, u32, u32)>, // first and last glyph, offset from the start of the font and length
+}
+
+impl SvgTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, offset: u32) -> SvgTable {
+        reader.seek_from_start(offset);
+        let version = reader.read_u16();
+        let document_list_offset = offset + reader.read_u32();
+
+        reader.seek_from_start(document_list_offset);
+        let num_entries = reader.read_u16();
+        let document_records = (0..num_entries)
+            .map(|_| {
+                let start_glyph_id = reader.read_u16();
+                let end_glyph_id = reader.read_u16();
+                let document_offset = reader.read_u32();
+                let document_length = reader.read_u32();
+                (
+                    start_glyph_id,
+                    end_glyph_id,
+                    document_list_offset + document_offset,
+                    document_length,
+                )
+            })
+            .collect();
+
+        SvgTable {
+            version,
+            document_records,
+        }
+    }
+
+    // Offset and length of the document with the glyph, the records are sorted
+    pub fn document_location(&self, glyph_id: GlyphId) -> Option<(u32, u32)> {
+        let id = glyph_id.id();
+        let index = self
+            .document_records
+            .partition_point(|(_, end_glyph_id, _, _)| *end_glyph_id < id);
+        self.document_records
+            .get(index)
+            .filter(|(start_glyph_id, _, _, _)| *start_glyph_id <= id)
+            .map(|(_, _, offset, length)| (*offset, *length))
+    }
+}
@@ -0,0 +1,97 @@
+use crate::reader::Reader;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct VdmxRecord {
+    pub y_pel_height: u16,
+    pub y_max: i16, // hinted extents over all glyphs, in pixels
+    pub y_min: i16,
+}
+
+// Aspect ratios x_ratio:y_start_ratio to x_ratio:y_end_ratio, all of them
+// when x_ratio is 0, with the records of their group
+#[derive(Debug)]
+pub struct VdmxRatio {
+    pub char_set: u8,
+    pub x_ratio: u8,
+    pub y_start_ratio: u8,
+    pub y_end_ratio: u8,
+    pub records: Vec<VdmxRecord>, // sorted by y_pel_height
+}
+
+// Vertical device metrics, the hinted ascent and descent at each size
+#[allow(unused)]
+#[derive(Debug)]
+pub struct VdmxTable {
+    version: u16,
+    pub ratios: Vec<VdmxRatio>,
+}
+
+impl VdmxTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, offset: u32) -> VdmxTable {
+        reader.seek_from_start(offset);
+        let version = reader.read_u16();
+        let _num_recs = reader.read_u16();
+        let num_ratios = reader.read_u16();
+        let ratios: Vec<(u8, u8, u8, u8)> = (0..num_ratios)
+            .map(|_| {
+                (
+                    reader.read_u8(),
+                    reader.read_u8(),
+                    reader.read_u8(),
+                    reader.read_u8(),
+                )
+            })
+            .collect();
+        let group_offsets: Vec<u16> = (0..num_ratios).map(|_| reader.read_u16()).collect();
+
+        // Ratios may share a group
+        let ratios = ratios
+            .into_iter()
+            .zip(group_offsets)
+            .map(
+                |((char_set, x_ratio, y_start_ratio, y_end_ratio), group_offset)| {
+                    reader.seek_from_start(offset + group_offset as u32);
+                    let recs = reader.read_u16();
+                    let _startsz = reader.read_u8();
+                    let _endsz = reader.read_u8();
+                    let records = (0..recs)
+                        .map(|_| VdmxRecord {
+                            y_pel_height: reader.read_u16(),
+                            y_max: reader.read_i16(),
+                            y_min: reader.read_i16(),
+                        })
+                        .collect();
+                    VdmxRatio {
+                        char_set,
+                        x_ratio,
+                        y_start_ratio,
+                        y_end_ratio,
+                        records,
+                    }
+                },
+            )
+            .collect();
+
+        VdmxTable { version, ratios }
+    }
+
+    // y_max and y_min at `ppem` from the first ratio holding x_ratio:y_ratio
+    pub fn extents(&self, ppem: u16, x_ratio: u16, y_ratio: u16) -> Option<(i16, i16)> {
+        let ratio = self.ratios.iter().find(|ratio| {
+            let (x, start, end) = (
+                ratio.x_ratio as u32,
+                ratio.y_start_ratio as u32,
+                ratio.y_end_ratio as u32,
+            );
+            x == 0
+                || (y_ratio as u32 * x >= x_ratio as u32 * start
+                    && y_ratio as u32 * x <= x_ratio as u32 * end)
+        })?;
+        let index = ratio
+            .records
+            .binary_search_by_key(&ppem, |record| record.y_pel_height)
+            .ok()?;
+        let record = &ratio.records[index];
+        Some((record.y_max, record.y_min))
+    }
+}
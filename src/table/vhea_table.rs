@@ -0,0 +1,60 @@
+use crate::model::{FWord, Fixed, UFWord};
+use crate::reader::Reader;
+
+#[allow(unused)]
+#[derive(Debug)]
+pub struct VheaTable {
+    version: Fixed,
+    pub ascent: FWord, // distance from the centerline to the previous line's descent
+    pub descent: FWord, // distance from the centerline to the next line's ascent
+    pub line_gap: FWord, // reserved, set to 0 in version 1.0
+    pub advance_height_max: UFWord, // maximum advance height
+    pub min_top_side_bearing: FWord, // minimum top side bearing
+    pub min_bottom_side_bearing: FWord, // minimum bottom side bearing
+    pub y_max_extent: FWord, // max(tsb + (yMax-yMin))
+    pub caret_slope_rise: i16, // 0 for a horizontal caret
+    pub caret_slope_run: i16, // 1 for a horizontal caret
+    pub caret_offset: FWord, // set value to 0 for non-slanted fonts
+    metric_data_format: i16,
+    pub num_of_long_ver_metrics: u16,
+}
+
+impl VheaTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, offset: u32) -> VheaTable {
+        reader.seek_from_start(offset);
+        let version: Fixed = reader.read_fixed();
+        let ascent: FWord = reader.read_fword();
+        let descent: FWord = reader.read_fword();
+        let line_gap: FWord = reader.read_fword();
+        let advance_height_max: UFWord = reader.read_ufword();
+        let min_top_side_bearing: FWord = reader.read_fword();
+        let min_bottom_side_bearing: FWord = reader.read_fword();
+        let y_max_extent: FWord = reader.read_fword();
+        let caret_slope_rise: i16 = reader.read_i16();
+        let caret_slope_run: i16 = reader.read_i16();
+        let caret_offset: FWord = reader.read_fword();
+
+        let _reserved = reader.read_i16();
+        let _reserved = reader.read_i16();
+        let _reserved = reader.read_i16();
+        let _reserved = reader.read_i16();
+
+        let metric_data_format: i16 = reader.read_i16();
+        let num_of_long_ver_metrics: u16 = reader.read_u16();
+        VheaTable {
+            version,
+            ascent,
+            descent,
+            line_gap,
+            advance_height_max,
+            min_top_side_bearing,
+            min_bottom_side_bearing,
+            y_max_extent,
+            caret_slope_rise,
+            caret_slope_run,
+            caret_offset,
+            metric_data_format,
+            num_of_long_ver_metrics,
+        }
+    }
+}
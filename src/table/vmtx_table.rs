@@ -0,0 +1,55 @@
+use crate::model::GlyphId;
+use crate::reader::Reader;
+use crate::table::maxp_table::MaximumProfileTable;
+use crate::table::vhea_table::VheaTable;
+use std::collections::HashMap;
+
+#[derive(Debug)]
+pub struct LongVerMetric {
+    pub advance_height: u16,
+    pub top_side_bearing: i16,
+}
+
+#[derive(Debug)]
+pub struct LongVerMetricLookup(pub HashMap<GlyphId, LongVerMetric>);
+
+impl LongVerMetricLookup {
+    pub fn from_file(
+        reader: &mut Box<dyn Reader>,
+        offset: u32,
+        vhea_table: &VheaTable,
+        maxp_table: &MaximumProfileTable,
+    ) -> LongVerMetricLookup {
+        reader.seek_from_start(offset);
+        let mut result: HashMap<GlyphId, LongVerMetric> = HashMap::new();
+        let mut last_advance_height = 0;
+
+        (0..vhea_table.num_of_long_ver_metrics).for_each(|index| {
+            let advance_height: u16 = reader.read_u16();
+            let top_side_bearing: i16 = reader.read_i16();
+            let long_ver_metric = LongVerMetric {
+                advance_height,
+                top_side_bearing,
+            };
+            last_advance_height = advance_height;
+            result.insert(GlyphId::new(index), long_ver_metric);
+        });
+
+        // As in hmtx, the remaining glyphs only have a top side bearing and
+        // share the last advance height.
+        (0..maxp_table
+            .num_glyphs
+            .saturating_sub(vhea_table.num_of_long_ver_metrics))
+            .for_each(|index| {
+                let top_side_bearing: i16 = reader.read_i16();
+                let long_ver_metric = LongVerMetric {
+                    advance_height: last_advance_height,
+                    top_side_bearing,
+                };
+                let index = vhea_table.num_of_long_ver_metrics + index;
+                result.insert(GlyphId::new(index), long_ver_metric);
+            });
+
+        LongVerMetricLookup(result)
+    }
+}
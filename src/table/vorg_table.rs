@@ -0,0 +1,46 @@
+use crate::model::GlyphId;
+use crate::reader::Reader;
+use std::collections::HashMap;
+
+// Vertical Origin table, y coordinates of the glyphs' vertical origins,
+// mostly found in fonts with CFF outlines.
+#[allow(unused)]
+#[derive(Debug)]
+pub struct VorgTable {
+    major_version: u16,
+    minor_version: u16,
+    pub default_vert_origin_y: i16,
+    pub vert_origin_y_metrics: HashMap<GlyphId, i16>,
+}
+
+impl VorgTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, offset: u32) -> VorgTable {
+        reader.seek_from_start(offset);
+        let major_version = reader.read_u16();
+        let minor_version = reader.read_u16();
+        let default_vert_origin_y = reader.read_i16();
+        let num_vert_origin_y_metrics = reader.read_u16();
+
+        let vert_origin_y_metrics: HashMap<GlyphId, i16> = (0..num_vert_origin_y_metrics)
+            .map(|_| {
+                let glyph_index = reader.read_u16();
+                let vert_origin_y = reader.read_i16();
+                (GlyphId::new(glyph_index), vert_origin_y)
+            })
+            .collect();
+
+        VorgTable {
+            major_version,
+            minor_version,
+            default_vert_origin_y,
+            vert_origin_y_metrics,
+        }
+    }
+
+    pub fn vert_origin_y(&self, glyph_id: GlyphId) -> i16 {
+        *self
+            .vert_origin_y_metrics
+            .get(&glyph_id)
+            .unwrap_or(&self.default_vert_origin_y)
+    }
+}
@@ -0,0 +1,83 @@
+use crate::item_variation_store::{DeltaSetIndexMap, ItemVariationStore};
+use crate::model::GlyphId;
+use crate::reader::Reader;
+
+// Vertical Metrics Variations table, deltas for the vmtx advances and side
+// bearings, and for the VORG vertical origins
+#[allow(unused)]
+#[derive(Debug)]
+pub struct VvarTable {
+    major_version: u16,
+    minor_version: u16,
+    item_variation_store: ItemVariationStore,
+    advance_height_mapping: Option<DeltaSetIndexMap>,
+    tsb_mapping: Option<DeltaSetIndexMap>,
+    bsb_mapping: Option<DeltaSetIndexMap>,
+    v_org_mapping: Option<DeltaSetIndexMap>,
+}
+
+impl VvarTable {
+    pub fn from_file(reader: &mut Box<dyn Reader>, offset: u32) -> VvarTable {
+        reader.seek_from_start(offset);
+        let major_version = reader.read_u16();
+        let minor_version = reader.read_u16();
+        let item_variation_store_offset = reader.read_u32();
+        let advance_height_mapping_offset = reader.read_u32();
+        let tsb_mapping_offset = reader.read_u32();
+        let bsb_mapping_offset = reader.read_u32();
+        let v_org_mapping_offset = reader.read_u32();
+
+        let item_variation_store =
+            ItemVariationStore::from_file(reader, offset + item_variation_store_offset);
+        let advance_height_mapping =
+            DeltaSetIndexMap::from_optional_offset(reader, offset, advance_height_mapping_offset);
+        let tsb_mapping =
+            DeltaSetIndexMap::from_optional_offset(reader, offset, tsb_mapping_offset);
+        let bsb_mapping =
+            DeltaSetIndexMap::from_optional_offset(reader, offset, bsb_mapping_offset);
+        let v_org_mapping =
+            DeltaSetIndexMap::from_optional_offset(reader, offset, v_org_mapping_offset);
+
+        VvarTable {
+            major_version,
+            minor_version,
+            item_variation_store,
+            advance_height_mapping,
+            tsb_mapping,
+            bsb_mapping,
+            v_org_mapping,
+        }
+    }
+
+    // Without a mapping, advance deltas are indexed directly by glyph id
+    pub fn advance_height_delta(&self, glyph_id: GlyphId, coordinates: &[f32]) -> f32 {
+        let (outer_index, inner_index) = match &self.advance_height_mapping {
+            Some(mapping) => mapping.get(glyph_id.id() as u32),
+            None => (0, glyph_id.id()),
+        };
+        self.item_variation_store
+            .delta(outer_index, inner_index, coordinates)
+    }
+
+    // None when the side bearing variations are left to the glyph outline variations
+    pub fn top_side_bearing_delta(&self, glyph_id: GlyphId, coordinates: &[f32]) -> Option<f32> {
+        self.mapped_delta(&self.tsb_mapping, glyph_id, coordinates)
+    }
+
+    pub fn vert_origin_y_delta(&self, glyph_id: GlyphId, coordinates: &[f32]) -> Option<f32> {
+        self.mapped_delta(&self.v_org_mapping, glyph_id, coordinates)
+    }
+
+    fn mapped_delta(
+        &self,
+        mapping: &Option<DeltaSetIndexMap>,
+        glyph_id: GlyphId,
+        coordinates: &[f32],
+    ) -> Option<f32> {
+        mapping.as_ref().map(|mapping| {
+            let (outer_index, inner_index) = mapping.get(glyph_id.id() as u32);
+            self.item_variation_store
+                .delta(outer_index, inner_index, coordinates)
+        })
+    }
+}
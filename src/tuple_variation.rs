@@ -0,0 +1,212 @@
+use crate::reader::Reader;
+
+// Shared by the gvar and cvar tables
+// https://learn.microsoft.com/en-us/typography/opentype/spec/otvarcommonformats#tuple-variation-store
+
+const SHARED_POINT_NUMBERS: u16 = 0x8000;
+const COUNT_MASK: u16 = 0x0FFF;
+
+const EMBEDDED_PEAK_TUPLE: u16 = 0x8000;
+const INTERMEDIATE_REGION: u16 = 0x4000;
+const PRIVATE_POINT_NUMBERS: u16 = 0x2000;
+const TUPLE_INDEX_MASK: u16 = 0x0FFF;
+
+const POINTS_ARE_WORDS: u8 = 0x80;
+const POINT_RUN_COUNT_MASK: u8 = 0x7F;
+
+const DELTAS_ARE_ZERO: u8 = 0x80;
+const DELTAS_ARE_WORDS: u8 = 0x40;
+const DELTA_RUN_COUNT_MASK: u8 = 0x3F;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TupleVariation {
+    pub peak_tuple: Vec<f32>,
+    pub intermediate_region: Option<(Vec<f32>, Vec<f32>)>, // (start, end)
+    pub point_numbers: Option<Vec<u16>>,                   // None when all points have deltas
+    pub x_deltas: Vec<i16>,
+    pub y_deltas: Vec<i16>, // empty for cvar, which has a single delta per value
+}
+
+impl TupleVariation {
+    // How much of this variation applies at the normalized `coordinates`,
+    // 0 outside of its region and 1 at its peak.
+    pub fn scalar(&self, coordinates: &[f32]) -> f32 {
+        self.peak_tuple
+            .iter()
+            .enumerate()
+            .map(|(axis_index, peak)| {
+                let peak = *peak;
+                let coordinate = coordinates.get(axis_index).copied().unwrap_or(0.0);
+                let (start, end) = match &self.intermediate_region {
+                    Some((start, end)) => (start[axis_index], end[axis_index]),
+                    None => (peak.min(0.0), peak.max(0.0)),
+                };
+
+                if peak == 0.0 || coordinate == peak {
+                    1.0
+                } else if start > peak || peak > end || (start < 0.0 && end > 0.0) {
+                    // Invalid region, the axis is ignored
+                    1.0
+                } else if coordinate < start || coordinate > end {
+                    0.0
+                } else if coordinate < peak {
+                    (coordinate - start) / (peak - start)
+                } else {
+                    (end - coordinate) / (end - peak)
+                }
+            })
+            .product()
+    }
+}
+
+// Reads a tuple variation store, the reader being at the start of the
+// GlyphVariationData (gvar) or of the cvar table after its version.
+// `data_start` is the address the serialized data offset is relative to and
+// `point_count` the number of points, including phantom points for glyphs.
+pub fn read_tuple_variations(
+    reader: &mut Box<dyn Reader>,
+    data_start: u32,
+    axis_count: u16,
+    shared_tuples: &[Vec<f32>],
+    point_count: usize,
+    has_y_deltas: bool,
+) -> Vec<TupleVariation> {
+    let tuple_variation_count = reader.read_u16();
+    let data_offset = reader.read_u16();
+
+    struct Header {
+        variation_data_size: u16,
+        peak_tuple: Vec<f32>,
+        intermediate_region: Option<(Vec<f32>, Vec<f32>)>,
+        has_private_point_numbers: bool,
+    }
+
+    let read_tuple = |reader: &mut Box<dyn Reader>| -> Vec<f32> {
+        (0..axis_count).map(|_| reader.read_f2dot14()).collect()
+    };
+
+    let headers: Vec<Header> = (0..tuple_variation_count & COUNT_MASK)
+        .map(|_| {
+            let variation_data_size = reader.read_u16();
+            let tuple_index = reader.read_u16();
+
+            let peak_tuple = if tuple_index & EMBEDDED_PEAK_TUPLE != 0 {
+                read_tuple(reader)
+            } else {
+                shared_tuples
+                    .get((tuple_index & TUPLE_INDEX_MASK) as usize)
+                    .cloned()
+                    .unwrap_or_else(|| vec![0.0; axis_count as usize])
+            };
+            let intermediate_region = if tuple_index & INTERMEDIATE_REGION != 0 {
+                let start = read_tuple(reader);
+                let end = read_tuple(reader);
+                Some((start, end))
+            } else {
+                None
+            };
+
+            Header {
+                variation_data_size,
+                peak_tuple,
+                intermediate_region,
+                has_private_point_numbers: tuple_index & PRIVATE_POINT_NUMBERS != 0,
+            }
+        })
+        .collect();
+
+    reader.seek_from_start(data_start + data_offset as u32);
+
+    let shared_point_numbers = if tuple_variation_count & SHARED_POINT_NUMBERS != 0 {
+        read_packed_point_numbers(reader)
+    } else {
+        None
+    };
+
+    let mut tuple_data_start = reader.read_address() as u32;
+
+    headers
+        .into_iter()
+        .map(|header| {
+            reader.seek_from_start(tuple_data_start);
+            tuple_data_start += header.variation_data_size as u32;
+
+            let point_numbers = if header.has_private_point_numbers {
+                read_packed_point_numbers(reader)
+            } else {
+                shared_point_numbers.clone()
+            };
+
+            let delta_count = point_numbers
+                .as_ref()
+                .map_or(point_count, |point_numbers| point_numbers.len());
+
+            let x_deltas = read_packed_deltas(reader, delta_count);
+            let y_deltas = if has_y_deltas {
+                read_packed_deltas(reader, delta_count)
+            } else {
+                vec![]
+            };
+
+            TupleVariation {
+                peak_tuple: header.peak_tuple,
+                intermediate_region: header.intermediate_region,
+                point_numbers,
+                x_deltas,
+                y_deltas,
+            }
+        })
+        .collect()
+}
+
+// None means that all points are referenced
+pub fn read_packed_point_numbers(reader: &mut Box<dyn Reader>) -> Option<Vec<u16>> {
+    let first = reader.read_u8();
+    if first == 0 {
+        return None;
+    }
+    let count = if first & POINTS_ARE_WORDS != 0 {
+        (((first & POINT_RUN_COUNT_MASK) as u16) << 8) | reader.read_u8() as u16
+    } else {
+        first as u16
+    };
+
+    let mut point_numbers: Vec<u16> = Vec::with_capacity(count as usize);
+    let mut last_point = 0u16;
+    while point_numbers.len() < count as usize {
+        let control = reader.read_u8();
+        let run_count = (control & POINT_RUN_COUNT_MASK) as usize + 1;
+        for _ in 0..run_count {
+            let delta = if control & POINTS_ARE_WORDS != 0 {
+                reader.read_u16()
+            } else {
+                reader.read_u8() as u16
+            };
+            last_point = last_point.wrapping_add(delta);
+            point_numbers.push(last_point);
+        }
+    }
+    point_numbers.truncate(count as usize);
+
+    Some(point_numbers)
+}
+
+pub fn read_packed_deltas(reader: &mut Box<dyn Reader>, count: usize) -> Vec<i16> {
+    let mut deltas: Vec<i16> = Vec::with_capacity(count);
+    while deltas.len() < count {
+        let control = reader.read_u8();
+        let run_count = (control & DELTA_RUN_COUNT_MASK) as usize + 1;
+        for _ in 0..run_count {
+            let delta = if control & DELTAS_ARE_ZERO != 0 {
+                0
+            } else if control & DELTAS_ARE_WORDS != 0 {
+                reader.read_i16()
+            } else {
+                reader.read_i8() as i16
+            };
+            deltas.push(delta);
+        }
+    }
+    deltas.truncate(count);
+    deltas
+}
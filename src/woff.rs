@@ -0,0 +1,228 @@
+use crate::deflate::zlib_compress;
+use crate::font_builder::FontBuilder;
+use crate::font_directory::TTC_TAG;
+use crate::inflate::zlib_decompress;
+use crate::writer::Writer;
+
+pub const WOFF_SIGNATURE: u32 = 0x774F4646; // 'wOFF'
+
+const HEADER_SIZE: usize = 44;
+const TABLE_DIRECTORY_ENTRY_SIZE: usize = 20;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WoffError {
+    NotWoff,
+    NotSfnt,           // encoder input that is a collection or shorter than an sfnt header
+    OutOfBounds,       // table directory or table data past the end of the file
+    InvalidTable,      // a table that does not decompress to its origLength
+    SizeLimitExceeded, // tables larger than the totalSfntSize of the header
+}
+
+#[allow(unused)]
+#[derive(Debug)]
+struct WoffHeader {
+    flavor: u32, // sfnt version of the original font
+    length: u32,
+    num_tables: u16,
+    total_sfnt_size: u32,
+    major_version: u16, // version of the font, not of the WOFF format
+    minor_version: u16,
+    meta_offset: u32, // compressed XML metadata, not part of the sfnt
+    meta_length: u32,
+    meta_orig_length: u32,
+    priv_offset: u32, // private data, not part of the sfnt
+    priv_length: u32,
+}
+
+impl WoffHeader {
+    fn from_data(data: &[u8]) -> Result<WoffHeader, WoffError> {
+        if data.len() < HEADER_SIZE || read_u32(data, 0) != WOFF_SIGNATURE {
+            return Err(WoffError::NotWoff);
+        }
+
+        Ok(WoffHeader {
+            flavor: read_u32(data, 4),
+            length: read_u32(data, 8),
+            num_tables: read_u16(data, 12),
+            total_sfnt_size: read_u32(data, 16),
+            major_version: read_u16(data, 20),
+            minor_version: read_u16(data, 22),
+            meta_offset: read_u32(data, 24),
+            meta_length: read_u32(data, 28),
+            meta_orig_length: read_u32(data, 32),
+            priv_offset: read_u32(data, 36),
+            priv_length: read_u32(data, 40),
+        })
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug)]
+struct WoffTableDirectoryEntry {
+    tag: String,
+    offset: u32,
+    comp_length: u32, // equal to orig_length when the table is stored uncompressed
+    orig_length: u32,
+    orig_checksum: u32,
+}
+
+impl WoffTableDirectoryEntry {
+    fn from_data(data: &[u8], offset: usize) -> WoffTableDirectoryEntry {
+        WoffTableDirectoryEntry {
+            tag: String::from_utf8_lossy(&data[offset..offset + 4]).to_string(),
+            offset: read_u32(data, offset + 4),
+            comp_length: read_u32(data, offset + 8),
+            orig_length: read_u32(data, offset + 12),
+            orig_checksum: read_u32(data, offset + 16),
+        }
+    }
+
+    // Inflating stops past orig_length, the size the table should have
+    fn table_data(&self, data: &[u8]) -> Result<Vec<u8>, WoffError> {
+        let start = self.offset as usize;
+        let table = start
+            .checked_add(self.comp_length as usize)
+            .and_then(|end| data.get(start..end))
+            .ok_or(WoffError::OutOfBounds)?;
+
+        let table = if self.comp_length < self.orig_length {
+            zlib_decompress(table, self.orig_length as usize)
+                .map_err(|_| WoffError::InvalidTable)?
+        } else {
+            table.to_vec()
+        };
+        if table.len() != self.orig_length as usize {
+            return Err(WoffError::InvalidTable);
+        }
+        Ok(table)
+    }
+}
+
+// Reconstructs the sfnt a WOFF 1.0 file was made from, to be read by
+// GlyphReader::from_vec. The tables may not add up to more than the
+// totalSfntSize of the header.
+pub fn decode_woff(data: &[u8]) -> Result<Vec<u8>, WoffError> {
+    let header = WoffHeader::from_data(data)?;
+    let directory_end = HEADER_SIZE + header.num_tables as usize * TABLE_DIRECTORY_ENTRY_SIZE;
+    if data.len() < directory_end {
+        return Err(WoffError::OutOfBounds);
+    }
+
+    let entries: Vec<WoffTableDirectoryEntry> = (0..header.num_tables as usize)
+        .map(|index| {
+            WoffTableDirectoryEntry::from_data(
+                data,
+                HEADER_SIZE + index * TABLE_DIRECTORY_ENTRY_SIZE,
+            )
+        })
+        .collect();
+    let sfnt_size: u64 = entries.iter().map(|entry| entry.orig_length as u64).sum();
+    if sfnt_size > header.total_sfnt_size as u64 {
+        return Err(WoffError::SizeLimitExceeded);
+    }
+
+    let mut font_builder = FontBuilder::new();
+    font_builder.sfnt_version(header.flavor);
+    for entry in entries {
+        font_builder.add_table(&entry.tag, entry.table_data(data)?);
+    }
+    Ok(font_builder.build())
+}
+
+struct EncodedTable<'a> {
+    tag: [u8; 4],
+    checksum: u32, // of the original table
+    data: &'a [u8],
+    stored: Vec<u8>, // compressed, or the original data when that is not smaller
+}
+
+// Wraps the tables of an sfnt into a WOFF 1.0 file, each table compressed with
+// zlib unless that does not make it smaller. Collections are not supported.
+pub fn encode_woff(font: &[u8]) -> Result<Vec<u8>, WoffError> {
+    let header = font.get(..12).ok_or(WoffError::NotSfnt)?;
+    let flavor = read_u32(header, 0);
+    if flavor == TTC_TAG {
+        return Err(WoffError::NotSfnt);
+    }
+    let num_tables = read_u16(header, 4) as usize;
+
+    let mut tables: Vec<EncodedTable> = (0..num_tables)
+        .map(|index| {
+            let entry = font
+                .get(12 + index * 16..12 + (index + 1) * 16)
+                .ok_or(WoffError::OutOfBounds)?;
+            let offset = read_u32(entry, 8) as usize;
+            let length = read_u32(entry, 12) as usize;
+            let table = offset
+                .checked_add(length)
+                .and_then(|end| font.get(offset..end))
+                .ok_or(WoffError::OutOfBounds)?;
+            let compressed = zlib_compress(table);
+            let stored = if compressed.len() < table.len() {
+                compressed
+            } else {
+                table.to_vec()
+            };
+            Ok(EncodedTable {
+                tag: entry[..4].try_into().unwrap(),
+                checksum: read_u32(entry, 4),
+                data: table,
+                stored,
+            })
+        })
+        .collect::<Result<_, WoffError>>()?;
+    tables.sort_by_key(|table| table.tag);
+
+    let directory_end = HEADER_SIZE + num_tables * TABLE_DIRECTORY_ENTRY_SIZE;
+    let length = padded_size(directory_end, tables.iter().map(|table| table.stored.len()))
+        .ok_or(WoffError::SizeLimitExceeded)?;
+    let total_sfnt_size = padded_size(
+        12 + 16 * num_tables,
+        tables.iter().map(|table| table.data.len()),
+    )
+    .ok_or(WoffError::SizeLimitExceeded)?;
+
+    let mut writer = Writer::new();
+    writer.write_u32(WOFF_SIGNATURE);
+    writer.write_u32(flavor);
+    writer.write_u32(length);
+    writer.write_u16(num_tables as u16);
+    writer.write_u16(0); // reserved
+    writer.write_u32(total_sfnt_size);
+    writer.write_u16(1);
+    writer.write_u16(0);
+    (0..5).for_each(|_| writer.write_u32(0)); // no metadata or private data
+
+    // The offsets fit in 32 bits as the whole file does
+    let mut offset = directory_end;
+    tables.iter().for_each(|table| {
+        writer.write_bytes(&table.tag);
+        writer.write_u32(offset as u32);
+        writer.write_u32(table.stored.len() as u32);
+        writer.write_u32(table.data.len() as u32);
+        writer.write_u32(table.checksum);
+        offset += table.stored.len().next_multiple_of(4);
+    });
+    tables.iter().for_each(|table| {
+        writer.write_bytes(&table.stored);
+        writer.align(4);
+    });
+    Ok(writer.into_vec())
+}
+
+// start plus the lengths padded to 4 bytes, None when that is not a 32-bit size
+pub(crate) fn padded_size(start: usize, mut lengths: impl Iterator<Item = usize>) -> Option<u32> {
+    lengths
+        .try_fold(start, |sum, length| {
+            sum.checked_add(length.next_multiple_of(4))
+        })
+        .and_then(|sum| u32::try_from(sum).ok())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+}
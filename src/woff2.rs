@@ -0,0 +1,1179 @@
+use crate::brotli::{brotli_compress, brotli_decompress, BrotliError};
+use crate::font_builder::{binary_search_header, checksum, FontBuilder};
+use crate::font_directory::TTC_TAG;
+use crate::glyf_encoder::encode_points;
+use crate::woff::padded_size;
+use crate::writer::Writer;
+
+pub const WOFF2_SIGNATURE: u32 = 0x774F4632; // 'wOF2'
+
+const HEADER_SIZE: usize = 48;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Woff2Error {
+    NotWoff2,
+    NotSfnt,           // encoder input that is a collection or shorter than an sfnt header
+    OutOfBounds,       // header, table directory or a stream past the end of its data
+    InvalidDirectory,  // malformed UIntBase128 or collection table index
+    InvalidData,       // Brotli stream that does not decompress to the table data
+    InvalidTable,      // transformed table that cannot be rebuilt, or not of its origLength
+    SizeLimitExceeded, // tables larger than the totalSfntSize of the header
+}
+
+// Tags of the known tables, in the order of their index in the table directory flags
+const KNOWN_TABLE_TAGS: [&str; 63] = [
+    "cmap", "head", "hhea", "hmtx", "maxp", "name", "OS/2", "post", "cvt ", "fpgm", "glyf", "loca",
+    "prep", "CFF ", "VORG", "EBDT", "EBLC", "gasp", "hdmx", "kern", "LTSH", "PCLT", "VDMX", "vhea",
+    "vmtx", "BASE", "GDEF", "GPOS", "GSUB", "EBSC", "JSTF", "MATH", "CBDT", "CBLC", "COLR", "CPAL",
+    "SVG ", "sbix", "acnt", "avar", "bdat", "bloc", "bsln", "cvar", "fdsc", "feat", "fmtx", "fvar",
+    "gvar", "hsty", "just", "lcar", "mort", "morx", "opbd", "prop", "trak", "Zapf", "Silf", "Glat",
+    "Gloc", "Feat", "Sill",
+];
+const ARBITRARY_TAG_INDEX: u8 = 63;
+
+const HEAD_CHECKSUM_ADJUSTMENT_OFFSET: usize = 8;
+const HEAD_FLAGS_OFFSET: usize = 16;
+const HEAD_INDEX_TO_LOC_FORMAT_OFFSET: usize = 50;
+const HHEA_NUMBER_OF_H_METRICS_OFFSET: usize = 34;
+const MAXP_NUM_GLYPHS_OFFSET: usize = 4;
+const HEAD_FLAG_LOSSLESS_TRANSFORM: u16 = 0x0800;
+
+const ON_CURVE_POINT: u8 = 0x80; // inverted in triplet flags
+const GLYF_ON_CURVE_POINT: u8 = 0x01;
+const GLYF_X_SHORT_VECTOR: u8 = 0x02;
+const GLYF_Y_SHORT_VECTOR: u8 = 0x04;
+const GLYF_REPEAT_FLAG: u8 = 0x08;
+const GLYF_X_IS_SAME_OR_POSITIVE: u8 = 0x10;
+const GLYF_Y_IS_SAME_OR_POSITIVE: u8 = 0x20;
+const GLYF_OVERLAP_SIMPLE: u8 = 0x40;
+const OVERLAP_SIMPLE_BITMAP: u16 = 0x0001;
+const HMTX_NO_PROPORTIONAL_LSBS: u8 = 0x01;
+const HMTX_NO_MONOSPACE_LSBS: u8 = 0x02;
+
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+
+// Big endian values and the WOFF2 variable length integers, read in sequence
+struct Stream<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Stream<'a> {
+    fn new(data: &'a [u8]) -> Stream<'a> {
+        Stream { data, offset: 0 }
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], Woff2Error> {
+        let bytes = self
+            .offset
+            .checked_add(length)
+            .and_then(|end| self.data.get(self.offset..end))
+            .ok_or(Woff2Error::OutOfBounds)?;
+        self.offset += length;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Woff2Error> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16, Woff2Error> {
+        Ok(u16::from_be_bytes(self.read_bytes(2)?.try_into().unwrap()))
+    }
+
+    fn read_i16(&mut self) -> Result<i16, Woff2Error> {
+        Ok(self.read_u16()? as i16)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, Woff2Error> {
+        Ok(u32::from_be_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    // UIntBase128: up to 5 bytes, 7 bits each, most significant first
+    fn read_base128(&mut self) -> Result<u32, Woff2Error> {
+        let mut value: u32 = 0;
+        for index in 0..5 {
+            let byte = self.read_u8()?;
+            // Leading zeros or overflow
+            if (index == 0 && byte == 0x80) || value & 0xFE000000 != 0 {
+                return Err(Woff2Error::InvalidDirectory);
+            }
+            value = (value << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(Woff2Error::InvalidDirectory)
+    }
+
+    // 255UInt16
+    fn read_255_u16(&mut self) -> Result<u16, Woff2Error> {
+        Ok(match self.read_u8()? {
+            253 => self.read_u16()?,
+            254 => 253 * 2 + self.read_u8()? as u16,
+            255 => 253 + self.read_u8()? as u16,
+            value => value as u16,
+        })
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug)]
+struct Woff2Header {
+    flavor: u32, // sfnt version of the original font, 'ttcf' for collections
+    length: u32,
+    num_tables: u16,
+    total_sfnt_size: u32,
+    total_compressed_size: u32, // of the Brotli stream holding all the tables
+    major_version: u16,         // version of the font, not of the WOFF2 format
+    minor_version: u16,
+    meta_offset: u32, // compressed XML metadata, not part of the sfnt
+    meta_length: u32,
+    meta_orig_length: u32,
+    priv_offset: u32, // private data, not part of the sfnt
+    priv_length: u32,
+}
+
+impl Woff2Header {
+    fn from_stream(stream: &mut Stream) -> Result<Woff2Header, Woff2Error> {
+        if stream.data.len() < HEADER_SIZE || stream.read_u32()? != WOFF2_SIGNATURE {
+            return Err(Woff2Error::NotWoff2);
+        }
+        let flavor = stream.read_u32()?;
+        let length = stream.read_u32()?;
+        let num_tables = stream.read_u16()?;
+        stream.read_u16()?; // reserved
+
+        Ok(Woff2Header {
+            flavor,
+            length,
+            num_tables,
+            total_sfnt_size: stream.read_u32()?,
+            total_compressed_size: stream.read_u32()?,
+            major_version: stream.read_u16()?,
+            minor_version: stream.read_u16()?,
+            meta_offset: stream.read_u32()?,
+            meta_length: stream.read_u32()?,
+            meta_orig_length: stream.read_u32()?,
+            priv_offset: stream.read_u32()?,
+            priv_length: stream.read_u32()?,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Woff2TableDirectoryEntry {
+    tag: String,
+    transform_version: u8,
+    orig_length: u32,
+    transform_length: u32, // length in the decompressed stream
+    offset: usize,         // in the decompressed stream
+}
+
+impl Woff2TableDirectoryEntry {
+    fn from_stream(
+        stream: &mut Stream,
+        offset: usize,
+    ) -> Result<Woff2TableDirectoryEntry, Woff2Error> {
+        let flags = stream.read_u8()?;
+        let tag = match flags & 0x3F {
+            ARBITRARY_TAG_INDEX => String::from_utf8_lossy(stream.read_bytes(4)?).to_string(),
+            index => KNOWN_TABLE_TAGS[index as usize].to_string(),
+        };
+        let transform_version = flags >> 6;
+        let orig_length = stream.read_base128()?;
+        let transform_length = if Self::is_transformed(&tag, transform_version) {
+            stream.read_base128()?
+        } else {
+            orig_length
+        };
+
+        Ok(Woff2TableDirectoryEntry {
+            tag,
+            transform_version,
+            orig_length,
+            transform_length,
+            offset,
+        })
+    }
+
+    // Version 0 is the null transform, except for glyf and loca where it is the only transform
+    fn is_transformed(tag: &str, transform_version: u8) -> bool {
+        match tag {
+            "glyf" | "loca" => transform_version == 0,
+            _ => transform_version != 0,
+        }
+    }
+
+    fn transformed(&self) -> bool {
+        Self::is_transformed(&self.tag, self.transform_version)
+    }
+
+    fn data<'a>(&self, tables: &'a [u8]) -> Result<&'a [u8], Woff2Error> {
+        tables
+            .get(self.offset..self.offset + self.transform_length as usize)
+            .ok_or(Woff2Error::OutOfBounds)
+    }
+}
+
+// A font of a collection: its sfnt version and the index of each of its tables in the directory
+#[derive(Debug)]
+struct CollectionFontEntry {
+    flavor: u32,
+    table_indices: Vec<usize>,
+}
+
+// Decodes a WOFF2 file into an sfnt, or a TrueType collection when it holds
+// several fonts. The tables may not add up to more than the totalSfntSize of
+// the header, before or after their transforms are undone.
+pub fn decode_woff2(data: &[u8]) -> Result<Vec<u8>, Woff2Error> {
+    let mut stream = Stream::new(data);
+    let header = Woff2Header::from_stream(&mut stream)?;
+
+    let mut offset = 0;
+    let entries: Vec<Woff2TableDirectoryEntry> = (0..header.num_tables)
+        .map(|_| {
+            let entry = Woff2TableDirectoryEntry::from_stream(&mut stream, offset)?;
+            offset += entry.transform_length as usize;
+            Ok(entry)
+        })
+        .collect::<Result<_, Woff2Error>>()?;
+    let sfnt_size: usize = entries.iter().map(|entry| entry.orig_length as usize).sum();
+    if sfnt_size.max(offset) > header.total_sfnt_size as usize {
+        return Err(Woff2Error::SizeLimitExceeded);
+    }
+
+    let (collection_version, fonts) = if header.flavor == TTC_TAG {
+        let version = stream.read_u32()?;
+        let num_fonts = stream.read_255_u16()?;
+        let fonts: Vec<CollectionFontEntry> = (0..num_fonts)
+            .map(|_| {
+                let num_tables = stream.read_255_u16()?;
+                let flavor = stream.read_u32()?;
+                let table_indices = (0..num_tables)
+                    .map(|_| {
+                        let index = stream.read_255_u16()? as usize;
+                        if index >= entries.len() {
+                            return Err(Woff2Error::InvalidDirectory);
+                        }
+                        Ok(index)
+                    })
+                    .collect::<Result<_, Woff2Error>>()?;
+                Ok(CollectionFontEntry {
+                    flavor,
+                    table_indices,
+                })
+            })
+            .collect::<Result<_, Woff2Error>>()?;
+        (Some(version), fonts)
+    } else {
+        let font = CollectionFontEntry {
+            flavor: header.flavor,
+            table_indices: (0..entries.len()).collect(),
+        };
+        (None, vec![font])
+    };
+
+    let compressed = stream.read_bytes(header.total_compressed_size as usize)?;
+    let tables = brotli_decompress(compressed, offset).map_err(|error| match error {
+        BrotliError::LimitExceeded => Woff2Error::SizeLimitExceeded,
+        _ => Woff2Error::InvalidData,
+    })?;
+    if tables.len() != offset {
+        return Err(Woff2Error::InvalidData);
+    }
+
+    // Tables are decoded once, fonts of a collection can share them
+    let mut decoded: Vec<Option<Vec<u8>>> = entries
+        .iter()
+        .map(|entry| match entry.transformed() {
+            true => Ok(None),
+            false => Ok(Some(entry.data(&tables)?.to_vec())),
+        })
+        .collect::<Result<_, Woff2Error>>()?;
+    fonts
+        .iter()
+        .try_for_each(|font| decode_transformed_tables(font, &entries, &tables, &mut decoded))?;
+    let decoded: Vec<Vec<u8>> = decoded
+        .into_iter()
+        .zip(&entries)
+        .map(|(data, entry)| {
+            // A transformed table without a glyf table to rebuild it from
+            let data = data.ok_or(Woff2Error::InvalidTable)?;
+            // Rebuilt glyphs can encode their points differently from the original font
+            if entry.tag != "glyf" && data.len() != entry.orig_length as usize {
+                return Err(Woff2Error::InvalidTable);
+            }
+            Ok(data)
+        })
+        .collect::<Result<_, Woff2Error>>()?;
+
+    Ok(match collection_version {
+        None => {
+            let mut font_builder = FontBuilder::new();
+            font_builder.sfnt_version(header.flavor);
+            entries.iter().zip(decoded).for_each(|(entry, data)| {
+                font_builder.add_table(&entry.tag, data);
+            });
+            font_builder.build()
+        }
+        Some(version) => build_collection(version, &fonts, &entries, &decoded),
+    })
+}
+
+fn decode_transformed_tables(
+    font: &CollectionFontEntry,
+    entries: &[Woff2TableDirectoryEntry],
+    tables: &[u8],
+    decoded: &mut [Option<Vec<u8>>],
+) -> Result<(), Woff2Error> {
+    let find = |tag: &str| {
+        font.table_indices
+            .iter()
+            .copied()
+            .find(|index| entries[*index].tag == tag)
+    };
+
+    let Some(glyf) = find("glyf").filter(|index| entries[*index].transformed()) else {
+        return Ok(());
+    };
+    let loca = find("loca").ok_or(Woff2Error::InvalidTable)?;
+    let (glyf_data, loca_data, x_mins) = reconstruct_glyf(entries[glyf].data(tables)?)?;
+    if let Some(head) = find("head").and_then(|head| decoded[head].as_ref()) {
+        let index_to_loc_format = head
+            .get(HEAD_INDEX_TO_LOC_FORMAT_OFFSET..HEAD_INDEX_TO_LOC_FORMAT_OFFSET + 2)
+            .map(|bytes| i16::from_be_bytes(bytes.try_into().unwrap()))
+            .ok_or(Woff2Error::InvalidTable)?;
+        let expected_loca_length =
+            (x_mins.len() + 1) * if index_to_loc_format == 0 { 2 } else { 4 };
+        if loca_data.len() != expected_loca_length {
+            return Err(Woff2Error::InvalidTable);
+        }
+    }
+    decoded[glyf] = Some(glyf_data);
+    decoded[loca] = Some(loca_data);
+
+    if let Some(hmtx) = find("hmtx").filter(|index| entries[*index].transformed()) {
+        let number_of_h_metrics = find("hhea")
+            .and_then(|hhea| decoded[hhea].as_ref())
+            .and_then(|hhea| {
+                hhea.get(HHEA_NUMBER_OF_H_METRICS_OFFSET..HHEA_NUMBER_OF_H_METRICS_OFFSET + 2)
+            })
+            .map(|bytes| u16::from_be_bytes(bytes.try_into().unwrap()))
+            .ok_or(Woff2Error::InvalidTable)?;
+        decoded[hmtx] = Some(reconstruct_hmtx(
+            entries[hmtx].data(tables)?,
+            number_of_h_metrics as usize,
+            &x_mins,
+        )?);
+    }
+    Ok(())
+}
+
+// glyf, loca and the xMin of each glyph
+type ReconstructedGlyf = (Vec<u8>, Vec<u8>, Vec<i16>);
+
+// Rebuilds glyf and loca from the transformed glyf table, also returns the xMin of each glyph
+fn reconstruct_glyf(data: &[u8]) -> Result<ReconstructedGlyf, Woff2Error> {
+    let mut header = Stream::new(data);
+    header.read_u16()?; // reserved
+    let option_flags = header.read_u16()?;
+    let num_glyphs = header.read_u16()? as usize;
+    let index_format = header.read_u16()?;
+    let stream_sizes: Vec<usize> = (0..7)
+        .map(|_| Ok(header.read_u32()? as usize))
+        .collect::<Result<_, Woff2Error>>()?;
+
+    let mut offset = header.offset;
+    let mut streams: Vec<Stream> = stream_sizes
+        .iter()
+        .map(|size| {
+            let stream = Stream::new(
+                data.get(offset..offset + size)
+                    .ok_or(Woff2Error::OutOfBounds)?,
+            );
+            offset += size;
+            Ok(stream)
+        })
+        .collect::<Result<_, Woff2Error>>()?;
+    let overlap_bitmap = if option_flags & OVERLAP_SIMPLE_BITMAP != 0 {
+        Some(
+            data.get(offset..offset + num_glyphs.div_ceil(8))
+                .ok_or(Woff2Error::OutOfBounds)?,
+        )
+    } else {
+        None
+    };
+
+    let [n_contour_stream, n_points_stream, flag_stream, glyph_stream, composite_stream, bbox_stream, instruction_stream] =
+        &mut streams[..]
+    else {
+        unreachable!()
+    };
+    let bbox_bitmap = bbox_stream.read_bytes(num_glyphs.div_ceil(32) * 4)?;
+    let is_set =
+        |bitmap: &[u8], glyph_id: usize| bitmap[glyph_id >> 3] & (0x80 >> (glyph_id & 7)) != 0;
+
+    let mut glyf = Writer::new();
+    let mut offsets: Vec<usize> = vec![0];
+    let mut x_mins: Vec<i16> = vec![];
+    for glyph_id in 0..num_glyphs {
+        let number_of_contours = n_contour_stream.read_i16()?;
+        let has_bbox = is_set(bbox_bitmap, glyph_id);
+        let mut glyph = Writer::new();
+
+        if number_of_contours == 0 {
+            if has_bbox {
+                return Err(Woff2Error::InvalidTable);
+            }
+            x_mins.push(0);
+        } else if number_of_contours > 0 {
+            let mut end_points: Vec<u16> = vec![];
+            let mut num_points: u16 = 0;
+            for _ in 0..number_of_contours {
+                num_points = num_points
+                    .checked_add(n_points_stream.read_255_u16()?)
+                    .ok_or(Woff2Error::InvalidTable)?;
+                end_points.push(num_points.wrapping_sub(1));
+            }
+
+            let (mut x, mut y) = (0i32, 0i32);
+            let points: Vec<(i32, i32, bool)> = (0..num_points)
+                .map(|_| {
+                    let (dx, dy, on_curve) = decode_triplet(flag_stream.read_u8()?, glyph_stream)?;
+                    x = x.wrapping_add(dx);
+                    y = y.wrapping_add(dy);
+                    Ok((x, y, on_curve))
+                })
+                .collect::<Result<_, Woff2Error>>()?;
+            let instruction_length = glyph_stream.read_255_u16()?;
+            let instructions = instruction_stream.read_bytes(instruction_length as usize)?;
+
+            let (x_min, y_min, x_max, y_max) = if has_bbox {
+                (
+                    bbox_stream.read_i16()?,
+                    bbox_stream.read_i16()?,
+                    bbox_stream.read_i16()?,
+                    bbox_stream.read_i16()?,
+                )
+            } else {
+                let min_max = |values: Vec<i32>| {
+                    (
+                        values.iter().copied().min().unwrap_or(0) as i16,
+                        values.iter().copied().max().unwrap_or(0) as i16,
+                    )
+                };
+                let (x_min, x_max) = min_max(points.iter().map(|point| point.0).collect());
+                let (y_min, y_max) = min_max(points.iter().map(|point| point.1).collect());
+                (x_min, y_min, x_max, y_max)
+            };
+            x_mins.push(x_min);
+
+            glyph.write_i16(number_of_contours);
+            glyph.write_i16(x_min);
+            glyph.write_i16(y_min);
+            glyph.write_i16(x_max);
+            glyph.write_i16(y_max);
+            end_points
+                .iter()
+                .for_each(|end_point| glyph.write_u16(*end_point));
+            glyph.write_u16(instruction_length);
+            glyph.write_bytes(instructions);
+            let overlap_simple = overlap_bitmap.is_some_and(|bitmap| is_set(bitmap, glyph_id));
+            encode_points(&points, overlap_simple, &mut glyph);
+        } else {
+            if !has_bbox {
+                return Err(Woff2Error::InvalidTable);
+            }
+            let start = composite_stream.offset;
+            let mut have_instructions = false;
+            loop {
+                let flags = composite_stream.read_u16()?;
+                composite_stream.read_u16()?; // glyph index
+                let arguments_size = if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+                    4
+                } else {
+                    2
+                };
+                let transform_size = if flags & WE_HAVE_A_SCALE != 0 {
+                    2
+                } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+                    4
+                } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+                    8
+                } else {
+                    0
+                };
+                composite_stream.read_bytes(arguments_size + transform_size)?;
+                have_instructions |= flags & WE_HAVE_INSTRUCTIONS != 0;
+                if flags & MORE_COMPONENTS == 0 {
+                    break;
+                }
+            }
+            let components = &composite_stream.data[start..composite_stream.offset];
+
+            glyph.write_i16(number_of_contours);
+            let x_min = bbox_stream.read_i16()?;
+            x_mins.push(x_min);
+            glyph.write_i16(x_min);
+            glyph.write_bytes(bbox_stream.read_bytes(6)?);
+            glyph.write_bytes(components);
+            if have_instructions {
+                let instruction_length = glyph_stream.read_255_u16()?;
+                glyph.write_u16(instruction_length);
+                glyph.write_bytes(instruction_stream.read_bytes(instruction_length as usize)?);
+            }
+        }
+
+        glyf.write_bytes(&glyph.into_vec());
+        glyf.align(4);
+        offsets.push(glyf.offset());
+    }
+
+    let mut loca = Writer::new();
+    offsets.iter().for_each(|offset| {
+        if index_format == 0 {
+            loca.write_u16((offset / 2) as u16);
+        } else {
+            loca.write_u32(*offset as u32);
+        }
+    });
+
+    Ok((glyf.into_vec(), loca.into_vec(), x_mins))
+}
+
+// A point delta and whether the point is on curve, from a flag and its data in the glyph stream
+fn decode_triplet(flag: u8, glyph_stream: &mut Stream) -> Result<(i32, i32, bool), Woff2Error> {
+    let on_curve = flag & ON_CURVE_POINT == 0;
+    let flag = (flag & !ON_CURVE_POINT) as i32;
+    let with_sign = |flag: i32, value: i32| if flag & 1 != 0 { value } else { -value };
+
+    let (dx, dy) = if flag < 10 {
+        let b0 = glyph_stream.read_u8()? as i32;
+        (0, with_sign(flag, ((flag & 14) << 7) + b0))
+    } else if flag < 20 {
+        let b0 = glyph_stream.read_u8()? as i32;
+        (with_sign(flag, (((flag - 10) & 14) << 7) + b0), 0)
+    } else if flag < 84 {
+        let b0 = flag - 20;
+        let b1 = glyph_stream.read_u8()? as i32;
+        (
+            with_sign(flag, 1 + (b0 & 0x30) + (b1 >> 4)),
+            with_sign(flag >> 1, 1 + ((b0 & 0x0C) << 2) + (b1 & 0x0F)),
+        )
+    } else if flag < 120 {
+        let b0 = flag - 84;
+        let b1 = glyph_stream.read_u8()? as i32;
+        let b2 = glyph_stream.read_u8()? as i32;
+        (
+            with_sign(flag, 1 + ((b0 / 12) << 8) + b1),
+            with_sign(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + b2),
+        )
+    } else if flag < 124 {
+        let b1 = glyph_stream.read_u8()? as i32;
+        let b2 = glyph_stream.read_u8()? as i32;
+        let b3 = glyph_stream.read_u8()? as i32;
+        (
+            with_sign(flag, (b1 << 4) + (b2 >> 4)),
+            with_sign(flag >> 1, ((b2 & 0x0F) << 8) + b3),
+        )
+    } else {
+        let b1 = glyph_stream.read_u8()? as i32;
+        let b2 = glyph_stream.read_u8()? as i32;
+        let b3 = glyph_stream.read_u8()? as i32;
+        let b4 = glyph_stream.read_u8()? as i32;
+        (
+            with_sign(flag, (b1 << 8) + b2),
+            with_sign(flag >> 1, (b3 << 8) + b4),
+        )
+    };
+    Ok((dx, dy, on_curve))
+}
+
+// Rebuilds hmtx, left side bearings left out of the transformed table are the glyph xMin
+fn reconstruct_hmtx(
+    data: &[u8],
+    number_of_h_metrics: usize,
+    x_mins: &[i16],
+) -> Result<Vec<u8>, Woff2Error> {
+    let mut stream = Stream::new(data);
+    let flags = stream.read_u8()?;
+    // A transform has to leave some left side bearings out
+    if flags & (HMTX_NO_PROPORTIONAL_LSBS | HMTX_NO_MONOSPACE_LSBS) == 0 {
+        return Err(Woff2Error::InvalidTable);
+    }
+    if number_of_h_metrics == 0 || number_of_h_metrics > x_mins.len() {
+        return Err(Woff2Error::InvalidTable);
+    }
+
+    let advance_widths: Vec<u16> = (0..number_of_h_metrics)
+        .map(|_| stream.read_u16())
+        .collect::<Result<_, Woff2Error>>()?;
+    let mut left_side_bearings =
+        |range: std::ops::Range<usize>, left_out: bool| -> Result<Vec<i16>, Woff2Error> {
+            range
+                .map(|glyph_id| {
+                    if left_out {
+                        Ok(x_mins[glyph_id])
+                    } else {
+                        stream.read_i16()
+                    }
+                })
+                .collect()
+        };
+    let proportional_lsbs = left_side_bearings(
+        0..number_of_h_metrics,
+        flags & HMTX_NO_PROPORTIONAL_LSBS != 0,
+    )?;
+    let monospace_lsbs = left_side_bearings(
+        number_of_h_metrics..x_mins.len(),
+        flags & HMTX_NO_MONOSPACE_LSBS != 0,
+    )?;
+
+    let mut writer = Writer::new();
+    advance_widths
+        .iter()
+        .zip(&proportional_lsbs)
+        .for_each(|(advance_width, lsb)| {
+            writer.write_u16(*advance_width);
+            writer.write_i16(*lsb);
+        });
+    monospace_lsbs.iter().for_each(|lsb| writer.write_i16(*lsb));
+    Ok(writer.into_vec())
+}
+
+// A TrueType collection, each table stored once however many fonts use it
+fn build_collection(
+    version: u32,
+    fonts: &[CollectionFontEntry],
+    entries: &[Woff2TableDirectoryEntry],
+    tables: &[Vec<u8>],
+) -> Vec<u8> {
+    let header_size = 12 + 4 * fonts.len() + if version >= 0x00020000 { 12 } else { 0 };
+    let directories_size: usize = fonts
+        .iter()
+        .map(|font| 12 + 16 * font.table_indices.len())
+        .sum();
+
+    let mut table_offsets: Vec<u32> = vec![];
+    let mut offset = (header_size + directories_size) as u32;
+    tables.iter().for_each(|table| {
+        table_offsets.push(offset);
+        offset += (table.len() as u32).next_multiple_of(4);
+    });
+
+    let mut writer = Writer::new();
+    writer.write_u32(TTC_TAG);
+    writer.write_u32(version);
+    writer.write_u32(fonts.len() as u32);
+    let mut directory_offset = header_size;
+    fonts.iter().for_each(|font| {
+        writer.write_u32(directory_offset as u32);
+        directory_offset += 12 + 16 * font.table_indices.len();
+    });
+    if version >= 0x00020000 {
+        // Not signed
+        writer.write_u32(0);
+        writer.write_u32(0);
+        writer.write_u32(0);
+    }
+
+    fonts.iter().for_each(|font| {
+        let mut table_indices = font.table_indices.clone();
+        table_indices.sort_by(|a, b| entries[*a].tag.cmp(&entries[*b].tag));
+        let num_tables = table_indices.len() as u16;
+        let (search_range, entry_selector, range_shift) = binary_search_header(num_tables, 16);
+        writer.write_u32(font.flavor);
+        writer.write_u16(num_tables);
+        writer.write_u16(search_range);
+        writer.write_u16(entry_selector);
+        writer.write_u16(range_shift);
+        table_indices.iter().for_each(|index| {
+            writer.write_table_name(&entries[*index].tag);
+            writer.write_u32(table_checksum(&entries[*index].tag, &tables[*index]));
+            writer.write_u32(table_offsets[*index]);
+            writer.write_u32(tables[*index].len() as u32);
+        });
+    });
+
+    tables.iter().for_each(|table| {
+        writer.write_bytes(table);
+        writer.align(4);
+    });
+    writer.into_vec()
+}
+
+// The head checksum is computed with checkSumAdjustment set to 0
+fn table_checksum(tag: &str, data: &[u8]) -> u32 {
+    if tag == "head" && data.len() >= HEAD_CHECKSUM_ADJUSTMENT_OFFSET + 4 {
+        let mut data = data.to_vec();
+        data[HEAD_CHECKSUM_ADJUSTMENT_OFFSET..HEAD_CHECKSUM_ADJUSTMENT_OFFSET + 4]
+            .copy_from_slice(&[0; 4]);
+        checksum(&data)
+    } else {
+        checksum(data)
+    }
+}
+
+// Encodes an sfnt as WOFF2, with the glyf and loca transform, and the hmtx transform
+// when the left side bearings equal the glyph xMin. Collections are not supported,
+// a glyf table that cannot be transformed gives an error.
+pub fn encode_woff2(font: &[u8]) -> Result<Vec<u8>, Woff2Error> {
+    let mut stream = Stream::new(font);
+    let flavor = stream.read_u32().map_err(|_| Woff2Error::NotSfnt)?;
+    let num_tables = stream.read_u16().map_err(|_| Woff2Error::NotSfnt)?;
+    if flavor == TTC_TAG || stream.read_bytes(6).is_err() {
+        return Err(Woff2Error::NotSfnt);
+    }
+    let mut tables: Vec<(String, Vec<u8>)> = (0..num_tables)
+        .map(|_| {
+            let tag = String::from_utf8_lossy(stream.read_bytes(4)?).to_string();
+            stream.read_u32()?; // checksum
+            let offset = stream.read_u32()? as usize;
+            let length = stream.read_u32()? as usize;
+            let data = offset
+                .checked_add(length)
+                .and_then(|end| font.get(offset..end))
+                .ok_or(Woff2Error::OutOfBounds)?;
+            Ok((tag, data.to_vec()))
+        })
+        .collect::<Result<_, Woff2Error>>()?;
+    let total_sfnt_size = padded_size(
+        12 + 16 * tables.len(),
+        tables.iter().map(|(_, data)| data.len()),
+    )
+    .ok_or(Woff2Error::SizeLimitExceeded)?;
+
+    // Tables in tag order, except for loca which directly follows glyf
+    tables.sort_by_key(|(tag, _)| match tag.as_str() {
+        "loca" => "glyf\u{1}".to_string(),
+        _ => tag.clone(),
+    });
+    let table = |tag: &str| {
+        tables
+            .iter()
+            .find(|(other, _)| other == tag)
+            .map(|(_, data)| data.as_slice())
+    };
+
+    // (transform version, transformed data) of the transformed tables
+    let mut transformed: Vec<(String, u8, Vec<u8>)> = vec![];
+    if let (Some(glyf), Some(loca), Some(head), Some(maxp)) =
+        (table("glyf"), table("loca"), table("head"), table("maxp"))
+    {
+        let index_to_loc_format = read_u16_at(head, HEAD_INDEX_TO_LOC_FORMAT_OFFSET)?;
+        let num_glyphs = read_u16_at(maxp, MAXP_NUM_GLYPHS_OFFSET)?;
+        let (glyf_data, x_mins) = transform_glyf(glyf, loca, index_to_loc_format, num_glyphs)?;
+        transformed.push(("glyf".to_string(), 0, glyf_data));
+        transformed.push(("loca".to_string(), 0, vec![]));
+
+        if let (Some(hmtx), Some(hhea)) = (table("hmtx"), table("hhea")) {
+            let number_of_h_metrics = read_u16_at(hhea, HHEA_NUMBER_OF_H_METRICS_OFFSET)?;
+            if let Some(hmtx_data) = transform_hmtx(hmtx, number_of_h_metrics as usize, &x_mins) {
+                transformed.push(("hmtx".to_string(), 1, hmtx_data));
+            }
+        }
+    }
+    if !transformed.is_empty() {
+        let (_, head) = tables
+            .iter_mut()
+            .find(|(tag, _)| tag == "head")
+            .ok_or(Woff2Error::InvalidTable)?;
+        let flags = read_u16_at(head, HEAD_FLAGS_OFFSET)? | HEAD_FLAG_LOSSLESS_TRANSFORM;
+        head[HEAD_FLAGS_OFFSET..HEAD_FLAGS_OFFSET + 2].copy_from_slice(&flags.to_be_bytes());
+    }
+
+    let mut directory = Writer::new();
+    let mut table_data: Vec<u8> = vec![];
+    tables.iter().for_each(|(tag, data)| {
+        let transform = transformed.iter().find(|(other, _, _)| other == tag);
+        let transform_version = match (tag.as_str(), transform) {
+            (_, Some((_, version, _))) => *version,
+            ("glyf" | "loca", None) => 3, // null transform
+            _ => 0,
+        };
+        let index = KNOWN_TABLE_TAGS
+            .iter()
+            .position(|known| known == tag)
+            .map_or(ARBITRARY_TAG_INDEX, |index| index as u8);
+        directory.write_u8(index | (transform_version << 6));
+        if index == ARBITRARY_TAG_INDEX {
+            directory.write_table_name(tag);
+        }
+        write_base128(&mut directory, data.len() as u32);
+        match transform {
+            Some((_, _, transformed_data)) => {
+                write_base128(&mut directory, transformed_data.len() as u32);
+                table_data.extend_from_slice(transformed_data);
+            }
+            None => table_data.extend_from_slice(data),
+        }
+    });
+    let compressed = brotli_compress(&table_data);
+
+    let directory = directory.into_vec();
+    let length = padded_size(
+        HEADER_SIZE + directory.len(),
+        [compressed.len()].into_iter(),
+    )
+    .ok_or(Woff2Error::SizeLimitExceeded)?;
+    let mut writer = Writer::new();
+    writer.write_u32(WOFF2_SIGNATURE);
+    writer.write_u32(flavor);
+    writer.write_u32(length);
+    writer.write_u16(tables.len() as u16);
+    writer.write_u16(0); // reserved
+    writer.write_u32(total_sfnt_size);
+    writer.write_u32(compressed.len() as u32);
+    writer.write_u16(1);
+    writer.write_u16(0);
+    (0..5).for_each(|_| writer.write_u32(0)); // no metadata or private data
+    writer.write_bytes(&directory);
+    writer.write_bytes(&compressed);
+    writer.align(4);
+    Ok(writer.into_vec())
+}
+
+// The transformed glyf table: glyphs split into streams of similar values. Also
+// returns the xMin of each glyph.
+fn transform_glyf(
+    glyf: &[u8],
+    loca: &[u8],
+    index_to_loc_format: u16,
+    num_glyphs: u16,
+) -> Result<(Vec<u8>, Vec<i16>), Woff2Error> {
+    let mut loca_stream = Stream::new(loca);
+    let offsets: Vec<usize> = (0..=num_glyphs)
+        .map(|_| match index_to_loc_format {
+            0 => Ok(loca_stream.read_u16()? as usize * 2),
+            _ => Ok(loca_stream.read_u32()? as usize),
+        })
+        .collect::<Result<_, Woff2Error>>()?;
+
+    let mut n_contour_stream = Writer::new();
+    let mut n_points_stream = Writer::new();
+    let mut flag_stream = Writer::new();
+    let mut glyph_stream = Writer::new();
+    let mut composite_stream = Writer::new();
+    let mut bbox_bitmap = vec![0u8; (num_glyphs as usize).div_ceil(32) * 4];
+    let mut bbox_stream = Writer::new();
+    let mut instruction_stream = Writer::new();
+    let mut overlap_bitmap = vec![0u8; (num_glyphs as usize).div_ceil(8)];
+    let mut x_mins: Vec<i16> = vec![];
+
+    let mut transform_glyph = |glyph_id: usize| -> Result<(), Woff2Error> {
+        let glyph = glyf
+            .get(offsets[glyph_id]..offsets[glyph_id + 1])
+            .ok_or(Woff2Error::OutOfBounds)?;
+        if glyph.is_empty() {
+            n_contour_stream.write_i16(0);
+            x_mins.push(0);
+            return Ok(());
+        }
+        let mut stream = Stream::new(glyph);
+        let number_of_contours = stream.read_i16()?;
+        let bbox = [
+            stream.read_i16()?,
+            stream.read_i16()?,
+            stream.read_i16()?,
+            stream.read_i16()?,
+        ];
+        n_contour_stream.write_i16(number_of_contours);
+        x_mins.push(bbox[0]);
+        let mut write_bbox = || {
+            bbox_bitmap[glyph_id >> 3] |= 0x80 >> (glyph_id & 7);
+            bbox.iter().for_each(|value| bbox_stream.write_i16(*value));
+        };
+
+        if number_of_contours < 0 {
+            // Components are copied as is, the bounding box is always stored
+            let start = stream.offset;
+            let mut have_instructions = false;
+            loop {
+                let flags = stream.read_u16()?;
+                stream.read_u16()?; // glyph index
+                stream.read_bytes(if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+                    4
+                } else {
+                    2
+                })?;
+                if flags & WE_HAVE_A_SCALE != 0 {
+                    stream.read_bytes(2)?;
+                } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+                    stream.read_bytes(4)?;
+                } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+                    stream.read_bytes(8)?;
+                }
+                have_instructions |= flags & WE_HAVE_INSTRUCTIONS != 0;
+                if flags & MORE_COMPONENTS == 0 {
+                    break;
+                }
+            }
+            composite_stream.write_bytes(&glyph[start..stream.offset]);
+            write_bbox();
+            if have_instructions {
+                let length = stream.read_u16()?;
+                write_255_u16(&mut glyph_stream, length);
+                instruction_stream.write_bytes(stream.read_bytes(length as usize)?);
+            }
+            return Ok(());
+        }
+
+        let end_points: Vec<u16> = (0..number_of_contours)
+            .map(|_| stream.read_u16())
+            .collect::<Result<_, Woff2Error>>()?;
+        let mut previous_end_point: i32 = -1;
+        for end_point in &end_points {
+            if *end_point as i32 <= previous_end_point {
+                return Err(Woff2Error::InvalidTable);
+            }
+            write_255_u16(
+                &mut n_points_stream,
+                (*end_point as i32 - previous_end_point) as u16,
+            );
+            previous_end_point = *end_point as i32;
+        }
+        let instruction_length = stream.read_u16()?;
+        let instructions = stream.read_bytes(instruction_length as usize)?;
+
+        let num_points = end_points
+            .last()
+            .map_or(0, |end_point| *end_point as usize + 1);
+        let mut flags: Vec<u8> = vec![];
+        while flags.len() < num_points {
+            let flag = stream.read_u8()?;
+            flags.push(flag);
+            if flag & GLYF_REPEAT_FLAG != 0 {
+                let repeat = stream.read_u8()?;
+                flags.extend(std::iter::repeat_n(flag, repeat as usize));
+            }
+        }
+        let mut read_coordinates =
+            |short_vector: u8, is_same_or_positive: u8| -> Result<Vec<i32>, Woff2Error> {
+                let mut value: i32 = 0;
+                flags
+                    .iter()
+                    .map(|flag| {
+                        if flag & short_vector != 0 {
+                            let delta = stream.read_u8()? as i32;
+                            value += if flag & is_same_or_positive != 0 {
+                                delta
+                            } else {
+                                -delta
+                            };
+                        } else if flag & is_same_or_positive == 0 {
+                            value += stream.read_i16()? as i32;
+                        }
+                        // Coordinates stay 16-bit, so neither sums nor deltas overflow
+                        if i16::try_from(value).is_err() {
+                            return Err(Woff2Error::InvalidTable);
+                        }
+                        Ok(value)
+                    })
+                    .collect()
+            };
+        let xs = read_coordinates(GLYF_X_SHORT_VECTOR, GLYF_X_IS_SAME_OR_POSITIVE)?;
+        let ys = read_coordinates(GLYF_Y_SHORT_VECTOR, GLYF_Y_IS_SAME_OR_POSITIVE)?;
+
+        let (mut x, mut y) = (0, 0);
+        flags
+            .iter()
+            .zip(xs.iter().zip(&ys))
+            .for_each(|(flag, (next_x, next_y))| {
+                encode_triplet(
+                    next_x - x,
+                    next_y - y,
+                    flag & GLYF_ON_CURVE_POINT != 0,
+                    &mut flag_stream,
+                    &mut glyph_stream,
+                );
+                (x, y) = (*next_x, *next_y);
+            });
+        if flags
+            .first()
+            .is_some_and(|flag| flag & GLYF_OVERLAP_SIMPLE != 0)
+        {
+            overlap_bitmap[glyph_id >> 3] |= 0x80 >> (glyph_id & 7);
+        }
+        write_255_u16(&mut glyph_stream, instruction_length);
+        instruction_stream.write_bytes(instructions);
+
+        // The decoder computes the bounding box from the points unless it is stored
+        let computed_bbox = [
+            xs.iter().copied().min(),
+            ys.iter().copied().min(),
+            xs.iter().copied().max(),
+            ys.iter().copied().max(),
+        ];
+        if computed_bbox
+            .iter()
+            .zip(&bbox)
+            .any(|(computed, value)| *computed != Some(*value as i32))
+        {
+            write_bbox();
+        }
+        Ok(())
+    };
+    (0..num_glyphs as usize).try_for_each(&mut transform_glyph)?;
+
+    let has_overlap = overlap_bitmap.iter().any(|byte| *byte != 0);
+    let mut bbox_data = bbox_bitmap;
+    bbox_data.extend_from_slice(&bbox_stream.into_vec());
+    let streams = [
+        n_contour_stream.into_vec(),
+        n_points_stream.into_vec(),
+        flag_stream.into_vec(),
+        glyph_stream.into_vec(),
+        composite_stream.into_vec(),
+        bbox_data,
+        instruction_stream.into_vec(),
+    ];
+
+    let mut writer = Writer::new();
+    writer.write_u16(0); // reserved
+    writer.write_u16(if has_overlap {
+        OVERLAP_SIMPLE_BITMAP
+    } else {
+        0
+    });
+    writer.write_u16(num_glyphs);
+    writer.write_u16(index_to_loc_format);
+    streams
+        .iter()
+        .for_each(|stream| writer.write_u32(stream.len() as u32));
+    streams.iter().for_each(|stream| writer.write_bytes(stream));
+    if has_overlap {
+        writer.write_bytes(&overlap_bitmap);
+    }
+    Ok((writer.into_vec(), x_mins))
+}
+
+// Writes a point delta as a triplet flag and its data, the inverse of decode_triplet
+fn encode_triplet(
+    dx: i32,
+    dy: i32,
+    on_curve: bool,
+    flag_stream: &mut Writer,
+    glyph_stream: &mut Writer,
+) {
+    let (abs_x, abs_y) = (dx.unsigned_abs() as i32, dy.unsigned_abs() as i32);
+    let on_curve_bit = if on_curve { 0 } else { ON_CURVE_POINT as i32 };
+    let x_sign = (dx >= 0) as i32;
+    let y_sign = (dy >= 0) as i32;
+    let xy_signs = x_sign + 2 * y_sign;
+
+    let (flag, data): (i32, Vec<i32>) = if dx == 0 && abs_y < 1280 {
+        (((abs_y & 0xF00) >> 7) + y_sign, vec![abs_y & 0xFF])
+    } else if dy == 0 && abs_x < 1280 {
+        (10 + ((abs_x & 0xF00) >> 7) + x_sign, vec![abs_x & 0xFF])
+    } else if abs_x < 65 && abs_y < 65 {
+        (
+            20 + ((abs_x - 1) & 0x30) + (((abs_y - 1) & 0x30) >> 2) + xy_signs,
+            vec![(((abs_x - 1) & 0x0F) << 4) | ((abs_y - 1) & 0x0F)],
+        )
+    } else if abs_x < 769 && abs_y < 769 {
+        (
+            84 + 12 * (((abs_x - 1) & 0x300) >> 8) + (((abs_y - 1) & 0x300) >> 6) + xy_signs,
+            vec![(abs_x - 1) & 0xFF, (abs_y - 1) & 0xFF],
+        )
+    } else if abs_x < 4096 && abs_y < 4096 {
+        (
+            120 + xy_signs,
+            vec![
+                abs_x >> 4,
+                ((abs_x & 0x0F) << 4) | (abs_y >> 8),
+                abs_y & 0xFF,
+            ],
+        )
+    } else {
+        (
+            124 + xy_signs,
+            vec![abs_x >> 8, abs_x & 0xFF, abs_y >> 8, abs_y & 0xFF],
+        )
+    };
+    flag_stream.write_u8((flag + on_curve_bit) as u8);
+    data.iter()
+        .for_each(|byte| glyph_stream.write_u8(*byte as u8));
+}
+
+// The transformed hmtx table, None when no left side bearings can be left out
+fn transform_hmtx(hmtx: &[u8], number_of_h_metrics: usize, x_mins: &[i16]) -> Option<Vec<u8>> {
+    if number_of_h_metrics == 0 || number_of_h_metrics > x_mins.len() {
+        return None;
+    }
+    let mut stream = Stream::new(hmtx);
+    let (advance_widths, proportional_lsbs): (Vec<u16>, Vec<i16>) = (0..number_of_h_metrics)
+        .map(|_| Ok((stream.read_u16()?, stream.read_i16()?)))
+        .collect::<Result<Vec<_>, Woff2Error>>()
+        .ok()?
+        .into_iter()
+        .unzip();
+    let monospace_lsbs: Vec<i16> = (number_of_h_metrics..x_mins.len())
+        .map(|_| stream.read_i16())
+        .collect::<Result<_, Woff2Error>>()
+        .ok()?;
+
+    let mut flags = 0;
+    if proportional_lsbs == x_mins[..number_of_h_metrics] {
+        flags |= HMTX_NO_PROPORTIONAL_LSBS;
+    }
+    if monospace_lsbs == x_mins[number_of_h_metrics..] {
+        flags |= HMTX_NO_MONOSPACE_LSBS;
+    }
+    if flags == 0 {
+        return None;
+    }
+
+    let mut writer = Writer::new();
+    writer.write_u8(flags);
+    advance_widths
+        .iter()
+        .for_each(|advance_width| writer.write_u16(*advance_width));
+    if flags & HMTX_NO_PROPORTIONAL_LSBS == 0 {
+        proportional_lsbs
+            .iter()
+            .for_each(|lsb| writer.write_i16(*lsb));
+    }
+    if flags & HMTX_NO_MONOSPACE_LSBS == 0 {
+        monospace_lsbs.iter().for_each(|lsb| writer.write_i16(*lsb));
+    }
+    Some(writer.into_vec())
+}
+
+// UIntBase128: 7 bits per byte, most significant first, without leading zeros
+fn write_base128(writer: &mut Writer, value: u32) {
+    let num_bytes = (1..5).find(|bytes| value >> (7 * bytes) == 0).unwrap_or(5);
+    (0..num_bytes).rev().for_each(|index| {
+        let byte = ((value >> (7 * index)) & 0x7F) as u8;
+        writer.write_u8(if index > 0 { byte | 0x80 } else { byte });
+    });
+}
+
+fn write_255_u16(writer: &mut Writer, value: u16) {
+    match value {
+        0..253 => writer.write_u8(value as u8),
+        253..506 => {
+            writer.write_u8(255);
+            writer.write_u8((value - 253) as u8);
+        }
+        506..762 => {
+            writer.write_u8(254);
+            writer.write_u8((value - 506) as u8);
+        }
+        _ => {
+            writer.write_u8(253);
+            writer.write_u16(value);
+        }
+    }
+}
+
+fn read_u16_at(data: &[u8], offset: usize) -> Result<u16, Woff2Error> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_be_bytes(bytes.try_into().unwrap()))
+        .ok_or(Woff2Error::InvalidTable)
+}
@@ -0,0 +1,79 @@
+use crate::model::{FWord, Fixed, UFWord};
+
+// Big endian counterpart of `Reader`, used to serialize tables
+#[derive(Debug, Default)]
+pub struct Writer {
+    data: Vec<u8>,
+}
+
+impl Writer {
+    pub fn new() -> Writer {
+        Writer { data: vec![] }
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn write_i8(&mut self, value: i8) {
+        self.data.push(value as u8);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.data.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn write_i16(&mut self, value: i16) {
+        self.data.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.data.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn write_fword(&mut self, value: FWord) {
+        self.write_i16(value.0);
+    }
+
+    pub fn write_ufword(&mut self, value: UFWord) {
+        self.write_u16(value.0);
+    }
+
+    pub fn write_fixed(&mut self, value: &Fixed) {
+        self.write_u16(value.major);
+        self.write_u16(value.minor);
+    }
+
+    pub fn write_long_date_time(&mut self, value: i64) {
+        self.data.extend_from_slice(&value.to_be_bytes());
+    }
+
+    // Tags shorter than 4 bytes, like 'cvt', are padded with spaces
+    pub fn write_table_name(&mut self, tag: &str) {
+        let mut buffer = [b' '; 4];
+        tag.bytes()
+            .take(4)
+            .enumerate()
+            .for_each(|(index, byte)| buffer[index] = byte);
+        self.data.extend_from_slice(&buffer);
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    // Pads with zeros up to a multiple of `alignment` bytes
+    pub fn align(&mut self, alignment: usize) {
+        let padded = self.data.len().div_ceil(alignment) * alignment;
+        self.data.resize(padded, 0);
+    }
+
+    // Number of bytes written so far
+    pub fn offset(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        self.data
+    }
+}